@@ -0,0 +1,632 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{
+    stop_words_set, CJKBigramFilter, DictionaryTokenizer, LowerCaseFilter, PorterStemFilter,
+    StandardTokenizer, StopFilter, TokenStream, WhitespaceTokenizer, WordDictionary,
+    DEFAULT_MAX_TOKEN_LENGTH, ENGLISH_STOP_WORDS,
+};
+
+use error::Result;
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::{Cursor, Read};
+use std::sync::Arc;
+
+/// An Analyzer builds `TokenStream`s, which analyze text. It thus represents a
+/// policy for extracting index terms from text.
+///
+/// In order to define what analysis is done, implementations must define their
+/// `TokenStream` in `create_components`, this is usually a tokenizer followed by
+/// an ordered chain of token filters, see `CustomAnalyzer`.
+pub trait Analyzer: Send + Sync {
+    /// Creates a new `TokenStream` for the given field, reading the text from `reader`.
+    fn create_components(
+        &self,
+        field_name: &str,
+        reader: Box<dyn Read>,
+    ) -> Result<Box<dyn TokenStream>>;
+
+    /// Returns a `TokenStream` suitable for `field_name`, tokenizing the contents of
+    /// `reader`.
+    fn token_stream(
+        &self,
+        field_name: &str,
+        reader: Box<dyn Read>,
+    ) -> Result<Box<dyn TokenStream>> {
+        self.create_components(field_name, reader)
+    }
+
+    /// Returns a `TokenStream` suitable for `field_name`, tokenizing the contents of
+    /// `text`.
+    fn token_stream_for_str(&self, field_name: &str, text: &str) -> Result<Box<dyn TokenStream>> {
+        self.token_stream(field_name, Box::new(Cursor::new(text.as_bytes().to_vec())))
+    }
+
+    /// Invoked before indexing a `Fieldable` instance if terms have already been
+    /// added to that field. This allows custom analyzers to place an automatic
+    /// position increment gap between `Fieldable` instances using the same field
+    /// name. The default value position increment gap is 0. With a 0 position
+    /// increment gap and the typical default token position increment of 1, all
+    /// terms in a field, including across `Fieldable` instances, are in successive
+    /// positions, allowing exact `PhraseQuery` matches, for instance, across
+    /// `Fieldable` instance boundaries.
+    fn position_increment_gap(&self, _field_name: &str) -> usize {
+        0
+    }
+
+    /// Just like `position_increment_gap`, except for Token offsets instead. By
+    /// default this returns 1. This method is only called if the field produced
+    /// at least one token for indexing.
+    fn offset_gap(&self, _field_name: &str) -> usize {
+        1
+    }
+}
+
+/// Creates the `Tokenizer` at the head of an analysis chain.
+pub trait TokenizerFactory: Send + Sync {
+    fn create(&self, reader: Box<dyn Read>) -> Box<dyn TokenStream>;
+}
+
+impl<F> TokenizerFactory for F
+where
+    F: Fn(Box<dyn Read>) -> Box<dyn TokenStream> + Send + Sync,
+{
+    fn create(&self, reader: Box<dyn Read>) -> Box<dyn TokenStream> {
+        self(reader)
+    }
+}
+
+/// Wraps the `TokenStream` produced by the previous stage of an analysis chain
+/// with a token filter.
+///
+/// A token filter shares the `Token` of its input, so `TokenStream::token` of
+/// the filter should return the token of the wrapped stream.
+pub trait TokenFilterFactory: Send + Sync {
+    fn create(&self, input: Box<dyn TokenStream>) -> Box<dyn TokenStream>;
+}
+
+impl<F> TokenFilterFactory for F
+where
+    F: Fn(Box<dyn TokenStream>) -> Box<dyn TokenStream> + Send + Sync,
+{
+    fn create(&self, input: Box<dyn TokenStream>) -> Box<dyn TokenStream> {
+        self(input)
+    }
+}
+
+/// An `Analyzer` built from a tokenizer followed by an ordered list of token
+/// filters, the first added filter consumes the tokenizer output directly.
+pub struct CustomAnalyzer {
+    tokenizer: Box<dyn TokenizerFactory>,
+    filters: Vec<Box<dyn TokenFilterFactory>>,
+    position_increment_gap: usize,
+    offset_gap: usize,
+}
+
+impl CustomAnalyzer {
+    pub fn new<T: TokenizerFactory + 'static>(tokenizer: T) -> Self {
+        CustomAnalyzer {
+            tokenizer: Box::new(tokenizer),
+            filters: vec![],
+            position_increment_gap: 0,
+            offset_gap: 1,
+        }
+    }
+
+    pub fn add_filter<F: TokenFilterFactory + 'static>(mut self, filter: F) -> Self {
+        self.filters.push(Box::new(filter));
+        self
+    }
+
+    pub fn with_position_increment_gap(mut self, gap: usize) -> Self {
+        self.position_increment_gap = gap;
+        self
+    }
+
+    pub fn with_offset_gap(mut self, gap: usize) -> Self {
+        self.offset_gap = gap;
+        self
+    }
+}
+
+impl Analyzer for CustomAnalyzer {
+    fn create_components(
+        &self,
+        _field_name: &str,
+        reader: Box<dyn Read>,
+    ) -> Result<Box<dyn TokenStream>> {
+        let mut stream = self.tokenizer.create(reader);
+        for filter in &self.filters {
+            stream = filter.create(stream);
+        }
+        Ok(stream)
+    }
+
+    fn position_increment_gap(&self, _field_name: &str) -> usize {
+        self.position_increment_gap
+    }
+
+    fn offset_gap(&self, _field_name: &str) -> usize {
+        self.offset_gap
+    }
+}
+
+impl fmt::Debug for CustomAnalyzer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CustomAnalyzer")
+            .field("filters", &self.filters.len())
+            .field("position_increment_gap", &self.position_increment_gap)
+            .field("offset_gap", &self.offset_gap)
+            .finish()
+    }
+}
+
+/// An `Analyzer` that uses `WhitespaceTokenizer`.
+#[derive(Debug, Default)]
+pub struct WhitespaceAnalyzer;
+
+impl Analyzer for WhitespaceAnalyzer {
+    fn create_components(
+        &self,
+        _field_name: &str,
+        reader: Box<dyn Read>,
+    ) -> Result<Box<dyn TokenStream>> {
+        Ok(Box::new(WhitespaceTokenizer::new(reader)))
+    }
+}
+
+/// Filters `StandardTokenizer` with `LowerCaseFilter` and `StopFilter`, using
+/// an empty list of stop words by default.
+#[derive(Debug)]
+pub struct StandardAnalyzer {
+    stop_words: Arc<HashSet<Vec<u8>>>,
+    max_token_length: usize,
+}
+
+impl Default for StandardAnalyzer {
+    fn default() -> Self {
+        StandardAnalyzer::new(Arc::new(HashSet::new()))
+    }
+}
+
+impl StandardAnalyzer {
+    pub fn new(stop_words: Arc<HashSet<Vec<u8>>>) -> Self {
+        StandardAnalyzer {
+            stop_words,
+            max_token_length: DEFAULT_MAX_TOKEN_LENGTH,
+        }
+    }
+
+    pub fn set_max_token_length(&mut self, length: usize) {
+        self.max_token_length = length;
+    }
+}
+
+impl Analyzer for StandardAnalyzer {
+    fn create_components(
+        &self,
+        _field_name: &str,
+        reader: Box<dyn Read>,
+    ) -> Result<Box<dyn TokenStream>> {
+        let mut tokenizer = StandardTokenizer::new(reader);
+        tokenizer.set_max_token_length(self.max_token_length);
+        let stream = Box::new(LowerCaseFilter::new(Box::new(tokenizer)));
+        if self.stop_words.is_empty() {
+            Ok(stream)
+        } else {
+            Ok(Box::new(StopFilter::new(
+                stream,
+                Arc::clone(&self.stop_words),
+            )))
+        }
+    }
+}
+
+/// `Analyzer` for English, filters `StandardTokenizer` with `LowerCaseFilter`,
+/// `StopFilter` using `ENGLISH_STOP_WORDS` and `PorterStemFilter`.
+#[derive(Debug)]
+pub struct EnglishAnalyzer {
+    stop_words: Arc<HashSet<Vec<u8>>>,
+}
+
+impl Default for EnglishAnalyzer {
+    fn default() -> Self {
+        EnglishAnalyzer::new(stop_words_set(&ENGLISH_STOP_WORDS))
+    }
+}
+
+impl EnglishAnalyzer {
+    pub fn new(stop_words: Arc<HashSet<Vec<u8>>>) -> Self {
+        EnglishAnalyzer { stop_words }
+    }
+}
+
+impl Analyzer for EnglishAnalyzer {
+    fn create_components(
+        &self,
+        _field_name: &str,
+        reader: Box<dyn Read>,
+    ) -> Result<Box<dyn TokenStream>> {
+        let tokenizer = Box::new(StandardTokenizer::new(reader));
+        let stream = Box::new(LowerCaseFilter::new(tokenizer));
+        let stream = Box::new(StopFilter::new(stream, Arc::clone(&self.stop_words)));
+        Ok(Box::new(PorterStemFilter::new(stream)))
+    }
+}
+
+/// `Analyzer` for Chinese, Japanese and Korean, filters `StandardTokenizer`
+/// with `LowerCaseFilter` and `CJKBigramFilter`, so the CJK text is indexed as
+/// overlapping bigrams.
+#[derive(Debug, Default)]
+pub struct CJKAnalyzer {
+    output_unigrams: bool,
+}
+
+impl CJKAnalyzer {
+    pub fn new(output_unigrams: bool) -> Self {
+        CJKAnalyzer { output_unigrams }
+    }
+}
+
+impl Analyzer for CJKAnalyzer {
+    fn create_components(
+        &self,
+        _field_name: &str,
+        reader: Box<dyn Read>,
+    ) -> Result<Box<dyn TokenStream>> {
+        let tokenizer = Box::new(StandardTokenizer::new(reader));
+        let stream = Box::new(LowerCaseFilter::new(tokenizer));
+        Ok(Box::new(CJKBigramFilter::new(stream, self.output_unigrams)))
+    }
+}
+
+/// Filters `DictionaryTokenizer` with `LowerCaseFilter`, the dictionary is
+/// shared by all the token streams created by this analyzer.
+#[derive(Debug)]
+pub struct DictionaryAnalyzer {
+    dictionary: Arc<WordDictionary>,
+}
+
+impl DictionaryAnalyzer {
+    pub fn new(dictionary: Arc<WordDictionary>) -> Self {
+        DictionaryAnalyzer { dictionary }
+    }
+}
+
+impl Analyzer for DictionaryAnalyzer {
+    fn create_components(
+        &self,
+        _field_name: &str,
+        reader: Box<dyn Read>,
+    ) -> Result<Box<dyn TokenStream>> {
+        let tokenizer = DictionaryTokenizer::new(reader, Arc::clone(&self.dictionary));
+        Ok(Box::new(LowerCaseFilter::new(Box::new(tokenizer))))
+    }
+}
+
+/// This analyzer is used to facilitate scenarios where different
+/// fields require different analysis techniques. Use the map
+/// argument in `new` to add non-default analyzers for fields.
+pub struct PerFieldAnalyzerWrapper {
+    default_analyzer: Arc<dyn Analyzer>,
+    field_analyzers: HashMap<String, Arc<dyn Analyzer>>,
+}
+
+impl PerFieldAnalyzerWrapper {
+    pub fn new(
+        default_analyzer: Arc<dyn Analyzer>,
+        field_analyzers: HashMap<String, Arc<dyn Analyzer>>,
+    ) -> Self {
+        PerFieldAnalyzerWrapper {
+            default_analyzer,
+            field_analyzers,
+        }
+    }
+
+    pub fn add_analyzer(&mut self, field_name: String, analyzer: Arc<dyn Analyzer>) {
+        self.field_analyzers.insert(field_name, analyzer);
+    }
+
+    fn wrapped_analyzer(&self, field_name: &str) -> &dyn Analyzer {
+        self.field_analyzers
+            .get(field_name)
+            .unwrap_or(&self.default_analyzer)
+            .as_ref()
+    }
+}
+
+impl Analyzer for PerFieldAnalyzerWrapper {
+    fn create_components(
+        &self,
+        field_name: &str,
+        reader: Box<dyn Read>,
+    ) -> Result<Box<dyn TokenStream>> {
+        self.wrapped_analyzer(field_name)
+            .create_components(field_name, reader)
+    }
+
+    fn token_stream(
+        &self,
+        field_name: &str,
+        reader: Box<dyn Read>,
+    ) -> Result<Box<dyn TokenStream>> {
+        self.wrapped_analyzer(field_name)
+            .token_stream(field_name, reader)
+    }
+
+    fn position_increment_gap(&self, field_name: &str) -> usize {
+        self.wrapped_analyzer(field_name)
+            .position_increment_gap(field_name)
+    }
+
+    fn offset_gap(&self, field_name: &str) -> usize {
+        self.wrapped_analyzer(field_name).offset_gap(field_name)
+    }
+}
+
+impl fmt::Debug for PerFieldAnalyzerWrapper {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut fields: Vec<&String> = self.field_analyzers.keys().collect();
+        fields.sort();
+        f.debug_struct("PerFieldAnalyzerWrapper")
+            .field("fields", &fields)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate tempfile;
+
+    use super::*;
+    use core::analysis::Token;
+    use core::doc::{Field, FieldType, Fieldable, IndexOptions, Term};
+    use core::index::writer::{IndexWriter, IndexWriterConfig};
+    use core::search::collector::TopDocsCollector;
+    use core::search::query::{PhraseQuery, TermQuery};
+    use core::search::{DefaultIndexSearcher, IndexSearcher};
+    use core::store::directory::FSDirectory;
+    use core::util::VariantValue;
+
+    #[derive(Debug)]
+    struct SkipShortFilter {
+        input: Box<dyn TokenStream>,
+    }
+
+    impl TokenStream for SkipShortFilter {
+        fn next_token(&mut self) -> Result<bool> {
+            let mut skipped = 0;
+            while self.input.next_token()? {
+                if self.input.token().term.len() > 3 {
+                    self.input.token_mut().position += skipped;
+                    return Ok(true);
+                }
+                skipped += self.input.token().position;
+            }
+            Ok(false)
+        }
+
+        fn end(&mut self) -> Result<()> {
+            self.input.end()
+        }
+
+        fn reset(&mut self) -> Result<()> {
+            self.input.reset()
+        }
+
+        fn token(&self) -> &Token {
+            self.input.token()
+        }
+
+        fn token_mut(&mut self) -> &mut Token {
+            self.input.token_mut()
+        }
+    }
+
+    fn collect(stream: &mut dyn TokenStream) -> Vec<(String, usize, usize, usize)> {
+        let mut tokens = vec![];
+        stream.reset().unwrap();
+        while stream.next_token().unwrap() {
+            let token = stream.token();
+            tokens.push((
+                String::from_utf8(token.term.clone()).unwrap(),
+                token.position,
+                token.start_offset,
+                token.end_offset,
+            ));
+        }
+        stream.end().unwrap();
+        tokens
+    }
+
+    #[test]
+    fn test_custom_analyzer() {
+        let analyzer = CustomAnalyzer::new(|reader: Box<dyn Read>| -> Box<dyn TokenStream> {
+            Box::new(WhitespaceTokenizer::new(reader))
+        })
+        .add_filter(|input: Box<dyn TokenStream>| -> Box<dyn TokenStream> {
+            Box::new(SkipShortFilter { input })
+        })
+        .with_position_increment_gap(100);
+
+        let mut stream = analyzer
+            .token_stream_for_str("title", "the quick fox jumps")
+            .unwrap();
+        let tokens = collect(stream.as_mut());
+        assert_eq!(
+            tokens,
+            vec![
+                ("quick".to_string(), 2, 4, 9),
+                ("jumps".to_string(), 2, 14, 19)
+            ]
+        );
+        assert_eq!(stream.token().end_offset, 19);
+        assert_eq!(analyzer.position_increment_gap("title"), 100);
+        assert_eq!(analyzer.offset_gap("title"), 1);
+    }
+
+    #[test]
+    fn test_per_field_analyzer_wrapper() {
+        let custom: Arc<dyn Analyzer> = Arc::new(
+            CustomAnalyzer::new(|reader: Box<dyn Read>| -> Box<dyn TokenStream> {
+                Box::new(WhitespaceTokenizer::new(reader))
+            })
+            .add_filter(|input: Box<dyn TokenStream>| -> Box<dyn TokenStream> {
+                Box::new(SkipShortFilter { input })
+            })
+            .with_offset_gap(10),
+        );
+        let mut analyzer =
+            PerFieldAnalyzerWrapper::new(Arc::new(WhitespaceAnalyzer), HashMap::new());
+        analyzer.add_analyzer("body".into(), custom);
+
+        let mut title = analyzer
+            .token_stream_for_str("title", "a lazy dog")
+            .unwrap();
+        assert_eq!(collect(title.as_mut()).len(), 3);
+        let mut body = analyzer.token_stream_for_str("body", "a lazy dog").unwrap();
+        assert_eq!(collect(body.as_mut()).len(), 1);
+
+        assert_eq!(analyzer.offset_gap("title"), 1);
+        assert_eq!(analyzer.offset_gap("body"), 10);
+    }
+
+    #[test]
+    fn test_index_analyzed_text_field() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let directory = Arc::new(FSDirectory::with_path(temp_dir.path()).unwrap());
+
+        let mut config = IndexWriterConfig::default();
+        let short_words: Arc<dyn Analyzer> = Arc::new(
+            CustomAnalyzer::new(|reader: Box<dyn Read>| -> Box<dyn TokenStream> {
+                Box::new(WhitespaceTokenizer::new(reader))
+            })
+            .add_filter(|input: Box<dyn TokenStream>| -> Box<dyn TokenStream> {
+                Box::new(SkipShortFilter { input })
+            }),
+        );
+        let mut analyzer =
+            PerFieldAnalyzerWrapper::new(Arc::new(WhitespaceAnalyzer), HashMap::new());
+        analyzer.add_analyzer("body".into(), short_words);
+        config.analyzer = Arc::new(analyzer);
+        let writer = IndexWriter::new(directory, Arc::new(config)).unwrap();
+
+        let mut field_type = FieldType::default();
+        field_type.index_options = IndexOptions::DocsAndFreqsAndPositions;
+        let text = "The quick brown fox jumps over a lazy dog";
+        let doc: Vec<Box<dyn Fieldable>> = vec![
+            Box::new(Field::new(
+                "title".into(),
+                field_type.clone(),
+                Some(VariantValue::VString(text.into())),
+                None,
+            )),
+            Box::new(Field::new(
+                "body".into(),
+                field_type,
+                Some(VariantValue::VString(text.into())),
+                None,
+            )),
+        ];
+        writer.add_document(doc).unwrap();
+        writer.commit().unwrap();
+
+        let reader = writer.get_reader(true, false).unwrap();
+        let searcher = DefaultIndexSearcher::new(Arc::new(reader), None);
+        let count = |field: &str, text: &str| {
+            let query =
+                TermQuery::new(Term::new(field.into(), text.as_bytes().to_vec()), 1.0, None);
+            let mut collector = TopDocsCollector::new(10);
+            searcher.search(&query, &mut collector).unwrap();
+            collector.top_docs().total_hits()
+        };
+        assert_eq!(count("title", "fox"), 1);
+        assert_eq!(count("body", "fox"), 0);
+        assert_eq!(count("body", "quick"), 1);
+    }
+
+    #[test]
+    fn test_position_increment_gap() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let directory = Arc::new(FSDirectory::with_path(temp_dir.path()).unwrap());
+
+        let mut config = IndexWriterConfig::default();
+        config.analyzer = Arc::new(
+            CustomAnalyzer::new(|reader: Box<dyn Read>| -> Box<dyn TokenStream> {
+                Box::new(WhitespaceTokenizer::new(reader))
+            })
+            .with_position_increment_gap(100),
+        );
+        let writer = IndexWriter::new(directory, Arc::new(config)).unwrap();
+
+        let mut field_type = FieldType::default();
+        field_type.index_options = IndexOptions::DocsAndFreqsAndPositions;
+        let mut doc: Vec<Box<dyn Fieldable>> = vec![];
+        for text in &["quick brown", "fox jumps"] {
+            // analyzed by the analyzer of the writer
+            doc.push(Box::new(Field::new(
+                "analyzed".into(),
+                field_type.clone(),
+                Some(VariantValue::VString((*text).to_string())),
+                None,
+            )));
+            // indexed from a pre-built token stream
+            let stream = WhitespaceTokenizer::new(Box::new(Cursor::new(text.as_bytes().to_vec())));
+            doc.push(Box::new(Field::new(
+                "pre_built".into(),
+                field_type.clone(),
+                None,
+                Some(Box::new(stream)),
+            )));
+        }
+        writer.add_document(doc).unwrap();
+        writer.commit().unwrap();
+
+        let reader = writer.get_reader(true, false).unwrap();
+        let searcher = DefaultIndexSearcher::new(Arc::new(reader), None);
+        let count = |field: &str| {
+            let terms = vec![
+                Term::new(field.into(), b"brown".to_vec()),
+                Term::new(field.into(), b"fox".to_vec()),
+            ];
+            let query = PhraseQuery::new(terms, vec![0, 1], 0, None, None).unwrap();
+            let mut collector = TopDocsCollector::new(10);
+            searcher.search(&query, &mut collector).unwrap();
+            collector.top_docs().total_hits()
+        };
+        assert_eq!(count("analyzed"), 0);
+        assert_eq!(count("pre_built"), 1);
+    }
+
+    #[test]
+    fn test_english_analyzer() {
+        let analyzer = EnglishAnalyzer::default();
+        let mut stream = analyzer
+            .token_stream_for_str("body", "The Foxes are JUMPING over the lazy dogs.")
+            .unwrap();
+        let tokens = collect(stream.as_mut());
+        assert_eq!(
+            tokens,
+            vec![
+                ("fox".to_string(), 2, 4, 9),
+                ("jump".to_string(), 2, 14, 21),
+                ("over".to_string(), 1, 22, 26),
+                ("lazi".to_string(), 2, 31, 35),
+                ("dog".to_string(), 1, 36, 40),
+            ]
+        );
+        assert_eq!(stream.token().end_offset, 41);
+    }
+}
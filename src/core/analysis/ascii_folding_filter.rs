@@ -0,0 +1,1085 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{Token, TokenStream};
+
+use error::Result;
+
+use std::str;
+
+/// This class converts alphabetic, numeric, and symbolic Unicode characters
+/// which are not in the first 127 ASCII characters (the "Basic Latin" Unicode
+/// block) into their ASCII equivalents, if one exists.
+///
+/// Characters from the following Unicode blocks are converted:
+///
+/// - Latin-1 Supplement, Latin Extended-A, Latin Extended-B and Latin Extended
+/// Additional
+/// - Superscripts and Subscripts, Enclosed Alphanumerics
+/// - Alphabetic Presentation Forms (ligatures) and Halfwidth and Fullwidth Forms
+/// - General Punctuation, such as typographic quotes and dashes
+///
+/// If `preserve_original` is true, the original token is emitted as well, and the
+/// folded token is emitted right after it at the same position.
+#[derive(Debug)]
+pub struct ASCIIFoldingFilter {
+    input: Box<dyn TokenStream>,
+    preserve_original: bool,
+    // the folded term to emit after the original one
+    pending: Option<Vec<u8>>,
+}
+
+impl ASCIIFoldingFilter {
+    pub fn new(input: Box<dyn TokenStream>, preserve_original: bool) -> Self {
+        ASCIIFoldingFilter {
+            input,
+            preserve_original,
+            pending: None,
+        }
+    }
+}
+
+impl TokenStream for ASCIIFoldingFilter {
+    fn next_token(&mut self) -> Result<bool> {
+        if let Some(term) = self.pending.take() {
+            let token = self.input.token_mut();
+            token.term = term;
+            token.position = 0;
+            return Ok(true);
+        }
+
+        if !self.input.next_token()? {
+            return Ok(false);
+        }
+
+        if !self.input.token().term.is_ascii() {
+            if let Ok(s) = str::from_utf8(&self.input.token().term) {
+                let folded = fold_to_ascii(s);
+                if folded != s {
+                    if self.preserve_original {
+                        self.pending = Some(folded.into_bytes());
+                    } else {
+                        self.input.token_mut().term = folded.into_bytes();
+                    }
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.input.end()
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.pending = None;
+        self.input.reset()
+    }
+
+    fn token(&self) -> &Token {
+        self.input.token()
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        self.input.token_mut()
+    }
+}
+
+/// Converts the characters in `input` to their ASCII equivalents, characters
+/// without an ASCII equivalent are kept unchanged.
+pub fn fold_to_ascii(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    for c in input.chars() {
+        if c.is_ascii() {
+            output.push(c);
+        } else {
+            match FOLDING_TABLE.binary_search_by_key(&c, |&(from, _)| from) {
+                Ok(idx) => output.push_str(FOLDING_TABLE[idx].1),
+                Err(_) => output.push(c),
+            }
+        }
+    }
+    output
+}
+
+// sorted by the char to fold
+const FOLDING_TABLE: &[(char, &str)] = &[
+    ('\u{AA}', "a"),
+    ('\u{AB}', "\""),
+    ('\u{B2}', "2"),
+    ('\u{B3}', "3"),
+    ('\u{B9}', "1"),
+    ('\u{BA}', "o"),
+    ('\u{BB}', "\""),
+    ('\u{C0}', "A"),
+    ('\u{C1}', "A"),
+    ('\u{C2}', "A"),
+    ('\u{C3}', "A"),
+    ('\u{C4}', "A"),
+    ('\u{C5}', "A"),
+    ('\u{C6}', "AE"),
+    ('\u{C7}', "C"),
+    ('\u{C8}', "E"),
+    ('\u{C9}', "E"),
+    ('\u{CA}', "E"),
+    ('\u{CB}', "E"),
+    ('\u{CC}', "I"),
+    ('\u{CD}', "I"),
+    ('\u{CE}', "I"),
+    ('\u{CF}', "I"),
+    ('\u{D0}', "D"),
+    ('\u{D1}', "N"),
+    ('\u{D2}', "O"),
+    ('\u{D3}', "O"),
+    ('\u{D4}', "O"),
+    ('\u{D5}', "O"),
+    ('\u{D6}', "O"),
+    ('\u{D8}', "O"),
+    ('\u{D9}', "U"),
+    ('\u{DA}', "U"),
+    ('\u{DB}', "U"),
+    ('\u{DC}', "U"),
+    ('\u{DD}', "Y"),
+    ('\u{DE}', "TH"),
+    ('\u{DF}', "ss"),
+    ('\u{E0}', "a"),
+    ('\u{E1}', "a"),
+    ('\u{E2}', "a"),
+    ('\u{E3}', "a"),
+    ('\u{E4}', "a"),
+    ('\u{E5}', "a"),
+    ('\u{E6}', "ae"),
+    ('\u{E7}', "c"),
+    ('\u{E8}', "e"),
+    ('\u{E9}', "e"),
+    ('\u{EA}', "e"),
+    ('\u{EB}', "e"),
+    ('\u{EC}', "i"),
+    ('\u{ED}', "i"),
+    ('\u{EE}', "i"),
+    ('\u{EF}', "i"),
+    ('\u{F0}', "d"),
+    ('\u{F1}', "n"),
+    ('\u{F2}', "o"),
+    ('\u{F3}', "o"),
+    ('\u{F4}', "o"),
+    ('\u{F5}', "o"),
+    ('\u{F6}', "o"),
+    ('\u{F8}', "o"),
+    ('\u{F9}', "u"),
+    ('\u{FA}', "u"),
+    ('\u{FB}', "u"),
+    ('\u{FC}', "u"),
+    ('\u{FD}', "y"),
+    ('\u{FE}', "th"),
+    ('\u{FF}', "y"),
+    ('\u{100}', "A"),
+    ('\u{101}', "a"),
+    ('\u{102}', "A"),
+    ('\u{103}', "a"),
+    ('\u{104}', "A"),
+    ('\u{105}', "a"),
+    ('\u{106}', "C"),
+    ('\u{107}', "c"),
+    ('\u{108}', "C"),
+    ('\u{109}', "c"),
+    ('\u{10A}', "C"),
+    ('\u{10B}', "c"),
+    ('\u{10C}', "C"),
+    ('\u{10D}', "c"),
+    ('\u{10E}', "D"),
+    ('\u{10F}', "d"),
+    ('\u{110}', "D"),
+    ('\u{111}', "d"),
+    ('\u{112}', "E"),
+    ('\u{113}', "e"),
+    ('\u{114}', "E"),
+    ('\u{115}', "e"),
+    ('\u{116}', "E"),
+    ('\u{117}', "e"),
+    ('\u{118}', "E"),
+    ('\u{119}', "e"),
+    ('\u{11A}', "E"),
+    ('\u{11B}', "e"),
+    ('\u{11C}', "G"),
+    ('\u{11D}', "g"),
+    ('\u{11E}', "G"),
+    ('\u{11F}', "g"),
+    ('\u{120}', "G"),
+    ('\u{121}', "g"),
+    ('\u{122}', "G"),
+    ('\u{123}', "g"),
+    ('\u{124}', "H"),
+    ('\u{125}', "h"),
+    ('\u{126}', "H"),
+    ('\u{127}', "h"),
+    ('\u{128}', "I"),
+    ('\u{129}', "i"),
+    ('\u{12A}', "I"),
+    ('\u{12B}', "i"),
+    ('\u{12C}', "I"),
+    ('\u{12D}', "i"),
+    ('\u{12E}', "I"),
+    ('\u{12F}', "i"),
+    ('\u{130}', "I"),
+    ('\u{131}', "i"),
+    ('\u{132}', "IJ"),
+    ('\u{133}', "ij"),
+    ('\u{134}', "J"),
+    ('\u{135}', "j"),
+    ('\u{136}', "K"),
+    ('\u{137}', "k"),
+    ('\u{138}', "q"),
+    ('\u{139}', "L"),
+    ('\u{13A}', "l"),
+    ('\u{13B}', "L"),
+    ('\u{13C}', "l"),
+    ('\u{13D}', "L"),
+    ('\u{13E}', "l"),
+    ('\u{13F}', "L"),
+    ('\u{140}', "l"),
+    ('\u{141}', "L"),
+    ('\u{142}', "l"),
+    ('\u{143}', "N"),
+    ('\u{144}', "n"),
+    ('\u{145}', "N"),
+    ('\u{146}', "n"),
+    ('\u{147}', "N"),
+    ('\u{148}', "n"),
+    ('\u{149}', "'n"),
+    ('\u{14A}', "N"),
+    ('\u{14B}', "n"),
+    ('\u{14C}', "O"),
+    ('\u{14D}', "o"),
+    ('\u{14E}', "O"),
+    ('\u{14F}', "o"),
+    ('\u{150}', "O"),
+    ('\u{151}', "o"),
+    ('\u{152}', "OE"),
+    ('\u{153}', "oe"),
+    ('\u{154}', "R"),
+    ('\u{155}', "r"),
+    ('\u{156}', "R"),
+    ('\u{157}', "r"),
+    ('\u{158}', "R"),
+    ('\u{159}', "r"),
+    ('\u{15A}', "S"),
+    ('\u{15B}', "s"),
+    ('\u{15C}', "S"),
+    ('\u{15D}', "s"),
+    ('\u{15E}', "S"),
+    ('\u{15F}', "s"),
+    ('\u{160}', "S"),
+    ('\u{161}', "s"),
+    ('\u{162}', "T"),
+    ('\u{163}', "t"),
+    ('\u{164}', "T"),
+    ('\u{165}', "t"),
+    ('\u{166}', "T"),
+    ('\u{167}', "t"),
+    ('\u{168}', "U"),
+    ('\u{169}', "u"),
+    ('\u{16A}', "U"),
+    ('\u{16B}', "u"),
+    ('\u{16C}', "U"),
+    ('\u{16D}', "u"),
+    ('\u{16E}', "U"),
+    ('\u{16F}', "u"),
+    ('\u{170}', "U"),
+    ('\u{171}', "u"),
+    ('\u{172}', "U"),
+    ('\u{173}', "u"),
+    ('\u{174}', "W"),
+    ('\u{175}', "w"),
+    ('\u{176}', "Y"),
+    ('\u{177}', "y"),
+    ('\u{178}', "Y"),
+    ('\u{179}', "Z"),
+    ('\u{17A}', "z"),
+    ('\u{17B}', "Z"),
+    ('\u{17C}', "z"),
+    ('\u{17D}', "Z"),
+    ('\u{17E}', "z"),
+    ('\u{17F}', "s"),
+    ('\u{180}', "b"),
+    ('\u{181}', "B"),
+    ('\u{182}', "B"),
+    ('\u{183}', "b"),
+    ('\u{187}', "C"),
+    ('\u{188}', "c"),
+    ('\u{189}', "D"),
+    ('\u{18A}', "D"),
+    ('\u{18B}', "D"),
+    ('\u{18C}', "d"),
+    ('\u{18E}', "E"),
+    ('\u{190}', "E"),
+    ('\u{191}', "F"),
+    ('\u{192}', "f"),
+    ('\u{193}', "G"),
+    ('\u{194}', "G"),
+    ('\u{195}', "hv"),
+    ('\u{196}', "I"),
+    ('\u{197}', "I"),
+    ('\u{198}', "K"),
+    ('\u{199}', "k"),
+    ('\u{19A}', "l"),
+    ('\u{19C}', "M"),
+    ('\u{19D}', "N"),
+    ('\u{19E}', "n"),
+    ('\u{19F}', "O"),
+    ('\u{1A0}', "O"),
+    ('\u{1A1}', "o"),
+    ('\u{1A4}', "P"),
+    ('\u{1A5}', "p"),
+    ('\u{1AB}', "t"),
+    ('\u{1AC}', "T"),
+    ('\u{1AD}', "t"),
+    ('\u{1AE}', "T"),
+    ('\u{1AF}', "U"),
+    ('\u{1B0}', "u"),
+    ('\u{1B2}', "V"),
+    ('\u{1B3}', "Y"),
+    ('\u{1B4}', "y"),
+    ('\u{1B5}', "Z"),
+    ('\u{1B6}', "z"),
+    ('\u{1C4}', "DZ"),
+    ('\u{1C5}', "Dz"),
+    ('\u{1C6}', "dz"),
+    ('\u{1C7}', "LJ"),
+    ('\u{1C8}', "Lj"),
+    ('\u{1C9}', "lj"),
+    ('\u{1CA}', "NJ"),
+    ('\u{1CB}', "Nj"),
+    ('\u{1CC}', "nj"),
+    ('\u{1CD}', "A"),
+    ('\u{1CE}', "a"),
+    ('\u{1CF}', "I"),
+    ('\u{1D0}', "i"),
+    ('\u{1D1}', "O"),
+    ('\u{1D2}', "o"),
+    ('\u{1D3}', "U"),
+    ('\u{1D4}', "u"),
+    ('\u{1D5}', "U"),
+    ('\u{1D6}', "u"),
+    ('\u{1D7}', "U"),
+    ('\u{1D8}', "u"),
+    ('\u{1D9}', "U"),
+    ('\u{1DA}', "u"),
+    ('\u{1DB}', "U"),
+    ('\u{1DC}', "u"),
+    ('\u{1DD}', "e"),
+    ('\u{1DE}', "A"),
+    ('\u{1DF}', "a"),
+    ('\u{1E0}', "A"),
+    ('\u{1E1}', "a"),
+    ('\u{1E2}', "AE"),
+    ('\u{1E3}', "ae"),
+    ('\u{1E4}', "G"),
+    ('\u{1E5}', "g"),
+    ('\u{1E6}', "G"),
+    ('\u{1E7}', "g"),
+    ('\u{1E8}', "K"),
+    ('\u{1E9}', "k"),
+    ('\u{1EA}', "O"),
+    ('\u{1EB}', "o"),
+    ('\u{1EC}', "O"),
+    ('\u{1ED}', "o"),
+    ('\u{1F0}', "j"),
+    ('\u{1F1}', "DZ"),
+    ('\u{1F2}', "Dz"),
+    ('\u{1F3}', "dz"),
+    ('\u{1F4}', "G"),
+    ('\u{1F5}', "g"),
+    ('\u{1F6}', "HV"),
+    ('\u{1F7}', "W"),
+    ('\u{1F8}', "N"),
+    ('\u{1F9}', "n"),
+    ('\u{1FA}', "A"),
+    ('\u{1FB}', "a"),
+    ('\u{1FC}', "AE"),
+    ('\u{1FD}', "ae"),
+    ('\u{1FE}', "O"),
+    ('\u{1FF}', "o"),
+    ('\u{200}', "A"),
+    ('\u{201}', "a"),
+    ('\u{202}', "A"),
+    ('\u{203}', "a"),
+    ('\u{204}', "E"),
+    ('\u{205}', "e"),
+    ('\u{206}', "E"),
+    ('\u{207}', "e"),
+    ('\u{208}', "I"),
+    ('\u{209}', "i"),
+    ('\u{20A}', "I"),
+    ('\u{20B}', "i"),
+    ('\u{20C}', "O"),
+    ('\u{20D}', "o"),
+    ('\u{20E}', "O"),
+    ('\u{20F}', "o"),
+    ('\u{210}', "R"),
+    ('\u{211}', "r"),
+    ('\u{212}', "R"),
+    ('\u{213}', "r"),
+    ('\u{214}', "U"),
+    ('\u{215}', "u"),
+    ('\u{216}', "U"),
+    ('\u{217}', "u"),
+    ('\u{218}', "S"),
+    ('\u{219}', "s"),
+    ('\u{21A}', "T"),
+    ('\u{21B}', "t"),
+    ('\u{21E}', "H"),
+    ('\u{21F}', "h"),
+    ('\u{221}', "d"),
+    ('\u{224}', "Z"),
+    ('\u{225}', "z"),
+    ('\u{226}', "A"),
+    ('\u{227}', "a"),
+    ('\u{228}', "E"),
+    ('\u{229}', "e"),
+    ('\u{22A}', "O"),
+    ('\u{22B}', "o"),
+    ('\u{22C}', "O"),
+    ('\u{22D}', "o"),
+    ('\u{22E}', "O"),
+    ('\u{22F}', "o"),
+    ('\u{230}', "O"),
+    ('\u{231}', "o"),
+    ('\u{232}', "Y"),
+    ('\u{233}', "y"),
+    ('\u{234}', "l"),
+    ('\u{235}', "n"),
+    ('\u{236}', "t"),
+    ('\u{237}', "j"),
+    ('\u{238}', "db"),
+    ('\u{239}', "qp"),
+    ('\u{23A}', "A"),
+    ('\u{23B}', "C"),
+    ('\u{23C}', "c"),
+    ('\u{23D}', "L"),
+    ('\u{23E}', "T"),
+    ('\u{23F}', "s"),
+    ('\u{240}', "z"),
+    ('\u{243}', "B"),
+    ('\u{244}', "U"),
+    ('\u{245}', "V"),
+    ('\u{246}', "E"),
+    ('\u{247}', "e"),
+    ('\u{248}', "J"),
+    ('\u{249}', "j"),
+    ('\u{24A}', "Q"),
+    ('\u{24B}', "q"),
+    ('\u{24C}', "R"),
+    ('\u{24D}', "r"),
+    ('\u{24E}', "Y"),
+    ('\u{24F}', "y"),
+    ('\u{1E00}', "A"),
+    ('\u{1E01}', "a"),
+    ('\u{1E02}', "B"),
+    ('\u{1E03}', "b"),
+    ('\u{1E04}', "B"),
+    ('\u{1E05}', "b"),
+    ('\u{1E06}', "B"),
+    ('\u{1E07}', "b"),
+    ('\u{1E08}', "C"),
+    ('\u{1E09}', "c"),
+    ('\u{1E0A}', "D"),
+    ('\u{1E0B}', "d"),
+    ('\u{1E0C}', "D"),
+    ('\u{1E0D}', "d"),
+    ('\u{1E0E}', "D"),
+    ('\u{1E0F}', "d"),
+    ('\u{1E10}', "D"),
+    ('\u{1E11}', "d"),
+    ('\u{1E12}', "D"),
+    ('\u{1E13}', "d"),
+    ('\u{1E14}', "E"),
+    ('\u{1E15}', "e"),
+    ('\u{1E16}', "E"),
+    ('\u{1E17}', "e"),
+    ('\u{1E18}', "E"),
+    ('\u{1E19}', "e"),
+    ('\u{1E1A}', "E"),
+    ('\u{1E1B}', "e"),
+    ('\u{1E1C}', "E"),
+    ('\u{1E1D}', "e"),
+    ('\u{1E1E}', "F"),
+    ('\u{1E1F}', "f"),
+    ('\u{1E20}', "G"),
+    ('\u{1E21}', "g"),
+    ('\u{1E22}', "H"),
+    ('\u{1E23}', "h"),
+    ('\u{1E24}', "H"),
+    ('\u{1E25}', "h"),
+    ('\u{1E26}', "H"),
+    ('\u{1E27}', "h"),
+    ('\u{1E28}', "H"),
+    ('\u{1E29}', "h"),
+    ('\u{1E2A}', "H"),
+    ('\u{1E2B}', "h"),
+    ('\u{1E2C}', "I"),
+    ('\u{1E2D}', "i"),
+    ('\u{1E2E}', "I"),
+    ('\u{1E2F}', "i"),
+    ('\u{1E30}', "K"),
+    ('\u{1E31}', "k"),
+    ('\u{1E32}', "K"),
+    ('\u{1E33}', "k"),
+    ('\u{1E34}', "K"),
+    ('\u{1E35}', "k"),
+    ('\u{1E36}', "L"),
+    ('\u{1E37}', "l"),
+    ('\u{1E38}', "L"),
+    ('\u{1E39}', "l"),
+    ('\u{1E3A}', "L"),
+    ('\u{1E3B}', "l"),
+    ('\u{1E3C}', "L"),
+    ('\u{1E3D}', "l"),
+    ('\u{1E3E}', "M"),
+    ('\u{1E3F}', "m"),
+    ('\u{1E40}', "M"),
+    ('\u{1E41}', "m"),
+    ('\u{1E42}', "M"),
+    ('\u{1E43}', "m"),
+    ('\u{1E44}', "N"),
+    ('\u{1E45}', "n"),
+    ('\u{1E46}', "N"),
+    ('\u{1E47}', "n"),
+    ('\u{1E48}', "N"),
+    ('\u{1E49}', "n"),
+    ('\u{1E4A}', "N"),
+    ('\u{1E4B}', "n"),
+    ('\u{1E4C}', "O"),
+    ('\u{1E4D}', "o"),
+    ('\u{1E4E}', "O"),
+    ('\u{1E4F}', "o"),
+    ('\u{1E50}', "O"),
+    ('\u{1E51}', "o"),
+    ('\u{1E52}', "O"),
+    ('\u{1E53}', "o"),
+    ('\u{1E54}', "P"),
+    ('\u{1E55}', "p"),
+    ('\u{1E56}', "P"),
+    ('\u{1E57}', "p"),
+    ('\u{1E58}', "R"),
+    ('\u{1E59}', "r"),
+    ('\u{1E5A}', "R"),
+    ('\u{1E5B}', "r"),
+    ('\u{1E5C}', "R"),
+    ('\u{1E5D}', "r"),
+    ('\u{1E5E}', "R"),
+    ('\u{1E5F}', "r"),
+    ('\u{1E60}', "S"),
+    ('\u{1E61}', "s"),
+    ('\u{1E62}', "S"),
+    ('\u{1E63}', "s"),
+    ('\u{1E64}', "S"),
+    ('\u{1E65}', "s"),
+    ('\u{1E66}', "S"),
+    ('\u{1E67}', "s"),
+    ('\u{1E68}', "S"),
+    ('\u{1E69}', "s"),
+    ('\u{1E6A}', "T"),
+    ('\u{1E6B}', "t"),
+    ('\u{1E6C}', "T"),
+    ('\u{1E6D}', "t"),
+    ('\u{1E6E}', "T"),
+    ('\u{1E6F}', "t"),
+    ('\u{1E70}', "T"),
+    ('\u{1E71}', "t"),
+    ('\u{1E72}', "U"),
+    ('\u{1E73}', "u"),
+    ('\u{1E74}', "U"),
+    ('\u{1E75}', "u"),
+    ('\u{1E76}', "U"),
+    ('\u{1E77}', "u"),
+    ('\u{1E78}', "U"),
+    ('\u{1E79}', "u"),
+    ('\u{1E7A}', "U"),
+    ('\u{1E7B}', "u"),
+    ('\u{1E7C}', "V"),
+    ('\u{1E7D}', "v"),
+    ('\u{1E7E}', "V"),
+    ('\u{1E7F}', "v"),
+    ('\u{1E80}', "W"),
+    ('\u{1E81}', "w"),
+    ('\u{1E82}', "W"),
+    ('\u{1E83}', "w"),
+    ('\u{1E84}', "W"),
+    ('\u{1E85}', "w"),
+    ('\u{1E86}', "W"),
+    ('\u{1E87}', "w"),
+    ('\u{1E88}', "W"),
+    ('\u{1E89}', "w"),
+    ('\u{1E8A}', "X"),
+    ('\u{1E8B}', "x"),
+    ('\u{1E8C}', "X"),
+    ('\u{1E8D}', "x"),
+    ('\u{1E8E}', "Y"),
+    ('\u{1E8F}', "y"),
+    ('\u{1E90}', "Z"),
+    ('\u{1E91}', "z"),
+    ('\u{1E92}', "Z"),
+    ('\u{1E93}', "z"),
+    ('\u{1E94}', "Z"),
+    ('\u{1E95}', "z"),
+    ('\u{1E96}', "h"),
+    ('\u{1E97}', "t"),
+    ('\u{1E98}', "w"),
+    ('\u{1E99}', "y"),
+    ('\u{1E9B}', "s"),
+    ('\u{1E9E}', "SS"),
+    ('\u{1EA0}', "A"),
+    ('\u{1EA1}', "a"),
+    ('\u{1EA2}', "A"),
+    ('\u{1EA3}', "a"),
+    ('\u{1EA4}', "A"),
+    ('\u{1EA5}', "a"),
+    ('\u{1EA6}', "A"),
+    ('\u{1EA7}', "a"),
+    ('\u{1EA8}', "A"),
+    ('\u{1EA9}', "a"),
+    ('\u{1EAA}', "A"),
+    ('\u{1EAB}', "a"),
+    ('\u{1EAC}', "A"),
+    ('\u{1EAD}', "a"),
+    ('\u{1EAE}', "A"),
+    ('\u{1EAF}', "a"),
+    ('\u{1EB0}', "A"),
+    ('\u{1EB1}', "a"),
+    ('\u{1EB2}', "A"),
+    ('\u{1EB3}', "a"),
+    ('\u{1EB4}', "A"),
+    ('\u{1EB5}', "a"),
+    ('\u{1EB6}', "A"),
+    ('\u{1EB7}', "a"),
+    ('\u{1EB8}', "E"),
+    ('\u{1EB9}', "e"),
+    ('\u{1EBA}', "E"),
+    ('\u{1EBB}', "e"),
+    ('\u{1EBC}', "E"),
+    ('\u{1EBD}', "e"),
+    ('\u{1EBE}', "E"),
+    ('\u{1EBF}', "e"),
+    ('\u{1EC0}', "E"),
+    ('\u{1EC1}', "e"),
+    ('\u{1EC2}', "E"),
+    ('\u{1EC3}', "e"),
+    ('\u{1EC4}', "E"),
+    ('\u{1EC5}', "e"),
+    ('\u{1EC6}', "E"),
+    ('\u{1EC7}', "e"),
+    ('\u{1EC8}', "I"),
+    ('\u{1EC9}', "i"),
+    ('\u{1ECA}', "I"),
+    ('\u{1ECB}', "i"),
+    ('\u{1ECC}', "O"),
+    ('\u{1ECD}', "o"),
+    ('\u{1ECE}', "O"),
+    ('\u{1ECF}', "o"),
+    ('\u{1ED0}', "O"),
+    ('\u{1ED1}', "o"),
+    ('\u{1ED2}', "O"),
+    ('\u{1ED3}', "o"),
+    ('\u{1ED4}', "O"),
+    ('\u{1ED5}', "o"),
+    ('\u{1ED6}', "O"),
+    ('\u{1ED7}', "o"),
+    ('\u{1ED8}', "O"),
+    ('\u{1ED9}', "o"),
+    ('\u{1EDA}', "O"),
+    ('\u{1EDB}', "o"),
+    ('\u{1EDC}', "O"),
+    ('\u{1EDD}', "o"),
+    ('\u{1EDE}', "O"),
+    ('\u{1EDF}', "o"),
+    ('\u{1EE0}', "O"),
+    ('\u{1EE1}', "o"),
+    ('\u{1EE2}', "O"),
+    ('\u{1EE3}', "o"),
+    ('\u{1EE4}', "U"),
+    ('\u{1EE5}', "u"),
+    ('\u{1EE6}', "U"),
+    ('\u{1EE7}', "u"),
+    ('\u{1EE8}', "U"),
+    ('\u{1EE9}', "u"),
+    ('\u{1EEA}', "U"),
+    ('\u{1EEB}', "u"),
+    ('\u{1EEC}', "U"),
+    ('\u{1EED}', "u"),
+    ('\u{1EEE}', "U"),
+    ('\u{1EEF}', "u"),
+    ('\u{1EF0}', "U"),
+    ('\u{1EF1}', "u"),
+    ('\u{1EF2}', "Y"),
+    ('\u{1EF3}', "y"),
+    ('\u{1EF4}', "Y"),
+    ('\u{1EF5}', "y"),
+    ('\u{1EF6}', "Y"),
+    ('\u{1EF7}', "y"),
+    ('\u{1EF8}', "Y"),
+    ('\u{1EF9}', "y"),
+    ('\u{1EFA}', "LL"),
+    ('\u{1EFB}', "ll"),
+    ('\u{1EFC}', "V"),
+    ('\u{1EFD}', "v"),
+    ('\u{1EFE}', "Y"),
+    ('\u{1EFF}', "y"),
+    ('\u{2010}', "-"),
+    ('\u{2011}', "-"),
+    ('\u{2012}', "-"),
+    ('\u{2013}', "-"),
+    ('\u{2014}', "-"),
+    ('\u{2015}', "-"),
+    ('\u{2018}', "'"),
+    ('\u{2019}', "'"),
+    ('\u{201A}', "'"),
+    ('\u{201B}', "'"),
+    ('\u{201C}', "\""),
+    ('\u{201D}', "\""),
+    ('\u{201E}', "\""),
+    ('\u{201F}', "\""),
+    ('\u{2022}', "*"),
+    ('\u{2026}', "..."),
+    ('\u{2032}', "'"),
+    ('\u{2033}', "\""),
+    ('\u{2035}', "'"),
+    ('\u{2036}', "\""),
+    ('\u{2039}', "\""),
+    ('\u{203A}', "\""),
+    ('\u{2070}', "0"),
+    ('\u{2071}', "i"),
+    ('\u{2074}', "4"),
+    ('\u{2075}', "5"),
+    ('\u{2076}', "6"),
+    ('\u{2077}', "7"),
+    ('\u{2078}', "8"),
+    ('\u{2079}', "9"),
+    ('\u{207A}', "+"),
+    ('\u{207C}', "="),
+    ('\u{207D}', "("),
+    ('\u{207E}', ")"),
+    ('\u{207F}', "n"),
+    ('\u{2080}', "0"),
+    ('\u{2081}', "1"),
+    ('\u{2082}', "2"),
+    ('\u{2083}', "3"),
+    ('\u{2084}', "4"),
+    ('\u{2085}', "5"),
+    ('\u{2086}', "6"),
+    ('\u{2087}', "7"),
+    ('\u{2088}', "8"),
+    ('\u{2089}', "9"),
+    ('\u{208A}', "+"),
+    ('\u{208C}', "="),
+    ('\u{208D}', "("),
+    ('\u{208E}', ")"),
+    ('\u{2090}', "a"),
+    ('\u{2091}', "e"),
+    ('\u{2092}', "o"),
+    ('\u{2093}', "x"),
+    ('\u{2095}', "h"),
+    ('\u{2096}', "k"),
+    ('\u{2097}', "l"),
+    ('\u{2098}', "m"),
+    ('\u{2099}', "n"),
+    ('\u{209A}', "p"),
+    ('\u{209B}', "s"),
+    ('\u{209C}', "t"),
+    ('\u{2212}', "-"),
+    ('\u{2460}', "1"),
+    ('\u{2461}', "2"),
+    ('\u{2462}', "3"),
+    ('\u{2463}', "4"),
+    ('\u{2464}', "5"),
+    ('\u{2465}', "6"),
+    ('\u{2466}', "7"),
+    ('\u{2467}', "8"),
+    ('\u{2468}', "9"),
+    ('\u{2469}', "10"),
+    ('\u{246A}', "11"),
+    ('\u{246B}', "12"),
+    ('\u{246C}', "13"),
+    ('\u{246D}', "14"),
+    ('\u{246E}', "15"),
+    ('\u{246F}', "16"),
+    ('\u{2470}', "17"),
+    ('\u{2471}', "18"),
+    ('\u{2472}', "19"),
+    ('\u{2473}', "20"),
+    ('\u{2474}', "(1)"),
+    ('\u{2475}', "(2)"),
+    ('\u{2476}', "(3)"),
+    ('\u{2477}', "(4)"),
+    ('\u{2478}', "(5)"),
+    ('\u{2479}', "(6)"),
+    ('\u{247A}', "(7)"),
+    ('\u{247B}', "(8)"),
+    ('\u{247C}', "(9)"),
+    ('\u{247D}', "(10)"),
+    ('\u{247E}', "(11)"),
+    ('\u{247F}', "(12)"),
+    ('\u{2480}', "(13)"),
+    ('\u{2481}', "(14)"),
+    ('\u{2482}', "(15)"),
+    ('\u{2483}', "(16)"),
+    ('\u{2484}', "(17)"),
+    ('\u{2485}', "(18)"),
+    ('\u{2486}', "(19)"),
+    ('\u{2487}', "(20)"),
+    ('\u{2488}', "1."),
+    ('\u{2489}', "2."),
+    ('\u{248A}', "3."),
+    ('\u{248B}', "4."),
+    ('\u{248C}', "5."),
+    ('\u{248D}', "6."),
+    ('\u{248E}', "7."),
+    ('\u{248F}', "8."),
+    ('\u{2490}', "9."),
+    ('\u{2491}', "10."),
+    ('\u{2492}', "11."),
+    ('\u{2493}', "12."),
+    ('\u{2494}', "13."),
+    ('\u{2495}', "14."),
+    ('\u{2496}', "15."),
+    ('\u{2497}', "16."),
+    ('\u{2498}', "17."),
+    ('\u{2499}', "18."),
+    ('\u{249A}', "19."),
+    ('\u{249B}', "20."),
+    ('\u{249C}', "(a)"),
+    ('\u{249D}', "(b)"),
+    ('\u{249E}', "(c)"),
+    ('\u{249F}', "(d)"),
+    ('\u{24A0}', "(e)"),
+    ('\u{24A1}', "(f)"),
+    ('\u{24A2}', "(g)"),
+    ('\u{24A3}', "(h)"),
+    ('\u{24A4}', "(i)"),
+    ('\u{24A5}', "(j)"),
+    ('\u{24A6}', "(k)"),
+    ('\u{24A7}', "(l)"),
+    ('\u{24A8}', "(m)"),
+    ('\u{24A9}', "(n)"),
+    ('\u{24AA}', "(o)"),
+    ('\u{24AB}', "(p)"),
+    ('\u{24AC}', "(q)"),
+    ('\u{24AD}', "(r)"),
+    ('\u{24AE}', "(s)"),
+    ('\u{24AF}', "(t)"),
+    ('\u{24B0}', "(u)"),
+    ('\u{24B1}', "(v)"),
+    ('\u{24B2}', "(w)"),
+    ('\u{24B3}', "(x)"),
+    ('\u{24B4}', "(y)"),
+    ('\u{24B5}', "(z)"),
+    ('\u{24B6}', "A"),
+    ('\u{24B7}', "B"),
+    ('\u{24B8}', "C"),
+    ('\u{24B9}', "D"),
+    ('\u{24BA}', "E"),
+    ('\u{24BB}', "F"),
+    ('\u{24BC}', "G"),
+    ('\u{24BD}', "H"),
+    ('\u{24BE}', "I"),
+    ('\u{24BF}', "J"),
+    ('\u{24C0}', "K"),
+    ('\u{24C1}', "L"),
+    ('\u{24C2}', "M"),
+    ('\u{24C3}', "N"),
+    ('\u{24C4}', "O"),
+    ('\u{24C5}', "P"),
+    ('\u{24C6}', "Q"),
+    ('\u{24C7}', "R"),
+    ('\u{24C8}', "S"),
+    ('\u{24C9}', "T"),
+    ('\u{24CA}', "U"),
+    ('\u{24CB}', "V"),
+    ('\u{24CC}', "W"),
+    ('\u{24CD}', "X"),
+    ('\u{24CE}', "Y"),
+    ('\u{24CF}', "Z"),
+    ('\u{24D0}', "a"),
+    ('\u{24D1}', "b"),
+    ('\u{24D2}', "c"),
+    ('\u{24D3}', "d"),
+    ('\u{24D4}', "e"),
+    ('\u{24D5}', "f"),
+    ('\u{24D6}', "g"),
+    ('\u{24D7}', "h"),
+    ('\u{24D8}', "i"),
+    ('\u{24D9}', "j"),
+    ('\u{24DA}', "k"),
+    ('\u{24DB}', "l"),
+    ('\u{24DC}', "m"),
+    ('\u{24DD}', "n"),
+    ('\u{24DE}', "o"),
+    ('\u{24DF}', "p"),
+    ('\u{24E0}', "q"),
+    ('\u{24E1}', "r"),
+    ('\u{24E2}', "s"),
+    ('\u{24E3}', "t"),
+    ('\u{24E4}', "u"),
+    ('\u{24E5}', "v"),
+    ('\u{24E6}', "w"),
+    ('\u{24E7}', "x"),
+    ('\u{24E8}', "y"),
+    ('\u{24E9}', "z"),
+    ('\u{24EA}', "0"),
+    ('\u{2C7C}', "j"),
+    ('\u{2C7D}', "V"),
+    ('\u{FB00}', "ff"),
+    ('\u{FB01}', "fi"),
+    ('\u{FB02}', "fl"),
+    ('\u{FB03}', "ffi"),
+    ('\u{FB04}', "ffl"),
+    ('\u{FB05}', "st"),
+    ('\u{FB06}', "st"),
+    ('\u{FF01}', "!"),
+    ('\u{FF02}', "\""),
+    ('\u{FF03}', "#"),
+    ('\u{FF04}', "$"),
+    ('\u{FF05}', "%"),
+    ('\u{FF06}', "&"),
+    ('\u{FF07}', "'"),
+    ('\u{FF08}', "("),
+    ('\u{FF09}', ")"),
+    ('\u{FF0A}', "*"),
+    ('\u{FF0B}', "+"),
+    ('\u{FF0C}', ","),
+    ('\u{FF0D}', "-"),
+    ('\u{FF0E}', "."),
+    ('\u{FF0F}', "/"),
+    ('\u{FF10}', "0"),
+    ('\u{FF11}', "1"),
+    ('\u{FF12}', "2"),
+    ('\u{FF13}', "3"),
+    ('\u{FF14}', "4"),
+    ('\u{FF15}', "5"),
+    ('\u{FF16}', "6"),
+    ('\u{FF17}', "7"),
+    ('\u{FF18}', "8"),
+    ('\u{FF19}', "9"),
+    ('\u{FF1A}', ":"),
+    ('\u{FF1B}', ";"),
+    ('\u{FF1C}', "<"),
+    ('\u{FF1D}', "="),
+    ('\u{FF1E}', ">"),
+    ('\u{FF1F}', "?"),
+    ('\u{FF20}', "@"),
+    ('\u{FF21}', "A"),
+    ('\u{FF22}', "B"),
+    ('\u{FF23}', "C"),
+    ('\u{FF24}', "D"),
+    ('\u{FF25}', "E"),
+    ('\u{FF26}', "F"),
+    ('\u{FF27}', "G"),
+    ('\u{FF28}', "H"),
+    ('\u{FF29}', "I"),
+    ('\u{FF2A}', "J"),
+    ('\u{FF2B}', "K"),
+    ('\u{FF2C}', "L"),
+    ('\u{FF2D}', "M"),
+    ('\u{FF2E}', "N"),
+    ('\u{FF2F}', "O"),
+    ('\u{FF30}', "P"),
+    ('\u{FF31}', "Q"),
+    ('\u{FF32}', "R"),
+    ('\u{FF33}', "S"),
+    ('\u{FF34}', "T"),
+    ('\u{FF35}', "U"),
+    ('\u{FF36}', "V"),
+    ('\u{FF37}', "W"),
+    ('\u{FF38}', "X"),
+    ('\u{FF39}', "Y"),
+    ('\u{FF3A}', "Z"),
+    ('\u{FF3B}', "["),
+    ('\u{FF3C}', "\\"),
+    ('\u{FF3D}', "]"),
+    ('\u{FF3E}', "^"),
+    ('\u{FF3F}', "_"),
+    ('\u{FF40}', "`"),
+    ('\u{FF41}', "a"),
+    ('\u{FF42}', "b"),
+    ('\u{FF43}', "c"),
+    ('\u{FF44}', "d"),
+    ('\u{FF45}', "e"),
+    ('\u{FF46}', "f"),
+    ('\u{FF47}', "g"),
+    ('\u{FF48}', "h"),
+    ('\u{FF49}', "i"),
+    ('\u{FF4A}', "j"),
+    ('\u{FF4B}', "k"),
+    ('\u{FF4C}', "l"),
+    ('\u{FF4D}', "m"),
+    ('\u{FF4E}', "n"),
+    ('\u{FF4F}', "o"),
+    ('\u{FF50}', "p"),
+    ('\u{FF51}', "q"),
+    ('\u{FF52}', "r"),
+    ('\u{FF53}', "s"),
+    ('\u{FF54}', "t"),
+    ('\u{FF55}', "u"),
+    ('\u{FF56}', "v"),
+    ('\u{FF57}', "w"),
+    ('\u{FF58}', "x"),
+    ('\u{FF59}', "y"),
+    ('\u{FF5A}', "z"),
+    ('\u{FF5B}', "{"),
+    ('\u{FF5C}', "|"),
+    ('\u{FF5D}', "}"),
+    ('\u{FF5E}', "~"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::WhitespaceTokenizer;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_fold_to_ascii() {
+        assert_eq!(fold_to_ascii("Ærøskøbing"), "AEroskobing");
+        assert_eq!(fold_to_ascii("café naïve façade"), "cafe naive facade");
+        assert_eq!(fold_to_ascii("Straße Łódź"), "Strasse Lodz");
+        assert_eq!(fold_to_ascii("Ｒｕｃｅｎｅ ﬁ ①"), "Rucene fi 1");
+        assert_eq!(fold_to_ascii("“quoted” – Việt"), "\"quoted\" - Viet");
+        assert_eq!(fold_to_ascii("中文"), "中文");
+    }
+
+    #[test]
+    fn test_ascii_folding_filter_preserve_original() {
+        let input = Box::new(WhitespaceTokenizer::new(Box::new(Cursor::new(
+            "déjà vu".as_bytes().to_vec(),
+        ))));
+        let mut stream = ASCIIFoldingFilter::new(input, true);
+        stream.reset().unwrap();
+        let mut tokens = vec![];
+        while stream.next_token().unwrap() {
+            let token = stream.token();
+            tokens.push((
+                String::from_utf8(token.term.clone()).unwrap(),
+                token.position,
+                token.start_offset,
+            ));
+        }
+        assert_eq!(
+            tokens,
+            vec![
+                ("déjà".to_string(), 1, 0),
+                ("deja".to_string(), 0, 0),
+                ("vu".to_string(), 1, 5),
+            ]
+        );
+    }
+}
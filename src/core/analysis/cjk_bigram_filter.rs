@@ -0,0 +1,269 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{
+    Token, TokenStream, TOKEN_TYPE_HANGUL, TOKEN_TYPE_HIRAGANA, TOKEN_TYPE_IDEOGRAPHIC,
+    TOKEN_TYPE_KATAKANA,
+};
+
+use error::Result;
+
+use std::str;
+
+/// Token type for a single CJK character emitted by `CJKBigramFilter`.
+pub const TOKEN_TYPE_SINGLE: &str = "<SINGLE>";
+/// Token type for a bigram of two CJK characters emitted by `CJKBigramFilter`.
+pub const TOKEN_TYPE_DOUBLE: &str = "<DOUBLE>";
+
+/// Forms bigrams of CJK terms that are generated from `StandardTokenizer`.
+///
+/// CJK types (Han, Hiragana, Katakana and Hangul) that are adjacent in the text
+/// are combined into overlapping bigrams, e.g. `中国人` produces `中国` and `国人`.
+/// A CJK character that is not adjacent to another one is emitted as a unigram,
+/// all the other tokens pass through unchanged.
+///
+/// If `output_unigrams` is true, each CJK character is emitted as a unigram as
+/// well, and the bigram starting at it follows at the same position.
+#[derive(Debug)]
+pub struct CJKBigramFilter {
+    input: Box<dyn TokenStream>,
+    output_unigrams: bool,
+    // chars of the current run of adjacent CJK tokens, with their offsets
+    run: Vec<(char, usize, usize)>,
+    // position increment of the first token in the run
+    run_position: usize,
+    // index of the next char to emit in the run
+    index: usize,
+    // whether the bigram ending at `index` is still to be emitted
+    pending_bigram: bool,
+    // the token which ended the current run
+    lookahead: Option<Token>,
+    exhausted: bool,
+}
+
+impl CJKBigramFilter {
+    pub fn new(input: Box<dyn TokenStream>, output_unigrams: bool) -> Self {
+        CJKBigramFilter {
+            input,
+            output_unigrams,
+            run: Vec::new(),
+            run_position: 1,
+            index: 0,
+            pending_bigram: false,
+            lookahead: None,
+            exhausted: false,
+        }
+    }
+
+    fn is_cjk(token: &Token) -> bool {
+        token.token_type == TOKEN_TYPE_IDEOGRAPHIC
+            || token.token_type == TOKEN_TYPE_HIRAGANA
+            || token.token_type == TOKEN_TYPE_KATAKANA
+            || token.token_type == TOKEN_TYPE_HANGUL
+    }
+
+    /// Splits the CJK token into chars and appends them to the current run.
+    fn push_run(&mut self, token: &Token) {
+        if self.run.is_empty() {
+            self.run_position = token.position;
+        }
+        let text = String::from_utf8_lossy(&token.term);
+        // the char offsets can only be recovered if the offsets were not corrected
+        let exact = token.end_offset - token.start_offset == text.chars().count();
+        for (i, c) in text.chars().enumerate() {
+            if exact {
+                let start = token.start_offset + i;
+                self.run.push((c, start, start + 1));
+            } else {
+                self.run.push((c, token.start_offset, token.end_offset));
+            }
+        }
+    }
+
+    /// Consumes the input tokens adjacent to the current run.
+    fn fill_run(&mut self) -> Result<()> {
+        loop {
+            if !self.input.next_token()? {
+                self.exhausted = true;
+                return Ok(());
+            }
+            let token = self.input.token();
+            let last_end = self.run.last().map(|c| c.2).unwrap_or(0);
+            if Self::is_cjk(token) && token.start_offset == last_end {
+                let token = token.clone();
+                self.push_run(&token);
+            } else {
+                self.lookahead = Some(token.clone());
+                return Ok(());
+            }
+        }
+    }
+
+    fn emit(&mut self, from: usize, to: usize, position: usize) {
+        let token = self.input.token_mut();
+        token.clear();
+        for &(c, _, _) in &self.run[from..=to] {
+            let mut buf = [0u8; 4];
+            token
+                .term
+                .extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+        }
+        token.position = position;
+        token.start_offset = self.run[from].1;
+        token.end_offset = self.run[to].2;
+        token.token_type = if from == to {
+            TOKEN_TYPE_SINGLE
+        } else {
+            TOKEN_TYPE_DOUBLE
+        };
+    }
+
+    fn emit_from_run(&mut self) {
+        let first_position = if self.index == 0 {
+            self.run_position
+        } else {
+            1
+        };
+        if self.output_unigrams {
+            if self.pending_bigram {
+                self.pending_bigram = false;
+                self.emit(self.index - 1, self.index, 0);
+            } else {
+                self.emit(self.index, self.index, first_position);
+                self.index += 1;
+                self.pending_bigram = self.index < self.run.len();
+            }
+        } else if self.run.len() == 1 {
+            self.emit(0, 0, first_position);
+            self.index = 1;
+        } else {
+            self.emit(self.index, self.index + 1, first_position);
+            self.index += 1;
+            if self.index + 1 == self.run.len() {
+                self.index = self.run.len();
+            }
+        }
+    }
+}
+
+impl TokenStream for CJKBigramFilter {
+    fn next_token(&mut self) -> Result<bool> {
+        if self.index < self.run.len() || self.pending_bigram {
+            self.emit_from_run();
+            return Ok(true);
+        }
+
+        self.run.clear();
+        self.index = 0;
+        let token = if let Some(token) = self.lookahead.take() {
+            token
+        } else if self.exhausted || !self.input.next_token()? {
+            self.exhausted = true;
+            return Ok(false);
+        } else if !Self::is_cjk(self.input.token()) {
+            return Ok(true);
+        } else {
+            self.input.token().clone()
+        };
+
+        if !Self::is_cjk(&token) {
+            *self.input.token_mut() = token;
+            return Ok(true);
+        }
+        self.push_run(&token);
+        self.fill_run()?;
+        self.emit_from_run();
+        Ok(true)
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.input.end()
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.run.clear();
+        self.run_position = 1;
+        self.index = 0;
+        self.pending_bigram = false;
+        self.lookahead = None;
+        self.exhausted = false;
+        self.input.reset()
+    }
+
+    fn token(&self) -> &Token {
+        self.input.token()
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        self.input.token_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::StandardTokenizer;
+    use std::io::Cursor;
+
+    fn bigrams(text: &str, output_unigrams: bool) -> Vec<(String, usize, usize, usize)> {
+        let tokenizer = StandardTokenizer::new(Box::new(Cursor::new(text.as_bytes().to_vec())));
+        let mut stream = CJKBigramFilter::new(Box::new(tokenizer), output_unigrams);
+        let mut tokens = vec![];
+        stream.reset().unwrap();
+        while stream.next_token().unwrap() {
+            let token = stream.token();
+            tokens.push((
+                String::from_utf8(token.term.clone()).unwrap(),
+                token.position,
+                token.start_offset,
+                token.end_offset,
+            ));
+        }
+        tokens
+    }
+
+    #[test]
+    fn test_cjk_bigram_filter() {
+        let tokens = bigrams("我是中国人, 在 Beijing 工作。한국어", false);
+        assert_eq!(
+            tokens,
+            vec![
+                ("我是".to_string(), 1, 0, 2),
+                ("是中".to_string(), 1, 1, 3),
+                ("中国".to_string(), 1, 2, 4),
+                ("国人".to_string(), 1, 3, 5),
+                ("在".to_string(), 1, 7, 8),
+                ("Beijing".to_string(), 1, 9, 16),
+                ("工作".to_string(), 1, 17, 19),
+                ("한국".to_string(), 1, 20, 22),
+                ("국어".to_string(), 1, 21, 23),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cjk_bigram_filter_unigrams() {
+        let tokens = bigrams("中国人 a", true);
+        assert_eq!(
+            tokens,
+            vec![
+                ("中".to_string(), 1, 0, 1),
+                ("中国".to_string(), 0, 0, 2),
+                ("国".to_string(), 1, 1, 2),
+                ("国人".to_string(), 0, 1, 3),
+                ("人".to_string(), 1, 2, 3),
+                ("a".to_string(), 1, 4, 5),
+            ]
+        );
+    }
+}
@@ -0,0 +1,483 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{Token, TokenStream, DEFAULT_TOKEN_TYPE, TOKEN_TYPE_ALPHANUM};
+use core::util::fst::{
+    Arc as FSTArc, ByteSequenceOutput, ByteSequenceOutputFactory, FstBuilder, InputType, Output,
+    OutputFactory, FST,
+};
+use core::util::{to_ints_ref, IntsRefBuilder};
+
+use error::{ErrorKind, Result};
+
+use std::fmt;
+use std::io::{BufRead, BufReader, Read};
+use std::sync::Arc;
+
+/// A word-frequency dictionary used by `DictionaryTokenizer`, the words are
+/// stored in a `FST` whose outputs are the frequencies of the words.
+///
+/// Words are matched case-insensitively: the words of the dictionary are
+/// lowercased when it is built, and so is the text looked up in it.
+pub struct WordDictionary {
+    fst: Option<FST<ByteSequenceOutputFactory>>,
+    num_words: usize,
+    // natural log of the sum of all the frequencies
+    log_total: f64,
+}
+
+impl fmt::Debug for WordDictionary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("WordDictionary")
+            .field("num_words", &self.num_words)
+            .field("log_total", &self.log_total)
+            .finish()
+    }
+}
+
+impl WordDictionary {
+    /// Builds the dictionary from `(word, frequency)` entries, the frequencies of
+    /// duplicated words, ignoring case, are summed up.
+    pub fn new(entries: Vec<(String, u64)>) -> Result<Self> {
+        let mut entries: Vec<(String, u64)> = entries
+            .into_iter()
+            .filter(|e| !e.0.is_empty())
+            .map(|(word, freq)| (word.chars().map(fold_case).collect(), freq))
+            .collect();
+        entries.sort_by(|a, b| a.0.as_bytes().cmp(b.0.as_bytes()));
+        entries.dedup_by(|a, b| {
+            if a.0 == b.0 {
+                b.1 += a.1;
+                true
+            } else {
+                false
+            }
+        });
+
+        let mut total = 0u64;
+        let mut builder = FstBuilder::new(InputType::Byte1, ByteSequenceOutputFactory::new());
+        builder.init();
+        let mut scratch_ints_ref = IntsRefBuilder::new();
+        for (word, freq) in &entries {
+            let freq = (*freq).max(1);
+            total += freq;
+            builder.add(
+                to_ints_ref(word.as_bytes(), &mut scratch_ints_ref),
+                ByteSequenceOutput::new(freq.to_be_bytes().to_vec()),
+            )?;
+        }
+
+        Ok(WordDictionary {
+            fst: builder.finish()?,
+            num_words: entries.len(),
+            log_total: (total.max(1) as f64).ln(),
+        })
+    }
+
+    /// Reads a dictionary with one entry per line, each line is a word followed
+    /// by its frequency, separated by whitespaces. Any extra column is ignored,
+    /// and blank lines or lines starting with `#` are skipped.
+    pub fn from_reader<R: Read>(reader: R) -> Result<Self> {
+        let mut entries = Vec::new();
+        for (i, line) in BufReader::new(reader).lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut columns = line.split_whitespace();
+            let word = columns.next().unwrap();
+            let freq = match columns.next().map(str::parse::<u64>) {
+                Some(Ok(freq)) => freq,
+                _ => bail!(ErrorKind::IllegalArgument(format!(
+                    "invalid dictionary entry at line {}: '{}'",
+                    i + 1,
+                    line
+                ))),
+            };
+            entries.push((word.to_string(), freq));
+        }
+        WordDictionary::new(entries)
+    }
+
+    pub fn num_words(&self) -> usize {
+        self.num_words
+    }
+
+    /// Returns the frequency of `word`, if it is in the dictionary.
+    pub fn frequency(&self, word: &str) -> Result<Option<u64>> {
+        match self.fst {
+            Some(ref fst) => {
+                let word: String = word.chars().map(fold_case).collect();
+                Ok(fst
+                    .get(word.as_bytes())?
+                    .map(|o| decode_frequency(o.inner())))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the arc reached in the `FST` by `chars`, `None` if no dictionary
+    /// word starts with them.
+    fn prefix_arc(&self, chars: &[char]) -> Result<Option<FSTArc<ByteSequenceOutput>>> {
+        let mut arc = match self.fst {
+            Some(ref fst) => fst.root_arc(),
+            None => return Ok(None),
+        };
+        for &c in chars {
+            match self.next_arc(&arc, c)? {
+                Some(next) => arc = next,
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(arc))
+    }
+
+    /// Follows `c` from `arc`, the arc reached by the chars of a prefix of a
+    /// word, returns `None` if no dictionary word starts with the extended prefix.
+    fn next_arc(
+        &self,
+        arc: &FSTArc<ByteSequenceOutput>,
+        c: char,
+    ) -> Result<Option<FSTArc<ByteSequenceOutput>>> {
+        let fst = self.fst.as_ref().unwrap();
+        let mut bytes_reader = fst.bytes_reader();
+        let mut arc = arc.clone();
+        let mut buf = [0u8; 4];
+        for &b in fold_case(c).encode_utf8(&mut buf).as_bytes() {
+            match fst.find_target_arc(i32::from(b), &arc, &mut bytes_reader)? {
+                Some(next) => arc = next,
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(arc))
+    }
+
+    /// Collects the dictionary words starting at `chars[start]`, as pairs of the
+    /// end index of the word (exclusive) and its frequency.
+    fn prefixes(&self, chars: &[char], start: usize, words: &mut Vec<(usize, u64)>) -> Result<()> {
+        let fst = match self.fst {
+            Some(ref fst) => fst,
+            None => return Ok(()),
+        };
+        let mut bytes_reader = fst.bytes_reader();
+        let mut arc = fst.root_arc();
+        let mut output = fst.outputs().empty();
+        let mut buf = [0u8; 4];
+        for (i, c) in chars[start..].iter().enumerate() {
+            for &b in fold_case(*c).encode_utf8(&mut buf).as_bytes() {
+                match fst.find_target_arc(i32::from(b), &arc, &mut bytes_reader)? {
+                    Some(next) => arc = next,
+                    None => return Ok(()),
+                }
+                if let Some(ref out) = arc.output {
+                    if !out.is_empty() {
+                        output.concat(out);
+                    }
+                }
+            }
+            if arc.is_final() {
+                let freq = match arc.next_final_output {
+                    Some(ref out) if !out.is_empty() => decode_frequency(output.cat(out).inner()),
+                    _ => decode_frequency(output.inner()),
+                };
+                words.push((start + i + 1, freq));
+            }
+        }
+        Ok(())
+    }
+
+    /// Segments `chars` into words along the path of maximum probability through
+    /// all the dictionary words found in it, returns the end index of each word.
+    fn segment(&self, chars: &[char]) -> Result<Vec<usize>> {
+        let n = chars.len();
+        // best[i] is the log probability of the best segmentation of chars[i..],
+        // and next[i] the end of the first word of that segmentation.
+        let mut best = vec![0f64; n + 1];
+        let mut next = vec![n; n + 1];
+        let mut words = Vec::new();
+        for i in (0..n).rev() {
+            words.clear();
+            self.prefixes(chars, i, &mut words)?;
+            if words.first().map(|w| w.0) != Some(i + 1) {
+                // a char that isn't a word by itself counts as a rare word
+                words.insert(0, (i + 1, 1));
+            }
+            best[i] = f64::NEG_INFINITY;
+            for &(end, freq) in &words {
+                let prob = (freq as f64).ln() - self.log_total + best[end];
+                if prob > best[i] {
+                    best[i] = prob;
+                    next[i] = end;
+                }
+            }
+        }
+
+        let mut ends = Vec::new();
+        let mut i = 0;
+        while i < n {
+            i = next[i];
+            ends.push(i);
+        }
+        Ok(ends)
+    }
+}
+
+// Lowercases `c` if it maps to a single lowercase char, so that the words
+// found in the folded text have the same char offsets as in the original one.
+fn fold_case(c: char) -> char {
+    let mut lower = c.to_lowercase();
+    match (lower.next(), lower.next()) {
+        (Some(l), None) => l,
+        _ => c,
+    }
+}
+
+fn decode_frequency(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0u64, |freq, &b| freq << 8 | u64::from(b))
+}
+
+fn is_han(c: char) -> bool {
+    match c {
+        '\u{3400}'..='\u{4DBF}'
+        | '\u{4E00}'..='\u{9FFF}'
+        | '\u{F900}'..='\u{FAFF}'
+        | '\u{20000}'..='\u{2FA1F}' => true,
+        _ => false,
+    }
+}
+
+/// A tokenizer which segments text into words with a `WordDictionary`, mostly
+/// useful for Chinese text which has no separator between words.
+///
+/// Runs of letters and digits are segmented along the path of maximum
+/// probability, where the probability of a word is its frequency divided by the
+/// total frequency of the dictionary. Consecutive letters or digits which are not
+/// Chinese and don't form a dictionary word together are kept as a single token.
+/// Other chars, such as whitespaces and punctuations, separate the tokens.
+pub struct DictionaryTokenizer {
+    dictionary: Arc<WordDictionary>,
+    reader: Box<dyn Read>,
+
+    chars: Vec<char>,
+    // (start, end) of the tokens in `chars`
+    tokens: Vec<(usize, usize)>,
+    loaded: bool,
+    index: usize,
+
+    token: Token,
+}
+
+impl fmt::Debug for DictionaryTokenizer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DictionaryTokenizer")
+            .field("dictionary", &self.dictionary)
+            .field("tokens", &self.tokens)
+            .field("index", &self.index)
+            .field("token", &self.token)
+            .finish()
+    }
+}
+
+impl DictionaryTokenizer {
+    pub fn new(reader: Box<dyn Read>, dictionary: Arc<WordDictionary>) -> Self {
+        DictionaryTokenizer {
+            dictionary,
+            reader,
+            chars: Vec::new(),
+            tokens: Vec::new(),
+            loaded: false,
+            index: 0,
+            token: Token::new(),
+        }
+    }
+
+    fn load(&mut self) -> Result<()> {
+        if self.loaded {
+            return Ok(());
+        }
+        let mut text = String::new();
+        self.reader.read_to_string(&mut text)?;
+        self.chars = text.chars().collect();
+        self.loaded = true;
+
+        let mut start = 0;
+        while start < self.chars.len() {
+            if !self.chars[start].is_alphanumeric() {
+                start += 1;
+                continue;
+            }
+            let mut end = start + 1;
+            while end < self.chars.len() && self.chars[end].is_alphanumeric() {
+                end += 1;
+            }
+            self.segment_run(start, end)?;
+            start = end;
+        }
+        Ok(())
+    }
+
+    fn segment_run(&mut self, start: usize, end: usize) -> Result<()> {
+        let ends = self.dictionary.segment(&self.chars[start..end])?;
+        let mut word_start = start;
+        // whether the last token of the run is made of non Han chars only
+        let mut last_non_han = false;
+        // the arc reached in the dictionary by the chars of the last token, it
+        // is followed one char at a time as the token grows, and `None` once no
+        // dictionary word starts with the token
+        let mut last_arc = None;
+        for word_end in ends {
+            let word_end = start + word_end;
+            let single_non_han = word_end == word_start + 1 && !is_han(self.chars[word_start]);
+            let last_is_word = last_arc.as_ref().map_or(false, FSTArc::is_final);
+            if single_non_han && last_non_han && !last_is_word {
+                // merge into the previous token which is made of such chars too
+                self.tokens.last_mut().unwrap().1 = word_end;
+                last_arc = match last_arc {
+                    Some(arc) => self.dictionary.next_arc(&arc, self.chars[word_start])?,
+                    None => None,
+                };
+            } else {
+                let word = &self.chars[word_start..word_end];
+                self.tokens.push((word_start, word_end));
+                last_non_han = word.iter().all(|&c| !is_han(c));
+                last_arc = if last_non_han {
+                    self.dictionary.prefix_arc(word)?
+                } else {
+                    None
+                };
+            }
+            word_start = word_end;
+        }
+        Ok(())
+    }
+
+    fn word(&self, (start, end): (usize, usize)) -> String {
+        self.chars[start..end].iter().collect()
+    }
+}
+
+impl TokenStream for DictionaryTokenizer {
+    fn next_token(&mut self) -> Result<bool> {
+        self.clear_token();
+        self.load()?;
+
+        if self.index >= self.tokens.len() {
+            return Ok(false);
+        }
+        let (start, end) = self.tokens[self.index];
+        self.index += 1;
+
+        let word = self.word((start, end));
+        self.token.term.extend_from_slice(word.as_bytes());
+        self.token.token_type = if word.chars().any(is_han) {
+            DEFAULT_TOKEN_TYPE
+        } else {
+            TOKEN_TYPE_ALPHANUM
+        };
+        self.token.set_offset(start, end)?;
+        Ok(true)
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.end_token();
+        let final_offset = self.chars.len();
+        self.token.set_offset(final_offset, final_offset)
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.index = 0;
+        Ok(())
+    }
+
+    fn token(&self) -> &Token {
+        &self.token
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        &mut self.token
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn dictionary() -> Arc<WordDictionary> {
+        let dict = "# word frequency\n我 1000\n来到 200\n北京 500\n清华 100\n清华大学 300\n大学 \
+                    400\n华大 10\nT恤 20\n";
+        Arc::new(WordDictionary::from_reader(dict.as_bytes()).unwrap())
+    }
+
+    fn tokenize(text: &str) -> Vec<(String, usize, usize)> {
+        let mut tokenizer = DictionaryTokenizer::new(
+            Box::new(Cursor::new(text.as_bytes().to_vec())),
+            dictionary(),
+        );
+        let mut tokens = vec![];
+        tokenizer.reset().unwrap();
+        while tokenizer.next_token().unwrap() {
+            let token = tokenizer.token();
+            tokens.push((
+                String::from_utf8(token.term.clone()).unwrap(),
+                token.start_offset,
+                token.end_offset,
+            ));
+        }
+        tokens
+    }
+
+    #[test]
+    fn test_word_dictionary() {
+        let dict = dictionary();
+        assert_eq!(dict.num_words(), 8);
+        assert_eq!(dict.frequency("清华大学").unwrap(), Some(300));
+        assert_eq!(dict.frequency("清").unwrap(), None);
+        assert_eq!(dict.frequency("t恤").unwrap(), Some(20));
+        assert_eq!(dict.frequency("T恤").unwrap(), Some(20));
+
+        assert!(WordDictionary::from_reader("北京 x".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_dictionary_tokenizer() {
+        assert_eq!(
+            tokenize("我来到北京清华大学。"),
+            vec![
+                ("我".to_string(), 0, 1),
+                ("来到".to_string(), 1, 3),
+                ("北京".to_string(), 3, 5),
+                ("清华大学".to_string(), 5, 9),
+            ]
+        );
+        assert_eq!(
+            tokenize("买iPhone 12和T恤"),
+            vec![
+                ("买".to_string(), 0, 1),
+                ("iPhone".to_string(), 1, 7),
+                ("12".to_string(), 8, 10),
+                ("和".to_string(), 10, 11),
+                ("T恤".to_string(), 11, 13),
+            ]
+        );
+        // dictionary words are found whatever the case of the text
+        assert_eq!(
+            tokenize("白t恤"),
+            vec![("白".to_string(), 0, 1), ("t恤".to_string(), 1, 3)]
+        );
+        // a long run of non Han chars is merged into a single token
+        let text = "x".repeat(100_000);
+        assert_eq!(tokenize(&text), vec![(text.clone(), 0, 100_000)]);
+    }
+}
@@ -0,0 +1,215 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{Token, TokenStream};
+
+use error::Result;
+
+/// Tokenizes the given token into n-grams of given size(s), starting from the
+/// front of the token, e.g. `search` with sizes 1 to 3 produces `s`, `se` and
+/// `sea`.
+///
+/// The first gram keeps the position increment of the input token, the others
+/// are emitted at the same position. When the offsets of the input token match
+/// its length, the offsets of each gram cover only the chars of the gram.
+///
+/// Tokens shorter than `min_gram` or longer than `max_gram` chars are dropped
+/// from the stream unless `preserve_original` is true.
+#[derive(Debug)]
+pub struct EdgeNGramTokenFilter {
+    input: Box<dyn TokenStream>,
+    min_gram: usize,
+    max_gram: usize,
+    preserve_original: bool,
+
+    // state of the token being split into grams
+    chars: Vec<char>,
+    start_offset: usize,
+    end_offset: usize,
+    position: usize,
+    gram_size: usize,
+    // whether the current token is to be emitted after its grams
+    pending_original: bool,
+    skipped_positions: usize,
+}
+
+impl EdgeNGramTokenFilter {
+    pub fn new(
+        input: Box<dyn TokenStream>,
+        min_gram: usize,
+        max_gram: usize,
+        preserve_original: bool,
+    ) -> Self {
+        assert!(min_gram >= 1, "minimum gram size must be greater than zero");
+        assert!(
+            min_gram <= max_gram,
+            "minimum gram size must not be greater than maximum gram size"
+        );
+        EdgeNGramTokenFilter {
+            input,
+            min_gram,
+            max_gram,
+            preserve_original,
+            chars: Vec::new(),
+            start_offset: 0,
+            end_offset: 0,
+            position: 0,
+            gram_size: 0,
+            pending_original: false,
+            skipped_positions: 0,
+        }
+    }
+
+    fn set_term(token: &mut Token, chars: &[char]) {
+        token.term.clear();
+        for &c in chars {
+            let mut buf = [0u8; 4];
+            token
+                .term
+                .extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+        }
+    }
+}
+
+impl TokenStream for EdgeNGramTokenFilter {
+    fn next_token(&mut self) -> Result<bool> {
+        loop {
+            if self.gram_size > 0 && self.gram_size <= self.max_gram.min(self.chars.len()) {
+                let token = self.input.token_mut();
+                Self::set_term(token, &self.chars[..self.gram_size]);
+                token.position = if self.gram_size == self.min_gram {
+                    self.position
+                } else {
+                    0
+                };
+                token.start_offset = self.start_offset;
+                token.end_offset = if self.end_offset - self.start_offset == self.chars.len() {
+                    self.start_offset + self.gram_size
+                } else {
+                    self.end_offset
+                };
+                self.gram_size += 1;
+                return Ok(true);
+            }
+            self.gram_size = 0;
+
+            if self.pending_original {
+                self.pending_original = false;
+                let token = self.input.token_mut();
+                Self::set_term(token, &self.chars);
+                token.position = 0;
+                token.start_offset = self.start_offset;
+                token.end_offset = self.end_offset;
+                return Ok(true);
+            }
+
+            if !self.input.next_token()? {
+                return Ok(false);
+            }
+
+            let token = self.input.token_mut();
+            token.position += self.skipped_positions;
+            self.skipped_positions = 0;
+            self.chars = String::from_utf8_lossy(&token.term).chars().collect();
+            if self.chars.len() < self.min_gram {
+                if self.preserve_original {
+                    return Ok(true);
+                }
+                self.skipped_positions = token.position;
+                continue;
+            }
+            self.start_offset = token.start_offset;
+            self.end_offset = token.end_offset;
+            self.position = token.position;
+            self.gram_size = self.min_gram;
+            self.pending_original = self.preserve_original && self.chars.len() > self.max_gram;
+        }
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.input.end()?;
+        self.input.token_mut().position += self.skipped_positions;
+        Ok(())
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.chars.clear();
+        self.gram_size = 0;
+        self.pending_original = false;
+        self.skipped_positions = 0;
+        self.input.reset()
+    }
+
+    fn token(&self) -> &Token {
+        self.input.token()
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        self.input.token_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::WhitespaceTokenizer;
+    use std::io::Cursor;
+
+    fn edge_ngrams(
+        text: &str,
+        min_gram: usize,
+        max_gram: usize,
+        preserve_original: bool,
+    ) -> Vec<(String, usize, usize, usize)> {
+        let tokenizer = WhitespaceTokenizer::new(Box::new(Cursor::new(text.as_bytes().to_vec())));
+        let mut stream =
+            EdgeNGramTokenFilter::new(Box::new(tokenizer), min_gram, max_gram, preserve_original);
+        let mut tokens = vec![];
+        stream.reset().unwrap();
+        while stream.next_token().unwrap() {
+            let token = stream.token();
+            tokens.push((
+                String::from_utf8(token.term.clone()).unwrap(),
+                token.position,
+                token.start_offset,
+                token.end_offset,
+            ));
+        }
+        tokens
+    }
+
+    #[test]
+    fn test_edge_ngram_filter() {
+        assert_eq!(
+            edge_ngrams("a 北京大学 ok", 2, 3, false),
+            vec![
+                ("北京".to_string(), 2, 2, 4),
+                ("北京大".to_string(), 0, 2, 5),
+                ("ok".to_string(), 1, 7, 9),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_edge_ngram_filter_preserve_original() {
+        assert_eq!(
+            edge_ngrams("a search", 2, 3, true),
+            vec![
+                ("a".to_string(), 1, 0, 1),
+                ("se".to_string(), 1, 2, 4),
+                ("sea".to_string(), 0, 2, 5),
+                ("search".to_string(), 0, 2, 8),
+            ]
+        );
+    }
+}
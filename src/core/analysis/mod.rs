@@ -11,6 +11,10 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod analyzer;
+
+pub use self::analyzer::*;
+
 mod token_stream;
 
 pub use self::token_stream::*;
@@ -19,12 +23,50 @@ mod whitespace_tokenizer;
 
 pub use self::whitespace_tokenizer::*;
 
+mod standard_tokenizer;
+
+pub use self::standard_tokenizer::*;
+
+mod token_filter;
+
+pub use self::token_filter::*;
+
+mod ascii_folding_filter;
+
+pub use self::ascii_folding_filter::*;
+
+mod porter_stem_filter;
+
+pub use self::porter_stem_filter::*;
+
+mod cjk_bigram_filter;
+
+pub use self::cjk_bigram_filter::*;
+
+mod ngram_tokenizer;
+
+pub use self::ngram_tokenizer::*;
+
+mod edge_ngram_filter;
+
+pub use self::edge_ngram_filter::*;
+
+mod dictionary_tokenizer;
+
+pub use self::dictionary_tokenizer::*;
+
+mod synonym_filter;
+
+pub use self::synonym_filter::*;
+
 use error::Result;
 
 use std::fmt::Debug;
 
 pub const MIN_BUFFER_SIZE: usize = 10;
 pub const MAX_WORD_LEN: usize = 128;
+/// the default type of a token
+pub const DEFAULT_TOKEN_TYPE: &str = "word";
 
 #[derive(Debug, Clone)]
 pub struct Token {
@@ -62,6 +104,10 @@ pub struct Token {
     /// best to use the minimum number of bytes necessary. Some codec implementations
     /// may optimize payload storage when all payloads have the same length.
     pub payload: Vec<u8>,
+    /// The lexical type of the token, e.g. `<ALPHANUM>` for tokens emitted by
+    /// `StandardTokenizer`. Token filters may use it to decide which tokens to
+    /// process.
+    pub token_type: &'static str,
 }
 
 impl Token {
@@ -72,6 +118,7 @@ impl Token {
             start_offset: 0,
             end_offset: 0,
             payload: Vec::with_capacity(0),
+            token_type: DEFAULT_TOKEN_TYPE,
         }
     }
 
@@ -81,6 +128,7 @@ impl Token {
         self.end_offset = 0;
         self.payload.clear();
         self.term.clear();
+        self.token_type = DEFAULT_TOKEN_TYPE;
     }
 
     pub fn end(&mut self) {
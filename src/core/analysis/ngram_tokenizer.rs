@@ -0,0 +1,199 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{Token, TokenStream};
+
+use error::Result;
+
+use std::fmt;
+use std::io::Read;
+
+pub const DEFAULT_MIN_NGRAM_SIZE: usize = 1;
+pub const DEFAULT_MAX_NGRAM_SIZE: usize = 2;
+
+/// Tokenizes the input into n-grams of the given size(s).
+///
+/// The grams are emitted ordered by their start offset first, then by their
+/// length, e.g. `abc` with sizes 1 and 2 produces `a`, `ab`, `b`, `bc`, `c`.
+///
+/// By default every char is a token char, `set_token_chars` can be used to
+/// prevent the grams from crossing some chars such as whitespaces.
+pub struct NGramTokenizer {
+    min_gram: usize,
+    max_gram: usize,
+    token_chars: fn(char) -> bool,
+
+    chars: Vec<char>,
+    loaded: bool,
+    // start of the next gram
+    pos: usize,
+    // length of the next gram
+    gram_size: usize,
+
+    token: Token,
+    reader: Box<dyn Read>,
+}
+
+impl fmt::Debug for NGramTokenizer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("NGramTokenizer")
+            .field("min_gram", &self.min_gram)
+            .field("max_gram", &self.max_gram)
+            .field("pos", &self.pos)
+            .field("gram_size", &self.gram_size)
+            .field("token", &self.token)
+            .finish()
+    }
+}
+
+impl NGramTokenizer {
+    pub fn new(reader: Box<dyn Read>, min_gram: usize, max_gram: usize) -> Self {
+        assert!(min_gram >= 1, "minimum gram size must be greater than zero");
+        assert!(
+            min_gram <= max_gram,
+            "minimum gram size must not be greater than maximum gram size"
+        );
+        NGramTokenizer {
+            min_gram,
+            max_gram,
+            token_chars: |_| true,
+            chars: Vec::new(),
+            loaded: false,
+            pos: 0,
+            gram_size: min_gram,
+            token: Token::new(),
+            reader,
+        }
+    }
+
+    /// Sets the predicate deciding which chars the grams may contain.
+    pub fn set_token_chars(&mut self, token_chars: fn(char) -> bool) {
+        self.token_chars = token_chars;
+    }
+
+    fn load(&mut self) -> Result<()> {
+        if !self.loaded {
+            let mut text = String::new();
+            self.reader.read_to_string(&mut text)?;
+            self.chars = text.chars().collect();
+            self.loaded = true;
+        }
+        Ok(())
+    }
+}
+
+impl TokenStream for NGramTokenizer {
+    fn next_token(&mut self) -> Result<bool> {
+        self.clear_token();
+        self.load()?;
+
+        loop {
+            if self.pos >= self.chars.len() {
+                return Ok(false);
+            }
+            let end = self.pos + self.gram_size;
+            if self.gram_size > self.max_gram
+                || end > self.chars.len()
+                || !(self.token_chars)(self.chars[end - 1])
+            {
+                // longer grams from this start can't be valid either
+                self.pos += 1;
+                self.gram_size = self.min_gram;
+                continue;
+            }
+            if self.chars[self.pos..end - 1]
+                .iter()
+                .all(|&c| (self.token_chars)(c))
+            {
+                break;
+            }
+            self.gram_size += 1;
+        }
+
+        let end = self.pos + self.gram_size;
+        for &c in &self.chars[self.pos..end] {
+            let mut buf = [0u8; 4];
+            self.token
+                .term
+                .extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+        }
+        self.token.set_offset(self.pos, end)?;
+        self.gram_size += 1;
+        Ok(true)
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.end_token();
+        let final_offset = self.chars.len();
+        self.token.set_offset(final_offset, final_offset)
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.pos = 0;
+        self.gram_size = self.min_gram;
+        Ok(())
+    }
+
+    fn token(&self) -> &Token {
+        &self.token
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        &mut self.token
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn ngrams(tokenizer: &mut NGramTokenizer) -> Vec<(String, usize, usize)> {
+        let mut tokens = vec![];
+        tokenizer.reset().unwrap();
+        while tokenizer.next_token().unwrap() {
+            let token = tokenizer.token();
+            tokens.push((
+                String::from_utf8(token.term.clone()).unwrap(),
+                token.start_offset,
+                token.end_offset,
+            ));
+        }
+        tokens
+    }
+
+    #[test]
+    fn test_ngram_tokenizer() {
+        let mut tokenizer = NGramTokenizer::new(Box::new(Cursor::new("中国人".as_bytes())), 1, 2);
+        assert_eq!(
+            ngrams(&mut tokenizer),
+            vec![
+                ("中".to_string(), 0, 1),
+                ("中国".to_string(), 0, 2),
+                ("国".to_string(), 1, 2),
+                ("国人".to_string(), 1, 3),
+                ("人".to_string(), 2, 3),
+            ]
+        );
+        tokenizer.end().unwrap();
+        assert_eq!(tokenizer.token().end_offset, 3);
+    }
+
+    #[test]
+    fn test_ngram_tokenizer_token_chars() {
+        let mut tokenizer = NGramTokenizer::new(Box::new(Cursor::new(&b"ab cde"[..])), 2, 3);
+        tokenizer.set_token_chars(|c| !c.is_whitespace());
+        let terms: Vec<String> = ngrams(&mut tokenizer).into_iter().map(|t| t.0).collect();
+        assert_eq!(terms, vec!["ab", "cd", "cde", "de"]);
+    }
+}
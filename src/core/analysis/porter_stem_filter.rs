@@ -0,0 +1,440 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{Token, TokenStream};
+
+use error::Result;
+
+/// Transforms the token stream as per the Porter stemming algorithm.
+///
+/// Note: the input to the stemming filter must already be in lower case, so you
+/// will need to use `LowerCaseFilter` farther down the analysis chain in order for
+/// this to work properly. Terms that are not pure ASCII are left unchanged.
+#[derive(Debug)]
+pub struct PorterStemFilter {
+    input: Box<dyn TokenStream>,
+    stemmer: PorterStemmer,
+}
+
+impl PorterStemFilter {
+    pub fn new(input: Box<dyn TokenStream>) -> Self {
+        PorterStemFilter {
+            input,
+            stemmer: PorterStemmer::default(),
+        }
+    }
+}
+
+impl TokenStream for PorterStemFilter {
+    fn next_token(&mut self) -> Result<bool> {
+        if !self.input.next_token()? {
+            return Ok(false);
+        }
+
+        let token = self.input.token_mut();
+        if token.term.is_ascii() && self.stemmer.stem(&token.term) {
+            token.term.clear();
+            token.term.extend_from_slice(self.stemmer.result());
+        }
+        Ok(true)
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.input.end()
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.input.reset()
+    }
+
+    fn token(&self) -> &Token {
+        self.input.token()
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        self.input.token_mut()
+    }
+}
+
+/// Stemmer, implementing the Porter Stemming Algorithm.
+///
+/// The Stemmer transforms a word into its root form. The input word should
+/// already be in lower case.
+#[derive(Debug, Default)]
+pub struct PorterStemmer {
+    b: Vec<u8>,
+    // offset to the end of the stemmed word, inclusive
+    k: isize,
+    // a general offset into the word
+    j: isize,
+}
+
+impl PorterStemmer {
+    /// Stem the word placed into the Stemmer buffer through calls to `stem`.
+    /// Returns true if the stemming process resulted in a word different from
+    /// the input, the stemmed word can then be retrieved by `result`.
+    pub fn stem(&mut self, word: &[u8]) -> bool {
+        self.b.clear();
+        self.b.extend_from_slice(word);
+        self.k = word.len() as isize - 1;
+        self.j = 0;
+        if self.k > 1 {
+            self.step1();
+            self.step2();
+            self.step3();
+            self.step4();
+            self.step5();
+            self.step6();
+        }
+        (self.k + 1) as usize != word.len() || self.result() != word
+    }
+
+    /// the result of the last `stem` call
+    pub fn result(&self) -> &[u8] {
+        &self.b[..(self.k + 1) as usize]
+    }
+
+    fn at(&self, i: isize) -> u8 {
+        self.b[i as usize]
+    }
+
+    /// true if b[i] is a consonant.
+    fn cons(&self, i: isize) -> bool {
+        match self.at(i) {
+            b'a' | b'e' | b'i' | b'o' | b'u' => false,
+            b'y' => i == 0 || !self.cons(i - 1),
+            _ => true,
+        }
+    }
+
+    /// measures the number of consonant sequences between 0 and j. if c is
+    /// a consonant sequence and v a vowel sequence, and <..> indicates arbitrary
+    /// presence,
+    ///
+    /// ```text
+    /// <c><v>       gives 0
+    /// <c>vc<v>     gives 1
+    /// <c>vcvc<v>   gives 2
+    /// <c>vcvcvc<v> gives 3
+    /// ....
+    /// ```
+    fn m(&self) -> usize {
+        let mut n = 0;
+        let mut i = 0;
+        loop {
+            if i > self.j {
+                return n;
+            }
+            if !self.cons(i) {
+                break;
+            }
+            i += 1;
+        }
+        i += 1;
+        loop {
+            loop {
+                if i > self.j {
+                    return n;
+                }
+                if self.cons(i) {
+                    break;
+                }
+                i += 1;
+            }
+            i += 1;
+            n += 1;
+            loop {
+                if i > self.j {
+                    return n;
+                }
+                if !self.cons(i) {
+                    break;
+                }
+                i += 1;
+            }
+            i += 1;
+        }
+    }
+
+    /// true if 0,...j contains a vowel
+    fn vowel_in_stem(&self) -> bool {
+        (0..=self.j).any(|i| !self.cons(i))
+    }
+
+    /// true if j,(j-1) contain a double consonant.
+    fn double_c(&self, j: isize) -> bool {
+        j >= 1 && self.at(j) == self.at(j - 1) && self.cons(j)
+    }
+
+    /// true if i-2,i-1,i has the form consonant - vowel - consonant and also if
+    /// the second c is not w,x or y. this is used when trying to restore an e at
+    /// the end of a short word. e.g.
+    ///
+    /// ```text
+    /// cav(e), lov(e), hop(e), crim(e), but
+    /// snow, box, tray.
+    /// ```
+    fn cvc(&self, i: isize) -> bool {
+        if i < 2 || !self.cons(i) || self.cons(i - 1) || !self.cons(i - 2) {
+            return false;
+        }
+        match self.at(i) {
+            b'w' | b'x' | b'y' => false,
+            _ => true,
+        }
+    }
+
+    fn ends(&mut self, s: &str) -> bool {
+        let s = s.as_bytes();
+        let len = s.len() as isize;
+        if len > self.k + 1 {
+            return false;
+        }
+        let start = (self.k + 1 - len) as usize;
+        if &self.b[start..(self.k + 1) as usize] != s {
+            return false;
+        }
+        self.j = self.k - len;
+        true
+    }
+
+    /// sets (j+1),...k to the characters in the string s, readjusting k.
+    fn set_to(&mut self, s: &str) {
+        let start = (self.j + 1) as usize;
+        self.b.truncate(start);
+        self.b.extend_from_slice(s.as_bytes());
+        self.k = self.j + s.len() as isize;
+    }
+
+    fn r(&mut self, s: &str) {
+        if self.m() > 0 {
+            self.set_to(s);
+        }
+    }
+
+    /// gets rid of plurals and -ed or -ing. e.g.
+    ///
+    /// ```text
+    /// caresses  ->  caress
+    /// ponies    ->  poni
+    /// ties      ->  ti
+    /// caress    ->  caress
+    /// cats      ->  cat
+    ///
+    /// feed      ->  feed
+    /// agreed    ->  agree
+    /// disabled  ->  disable
+    ///
+    /// matting   ->  mat
+    /// mating    ->  mate
+    /// meeting   ->  meet
+    /// milling   ->  mill
+    /// messing   ->  mess
+    ///
+    /// meetings  ->  meet
+    /// ```
+    fn step1(&mut self) {
+        if self.at(self.k) == b's' {
+            if self.ends("sses") {
+                self.k -= 2;
+            } else if self.ends("ies") {
+                self.set_to("i");
+            } else if self.at(self.k - 1) != b's' {
+                self.k -= 1;
+            }
+        }
+        if self.ends("eed") {
+            if self.m() > 0 {
+                self.k -= 1;
+            }
+        } else if (self.ends("ed") || self.ends("ing")) && self.vowel_in_stem() {
+            self.k = self.j;
+            if self.ends("at") {
+                self.set_to("ate");
+            } else if self.ends("bl") {
+                self.set_to("ble");
+            } else if self.ends("iz") {
+                self.set_to("ize");
+            } else if self.double_c(self.k) {
+                let ch = self.at(self.k);
+                if ch != b'l' && ch != b's' && ch != b'z' {
+                    self.k -= 1;
+                }
+            } else if self.m() == 1 && self.cvc(self.k) {
+                self.set_to("e");
+            }
+        }
+    }
+
+    /// turns terminal y to i when there is another vowel in the stem.
+    fn step2(&mut self) {
+        if self.ends("y") && self.vowel_in_stem() {
+            let k = self.k as usize;
+            self.b.truncate(k + 1);
+            self.b[k] = b'i';
+        }
+    }
+
+    /// maps double suffices to single ones. so -ization ( = -ize plus -ation) maps
+    /// to -ize etc. note that the string before the suffix must give m() > 0.
+    fn step3(&mut self) {
+        if self.k == 0 {
+            return;
+        }
+        let rules: &[(&str, &str)] = match self.at(self.k - 1) {
+            b'a' => &[("ational", "ate"), ("tional", "tion")],
+            b'c' => &[("enci", "ence"), ("anci", "ance")],
+            b'e' => &[("izer", "ize")],
+            b'l' => &[
+                ("bli", "ble"),
+                ("alli", "al"),
+                ("entli", "ent"),
+                ("eli", "e"),
+                ("ousli", "ous"),
+            ],
+            b'o' => &[("ization", "ize"), ("ation", "ate"), ("ator", "ate")],
+            b's' => &[
+                ("alism", "al"),
+                ("iveness", "ive"),
+                ("fulness", "ful"),
+                ("ousness", "ous"),
+            ],
+            b't' => &[("aliti", "al"), ("iviti", "ive"), ("biliti", "ble")],
+            b'g' => &[("logi", "log")],
+            _ => &[],
+        };
+        for &(suffix, replacement) in rules {
+            if self.ends(suffix) {
+                self.r(replacement);
+                break;
+            }
+        }
+    }
+
+    /// deals with -ic-, -full, -ness etc. similar strategy to step3.
+    fn step4(&mut self) {
+        let rules: &[(&str, &str)] = match self.at(self.k) {
+            b'e' => &[("icate", "ic"), ("ative", ""), ("alize", "al")],
+            b'i' => &[("iciti", "ic")],
+            b'l' => &[("ical", "ic"), ("ful", "")],
+            b's' => &[("ness", "")],
+            _ => &[],
+        };
+        for &(suffix, replacement) in rules {
+            if self.ends(suffix) {
+                self.r(replacement);
+                break;
+            }
+        }
+    }
+
+    /// takes off -ant, -ence etc., in context <c>vcvc<v>.
+    fn step5(&mut self) {
+        if self.k == 0 {
+            return;
+        }
+        let suffixes: &[&str] = match self.at(self.k - 1) {
+            b'a' => &["al"],
+            b'c' => &["ance", "ence"],
+            b'e' => &["er"],
+            b'i' => &["ic"],
+            b'l' => &["able", "ible"],
+            b'n' => &["ant", "ement", "ment", "ent"],
+            b'o' => {
+                if self.ends("ion")
+                    && self.j >= 0
+                    && (self.at(self.j) == b's' || self.at(self.j) == b't')
+                {
+                    &[]
+                } else {
+                    &["ou"]
+                }
+            }
+            b's' => &["ism"],
+            b't' => &["ate", "iti"],
+            b'u' => &["ous"],
+            b'v' => &["ive"],
+            b'z' => &["ize"],
+            _ => return,
+        };
+        // for "-ion" the `ends` is already checked above
+        let matched = suffixes.is_empty() || suffixes.iter().any(|s| self.ends(s));
+        if matched && self.m() > 1 {
+            self.k = self.j;
+        }
+    }
+
+    /// removes a final -e if m() > 1, and changes -ll to -l if m() > 1.
+    fn step6(&mut self) {
+        self.j = self.k;
+        if self.at(self.k) == b'e' {
+            let a = self.m();
+            if a > 1 || (a == 1 && !self.cvc(self.k - 1)) {
+                self.k -= 1;
+            }
+        }
+        if self.at(self.k) == b'l' && self.double_c(self.k) && self.m() > 1 {
+            self.k -= 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_porter_stemmer() {
+        let cases = [
+            ("caresses", "caress"),
+            ("ponies", "poni"),
+            ("ties", "ti"),
+            ("caress", "caress"),
+            ("cats", "cat"),
+            ("feed", "feed"),
+            ("agreed", "agre"),
+            ("disabled", "disabl"),
+            ("matting", "mat"),
+            ("mating", "mate"),
+            ("meeting", "meet"),
+            ("milling", "mill"),
+            ("messing", "mess"),
+            ("meetings", "meet"),
+            ("happy", "happi"),
+            ("relational", "relat"),
+            ("conditional", "condit"),
+            ("rational", "ration"),
+            ("digitizer", "digit"),
+            ("generalization", "gener"),
+            ("electrical", "electr"),
+            ("hopefulness", "hope"),
+            ("adjustment", "adjust"),
+            ("adoption", "adopt"),
+            ("controll", "control"),
+            ("roll", "roll"),
+            ("jumps", "jump"),
+            ("running", "run"),
+            ("is", "is"),
+        ];
+        let mut stemmer = PorterStemmer::default();
+        for &(word, stem) in cases.iter() {
+            stemmer.stem(word.as_bytes());
+            assert_eq!(
+                String::from_utf8(stemmer.result().to_vec()).unwrap(),
+                stem,
+                "stem of {}",
+                word
+            );
+        }
+    }
+}
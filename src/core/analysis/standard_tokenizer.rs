@@ -0,0 +1,829 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{CharacterBuffer, Token, TokenStream};
+
+use error::Result;
+
+use std::fmt;
+use std::io::Read;
+
+/// Default maximum allowed token length, count by chars
+pub const DEFAULT_MAX_TOKEN_LENGTH: usize = 255;
+const IO_BUFFER_SIZE: usize = 4096;
+
+pub const TOKEN_TYPE_ALPHANUM: &str = "<ALPHANUM>";
+pub const TOKEN_TYPE_NUM: &str = "<NUM>";
+pub const TOKEN_TYPE_SOUTHEAST_ASIAN: &str = "<SOUTHEAST_ASIAN>";
+pub const TOKEN_TYPE_IDEOGRAPHIC: &str = "<IDEOGRAPHIC>";
+pub const TOKEN_TYPE_HIRAGANA: &str = "<HIRAGANA>";
+pub const TOKEN_TYPE_KATAKANA: &str = "<KATAKANA>";
+pub const TOKEN_TYPE_HANGUL: &str = "<HANGUL>";
+
+/// A grammar-based tokenizer implementing the Word Break rules from the Unicode
+/// Text Segmentation algorithm, as specified in
+/// [Unicode Standard Annex #29](http://unicode.org/reports/tr29/).
+///
+/// Like Lucene's `StandardTokenizer`, only the segments that contain letters or
+/// digits are emitted as tokens. Each ideographic or hiragana character is
+/// emitted as a single token, and runs of southeast asian characters (Thai, Lao,
+/// Myanmar and Khmer), which need a dictionary to be segmented, are emitted as
+/// a whole.
+///
+/// The token offsets are counted by chars, same as `WhitespaceTokenizer`.
+pub struct StandardTokenizer {
+    max_token_length: usize,
+    // chars read from reader but not consumed yet, `chars[pos]` is the next char.
+    chars: Vec<char>,
+    pos: usize,
+    // char offset of `chars[0]` in the whole input.
+    offset: usize,
+    final_offset: usize,
+    exhausted: bool,
+
+    token: Token,
+    io_buffer: CharacterBuffer,
+    reader: Box<dyn Read>,
+}
+
+impl fmt::Debug for StandardTokenizer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("StandardTokenizer")
+            .field("max_token_length", &self.max_token_length)
+            .field("pos", &self.pos)
+            .field("offset", &self.offset)
+            .field("final_offset", &self.final_offset)
+            .field(
+                "term",
+                &String::from_utf8(self.token.term.clone()).unwrap_or_else(|_| "".to_string()),
+            )
+            .field("position", &self.token.position)
+            .field("start_offset", &self.token.start_offset)
+            .field("end_offset", &self.token.end_offset)
+            .field("token_type", &self.token.token_type)
+            .finish()
+    }
+}
+
+impl StandardTokenizer {
+    pub fn new(reader: Box<dyn Read>) -> Self {
+        StandardTokenizer {
+            max_token_length: DEFAULT_MAX_TOKEN_LENGTH,
+            chars: Vec::with_capacity(IO_BUFFER_SIZE),
+            pos: 0,
+            offset: 0,
+            final_offset: 0,
+            exhausted: false,
+            token: Token::new(),
+            io_buffer: CharacterBuffer::new(IO_BUFFER_SIZE),
+            reader,
+        }
+    }
+
+    /// Set the max allowed token length. Tokens longer than this are split at
+    /// the max length.
+    pub fn set_max_token_length(&mut self, length: usize) {
+        assert!(length > 0, "max_token_length must be greater than zero");
+        self.max_token_length = length;
+    }
+
+    pub fn max_token_length(&self) -> usize {
+        self.max_token_length
+    }
+
+    /// Returns the char `index` chars after the current position, reading more
+    /// chars from the reader if necessary.
+    fn peek(&mut self, index: usize) -> Result<Option<char>> {
+        while self.pos + index >= self.chars.len() {
+            if self.exhausted {
+                return Ok(None);
+            }
+            self.io_buffer.fill(&mut self.reader)?;
+            if self.io_buffer.is_empty() {
+                self.exhausted = true;
+            } else {
+                let length = self.io_buffer.length;
+                self.chars
+                    .extend_from_slice(&self.io_buffer.buffer[..length]);
+            }
+        }
+        Ok(Some(self.chars[self.pos + index]))
+    }
+
+    fn peek_break(&mut self, index: usize) -> Result<Option<WordBreak>> {
+        Ok(self.peek(index)?.map(word_break))
+    }
+
+    /// Returns the relative index and word break property of the first char at or
+    /// after `index` which is not ignored by rule WB4.
+    fn next_significant(&mut self, mut index: usize) -> Result<Option<(usize, WordBreak)>> {
+        while let Some(wb) = self.peek_break(index)? {
+            if !wb.is_ignorable() {
+                return Ok(Some((index, wb)));
+            }
+            index += 1;
+        }
+        Ok(None)
+    }
+
+    /// drop the chars that already consumed
+    fn compact(&mut self) {
+        if self.pos >= IO_BUFFER_SIZE {
+            self.chars.drain(..self.pos);
+            self.offset += self.pos;
+            self.pos = 0;
+        }
+    }
+
+    /// Consume the Extend, Format and ZWJ chars following a base char.
+    fn consume_ignorable(&mut self, length: &mut usize) -> Result<()> {
+        while *length < self.max_token_length {
+            match self.peek_break(0)? {
+                Some(wb) if wb.is_ignorable() => {
+                    self.pos += 1;
+                    *length += 1;
+                }
+                _ => break,
+            }
+        }
+        Ok(())
+    }
+
+    /// Scan a word made of letters, digits, katakana and connectors, whose first
+    /// char is already consumed. Returns the token type, or `None` if the word
+    /// only contains connectors.
+    fn scan_word(&mut self, first: WordBreak, length: &mut usize) -> Result<Option<&'static str>> {
+        let mut kinds = WordKinds::default();
+        kinds.add(first);
+        let mut last = first;
+
+        while *length < self.max_token_length {
+            let wb = match self.peek_break(0)? {
+                Some(wb) => wb,
+                None => break,
+            };
+            // WB4
+            if wb.is_ignorable() {
+                self.pos += 1;
+                *length += 1;
+                continue;
+            }
+
+            let joined = if joins(last, wb) {
+                true
+            } else if can_join_over(last, wb) {
+                // WB6/WB7, WB7b/WB7c and WB11/WB12 need to look ahead the char after
+                // the middle one.
+                match self.next_significant(1)? {
+                    Some((index, next))
+                        if joins_over(last, wb, next)
+                            && *length + index < self.max_token_length =>
+                    {
+                        // consume the middle char, `next` is consumed below
+                        self.pos += index;
+                        *length += index;
+                        true
+                    }
+                    _ => false,
+                }
+            } else {
+                false
+            };
+            if !joined {
+                break;
+            }
+
+            // consume the char joined to the word
+            let wb = self.peek_break(0)?.unwrap();
+            self.pos += 1;
+            *length += 1;
+            kinds.add(wb);
+            last = wb;
+        }
+
+        Ok(kinds.token_type())
+    }
+
+    fn push_chars(&mut self, start: usize, end: usize) {
+        for i in start..end {
+            let c = self.chars[i];
+            let char_len = c.len_utf8();
+            let term_len = self.token.term.len();
+            self.token.term.resize(term_len + char_len, 0u8);
+            c.encode_utf8(&mut self.token.term[term_len..]);
+        }
+    }
+}
+
+impl TokenStream for StandardTokenizer {
+    fn next_token(&mut self) -> Result<bool> {
+        self.clear_token();
+        self.compact();
+
+        loop {
+            // skip the chars that can't start a token
+            let first = loop {
+                match self.peek_break(0)? {
+                    Some(wb) if wb.starts_token() => break wb,
+                    Some(_) => {
+                        self.pos += 1;
+                        self.compact();
+                    }
+                    None => {
+                        self.final_offset = self.offset + self.pos;
+                        return Ok(false);
+                    }
+                }
+            };
+
+            let start = self.pos;
+            self.pos += 1;
+            let mut length = 1;
+            let token_type = match first {
+                WordBreak::Ideographic => {
+                    self.consume_ignorable(&mut length)?;
+                    Some(TOKEN_TYPE_IDEOGRAPHIC)
+                }
+                WordBreak::Hiragana => {
+                    self.consume_ignorable(&mut length)?;
+                    Some(TOKEN_TYPE_HIRAGANA)
+                }
+                WordBreak::SoutheastAsian => {
+                    while length < self.max_token_length {
+                        match self.peek_break(0)? {
+                            Some(wb) if wb == WordBreak::SoutheastAsian || wb.is_ignorable() => {
+                                self.pos += 1;
+                                length += 1;
+                            }
+                            _ => break,
+                        }
+                    }
+                    Some(TOKEN_TYPE_SOUTHEAST_ASIAN)
+                }
+                _ => self.scan_word(first, &mut length)?,
+            };
+
+            if let Some(token_type) = token_type {
+                let end = self.pos;
+                self.push_chars(start, end);
+                self.token.token_type = token_type;
+                self.token
+                    .set_offset(self.offset + start, self.offset + end)?;
+                self.final_offset = self.offset + end;
+                return Ok(true);
+            }
+        }
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.end_token();
+        // set final offset
+        let final_offset = self.final_offset;
+        self.token.set_offset(final_offset, final_offset)
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.chars.clear();
+        self.pos = 0;
+        self.offset = 0;
+        self.final_offset = 0;
+        self.exhausted = false;
+        self.io_buffer.reset();
+        Ok(())
+    }
+
+    fn token(&self) -> &Token {
+        &self.token
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        &mut self.token
+    }
+}
+
+/// The kinds of chars a word contains, used to decide the token type.
+#[derive(Default)]
+struct WordKinds {
+    letter: bool,
+    numeric: bool,
+    katakana: bool,
+    hangul: bool,
+}
+
+impl WordKinds {
+    fn add(&mut self, wb: WordBreak) {
+        match wb {
+            WordBreak::ALetter | WordBreak::HebrewLetter => self.letter = true,
+            WordBreak::Hangul => self.hangul = true,
+            WordBreak::Numeric => self.numeric = true,
+            WordBreak::Katakana => self.katakana = true,
+            _ => {}
+        }
+    }
+
+    fn token_type(&self) -> Option<&'static str> {
+        match (self.letter, self.numeric, self.katakana, self.hangul) {
+            (false, false, false, false) => None,
+            (false, true, false, false) => Some(TOKEN_TYPE_NUM),
+            (false, false, true, false) => Some(TOKEN_TYPE_KATAKANA),
+            (false, false, false, true) => Some(TOKEN_TYPE_HANGUL),
+            _ => Some(TOKEN_TYPE_ALPHANUM),
+        }
+    }
+}
+
+/// The Word_Break property values of UAX#29, with the extra classes that
+/// `StandardTokenizer` treats specially.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum WordBreak {
+    Other,
+    Newline,
+    Extend,
+    ZWJ,
+    Format,
+    Katakana,
+    HebrewLetter,
+    ALetter,
+    SingleQuote,
+    DoubleQuote,
+    MidNumLet,
+    MidLetter,
+    MidNum,
+    Numeric,
+    ExtendNumLet,
+    WSegSpace,
+    // ALetter in Hangul scripts
+    Hangul,
+    // the following are `Other` in UAX#29
+    Ideographic,
+    Hiragana,
+    SoutheastAsian,
+}
+
+impl WordBreak {
+    fn is_ignorable(self) -> bool {
+        match self {
+            WordBreak::Extend | WordBreak::Format | WordBreak::ZWJ => true,
+            _ => false,
+        }
+    }
+
+    fn is_ah_letter(self) -> bool {
+        match self {
+            WordBreak::ALetter | WordBreak::HebrewLetter | WordBreak::Hangul => true,
+            _ => false,
+        }
+    }
+
+    fn is_mid_letter(self) -> bool {
+        match self {
+            WordBreak::MidLetter | WordBreak::MidNumLet | WordBreak::SingleQuote => true,
+            _ => false,
+        }
+    }
+
+    fn is_mid_num(self) -> bool {
+        match self {
+            WordBreak::MidNum | WordBreak::MidNumLet | WordBreak::SingleQuote => true,
+            _ => false,
+        }
+    }
+
+    fn starts_token(self) -> bool {
+        match self {
+            WordBreak::ALetter
+            | WordBreak::HebrewLetter
+            | WordBreak::Hangul
+            | WordBreak::Numeric
+            | WordBreak::Katakana
+            | WordBreak::ExtendNumLet
+            | WordBreak::Ideographic
+            | WordBreak::Hiragana
+            | WordBreak::SoutheastAsian => true,
+            _ => false,
+        }
+    }
+}
+
+/// Whether there is no word boundary between `last` and `next`.
+fn joins(last: WordBreak, next: WordBreak) -> bool {
+    match (last, next) {
+        // WB5, WB9, WB10, WB8
+        (l, n) if l.is_ah_letter() && n.is_ah_letter() => true,
+        (l, WordBreak::Numeric) if l.is_ah_letter() => true,
+        (WordBreak::Numeric, n) if n.is_ah_letter() => true,
+        (WordBreak::Numeric, WordBreak::Numeric) => true,
+        // WB7a
+        (WordBreak::HebrewLetter, WordBreak::SingleQuote) => true,
+        // WB13
+        (WordBreak::Katakana, WordBreak::Katakana) => true,
+        // WB13a
+        (l, WordBreak::ExtendNumLet) => {
+            l.is_ah_letter()
+                || l == WordBreak::Numeric
+                || l == WordBreak::Katakana
+                || l == WordBreak::ExtendNumLet
+        }
+        // WB13b
+        (WordBreak::ExtendNumLet, n) => {
+            n.is_ah_letter() || n == WordBreak::Numeric || n == WordBreak::Katakana
+        }
+        _ => false,
+    }
+}
+
+/// Whether `mid` may be inside a word when it is between `last` and some char.
+fn can_join_over(last: WordBreak, mid: WordBreak) -> bool {
+    (last.is_ah_letter() && mid.is_mid_letter())
+        || (last == WordBreak::HebrewLetter && mid == WordBreak::DoubleQuote)
+        || (last == WordBreak::Numeric && mid.is_mid_num())
+}
+
+/// Whether there is no word boundary around `mid` in `last mid next`.
+fn joins_over(last: WordBreak, mid: WordBreak, next: WordBreak) -> bool {
+    // WB6, WB7
+    (last.is_ah_letter() && mid.is_mid_letter() && next.is_ah_letter())
+        // WB7b, WB7c
+        || (last == WordBreak::HebrewLetter
+            && mid == WordBreak::DoubleQuote
+            && next == WordBreak::HebrewLetter)
+        // WB11, WB12
+        || (last == WordBreak::Numeric && mid.is_mid_num() && next == WordBreak::Numeric)
+}
+
+fn in_ranges(c: char, ranges: &[(u32, u32)]) -> bool {
+    let c = c as u32;
+    ranges
+        .binary_search_by(|&(start, end)| {
+            if end < c {
+                ::std::cmp::Ordering::Less
+            } else if start > c {
+                ::std::cmp::Ordering::Greater
+            } else {
+                ::std::cmp::Ordering::Equal
+            }
+        })
+        .is_ok()
+}
+
+const EXTEND_RANGES: &[(u32, u32)] = &[
+    (0x0300, 0x036F),
+    (0x0483, 0x0489),
+    (0x0591, 0x05BD),
+    (0x05BF, 0x05BF),
+    (0x05C1, 0x05C2),
+    (0x05C4, 0x05C5),
+    (0x05C7, 0x05C7),
+    (0x0610, 0x061A),
+    (0x064B, 0x065F),
+    (0x0670, 0x0670),
+    (0x06D6, 0x06DC),
+    (0x06DF, 0x06E4),
+    (0x06E7, 0x06E8),
+    (0x06EA, 0x06ED),
+    (0x0711, 0x0711),
+    (0x0730, 0x074A),
+    (0x07A6, 0x07B0),
+    (0x07EB, 0x07F3),
+    (0x0816, 0x082D),
+    (0x0859, 0x085B),
+    (0x08D3, 0x08E1),
+    (0x08E3, 0x0903),
+    (0x093A, 0x093C),
+    (0x093E, 0x094F),
+    (0x0951, 0x0957),
+    (0x0962, 0x0963),
+    (0x0981, 0x0983),
+    (0x09BC, 0x09BC),
+    (0x09BE, 0x09CD),
+    (0x09D7, 0x09D7),
+    (0x09E2, 0x09E3),
+    (0x0A01, 0x0A03),
+    (0x0A3C, 0x0A51),
+    (0x0A70, 0x0A71),
+    (0x0A75, 0x0A75),
+    (0x0A81, 0x0A83),
+    (0x0ABC, 0x0ABC),
+    (0x0ABE, 0x0ACD),
+    (0x0AE2, 0x0AE3),
+    (0x0B01, 0x0B03),
+    (0x0B3C, 0x0B3C),
+    (0x0B3E, 0x0B57),
+    (0x0B62, 0x0B63),
+    (0x0B82, 0x0B82),
+    (0x0BBE, 0x0BCD),
+    (0x0BD7, 0x0BD7),
+    (0x0C00, 0x0C04),
+    (0x0C3E, 0x0C56),
+    (0x0C62, 0x0C63),
+    (0x0C81, 0x0C83),
+    (0x0CBC, 0x0CBC),
+    (0x0CBE, 0x0CD6),
+    (0x0CE2, 0x0CE3),
+    (0x0D00, 0x0D03),
+    (0x0D3B, 0x0D3C),
+    (0x0D3E, 0x0D4D),
+    (0x0D57, 0x0D57),
+    (0x0D62, 0x0D63),
+    (0x0D82, 0x0D83),
+    (0x0DCA, 0x0DDF),
+    (0x0DF2, 0x0DF3),
+    (0x0F18, 0x0F19),
+    (0x0F35, 0x0F35),
+    (0x0F37, 0x0F37),
+    (0x0F39, 0x0F39),
+    (0x0F3E, 0x0F3F),
+    (0x0F71, 0x0F84),
+    (0x0F86, 0x0F87),
+    (0x0F8D, 0x0FBC),
+    (0x0FC6, 0x0FC6),
+    (0x1AB0, 0x1AFF),
+    (0x1DC0, 0x1DFF),
+    (0x200C, 0x200C),
+    (0x20D0, 0x20FF),
+    (0x302A, 0x302F),
+    (0x3099, 0x309A),
+    (0xFE00, 0xFE0F),
+    (0xFE20, 0xFE2F),
+    (0xFF9E, 0xFF9F),
+    (0x1F3FB, 0x1F3FF),
+    (0xE0020, 0xE007F),
+    (0xE0100, 0xE01EF),
+];
+
+const FORMAT_RANGES: &[(u32, u32)] = &[
+    (0x00AD, 0x00AD),
+    (0x0600, 0x0605),
+    (0x061C, 0x061C),
+    (0x06DD, 0x06DD),
+    (0x070F, 0x070F),
+    (0x08E2, 0x08E2),
+    (0x180E, 0x180E),
+    (0x200E, 0x200F),
+    (0x202A, 0x202E),
+    (0x2060, 0x2064),
+    (0x2066, 0x206F),
+    (0xFEFF, 0xFEFF),
+    (0xFFF9, 0xFFFB),
+    (0x110BD, 0x110BD),
+    (0x1BCA0, 0x1BCA3),
+    (0x1D173, 0x1D17A),
+    (0xE0001, 0xE0001),
+];
+
+const KATAKANA_RANGES: &[(u32, u32)] = &[
+    (0x3031, 0x3035),
+    (0x309B, 0x309C),
+    (0x30A0, 0x30FA),
+    (0x30FC, 0x30FF),
+    (0x31F0, 0x31FF),
+    (0x32D0, 0x32FE),
+    (0x3300, 0x3357),
+    (0xFF66, 0xFF9D),
+    (0x1B000, 0x1B000),
+];
+
+const HEBREW_LETTER_RANGES: &[(u32, u32)] = &[
+    (0x05D0, 0x05EA),
+    (0x05EF, 0x05F2),
+    (0xFB1D, 0xFB1D),
+    (0xFB1F, 0xFB28),
+    (0xFB2A, 0xFB4F),
+];
+
+const HANGUL_RANGES: &[(u32, u32)] = &[
+    (0x1100, 0x11FF),
+    (0x3131, 0x318E),
+    (0xA960, 0xA97F),
+    (0xAC00, 0xD7A3),
+    (0xD7B0, 0xD7FF),
+    (0xFFA0, 0xFFDC),
+];
+
+const IDEOGRAPHIC_RANGES: &[(u32, u32)] = &[
+    (0x2E80, 0x2FDF),
+    (0x3005, 0x3007),
+    (0x3021, 0x3029),
+    (0x3038, 0x303B),
+    (0x3400, 0x4DBF),
+    (0x4E00, 0x9FFF),
+    (0xF900, 0xFAFF),
+    (0x20000, 0x2FFFF),
+    (0x30000, 0x3134F),
+];
+
+const HIRAGANA_RANGES: &[(u32, u32)] = &[(0x3041, 0x3096), (0x309D, 0x309F), (0x1B001, 0x1B11F)];
+
+const SOUTHEAST_ASIAN_RANGES: &[(u32, u32)] = &[
+    (0x0E00, 0x0E4F),
+    (0x0E5A, 0x0ECF),
+    (0x0EDA, 0x0EFF),
+    (0x1000, 0x103F),
+    (0x104A, 0x108F),
+    (0x109A, 0x109F),
+    (0x1780, 0x17DF),
+    (0x17EA, 0x17FF),
+    (0x1950, 0x19DF),
+    (0x19E0, 0x19FF),
+    (0x1A20, 0x1AAF),
+    (0xA9E0, 0xA9FF),
+    (0xAA60, 0xAADF),
+];
+
+fn word_break(c: char) -> WordBreak {
+    match c {
+        '\r' | '\n' | '\u{0B}' | '\u{0C}' | '\u{85}' | '\u{2028}' | '\u{2029}' => {
+            return WordBreak::Newline;
+        }
+        '\u{200D}' => return WordBreak::ZWJ,
+        '\'' => return WordBreak::SingleQuote,
+        '"' => return WordBreak::DoubleQuote,
+        '.' | '\u{2018}' | '\u{2019}' | '\u{2024}' | '\u{FE52}' | '\u{FF07}' | '\u{FF0E}' => {
+            return WordBreak::MidNumLet;
+        }
+        ':' | '\u{B7}' | '\u{387}' | '\u{55F}' | '\u{5F4}' | '\u{2027}' | '\u{FE13}'
+        | '\u{FE55}' | '\u{FF1A}' => return WordBreak::MidLetter,
+        ',' | ';' | '\u{37E}' | '\u{589}' | '\u{60C}' | '\u{60D}' | '\u{66C}' | '\u{7F8}'
+        | '\u{2044}' | '\u{FE10}' | '\u{FE14}' | '\u{FE50}' | '\u{FE54}' | '\u{FF0C}'
+        | '\u{FF1B}' => return WordBreak::MidNum,
+        '_'
+        | '\u{202F}'
+        | '\u{203F}'
+        | '\u{2040}'
+        | '\u{2054}'
+        | '\u{FE33}'
+        | '\u{FE34}'
+        | '\u{FE4D}'..='\u{FE4F}'
+        | '\u{FF3F}' => return WordBreak::ExtendNumLet,
+        ' '
+        | '\u{1680}'
+        | '\u{2000}'..='\u{2006}'
+        | '\u{2008}'..='\u{200A}'
+        | '\u{205F}'
+        | '\u{3000}' => return WordBreak::WSegSpace,
+        _ => {}
+    }
+
+    if c.is_ascii() {
+        return if c.is_ascii_alphabetic() {
+            WordBreak::ALetter
+        } else if c.is_ascii_digit() {
+            WordBreak::Numeric
+        } else {
+            WordBreak::Other
+        };
+    }
+
+    if in_ranges(c, EXTEND_RANGES) {
+        WordBreak::Extend
+    } else if in_ranges(c, FORMAT_RANGES) {
+        WordBreak::Format
+    } else if in_ranges(c, IDEOGRAPHIC_RANGES) {
+        WordBreak::Ideographic
+    } else if in_ranges(c, HIRAGANA_RANGES) {
+        WordBreak::Hiragana
+    } else if in_ranges(c, KATAKANA_RANGES) {
+        WordBreak::Katakana
+    } else if in_ranges(c, HEBREW_LETTER_RANGES) {
+        WordBreak::HebrewLetter
+    } else if in_ranges(c, HANGUL_RANGES) {
+        WordBreak::Hangul
+    } else if c.is_numeric() {
+        WordBreak::Numeric
+    } else if in_ranges(c, SOUTHEAST_ASIAN_RANGES) {
+        WordBreak::SoutheastAsian
+    } else if c.is_alphabetic() {
+        WordBreak::ALetter
+    } else {
+        WordBreak::Other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn tokenize(text: &str) -> Vec<(String, usize, usize, &'static str)> {
+        let mut tokenizer = StandardTokenizer::new(Box::new(Cursor::new(text.as_bytes().to_vec())));
+        tokenizer.reset().unwrap();
+        let mut tokens = vec![];
+        while tokenizer.next_token().unwrap() {
+            let token = tokenizer.token();
+            tokens.push((
+                String::from_utf8(token.term.clone()).unwrap(),
+                token.start_offset,
+                token.end_offset,
+                token.token_type,
+            ));
+        }
+        tokenizer.end().unwrap();
+        assert_eq!(tokenizer.token().end_offset, text.chars().count());
+        tokens
+    }
+
+    fn terms(text: &str) -> Vec<String> {
+        tokenize(text).into_iter().map(|t| t.0).collect()
+    }
+
+    #[test]
+    fn test_standard_tokenizer_punctuation() {
+        let tokens = tokenize("The quick (\"brown\") fox can't jump 32.3 feet, right? dog.");
+        let expected = [
+            ("The", 0, 3, TOKEN_TYPE_ALPHANUM),
+            ("quick", 4, 9, TOKEN_TYPE_ALPHANUM),
+            ("brown", 12, 17, TOKEN_TYPE_ALPHANUM),
+            ("fox", 20, 23, TOKEN_TYPE_ALPHANUM),
+            ("can't", 24, 29, TOKEN_TYPE_ALPHANUM),
+            ("jump", 30, 34, TOKEN_TYPE_ALPHANUM),
+            ("32.3", 35, 39, TOKEN_TYPE_NUM),
+            ("feet", 40, 44, TOKEN_TYPE_ALPHANUM),
+            ("right", 46, 51, TOKEN_TYPE_ALPHANUM),
+            ("dog", 53, 56, TOKEN_TYPE_ALPHANUM),
+        ];
+        assert_eq!(tokens.len(), expected.len());
+        for (token, expected) in tokens.iter().zip(expected.iter()) {
+            assert_eq!(token.0, expected.0);
+            assert_eq!(token.1, expected.1);
+            assert_eq!(token.2, expected.2);
+            assert_eq!(token.3, expected.3);
+        }
+    }
+
+    #[test]
+    fn test_standard_tokenizer_word_rules() {
+        assert_eq!(
+            terms("1,000.5 a.b.c U.S.A. e-mail"),
+            vec!["1,000.5", "a.b.c", "U.S.A", "e", "mail"]
+        );
+        assert_eq!(
+            terms("foo_bar __ 3com wi-fi"),
+            vec!["foo_bar", "3com", "wi", "fi"]
+        );
+        assert_eq!(terms("naïve café"), vec!["naïve", "café"]);
+        assert_eq!(terms("e\u{301}tude"), vec!["e\u{301}tude"]);
+    }
+
+    #[test]
+    fn test_standard_tokenizer_cjk() {
+        let tokens = tokenize("我是中国人 カタカナ ひらがな 한국어");
+        let terms: Vec<&str> = tokens.iter().map(|t| t.0.as_str()).collect();
+        assert_eq!(
+            terms,
+            vec![
+                "我",
+                "是",
+                "中",
+                "国",
+                "人",
+                "カタカナ",
+                "ひ",
+                "ら",
+                "が",
+                "な",
+                "한국어"
+            ]
+        );
+        assert_eq!(tokens[1].1, 1);
+        assert_eq!(tokens[1].2, 2);
+        assert_eq!(tokens[0].3, TOKEN_TYPE_IDEOGRAPHIC);
+        assert_eq!(tokens[5].3, TOKEN_TYPE_KATAKANA);
+        assert_eq!((tokens[5].1, tokens[5].2), (6, 10));
+        assert_eq!(tokens[6].3, TOKEN_TYPE_HIRAGANA);
+        assert_eq!(tokens[10].3, TOKEN_TYPE_HANGUL);
+    }
+
+    #[test]
+    fn test_standard_tokenizer_long_input() {
+        let text = "word. ".repeat(2000);
+        let tokens = tokenize(&text);
+        assert_eq!(tokens.len(), 2000);
+        for (i, token) in tokens.iter().enumerate() {
+            assert_eq!(token.0, "word");
+            assert_eq!(token.1, i * 6);
+            assert_eq!(token.2, i * 6 + 4);
+        }
+    }
+
+    #[test]
+    fn test_standard_tokenizer_max_token_length() {
+        let mut tokenizer = StandardTokenizer::new(Box::new(Cursor::new(b"abcdefgh ij".to_vec())));
+        tokenizer.set_max_token_length(3);
+        tokenizer.reset().unwrap();
+        let mut terms = vec![];
+        while tokenizer.next_token().unwrap() {
+            terms.push(String::from_utf8(tokenizer.token().term.clone()).unwrap());
+        }
+        assert_eq!(terms, vec!["abc", "def", "gh", "ij"]);
+    }
+}
@@ -0,0 +1,561 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{Analyzer, Token, TokenStream};
+use core::util::fst::{
+    ByteSequenceOutput, ByteSequenceOutputFactory, FstBuilder, InputType, Output, OutputFactory,
+    FST,
+};
+use core::util::{to_ints_ref, IntsRefBuilder};
+
+use error::{ErrorKind, Result};
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::io::{BufRead, BufReader, Read};
+use std::sync::Arc;
+
+/// Token type for the tokens injected by `SynonymFilter`.
+pub const TOKEN_TYPE_SYNONYM: &str = "SYNONYM";
+
+/// Separates the words of a multi-word input or output in a `SynonymMap`.
+pub const WORD_SEPARATOR: u8 = 0;
+
+/// A map of synonyms, keys and values are phrases. The inputs are stored in a
+/// `FST`, whose outputs are the ordinals of the output phrases.
+pub struct SynonymMap {
+    fst: Option<FST<ByteSequenceOutputFactory>>,
+    // output phrases, the words are separated by `WORD_SEPARATOR`
+    words: Vec<Vec<u8>>,
+    // max number of words of an input
+    max_horizontal_context: usize,
+}
+
+impl fmt::Debug for SynonymMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SynonymMap")
+            .field("words", &self.words.len())
+            .field("max_horizontal_context", &self.max_horizontal_context)
+            .finish()
+    }
+}
+
+impl SynonymMap {
+    pub fn max_horizontal_context(&self) -> usize {
+        self.max_horizontal_context
+    }
+}
+
+#[derive(Default)]
+struct MapEntry {
+    include_orig: bool,
+    ords: Vec<usize>,
+}
+
+/// Builds a `SynonymMap`, either rule by rule with `add`, or from a file in the
+/// Solr synonyms format with `parse_solr`.
+#[derive(Default)]
+pub struct SynonymMapBuilder {
+    entries: HashMap<Vec<u8>, MapEntry>,
+    words: Vec<Vec<u8>>,
+    word_ords: HashMap<Vec<u8>, usize>,
+    max_horizontal_context: usize,
+}
+
+impl SynonymMapBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Joins the words of `text` with `WORD_SEPARATOR`, the words are separated by
+    /// whitespaces.
+    pub fn join(text: &str) -> Vec<u8> {
+        let mut phrase = Vec::with_capacity(text.len());
+        for word in text.split_whitespace() {
+            if !phrase.is_empty() {
+                phrase.push(WORD_SEPARATOR);
+            }
+            phrase.extend_from_slice(word.as_bytes());
+        }
+        phrase
+    }
+
+    /// Analyzes `text` with `analyzer` and joins the terms with `WORD_SEPARATOR`.
+    pub fn analyze(analyzer: &dyn Analyzer, text: &str) -> Result<Vec<u8>> {
+        let mut stream = analyzer.token_stream_for_str("", text)?;
+        let mut phrase = Vec::new();
+        stream.reset()?;
+        let mut first = true;
+        while stream.next_token()? {
+            let token = stream.token();
+            if token.term.is_empty() {
+                bail!(ErrorKind::IllegalArgument(format!(
+                    "term: '{}' analyzed to a zero-length token",
+                    text
+                )));
+            }
+            if token.position != 1 {
+                bail!(ErrorKind::IllegalArgument(format!(
+                    "term: '{}' analyzed to a token with position increment != 1",
+                    text
+                )));
+            }
+            if !first {
+                phrase.push(WORD_SEPARATOR);
+            }
+            first = false;
+            phrase.extend_from_slice(&token.term);
+        }
+        stream.end()?;
+        if phrase.is_empty() {
+            bail!(ErrorKind::IllegalArgument(format!(
+                "term: '{}' was completely eliminated by analyzer",
+                text
+            )));
+        }
+        Ok(phrase)
+    }
+
+    /// Adds a mapping from `input` to `output`, both are phrases joined with
+    /// `WORD_SEPARATOR`. If `include_orig` is true the input tokens are kept in
+    /// the token stream.
+    pub fn add(&mut self, input: Vec<u8>, output: Vec<u8>, include_orig: bool) -> Result<()> {
+        if input.is_empty() || output.is_empty() {
+            bail!(ErrorKind::IllegalArgument(
+                "synonym input and output must not be empty".into()
+            ));
+        }
+        let num_words = input.split(|&b| b == WORD_SEPARATOR).count();
+        self.max_horizontal_context = self.max_horizontal_context.max(num_words);
+
+        let ord = match self.word_ords.get(&output) {
+            Some(&ord) => ord,
+            None => {
+                let ord = self.words.len();
+                self.word_ords.insert(output.clone(), ord);
+                self.words.push(output);
+                ord
+            }
+        };
+        let entry = self.entries.entry(input).or_insert_with(MapEntry::default);
+        if !entry.ords.contains(&ord) {
+            entry.ords.push(ord);
+        }
+        entry.include_orig |= include_orig;
+        Ok(())
+    }
+
+    /// Parses synonym rules in the Solr format, one rule per line:
+    ///
+    /// ```text
+    /// # explicit mappings replace the words on the left with the ones on the right
+    /// i-pod, i pod => ipod
+    /// # equivalent synonyms, with `expand` every word is mapped to all the others,
+    /// # otherwise every word is mapped to the first one
+    /// ipod, i-pod, i pod
+    /// ```
+    ///
+    /// The words are analyzed with `analyzer`, which should match the analysis
+    /// chain preceding the `SynonymFilter`.
+    pub fn parse_solr<R: Read>(
+        &mut self,
+        reader: R,
+        analyzer: &dyn Analyzer,
+        expand: bool,
+    ) -> Result<()> {
+        for (i, line) in BufReader::new(reader).lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let sides: Vec<&str> = line.split("=>").collect();
+            let analyze_all = |side: &str| -> Result<Vec<Vec<u8>>> {
+                side.split(',')
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())
+                    .map(|s| Self::analyze(analyzer, s))
+                    .collect()
+            };
+            match sides.len() {
+                1 => {
+                    let words = analyze_all(sides[0])?;
+                    if expand {
+                        for input in &words {
+                            for output in &words {
+                                self.add(input.clone(), output.clone(), false)?;
+                            }
+                        }
+                    } else if let Some(first) = words.first() {
+                        for input in &words {
+                            self.add(input.clone(), first.clone(), false)?;
+                        }
+                    }
+                }
+                2 => {
+                    let inputs = analyze_all(sides[0])?;
+                    let outputs = analyze_all(sides[1])?;
+                    for input in &inputs {
+                        for output in &outputs {
+                            self.add(input.clone(), output.clone(), false)?;
+                        }
+                    }
+                }
+                _ => bail!(ErrorKind::IllegalArgument(format!(
+                    "more than one explicit mapping specified at line {}: '{}'",
+                    i + 1,
+                    line
+                ))),
+            }
+        }
+        Ok(())
+    }
+
+    pub fn build(self) -> Result<SynonymMap> {
+        let mut entries: Vec<(Vec<u8>, MapEntry)> = self.entries.into_iter().collect();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut builder = FstBuilder::new(InputType::Byte1, ByteSequenceOutputFactory::new());
+        builder.init();
+        let mut scratch_ints_ref = IntsRefBuilder::new();
+        for (input, entry) in entries {
+            // the header encodes the number of ords and whether to keep the original
+            let mut output = Vec::new();
+            let header = (entry.ords.len() << 1) | if entry.include_orig { 0 } else { 1 };
+            write_vint(&mut output, header);
+            for ord in entry.ords {
+                write_vint(&mut output, ord);
+            }
+            builder.add(
+                to_ints_ref(&input, &mut scratch_ints_ref),
+                ByteSequenceOutput::new(output),
+            )?;
+        }
+
+        Ok(SynonymMap {
+            fst: builder.finish()?,
+            words: self.words,
+            max_horizontal_context: self.max_horizontal_context,
+        })
+    }
+}
+
+fn write_vint(out: &mut Vec<u8>, mut v: usize) {
+    while v >= 0x80 {
+        out.push((v & 0x7f) as u8 | 0x80);
+        v >>= 7;
+    }
+    out.push(v as u8);
+}
+
+fn read_vint(bytes: &[u8], pos: &mut usize) -> usize {
+    let mut v = 0usize;
+    let mut shift = 0;
+    loop {
+        let b = bytes[*pos];
+        *pos += 1;
+        v |= ((b & 0x7f) as usize) << shift;
+        if b & 0x80 == 0 {
+            return v;
+        }
+        shift += 7;
+    }
+}
+
+/// Matches single or multi word synonyms in a token stream.
+///
+/// The longest input matching a rule of the `SynonymMap` is replaced by the
+/// outputs of the rule, or the outputs are injected along the input if the rule
+/// keeps the original. The `n`-th word of an output is stacked at the position of
+/// the `n`-th input token with a position increment of 0, and words of an output
+/// longer than the input follow at the next positions. Since a token has no
+/// position length, a multi-word synonym is flattened onto the input positions.
+#[derive(Debug)]
+pub struct SynonymFilter {
+    input: Box<dyn TokenStream>,
+    synonyms: Arc<SynonymMap>,
+    // input tokens read ahead while matching
+    lookahead: VecDeque<Token>,
+    // tokens ready to be emitted
+    pending: VecDeque<Token>,
+    exhausted: bool,
+}
+
+impl SynonymFilter {
+    pub fn new(input: Box<dyn TokenStream>, synonyms: Arc<SynonymMap>) -> Self {
+        SynonymFilter {
+            input,
+            synonyms,
+            lookahead: VecDeque::new(),
+            pending: VecDeque::new(),
+            exhausted: false,
+        }
+    }
+
+    // make sure the lookahead holds the `i`th token, returns false if the input
+    // is exhausted before
+    fn fill_lookahead(&mut self, i: usize) -> Result<bool> {
+        while self.lookahead.len() <= i {
+            if self.exhausted || !self.input.next_token()? {
+                self.exhausted = true;
+                return Ok(false);
+            }
+            self.lookahead.push_back(self.input.token().clone());
+        }
+        Ok(true)
+    }
+
+    /// Finds the longest input at the start of the lookahead, returns the number
+    /// of tokens it spans and its output.
+    fn longest_match(&mut self) -> Result<Option<(usize, Vec<u8>)>> {
+        let synonyms = Arc::clone(&self.synonyms);
+        let fst = match synonyms.fst {
+            Some(ref fst) => fst,
+            None => return Ok(None),
+        };
+        let mut bytes_reader = fst.bytes_reader();
+        let mut arc = fst.root_arc();
+        let mut output = fst.outputs().empty();
+        let mut best = None;
+
+        'tokens: for i in 0..synonyms.max_horizontal_context {
+            if !self.fill_lookahead(i)? {
+                break;
+            }
+            let token = &self.lookahead[i];
+            if i > 0 && token.position == 0 {
+                break;
+            }
+            let separator = if i > 0 { Some(WORD_SEPARATOR) } else { None };
+            for &b in separator.iter().chain(token.term.iter()) {
+                match fst.find_target_arc(i32::from(b), &arc, &mut bytes_reader)? {
+                    Some(next) => arc = next,
+                    None => break 'tokens,
+                }
+                if let Some(ref out) = arc.output {
+                    if !out.is_empty() {
+                        output.concat(out);
+                    }
+                }
+            }
+            if arc.is_final() {
+                let out = match arc.next_final_output {
+                    Some(ref out) if !out.is_empty() => output.cat(out),
+                    _ => output.clone(),
+                };
+                best = Some((i + 1, out.inner().to_vec()));
+            }
+        }
+        Ok(best)
+    }
+
+    fn add_outputs(&mut self, matched: Vec<Token>, output: &[u8]) {
+        let mut pos = 0;
+        let header = read_vint(output, &mut pos);
+        let keep_orig = header & 1 == 0;
+        let synonyms = Arc::clone(&self.synonyms);
+        let phrases: Vec<Vec<&[u8]>> = (0..header >> 1)
+            .map(|_| {
+                let ord = read_vint(output, &mut pos);
+                synonyms.words[ord]
+                    .split(|&b| b == WORD_SEPARATOR)
+                    .collect()
+            })
+            .collect();
+
+        let len = matched.len();
+        let max_len = phrases.iter().map(Vec::len).fold(len, usize::max);
+        for p in 0..max_len {
+            let keep = p < len && keep_orig;
+            if keep {
+                self.pending.push_back(matched[p].clone());
+            }
+            let mut first_at_position = !keep;
+            for phrase in &phrases {
+                if p >= phrase.len() {
+                    continue;
+                }
+                let mut token = matched[p.min(len - 1)].clone();
+                token.term.clear();
+                token.term.extend_from_slice(phrase[p]);
+                token.token_type = TOKEN_TYPE_SYNONYM;
+                if p + 1 == phrase.len() {
+                    // the last word of an output spans the rest of the input
+                    token.end_offset = matched[len - 1].end_offset;
+                }
+                token.position = if !first_at_position {
+                    0
+                } else if p < len {
+                    matched[p].position
+                } else {
+                    1
+                };
+                first_at_position = false;
+                self.pending.push_back(token);
+            }
+        }
+    }
+}
+
+impl TokenStream for SynonymFilter {
+    fn next_token(&mut self) -> Result<bool> {
+        if self.pending.is_empty() {
+            match self.longest_match()? {
+                Some((len, output)) => {
+                    let matched: Vec<Token> = self.lookahead.drain(..len).collect();
+                    self.add_outputs(matched, &output);
+                }
+                None => {
+                    if !self.fill_lookahead(0)? {
+                        return Ok(false);
+                    }
+                    let token = self.lookahead.pop_front().unwrap();
+                    self.pending.push_back(token);
+                }
+            }
+        }
+
+        match self.pending.pop_front() {
+            Some(token) => {
+                *self.input.token_mut() = token;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.input.end()
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.lookahead.clear();
+        self.pending.clear();
+        self.exhausted = false;
+        self.input.reset()
+    }
+
+    fn token(&self) -> &Token {
+        self.input.token()
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        self.input.token_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::{LowerCaseFilter, WhitespaceAnalyzer, WhitespaceTokenizer};
+    use std::io::Cursor;
+
+    fn synonyms(rules: &str, expand: bool) -> Arc<SynonymMap> {
+        let mut builder = SynonymMapBuilder::new();
+        builder
+            .parse_solr(rules.as_bytes(), &WhitespaceAnalyzer, expand)
+            .unwrap();
+        Arc::new(builder.build().unwrap())
+    }
+
+    fn filter(text: &str, synonyms: Arc<SynonymMap>) -> Vec<(String, usize, usize, usize)> {
+        let tokenizer = WhitespaceTokenizer::new(Box::new(Cursor::new(text.as_bytes().to_vec())));
+        let stream = Box::new(LowerCaseFilter::new(Box::new(tokenizer)));
+        let mut stream = SynonymFilter::new(stream, synonyms);
+        let mut tokens = vec![];
+        stream.reset().unwrap();
+        while stream.next_token().unwrap() {
+            let token = stream.token();
+            tokens.push((
+                String::from_utf8(token.term.clone()).unwrap(),
+                token.position,
+                token.start_offset,
+                token.end_offset,
+            ));
+        }
+        stream.end().unwrap();
+        tokens
+    }
+
+    #[test]
+    fn test_synonym_filter_expand() {
+        let map = synonyms("# comment\nfast, quick\n", true);
+        assert_eq!(
+            filter("a Quick fox", map),
+            vec![
+                ("a".to_string(), 1, 0, 1),
+                ("fast".to_string(), 1, 2, 7),
+                ("quick".to_string(), 0, 2, 7),
+                ("fox".to_string(), 1, 8, 11),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_synonym_filter_multi_words() {
+        let map = synonyms(
+            "usa, united states => usa, united states of america\n",
+            false,
+        );
+        assert_eq!(
+            filter("the united states army", map.clone()),
+            vec![
+                ("the".to_string(), 1, 0, 3),
+                ("usa".to_string(), 1, 4, 17),
+                ("united".to_string(), 0, 4, 10),
+                ("states".to_string(), 1, 11, 17),
+                ("of".to_string(), 1, 11, 17),
+                ("america".to_string(), 1, 11, 17),
+                ("army".to_string(), 1, 18, 22),
+            ]
+        );
+        assert_eq!(
+            filter("united usa", map),
+            vec![
+                ("united".to_string(), 1, 0, 6),
+                ("usa".to_string(), 1, 7, 10),
+                ("united".to_string(), 0, 7, 10),
+                ("states".to_string(), 1, 7, 10),
+                ("of".to_string(), 1, 7, 10),
+                ("america".to_string(), 1, 7, 10),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_synonym_map_keep_original() {
+        let mut builder = SynonymMapBuilder::new();
+        builder
+            .add(
+                SynonymMapBuilder::join("wi fi"),
+                SynonymMapBuilder::join("wifi"),
+                true,
+            )
+            .unwrap();
+        let map = Arc::new(builder.build().unwrap());
+        assert_eq!(map.max_horizontal_context(), 2);
+        assert_eq!(
+            filter("wi fi wi", map),
+            vec![
+                ("wi".to_string(), 1, 0, 2),
+                ("wifi".to_string(), 0, 0, 5),
+                ("fi".to_string(), 1, 3, 5),
+                ("wi".to_string(), 1, 6, 8),
+            ]
+        );
+
+        assert!(SynonymMapBuilder::new()
+            .parse_solr(&b"a => b => c"[..], &WhitespaceAnalyzer, true)
+            .is_err());
+    }
+}
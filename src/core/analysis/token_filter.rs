@@ -0,0 +1,274 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::analysis::{Token, TokenStream};
+
+use error::Result;
+
+use std::collections::HashSet;
+use std::str;
+use std::sync::Arc;
+
+/// An unmodifiable set containing some common English words that are not usually
+/// useful for searching.
+pub const ENGLISH_STOP_WORDS: [&str; 33] = [
+    "a", "an", "and", "are", "as", "at", "be", "but", "by", "for", "if", "in", "into", "is", "it",
+    "no", "not", "of", "on", "or", "such", "that", "the", "their", "then", "there", "these",
+    "they", "this", "to", "was", "will", "with",
+];
+
+/// Builds a stop words set for `StopFilter` from the given words.
+pub fn stop_words_set(words: &[&str]) -> Arc<HashSet<Vec<u8>>> {
+    Arc::new(words.iter().map(|w| w.as_bytes().to_vec()).collect())
+}
+
+/// Advances `input` to the next token accepted by `accept`, the position increments
+/// of the skipped tokens are added to the accepted one, or to `skipped_positions`
+/// if the stream is exhausted.
+fn next_accepted_token<F>(
+    input: &mut dyn TokenStream,
+    skipped_positions: &mut usize,
+    mut accept: F,
+) -> Result<bool>
+where
+    F: FnMut(&Token) -> bool,
+{
+    *skipped_positions = 0;
+    while input.next_token()? {
+        if accept(input.token()) {
+            if *skipped_positions > 0 {
+                input.token_mut().position += *skipped_positions;
+            }
+            return Ok(true);
+        }
+        *skipped_positions += input.token().position;
+    }
+    Ok(false)
+}
+
+/// Normalizes token text to lower case.
+#[derive(Debug)]
+pub struct LowerCaseFilter {
+    input: Box<dyn TokenStream>,
+}
+
+impl LowerCaseFilter {
+    pub fn new(input: Box<dyn TokenStream>) -> Self {
+        LowerCaseFilter { input }
+    }
+}
+
+impl TokenStream for LowerCaseFilter {
+    fn next_token(&mut self) -> Result<bool> {
+        if !self.input.next_token()? {
+            return Ok(false);
+        }
+
+        let term = &mut self.input.token_mut().term;
+        if term.is_ascii() {
+            term.make_ascii_lowercase();
+        } else if let Ok(s) = str::from_utf8(term) {
+            let lower = s.to_lowercase();
+            term.clear();
+            term.extend_from_slice(lower.as_bytes());
+        }
+        Ok(true)
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.input.end()
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.input.reset()
+    }
+
+    fn token(&self) -> &Token {
+        self.input.token()
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        self.input.token_mut()
+    }
+}
+
+/// Removes stop words from a token stream.
+///
+/// The position increments of the removed tokens are added to the next token, so
+/// that phrase queries with slop keep matching the original positions.
+#[derive(Debug)]
+pub struct StopFilter {
+    input: Box<dyn TokenStream>,
+    stop_words: Arc<HashSet<Vec<u8>>>,
+    skipped_positions: usize,
+}
+
+impl StopFilter {
+    pub fn new(input: Box<dyn TokenStream>, stop_words: Arc<HashSet<Vec<u8>>>) -> Self {
+        StopFilter {
+            input,
+            stop_words,
+            skipped_positions: 0,
+        }
+    }
+}
+
+impl TokenStream for StopFilter {
+    fn next_token(&mut self) -> Result<bool> {
+        let stop_words = &self.stop_words;
+        next_accepted_token(self.input.as_mut(), &mut self.skipped_positions, |token| {
+            !stop_words.contains(token.term.as_slice())
+        })
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.input.end()?;
+        self.input.token_mut().position += self.skipped_positions;
+        Ok(())
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.skipped_positions = 0;
+        self.input.reset()
+    }
+
+    fn token(&self) -> &Token {
+        self.input.token()
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        self.input.token_mut()
+    }
+}
+
+/// Removes words that are too long or too short from the stream.
+///
+/// The length is counted by chars, both `min` and `max` are inclusive.
+#[derive(Debug)]
+pub struct LengthFilter {
+    input: Box<dyn TokenStream>,
+    min: usize,
+    max: usize,
+    skipped_positions: usize,
+}
+
+impl LengthFilter {
+    pub fn new(input: Box<dyn TokenStream>, min: usize, max: usize) -> Self {
+        assert!(
+            min <= max,
+            "minimum length must not be greater than maximum length"
+        );
+        LengthFilter {
+            input,
+            min,
+            max,
+            skipped_positions: 0,
+        }
+    }
+}
+
+impl TokenStream for LengthFilter {
+    fn next_token(&mut self) -> Result<bool> {
+        let (min, max) = (self.min, self.max);
+        next_accepted_token(self.input.as_mut(), &mut self.skipped_positions, |token| {
+            let len = match str::from_utf8(&token.term) {
+                Ok(s) => s.chars().count(),
+                Err(_) => token.term.len(),
+            };
+            len >= min && len <= max
+        })
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.input.end()?;
+        self.input.token_mut().position += self.skipped_positions;
+        Ok(())
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        self.skipped_positions = 0;
+        self.input.reset()
+    }
+
+    fn token(&self) -> &Token {
+        self.input.token()
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        self.input.token_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::analysis::WhitespaceTokenizer;
+    use std::io::Cursor;
+
+    fn whitespace(text: &str) -> Box<dyn TokenStream> {
+        Box::new(WhitespaceTokenizer::new(Box::new(Cursor::new(
+            text.as_bytes().to_vec(),
+        ))))
+    }
+
+    fn collect(stream: &mut dyn TokenStream) -> Vec<(String, usize)> {
+        let mut tokens = vec![];
+        stream.reset().unwrap();
+        while stream.next_token().unwrap() {
+            let token = stream.token();
+            tokens.push((
+                String::from_utf8(token.term.clone()).unwrap(),
+                token.position,
+            ));
+        }
+        tokens
+    }
+
+    #[test]
+    fn test_lower_case_filter() {
+        let mut stream = LowerCaseFilter::new(whitespace("The QUICK Ünïcode ΣΑΣ"));
+        let terms: Vec<String> = collect(&mut stream).into_iter().map(|t| t.0).collect();
+        assert_eq!(terms, vec!["the", "quick", "ünïcode", "σας"]);
+    }
+
+    #[test]
+    fn test_stop_filter() {
+        let stop_words = stop_words_set(&ENGLISH_STOP_WORDS);
+        let mut stream = StopFilter::new(whitespace("the quick fox is in the box of"), stop_words);
+        let tokens = collect(&mut stream);
+        assert_eq!(
+            tokens,
+            vec![
+                ("quick".to_string(), 2),
+                ("fox".to_string(), 1),
+                ("box".to_string(), 4),
+            ]
+        );
+        stream.end().unwrap();
+        assert_eq!(stream.token().position, 1);
+    }
+
+    #[test]
+    fn test_length_filter() {
+        let mut stream = LengthFilter::new(whitespace("a bb ccc dddd 中文字"), 2, 3);
+        let tokens = collect(&mut stream);
+        assert_eq!(
+            tokens,
+            vec![
+                ("bb".to_string(), 2),
+                ("ccc".to_string(), 1),
+                ("中文字".to_string(), 2),
+            ]
+        );
+    }
+}
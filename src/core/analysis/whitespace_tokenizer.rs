@@ -132,8 +132,9 @@ impl TokenStream for WhitespaceTokenizer {
 
     fn end(&mut self) -> Result<()> {
         self.end_token();
-
-        Ok(())
+        // set final offset
+        let final_offset = self.final_offset;
+        self.token.set_offset(final_offset, final_offset)
     }
 
     fn reset(&mut self) -> Result<()> {
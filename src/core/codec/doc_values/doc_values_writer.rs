@@ -904,7 +904,7 @@ impl DocValuesWriter for SortedDocValuesWriter {
         consumer: &mut W,
     ) -> Result<()> {
         let max_doc = state.segment_info.max_doc();
-        debug_assert!(self.pending.size() == max_doc as i64);
+        debug_assert!(self.pending.size() <= max_doc as i64);
 
         let value_count = self.hash.len();
 
@@ -1047,13 +1047,16 @@ impl<'a> Iterator for SortedOrdsIterator<'a> {
         if self.doc_upto >= self.max_doc {
             None
         } else {
-            self.doc_upto += 1;
-            let ord = if self.docs_with_field.get(self.doc_upto as usize).unwrap() {
+            // the bit set only grows up to the last doc with a value
+            let doc = self.doc_upto as usize;
+            let ord = if doc < self.docs_with_field.len() && self.docs_with_field.get(doc).unwrap()
+            {
                 let i = self.iter.next().unwrap();
                 self.ord_map[i as usize]
             } else {
                 -1
             };
+            self.doc_upto += 1;
             Some(Ok(Numeric::Int(ord)))
         }
     }
@@ -2338,3 +2341,63 @@ impl ReusableIterator for SortedDVIter {
         self.doc = -1;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    extern crate tempfile;
+
+    use core::doc::{Field, Fieldable, NumericDocValuesField, SORTED_DOC_VALUES_FIELD_TYPE};
+    use core::index::reader::IndexReader;
+    use core::index::writer::{IndexWriter, IndexWriterConfig};
+    use core::store::directory::FSDirectory;
+    use core::util::VariantValue;
+
+    use std::sync::Arc;
+
+    fn tag(i: i32) -> Option<String> {
+        if i % 3 == 0 {
+            None
+        } else {
+            Some(format!("t{}", (7 * i) % 10))
+        }
+    }
+
+    #[test]
+    fn test_sorted_ords_of_docs_without_value() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let directory = Arc::new(FSDirectory::with_path(temp_dir.path()).unwrap());
+        let writer = IndexWriter::new(directory, Arc::new(IndexWriterConfig::default())).unwrap();
+
+        // every third doc has no tag, the tags aren't in doc order
+        for i in 0..10 {
+            let mut doc: Vec<Box<dyn Fieldable>> =
+                vec![Box::new(NumericDocValuesField::new("id", i64::from(i)))];
+            if let Some(tag) = tag(i) {
+                doc.push(Box::new(Field::new(
+                    "tag".into(),
+                    SORTED_DOC_VALUES_FIELD_TYPE,
+                    Some(VariantValue::Binary(tag.into_bytes())),
+                    None,
+                )));
+            }
+            writer.add_document(doc).unwrap();
+        }
+        writer.commit().unwrap();
+
+        let reader = writer.get_reader(true, false).unwrap();
+        let mut docs = 0;
+        for leaf in reader.leaves() {
+            let mut values = leaf.reader.get_sorted_doc_values("tag").unwrap();
+            assert_eq!(values.value_count(), 6);
+            for doc in 0..leaf.reader.max_doc() {
+                let ord = values.get_ord(doc).unwrap();
+                match tag(leaf.doc_base + doc) {
+                    Some(tag) => assert_eq!(values.lookup_ord(ord).unwrap(), tag.into_bytes()),
+                    None => assert_eq!(ord, -1),
+                }
+                docs += 1;
+            }
+        }
+        assert_eq!(docs, 10);
+    }
+}
@@ -32,12 +32,14 @@ mod doc_values_term_iterator;
 pub use self::doc_values_term_iterator::*;
 
 use core::codec::doc_values::{
-    BinaryDocValues, BinaryDocValuesProvider, NumericDocValues, NumericDocValuesProvider,
-    SortedDocValues, SortedNumericDocValues, SortedSetDocValues, NO_MORE_ORDS,
+    BinaryDocValues, BinaryDocValuesProvider, EmptySortedSetDocValues, NumericDocValues,
+    NumericDocValuesProvider, SortedDocValues, SortedNumericDocValues, SortedSetDocValues,
+    NO_MORE_ORDS,
 };
 use core::codec::posting_iterator::EmptyPostingIterator;
 use core::codec::terms::{EmptyTermIterator, OrdTermState, SeekStatus, TermIterator};
 use core::codec::Codec;
+use core::doc::DocValuesType;
 use core::index::reader::SearchLeafReader;
 use core::store::io::IndexInput;
 use core::util::packed::{
@@ -47,6 +49,7 @@ use core::util::{
     Bits, BitsMut, CloneableLongValues, DocId, LiveBits, LongValues, MatchAllBits, MatchNoBits,
     PagedBytesReader, SparseBits,
 };
+use error::ErrorKind::IllegalArgument;
 use error::Result;
 use std::sync::Arc;
 
@@ -127,6 +130,25 @@ impl DocValues {
         reader.get_sorted_set_doc_values(field)
     }
 
+    /// Returns a multi-valued view over the `SortedDocValues` or `SortedSetDocValues`
+    /// of `field`, or an empty instance if the segment has no doc values for it.
+    pub fn get_sorted_set_view<C: Codec>(
+        reader: &SearchLeafReader<C>,
+        field: &str,
+    ) -> Result<Box<dyn SortedSetDocValues>> {
+        match reader.field_info(field).map(|fi| fi.doc_values_type) {
+            Some(DocValuesType::SortedSet) => reader.get_sorted_set_doc_values(field),
+            Some(DocValuesType::Sorted) => Ok(Box::new(SingletonSortedSetDocValues::new(
+                reader.get_sorted_doc_values(field)?,
+            ))),
+            Some(DocValuesType::Null) | None => Ok(Box::new(EmptySortedSetDocValues)),
+            Some(dv_type) => bail!(IllegalArgument(format!(
+                "field '{}' has doc values of type {:?}, expected sorted or sorted_set",
+                field, dv_type
+            ))),
+        }
+    }
+
     pub fn unwrap_singleton<DV: SortedNumericDocValues>(
         dv: &DV,
     ) -> Option<Box<dyn NumericDocValues>> {
@@ -23,6 +23,7 @@ use core::index::reader::{IndexReader, ReaderSlice};
 use core::index::writer::INDEX_MAX_POSITION;
 use core::search::{DocIterator, Payload, NO_MORE_DOCS};
 use core::store::directory::Directory;
+use core::util::automaton::CompiledAutomaton;
 use core::util::DocId;
 
 use error::ErrorKind::{CorruptIndex, UnsupportedOperation};
@@ -198,6 +199,13 @@ impl<T: Terms> Terms for TermsEnum<T> {
         }
     }
 
+    fn intersect(&self, compiled: &CompiledAutomaton) -> Result<Self::Iterator> {
+        match self {
+            TermsEnum::Raw(t) => Ok(MultiTermIteratorEnum::Raw(t.intersect(compiled)?)),
+            TermsEnum::Multi(t) => t.intersect(compiled),
+        }
+    }
+
     fn size(&self) -> Result<i64> {
         match self {
             TermsEnum::Raw(t) => t.size(),
@@ -77,6 +77,42 @@ pub trait PostingIterator: DocIterator {
     /// (neither members of the returned BytesRef nor bytes
     /// in the bytes). */
     fn payload(&self) -> Result<Payload>;
+
+    /// Shallow-advances the skip data to `target` without moving the iterator,
+    /// and returns the last doc of the block that contains `target`, or
+    /// `NO_MORE_DOCS` if the iterator has no block information.
+    ///
+    /// Targets must be non-decreasing across calls.
+    fn advance_shallow(&mut self, _target: DocId) -> Result<DocId> {
+        Ok(NO_MORE_DOCS)
+    }
+
+    /// Returns the most competitive `Impact` of the docs between the last
+    /// `advance_shallow()` target and `up_to`, both inclusive, or `None` if it is
+    /// unknown.
+    fn impact(&self, _up_to: DocId) -> Option<Impact> {
+        None
+    }
+}
+
+/// The most competitive (freq, norm) pair of a range of postings, no doc of
+/// the range has a higher freq or a higher encoded norm.
+///
+/// Norms encode `boost / sqrt(length)`, so that a higher norm means a
+/// shorter, thus more competitive, field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Impact {
+    pub freq: i32,
+    pub norm: i64,
+}
+
+impl Impact {
+    /// The highest encoded norm value.
+    pub const MAX_NORM: i64 = 0xFF;
+
+    pub fn new(freq: i32, norm: i64) -> Impact {
+        Impact { freq, norm }
+    }
 }
 
 /// a `PostingIterator` that no matching docs are available.
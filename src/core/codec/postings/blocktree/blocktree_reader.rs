@@ -22,7 +22,9 @@ use std::string::ToString;
 use std::sync::Arc;
 
 use core::codec::field_infos::FieldInfo;
-use core::codec::postings::blocktree::{BlockTermState, SegmentTermsIterFrame, MAX_LONGS_SIZE};
+use core::codec::postings::blocktree::{
+    BlockTermState, IntersectTermIterator, SegmentTermsIterFrame, MAX_LONGS_SIZE,
+};
 use core::codec::postings::{
     FieldsProducer, Lucene50PostingIterator, Lucene50PostingsReader, Lucene50PostingsReaderRef,
 };
@@ -32,13 +34,14 @@ use core::codec::{Fields, SeekStatus, TermIterator, Terms};
 use core::doc::IndexOptions;
 use core::store::directory::Directory;
 use core::store::io::{ByteArrayDataInput, DataInput, IndexInput};
+use core::util::automaton::{AutomatonType, CompiledAutomaton};
 use core::util::fst::{
     Arc as FSTArc, ByteSequenceOutput, ByteSequenceOutputFactory, DirectionalBytesReader,
     FSTBytesReader, OutputFactory, FST,
 };
 use core::util::UnsignedShift;
 use error::{
-    ErrorKind::{CorruptIndex, IllegalState, UnsupportedOperation},
+    ErrorKind::{CorruptIndex, IllegalArgument, IllegalState, UnsupportedOperation},
     Result,
 };
 
@@ -499,6 +502,25 @@ impl<'a> Terms for FieldReader {
         ))
     }
 
+    fn intersect(&self, compiled: &CompiledAutomaton) -> Result<Self::Iterator> {
+        if compiled.automaton_type != AutomatonType::Normal {
+            bail!(IllegalArgument(
+                "please use CompiledAutomaton's type to handle non-normal automata".into()
+            ));
+        }
+        let iter = IntersectTermIterator::new(
+            compiled,
+            (*self.terms_in).clone()?,
+            Arc::clone(self.index()),
+            &self.root_code,
+            Arc::clone(&self.field_info),
+            self.longs_size,
+            self.postings_reader.clone(),
+            self.parent.is_any_auto_prefix_terms(),
+        )?;
+        Ok(SegmentTermIterator::intersect(iter))
+    }
+
     fn size(&self) -> Result<i64> {
         Ok(self.num_terms)
     }
@@ -905,7 +927,12 @@ impl ToString for Stats {
 ///
 /// This implementation skips any auto-prefix terms it encounters.
 pub struct SegmentTermIterator {
-    iter: Box<SegmentTermIteratorInner>,
+    iter: SegmentTermIteratorEnum,
+}
+
+enum SegmentTermIteratorEnum {
+    Seek(Box<SegmentTermIteratorInner>),
+    Intersect(Box<IntersectTermIterator>),
 }
 
 impl SegmentTermIterator {
@@ -921,7 +948,15 @@ impl SegmentTermIterator {
             postings_reader,
             field_info,
         ));
-        Self { iter }
+        Self {
+            iter: SegmentTermIteratorEnum::Seek(iter),
+        }
+    }
+
+    fn intersect(iter: IntersectTermIterator) -> Self {
+        Self {
+            iter: SegmentTermIteratorEnum::Intersect(Box::new(iter)),
+        }
     }
 }
 
@@ -931,67 +966,106 @@ impl TermIterator for SegmentTermIterator {
 
     #[inline]
     fn next(&mut self) -> Result<Option<Vec<u8>>> {
-        self.iter.next()
+        match &mut self.iter {
+            SegmentTermIteratorEnum::Seek(i) => i.next(),
+            SegmentTermIteratorEnum::Intersect(i) => i.next(),
+        }
     }
 
     #[inline]
     fn seek_exact(&mut self, text: &[u8]) -> Result<bool> {
-        self.iter.seek_exact(text)
+        match &mut self.iter {
+            SegmentTermIteratorEnum::Seek(i) => i.seek_exact(text),
+            SegmentTermIteratorEnum::Intersect(i) => i.seek_exact(text),
+        }
     }
 
     #[inline]
     fn seek_ceil(&mut self, text: &[u8]) -> Result<SeekStatus> {
-        self.iter.seek_ceil(text)
+        match &mut self.iter {
+            SegmentTermIteratorEnum::Seek(i) => i.seek_ceil(text),
+            SegmentTermIteratorEnum::Intersect(i) => i.seek_ceil(text),
+        }
     }
 
     #[inline]
     fn seek_exact_ord(&mut self, ord: i64) -> Result<()> {
-        self.iter.seek_exact_ord(ord)
+        match &mut self.iter {
+            SegmentTermIteratorEnum::Seek(i) => i.seek_exact_ord(ord),
+            SegmentTermIteratorEnum::Intersect(i) => i.seek_exact_ord(ord),
+        }
     }
 
     #[inline]
     fn seek_exact_state(&mut self, text: &[u8], state: &Self::TermState) -> Result<()> {
-        self.iter.seek_exact_state(text, state)
+        match &mut self.iter {
+            SegmentTermIteratorEnum::Seek(i) => i.seek_exact_state(text, state),
+            SegmentTermIteratorEnum::Intersect(i) => i.seek_exact_state(text, state),
+        }
     }
 
     #[inline]
     fn term(&self) -> Result<&[u8]> {
-        Ok(self.iter.term())
+        match &self.iter {
+            SegmentTermIteratorEnum::Seek(i) => Ok(i.term()),
+            SegmentTermIteratorEnum::Intersect(i) => i.term(),
+        }
     }
 
     #[inline]
     fn ord(&self) -> Result<i64> {
-        self.iter.ord()
+        match &self.iter {
+            SegmentTermIteratorEnum::Seek(i) => i.ord(),
+            SegmentTermIteratorEnum::Intersect(i) => i.ord(),
+        }
     }
 
     #[inline]
     fn doc_freq(&mut self) -> Result<i32> {
-        self.iter.doc_freq()
+        match &mut self.iter {
+            SegmentTermIteratorEnum::Seek(i) => i.doc_freq(),
+            SegmentTermIteratorEnum::Intersect(i) => i.doc_freq(),
+        }
     }
 
     #[inline]
     fn total_term_freq(&mut self) -> Result<i64> {
-        self.iter.total_term_freq()
+        match &mut self.iter {
+            SegmentTermIteratorEnum::Seek(i) => i.total_term_freq(),
+            SegmentTermIteratorEnum::Intersect(i) => i.total_term_freq(),
+        }
     }
 
     #[inline]
     fn postings(&mut self) -> Result<Self::Postings> {
-        self.iter.postings()
+        match &mut self.iter {
+            SegmentTermIteratorEnum::Seek(i) => i.postings(),
+            SegmentTermIteratorEnum::Intersect(i) => i.postings(),
+        }
     }
 
     #[inline]
     fn postings_with_flags(&mut self, flags: u16) -> Result<Self::Postings> {
-        self.iter.postings_with_flags(flags)
+        match &mut self.iter {
+            SegmentTermIteratorEnum::Seek(i) => i.postings_with_flags(flags),
+            SegmentTermIteratorEnum::Intersect(i) => i.postings_with_flags(flags),
+        }
     }
 
     #[inline]
     fn term_state(&mut self) -> Result<Self::TermState> {
-        self.iter.term_state()
+        match &mut self.iter {
+            SegmentTermIteratorEnum::Seek(i) => i.term_state(),
+            SegmentTermIteratorEnum::Intersect(i) => i.term_state(),
+        }
     }
 
     #[inline]
     fn is_empty(&self) -> bool {
-        self.iter.is_empty()
+        match &self.iter {
+            SegmentTermIteratorEnum::Seek(i) => i.is_empty(),
+            SegmentTermIteratorEnum::Intersect(i) => i.is_empty(),
+        }
     }
 }
 
@@ -12,7 +12,9 @@
 // limitations under the License.
 
 use core::codec::codec_util::{write_footer, write_index_header};
+use core::codec::doc_values::NumericDocValues;
 use core::codec::field_infos::{FieldInfo, FieldInfos};
+use core::codec::norms::NormsProducer;
 use core::codec::postings::blocktree::*;
 use core::codec::postings::{
     FieldsConsumer, PostingsWriterBase, DEFAULT_DOC_TERM_FREQ, DEFAULT_SEGMENT_DOC_FREQ,
@@ -272,7 +274,7 @@ impl<T: PostingsWriterBase, O: IndexOutput> BlockTreeTermsWriter<T, O> {
 }
 
 impl<T: PostingsWriterBase, O: IndexOutput> FieldsConsumer for BlockTreeTermsWriter<T, O> {
-    fn write(&mut self, fields: &impl Fields) -> Result<()> {
+    fn write(&mut self, fields: &impl Fields, norms: Option<&dyn NormsProducer>) -> Result<()> {
         let mut last_field = String::new();
         for field in fields.fields() {
             debug_assert!(last_field < field);
@@ -280,6 +282,10 @@ impl<T: PostingsWriterBase, O: IndexOutput> FieldsConsumer for BlockTreeTermsWri
             if let Some(terms) = fields.terms(&field)? {
                 let mut terms_iter = terms.iterator()?;
                 let field_info = self.field_infos.field_info_by_name(&field).unwrap().clone();
+                let field_norms = match norms {
+                    Some(norms) if field_info.has_norms() => Some(norms.norms(&field_info)?),
+                    _ => None,
+                };
                 let mut terms_writer = TermsWriter::new(field_info, self);
 
                 while let Some(term) = terms_iter.next()? {
@@ -288,6 +294,7 @@ impl<T: PostingsWriterBase, O: IndexOutput> FieldsConsumer for BlockTreeTermsWri
                         &mut terms_iter,
                         DEFAULT_SEGMENT_DOC_FREQ,
                         DEFAULT_DOC_TERM_FREQ,
+                        field_norms.as_deref(),
                     )?;
                 }
                 terms_writer.finish()?;
@@ -710,6 +717,7 @@ impl<'a, T: PostingsWriterBase, O: IndexOutput> TermsWriter<'a, T, O> {
         terms_iter: &mut impl TermIterator,
         doc_freq_limit: i32,
         term_freq_limit: i32,
+        norms: Option<&dyn NumericDocValues>,
     ) -> Result<()> {
         if let Some(state) = self.block_tree_writer.postings_writer.write_term(
             text,
@@ -717,6 +725,7 @@ impl<'a, T: PostingsWriterBase, O: IndexOutput> TermsWriter<'a, T, O> {
             &mut self.docs_seen,
             doc_freq_limit,
             term_freq_limit,
+            norms,
         )? {
             assert_ne!(state.doc_freq, 0);
             assert!(
@@ -0,0 +1,776 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::borrow::Cow;
+use std::sync::Arc;
+
+use core::codec::field_infos::FieldInfo;
+use core::codec::postings::blocktree::{BlockTermState, MAX_LONGS_SIZE, OUTPUT_FLAGS_IS_FLOOR};
+use core::codec::postings::{
+    lucene50_decode_term, Lucene50PostingIterator, Lucene50PostingsReaderRef,
+};
+use core::codec::{SeekStatus, TermIterator};
+use core::doc::IndexOptions;
+use core::store::io::{ByteArrayDataInput, DataInput, IndexInput};
+use core::util::automaton::{Automaton, ByteRunAutomaton, CompiledAutomaton};
+use core::util::fst::{
+    Arc as FSTArc, ByteSequenceOutput, ByteSequenceOutputFactory, FSTBytesReader, Output, FST,
+};
+use core::util::{BytesRef, UnsignedShift};
+
+use error::{
+    ErrorKind::{CorruptIndex, UnsupportedOperation},
+    Result,
+};
+
+const OUTPUT_FLAGS_NUM_BITS: usize = 2;
+
+/// One block of the terms dictionary visited by `IntersectTermIterator`,
+/// together with the automaton state reached by the block's prefix.
+struct IntersectTermsIterFrame {
+    ord: usize,
+    fp: i64,
+    fp_orig: i64,
+    fp_end: i64,
+    last_sub_fp: i64,
+
+    // State in automaton
+    state: usize,
+    // State just before the last label
+    last_state: usize,
+
+    metadata_upto: i32,
+
+    suffix_bytes: Vec<u8>,
+    suffixes_reader: ByteArrayDataInput<BytesRef>,
+    stat_bytes: Vec<u8>,
+    stats_reader: ByteArrayDataInput<BytesRef>,
+    floor_data: Vec<u8>,
+    floor_data_reader: ByteArrayDataInput<BytesRef>,
+
+    // Length of prefix shared by all terms in this block
+    prefix: usize,
+    // Number of entries (term or sub-block) in this block
+    ent_count: i32,
+    // Which term we will next read
+    next_ent: i32,
+    // True if this block is either not a floor block,
+    // or, it's the last sub-block of a floor block
+    is_last_in_floor: bool,
+    // True if all entries are terms
+    is_leaf_block: bool,
+    num_follow_floor_blocks: i32,
+    next_floor_label: i32,
+
+    // The transition of `state` we are currently matching labels against;
+    // min and max are -1 if the state has no transitions
+    transition_index: usize,
+    transition_count: usize,
+    transition_min: i32,
+    transition_max: i32,
+    transition_dest: usize,
+
+    version_auto_prefix: bool,
+    is_auto_prefix_term: bool,
+
+    arc: Option<FSTArc<ByteSequenceOutput>>,
+    term_state: BlockTermState,
+
+    // metadata buffer, holding monotonic values
+    longs: [i64; MAX_LONGS_SIZE],
+    // metadata buffer, holding general values
+    bytes: Vec<u8>,
+    bytes_reader: ByteArrayDataInput<BytesRef>,
+
+    // Cumulative output so far
+    output_prefix: ByteSequenceOutput,
+
+    start_byte_pos: usize,
+    suffix: usize,
+}
+
+impl IntersectTermsIterFrame {
+    fn new(ord: usize, term_state: BlockTermState, version_auto_prefix: bool) -> Self {
+        IntersectTermsIterFrame {
+            ord,
+            fp: 0,
+            fp_orig: 0,
+            fp_end: 0,
+            last_sub_fp: 0,
+            state: 0,
+            last_state: 0,
+            metadata_upto: 0,
+            suffix_bytes: vec![0; 128],
+            suffixes_reader: ByteArrayDataInput::new(BytesRef::default()),
+            stat_bytes: vec![0; 64],
+            stats_reader: ByteArrayDataInput::new(BytesRef::default()),
+            floor_data: vec![0; 32],
+            floor_data_reader: ByteArrayDataInput::new(BytesRef::default()),
+            prefix: 0,
+            ent_count: 0,
+            next_ent: 0,
+            is_last_in_floor: false,
+            is_leaf_block: false,
+            num_follow_floor_blocks: 0,
+            next_floor_label: 0,
+            transition_index: 0,
+            transition_count: 0,
+            transition_min: -1,
+            transition_max: -1,
+            transition_dest: 0,
+            version_auto_prefix,
+            is_auto_prefix_term: false,
+            arc: None,
+            term_state,
+            longs: [0; MAX_LONGS_SIZE],
+            bytes: vec![0; 32],
+            bytes_reader: ByteArrayDataInput::new(BytesRef::default()),
+            output_prefix: ByteSequenceOutput::empty(),
+            start_byte_pos: 0,
+            suffix: 0,
+        }
+    }
+
+    fn set_state(&mut self, automaton: &Automaton, state: usize) {
+        self.state = state;
+        self.transition_index = 0;
+        let transitions = automaton.transitions(state);
+        self.transition_count = transitions.len();
+        if let Some(t) = transitions.first() {
+            self.transition_min = i32::from(t.min);
+            self.transition_max = i32::from(t.max);
+            self.transition_dest = t.dest;
+        } else {
+            // Must set min to -1 so the "label < min" check never falsely
+            // triggers, and max to -1 so we immediately realize we need to
+            // step to the next transition and then pop this frame
+            self.transition_min = -1;
+            self.transition_max = -1;
+        }
+    }
+
+    fn next_transition(&mut self, automaton: &Automaton) {
+        self.transition_index += 1;
+        let t = &automaton.transitions(self.state)[self.transition_index];
+        self.transition_min = i32::from(t.min);
+        self.transition_max = i32::from(t.max);
+        self.transition_dest = t.dest;
+    }
+
+    #[inline]
+    fn label(&self) -> i32 {
+        i32::from(self.suffix_bytes[self.start_byte_pos])
+    }
+
+    #[inline]
+    fn suffix_bytes(&self) -> &[u8] {
+        &self.suffix_bytes[self.start_byte_pos..self.start_byte_pos + self.suffix]
+    }
+
+    fn read_next_floor_fp(&mut self) -> Result<()> {
+        self.fp = self.fp_orig + self.floor_data_reader.read_vlong()?.unsigned_shift(1);
+        self.num_follow_floor_blocks -= 1;
+        if self.num_follow_floor_blocks != 0 {
+            self.next_floor_label = i32::from(self.floor_data_reader.read_byte()?);
+        } else {
+            self.next_floor_label = 256;
+        }
+        Ok(())
+    }
+
+    fn load_next_floor_block(&mut self, input: &mut dyn IndexInput) -> Result<()> {
+        debug_assert!(self.num_follow_floor_blocks > 0);
+        loop {
+            self.read_next_floor_fp()?;
+            if self.num_follow_floor_blocks == 0 || self.next_floor_label > self.transition_min {
+                break;
+            }
+        }
+        self.load(input, None, None)
+    }
+
+    fn load(
+        &mut self,
+        input: &mut dyn IndexInput,
+        frame_index_data: Option<&[u8]>,
+        run_automaton: Option<&ByteRunAutomaton>,
+    ) -> Result<()> {
+        if let Some(data) = frame_index_data {
+            self.floor_data.resize(data.len(), 0);
+            self.floor_data.copy_from_slice(data);
+            self.floor_data_reader
+                .reset(BytesRef::new(&self.floor_data));
+            // Skip first long -- has redundant fp, hasTerms
+            // flag, isFloor flag
+            let code = self.floor_data_reader.read_vlong()?;
+            if (code & OUTPUT_FLAGS_IS_FLOOR) != 0 {
+                // Floor frame
+                self.num_follow_floor_blocks = self.floor_data_reader.read_vint()?;
+                self.next_floor_label = i32::from(self.floor_data_reader.read_byte()?);
+
+                // If current state is not accept, and has transitions, we must
+                // process first block in case it has empty suffix:
+                let accept = run_automaton.map_or(false, |r| r.is_accept(self.state));
+                if !accept && self.transition_count != 0 {
+                    // Maybe skip floor blocks:
+                    debug_assert_eq!(self.transition_index, 0);
+                    while self.num_follow_floor_blocks != 0
+                        && self.next_floor_label <= self.transition_min
+                    {
+                        self.read_next_floor_fp()?;
+                    }
+                }
+            }
+        }
+
+        input.seek(self.fp)?;
+        let mut code = input.read_vint()?;
+        self.ent_count = code.unsigned_shift(1);
+        debug_assert!(self.ent_count > 0);
+        self.is_last_in_floor = (code & 1) != 0;
+
+        // term suffixes:
+        code = input.read_vint()?;
+        self.is_leaf_block = (code & 1) != 0;
+        let num_bytes = code.unsigned_shift(1) as usize;
+        self.suffix_bytes.resize(num_bytes, 0);
+        input.read_exact(&mut self.suffix_bytes)?;
+        self.suffixes_reader
+            .reset(BytesRef::new(&self.suffix_bytes));
+
+        // stats
+        let num_bytes = input.read_vint()? as usize;
+        self.stat_bytes.resize(num_bytes, 0);
+        input.read_exact(&mut self.stat_bytes)?;
+        self.stats_reader.reset(BytesRef::new(&self.stat_bytes));
+        self.metadata_upto = 0;
+
+        self.term_state.term_block_ord = 0;
+        self.next_ent = 0;
+
+        // metadata
+        let num_bytes = input.read_vint()? as usize;
+        self.bytes.resize(num_bytes, 0);
+        input.read_exact(&mut self.bytes)?;
+        self.bytes_reader.reset(BytesRef::new(&self.bytes));
+
+        if !self.is_last_in_floor {
+            // Sub-blocks of a single floor block are always
+            // written one after another -- tail recurse:
+            self.fp_end = input.file_pointer();
+        }
+        Ok(())
+    }
+
+    // Decodes next entry; returns true if it's a sub-block
+    fn next(&mut self) -> Result<bool> {
+        if self.is_leaf_block {
+            self.next_leaf()?;
+            Ok(false)
+        } else {
+            self.next_non_leaf()
+        }
+    }
+
+    fn next_leaf(&mut self) -> Result<()> {
+        debug_assert!(self.next_ent != -1 && self.next_ent < self.ent_count);
+        self.next_ent += 1;
+        self.suffix = self.suffixes_reader.read_vint()? as usize;
+        self.start_byte_pos = self.suffixes_reader.position();
+        self.suffixes_reader.skip_bytes(self.suffix)
+    }
+
+    fn next_non_leaf(&mut self) -> Result<bool> {
+        debug_assert!(self.next_ent != -1 && self.next_ent < self.ent_count);
+        self.next_ent += 1;
+        let code = self.suffixes_reader.read_vint()?;
+        if !self.version_auto_prefix {
+            self.suffix = code.unsigned_shift(1) as usize;
+            self.start_byte_pos = self.suffixes_reader.position();
+            self.suffixes_reader.skip_bytes(self.suffix)?;
+            if (code & 1) == 0 {
+                // A normal term
+                self.term_state.term_block_ord += 1;
+                Ok(false)
+            } else {
+                // A sub-block; make sub-FP absolute:
+                self.last_sub_fp = self.fp - self.suffixes_reader.read_vlong()?;
+                Ok(true)
+            }
+        } else {
+            self.suffix = code.unsigned_shift(2) as usize;
+            self.start_byte_pos = self.suffixes_reader.position();
+            self.suffixes_reader.skip_bytes(self.suffix)?;
+            self.is_auto_prefix_term = false;
+            match code & 3 {
+                0 => {
+                    // A normal term
+                    self.term_state.term_block_ord += 1;
+                    Ok(false)
+                }
+                1 => {
+                    // A sub-block; make sub-FP absolute:
+                    self.last_sub_fp = self.fp - self.suffixes_reader.read_vlong()?;
+                    Ok(true)
+                }
+                _ => {
+                    // A prefix term: the caller skips it
+                    self.is_auto_prefix_term = true;
+                    self.term_state.term_block_ord += 1;
+                    self.suffixes_reader.read_byte()?;
+                    Ok(false)
+                }
+            }
+        }
+    }
+
+    fn term_block_ord(&self) -> i32 {
+        if self.is_leaf_block {
+            self.next_ent
+        } else {
+            self.term_state.term_block_ord
+        }
+    }
+
+    fn decode_metadata(&mut self, field_info: &FieldInfo, longs_size: usize) -> Result<()> {
+        // lazily catch up on metadata decode:
+        let limit = self.term_block_ord();
+        let mut absolute = self.metadata_upto == 0;
+        debug_assert!(limit > 0);
+
+        while self.metadata_upto < limit {
+            // stats
+            self.term_state.doc_freq = self.stats_reader.read_vint()?;
+            if field_info.index_options != IndexOptions::Docs {
+                self.term_state.total_term_freq =
+                    i64::from(self.term_state.doc_freq) + self.stats_reader.read_vlong()?;
+            }
+            // metadata
+            for i in 0..longs_size {
+                self.longs[i] = self.bytes_reader.read_vlong()?;
+            }
+            lucene50_decode_term(
+                &self.longs,
+                &mut self.bytes_reader,
+                field_info,
+                &mut self.term_state,
+                absolute,
+            )?;
+            self.metadata_upto += 1;
+            absolute = false;
+        }
+        self.term_state.term_block_ord = self.metadata_upto;
+        Ok(())
+    }
+}
+
+/// This is used to implement efficient `Terms::intersect` for
+/// block-tree.  Note that it cannot seek, except for the initial term on
+/// init.  It just "nexts" through the intersection of the automaton and
+/// the terms.  It does not use the terms index at all: on init, it
+/// loads the root block, and scans its way to the initial term.
+/// Likewise, in next it scans until it finds a term that matches the
+/// current automaton transition.
+pub struct IntersectTermIterator {
+    input: Box<dyn IndexInput>,
+    stack: Vec<IntersectTermsIterFrame>,
+    current_frame: usize,
+    automaton: Arc<Automaton>,
+    run_automaton: Arc<ByteRunAutomaton>,
+    common_suffix: Option<Vec<u8>>,
+    term: Vec<u8>,
+    index: Arc<FST<ByteSequenceOutputFactory>>,
+    fst_reader: FSTBytesReader,
+    field_info: Arc<FieldInfo>,
+    longs_size: usize,
+    postings_reader: Lucene50PostingsReaderRef,
+    version_auto_prefix: bool,
+    eof: bool,
+}
+
+impl IntersectTermIterator {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        compiled: &CompiledAutomaton,
+        input: Box<dyn IndexInput>,
+        index: Arc<FST<ByteSequenceOutputFactory>>,
+        root_code: &[u8],
+        field_info: Arc<FieldInfo>,
+        longs_size: usize,
+        postings_reader: Lucene50PostingsReaderRef,
+        version_auto_prefix: bool,
+    ) -> Result<IntersectTermIterator> {
+        let (automaton, run_automaton) = match (&compiled.automaton, &compiled.run_automaton) {
+            (Some(a), Some(r)) => (Arc::clone(a), Arc::clone(r)),
+            _ => bail!(UnsupportedOperation(Cow::Borrowed(
+                "please use CompiledAutomaton's type to handle non-normal automata"
+            ))),
+        };
+        let fst_reader = index.bytes_reader();
+        let mut iter = IntersectTermIterator {
+            input,
+            stack: Vec::with_capacity(5),
+            current_frame: 0,
+            automaton,
+            run_automaton,
+            common_suffix: compiled.common_suffix.clone(),
+            term: Vec::new(),
+            index,
+            fst_reader,
+            field_info,
+            longs_size,
+            postings_reader,
+            version_auto_prefix,
+            eof: false,
+        };
+
+        let arc = iter.index.root_arc();
+        // Empty string prefix must have an output in the index!
+        debug_assert!(arc.is_final());
+
+        // Special push frame since it's the first one:
+        let mut root_code_reader = root_code;
+        let root_fp = root_code_reader
+            .read_vlong()?
+            .unsigned_shift(OUTPUT_FLAGS_NUM_BITS);
+        let mut frame = iter.new_frame(0);
+        frame.fp = root_fp;
+        frame.fp_orig = root_fp;
+        frame.prefix = 0;
+        frame.set_state(&iter.automaton, 0);
+        frame.output_prefix = arc.output.clone().unwrap_or_else(ByteSequenceOutput::empty);
+        frame.arc = Some(arc);
+        frame.load(
+            iter.input.as_mut(),
+            Some(root_code),
+            Some(&iter.run_automaton),
+        )?;
+        iter.stack.push(frame);
+        Ok(iter)
+    }
+
+    fn new_frame(&self, ord: usize) -> IntersectTermsIterFrame {
+        let mut term_state = self.postings_reader.new_term_state();
+        term_state.total_term_freq = -1;
+        IntersectTermsIterFrame::new(ord, term_state, self.version_auto_prefix)
+    }
+
+    fn push_frame(&mut self, state: usize) -> Result<usize> {
+        let ord = self.current_frame + 1;
+        if ord == self.stack.len() {
+            let frame = self.new_frame(ord);
+            self.stack.push(frame);
+        }
+
+        let (fp, prefix, mut idx, mut arc, mut output) = {
+            let current = &self.stack[self.current_frame];
+            debug_assert!(current.suffix > 0);
+            (
+                current.last_sub_fp,
+                current.prefix + current.suffix,
+                current.prefix,
+                current.arc.clone().unwrap(),
+                current.output_prefix.clone(),
+            )
+        };
+
+        // Walk the arc through the index -- we only
+        // "bother" with this so we can get the floor data
+        // from the index and skip floor blocks when
+        // possible:
+        while idx < prefix {
+            let target = i32::from(self.term[idx]);
+            arc = match self
+                .index
+                .find_target_arc(target, &arc, &mut self.fst_reader)?
+            {
+                Some(arc) => arc,
+                None => bail!(CorruptIndex(format!(
+                    "missing terms index arc for prefix {:?}",
+                    &self.term[..=idx]
+                ))),
+            };
+            if let Some(ref out) = arc.output {
+                output.concat(out);
+            }
+            idx += 1;
+        }
+        debug_assert!(arc.is_final());
+        let mut frame_data = output.clone();
+        if let Some(ref out) = arc.next_final_output {
+            frame_data.concat(out);
+        }
+
+        let frame = &mut self.stack[ord];
+        frame.fp = fp;
+        frame.fp_orig = fp;
+        frame.prefix = prefix;
+        frame.set_state(&self.automaton, state);
+        frame.arc = Some(arc);
+        frame.output_prefix = output;
+        frame.load(
+            self.input.as_mut(),
+            Some(frame_data.inner()),
+            Some(&self.run_automaton),
+        )?;
+        Ok(ord)
+    }
+
+    fn copy_term(&mut self) {
+        let frame = &self.stack[self.current_frame];
+        let len = frame.prefix + frame.suffix;
+        self.term.resize(len, 0);
+        self.term[frame.prefix..len].copy_from_slice(frame.suffix_bytes());
+    }
+
+    /// Pops finished frames and decodes the next entry, returning whether it
+    /// is a sub-block, or `None` if all terms have been visited.
+    fn pop_push_next(&mut self) -> Result<Option<bool>> {
+        loop {
+            let frame = &mut self.stack[self.current_frame];
+            if frame.next_ent != frame.ent_count {
+                break;
+            }
+            if !frame.is_last_in_floor {
+                // Advance to next floor block
+                frame.load_next_floor_block(self.input.as_mut())?;
+                break;
+            }
+            if frame.ord == 0 {
+                return Ok(None);
+            }
+            let last_fp = frame.fp_orig;
+            self.current_frame -= 1;
+            debug_assert_eq!(self.stack[self.current_frame].last_sub_fp, last_fp);
+        }
+        self.stack[self.current_frame].next().map(Some)
+    }
+
+    /// Whether the term of the current entry ends with the common suffix.
+    fn matches_common_suffix(&self, common_suffix: &[u8]) -> bool {
+        let frame = &self.stack[self.current_frame];
+        if frame.prefix + frame.suffix < common_suffix.len() {
+            return false;
+        }
+        let suffix_bytes = frame.suffix_bytes();
+        if common_suffix.len() > frame.suffix {
+            // A prefix of the common suffix overlaps with
+            // the suffix of the block prefix so we first
+            // test whether the prefix part matches:
+            let len_in_prefix = common_suffix.len() - frame.suffix;
+            self.term[frame.prefix - len_in_prefix..frame.prefix] == common_suffix[..len_in_prefix]
+                && suffix_bytes == &common_suffix[len_in_prefix..]
+        } else {
+            &suffix_bytes[frame.suffix - common_suffix.len()..] == common_suffix
+        }
+    }
+
+    fn next_term(&mut self) -> Result<bool> {
+        macro_rules! pop_push_next {
+            () => {
+                match self.pop_push_next()? {
+                    Some(is_sub_block) => is_sub_block,
+                    None => return Ok(false),
+                }
+            };
+        }
+
+        let automaton = Arc::clone(&self.automaton);
+        let run_automaton = Arc::clone(&self.run_automaton);
+        let mut is_sub_block = pop_push_next!();
+
+        'next_term: loop {
+            let mut state;
+            let mut last_state;
+
+            if self.stack[self.current_frame].is_auto_prefix_term {
+                is_sub_block = pop_push_next!();
+                continue;
+            }
+
+            // NOTE: suffix == 0 can only happen on the first term in a block, when
+            // there is a term exactly matching a prefix in the index.
+            if self.stack[self.current_frame].suffix != 0 {
+                {
+                    let frame = &mut self.stack[self.current_frame];
+                    // This is the first byte of the suffix of the term we are now on:
+                    let label = frame.label();
+
+                    if label < frame.transition_min {
+                        // Common case: we are scanning terms in this block to "catch up" to
+                        // current transition in the automaton:
+                        let min_trans = frame.transition_min;
+                        while frame.next_ent < frame.ent_count {
+                            is_sub_block = frame.next()?;
+                            if frame.label() >= min_trans {
+                                continue 'next_term;
+                            }
+                        }
+                        // End of frame:
+                        is_sub_block = pop_push_next!();
+                        continue;
+                    }
+
+                    // Advance where we are in the automaton to match this label:
+                    while label > frame.transition_max {
+                        if frame.transition_index + 1 >= frame.transition_count {
+                            // Pop this frame: no further matches are possible because
+                            // we've moved beyond what the max transition will allow
+                            if frame.ord == 0 {
+                                return Ok(false);
+                            }
+                            self.current_frame -= 1;
+                            is_sub_block = pop_push_next!();
+                            continue 'next_term;
+                        }
+                        frame.next_transition(&automaton);
+
+                        if label < frame.transition_min {
+                            let min_trans = frame.transition_min;
+                            while frame.next_ent < frame.ent_count {
+                                is_sub_block = frame.next()?;
+                                if frame.label() >= min_trans {
+                                    continue 'next_term;
+                                }
+                            }
+                            // End of frame:
+                            is_sub_block = pop_push_next!();
+                            continue 'next_term;
+                        }
+                    }
+                }
+
+                if !is_sub_block {
+                    if let Some(ref common_suffix) = self.common_suffix {
+                        if !self.matches_common_suffix(common_suffix) {
+                            is_sub_block = pop_push_next!();
+                            continue;
+                        }
+                    }
+                }
+
+                // See if the term suffix matches the automaton:
+
+                // We know from above that the first byte in our suffix (label)
+                // matches the current transition, so we step from the 2nd byte
+                // in the suffix:
+                let frame = &self.stack[self.current_frame];
+                last_state = frame.state;
+                state = frame.transition_dest;
+                for &b in &frame.suffix_bytes()[1..] {
+                    last_state = state;
+                    match run_automaton.step(state, b) {
+                        Some(s) => state = s,
+                        None => {
+                            // No match
+                            is_sub_block = pop_push_next!();
+                            continue 'next_term;
+                        }
+                    }
+                }
+            } else {
+                let frame = &self.stack[self.current_frame];
+                state = frame.state;
+                last_state = frame.last_state;
+            }
+
+            if is_sub_block {
+                // Match!  Recurse:
+                self.copy_term();
+                self.current_frame = self.push_frame(state)?;
+                self.stack[self.current_frame].last_state = last_state;
+            } else if run_automaton.is_accept(state) {
+                self.copy_term();
+                return Ok(true);
+            }
+            // else this term is a prefix of a term accepted by the
+            // automaton, but is not itself accepted
+
+            is_sub_block = pop_push_next!();
+        }
+    }
+
+    fn unsupported<T>() -> Result<T> {
+        bail!(UnsupportedOperation(Cow::Borrowed(
+            "intersect term iterator doesn't support seek"
+        )))
+    }
+}
+
+impl TermIterator for IntersectTermIterator {
+    type Postings = Lucene50PostingIterator;
+    type TermState = BlockTermState;
+
+    fn next(&mut self) -> Result<Option<Vec<u8>>> {
+        if self.eof {
+            return Ok(None);
+        }
+        if self.next_term()? {
+            Ok(Some(self.term.clone()))
+        } else {
+            self.eof = true;
+            Ok(None)
+        }
+    }
+
+    fn seek_exact(&mut self, _text: &[u8]) -> Result<bool> {
+        Self::unsupported()
+    }
+
+    fn seek_ceil(&mut self, _text: &[u8]) -> Result<SeekStatus> {
+        Self::unsupported()
+    }
+
+    fn seek_exact_ord(&mut self, _ord: i64) -> Result<()> {
+        Self::unsupported()
+    }
+
+    fn seek_exact_state(&mut self, _text: &[u8], _state: &Self::TermState) -> Result<()> {
+        Self::unsupported()
+    }
+
+    fn term(&self) -> Result<&[u8]> {
+        Ok(&self.term)
+    }
+
+    fn ord(&self) -> Result<i64> {
+        bail!(UnsupportedOperation(Cow::Borrowed("")))
+    }
+
+    fn doc_freq(&mut self) -> Result<i32> {
+        debug_assert!(!self.eof);
+        self.stack[self.current_frame].decode_metadata(&self.field_info, self.longs_size)?;
+        Ok(self.stack[self.current_frame].term_state.doc_freq)
+    }
+
+    fn total_term_freq(&mut self) -> Result<i64> {
+        debug_assert!(!self.eof);
+        self.stack[self.current_frame].decode_metadata(&self.field_info, self.longs_size)?;
+        Ok(self.stack[self.current_frame].term_state.total_term_freq)
+    }
+
+    fn postings_with_flags(&mut self, flags: u16) -> Result<Self::Postings> {
+        debug_assert!(!self.eof);
+        self.stack[self.current_frame].decode_metadata(&self.field_info, self.longs_size)?;
+        self.postings_reader.postings(
+            &self.field_info,
+            &self.stack[self.current_frame].term_state,
+            flags,
+        )
+    }
+
+    fn term_state(&mut self) -> Result<Self::TermState> {
+        self.stack[self.current_frame].decode_metadata(&self.field_info, self.longs_size)?;
+        Ok(self.stack[self.current_frame].term_state.clone())
+    }
+}
@@ -23,6 +23,10 @@ mod term_iter_frame;
 
 pub use self::term_iter_frame::*;
 
+mod intersect_term_iter;
+
+pub use self::intersect_term_iter::*;
+
 const MAX_LONGS_SIZE: usize = 3;
 
 use core::codec::TermState;
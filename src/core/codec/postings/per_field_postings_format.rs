@@ -17,6 +17,7 @@ use std::fmt;
 use std::mem;
 use std::sync::Arc;
 
+use core::codec::norms::NormsProducer;
 use core::codec::postings::blocktree::FieldReaderRef;
 use core::codec::postings::{
     postings_format_for_name, FieldsConsumer, FieldsConsumerEnum, FieldsProducer,
@@ -201,7 +202,7 @@ impl<D: Directory, DW: Directory, C: Codec> FieldsConsumer for PerFieldFieldsWri
     // And we assume that we always use one format all the time.
     // so we won't implement this like lucene
     // when the format changes, it's easy to change the hard code then.
-    fn write(&mut self, fields: &impl Fields) -> Result<()> {
+    fn write(&mut self, fields: &impl Fields, norms: Option<&dyn NormsProducer>) -> Result<()> {
         // this is only one format, so suffix is always "0"
         let segment_suffix =
             self.get_full_segment_suffix(&self.write_state.segment_suffix, "Lucene50_0".into());
@@ -211,7 +212,7 @@ impl<D: Directory, DW: Directory, C: Codec> FieldsConsumer for PerFieldFieldsWri
         let old_suffix = mem::replace(&mut self.write_state.segment_suffix, segment_suffix);
 
         let mut consumer = format.fields_consumer(&self.write_state)?;
-        consumer.write(fields)?;
+        consumer.write(fields, norms)?;
 
         self.write_state.segment_suffix = old_suffix;
 
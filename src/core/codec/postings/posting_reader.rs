@@ -18,7 +18,7 @@ use core::codec::postings::posting_format::BLOCK_SIZE;
 use core::codec::postings::skip_reader::*;
 use core::codec::segment_infos::{segment_file_name, SegmentReadState};
 use core::codec::{codec_util, Codec};
-use core::codec::{PostingIterator, PostingIteratorFlags};
+use core::codec::{Impact, PostingIterator, PostingIteratorFlags};
 use core::search::{DocIterator, Payload, NO_MORE_DOCS};
 use core::store::directory::Directory;
 use core::store::io::{DataInput, IndexInput};
@@ -55,7 +55,9 @@ pub const PAY_CODEC: &str = "Lucene50PostingsWriterPay";
 
 // Increment version to change it
 pub const VERSION_START: i32 = 0;
-pub const VERSION_CURRENT: i32 = VERSION_START + 1;
+/// Skip entries carry the most competitive freq and norm of the docs they skip over.
+pub const VERSION_IMPACT_SKIP_DATA: i32 = VERSION_START + 2;
+pub const VERSION_CURRENT: i32 = VERSION_IMPACT_SKIP_DATA;
 
 fn clone_option_index_input(input: &Option<Box<dyn IndexInput>>) -> Result<Box<dyn IndexInput>> {
     debug_assert!(input.is_some());
@@ -196,6 +198,7 @@ impl Lucene50PostingsReader {
         let index_has_positions = options.has_positions();
         let index_has_offsets = options.has_offsets();
         let index_has_payloads = field_info.has_store_payloads;
+        let has_impacts = self.version >= VERSION_IMPACT_SKIP_DATA;
 
         if !index_has_positions
             || !PostingIteratorFlags::feature_requested(flags, PostingIteratorFlags::POSITIONS)
@@ -208,6 +211,7 @@ impl Lucene50PostingsReader {
                     flags,
                     self.for_util.clone(),
                     self.use_simd,
+                    has_impacts,
                 )?,
             )))
         } else if (!index_has_offsets
@@ -224,6 +228,7 @@ impl Lucene50PostingsReader {
                     flags,
                     self.for_util.clone(),
                     self.use_simd,
+                    has_impacts,
                 )?,
             )))
         } else {
@@ -239,6 +244,7 @@ impl Lucene50PostingsReader {
                     flags,
                     self.for_util.clone(),
                     self.use_simd,
+                    has_impacts,
                 )?),
             ))
         }
@@ -350,6 +356,10 @@ struct BlockDocIterator {
 
     pub skipper: Option<Lucene50SkipReader>,
     skipped: bool,
+    /// skip reader dedicated to `advance_shallow`, which must not move `skipper`
+    impacts_skipper: Option<Lucene50SkipReader>,
+    shallow_skipped: bool,
+    has_impacts: bool,
 
     start_doc_in: Box<dyn IndexInput>,
 
@@ -414,6 +424,7 @@ impl BlockDocIterator {
         flags: u16,
         for_util: ForUtil,
         use_simd: bool,
+        has_impacts: bool,
     ) -> Result<BlockDocIterator> {
         let options = &field_info.index_options;
         let mut iterator = BlockDocIterator {
@@ -424,6 +435,9 @@ impl BlockDocIterator {
             doc_buffer_upto: 0,
             skipped: false,
             skipper: None,
+            impacts_skipper: None,
+            shallow_skipped: false,
+            has_impacts,
             doc_in: None,
             doc_freq: 0,
             total_term_freq: 0,
@@ -487,6 +501,7 @@ impl BlockDocIterator {
         self.next_skip_doc = BLOCK_SIZE - 1; // we won't skip if target is found in first block
         self.doc_buffer_upto = BLOCK_SIZE;
         self.skipped = false;
+        self.shallow_skipped = false;
 
         self.encode_type = EncodeType::PF;
         self.ef_decoder = None;
@@ -602,6 +617,56 @@ impl PostingIterator for BlockDocIterator {
     fn payload(&self) -> Result<Payload> {
         Ok(Payload::new())
     }
+
+    fn advance_shallow(&mut self, target: DocId) -> Result<DocId> {
+        if self.doc_freq <= BLOCK_SIZE || !self.has_impacts {
+            return Ok(NO_MORE_DOCS);
+        }
+        if self.impacts_skipper.is_none() {
+            self.impacts_skipper = Some(Lucene50SkipReader::new(
+                clone_option_index_input(&self.doc_in)?,
+                MAX_SKIP_LEVELS,
+                self.index_has_pos,
+                self.index_has_offsets,
+                self.index_has_payloads,
+                self.has_impacts,
+            ));
+        }
+
+        let skipper = self.impacts_skipper.as_mut().unwrap();
+        if !self.shallow_skipped {
+            debug_assert_ne!(self.skip_offset, -1);
+            skipper.init(
+                self.doc_term_start_fp + self.skip_offset,
+                self.doc_term_start_fp,
+                0,
+                0,
+                self.doc_freq,
+            )?;
+            // load the first skip entries, which also cover doc 0
+            skipper.skip_to(1)?;
+            self.shallow_skipped = true;
+        }
+        if target > skipper.next_skip_doc() {
+            skipper.skip_to(target)?;
+        }
+        // the last block has no skip entry, `next_skip_doc` is `i32::MAX` then
+        Ok(skipper.next_skip_doc())
+    }
+
+    fn impact(&self, up_to: DocId) -> Option<Impact> {
+        let block_impact = if self.shallow_skipped {
+            self.impacts_skipper.as_ref().unwrap().impact(up_to)
+        } else {
+            None
+        };
+        // no doc can have a freq higher than what remains once all the other
+        // docs got a freq of 1
+        Some(block_impact.unwrap_or_else(|| {
+            let max_freq = (self.total_term_freq - i64::from(self.doc_freq) + 1).max(1);
+            Impact::new(max_freq as i32, Impact::MAX_NORM)
+        }))
+    }
 }
 
 impl DocIterator for BlockDocIterator {
@@ -663,6 +728,7 @@ impl DocIterator for BlockDocIterator {
                     self.index_has_pos,
                     self.index_has_offsets,
                     self.index_has_payloads,
+                    self.has_impacts,
                 ));
             }
 
@@ -807,6 +873,7 @@ impl SIMDBlockDocIterator {
         term_state: &BlockTermState,
         flags: u16,
         for_util: ForUtil,
+        has_impacts: bool,
     ) -> Result<Self> {
         Ok(Self {
             doc_iter: BlockDocIterator::new(
@@ -816,6 +883,7 @@ impl SIMDBlockDocIterator {
                 flags,
                 for_util,
                 false,
+                has_impacts,
             )?,
             simd_doc_deltas: SIMDBlockDecoder::new(),
             total_base: 0,
@@ -920,6 +988,7 @@ impl DocIterator for SIMDBlockDocIterator {
                     self.doc_iter.index_has_pos,
                     self.doc_iter.index_has_offsets,
                     self.doc_iter.index_has_payloads,
+                    self.doc_iter.has_impacts,
                 ));
             }
 
@@ -1037,6 +1106,14 @@ impl PostingIterator for SIMDBlockDocIterator {
     fn payload(&self) -> Result<Payload> {
         self.doc_iter.payload()
     }
+
+    fn advance_shallow(&mut self, target: DocId) -> Result<DocId> {
+        self.doc_iter.advance_shallow(target)
+    }
+
+    fn impact(&self, up_to: DocId) -> Option<Impact> {
+        self.doc_iter.impact(up_to)
+    }
 }
 
 struct BlockPostingIterator {
@@ -1051,6 +1128,7 @@ struct BlockPostingIterator {
 
     skipper: Option<Lucene50SkipReader>,
     skipped: bool,
+    has_impacts: bool,
 
     start_doc_in: Box<dyn IndexInput>,
 
@@ -1122,6 +1200,7 @@ struct BlockPostingIterator {
 }
 
 impl BlockPostingIterator {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         start_doc_in: Box<dyn IndexInput>,
         pos_in: Box<dyn IndexInput>,
@@ -1130,6 +1209,7 @@ impl BlockPostingIterator {
         _flags: u16,
         for_util: ForUtil,
         use_simd: bool,
+        has_impacts: bool,
     ) -> Result<BlockPostingIterator> {
         let options = &field_info.index_options;
         let mut iterator = BlockPostingIterator {
@@ -1142,6 +1222,7 @@ impl BlockPostingIterator {
             pos_buffer_upto: 0,
             skipped: false,
             skipper: None,
+            has_impacts,
             doc_in: None,
             doc_freq: 0,
             pos_in,
@@ -1452,6 +1533,7 @@ impl DocIterator for BlockPostingIterator {
                     self.index_has_pos,
                     self.index_has_offsets,
                     self.index_has_payloads,
+                    self.has_impacts,
                 ));
             }
 
@@ -1616,6 +1698,7 @@ struct EverythingIterator {
 
     skipper: Option<Lucene50SkipReader>,
     skipped: bool,
+    has_impacts: bool,
 
     start_doc_in: Box<dyn IndexInput>,
 
@@ -1694,7 +1777,7 @@ struct EverythingIterator {
 }
 
 impl<'a> EverythingIterator {
-    //#[allow(too_many_arguments)]
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         start_doc_in: Box<dyn IndexInput>,
         pos_in: Box<dyn IndexInput>,
@@ -1704,6 +1787,7 @@ impl<'a> EverythingIterator {
         flags: u16,
         for_util: ForUtil,
         use_simd: bool,
+        has_impacts: bool,
     ) -> Result<EverythingIterator> {
         let encoded = [0u8; MAX_ENCODED_SIZE];
         let index_has_offsets = field_info.index_options.has_offsets();
@@ -1765,6 +1849,7 @@ impl<'a> EverythingIterator {
             skip_offset: 0,
             skipper: None,
             skipped: false,
+            has_impacts,
             total_term_freq: 0,
             for_util,
             encode_type: EncodeType::PF,
@@ -2198,6 +2283,7 @@ impl DocIterator for EverythingIterator {
                     true,
                     self.index_has_offsets,
                     self.index_has_payloads,
+                    self.has_impacts,
                 ));
             }
 
@@ -2392,6 +2478,24 @@ impl PostingIterator for Lucene50PostingIterator {
             Lucene50PostingIterEnum::Everything(i) => i.payload(),
         }
     }
+
+    fn advance_shallow(&mut self, target: DocId) -> Result<DocId> {
+        match &mut self.0 {
+            Lucene50PostingIterEnum::Doc(i) => i.advance_shallow(target),
+            Lucene50PostingIterEnum::SDoc(i) => i.advance_shallow(target),
+            Lucene50PostingIterEnum::Posting(i) => i.advance_shallow(target),
+            Lucene50PostingIterEnum::Everything(i) => i.advance_shallow(target),
+        }
+    }
+
+    fn impact(&self, up_to: DocId) -> Option<Impact> {
+        match &self.0 {
+            Lucene50PostingIterEnum::Doc(i) => i.impact(up_to),
+            Lucene50PostingIterEnum::SDoc(i) => i.impact(up_to),
+            Lucene50PostingIterEnum::Posting(i) => i.impact(up_to),
+            Lucene50PostingIterEnum::Everything(i) => i.impact(up_to),
+        }
+    }
 }
 
 impl DocIterator for Lucene50PostingIterator {
@@ -11,6 +11,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use core::codec::doc_values::NumericDocValues;
 use core::codec::field_infos::FieldInfo;
 use core::codec::postings::blocktree::BlockTermState;
 use core::codec::postings::for_util::*;
@@ -20,7 +21,7 @@ use core::codec::postings::skip_writer::Lucene50SkipWriter;
 use core::codec::postings::{PostingsWriterBase, VERSION_START};
 use core::codec::segment_infos::{segment_file_name, SegmentWriteState};
 use core::codec::{write_footer, write_index_header, Codec, TermIterator};
-use core::codec::{PostingIterator, PostingIteratorFlags};
+use core::codec::{Impact, PostingIterator, PostingIteratorFlags};
 use core::doc::IndexOptions;
 use core::index::writer::INDEX_MAX_POSITION;
 use core::search::{DocIterator, NO_MORE_DOCS};
@@ -88,6 +89,9 @@ pub struct Lucene50PostingsWriter<O: IndexOutput> {
     last_block_pay_fp: i64,
     last_block_pos_buffer_upto: usize,
     last_block_payload_byte_upto: usize,
+    // most competitive freq and norm of the current block
+    competitive_freq: i32,
+    competitive_norm: i64,
 
     last_doc_id: DocId,
     last_position: usize,
@@ -231,6 +235,8 @@ impl<O: IndexOutput> Lucene50PostingsWriter<O> {
             last_block_pay_fp: 0,
             last_block_pos_buffer_upto: 0,
             last_block_payload_byte_upto: 0,
+            competitive_freq: 0,
+            competitive_norm: 0,
 
             last_doc_id: 0,
             last_position: 0,
@@ -297,11 +303,15 @@ impl<O: IndexOutput> Lucene50PostingsWriter<O> {
         }
         self.last_doc_id = 0;
         self.last_block_doc_id = -1;
+        self.competitive_freq = 0;
+        self.competitive_norm = 0;
         self.skip_writer
             .reset_skip(self.doc_start_fp, self.pos_start_fp, self.pay_start_fp);
     }
 
-    pub fn start_doc(&mut self, doc_id: DocId, term_doc_freq: i32) -> Result<()> {
+    /// `norm` is the encoded norm of the doc for the field, or `Impact::MAX_NORM`
+    /// if it is unknown.
+    pub fn start_doc(&mut self, doc_id: DocId, term_doc_freq: i32, norm: i64) -> Result<()> {
         // Have collected a block of docs, and get a new doc.
         // Should write skip data as well as postings list for
         // current block.
@@ -314,8 +324,13 @@ impl<O: IndexOutput> Lucene50PostingsWriter<O> {
                 self.last_block_pos_buffer_upto,
                 self.last_block_payload_byte_upto,
                 self.doc_out.file_pointer(),
+                Impact::new(self.competitive_freq, self.competitive_norm),
             )?;
+            self.competitive_freq = 0;
+            self.competitive_norm = 0;
         }
+        self.competitive_freq = self.competitive_freq.max(term_doc_freq.max(1));
+        self.competitive_norm = self.competitive_norm.max(norm);
 
         let doc_delta = doc_id - self.last_doc_id;
 
@@ -625,6 +640,7 @@ impl<O: IndexOutput> PostingsWriterBase for Lucene50PostingsWriter<O> {
         docs_seen: &mut FixedBitSet,
         doc_freq_limit: i32,
         term_freq_limit: i32,
+        norms: Option<&dyn NumericDocValues>,
     ) -> Result<Option<BlockTermState>> {
         self.start_term();
         let mut postings_enum = terms.postings_with_flags(self.enum_flags)?;
@@ -644,7 +660,11 @@ impl<O: IndexOutput> PostingsWriterBase for Lucene50PostingsWriter<O> {
             } else {
                 -1
             };
-            self.start_doc(doc_id, freq)?;
+            let norm = match norms {
+                Some(norms) => norms.get(doc_id)? & Impact::MAX_NORM,
+                None => Impact::MAX_NORM,
+            };
+            self.start_doc(doc_id, freq, norm)?;
 
             if self.write_positions {
                 for _ in 0..freq {
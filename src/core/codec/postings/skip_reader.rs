@@ -15,6 +15,7 @@ use std::io;
 use std::io::Read;
 
 use core::codec::postings::posting_format::BLOCK_SIZE;
+use core::codec::Impact;
 use core::store::io::{DataInput, IndexInput, RandomAccessInput};
 use core::util::{log, DocId};
 
@@ -175,6 +176,13 @@ pub struct Lucene50SkipReader {
     last_payload_byte_upto: i32,
     last_doc_pointer: i64,
     last_pos_buffer_upto: i32,
+
+    /// whether the skip entries carry impacts, which is the case since
+    /// `VERSION_IMPACT_SKIP_DATA`
+    has_impacts: bool,
+    /// Most competitive freq and norm of current skip entry per level.
+    max_freq: Vec<i32>,
+    max_norm: Vec<i64>,
 }
 
 impl Lucene50SkipReader {
@@ -216,6 +224,10 @@ impl Lucene50SkipReader {
             last_payload_byte_upto: self.last_payload_byte_upto,
             last_doc_pointer: self.last_doc_pointer,
             last_pos_buffer_upto: self.last_pos_buffer_upto,
+
+            has_impacts: self.has_impacts,
+            max_freq: self.max_freq.clone(),
+            max_norm: self.max_norm.clone(),
         })
     }
 
@@ -225,6 +237,7 @@ impl Lucene50SkipReader {
         has_pos: bool,
         has_offsets: bool,
         has_payloads: bool,
+        has_impacts: bool,
     ) -> Lucene50SkipReader {
         // fields for MultiLevelSkipReader part
         let max_number_of_skip_levels = max_skip_levels;
@@ -295,6 +308,10 @@ impl Lucene50SkipReader {
             last_payload_byte_upto: 0,
             last_doc_pointer: 0,
             last_pos_buffer_upto: 0,
+
+            has_impacts,
+            max_freq: vec![0_i32; max_skip_levels],
+            max_norm: vec![0_i64; max_skip_levels],
         }
     }
 
@@ -381,6 +398,20 @@ impl Lucene50SkipReader {
         self.skip_doc[0]
     }
 
+    /// Returns the most competitive impact of the docs between the last
+    /// `skip_to()` target and `up_to`, taken from the lowest level whose current
+    /// skip entry covers `up_to`, or `None` if no such entry is loaded.
+    pub fn impact(&self, up_to: DocId) -> Option<Impact> {
+        if !self.has_impacts {
+            return None;
+        }
+        (0..self.number_of_skip_levels.max(0) as usize)
+            .find(|&level| {
+                self.skip_doc[level] >= up_to && self.skip_doc[level] != i32::max_value()
+            })
+            .map(|level| Impact::new(self.max_freq[level], self.max_norm[level]))
+    }
+
     /// Seeks the skip entry on the given level
     pub fn seek_child(&mut self, level: i32) -> Result<()> {
         let ulevel = level as usize;
@@ -449,6 +480,11 @@ impl Lucene50SkipReader {
                 self.pay_pointer.as_mut().unwrap()[level] += pointer;
             }
         }
+
+        if self.has_impacts {
+            self.max_freq[level] = self.stream(level)?.read_vint()?;
+            self.max_norm[level] = i64::from(self.stream(level)?.read_byte()?);
+        }
         Ok(delta)
     }
 
@@ -13,6 +13,7 @@
 
 use std::cmp::min;
 
+use core::codec::Impact;
 use core::store::io::{DataOutput, IndexOutput, RAMOutputStream};
 use core::util::{fill_slice, log, DocId};
 
@@ -37,11 +38,16 @@ const SKIP_MULTIPLIER: u32 = 8;
 /// 2. its related file points(position, payload),
 /// 3. related numbers or uptos(position, payload).
 /// 4. start offset.
+/// 5. the most competitive freq and norm of the docs since the previous skip
+///    point of the same level.
 pub struct Lucene50SkipWriter {
     last_skip_doc: Vec<i32>,
     last_skip_doc_pointer: Vec<i64>,
     last_skip_pos_pointer: Vec<i64>,
     last_skip_pay_pointer: Vec<i64>,
+    /// most competitive impact per level since the last skip point of the level
+    competitive_freq: Vec<i32>,
+    competitive_norm: Vec<i64>,
     // these three output must be parameter when called
     //    doc_out: Box<IndexOutput>,
     //    pos_out: Option<Box<IndexOutput>>,
@@ -106,6 +112,8 @@ impl Lucene50SkipWriter {
             last_skip_doc_pointer: vec![0i64; max_skip_levels],
             last_skip_pos_pointer,
             last_skip_pay_pointer,
+            competitive_freq: vec![0i32; max_skip_levels],
+            competitive_norm: vec![0i64; max_skip_levels],
             cur_doc: 0,
             cur_doc_pointer: 0,
             cur_pos_pointer: 0,
@@ -153,6 +161,8 @@ impl Lucene50SkipWriter {
             self.reset_skip_base();
             fill_slice(&mut self.last_skip_doc, 0);
             fill_slice(&mut self.last_skip_doc_pointer, self.last_doc_fp);
+            fill_slice(&mut self.competitive_freq, 0);
+            fill_slice(&mut self.competitive_norm, 0);
             if self.field_has_positions {
                 fill_slice(&mut self.last_skip_pos_pointer, self.last_pos_fp);
                 if self.field_has_offsets || self.field_has_payloads {
@@ -182,7 +192,8 @@ impl Lucene50SkipWriter {
         }
     }
 
-    /// Sets the values for the current skip data.
+    /// Sets the values for the current skip data, `impact` is the most
+    /// competitive impact of the block that ends with `doc`.
     #[allow(clippy::too_many_arguments)]
     pub fn buffer_skip(
         &mut self,
@@ -193,8 +204,18 @@ impl Lucene50SkipWriter {
         pos_buffer_upto: usize,
         payload_byte_upto: usize,
         doc_out_pointer: i64,
+        impact: Impact,
     ) -> Result<()> {
         self.init_skip();
+        let levels = self
+            .competitive_freq
+            .iter_mut()
+            .zip(self.competitive_norm.iter_mut())
+            .take(self.number_of_skip_levels);
+        for (freq, norm) in levels {
+            *freq = (*freq).max(impact.freq);
+            *norm = (*norm).max(impact.norm);
+        }
         self.cur_doc = doc;
         self.cur_doc_pointer = doc_out_pointer;
         self.cur_pos_pointer = pos_fp;
@@ -285,6 +306,11 @@ impl Lucene50SkipWriter {
             }
         }
 
+        self.skip_buffer[level].write_vint(self.competitive_freq[level])?;
+        self.skip_buffer[level].write_byte(self.competitive_norm[level] as u8)?;
+        self.competitive_freq[level] = 0;
+        self.competitive_norm[level] = 0;
+
         Ok(())
     }
 }
@@ -339,15 +339,22 @@ impl SegmentInfoFormat for Lucene62SegmentInfoFormat {
                     SortFieldType::Int => 2,
                     SortFieldType::Double => 3,
                     SortFieldType::Float => 4,
-                    SortFieldType::Custom => {
-                        match sort_field {
-                            // SortField::SortedSet(_) => 5,
-                            SortField::SortedNumeric(_) => 6,
-                            _ => {
-                                bail!(IllegalState("Unexpected SortedNumericSortField".into()));
-                            }
+                    SortFieldType::Custom => match sort_field {
+                        SortField::SortedSet(_) => {
+                            bail!(IllegalArgument(
+                                "index sort by SortedSetSortField is not supported".into()
+                            ));
                         }
-                    }
+                        SortField::SortedNumeric(_) => 6,
+                        SortField::Custom(_) => {
+                            bail!(IllegalArgument(
+                                "index sort by ExpressionSortField is not supported".into()
+                            ));
+                        }
+                        _ => {
+                            bail!(IllegalState("Unexpected SortedNumericSortField".into()));
+                        }
+                    },
                     _ => {
                         bail!(IllegalState(format!(
                             "Unexpected sort type: {:?}",
@@ -47,6 +47,8 @@ impl Sorter {
         match sort {
             SortField::Simple(s) => s.field_type(),
             SortField::SortedNumeric(s) => s.numeric_type(),
+            SortField::SortedSet(_) => SortFieldType::String,
+            SortField::Custom(_) => SortFieldType::Custom,
         }
     }
 
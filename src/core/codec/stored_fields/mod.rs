@@ -27,7 +27,7 @@ mod stored_fields_consumer;
 
 pub use self::stored_fields_consumer::*;
 
-use core::analysis::TokenStream;
+use core::analysis::{Analyzer, TokenStream};
 use core::codec::field_infos::{FieldInfo, FieldInfos};
 use core::codec::segment_infos::SegmentInfo;
 use core::codec::stored_fields::CompressingStoredFieldsWriter;
@@ -396,7 +396,7 @@ impl<S: StoredFieldsWriter> Fieldable for MergeVisitor<S> {
         debug_assert!(self.value.is_some());
         self.value.as_ref()
     }
-    fn token_stream(&mut self) -> Result<Box<dyn TokenStream>> {
+    fn token_stream(&mut self, _analyzer: &dyn Analyzer) -> Result<Box<dyn TokenStream>> {
         unreachable!()
     }
 
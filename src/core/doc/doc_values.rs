@@ -11,7 +11,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use core::analysis::{BinaryTokenStream, TokenStream};
+use core::analysis::{Analyzer, BinaryTokenStream, TokenStream};
 use core::doc::{
     Field, FieldType, Fieldable, BINARY_DOC_VALUES_FIELD_TYPE, NUMERIC_DOC_VALUES_FIELD_TYPE,
     SORTED_NUMERIC_DOC_VALUES_FIELD_TYPE, SORTED_SET_DOC_VALUES_FIELD_TYPE,
@@ -108,7 +108,7 @@ impl Fieldable for BinaryDocValuesField {
         self.field.field_data()
     }
 
-    fn token_stream(&mut self) -> Result<Box<dyn TokenStream>> {
+    fn token_stream(&mut self, _analyzer: &dyn Analyzer) -> Result<Box<dyn TokenStream>> {
         if let VariantValue::Binary(ref v) = self.field_data().unwrap() {
             Ok(Box::new(BinaryTokenStream::new(BytesRef::new(v.as_ref()))))
         } else {
@@ -171,6 +171,41 @@ impl DoubleDocValuesField {
     }
 }
 
+impl Fieldable for DoubleDocValuesField {
+    fn name(&self) -> &str {
+        self.field.name()
+    }
+
+    fn field_type(&self) -> &FieldType {
+        self.field.field_type()
+    }
+
+    fn boost(&self) -> f32 {
+        self.field.boost()
+    }
+
+    fn field_data(&self) -> Option<&VariantValue> {
+        self.field.field_data()
+    }
+
+    fn token_stream(&mut self, _analyzer: &dyn Analyzer) -> Result<Box<dyn TokenStream>> {
+        unreachable!()
+    }
+
+    fn binary_value(&self) -> Option<&[u8]> {
+        None
+    }
+
+    fn string_value(&self) -> Option<&str> {
+        None
+    }
+
+    fn numeric_value(&self) -> Option<Numeric> {
+        self.field_data()
+            .map(|v| Numeric::Double(v.get_double().unwrap()))
+    }
+}
+
 pub struct NumericDocValuesField {
     field: Field,
 }
@@ -209,7 +244,7 @@ impl Fieldable for NumericDocValuesField {
         self.field.field_data()
     }
 
-    fn token_stream(&mut self) -> Result<Box<dyn TokenStream>> {
+    fn token_stream(&mut self, _analyzer: &dyn Analyzer) -> Result<Box<dyn TokenStream>> {
         unreachable!()
     }
 
@@ -268,8 +303,8 @@ impl Fieldable for SortedNumericDocValuesField {
         self.field.field_data()
     }
 
-    fn token_stream(&mut self) -> Result<Box<dyn TokenStream>> {
-        self.field.token_stream()
+    fn token_stream(&mut self, analyzer: &dyn Analyzer) -> Result<Box<dyn TokenStream>> {
+        self.field.token_stream(analyzer)
     }
 
     fn binary_value(&self) -> Option<&[u8]> {
@@ -326,8 +361,8 @@ impl Fieldable for SortedSetDocValuesField {
         self.field.field_data()
     }
 
-    fn token_stream(&mut self) -> Result<Box<dyn TokenStream>> {
-        self.field.token_stream()
+    fn token_stream(&mut self, analyzer: &dyn Analyzer) -> Result<Box<dyn TokenStream>> {
+        self.field.token_stream(analyzer)
     }
 
     fn binary_value(&self) -> Option<&[u8]> {
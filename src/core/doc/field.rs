@@ -11,7 +11,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use core::analysis::{BinaryTokenStream, StringTokenStream, TokenStream};
+use core::analysis::{Analyzer, BinaryTokenStream, StringTokenStream, TokenStream};
 use core::doc::{DocValuesType, IndexOptions};
 use core::util::{ByteBlockPool, BytesRef, Numeric, VariantValue};
 
@@ -41,7 +41,11 @@ impl Field {
                     let bytes = b[..(ByteBlockPool::BYTE_BLOCK_SIZE - 2).min(b.len())].to_vec();
                     Some(VariantValue::Binary(bytes))
                 }
-                VariantValue::VString(vs) => {
+                // tokenized text is analyzed into terms, so only single term values
+                // need to be truncated
+                VariantValue::VString(vs)
+                    if !field_type.tokenized || field_type.index_options == IndexOptions::Null =>
+                {
                     let mut index = (ByteBlockPool::BYTE_BLOCK_SIZE - 2).min(vs.len());
                     while !vs.is_char_boundary(index) {
                         index -= 1;
@@ -102,7 +106,7 @@ impl Fieldable for Field {
     }
 
     // TODO currently this function should only be called once per doc field
-    fn token_stream(&mut self) -> Result<Box<dyn TokenStream>> {
+    fn token_stream(&mut self, analyzer: &dyn Analyzer) -> Result<Box<dyn TokenStream>> {
         if self.token_stream.is_some() {
             return Ok(self.token_stream.take().unwrap());
         }
@@ -123,6 +127,8 @@ impl Fieldable for Field {
                     )),
                 }
             }
+        } else if let Some(VariantValue::VString(ref s)) = self.field_data {
+            return analyzer.token_stream_for_str(&self.field_name, s);
         }
 
         bail!(ErrorKind::IllegalArgument(
@@ -165,7 +171,7 @@ pub trait Fieldable {
     fn field_type(&self) -> &FieldType;
     fn boost(&self) -> f32;
     fn field_data(&self) -> Option<&VariantValue>;
-    fn token_stream(&mut self) -> Result<Box<dyn TokenStream>>;
+    fn token_stream(&mut self, analyzer: &dyn Analyzer) -> Result<Box<dyn TokenStream>>;
     fn binary_value(&self) -> Option<&[u8]>;
     fn string_value(&self) -> Option<&str>;
     fn numeric_value(&self) -> Option<Numeric>;
@@ -184,8 +190,8 @@ impl<T: Fieldable + ?Sized> Fieldable for Box<T> {
     fn field_data(&self) -> Option<&VariantValue> {
         (**self).field_data()
     }
-    fn token_stream(&mut self) -> Result<Box<dyn TokenStream>> {
-        (**self).token_stream()
+    fn token_stream(&mut self, analyzer: &dyn Analyzer) -> Result<Box<dyn TokenStream>> {
+        (**self).token_stream(analyzer)
     }
     fn binary_value(&self) -> Option<&[u8]> {
         (**self).binary_value()
@@ -13,10 +13,10 @@
 
 use core::codec::doc_values::{DocValuesConsumer, DocValuesFormat};
 use core::codec::field_infos::{FieldInfosBuilder, FieldInfosFormat, FieldNumbersRef};
-use core::codec::norms::{NormsConsumer, NormsFormat};
+use core::codec::norms::{NormsConsumer, NormsFormat, NormsProducer};
 use core::codec::points::{PointsFormat, PointsWriter};
 use core::codec::postings::{FieldsConsumer, PostingsFormat};
-use core::codec::segment_infos::{SegmentInfo, SegmentWriteState};
+use core::codec::segment_infos::{SegmentInfo, SegmentReadState, SegmentWriteState};
 use core::codec::stored_fields::{StoredFieldsFormat, StoredFieldsWriter};
 use core::codec::term_vectors::{TermVectorsFormat, TermVectorsWriter};
 use core::codec::Codec;
@@ -100,36 +100,37 @@ where
             self.context,
             "".into(),
         );
-        self.merge_terms(&segment_write_state)?;
-
+        // norms are merged first so that the postings can record their impacts
         if self
             .merge_state
             .merge_field_infos
             .as_ref()
             .unwrap()
-            .has_doc_values
+            .has_norms
         {
-            self.merge_doc_values(&segment_write_state)?;
+            self.merge_norms(&segment_write_state)?;
         }
+        self.merge_terms(&segment_write_state)?;
+
         if self
             .merge_state
             .merge_field_infos
             .as_ref()
             .unwrap()
-            .has_point_values
+            .has_doc_values
         {
-            self.merge_points(&segment_write_state)?;
+            self.merge_doc_values(&segment_write_state)?;
         }
-
         if self
             .merge_state
             .merge_field_infos
             .as_ref()
             .unwrap()
-            .has_norms
+            .has_point_values
         {
-            self.merge_norms(&segment_write_state)?;
+            self.merge_points(&segment_write_state)?;
         }
+
         if self
             .merge_state
             .merge_field_infos
@@ -225,10 +226,25 @@ where
     }
 
     fn merge_terms(&mut self, segment_write_state: &SegmentWriteState<D, DW, C>) -> Result<()> {
+        let norms = if segment_write_state.field_infos.has_norms {
+            let read_state = SegmentReadState::new(
+                Arc::clone(&self.directory),
+                &segment_write_state.segment_info,
+                Arc::new(segment_write_state.field_infos.clone()),
+                &IOContext::READ,
+                segment_write_state.segment_suffix.clone(),
+            );
+            Some(self.codec.norms_format().norms_producer(&read_state)?)
+        } else {
+            None
+        };
         let mut consumer = self
             .codec
             .postings_format()
             .fields_consumer(segment_write_state)?;
-        consumer.merge(&mut self.merge_state)
+        consumer.merge(
+            &mut self.merge_state,
+            norms.as_ref().map(|n| n as &dyn NormsProducer),
+        )
     }
 }
@@ -29,6 +29,7 @@ use core::search::sort_field::Sort;
 use core::search::{DocIterator, Payload, NO_MORE_DOCS};
 use core::store::directory::Directory;
 use core::store::io::{DataInput, IndexInput, IndexOutput, RAMOutputStream};
+use core::util::automaton::CompiledAutomaton;
 use core::util::external::Deferred;
 use core::util::fst::{BytesStore, StoreBytesReader};
 use core::util::{Bits, BitsMut, BitsRef, DocId};
@@ -551,6 +552,15 @@ impl<T: Terms> Terms for SortingTerms<T> {
         ))
     }
 
+    fn intersect(&self, compiled: &CompiledAutomaton) -> Result<Self::Iterator> {
+        Ok(SortingTermsIterator::new(
+            self.terms.intersect(compiled)?,
+            Arc::clone(&self.doc_map),
+            self.index_options,
+            self.has_positions()?,
+        ))
+    }
+
     fn size(&self) -> Result<i64> {
         self.terms.size()
     }
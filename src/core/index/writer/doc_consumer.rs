@@ -11,7 +11,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use core::analysis::TokenStream;
+use core::analysis::{Analyzer, TokenStream};
 use core::codec::doc_values::*;
 use core::codec::field_infos::{
     FieldInfo, FieldInfosBuilder, FieldInfosFormat, FieldInvertState, FieldNumbersRef,
@@ -46,6 +46,8 @@ use core::index::merge::MergeScheduler;
 use std::borrow::Cow;
 use std::cmp::Ordering;
 use std::collections::{BTreeMap, HashSet};
+use std::io::Read;
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
 use std::sync::Arc;
 
 const MAX_FIELD_COUNT: usize = 65536;
@@ -874,7 +876,7 @@ impl<T: TermsHashPerField> PerField<T> {
                 .omit_norms = true;
         }
 
-        // let analyzed = field.field_type().tokenized() && doc_state.analyzer.is_some();
+        let analyzer = TrackingAnalyzer::new(doc_state.analyzer.as_ref());
         // only bother checking offsets if something will consume them.
         // TODO: after we fix analyzers, also check if termVectorOffsets will be indexed.
         let check_offset = index_options == IndexOptions::DocsAndFreqsAndPositionsAndOffsets;
@@ -883,7 +885,7 @@ impl<T: TermsHashPerField> PerField<T> {
         // write the field name to the infostream when we fail. We expect some caller to
         // eventually deal with the real exception, so we don't want any 'catch' clauses,
         // but rather a finally that takes note of the problem.
-        let mut token_stream: Box<dyn TokenStream> = field.token_stream()?;
+        let mut token_stream: Box<dyn TokenStream> = field.token_stream(&analyzer)?;
         token_stream.reset()?;
 
         self.term_hash_per_field
@@ -965,10 +967,13 @@ impl<T: TermsHashPerField> PerField<T> {
         self.invert_state.position += token_stream.token().position as i32;
         self.invert_state.offset += token_stream.token().end_offset;
 
-        //        if analyzed {
-        //            self.invert_state.position += doc_state.analyzer.get_position_increment_gap();
-        //            self.invert_state.offset += doc_state.analyzer.get_offset_gap();
-        //        }
+        // the gaps of the analyzer don't apply to pre-built token streams
+        if field.field_type().tokenized() && analyzer.used() {
+            let position_increment_gap = analyzer.position_increment_gap(&self.field_info().name);
+            let offset_gap = analyzer.offset_gap(&self.field_info().name);
+            self.invert_state.position += position_increment_gap as i32;
+            self.invert_state.offset += offset_gap;
+        }
 
         self.invert_state.boost *= field.boost();
 
@@ -978,6 +983,59 @@ impl<T: TermsHashPerField> PerField<T> {
 
 impl<T: TermsHashPerField> Eq for PerField<T> {}
 
+/// Forwards to the analyzer of the writer and records whether it built the
+/// token stream of a field.
+struct TrackingAnalyzer<'a> {
+    analyzer: &'a dyn Analyzer,
+    used: AtomicBool,
+}
+
+impl<'a> TrackingAnalyzer<'a> {
+    fn new(analyzer: &'a dyn Analyzer) -> Self {
+        TrackingAnalyzer {
+            analyzer,
+            used: AtomicBool::new(false),
+        }
+    }
+
+    fn used(&self) -> bool {
+        self.used.load(AtomicOrdering::Acquire)
+    }
+}
+
+impl<'a> Analyzer for TrackingAnalyzer<'a> {
+    fn create_components(
+        &self,
+        field_name: &str,
+        reader: Box<dyn Read>,
+    ) -> Result<Box<dyn TokenStream>> {
+        self.used.store(true, AtomicOrdering::Release);
+        self.analyzer.create_components(field_name, reader)
+    }
+
+    fn token_stream(
+        &self,
+        field_name: &str,
+        reader: Box<dyn Read>,
+    ) -> Result<Box<dyn TokenStream>> {
+        self.used.store(true, AtomicOrdering::Release);
+        self.analyzer.token_stream(field_name, reader)
+    }
+
+    fn token_stream_for_str(&self, field_name: &str, text: &str) -> Result<Box<dyn TokenStream>> {
+        self.used.store(true, AtomicOrdering::Release);
+        self.analyzer.token_stream_for_str(field_name, text)
+    }
+
+    fn position_increment_gap(&self, field_name: &str) -> usize {
+        self.analyzer.position_increment_gap(field_name)
+    }
+
+    fn offset_gap(&self, field_name: &str) -> usize {
+        self.analyzer.offset_gap(field_name)
+    }
+}
+
 impl<T: TermsHashPerField> PartialEq for PerField<T> {
     fn eq(&self, other: &Self) -> bool {
         self.field_info().name.eq(&other.field_info().name)
@@ -34,6 +34,7 @@ use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex, MutexGuard, RwLock, RwLockWriteGuard, Weak};
 use std::time::SystemTime;
 
+use core::analysis::Analyzer;
 use core::codec::{PackedLongDocMap, SorterDocMap};
 use core::util::external::Volatile;
 use core::util::Bits;
@@ -43,17 +44,19 @@ use error::Result;
 use std::mem::MaybeUninit;
 use std::ptr;
 
-#[derive(Default)]
 pub struct DocState {
-    // analyzer: Analyzer,  // TODO, current Analyzer is not implemented
+    pub analyzer: Arc<dyn Analyzer>,
     // pub similarity: Option<Box<Similarity>>,
     pub doc_id: DocId,
     // pub doc: Vec<Box<dyn Fieldable>>,
 }
 
 impl DocState {
-    pub fn new() -> Self {
-        Default::default()
+    pub fn new(analyzer: Arc<dyn Analyzer>) -> Self {
+        DocState {
+            analyzer,
+            doc_id: 0,
+        }
     }
     pub fn clear(&mut self) {
         // self.doc = Vec::with_capacity(0);
@@ -136,7 +139,7 @@ where
             writer.config.index_sort().map(|s| s.clone()),
         )?;
         let delete_slice = delete_queue.new_slice();
-        let doc_state = DocState::new();
+        let doc_state = DocState::new(Arc::clone(&index_writer_config.analyzer));
         // doc_state.similarity = Some(index_writer_config.similarity());
         Ok(DocumentsWriterPerThread {
             directory,
@@ -203,7 +206,6 @@ where
         self.reserve_one_doc()?;
         // self.doc_state.doc = doc;
         self.doc_state.doc_id = self.num_docs_in_ram as i32;
-        // self.doc_state.analyzer = analyzer;
 
         // Even on exception, the document is still added (but marked
         // deleted), so we don't need to un-reserve at that point.
@@ -1205,6 +1205,7 @@ where
         segment_infos: &SegmentInfos<D, C>,
     ) -> Result<()> {
         if let Some(index_sort) = config.index_sort() {
+            IndexWriterConfig::<C, MS1, MP1>::check_index_sort(index_sort)?;
             for info in &segment_infos.segments {
                 if let Some(segment_sort) = info.info.index_sort() {
                     if segment_sort != index_sort {
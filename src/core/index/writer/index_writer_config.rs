@@ -11,12 +11,16 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use core::analysis::{Analyzer, WhitespaceAnalyzer};
 use core::codec::{Codec, CodecEnum, Lucene62Codec};
 use core::index::merge::MergeScheduler;
 use core::index::merge::SerialMergeScheduler;
 use core::index::merge::{MergePolicy, TieredMergePolicy};
 use core::index::writer::KeepOnlyLastCommitDeletionPolicy;
-use core::search::sort_field::Sort;
+use core::search::sort_field::{Sort, SortField};
+
+use error::ErrorKind::IllegalArgument;
+use error::Result;
 
 use std::sync::Arc;
 
@@ -70,6 +74,9 @@ pub struct IndexWriterConfig<C: Codec, MS: MergeScheduler, MP: MergePolicy> {
     pub open_mode: OpenMode,
     pub codec: Arc<C>,
     pub commit_on_close: bool,
+    /// The analyzer used to tokenize text fields that are added without a
+    /// pre-built `TokenStream`, `WhitespaceAnalyzer` by default.
+    pub analyzer: Arc<dyn Analyzer>,
 }
 
 impl Default for IndexWriterConfig<CodecEnum, SerialMergeScheduler, TieredMergePolicy> {
@@ -95,6 +102,7 @@ impl<C: Codec, MS: MergeScheduler, MP: MergePolicy> IndexWriterConfig<C, MS, MP>
             open_mode: OpenMode::CreateOrAppend,
             codec,
             commit_on_close: true,
+            analyzer: Arc::new(WhitespaceAnalyzer),
         }
     }
 
@@ -122,6 +130,26 @@ impl<C: Codec, MS: MergeScheduler, MP: MergePolicy> IndexWriterConfig<C, MS, MP>
         self.index_sort.as_ref()
     }
 
+    /// Sets the sort order of the documents of every new segment, fails if
+    /// one of its fields can't sort an index.
+    pub fn set_index_sort(&mut self, sort: Sort) -> Result<()> {
+        Self::check_index_sort(&sort)?;
+        self.index_sort = Some(sort);
+        Ok(())
+    }
+
+    pub(crate) fn check_index_sort(sort: &Sort) -> Result<()> {
+        for sort_field in sort.get_sort() {
+            if let SortField::SortedSet(_) = sort_field {
+                bail!(IllegalArgument(format!(
+                    "index sort by SortedSetSortField is not supported, field: {}",
+                    sort_field.field()
+                )));
+            }
+        }
+        Ok(())
+    }
+
     pub fn index_deletion_policy(&self) -> KeepOnlyLastCommitDeletionPolicy {
         KeepOnlyLastCommitDeletionPolicy::default()
     }
@@ -133,4 +161,41 @@ impl<C: Codec, MS: MergeScheduler, MP: MergePolicy> IndexWriterConfig<C, MS, MP>
     pub fn codec(&self) -> &C {
         self.codec.as_ref()
     }
+
+    pub fn analyzer(&self) -> &dyn Analyzer {
+        self.analyzer.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate tempfile;
+
+    use super::*;
+    use core::index::writer::IndexWriter;
+    use core::search::sort_field::{SimpleSortField, SortFieldType, SortedSetSortField};
+    use core::store::directory::FSDirectory;
+
+    #[test]
+    fn test_index_sort_by_sorted_set() {
+        let sorted_set = SortField::SortedSet(SortedSetSortField::with_field("tags".into()));
+        let simple = SortField::Simple(SimpleSortField::new(
+            "id".into(),
+            SortFieldType::Long,
+            false,
+        ));
+
+        let mut config = IndexWriterConfig::default();
+        assert!(config
+            .set_index_sort(Sort::new(vec![simple.clone(), sorted_set.clone()]))
+            .is_err());
+        assert!(config.index_sort().is_none());
+        assert!(config.set_index_sort(Sort::new(vec![simple])).is_ok());
+
+        // the field can also be set directly, the writer checks it again
+        config.index_sort = Some(Sort::new(vec![sorted_set]));
+        let temp_dir = tempfile::tempdir().unwrap();
+        let directory = Arc::new(FSDirectory::with_path(temp_dir.path()).unwrap());
+        assert!(IndexWriter::new(directory, Arc::new(config)).is_err());
+    }
 }
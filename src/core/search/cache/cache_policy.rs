@@ -15,7 +15,7 @@ use std::cmp::max;
 use std::sync::Mutex;
 
 use core::codec::Codec;
-use core::search::query::{Weight, CONSTANT, MATCH_ALL, POINT_RANGE, TERM};
+use core::search::query::{Weight, CONSTANT, MATCH_ALL, MULTI_TERM, POINT_RANGE, TERM};
 
 use error::Result;
 
@@ -71,8 +71,8 @@ impl UsageTrackingQueryCachingPolicy {
     }
 
     fn is_costly<C: Codec>(w: &dyn Weight<C>) -> bool {
-        // TODO currently only PointRangeQuery is costly
-        w.actual_query_type() == POINT_RANGE
+        let query_type = w.actual_query_type();
+        query_type == POINT_RANGE || query_type == MULTI_TERM
     }
 
     fn is_cheap<C: Codec>(w: &dyn Weight<C>) -> bool {
@@ -0,0 +1,413 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashSet;
+use std::f32;
+use std::mem;
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use core::codec::Codec;
+use core::index::reader::LeafReaderContext;
+use core::search::collector::grouping::{GroupFieldType, GroupValueSource, TopGroupHeads};
+use core::search::collector::{Collector, ParallelLeafCollector, SearchCollector};
+use core::search::scorer::Scorer;
+use core::search::sort_field::{CollapseTopFieldDocs, FieldDoc, ScoreDocHit, Sort, TopDocs};
+use core::util::{DocId, VariantValue};
+use error::{
+    ErrorKind::{IllegalArgument, IllegalState},
+    Result, ResultExt,
+};
+
+struct CollapsingBaseCollector {
+    source: GroupValueSource,
+    heads: TopGroupHeads,
+    /// Every collapse value seen so far.
+    groups: HashSet<VariantValue>,
+    needs_scores: bool,
+    total_hits: usize,
+    max_score: f32,
+}
+
+impl CollapsingBaseCollector {
+    fn new(
+        field: String,
+        field_type: GroupFieldType,
+        sort: &Sort,
+        num_groups: usize,
+        track_max_score: bool,
+    ) -> Self {
+        CollapsingBaseCollector {
+            source: GroupValueSource::new(field, field_type),
+            heads: TopGroupHeads::new(sort, num_groups),
+            groups: HashSet::new(),
+            needs_scores: track_max_score || sort.needs_scores(),
+            total_hits: 0,
+            max_score: f32::NEG_INFINITY,
+        }
+    }
+
+    fn set_next_reader<C: Codec>(&mut self, reader: &LeafReaderContext<'_, C>) -> Result<()> {
+        self.source.set_next_reader(reader)?;
+        self.heads.set_next_reader(reader)
+    }
+}
+
+impl Collector for CollapsingBaseCollector {
+    fn needs_scores(&self) -> bool {
+        self.needs_scores
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
+        self.total_hits += 1;
+        let score = if self.needs_scores {
+            let score = scorer.score()?;
+            if score > self.max_score {
+                self.max_score = score;
+            }
+            score
+        } else {
+            f32::NAN
+        };
+        let value = self.source.value(doc)?;
+        if !self.groups.contains(&value) {
+            self.groups.insert(value.clone());
+        }
+        if self.heads.is_competitive(doc, score)? {
+            self.heads.add(doc, score, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// A `Collector` that collapses the hits on the value of a doc values field,
+/// e.g. to show a single hit per author, and returns them as
+/// `TopDocs::Collapse`.
+///
+/// Only the top hit of each group by the given `Sort` is kept, and the top
+/// groups are returned in the order of their top hits along with their
+/// collapse values. `total_groups` is the number of distinct collapse values
+/// of all the hits.
+pub struct CollapsingTopDocsCollector {
+    sort: Sort,
+    base: CollapsingBaseCollector,
+    /// The group heads collected by the leaf collectors of a parallel search.
+    leaf_docs: Vec<(FieldDoc, VariantValue)>,
+    channel: Option<(Sender<LeafCollapseDocs>, Receiver<LeafCollapseDocs>)>,
+}
+
+impl CollapsingTopDocsCollector {
+    /// Creates a collector that collapses on a `NumericDocValues` field,
+    /// documents without a value are collapsed on 0.
+    pub fn create_numeric(
+        collapse_field: String,
+        sort: Sort,
+        top_n: usize,
+        track_max_score: bool,
+    ) -> Result<Self> {
+        Self::build(
+            collapse_field,
+            GroupFieldType::Numeric,
+            sort,
+            top_n,
+            track_max_score,
+        )
+    }
+
+    /// Creates a collector that collapses on a `SortedDocValues` field,
+    /// documents without a value are collapsed on an empty binary.
+    pub fn create_keyword(
+        collapse_field: String,
+        sort: Sort,
+        top_n: usize,
+        track_max_score: bool,
+    ) -> Result<Self> {
+        Self::build(
+            collapse_field,
+            GroupFieldType::Keyword,
+            sort,
+            top_n,
+            track_max_score,
+        )
+    }
+
+    fn build(
+        field: String,
+        field_type: GroupFieldType,
+        sort: Sort,
+        top_n: usize,
+        track_max_score: bool,
+    ) -> Result<Self> {
+        if top_n == 0 {
+            bail!(IllegalArgument("top_n must be > 0".into()));
+        }
+        if sort.get_sort().is_empty() {
+            bail!(IllegalArgument(
+                "sort must contain at least one field".into()
+            ));
+        }
+        let base = CollapsingBaseCollector::new(field, field_type, &sort, top_n, track_max_score);
+        Ok(CollapsingTopDocsCollector {
+            sort,
+            base,
+            leaf_docs: vec![],
+            channel: None,
+        })
+    }
+
+    /// Returns the top groups that were collected by this collector.
+    pub fn top_docs(&mut self) -> TopDocs {
+        let mut docs = self.base.heads.top_heads();
+        if !self.leaf_docs.is_empty() {
+            docs.append(&mut self.leaf_docs);
+            docs = self.base.heads.merge(docs);
+        }
+        let max_score = if self.base.needs_scores && self.base.total_hits > 0 {
+            self.base.max_score
+        } else {
+            f32::NAN
+        };
+        let (score_docs, collapse_values) = docs
+            .into_iter()
+            .map(|(doc, value)| (ScoreDocHit::Field(doc), value))
+            .unzip();
+        TopDocs::Collapse(CollapseTopFieldDocs::new(
+            self.base.source.field().to_string(),
+            self.base.total_hits,
+            self.base.groups.len(),
+            score_docs,
+            self.sort.get_sort().to_vec(),
+            collapse_values,
+            max_score,
+        ))
+    }
+}
+
+impl SearchCollector for CollapsingTopDocsCollector {
+    type LC = CollapsingLeafCollector;
+
+    fn set_next_reader<C: Codec>(&mut self, reader: &LeafReaderContext<'_, C>) -> Result<()> {
+        self.base.set_next_reader(reader)
+    }
+
+    fn support_parallel(&self) -> bool {
+        true
+    }
+
+    fn init_parallel(&mut self) {
+        if self.channel.is_none() {
+            self.channel = Some(channel());
+        }
+    }
+
+    fn leaf_collector<C: Codec>(
+        &self,
+        reader: &LeafReaderContext<'_, C>,
+    ) -> Result<CollapsingLeafCollector> {
+        let mut collector = CollapsingBaseCollector::new(
+            self.base.source.field().to_string(),
+            self.base.source.field_type(),
+            &self.sort,
+            self.base.heads.num_groups,
+            self.base.needs_scores,
+        );
+        collector.set_next_reader(reader)?;
+        Ok(CollapsingLeafCollector {
+            collector,
+            channel: self.channel.as_ref().unwrap().0.clone(),
+        })
+    }
+
+    fn finish_parallel(&mut self) -> Result<()> {
+        let channel = self.channel.take();
+        // iff all the `weight.create_scorer(leaf_reader)` return None, the channel won't
+        // inited and thus stay None
+        if let Some((sender, receiver)) = channel {
+            drop(sender);
+            while let Ok(docs) = receiver.recv() {
+                self.base.total_hits += docs.total_hits;
+                if docs.max_score > self.base.max_score {
+                    self.base.max_score = docs.max_score;
+                }
+                self.base.groups.extend(docs.groups);
+                self.leaf_docs.extend(docs.docs);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Collector for CollapsingTopDocsCollector {
+    fn needs_scores(&self) -> bool {
+        self.base.needs_scores
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
+        self.base.collect(doc, scorer)
+    }
+}
+
+struct LeafCollapseDocs {
+    docs: Vec<(FieldDoc, VariantValue)>,
+    /// All the collapse values of the leaf, to count the groups.
+    groups: HashSet<VariantValue>,
+    total_hits: usize,
+    max_score: f32,
+}
+
+pub struct CollapsingLeafCollector {
+    collector: CollapsingBaseCollector,
+    channel: Sender<LeafCollapseDocs>,
+}
+
+impl ParallelLeafCollector for CollapsingLeafCollector {
+    fn finish_leaf(&mut self) -> Result<()> {
+        let top_docs = LeafCollapseDocs {
+            docs: self.collector.heads.top_heads(),
+            groups: mem::replace(&mut self.collector.groups, HashSet::new()),
+            total_hits: self.collector.total_hits,
+            max_score: self.collector.max_score,
+        };
+        self.channel
+            .send(top_docs)
+            .chain_err(|| IllegalState("channel unexpected closed before search complete".into()))
+    }
+}
+
+impl Collector for CollapsingLeafCollector {
+    fn needs_scores(&self) -> bool {
+        self.collector.needs_scores
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
+        self.collector.collect(doc, scorer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate tempfile;
+
+    use super::*;
+    use core::doc::{Field, Fieldable, NumericDocValuesField, SORTED_DOC_VALUES_FIELD_TYPE};
+    use core::index::writer::{IndexWriter, IndexWriterConfig};
+    use core::search::query::MatchAllDocsQuery;
+    use core::search::sort_field::{SimpleSortField, SortField, SortFieldType};
+    use core::search::{DefaultIndexSearcher, IndexSearcher};
+    use core::store::directory::FSDirectory;
+
+    use std::sync::Arc;
+
+    /// Returns the expected (doc, collapse value) of the top `top_n` groups,
+    /// hits sort by descending price and then by doc id.
+    fn expected_groups(
+        hits: &[(DocId, VariantValue, i64)],
+        top_n: usize,
+    ) -> Vec<(DocId, VariantValue)> {
+        let mut hits = hits.to_vec();
+        hits.sort_by(|a, b| b.2.cmp(&a.2).then(a.0.cmp(&b.0)));
+        let mut seen = HashSet::new();
+        hits.into_iter()
+            .filter(|h| seen.insert(h.1.clone()))
+            .take(top_n)
+            .map(|h| (h.0, h.1))
+            .collect()
+    }
+
+    fn collapsed_groups(top_docs: &TopDocs) -> Vec<(DocId, VariantValue)> {
+        match top_docs {
+            TopDocs::Collapse(c) => c
+                .score_docs
+                .iter()
+                .map(|hit| hit.doc_id())
+                .zip(c.collapse_values.iter().cloned())
+                .collect(),
+            _ => panic!("expected collapse top docs"),
+        }
+    }
+
+    #[test]
+    fn test_collapsing_top_docs_collector() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let directory = Arc::new(FSDirectory::with_path(temp_dir.path()).unwrap());
+        let writer = IndexWriter::new(directory, Arc::new(IndexWriterConfig::default())).unwrap();
+
+        // (doc, group, price) and (doc, author, price), every fifth doc has no author
+        let mut by_group = vec![];
+        let mut by_author = vec![];
+        for i in 0..80 {
+            let price = i64::from(i * 37 % 50);
+            let mut doc: Vec<Box<dyn Fieldable>> = vec![
+                Box::new(NumericDocValuesField::new("group", i64::from(i % 9))),
+                Box::new(NumericDocValuesField::new("price", price)),
+            ];
+            let author = if i % 5 == 0 {
+                vec![]
+            } else {
+                format!("author{}", i % 7).into_bytes()
+            };
+            if !author.is_empty() {
+                doc.push(Box::new(Field::new(
+                    "author".into(),
+                    SORTED_DOC_VALUES_FIELD_TYPE,
+                    Some(VariantValue::Binary(author.clone())),
+                    None,
+                )));
+            }
+            writer.add_document(doc).unwrap();
+            by_group.push((i, VariantValue::Long(i64::from(i % 9)), price));
+            by_author.push((i, VariantValue::Binary(author), price));
+            if i == 30 {
+                writer.commit().unwrap();
+            }
+        }
+        writer.commit().unwrap();
+
+        let reader = writer.get_reader(true, false).unwrap();
+        let searcher = DefaultIndexSearcher::new(Arc::new(reader), None);
+        let sort = Sort::new(vec![SortField::Simple(SimpleSortField::new(
+            "price".into(),
+            SortFieldType::Long,
+            true,
+        ))]);
+
+        let mut collector =
+            CollapsingTopDocsCollector::create_numeric("group".into(), sort.clone(), 5, false)
+                .unwrap();
+        searcher.search(&MatchAllDocsQuery, &mut collector).unwrap();
+        let top_docs = collector.top_docs();
+        assert_eq!(top_docs.total_hits(), 80);
+        assert_eq!(top_docs.total_groups(), 9);
+        assert_eq!(collapsed_groups(&top_docs), expected_groups(&by_group, 5));
+
+        let mut collector =
+            CollapsingTopDocsCollector::create_keyword("author".into(), sort.clone(), 20, false)
+                .unwrap();
+        searcher.search(&MatchAllDocsQuery, &mut collector).unwrap();
+        let top_docs = collector.top_docs();
+        assert_eq!(top_docs.total_hits(), 80);
+        // seven authors and the docs without one
+        assert_eq!(top_docs.total_groups(), 8);
+        assert_eq!(collapsed_groups(&top_docs), expected_groups(&by_author, 20));
+
+        let mut collector =
+            CollapsingTopDocsCollector::create_keyword("group".into(), sort.clone(), 5, false)
+                .unwrap();
+        // the searcher skips the leaves the collector fails on
+        searcher.search(&MatchAllDocsQuery, &mut collector).unwrap();
+        assert_eq!(collector.top_docs().total_hits(), 0);
+        assert!(
+            CollapsingTopDocsCollector::create_numeric("group".into(), sort, 0, false).is_err()
+        );
+    }
+}
@@ -0,0 +1,1034 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::f32;
+use std::mem;
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use core::codec::doc_values::{NumericDocValues, SortedDocValues};
+use core::codec::Codec;
+use core::doc::DocValuesType;
+use core::index::reader::LeafReaderContext;
+use core::search::collector::top_field::TopFieldBaseCollector;
+use core::search::collector::{Collector, ParallelLeafCollector, SearchCollector};
+use core::search::scorer::Scorer;
+use core::search::sort_field::{
+    CollectedSearchGroup, ComparatorValue, FieldComparator, FieldComparatorEnum, FieldDoc,
+    GroupDocs, ScoreDocHit, Sort, SortFieldType, SortInfo, TopGroups, WilsonInfo,
+};
+use core::util::{DocId, VariantValue};
+use error::{
+    ErrorKind::{IllegalArgument, IllegalState},
+    Result, ResultExt,
+};
+
+/// The kind of doc values the hits are grouped by.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GroupFieldType {
+    /// A `NumericDocValues` field, documents without a value are in the
+    /// group of 0.
+    Numeric,
+    /// A `SortedDocValues` field, documents without a value are in the group
+    /// of an empty binary.
+    Keyword,
+}
+
+enum GroupDocValues {
+    Numeric(Box<dyn NumericDocValues>),
+    Keyword(Box<dyn SortedDocValues>),
+    /// The field has no doc values in the current leaf.
+    Empty,
+}
+
+/// Reads the group value of the documents of the current leaf.
+pub(crate) struct GroupValueSource {
+    field: String,
+    field_type: GroupFieldType,
+    doc_values: GroupDocValues,
+}
+
+impl GroupValueSource {
+    pub(crate) fn new(field: String, field_type: GroupFieldType) -> Self {
+        GroupValueSource {
+            field,
+            field_type,
+            doc_values: GroupDocValues::Empty,
+        }
+    }
+
+    pub(crate) fn field(&self) -> &str {
+        &self.field
+    }
+
+    pub(crate) fn field_type(&self) -> GroupFieldType {
+        self.field_type
+    }
+
+    pub(crate) fn set_next_reader<C: Codec>(
+        &mut self,
+        reader: &LeafReaderContext<'_, C>,
+    ) -> Result<()> {
+        let doc_values_type = match reader.reader.field_info(&self.field) {
+            Some(field_info) => field_info.doc_values_type,
+            None => DocValuesType::Null,
+        };
+        self.doc_values = match (self.field_type, doc_values_type) {
+            (_, DocValuesType::Null) => GroupDocValues::Empty,
+            (GroupFieldType::Numeric, DocValuesType::Numeric) => {
+                GroupDocValues::Numeric(reader.reader.get_numeric_doc_values(&self.field)?)
+            }
+            (GroupFieldType::Keyword, DocValuesType::Sorted) => {
+                GroupDocValues::Keyword(reader.reader.get_sorted_doc_values(&self.field)?)
+            }
+            (field_type, doc_values_type) => bail!(IllegalArgument(format!(
+                "can't group by {:?} field '{}' with {:?} doc values",
+                field_type, self.field, doc_values_type
+            ))),
+        };
+        Ok(())
+    }
+
+    pub(crate) fn value(&mut self, doc: DocId) -> Result<VariantValue> {
+        match self.doc_values {
+            GroupDocValues::Numeric(ref mut doc_values) => {
+                Ok(VariantValue::Long(doc_values.get_mut(doc)?))
+            }
+            GroupDocValues::Keyword(ref mut doc_values) => {
+                let ord = doc_values.get_ord(doc)?;
+                if ord < 0 {
+                    Ok(VariantValue::Binary(vec![]))
+                } else {
+                    Ok(VariantValue::Binary(doc_values.lookup_ord(ord)?))
+                }
+            }
+            GroupDocValues::Empty => match self.field_type {
+                GroupFieldType::Numeric => Ok(VariantValue::Long(0)),
+                GroupFieldType::Keyword => Ok(VariantValue::Binary(vec![])),
+            },
+        }
+    }
+}
+
+/// The head of a group, its sort values are kept in `slot` of the comparators.
+struct GroupHead {
+    value: VariantValue,
+    slot: usize,
+    doc: DocId,
+    score: f32,
+}
+
+/// Keeps the heads of the top groups, i.e. the hits that sort first in
+/// their group, where groups sort by their heads.
+pub(crate) struct TopGroupHeads {
+    comparators: Vec<FieldComparatorEnum>,
+    reverses: Vec<bool>,
+    pub(crate) num_groups: usize,
+    heads: Vec<GroupHead>,
+    /// The index of the head of every top group in `heads`.
+    group_index: HashMap<VariantValue, usize>,
+    /// The extra slot a hit is copied to, to compare it with its group head.
+    spare_slot: usize,
+    /// The index of the head that sorts last, once `heads` is full.
+    bottom: usize,
+    cur_doc_base: DocId,
+}
+
+impl TopGroupHeads {
+    pub(crate) fn new(sort: &Sort, num_groups: usize) -> Self {
+        let fields = sort.get_sort();
+        let comparators = fields
+            .iter()
+            .map(|f| f.get_comparator(num_groups + 1, f.missing_value()))
+            .collect();
+        TopGroupHeads {
+            comparators,
+            reverses: fields.iter().map(|f| f.is_reverse()).collect(),
+            num_groups,
+            heads: Vec::with_capacity(num_groups),
+            group_index: HashMap::new(),
+            spare_slot: num_groups,
+            bottom: 0,
+            cur_doc_base: 0,
+        }
+    }
+
+    pub(crate) fn set_next_reader<C: Codec>(
+        &mut self,
+        reader: &LeafReaderContext<'_, C>,
+    ) -> Result<()> {
+        self.cur_doc_base = reader.doc_base;
+        for comparator in &mut self.comparators {
+            comparator.get_information_from_reader(reader)?;
+        }
+        Ok(())
+    }
+
+    fn compare_slots(&self, slot1: usize, slot2: usize) -> Ordering {
+        for (comparator, &reverse) in self.comparators.iter().zip(&self.reverses) {
+            let cmp = comparator.compare(slot1, slot2);
+            if cmp != Ordering::Equal {
+                return if reverse { cmp.reverse() } else { cmp };
+            }
+        }
+        Ordering::Equal
+    }
+
+    fn compare_heads(&self, h1: &GroupHead, h2: &GroupHead) -> Ordering {
+        self.compare_slots(h1.slot, h2.slot)
+            .then(h1.doc.cmp(&h2.doc))
+    }
+
+    /// Compares two group heads by their sort values, e.g. the ones collected
+    /// by different leaf collectors.
+    pub(crate) fn compare_field_docs(&self, d1: &FieldDoc, d2: &FieldDoc) -> Ordering {
+        for (i, (comparator, &reverse)) in self.comparators.iter().zip(&self.reverses).enumerate() {
+            let cmp = comparator.compare_values(&d1.fields[i], &d2.fields[i]);
+            if cmp != Ordering::Equal {
+                return if reverse { cmp.reverse() } else { cmp };
+            }
+        }
+        d1.doc.cmp(&d2.doc)
+    }
+
+    fn copy(&mut self, slot: usize, doc: DocId, score: f32) -> Result<()> {
+        for comparator in &mut self.comparators {
+            let value = comparator.comparator_value(doc, score);
+            comparator.copy(slot, value)?;
+        }
+        Ok(())
+    }
+
+    /// Finds the head that sorts last, the number of groups is expected to
+    /// be small so a linear scan is cheaper than keeping them ordered.
+    fn update_bottom(&mut self) {
+        let mut bottom = 0;
+        for i in 1..self.heads.len() {
+            if self.compare_heads(&self.heads[i], &self.heads[bottom]) == Ordering::Greater {
+                bottom = i;
+            }
+        }
+        self.bottom = bottom;
+        let slot = self.heads[bottom].slot;
+        for comparator in &mut self.comparators {
+            comparator.set_bottom(slot);
+        }
+    }
+
+    /// Returns whether the given hit of the current leaf may be the head of
+    /// one of the top groups, i.e. whether it sorts before the bottom head.
+    pub(crate) fn is_competitive(&mut self, doc: DocId, score: f32) -> Result<bool> {
+        if self.heads.len() < self.num_groups {
+            return Ok(true);
+        }
+        for (comparator, &reverse) in self.comparators.iter_mut().zip(&self.reverses) {
+            let value = comparator.comparator_value(doc, score);
+            let cmp = comparator.compare_bottom(value)?;
+            if cmp != Ordering::Equal {
+                let cmp = if reverse { cmp.reverse() } else { cmp };
+                return Ok(cmp == Ordering::Greater);
+            }
+        }
+        // hits are collected in doc id order, so the bottom head wins ties
+        Ok(false)
+    }
+
+    /// Adds a hit of the current leaf that `is_competitive`, either as the
+    /// new head of its group or as the head of a new group, which replaces
+    /// the bottom group if there are enough groups already.
+    pub(crate) fn add(&mut self, doc: DocId, score: f32, value: VariantValue) -> Result<()> {
+        let global_doc = doc + self.cur_doc_base;
+        let full = self.heads.len() == self.num_groups;
+        if let Some(&index) = self.group_index.get(&value) {
+            // replace the head if the hit sorts before it
+            let spare_slot = self.spare_slot;
+            self.copy(spare_slot, doc, score)?;
+            let head = &self.heads[index];
+            let cmp = self
+                .compare_slots(spare_slot, head.slot)
+                .then(global_doc.cmp(&head.doc));
+            if cmp != Ordering::Less {
+                return Ok(());
+            }
+            let head = &mut self.heads[index];
+            self.spare_slot = head.slot;
+            head.slot = spare_slot;
+            head.doc = global_doc;
+            head.score = score;
+            if full {
+                self.update_bottom();
+            }
+        } else if full {
+            let index = self.bottom;
+            let slot = self.heads[index].slot;
+            self.copy(slot, doc, score)?;
+            let head = GroupHead {
+                value: value.clone(),
+                slot,
+                doc: global_doc,
+                score,
+            };
+            let evicted = mem::replace(&mut self.heads[index], head);
+            self.group_index.remove(&evicted.value);
+            self.group_index.insert(value, index);
+            self.update_bottom();
+        } else {
+            let index = self.heads.len();
+            self.copy(index, doc, score)?;
+            self.heads.push(GroupHead {
+                value: value.clone(),
+                slot: index,
+                doc: global_doc,
+                score,
+            });
+            self.group_index.insert(value, index);
+            if self.heads.len() == self.num_groups {
+                self.update_bottom();
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the group heads in sort order with their group values, and
+    /// empties the groups.
+    pub(crate) fn top_heads(&mut self) -> Vec<(FieldDoc, VariantValue)> {
+        self.group_index.clear();
+        let mut heads = mem::replace(&mut self.heads, vec![]);
+        heads.sort_by(|h1, h2| self.compare_heads(h1, h2));
+        heads
+            .into_iter()
+            .map(|h| {
+                let fields = self.comparators.iter().map(|c| c.value(h.slot)).collect();
+                (FieldDoc::new(h.doc, h.score, fields), h.value)
+            })
+            .collect()
+    }
+
+    /// Merges the group heads of several leaves, keeping the best head of
+    /// every group and the top groups.
+    pub(crate) fn merge(
+        &self,
+        mut heads: Vec<(FieldDoc, VariantValue)>,
+    ) -> Vec<(FieldDoc, VariantValue)> {
+        heads.sort_by(|(d1, _), (d2, _)| self.compare_field_docs(d1, d2));
+        let mut seen = HashSet::new();
+        heads.retain(|(_, value)| seen.insert(value.clone()));
+        heads.truncate(self.num_groups);
+        heads
+    }
+}
+
+/// The group heads collected by a leaf collector of the first pass.
+type LeafGroupHeads = Vec<(FieldDoc, VariantValue)>;
+
+/// The first pass of a two-pass grouping search, it finds the top groups of
+/// the hits, groups sorting by their top hit by the group sort.
+///
+/// The top groups are then passed to a `SecondPassGroupingCollector` that
+/// collects the top hits of each group.
+pub struct FirstPassGroupingCollector {
+    group_sort: Sort,
+    source: GroupValueSource,
+    heads: TopGroupHeads,
+    /// The group heads collected by the leaf collectors of a parallel search.
+    leaf_heads: LeafGroupHeads,
+    channel: Option<(Sender<LeafGroupHeads>, Receiver<LeafGroupHeads>)>,
+}
+
+impl FirstPassGroupingCollector {
+    pub fn new(
+        group_field: String,
+        field_type: GroupFieldType,
+        group_sort: Sort,
+        top_n_groups: usize,
+    ) -> Result<Self> {
+        if top_n_groups == 0 {
+            bail!(IllegalArgument("top_n_groups must be > 0".into()));
+        }
+        if group_sort.get_sort().is_empty() {
+            bail!(IllegalArgument(
+                "group sort must contain at least one field".into()
+            ));
+        }
+        let heads = TopGroupHeads::new(&group_sort, top_n_groups);
+        Ok(FirstPassGroupingCollector {
+            group_sort,
+            source: GroupValueSource::new(group_field, field_type),
+            heads,
+            leaf_heads: vec![],
+            channel: None,
+        })
+    }
+
+    /// Returns the top groups that were collected by this collector, skipping
+    /// the first `group_offset` ones.
+    ///
+    /// The `sort_info_list` of every group holds the values its top hit was
+    /// passed to the comparators with, `comparator_slot` is its rank in the
+    /// returned groups and `sort_values` are its sort values.
+    pub fn top_groups(&mut self, group_offset: usize) -> Vec<CollectedSearchGroup> {
+        let mut heads = self.heads.top_heads();
+        if !self.leaf_heads.is_empty() {
+            heads.append(&mut self.leaf_heads);
+            heads = self.heads.merge(heads);
+        }
+        let fields = self.group_sort.get_sort();
+        heads
+            .into_iter()
+            .skip(group_offset)
+            .enumerate()
+            .map(|(rank, (doc, value))| {
+                let sort_info_list = fields
+                    .iter()
+                    .map(|f| {
+                        let sort_value = if f.field_type() == SortFieldType::Score {
+                            ComparatorValue::Score(doc.score)
+                        } else {
+                            ComparatorValue::Doc(doc.doc)
+                        };
+                        SortInfo::new(f.field_type(), sort_value)
+                    })
+                    .collect();
+                let wilson_info = WilsonInfo::new(doc.doc, VariantValue::Float(doc.score), None);
+                CollectedSearchGroup::new(value, sort_info_list, wilson_info, rank, doc.doc)
+                    .with_sort_values(doc.fields)
+            })
+            .collect()
+    }
+}
+
+impl SearchCollector for FirstPassGroupingCollector {
+    type LC = FirstPassGroupingLeafCollector;
+
+    fn set_next_reader<C: Codec>(&mut self, reader: &LeafReaderContext<'_, C>) -> Result<()> {
+        self.source.set_next_reader(reader)?;
+        self.heads.set_next_reader(reader)
+    }
+
+    fn support_parallel(&self) -> bool {
+        true
+    }
+
+    fn init_parallel(&mut self) {
+        if self.channel.is_none() {
+            self.channel = Some(channel());
+        }
+    }
+
+    fn leaf_collector<C: Codec>(
+        &self,
+        reader: &LeafReaderContext<'_, C>,
+    ) -> Result<FirstPassGroupingLeafCollector> {
+        let mut source =
+            GroupValueSource::new(self.source.field().to_string(), self.source.field_type());
+        source.set_next_reader(reader)?;
+        let mut heads = TopGroupHeads::new(&self.group_sort, self.heads.num_groups);
+        heads.set_next_reader(reader)?;
+        Ok(FirstPassGroupingLeafCollector {
+            needs_scores: self.group_sort.needs_scores(),
+            source,
+            heads,
+            channel: self.channel.as_ref().unwrap().0.clone(),
+        })
+    }
+
+    fn finish_parallel(&mut self) -> Result<()> {
+        let channel = self.channel.take();
+        // iff all the `weight.create_scorer(leaf_reader)` return None, the channel won't
+        // inited and thus stay None
+        if let Some((sender, receiver)) = channel {
+            drop(sender);
+            while let Ok(heads) = receiver.recv() {
+                self.leaf_heads.extend(heads);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Collector for FirstPassGroupingCollector {
+    fn needs_scores(&self) -> bool {
+        self.group_sort.needs_scores()
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
+        let score = if self.group_sort.needs_scores() {
+            scorer.score()?
+        } else {
+            f32::NAN
+        };
+        if self.heads.is_competitive(doc, score)? {
+            let value = self.source.value(doc)?;
+            self.heads.add(doc, score, value)?;
+        }
+        Ok(())
+    }
+}
+
+pub struct FirstPassGroupingLeafCollector {
+    needs_scores: bool,
+    source: GroupValueSource,
+    heads: TopGroupHeads,
+    channel: Sender<LeafGroupHeads>,
+}
+
+impl ParallelLeafCollector for FirstPassGroupingLeafCollector {
+    fn finish_leaf(&mut self) -> Result<()> {
+        self.channel
+            .send(self.heads.top_heads())
+            .chain_err(|| IllegalState("channel unexpected closed before search complete".into()))
+    }
+}
+
+impl Collector for FirstPassGroupingLeafCollector {
+    fn needs_scores(&self) -> bool {
+        self.needs_scores
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
+        let score = if self.needs_scores {
+            scorer.score()?
+        } else {
+            f32::NAN
+        };
+        if self.heads.is_competitive(doc, score)? {
+            let value = self.source.value(doc)?;
+            self.heads.add(doc, score, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// The second pass of a two-pass grouping search, it collects the top hits
+/// of each of the groups found by a `FirstPassGroupingCollector`.
+pub struct SecondPassGroupingCollector {
+    group_sort: Sort,
+    within_group_sort: Sort,
+    max_docs_per_group: usize,
+    track_scores: bool,
+    source: GroupValueSource,
+    /// The group values and their sort values, in the order of the groups.
+    groups: Vec<(VariantValue, Vec<VariantValue>)>,
+    group_index: HashMap<VariantValue, usize>,
+    /// A top hits collector for each group.
+    collectors: Vec<TopFieldBaseCollector>,
+    /// The hits of each group collected by the leaf collectors of a
+    /// parallel search.
+    leaf_docs: Vec<Vec<FieldDoc>>,
+    total_hits: usize,
+    total_grouped_hits: usize,
+    channel: Option<(Sender<LeafGroupDocs>, Receiver<LeafGroupDocs>)>,
+}
+
+impl SecondPassGroupingCollector {
+    pub fn new(
+        group_field: String,
+        field_type: GroupFieldType,
+        groups: &[CollectedSearchGroup],
+        group_sort: Sort,
+        within_group_sort: Sort,
+        max_docs_per_group: usize,
+        track_scores: bool,
+    ) -> Result<Self> {
+        if groups.is_empty() {
+            bail!(IllegalArgument("no groups to collect".into()));
+        }
+        if max_docs_per_group == 0 {
+            bail!(IllegalArgument("max_docs_per_group must be > 0".into()));
+        }
+        if within_group_sort.get_sort().is_empty() {
+            bail!(IllegalArgument(
+                "within group sort must contain at least one field".into()
+            ));
+        }
+        let mut group_index = HashMap::with_capacity(groups.len());
+        for (i, group) in groups.iter().enumerate() {
+            group_index.insert(group.group_value.clone(), i);
+        }
+        let collectors = Self::group_collectors(
+            &within_group_sort,
+            groups.len(),
+            max_docs_per_group,
+            track_scores,
+        )?;
+        Ok(SecondPassGroupingCollector {
+            group_sort,
+            within_group_sort,
+            max_docs_per_group,
+            track_scores,
+            source: GroupValueSource::new(group_field, field_type),
+            groups: groups
+                .iter()
+                .map(|g| (g.group_value.clone(), g.sort_values.clone()))
+                .collect(),
+            group_index,
+            collectors,
+            leaf_docs: vec![vec![]; groups.len()],
+            total_hits: 0,
+            total_grouped_hits: 0,
+            channel: None,
+        })
+    }
+
+    fn group_collectors(
+        within_group_sort: &Sort,
+        num_groups: usize,
+        max_docs_per_group: usize,
+        track_scores: bool,
+    ) -> Result<Vec<TopFieldBaseCollector>> {
+        let mut collectors = Vec::with_capacity(num_groups);
+        for _ in 0..num_groups {
+            collectors.push(TopFieldBaseCollector::new(
+                within_group_sort.get_sort(),
+                max_docs_per_group,
+                None,
+                track_scores,
+            )?);
+        }
+        Ok(collectors)
+    }
+
+    /// Returns the top hits of every group, skipping the first
+    /// `within_group_offset` hits of each group.
+    pub fn top_groups(&mut self, within_group_offset: usize) -> TopGroups {
+        let mut groups = Vec::with_capacity(self.groups.len());
+        for (i, (group_value, group_sort_values)) in self.groups.iter().enumerate() {
+            let collector = &mut self.collectors[i];
+            let mut docs = collector.field_docs();
+            let leaf_docs = &mut self.leaf_docs[i];
+            if !leaf_docs.is_empty() {
+                docs.append(leaf_docs);
+                docs.sort_by(|d1, d2| collector.compare_field_docs(d1, d2));
+                docs.truncate(collector.num_hits);
+            }
+            let max_score = if collector.needs_scores && collector.total_hits > 0 {
+                collector.max_score
+            } else {
+                f32::NAN
+            };
+            groups.push(GroupDocs {
+                group_value: group_value.clone(),
+                group_sort_values: group_sort_values.clone(),
+                total_hits: collector.total_hits,
+                score_docs: docs
+                    .into_iter()
+                    .skip(within_group_offset)
+                    .map(ScoreDocHit::Field)
+                    .collect(),
+                max_score,
+            });
+        }
+        TopGroups {
+            group_sort: self.group_sort.get_sort().to_vec(),
+            within_group_sort: self.within_group_sort.get_sort().to_vec(),
+            total_hit_count: self.total_hits,
+            total_grouped_hit_count: self.total_grouped_hits,
+            total_group_count: None,
+            groups,
+        }
+    }
+}
+
+impl SearchCollector for SecondPassGroupingCollector {
+    type LC = SecondPassGroupingLeafCollector;
+
+    fn set_next_reader<C: Codec>(&mut self, reader: &LeafReaderContext<'_, C>) -> Result<()> {
+        self.source.set_next_reader(reader)?;
+        for collector in &mut self.collectors {
+            collector.set_next_reader(reader)?;
+        }
+        Ok(())
+    }
+
+    fn support_parallel(&self) -> bool {
+        true
+    }
+
+    fn init_parallel(&mut self) {
+        if self.channel.is_none() {
+            self.channel = Some(channel());
+        }
+    }
+
+    fn leaf_collector<C: Codec>(
+        &self,
+        reader: &LeafReaderContext<'_, C>,
+    ) -> Result<SecondPassGroupingLeafCollector> {
+        let mut source =
+            GroupValueSource::new(self.source.field().to_string(), self.source.field_type());
+        source.set_next_reader(reader)?;
+        let mut collectors = Self::group_collectors(
+            &self.within_group_sort,
+            self.groups.len(),
+            self.max_docs_per_group,
+            self.track_scores,
+        )?;
+        for collector in &mut collectors {
+            collector.set_next_reader(reader)?;
+        }
+        Ok(SecondPassGroupingLeafCollector {
+            source,
+            group_index: self.group_index.clone(),
+            collectors,
+            total_hits: 0,
+            total_grouped_hits: 0,
+            channel: self.channel.as_ref().unwrap().0.clone(),
+        })
+    }
+
+    fn finish_parallel(&mut self) -> Result<()> {
+        let channel = self.channel.take();
+        // iff all the `weight.create_scorer(leaf_reader)` return None, the channel won't
+        // inited and thus stay None
+        if let Some((sender, receiver)) = channel {
+            drop(sender);
+            while let Ok(docs) = receiver.recv() {
+                self.total_hits += docs.total_hits;
+                self.total_grouped_hits += docs.total_grouped_hits;
+                for (i, group) in docs.groups.into_iter().enumerate() {
+                    let collector = &mut self.collectors[i];
+                    collector.total_hits += group.total_hits;
+                    if group.max_score > collector.max_score {
+                        collector.max_score = group.max_score;
+                    }
+                    self.leaf_docs[i].extend(group.docs);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Collector for SecondPassGroupingCollector {
+    fn needs_scores(&self) -> bool {
+        self.track_scores || self.within_group_sort.needs_scores()
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
+        self.total_hits += 1;
+        let value = self.source.value(doc)?;
+        if let Some(&index) = self.group_index.get(&value) {
+            self.total_grouped_hits += 1;
+            self.collectors[index].collect(doc, scorer)?;
+        }
+        Ok(())
+    }
+}
+
+struct LeafGroup {
+    docs: Vec<FieldDoc>,
+    total_hits: usize,
+    max_score: f32,
+}
+
+struct LeafGroupDocs {
+    groups: Vec<LeafGroup>,
+    total_hits: usize,
+    total_grouped_hits: usize,
+}
+
+pub struct SecondPassGroupingLeafCollector {
+    source: GroupValueSource,
+    group_index: HashMap<VariantValue, usize>,
+    collectors: Vec<TopFieldBaseCollector>,
+    total_hits: usize,
+    total_grouped_hits: usize,
+    channel: Sender<LeafGroupDocs>,
+}
+
+impl ParallelLeafCollector for SecondPassGroupingLeafCollector {
+    fn finish_leaf(&mut self) -> Result<()> {
+        let groups = self
+            .collectors
+            .iter_mut()
+            .map(|c| LeafGroup {
+                docs: c.field_docs(),
+                total_hits: c.total_hits,
+                max_score: c.max_score,
+            })
+            .collect();
+        let docs = LeafGroupDocs {
+            groups,
+            total_hits: self.total_hits,
+            total_grouped_hits: self.total_grouped_hits,
+        };
+        self.channel
+            .send(docs)
+            .chain_err(|| IllegalState("channel unexpected closed before search complete".into()))
+    }
+}
+
+impl Collector for SecondPassGroupingLeafCollector {
+    fn needs_scores(&self) -> bool {
+        self.collectors[0].needs_scores
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
+        self.total_hits += 1;
+        let value = self.source.value(doc)?;
+        if let Some(&index) = self.group_index.get(&value) {
+            self.total_grouped_hits += 1;
+            self.collectors[index].collect(doc, scorer)?;
+        }
+        Ok(())
+    }
+}
+
+/// The group values collected by a leaf collector of `AllGroupsCollector`.
+type LeafGroups = HashSet<VariantValue>;
+
+/// A `Collector` that collects the values of all the groups of the hits,
+/// e.g. to count the groups of a grouping search.
+pub struct AllGroupsCollector {
+    source: GroupValueSource,
+    groups: HashSet<VariantValue>,
+    channel: Option<(Sender<LeafGroups>, Receiver<LeafGroups>)>,
+}
+
+impl AllGroupsCollector {
+    pub fn new(group_field: String, field_type: GroupFieldType) -> Self {
+        AllGroupsCollector {
+            source: GroupValueSource::new(group_field, field_type),
+            groups: HashSet::new(),
+            channel: None,
+        }
+    }
+
+    /// Returns the number of distinct groups of the hits.
+    pub fn group_count(&self) -> usize {
+        self.groups.len()
+    }
+
+    /// Returns the values of the groups of the hits.
+    pub fn groups(&self) -> &HashSet<VariantValue> {
+        &self.groups
+    }
+}
+
+impl SearchCollector for AllGroupsCollector {
+    type LC = AllGroupsLeafCollector;
+
+    fn set_next_reader<C: Codec>(&mut self, reader: &LeafReaderContext<'_, C>) -> Result<()> {
+        self.source.set_next_reader(reader)
+    }
+
+    fn support_parallel(&self) -> bool {
+        true
+    }
+
+    fn init_parallel(&mut self) {
+        if self.channel.is_none() {
+            self.channel = Some(channel());
+        }
+    }
+
+    fn leaf_collector<C: Codec>(
+        &self,
+        reader: &LeafReaderContext<'_, C>,
+    ) -> Result<AllGroupsLeafCollector> {
+        let mut source =
+            GroupValueSource::new(self.source.field().to_string(), self.source.field_type());
+        source.set_next_reader(reader)?;
+        Ok(AllGroupsLeafCollector {
+            source,
+            groups: HashSet::new(),
+            channel: self.channel.as_ref().unwrap().0.clone(),
+        })
+    }
+
+    fn finish_parallel(&mut self) -> Result<()> {
+        let channel = self.channel.take();
+        // iff all the `weight.create_scorer(leaf_reader)` return None, the channel won't
+        // inited and thus stay None
+        if let Some((sender, receiver)) = channel {
+            drop(sender);
+            while let Ok(groups) = receiver.recv() {
+                self.groups.extend(groups);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Collector for AllGroupsCollector {
+    fn needs_scores(&self) -> bool {
+        false
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, _scorer: &mut S) -> Result<()> {
+        self.groups.insert(self.source.value(doc)?);
+        Ok(())
+    }
+}
+
+pub struct AllGroupsLeafCollector {
+    source: GroupValueSource,
+    groups: HashSet<VariantValue>,
+    channel: Sender<LeafGroups>,
+}
+
+impl ParallelLeafCollector for AllGroupsLeafCollector {
+    fn finish_leaf(&mut self) -> Result<()> {
+        let groups = mem::replace(&mut self.groups, HashSet::new());
+        self.channel
+            .send(groups)
+            .chain_err(|| IllegalState("channel unexpected closed before search complete".into()))
+    }
+}
+
+impl Collector for AllGroupsLeafCollector {
+    fn needs_scores(&self) -> bool {
+        false
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, _scorer: &mut S) -> Result<()> {
+        self.groups.insert(self.source.value(doc)?);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate tempfile;
+
+    use super::*;
+    use core::doc::{Field, Fieldable, NumericDocValuesField, SORTED_DOC_VALUES_FIELD_TYPE};
+    use core::index::writer::{IndexWriter, IndexWriterConfig};
+    use core::search::query::MatchAllDocsQuery;
+    use core::search::sort_field::{SimpleSortField, SortField};
+    use core::search::{DefaultIndexSearcher, IndexSearcher};
+    use core::store::directory::FSDirectory;
+
+    use std::sync::Arc;
+
+    #[test]
+    fn test_two_pass_grouping() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let directory = Arc::new(FSDirectory::with_path(temp_dir.path()).unwrap());
+        let writer = IndexWriter::new(directory, Arc::new(IndexWriterConfig::default())).unwrap();
+
+        // (doc, author, price), every sixth doc has no author
+        let mut hits: Vec<(DocId, Vec<u8>, i64)> = vec![];
+        for i in 0..90 {
+            let price = i64::from(i * 37 % 50);
+            let mut doc: Vec<Box<dyn Fieldable>> =
+                vec![Box::new(NumericDocValuesField::new("price", price))];
+            let author = if i % 6 == 0 {
+                vec![]
+            } else {
+                format!("author{}", i % 8).into_bytes()
+            };
+            if !author.is_empty() {
+                doc.push(Box::new(Field::new(
+                    "author".into(),
+                    SORTED_DOC_VALUES_FIELD_TYPE,
+                    Some(VariantValue::Binary(author.clone())),
+                    None,
+                )));
+            }
+            writer.add_document(doc).unwrap();
+            hits.push((i, author, price));
+            if i == 40 {
+                writer.commit().unwrap();
+            }
+        }
+        writer.commit().unwrap();
+
+        let reader = writer.get_reader(true, false).unwrap();
+        let searcher = DefaultIndexSearcher::new(Arc::new(reader), None);
+        // groups sort by their most expensive doc, docs of a group by ascending price
+        let group_sort = Sort::new(vec![SortField::Simple(SimpleSortField::new(
+            "price".into(),
+            SortFieldType::Long,
+            true,
+        ))]);
+        let within_group_sort = Sort::new(vec![SortField::Simple(SimpleSortField::new(
+            "price".into(),
+            SortFieldType::Long,
+            false,
+        ))]);
+
+        hits.sort_by(|a, b| b.2.cmp(&a.2).then(a.0.cmp(&b.0)));
+        let mut expected_groups: Vec<Vec<u8>> = vec![];
+        for hit in &hits {
+            if !expected_groups.contains(&hit.1) {
+                expected_groups.push(hit.1.clone());
+            }
+        }
+        // eight authors and the docs without one
+        assert_eq!(expected_groups.len(), 9);
+
+        let mut first_pass = FirstPassGroupingCollector::new(
+            "author".into(),
+            GroupFieldType::Keyword,
+            group_sort.clone(),
+            4,
+        )
+        .unwrap();
+        let mut all_groups = AllGroupsCollector::new("author".into(), GroupFieldType::Keyword);
+        searcher
+            .search(&MatchAllDocsQuery, &mut first_pass)
+            .unwrap();
+        searcher
+            .search(&MatchAllDocsQuery, &mut all_groups)
+            .unwrap();
+        assert_eq!(all_groups.group_count(), 9);
+
+        let groups = first_pass.top_groups(1);
+        let values: Vec<VariantValue> = groups.iter().map(|g| g.group_value.clone()).collect();
+        let expected_values: Vec<VariantValue> = expected_groups[1..4]
+            .iter()
+            .map(|g| VariantValue::Binary(g.clone()))
+            .collect();
+        assert_eq!(values, expected_values);
+
+        let mut second_pass = SecondPassGroupingCollector::new(
+            "author".into(),
+            GroupFieldType::Keyword,
+            &groups,
+            group_sort,
+            within_group_sort,
+            3,
+            false,
+        )
+        .unwrap();
+        searcher
+            .search(&MatchAllDocsQuery, &mut second_pass)
+            .unwrap();
+        let top_groups = second_pass.top_groups(0);
+        assert_eq!(top_groups.total_hit_count, 90);
+        assert_eq!(top_groups.groups.len(), 3);
+
+        let mut grouped_hits = 0;
+        for (group, author) in top_groups.groups.iter().zip(&expected_groups[1..4]) {
+            let mut group_hits: Vec<&(DocId, Vec<u8>, i64)> =
+                hits.iter().filter(|h| &h.1 == author).collect();
+            grouped_hits += group_hits.len();
+            assert_eq!(group.total_hits, group_hits.len());
+            assert_eq!(
+                group.group_sort_values,
+                vec![VariantValue::Long(group_hits[0].2)]
+            );
+
+            group_hits.sort_by(|a, b| a.2.cmp(&b.2).then(a.0.cmp(&b.0)));
+            let docs: Vec<DocId> = group.score_docs.iter().map(|d| d.doc_id()).collect();
+            let expected_docs: Vec<DocId> = group_hits.iter().take(3).map(|h| h.0).collect();
+            assert_eq!(docs, expected_docs);
+        }
+        assert_eq!(top_groups.total_grouped_hit_count, grouped_hits);
+    }
+}
@@ -15,6 +15,18 @@ mod top_docs;
 
 pub use self::top_docs::*;
 
+mod top_field;
+
+pub use self::top_field::*;
+
+mod collapse;
+
+pub use self::collapse::*;
+
+mod grouping;
+
+pub use self::grouping::*;
+
 mod early_terminating;
 
 pub use self::early_terminating::*;
@@ -32,6 +44,7 @@ use error::Result;
 use core::codec::Codec;
 use core::index::reader::LeafReaderContext;
 use core::search::scorer::Scorer;
+use core::search::ScoreMode;
 use core::util::DocId;
 
 error_chain! {
@@ -118,6 +131,16 @@ pub trait Collector {
     /// return `true` if scores are needed.
     fn needs_scores(&self) -> bool;
 
+    /// Indicates how the scorers feeding this collector must score the docs,
+    /// `ScoreMode::TopScores` allows them to skip the non-competitive ones.
+    fn score_mode(&self) -> ScoreMode {
+        if self.needs_scores() {
+            ScoreMode::Complete
+        } else {
+            ScoreMode::CompleteNoScores
+        }
+    }
+
     /// Called once for every document matching a query, with the unbased document
     /// number.
     /// Note: The collection of the current segment can be terminated by throwing
@@ -136,6 +159,10 @@ impl<'a, T: Collector + 'a> Collector for &'a mut T {
         (**self).needs_scores()
     }
 
+    fn score_mode(&self) -> ScoreMode {
+        (**self).score_mode()
+    }
+
     fn collect<S: Scorer + ?Sized>(&mut self, doc: i32, scorer: &mut S) -> Result<()> {
         (**self).collect(doc, scorer)
     }
@@ -22,6 +22,7 @@ use core::index::reader::LeafReaderContext;
 use core::search::collector::{Collector, ParallelLeafCollector, SearchCollector};
 use core::search::scorer::Scorer;
 use core::search::sort_field::{ScoreDoc, ScoreDocHit, TopDocs, TopScoreDocs};
+use core::search::ScoreMode;
 use core::util::DocId;
 use error::{ErrorKind::IllegalState, Result, ResultExt};
 
@@ -37,16 +38,25 @@ struct TopDocsBaseCollector {
     /// The total number of documents that the collector encountered.
     total_hits: usize,
 
+    /// Once more than this number of hits are collected, the scorer is
+    /// allowed to skip the non-competitive docs.
+    total_hits_threshold: usize,
+
+    /// The min competitive score the current scorer was told about.
+    min_competitive_score: f32,
+
     cur_doc_base: DocId,
 }
 
 impl TopDocsBaseCollector {
-    fn new(estimated_hits: usize) -> Self {
+    fn new(estimated_hits: usize, total_hits_threshold: usize) -> Self {
         let pq = BinaryHeap::with_capacity(estimated_hits);
         Self {
             pq,
             estimated_hits,
             total_hits: 0,
+            total_hits_threshold,
+            min_competitive_score: 0.0,
             cur_doc_base: 0,
         }
     }
@@ -81,6 +91,14 @@ impl Collector for TopDocsBaseCollector {
         true
     }
 
+    fn score_mode(&self) -> ScoreMode {
+        if self.total_hits_threshold < usize::MAX {
+            ScoreMode::TopScores
+        } else {
+            ScoreMode::Complete
+        }
+    }
+
     fn collect<S: Scorer + ?Sized>(&mut self, doc: i32, scorer: &mut S) -> Result<()> {
         let score = scorer.score()?;
         debug_assert!((score - f32::NEG_INFINITY).abs() >= f32::EPSILON);
@@ -90,6 +108,15 @@ impl Collector for TopDocsBaseCollector {
         self.add_doc(id, score);
         self.total_hits += 1;
 
+        if self.total_hits > self.total_hits_threshold && self.pq.len() >= self.estimated_hits {
+            if let Some(bottom) = self.pq.peek() {
+                if bottom.score > self.min_competitive_score {
+                    self.min_competitive_score = bottom.score;
+                    scorer.set_min_competitive_score(bottom.score)?;
+                }
+            }
+        }
+
         Ok(())
     }
 }
@@ -106,7 +133,18 @@ pub struct TopDocsCollector {
 
 impl TopDocsCollector {
     pub fn new(estimated_hits: usize) -> Self {
-        let base = TopDocsBaseCollector::new(estimated_hits);
+        Self::with_total_hits_threshold(estimated_hits, usize::MAX)
+    }
+
+    /// Creates a collector that counts the hits exactly only up to
+    /// `total_hits_threshold`, beyond which the scorer may skip the docs that
+    /// can't make it into the top `estimated_hits` docs, e.g. with a
+    /// `WANDScorer`. The total hits of the top docs are then a lower bound.
+    ///
+    /// This must not be chained with collectors that need to see every
+    /// matching doc.
+    pub fn with_total_hits_threshold(estimated_hits: usize, total_hits_threshold: usize) -> Self {
+        let base = TopDocsBaseCollector::new(estimated_hits, total_hits_threshold);
         Self {
             base,
             channel: None,
@@ -128,6 +166,7 @@ impl SearchCollector for TopDocsCollector {
 
     fn set_next_reader<C: Codec>(&mut self, reader: &LeafReaderContext<'_, C>) -> Result<()> {
         self.base.cur_doc_base = reader.doc_base;
+        self.base.min_competitive_score = 0.0;
 
         Ok(())
     }
@@ -146,7 +185,8 @@ impl SearchCollector for TopDocsCollector {
         &self,
         reader: &LeafReaderContext<'_, C>,
     ) -> Result<TopDocsLeafCollector> {
-        let mut collector = TopDocsBaseCollector::new(self.base.estimated_hits);
+        let mut collector =
+            TopDocsBaseCollector::new(self.base.estimated_hits, self.base.total_hits_threshold);
         collector.cur_doc_base = reader.doc_base;
         Ok(TopDocsLeafCollector::new(
             collector,
@@ -177,6 +217,10 @@ impl Collector for TopDocsCollector {
         true
     }
 
+    fn score_mode(&self) -> ScoreMode {
+        self.base.score_mode()
+    }
+
     fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
         self.base.collect(doc, scorer)
     }
@@ -262,4 +306,67 @@ mod tests {
         assert_eq!(score_docs[1].doc_id(), 3);
         assert_eq!(score_docs[2].doc_id(), 3);
     }
+
+    struct MinScoreRecorder {
+        scorer: MockSimpleScorer<MockDocIterator>,
+        min_scores: Vec<f32>,
+    }
+
+    impl Scorer for MinScoreRecorder {
+        fn score(&mut self) -> Result<f32> {
+            self.scorer.score()
+        }
+
+        fn set_min_competitive_score(&mut self, min_score: f32) -> Result<()> {
+            self.min_scores.push(min_score);
+            Ok(())
+        }
+    }
+
+    impl DocIterator for MinScoreRecorder {
+        fn doc_id(&self) -> DocId {
+            self.scorer.doc_id()
+        }
+
+        fn next(&mut self) -> Result<DocId> {
+            self.scorer.next()
+        }
+
+        fn advance(&mut self, target: DocId) -> Result<DocId> {
+            self.scorer.advance(target)
+        }
+
+        fn cost(&self) -> usize {
+            self.scorer.cost()
+        }
+    }
+
+    #[test]
+    fn test_total_hits_threshold() {
+        let leaf_reader = MockLeafReader::new(0);
+        let index_reader = MockIndexReader::new(vec![leaf_reader]);
+        let leaf_reader_context = index_reader.leaves();
+
+        for (threshold, expected) in vec![
+            (usize::MAX, vec![]),
+            (0, vec![1.0, 2.0, 3.0, 4.0, 5.0]),
+            (4, vec![4.0, 5.0]),
+        ] {
+            let mut scorer = MinScoreRecorder {
+                scorer: create_mock_scorer(vec![1, 2, 3, 4, 5, 6]),
+                min_scores: vec![],
+            };
+            let mut collector = TopDocsCollector::with_total_hits_threshold(2, threshold);
+            collector.set_next_reader(&leaf_reader_context[0]).unwrap();
+            while scorer.next().unwrap() != NO_MORE_DOCS {
+                let doc = scorer.doc_id();
+                collector.collect(doc, &mut scorer).unwrap();
+            }
+            assert_eq!(scorer.min_scores, expected);
+
+            let top_docs = collector.top_docs();
+            assert_eq!(top_docs.total_hits(), 6);
+            assert_eq!(top_docs.score_docs()[0].doc_id(), 6);
+        }
+    }
 }
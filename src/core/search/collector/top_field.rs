@@ -0,0 +1,672 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cmp::Ordering;
+use std::f32;
+use std::mem;
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use core::codec::Codec;
+use core::index::reader::LeafReaderContext;
+use core::search::collector::{Collector, ParallelLeafCollector, SearchCollector};
+use core::search::scorer::Scorer;
+use core::search::sort_field::{
+    FieldComparator, FieldComparatorEnum, FieldDoc, ScoreDocHit, Sort, SortField, TopDocs,
+    TopFieldDocs,
+};
+use core::util::DocId;
+use error::{
+    ErrorKind::{IllegalArgument, IllegalState},
+    Result, ResultExt,
+};
+
+/// A hit in the queue, its sort values are kept in `slot` of the comparators.
+#[derive(Clone, Copy)]
+struct SlotEntry {
+    slot: usize,
+    doc: DocId,
+    score: f32,
+}
+
+pub(crate) struct TopFieldBaseCollector {
+    comparators: Vec<FieldComparatorEnum>,
+    reverses: Vec<bool>,
+    pub(crate) num_hits: usize,
+    /// A binary heap of the collected hits, with the one that sorts last on top.
+    queue: Vec<SlotEntry>,
+    /// The doc id of the hit to search after, if any.
+    after_doc: Option<DocId>,
+    pub(crate) needs_scores: bool,
+    pub(crate) total_hits: usize,
+    pub(crate) max_score: f32,
+    cur_doc_base: DocId,
+}
+
+impl TopFieldBaseCollector {
+    pub(crate) fn new(
+        fields: &[SortField],
+        num_hits: usize,
+        after: Option<&FieldDoc>,
+        track_scores: bool,
+    ) -> Result<Self> {
+        let mut comparators = Vec::with_capacity(fields.len());
+        for (i, field) in fields.iter().enumerate() {
+            let mut comparator = field.get_comparator(num_hits, field.missing_value());
+            if let Some(after) = after {
+                comparator.set_top_value(after.fields[i].clone())?;
+            }
+            comparators.push(comparator);
+        }
+        Ok(Self {
+            comparators,
+            reverses: fields.iter().map(|f| f.is_reverse()).collect(),
+            num_hits,
+            queue: Vec::with_capacity(num_hits),
+            after_doc: after.map(|a| a.doc),
+            needs_scores: track_scores || fields.iter().any(|f| f.needs_scores()),
+            total_hits: 0,
+            max_score: f32::NEG_INFINITY,
+            cur_doc_base: 0,
+        })
+    }
+
+    pub(crate) fn set_next_reader<C: Codec>(
+        &mut self,
+        reader: &LeafReaderContext<'_, C>,
+    ) -> Result<()> {
+        self.cur_doc_base = reader.doc_base;
+        for comparator in &mut self.comparators {
+            comparator.get_information_from_reader(reader)?;
+        }
+        Ok(())
+    }
+
+    /// Compares the bottom of the queue, or the hit to search after if `top`
+    /// is set, with the given hit of the current leaf.
+    fn compare_hit(&mut self, doc: DocId, score: f32, top: bool) -> Result<Ordering> {
+        for (comparator, &reverse) in self.comparators.iter_mut().zip(&self.reverses) {
+            let value = comparator.comparator_value(doc, score);
+            let cmp = if top {
+                comparator.compare_top(value)?
+            } else {
+                comparator.compare_bottom(value)?
+            };
+            if cmp != Ordering::Equal {
+                return Ok(if reverse { cmp.reverse() } else { cmp });
+            }
+        }
+        Ok(Ordering::Equal)
+    }
+
+    fn compare_entries(&self, e1: &SlotEntry, e2: &SlotEntry) -> Ordering {
+        for (comparator, &reverse) in self.comparators.iter().zip(&self.reverses) {
+            let cmp = comparator.compare(e1.slot, e2.slot);
+            if cmp != Ordering::Equal {
+                return if reverse { cmp.reverse() } else { cmp };
+            }
+        }
+        e1.doc.cmp(&e2.doc)
+    }
+
+    /// Compares two hits by their sort values, e.g. the ones collected by
+    /// different leaf collectors.
+    pub(crate) fn compare_field_docs(&self, d1: &FieldDoc, d2: &FieldDoc) -> Ordering {
+        for (i, (comparator, &reverse)) in self.comparators.iter().zip(&self.reverses).enumerate() {
+            let cmp = comparator.compare_values(&d1.fields[i], &d2.fields[i]);
+            if cmp != Ordering::Equal {
+                return if reverse { cmp.reverse() } else { cmp };
+            }
+        }
+        d1.doc.cmp(&d2.doc)
+    }
+
+    fn up_heap(&mut self, mut i: usize) {
+        while i > 0 {
+            let parent = (i - 1) / 2;
+            if self.compare_entries(&self.queue[i], &self.queue[parent]) != Ordering::Greater {
+                break;
+            }
+            self.queue.swap(i, parent);
+            i = parent;
+        }
+    }
+
+    fn down_heap(&mut self, mut i: usize) {
+        loop {
+            let left = 2 * i + 1;
+            if left >= self.queue.len() {
+                break;
+            }
+            let right = left + 1;
+            let child = if right < self.queue.len()
+                && self.compare_entries(&self.queue[right], &self.queue[left]) == Ordering::Greater
+            {
+                right
+            } else {
+                left
+            };
+            if self.compare_entries(&self.queue[child], &self.queue[i]) != Ordering::Greater {
+                break;
+            }
+            self.queue.swap(i, child);
+            i = child;
+        }
+    }
+
+    fn copy(&mut self, slot: usize, doc: DocId, score: f32) -> Result<()> {
+        for comparator in &mut self.comparators {
+            let value = comparator.comparator_value(doc, score);
+            comparator.copy(slot, value)?;
+        }
+        Ok(())
+    }
+
+    fn update_bottom(&mut self) {
+        let slot = self.queue[0].slot;
+        for comparator in &mut self.comparators {
+            comparator.set_bottom(slot);
+        }
+    }
+
+    /// Returns the collected hits in sort order, and empties the queue.
+    pub(crate) fn field_docs(&mut self) -> Vec<FieldDoc> {
+        let mut entries = mem::replace(&mut self.queue, vec![]);
+        entries.sort_by(|e1, e2| self.compare_entries(e1, e2));
+        entries
+            .iter()
+            .map(|e| {
+                let fields = self.comparators.iter().map(|c| c.value(e.slot)).collect();
+                FieldDoc::new(e.doc, e.score, fields)
+            })
+            .collect()
+    }
+}
+
+impl Collector for TopFieldBaseCollector {
+    fn needs_scores(&self) -> bool {
+        self.needs_scores
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
+        self.total_hits += 1;
+        let score = if self.needs_scores {
+            let score = scorer.score()?;
+            if score > self.max_score {
+                self.max_score = score;
+            }
+            score
+        } else {
+            f32::NAN
+        };
+        let global_doc = doc + self.cur_doc_base;
+
+        let full = self.queue.len() == self.num_hits;
+        if full {
+            // skip the hit if it doesn't sort before the bottom
+            let bottom_doc = self.queue[0].doc;
+            let cmp = self.compare_hit(doc, score, false)?;
+            if cmp.then(bottom_doc.cmp(&global_doc)) != Ordering::Greater {
+                return Ok(());
+            }
+        }
+        if let Some(after_doc) = self.after_doc {
+            // skip the hit if it was already on a previous page
+            let cmp = self.compare_hit(doc, score, true)?;
+            if cmp.then(after_doc.cmp(&global_doc)) != Ordering::Less {
+                return Ok(());
+            }
+        }
+
+        if full {
+            let slot = self.queue[0].slot;
+            self.copy(slot, doc, score)?;
+            self.queue[0] = SlotEntry {
+                slot,
+                doc: global_doc,
+                score,
+            };
+            self.down_heap(0);
+            self.update_bottom();
+        } else {
+            let slot = self.queue.len();
+            self.copy(slot, doc, score)?;
+            self.queue.push(SlotEntry {
+                slot,
+                doc: global_doc,
+                score,
+            });
+            self.up_heap(slot);
+            if self.queue.len() == self.num_hits {
+                self.update_bottom();
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A `Collector` that collects the top hits sorted by a `Sort`, e.g. by the
+/// doc values of some fields, and returns them as `TopDocs::Field`.
+///
+/// Every returned `FieldDoc` holds the values the hit was sorted by, so that
+/// the last one of a page may be passed to `search_after` to get the next
+/// page. Hits are only scored if the sort needs scores or `track_scores` is
+/// set, otherwise their score is `NaN`.
+pub struct TopFieldCollector {
+    sort: Sort,
+    after: Option<FieldDoc>,
+    base: TopFieldBaseCollector,
+    /// The hits collected by the leaf collectors of a parallel search.
+    leaf_docs: Vec<FieldDoc>,
+    channel: Option<(Sender<LeafTopFieldDocs>, Receiver<LeafTopFieldDocs>)>,
+}
+
+impl TopFieldCollector {
+    pub fn new(sort: Sort, num_hits: usize, track_scores: bool) -> Result<Self> {
+        Self::build(sort, num_hits, None, track_scores)
+    }
+
+    /// Creates a collector that only collects the hits sorting after `after`,
+    /// which must have been returned by a search with the same sort.
+    pub fn search_after(
+        sort: Sort,
+        num_hits: usize,
+        after: FieldDoc,
+        track_scores: bool,
+    ) -> Result<Self> {
+        Self::build(sort, num_hits, Some(after), track_scores)
+    }
+
+    fn build(
+        sort: Sort,
+        num_hits: usize,
+        after: Option<FieldDoc>,
+        track_scores: bool,
+    ) -> Result<Self> {
+        if num_hits == 0 {
+            bail!(IllegalArgument("num_hits must be > 0".into()));
+        }
+        if sort.get_sort().is_empty() {
+            bail!(IllegalArgument(
+                "sort must contain at least one field".into()
+            ));
+        }
+        if let Some(ref after) = after {
+            if after.fields.len() != sort.get_sort().len() {
+                bail!(IllegalArgument(format!(
+                    "after has {} sort values, but sort has {} fields",
+                    after.fields.len(),
+                    sort.get_sort().len()
+                )));
+            }
+        }
+        let base =
+            TopFieldBaseCollector::new(sort.get_sort(), num_hits, after.as_ref(), track_scores)?;
+        Ok(TopFieldCollector {
+            sort,
+            after,
+            base,
+            leaf_docs: vec![],
+            channel: None,
+        })
+    }
+
+    /// Returns the top docs that were collected by this collector.
+    pub fn top_docs(&mut self) -> TopDocs {
+        let fields = self.sort.get_sort();
+        let mut docs = self.base.field_docs();
+        if !self.leaf_docs.is_empty() {
+            docs.append(&mut self.leaf_docs);
+            let base = &self.base;
+            docs.sort_by(|d1, d2| base.compare_field_docs(d1, d2));
+            docs.truncate(self.base.num_hits);
+        }
+        let max_score = if self.base.needs_scores && self.base.total_hits > 0 {
+            self.base.max_score
+        } else {
+            f32::NAN
+        };
+        TopDocs::Field(TopFieldDocs {
+            total_hits: self.base.total_hits,
+            score_docs: docs.into_iter().map(ScoreDocHit::Field).collect(),
+            max_score,
+            fields: fields.to_vec(),
+        })
+    }
+}
+
+impl SearchCollector for TopFieldCollector {
+    type LC = TopFieldLeafCollector;
+
+    fn set_next_reader<C: Codec>(&mut self, reader: &LeafReaderContext<'_, C>) -> Result<()> {
+        self.base.set_next_reader(reader)
+    }
+
+    fn support_parallel(&self) -> bool {
+        true
+    }
+
+    fn init_parallel(&mut self) {
+        if self.channel.is_none() {
+            self.channel = Some(channel());
+        }
+    }
+
+    fn leaf_collector<C: Codec>(
+        &self,
+        reader: &LeafReaderContext<'_, C>,
+    ) -> Result<TopFieldLeafCollector> {
+        let mut collector = TopFieldBaseCollector::new(
+            self.sort.get_sort(),
+            self.base.num_hits,
+            self.after.as_ref(),
+            self.base.needs_scores,
+        )?;
+        collector.set_next_reader(reader)?;
+        Ok(TopFieldLeafCollector {
+            collector,
+            channel: self.channel.as_ref().unwrap().0.clone(),
+        })
+    }
+
+    fn finish_parallel(&mut self) -> Result<()> {
+        let channel = self.channel.take();
+        // iff all the `weight.create_scorer(leaf_reader)` return None, the channel won't
+        // inited and thus stay None
+        if let Some((sender, receiver)) = channel {
+            drop(sender);
+            while let Ok(docs) = receiver.recv() {
+                self.base.total_hits += docs.total_hits;
+                if docs.max_score > self.base.max_score {
+                    self.base.max_score = docs.max_score;
+                }
+                self.leaf_docs.extend(docs.docs);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Collector for TopFieldCollector {
+    fn needs_scores(&self) -> bool {
+        self.base.needs_scores
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
+        self.base.collect(doc, scorer)
+    }
+}
+
+struct LeafTopFieldDocs {
+    docs: Vec<FieldDoc>,
+    total_hits: usize,
+    max_score: f32,
+}
+
+pub struct TopFieldLeafCollector {
+    collector: TopFieldBaseCollector,
+    channel: Sender<LeafTopFieldDocs>,
+}
+
+impl ParallelLeafCollector for TopFieldLeafCollector {
+    fn finish_leaf(&mut self) -> Result<()> {
+        let top_docs = LeafTopFieldDocs {
+            docs: self.collector.field_docs(),
+            total_hits: self.collector.total_hits,
+            max_score: self.collector.max_score,
+        };
+        self.channel
+            .send(top_docs)
+            .chain_err(|| IllegalState("channel unexpected closed before search complete".into()))
+    }
+}
+
+impl Collector for TopFieldLeafCollector {
+    fn needs_scores(&self) -> bool {
+        self.collector.needs_scores
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, scorer: &mut S) -> Result<()> {
+        self.collector.collect(doc, scorer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate tempfile;
+
+    use super::*;
+    use core::doc::{
+        Field, FieldType, Fieldable, IndexOptions, NumericDocValuesField, SortedSetDocValuesField,
+    };
+    use core::index::writer::{IndexWriter, IndexWriterConfig};
+    use core::search::query::MatchAllDocsQuery;
+    use core::search::sort_field::{
+        SimpleSortField, SortFieldMissingValue, SortFieldType, SortedSetSelectorType,
+        SortedSetSortField,
+    };
+    use core::search::{DefaultIndexSearcher, IndexSearcher};
+    use core::store::directory::FSDirectory;
+    use core::util::VariantValue;
+
+    use std::sync::Arc;
+
+    #[test]
+    fn test_top_field_collector() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let directory = Arc::new(FSDirectory::with_path(temp_dir.path()).unwrap());
+        let writer = IndexWriter::new(directory, Arc::new(IndexWriterConfig::default())).unwrap();
+
+        let mut field_type = FieldType::default();
+        field_type.index_options = IndexOptions::Docs;
+        field_type.tokenized = false;
+        // (doc, group, price), every tenth doc has no price
+        let mut expected: Vec<(DocId, i64, i64)> = vec![];
+        for i in 0..100 {
+            let mut doc: Vec<Box<dyn Fieldable>> = vec![
+                Box::new(Field::new(
+                    "id".into(),
+                    field_type.clone(),
+                    Some(VariantValue::VString(i.to_string())),
+                    None,
+                )),
+                Box::new(NumericDocValuesField::new("group", i64::from(i % 3))),
+            ];
+            let price = if i % 10 == 0 {
+                -1
+            } else {
+                let price = i64::from(i * 37 % 50);
+                doc.push(Box::new(NumericDocValuesField::new("price", price)));
+                price
+            };
+            writer.add_document(doc).unwrap();
+            expected.push((i, i64::from(i % 3), price));
+            if i == 60 {
+                writer.commit().unwrap();
+            }
+        }
+        writer.commit().unwrap();
+        // group ascending, then price descending
+        expected.sort_by(|a, b| a.1.cmp(&b.1).then(b.2.cmp(&a.2)).then(a.0.cmp(&b.0)));
+
+        let reader = writer.get_reader(true, false).unwrap();
+        let searcher = DefaultIndexSearcher::new(Arc::new(reader), None);
+        let mut price = SortField::Simple(SimpleSortField::new(
+            "price".into(),
+            SortFieldType::Long,
+            true,
+        ));
+        price.set_missing_value(Some(VariantValue::Long(-1)));
+        let sort = Sort::new(vec![
+            SortField::Simple(SimpleSortField::new(
+                "group".into(),
+                SortFieldType::Long,
+                false,
+            )),
+            price,
+        ]);
+
+        let mut collector = TopFieldCollector::new(sort.clone(), 10, false).unwrap();
+        searcher.search(&MatchAllDocsQuery, &mut collector).unwrap();
+        let top_docs = collector.top_docs();
+        assert_eq!(top_docs.total_hits(), 100);
+        assert_eq!(top_docs.score_docs().len(), 10);
+        for (hit, &(doc, group, price)) in top_docs.score_docs().iter().zip(&expected) {
+            match hit {
+                ScoreDocHit::Field(field_doc) => {
+                    assert_eq!(field_doc.doc, doc);
+                    assert_eq!(
+                        field_doc.fields,
+                        vec![VariantValue::Long(group), VariantValue::Long(price)]
+                    );
+                }
+                ScoreDocHit::Score(_) => panic!("expected a field doc"),
+            }
+        }
+
+        // page through all hits
+        let mut docs = vec![];
+        let mut after: Option<FieldDoc> = None;
+        loop {
+            let mut collector = match after {
+                Some(after) => TopFieldCollector::search_after(sort.clone(), 7, after, false),
+                None => TopFieldCollector::new(sort.clone(), 7, false),
+            }
+            .unwrap();
+            searcher.search(&MatchAllDocsQuery, &mut collector).unwrap();
+            let top_docs = collector.top_docs();
+            assert_eq!(top_docs.total_hits(), 100);
+            after = match top_docs.score_docs().last() {
+                Some(ScoreDocHit::Field(field_doc)) => Some(field_doc.clone()),
+                _ => break,
+            };
+            docs.extend(top_docs.score_docs().iter().map(|hit| hit.doc_id()));
+        }
+        let expected_docs: Vec<DocId> = expected.iter().map(|e| e.0).collect();
+        assert_eq!(docs, expected_docs);
+
+        let after = FieldDoc::new(0, 0.0, vec![VariantValue::Long(0)]);
+        assert!(TopFieldCollector::search_after(sort.clone(), 7, after, false).is_err());
+        assert!(TopFieldCollector::new(sort, 0, false).is_err());
+    }
+
+    fn collect_sorted_docs<C: Codec, IS: IndexSearcher<C>>(
+        searcher: &IS,
+        sort: &Sort,
+        page_size: usize,
+    ) -> Vec<(DocId, Vec<VariantValue>)> {
+        let mut docs = vec![];
+        let mut after: Option<FieldDoc> = None;
+        loop {
+            let mut collector = match after {
+                Some(after) => {
+                    TopFieldCollector::search_after(sort.clone(), page_size, after, false)
+                }
+                None => TopFieldCollector::new(sort.clone(), page_size, false),
+            }
+            .unwrap();
+            searcher.search(&MatchAllDocsQuery, &mut collector).unwrap();
+            let top_docs = collector.top_docs();
+            after = match top_docs.score_docs().last() {
+                Some(ScoreDocHit::Field(field_doc)) => Some(field_doc.clone()),
+                _ => break,
+            };
+            for hit in top_docs.score_docs() {
+                if let ScoreDocHit::Field(field_doc) = hit {
+                    docs.push((field_doc.doc, field_doc.fields.clone()));
+                }
+            }
+        }
+        docs
+    }
+
+    #[test]
+    fn test_sort_by_sorted_set() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let directory = Arc::new(FSDirectory::with_path(temp_dir.path()).unwrap());
+        let writer = IndexWriter::new(directory, Arc::new(IndexWriterConfig::default())).unwrap();
+
+        // every seventh doc has no tags, the others have one or two tags
+        let mut tags: Vec<Vec<String>> = vec![];
+        for i in 0..60 {
+            let mut doc_tags = vec![];
+            if i % 7 != 0 {
+                doc_tags.push(format!("t{:02}", i * 13 % 29));
+                doc_tags.push(format!("t{:02}", i * 7 % 23));
+                doc_tags.sort();
+                doc_tags.dedup();
+            }
+            let doc: Vec<Box<dyn Fieldable>> = doc_tags
+                .iter()
+                .map(|tag| {
+                    Box::new(SortedSetDocValuesField::new("tags", tag.as_bytes()))
+                        as Box<dyn Fieldable>
+                })
+                .collect();
+            writer.add_document(doc).unwrap();
+            tags.push(doc_tags);
+            if i == 25 {
+                writer.commit().unwrap();
+            }
+        }
+        writer.commit().unwrap();
+
+        let reader = writer.get_reader(true, false).unwrap();
+        let searcher = DefaultIndexSearcher::new(Arc::new(reader), None);
+        let value_of = |tag: Option<&String>| match tag {
+            Some(t) => VariantValue::Binary(t.as_bytes().to_vec()),
+            None => VariantValue::Null,
+        };
+
+        // min tag ascending, docs without tags last
+        let mut field = SortField::SortedSet(SortedSetSortField::with_field("tags".into()));
+        field.set_missing_value(Some(SortFieldMissingValue::StringLast.value()));
+        let sort = Sort::new(vec![field]);
+        let mut expected: Vec<(DocId, Option<&String>)> = tags
+            .iter()
+            .enumerate()
+            .map(|(doc, t)| (doc as DocId, t.first()))
+            .collect();
+        expected.sort_by(|a, b| {
+            a.1.is_none()
+                .cmp(&b.1.is_none())
+                .then(a.1.cmp(&b.1))
+                .then(a.0.cmp(&b.0))
+        });
+        let expected: Vec<(DocId, Vec<VariantValue>)> = expected
+            .into_iter()
+            .map(|(doc, tag)| (doc, vec![value_of(tag)]))
+            .collect();
+        assert_eq!(collect_sorted_docs(&searcher, &sort, 60), expected);
+        assert_eq!(collect_sorted_docs(&searcher, &sort, 9), expected);
+
+        // max tag descending, missing docs sort first before reversing
+        let sort = Sort::new(vec![SortField::SortedSet(SortedSetSortField::new(
+            "tags".into(),
+            true,
+            SortedSetSelectorType::Max,
+        ))]);
+        let mut expected: Vec<(DocId, Option<&String>)> = tags
+            .iter()
+            .enumerate()
+            .map(|(doc, t)| (doc as DocId, t.last()))
+            .collect();
+        expected.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        let expected: Vec<(DocId, Vec<VariantValue>)> = expected
+            .into_iter()
+            .map(|(doc, tag)| (doc, vec![value_of(tag)]))
+            .collect();
+        assert_eq!(collect_sorted_docs(&searcher, &sort, 60), expected);
+        assert_eq!(collect_sorted_docs(&searcher, &sort, 11), expected);
+    }
+}
@@ -0,0 +1,331 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use core::codec::Codec;
+use core::index::reader::LeafReaderContext;
+use core::search::expression::parser::{Node, Parser};
+use core::search::facet::{min_value, NumericFieldType, NumericValues};
+use core::util::{DocId, IndexedContext};
+
+use error::{ErrorKind::IllegalArgument, Result};
+
+/// The name of the variable that is bound to the score of the doc.
+pub const SCORE_VARIABLE: &str = "_score";
+
+/// What a variable of an expression reads.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Binding {
+    /// The score of the doc.
+    Score,
+    /// The value of a `NumericDocValues` or `SortedNumericDocValues` field,
+    /// the smallest one if the doc has several values, 0 if it has none.
+    Field(String, NumericFieldType),
+    /// A numeric value of the `IndexedContext` of the scorer, 0 if it is
+    /// not set.
+    Feature(usize),
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Binding::Score => write!(f, "{}", SCORE_VARIABLE),
+            Binding::Field(field, _) => write!(f, "doc['{}'].value", field),
+            Binding::Feature(index) => write!(f, "feature[{}]", index),
+        }
+    }
+}
+
+/// Maps the variable names of expressions to what they read. `_score` is
+/// always bound to the score of the doc.
+#[derive(Debug, Clone, Default)]
+pub struct Bindings {
+    bindings: HashMap<String, Binding>,
+}
+
+impl Bindings {
+    pub fn add(&mut self, name: String, binding: Binding) {
+        self.bindings.insert(name, binding);
+    }
+
+    /// Binds the variable named like `field` to its doc values.
+    pub fn add_field(&mut self, field: &str, field_type: NumericFieldType) {
+        self.add(
+            field.to_string(),
+            Binding::Field(field.to_string(), field_type),
+        );
+    }
+
+    pub fn add_feature(&mut self, name: String, index: usize) {
+        self.add(name, Binding::Feature(index));
+    }
+
+    fn get(&self, name: &str) -> Option<Binding> {
+        if name == SCORE_VARIABLE {
+            Some(Binding::Score)
+        } else {
+            self.bindings.get(name).cloned()
+        }
+    }
+}
+
+/// A compiled expression, that computes a double from the score of a doc,
+/// its numeric doc values and the features of its score context.
+///
+/// The expressions support:
+///
+/// * number literals, e.g. `2`, `0.5` or `1e-3`
+/// * the arithmetic operators `+`, `-`, `*`, `/` and `%`
+/// * the comparison operators `<`, `<=`, `>`, `>=`, `==` and `!=`, and the logical operators `&&`,
+///   `||` and `!`, where `0` is false and any other value is true; they return `1` for true and `0`
+///   for false
+/// * the ternary operator `cond ? a : b`
+/// * the functions `abs`, `ceil`, `floor`, `exp`, `ln`, `log10`, `log1p`, `sqrt`, `pow(x, y)`, and
+///   `min` and `max` of two or more arguments
+/// * variables, which must be bound to a `Binding` when the expression is compiled
+///
+/// e.g. `_score * ln(2 + popularity) + (in_stock ? 1 : 0)`.
+///
+/// An expression is parsed and its variables resolved once, and is then
+/// evaluated per doc by a `LeafExpression`.
+#[derive(Debug, Clone)]
+pub struct Expression {
+    source: String,
+    root: Node,
+    variables: Vec<(String, Binding)>,
+}
+
+impl Expression {
+    pub fn compile(source: &str, bindings: &Bindings) -> Result<Expression> {
+        let (root, names) = Parser::parse(source)?;
+        let mut variables = Vec::with_capacity(names.len());
+        for name in names {
+            match bindings.get(&name) {
+                Some(binding) => variables.push((name, binding)),
+                None => bail!(IllegalArgument(format!(
+                    "unbound variable '{}' in expression '{}'",
+                    name, source
+                ))),
+            }
+        }
+        Ok(Expression {
+            source: source.to_string(),
+            root,
+            variables,
+        })
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// The variables of the expression, in order of appearance.
+    pub fn variables(&self) -> &[(String, Binding)] {
+        &self.variables
+    }
+
+    pub fn needs_scores(&self) -> bool {
+        self.variables.iter().any(|(_, b)| *b == Binding::Score)
+    }
+
+    /// Whether the expression reads the `IndexedContext` of the scorer.
+    pub fn needs_features(&self) -> bool {
+        self.variables.iter().any(|(_, b)| match b {
+            Binding::Feature(_) => true,
+            _ => false,
+        })
+    }
+
+    /// Evaluates the expression with the values of its variables, in the
+    /// order of `variables()`.
+    pub fn evaluate(&self, values: &[f64]) -> f64 {
+        debug_assert_eq!(values.len(), self.variables.len());
+        self.root.evaluate(values)
+    }
+}
+
+impl PartialEq for Expression {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source && self.variables == other.variables
+    }
+}
+
+impl Eq for Expression {}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+/// An `Expression` bound to the doc values of a leaf.
+pub struct LeafExpression {
+    expression: Arc<Expression>,
+    // the doc values of the field variables, by variable
+    fields: Vec<Option<(NumericValues, NumericFieldType)>>,
+    values: Vec<f64>,
+    value_buf: Vec<i64>,
+}
+
+impl LeafExpression {
+    pub fn new<C: Codec>(
+        expression: Arc<Expression>,
+        reader: &LeafReaderContext<'_, C>,
+    ) -> Result<LeafExpression> {
+        let mut fields = Vec::with_capacity(expression.variables.len());
+        for (_, binding) in &expression.variables {
+            fields.push(match binding {
+                Binding::Field(field, field_type) => {
+                    Some((NumericValues::new(reader, field, *field_type)?, *field_type))
+                }
+                _ => None,
+            });
+        }
+        let values = vec![0.0; expression.variables.len()];
+        Ok(LeafExpression {
+            expression,
+            fields,
+            values,
+            value_buf: vec![],
+        })
+    }
+
+    pub fn expression(&self) -> &Expression {
+        &self.expression
+    }
+
+    /// Evaluates the expression for `doc`, `features` are only read by the
+    /// feature variables.
+    pub fn evaluate(&mut self, doc: DocId, score: f32, features: &IndexedContext) -> Result<f64> {
+        for (i, (_, binding)) in self.expression.variables.iter().enumerate() {
+            self.values[i] = match binding {
+                Binding::Score => f64::from(score),
+                Binding::Field(..) => {
+                    let (values, field_type) = self.fields[i].as_mut().unwrap();
+                    values.read(doc, &mut self.value_buf)?;
+                    let field_type = *field_type;
+                    min_value(self.value_buf.iter().map(|&raw| field_type.to_f64(raw)))
+                        .unwrap_or(0.0)
+                }
+                Binding::Feature(index) => features
+                    .get(*index)
+                    .and_then(|v| v.get_numeric())
+                    .map_or(0.0, |v| v.double_value()),
+            };
+        }
+        Ok(self.expression.evaluate(&self.values))
+    }
+
+    /// The values of the variables of the last evaluated doc.
+    pub fn values(&self) -> &[f64] {
+        &self.values
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(source: &str, values: &[(&str, f64)]) -> Result<f64> {
+        let mut bindings = Bindings::default();
+        for (i, (name, _)) in values.iter().enumerate() {
+            bindings.add_feature((*name).to_string(), i);
+        }
+        let expression = Expression::compile(source, &bindings)?;
+        let values: Vec<f64> = expression
+            .variables()
+            .iter()
+            .map(|(name, _)| values.iter().find(|(n, _)| n == name).unwrap().1)
+            .collect();
+        Ok(expression.evaluate(&values))
+    }
+
+    fn assert_eval(source: &str, values: &[(&str, f64)], expected: f64) {
+        let actual = eval(source, values).unwrap();
+        assert!(
+            (actual - expected).abs() < ::std::f64::EPSILON,
+            "{} = {}",
+            source,
+            actual
+        );
+    }
+
+    #[test]
+    fn test_evaluate() {
+        assert_eval("1 + 2 * 3", &[], 7.0);
+        assert_eval("(1 + 2) * 3", &[], 9.0);
+        assert_eval("10 - 4 - 3", &[], 3.0);
+        assert_eval("2 * -3 % 4", &[], -2.0);
+        assert_eval("-.5e1", &[], -5.0);
+        assert_eval("sqrt(16) + ln(1) + log1p(0)", &[], 4.0);
+        assert_eval("max(1, x, 3)", &[("x", 5.0)], 5.0);
+        assert_eval("min(x, 2) + pow(2, 10)", &[("x", 5.0)], 1026.0);
+        assert_eval("x > 2 && x <= 3", &[("x", 3.0)], 1.0);
+        assert_eval("x == 2 || !x", &[("x", 3.0)], 0.0);
+        assert_eval("x > 2 ? y : 1 / y", &[("x", 1.0), ("y", 4.0)], 0.25);
+        // the ternary operator is right associative
+        assert_eval("x < 0 ? -1 : x > 0 ? 1 : 0", &[("x", 7.0)], 1.0);
+        assert_eval("a.b * 2", &[("a.b", 1.5)], 3.0);
+
+        for source in &[
+            "",
+            "1 +",
+            "(1",
+            "1 2",
+            "x ? 1",
+            "foo(1)",
+            "sqrt(1, 2)",
+            "max(1)",
+            "1 # 2",
+            "1..2",
+            "unbound",
+        ] {
+            assert!(eval(source, &[("x", 1.0)]).is_err(), "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_compile() {
+        let mut bindings = Bindings::default();
+        bindings.add_field("popularity", NumericFieldType::Long);
+        bindings.add_feature("ctr".into(), 2);
+        let expression =
+            Expression::compile("_score * ln(2 + popularity) + ctr * popularity", &bindings)
+                .unwrap();
+        assert_eq!(
+            expression.variables(),
+            &[
+                (SCORE_VARIABLE.to_string(), Binding::Score),
+                (
+                    "popularity".to_string(),
+                    Binding::Field("popularity".into(), NumericFieldType::Long)
+                ),
+                ("ctr".to_string(), Binding::Feature(2)),
+            ]
+        );
+        assert!(expression.needs_scores());
+        assert!(expression.needs_features());
+
+        // constants are folded
+        let expression = Expression::compile("max(1, 2) * (3 > 2 ? 4 : 5)", &bindings).unwrap();
+        match &expression.root {
+            Node::Const(v) => assert!((v - 8.0).abs() < ::std::f64::EPSILON),
+            _ => panic!("{:?} is not folded", expression.root),
+        }
+        assert!(!expression.needs_scores());
+        assert!(!expression.needs_features());
+    }
+}
@@ -0,0 +1,26 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A small expression language to compute scores and sort values.
+//!
+//! An [`Expression`] is compiled once from its source and the [`Bindings`]
+//! of its variables, which read the score of a doc, numeric doc values
+//! fields or the features of the `IndexedContext` of the scorer. It is then
+//! evaluated per doc by a [`LeafExpression`], e.g. by a `ScriptScoreQuery`
+//! or to sort by an `ExpressionSortField`.
+
+mod parser;
+
+mod expression;
+
+pub use self::expression::*;
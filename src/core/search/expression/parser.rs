@@ -0,0 +1,567 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::f64;
+use std::fmt;
+
+use error::{ErrorKind::IllegalArgument, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum UnaryOp {
+    Neg,
+    Not,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+    And,
+    Or,
+}
+
+impl BinaryOp {
+    fn apply(self, a: f64, b: f64) -> f64 {
+        match self {
+            BinaryOp::Add => a + b,
+            BinaryOp::Sub => a - b,
+            BinaryOp::Mul => a * b,
+            BinaryOp::Div => a / b,
+            BinaryOp::Rem => a % b,
+            BinaryOp::Lt => bool_value(a < b),
+            BinaryOp::Le => bool_value(a <= b),
+            BinaryOp::Gt => bool_value(a > b),
+            BinaryOp::Ge => bool_value(a >= b),
+            BinaryOp::Eq => bool_value(float_eq(a, b)),
+            BinaryOp::Ne => bool_value(!float_eq(a, b)),
+            // the logical operators are short-circuited by `Node::evaluate`
+            BinaryOp::And => bool_value(is_true(a) && is_true(b)),
+            BinaryOp::Or => bool_value(is_true(a) || is_true(b)),
+        }
+    }
+}
+
+/// The functions that may be called in an expression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Function {
+    Abs,
+    Ceil,
+    Floor,
+    Exp,
+    Ln,
+    Log10,
+    Log1p,
+    Sqrt,
+    Pow,
+    Min,
+    Max,
+}
+
+impl Function {
+    fn from_name(name: &str) -> Option<Function> {
+        let function = match name {
+            "abs" => Function::Abs,
+            "ceil" => Function::Ceil,
+            "floor" => Function::Floor,
+            "exp" => Function::Exp,
+            "ln" => Function::Ln,
+            "log10" => Function::Log10,
+            "log1p" => Function::Log1p,
+            "sqrt" => Function::Sqrt,
+            "pow" => Function::Pow,
+            "min" => Function::Min,
+            "max" => Function::Max,
+            _ => return None,
+        };
+        Some(function)
+    }
+
+    /// Whether the function accepts `n` arguments.
+    fn accepts(self, n: usize) -> bool {
+        match self {
+            Function::Pow => n == 2,
+            Function::Min | Function::Max => n >= 2,
+            _ => n == 1,
+        }
+    }
+
+    fn apply(self, args: &[f64]) -> f64 {
+        match self {
+            Function::Abs => args[0].abs(),
+            Function::Ceil => args[0].ceil(),
+            Function::Floor => args[0].floor(),
+            Function::Exp => args[0].exp(),
+            Function::Ln => args[0].ln(),
+            Function::Log10 => args[0].log10(),
+            Function::Log1p => args[0].ln_1p(),
+            Function::Sqrt => args[0].sqrt(),
+            Function::Pow => args[0].powf(args[1]),
+            Function::Min => args.iter().cloned().fold(f64::INFINITY, f64::min),
+            Function::Max => args.iter().cloned().fold(f64::NEG_INFINITY, f64::max),
+        }
+    }
+}
+
+/// A node of the syntax tree of an expression. The variables are resolved to
+/// their index in the variables of the expression when it is parsed.
+#[derive(Debug, Clone)]
+pub(crate) enum Node {
+    Const(f64),
+    Var(usize),
+    Unary(UnaryOp, Box<Node>),
+    Binary(BinaryOp, Box<Node>, Box<Node>),
+    Call(Function, Vec<Node>),
+    Cond(Box<Node>, Box<Node>, Box<Node>),
+}
+
+impl Node {
+    /// Evaluates the node with the given values of the variables.
+    pub(crate) fn evaluate(&self, vars: &[f64]) -> f64 {
+        match self {
+            Node::Const(v) => *v,
+            Node::Var(i) => vars[*i],
+            Node::Unary(UnaryOp::Neg, node) => -node.evaluate(vars),
+            Node::Unary(UnaryOp::Not, node) => bool_value(!is_true(node.evaluate(vars))),
+            Node::Binary(BinaryOp::And, a, b) => {
+                bool_value(is_true(a.evaluate(vars)) && is_true(b.evaluate(vars)))
+            }
+            Node::Binary(BinaryOp::Or, a, b) => {
+                bool_value(is_true(a.evaluate(vars)) || is_true(b.evaluate(vars)))
+            }
+            Node::Binary(op, a, b) => op.apply(a.evaluate(vars), b.evaluate(vars)),
+            Node::Call(function, args) => {
+                let args: Vec<f64> = args.iter().map(|arg| arg.evaluate(vars)).collect();
+                function.apply(&args)
+            }
+            Node::Cond(cond, a, b) => {
+                if is_true(cond.evaluate(vars)) {
+                    a.evaluate(vars)
+                } else {
+                    b.evaluate(vars)
+                }
+            }
+        }
+    }
+
+    fn is_const(&self) -> bool {
+        match self {
+            Node::Const(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Replaces the node by its value if it doesn't depend on any variable.
+    fn fold(self) -> Node {
+        let constant = match &self {
+            Node::Const(_) | Node::Var(_) => false,
+            Node::Unary(_, node) => node.is_const(),
+            Node::Binary(_, a, b) => a.is_const() && b.is_const(),
+            Node::Call(_, args) => args.iter().all(Node::is_const),
+            Node::Cond(cond, a, b) => cond.is_const() && a.is_const() && b.is_const(),
+        };
+        if constant {
+            Node::Const(self.evaluate(&[]))
+        } else {
+            self
+        }
+    }
+}
+
+#[allow(clippy::float_cmp)]
+fn float_eq(a: f64, b: f64) -> bool {
+    a == b
+}
+
+fn is_true(v: f64) -> bool {
+    v != 0.0
+}
+
+fn bool_value(b: bool) -> f64 {
+    if b {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    LParen,
+    RParen,
+    Comma,
+    Question,
+    Colon,
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Percent,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    EqEq,
+    Ne,
+    Bang,
+    AndAnd,
+    OrOr,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Token::Number(v) => return write!(f, "{}", v),
+            Token::Ident(name) => return write!(f, "{}", name),
+            Token::LParen => "(",
+            Token::RParen => ")",
+            Token::Comma => ",",
+            Token::Question => "?",
+            Token::Colon => ":",
+            Token::Plus => "+",
+            Token::Minus => "-",
+            Token::Star => "*",
+            Token::Slash => "/",
+            Token::Percent => "%",
+            Token::Lt => "<",
+            Token::Le => "<=",
+            Token::Gt => ">",
+            Token::Ge => ">=",
+            Token::EqEq => "==",
+            Token::Ne => "!=",
+            Token::Bang => "!",
+            Token::AndAnd => "&&",
+            Token::OrOr => "||",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// Splits the source into tokens, along with their offsets in the source.
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        if c.is_ascii_digit() || (c == '.' && i + 1 < chars.len() && chars[i + 1].is_ascii_digit())
+        {
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            if i < chars.len() && (chars[i] == 'e' || chars[i] == 'E') {
+                i += 1;
+                if i < chars.len() && (chars[i] == '+' || chars[i] == '-') {
+                    i += 1;
+                }
+                while i < chars.len() && chars[i].is_ascii_digit() {
+                    i += 1;
+                }
+            }
+            let text: String = chars[start..i].iter().collect();
+            match text.parse() {
+                Ok(v) => tokens.push((Token::Number(v), start)),
+                Err(_) => bail!(IllegalArgument(format!(
+                    "invalid number '{}' at position {} of expression '{}'",
+                    text, start, source
+                ))),
+            }
+            continue;
+        }
+        if c.is_ascii_alphabetic() || c == '_' {
+            while i < chars.len()
+                && (chars[i].is_ascii_alphanumeric() || chars[i] == '_' || chars[i] == '.')
+            {
+                i += 1;
+            }
+            tokens.push((Token::Ident(chars[start..i].iter().collect()), start));
+            continue;
+        }
+        let next = chars.get(i + 1).cloned();
+        let (token, len) = match (c, next) {
+            ('<', Some('=')) => (Token::Le, 2),
+            ('>', Some('=')) => (Token::Ge, 2),
+            ('=', Some('=')) => (Token::EqEq, 2),
+            ('!', Some('=')) => (Token::Ne, 2),
+            ('&', Some('&')) => (Token::AndAnd, 2),
+            ('|', Some('|')) => (Token::OrOr, 2),
+            ('(', _) => (Token::LParen, 1),
+            (')', _) => (Token::RParen, 1),
+            (',', _) => (Token::Comma, 1),
+            ('?', _) => (Token::Question, 1),
+            (':', _) => (Token::Colon, 1),
+            ('+', _) => (Token::Plus, 1),
+            ('-', _) => (Token::Minus, 1),
+            ('*', _) => (Token::Star, 1),
+            ('/', _) => (Token::Slash, 1),
+            ('%', _) => (Token::Percent, 1),
+            ('<', _) => (Token::Lt, 1),
+            ('>', _) => (Token::Gt, 1),
+            ('!', _) => (Token::Bang, 1),
+            _ => bail!(IllegalArgument(format!(
+                "unexpected character '{}' at position {} of expression '{}'",
+                c, start, source
+            ))),
+        };
+        tokens.push((token, start));
+        i += len;
+    }
+    Ok(tokens)
+}
+
+/// A recursive descent parser of expressions, from the lowest precedence to
+/// the highest:
+///
+/// * `cond ? a : b`, right associative
+/// * `||`
+/// * `&&`
+/// * `==`, `!=`
+/// * `<`, `<=`, `>`, `>=`
+/// * `+`, `-`
+/// * `*`, `/`, `%`
+/// * unary `-`, `+` and `!`
+///
+/// The variables are added to `variables` in order of appearance.
+pub(crate) struct Parser<'a> {
+    source: &'a str,
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    variables: Vec<String>,
+}
+
+impl<'a> Parser<'a> {
+    /// Parses `source`, and returns its syntax tree and the names of its
+    /// variables.
+    pub(crate) fn parse(source: &'a str) -> Result<(Node, Vec<String>)> {
+        let mut parser = Parser {
+            source,
+            tokens: tokenize(source)?,
+            pos: 0,
+            variables: vec![],
+        };
+        if parser.tokens.is_empty() {
+            bail!(IllegalArgument("expression is empty".into()));
+        }
+        let node = parser.conditional()?;
+        if parser.pos < parser.tokens.len() {
+            return parser.unexpected();
+        }
+        Ok((node, parser.variables))
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(token, _)| token)
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &Token) -> Result<()> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            self.unexpected()
+        }
+    }
+
+    fn unexpected<T>(&self) -> Result<T> {
+        match self.tokens.get(self.pos) {
+            Some((token, offset)) => bail!(IllegalArgument(format!(
+                "unexpected '{}' at position {} of expression '{}'",
+                token, offset, self.source
+            ))),
+            None => bail!(IllegalArgument(format!(
+                "unexpected end of expression '{}'",
+                self.source
+            ))),
+        }
+    }
+
+    fn conditional(&mut self) -> Result<Node> {
+        let cond = self.or()?;
+        if !self.eat(&Token::Question) {
+            return Ok(cond);
+        }
+        let a = self.conditional()?;
+        self.expect(&Token::Colon)?;
+        let b = self.conditional()?;
+        Ok(Node::Cond(Box::new(cond), Box::new(a), Box::new(b)).fold())
+    }
+
+    /// Parses a left associative sequence of binary operators of the same
+    /// precedence.
+    fn binary<F>(&mut self, operand: F, ops: &[(Token, BinaryOp)]) -> Result<Node>
+    where
+        F: Fn(&mut Self) -> Result<Node>,
+    {
+        let mut node = operand(self)?;
+        'outer: loop {
+            for (token, op) in ops {
+                if self.eat(token) {
+                    let rhs = operand(self)?;
+                    node = Node::Binary(*op, Box::new(node), Box::new(rhs)).fold();
+                    continue 'outer;
+                }
+            }
+            return Ok(node);
+        }
+    }
+
+    fn or(&mut self) -> Result<Node> {
+        self.binary(Self::and, &[(Token::OrOr, BinaryOp::Or)])
+    }
+
+    fn and(&mut self) -> Result<Node> {
+        self.binary(Self::equality, &[(Token::AndAnd, BinaryOp::And)])
+    }
+
+    fn equality(&mut self) -> Result<Node> {
+        self.binary(
+            Self::comparison,
+            &[(Token::EqEq, BinaryOp::Eq), (Token::Ne, BinaryOp::Ne)],
+        )
+    }
+
+    fn comparison(&mut self) -> Result<Node> {
+        self.binary(
+            Self::additive,
+            &[
+                (Token::Le, BinaryOp::Le),
+                (Token::Lt, BinaryOp::Lt),
+                (Token::Ge, BinaryOp::Ge),
+                (Token::Gt, BinaryOp::Gt),
+            ],
+        )
+    }
+
+    fn additive(&mut self) -> Result<Node> {
+        self.binary(
+            Self::multiplicative,
+            &[(Token::Plus, BinaryOp::Add), (Token::Minus, BinaryOp::Sub)],
+        )
+    }
+
+    fn multiplicative(&mut self) -> Result<Node> {
+        self.binary(
+            Self::unary,
+            &[
+                (Token::Star, BinaryOp::Mul),
+                (Token::Slash, BinaryOp::Div),
+                (Token::Percent, BinaryOp::Rem),
+            ],
+        )
+    }
+
+    fn unary(&mut self) -> Result<Node> {
+        if self.eat(&Token::Minus) {
+            let node = self.unary()?;
+            Ok(Node::Unary(UnaryOp::Neg, Box::new(node)).fold())
+        } else if self.eat(&Token::Bang) {
+            let node = self.unary()?;
+            Ok(Node::Unary(UnaryOp::Not, Box::new(node)).fold())
+        } else if self.eat(&Token::Plus) {
+            self.unary()
+        } else {
+            self.primary()
+        }
+    }
+
+    fn primary(&mut self) -> Result<Node> {
+        let token = match self.peek() {
+            Some(token) => token.clone(),
+            None => return self.unexpected(),
+        };
+        match token {
+            Token::Number(v) => {
+                self.pos += 1;
+                Ok(Node::Const(v))
+            }
+            Token::LParen => {
+                self.pos += 1;
+                let node = self.conditional()?;
+                self.expect(&Token::RParen)?;
+                Ok(node)
+            }
+            Token::Ident(name) => {
+                self.pos += 1;
+                if self.peek() == Some(&Token::LParen) {
+                    self.call(&name)
+                } else {
+                    Ok(Node::Var(self.variable(name)))
+                }
+            }
+            _ => self.unexpected(),
+        }
+    }
+
+    fn call(&mut self, name: &str) -> Result<Node> {
+        let function = match Function::from_name(name) {
+            Some(function) => function,
+            None => bail!(IllegalArgument(format!(
+                "unknown function '{}' in expression '{}'",
+                name, self.source
+            ))),
+        };
+        self.expect(&Token::LParen)?;
+        let mut args = vec![];
+        if !self.eat(&Token::RParen) {
+            loop {
+                args.push(self.conditional()?);
+                if self.eat(&Token::RParen) {
+                    break;
+                }
+                self.expect(&Token::Comma)?;
+            }
+        }
+        if !function.accepts(args.len()) {
+            bail!(IllegalArgument(format!(
+                "function '{}' can't be called with {} arguments in expression '{}'",
+                name,
+                args.len(),
+                self.source
+            )));
+        }
+        Ok(Node::Call(function, args).fold())
+    }
+
+    fn variable(&mut self, name: String) -> usize {
+        match self.variables.iter().position(|v| *v == name) {
+            Some(i) => i,
+            None => {
+                self.variables.push(name);
+                self.variables.len() - 1
+            }
+        }
+    }
+}
@@ -0,0 +1,317 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::mem;
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use core::codec::doc_values::lucene54::DocValues;
+use core::codec::doc_values::{SortedSetDocValues, NO_MORE_ORDS};
+use core::codec::Codec;
+use core::doc::DocValuesType;
+use core::index::reader::LeafReaderContext;
+use core::search::collector::{Collector, ParallelLeafCollector, SearchCollector};
+use core::search::facet::{NumericFieldType, NumericValues};
+use core::search::scorer::Scorer;
+use core::search::NO_MORE_DOCS;
+use core::util::{BitSet, DocId, FixedBitSet, HyperLogLogPlusPlus, ImmutableBitSet};
+
+use error::{
+    ErrorKind::{IllegalArgument, IllegalState},
+    Result, ResultExt,
+};
+
+/// The values of the field in a leaf.
+enum CardinalityValues {
+    /// Sorted or sorted set doc values: the ordinals of the hits are gathered,
+    /// and their values are hashed once the leaf is done.
+    Ordinals {
+        values: Box<dyn SortedSetDocValues>,
+        seen: FixedBitSet,
+    },
+    /// Numeric or sorted numeric doc values, hashed per hit.
+    Numeric {
+        values: NumericValues,
+        buf: Vec<i64>,
+    },
+}
+
+impl CardinalityValues {
+    fn new<C: Codec>(reader: &LeafReaderContext<'_, C>, field: &str) -> Result<Self> {
+        match reader.reader.field_info(field).map(|fi| fi.doc_values_type) {
+            Some(DocValuesType::Sorted) | Some(DocValuesType::SortedSet) => {
+                let values = DocValues::get_sorted_set_view(reader.reader, field)?;
+                let seen = FixedBitSet::new(values.get_value_count());
+                Ok(CardinalityValues::Ordinals { values, seen })
+            }
+            Some(DocValuesType::Numeric)
+            | Some(DocValuesType::SortedNumeric)
+            | Some(DocValuesType::Null)
+            | None => Ok(CardinalityValues::Numeric {
+                // the distinct values are the distinct stored longs, whatever they encode
+                values: NumericValues::new(reader, field, NumericFieldType::Long)?,
+                buf: vec![],
+            }),
+            Some(dv_type) => bail!(IllegalArgument(format!(
+                "can't count the distinct values of field '{}' with doc values of type {:?}",
+                field, dv_type
+            ))),
+        }
+    }
+
+    fn collect(&mut self, doc: DocId, counter: &mut HyperLogLogPlusPlus) -> Result<()> {
+        match self {
+            CardinalityValues::Ordinals { values, seen } => {
+                values.set_document(doc)?;
+                loop {
+                    let ord = values.next_ord()?;
+                    if ord == NO_MORE_ORDS {
+                        break;
+                    }
+                    seen.set(ord as usize);
+                }
+            }
+            CardinalityValues::Numeric { values, buf } => {
+                values.read(doc, buf)?;
+                for &value in buf.iter() {
+                    counter.add_long(value);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds the values of the ordinals gathered so far to `counter`.
+    fn finish(&mut self, counter: &mut HyperLogLogPlusPlus) -> Result<()> {
+        if let CardinalityValues::Ordinals { values, seen } = self {
+            let num_ords = values.get_value_count();
+            let mut ord = if num_ords > 0 {
+                seen.next_set_bit(0)
+            } else {
+                NO_MORE_DOCS
+            };
+            while ord != NO_MORE_DOCS {
+                counter.add_bytes(&values.lookup_ord(i64::from(ord))?);
+                seen.clear(ord as usize);
+                ord = if (ord as usize) + 1 < num_ords {
+                    seen.next_set_bit(ord as usize + 1)
+                } else {
+                    NO_MORE_DOCS
+                };
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A `Collector` that estimates the number of distinct values of a field
+/// over the hits of a query, with a `HyperLogLogPlusPlus` counter.
+///
+/// Keyword fields, i.e. `SortedDocValues` or `SortedSetDocValues`, are counted
+/// by ordinal and each value is hashed once per segment. Numeric fields are
+/// hashed per hit.
+pub struct CardinalityCollector {
+    field: String,
+    counter: HyperLogLogPlusPlus,
+    current: Option<CardinalityValues>,
+    channel: Option<(Sender<HyperLogLogPlusPlus>, Receiver<HyperLogLogPlusPlus>)>,
+}
+
+impl CardinalityCollector {
+    /// Creates a collector with a counter of the given precision, see
+    /// `HyperLogLogPlusPlus::new`.
+    pub fn new(field: String, precision: u32) -> Result<Self> {
+        Ok(CardinalityCollector {
+            field,
+            counter: HyperLogLogPlusPlus::new(precision)?,
+            current: None,
+            channel: None,
+        })
+    }
+
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    /// Returns the estimated number of distinct values of the hits collected.
+    pub fn cardinality(&mut self) -> Result<u64> {
+        if let Some(ref mut values) = self.current {
+            values.finish(&mut self.counter)?;
+        }
+        Ok(self.counter.cardinality())
+    }
+
+    /// Returns the counter of the hits collected, e.g. to merge it with the
+    /// counter of another search.
+    pub fn counter(&mut self) -> Result<&HyperLogLogPlusPlus> {
+        if let Some(ref mut values) = self.current {
+            values.finish(&mut self.counter)?;
+        }
+        Ok(&self.counter)
+    }
+}
+
+impl SearchCollector for CardinalityCollector {
+    type LC = CardinalityLeafCollector;
+
+    fn set_next_reader<C: Codec>(&mut self, reader: &LeafReaderContext<'_, C>) -> Result<()> {
+        if let Some(mut values) = self.current.take() {
+            values.finish(&mut self.counter)?;
+        }
+        self.current = Some(CardinalityValues::new(reader, &self.field)?);
+        Ok(())
+    }
+
+    fn support_parallel(&self) -> bool {
+        true
+    }
+
+    fn init_parallel(&mut self) {
+        if self.channel.is_none() {
+            self.channel = Some(channel());
+        }
+    }
+
+    fn leaf_collector<C: Codec>(
+        &self,
+        reader: &LeafReaderContext<'_, C>,
+    ) -> Result<CardinalityLeafCollector> {
+        Ok(CardinalityLeafCollector {
+            values: CardinalityValues::new(reader, &self.field)?,
+            counter: HyperLogLogPlusPlus::new(self.counter.precision())?,
+            channel: self.channel.as_ref().unwrap().0.clone(),
+        })
+    }
+
+    fn finish_parallel(&mut self) -> Result<()> {
+        let channel = self.channel.take();
+        // iff all the `weight.create_scorer(leaf_reader)` return None, the channel won't
+        // inited and thus stay None
+        if let Some((sender, receiver)) = channel {
+            drop(sender);
+            while let Ok(counter) = receiver.recv() {
+                self.counter.merge(&counter)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Collector for CardinalityCollector {
+    fn needs_scores(&self) -> bool {
+        false
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, _scorer: &mut S) -> Result<()> {
+        match self.current {
+            Some(ref mut values) => values.collect(doc, &mut self.counter),
+            None => bail!(IllegalState(
+                "set_next_reader must be called before collect".into()
+            )),
+        }
+    }
+}
+
+pub struct CardinalityLeafCollector {
+    values: CardinalityValues,
+    counter: HyperLogLogPlusPlus,
+    channel: Sender<HyperLogLogPlusPlus>,
+}
+
+impl ParallelLeafCollector for CardinalityLeafCollector {
+    fn finish_leaf(&mut self) -> Result<()> {
+        self.values.finish(&mut self.counter)?;
+        let counter = HyperLogLogPlusPlus::new(self.counter.precision())?;
+        let counter = mem::replace(&mut self.counter, counter);
+        self.channel
+            .send(counter)
+            .chain_err(|| IllegalState("channel unexpected closed before search complete".into()))
+    }
+}
+
+impl Collector for CardinalityLeafCollector {
+    fn needs_scores(&self) -> bool {
+        false
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, _scorer: &mut S) -> Result<()> {
+        self.values.collect(doc, &mut self.counter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate tempfile;
+
+    use super::*;
+    use core::doc::{Fieldable, NumericDocValuesField, SortedSetDocValuesField};
+    use core::index::writer::{IndexWriter, IndexWriterConfig};
+    use core::search::collector::{ChainedCollector, TopDocsCollector};
+    use core::search::query::MatchAllDocsQuery;
+    use core::search::{DefaultIndexSearcher, IndexSearcher};
+    use core::store::directory::FSDirectory;
+
+    use std::sync::Arc;
+
+    #[test]
+    fn test_cardinality_collector() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let directory = Arc::new(FSDirectory::with_path(temp_dir.path()).unwrap());
+        let writer = IndexWriter::new(directory, Arc::new(IndexWriterConfig::default())).unwrap();
+
+        // 300 users across three segments, every doc tagged with two of 40 tags
+        for i in 0..1500i64 {
+            let doc: Vec<Box<dyn Fieldable>> = vec![
+                Box::new(NumericDocValuesField::new("user", i % 300)),
+                Box::new(SortedSetDocValuesField::new(
+                    "tags",
+                    format!("tag{}", i % 40).as_bytes(),
+                )),
+                Box::new(SortedSetDocValuesField::new(
+                    "tags",
+                    format!("tag{}", (i + 7) % 40).as_bytes(),
+                )),
+            ];
+            writer.add_document(doc).unwrap();
+            if i % 500 == 499 {
+                writer.commit().unwrap();
+            }
+        }
+        writer.commit().unwrap();
+
+        let reader = writer.get_reader(true, false).unwrap();
+        let searcher = DefaultIndexSearcher::new(Arc::new(reader), None);
+
+        let mut users =
+            CardinalityCollector::new("user".into(), HyperLogLogPlusPlus::DEFAULT_PRECISION)
+                .unwrap();
+        let mut tags = CardinalityCollector::new("tags".into(), 10).unwrap();
+        let mut top_docs = TopDocsCollector::new(10);
+        {
+            let mut collector =
+                ChainedCollector::new(&mut top_docs, ChainedCollector::new(&mut users, &mut tags));
+            searcher.search(&MatchAllDocsQuery, &mut collector).unwrap();
+        }
+        assert_eq!(top_docs.top_docs().total_hits(), 1500);
+        // small cardinalities are exact
+        assert_eq!(users.cardinality().unwrap(), 300);
+        assert_eq!(tags.cardinality().unwrap(), 40);
+        assert_eq!(tags.counter().unwrap().precision(), 10);
+
+        let mut missing =
+            CardinalityCollector::new("missing".into(), HyperLogLogPlusPlus::DEFAULT_PRECISION)
+                .unwrap();
+        searcher.search(&MatchAllDocsQuery, &mut missing).unwrap();
+        assert_eq!(missing.cardinality().unwrap(), 0);
+    }
+}
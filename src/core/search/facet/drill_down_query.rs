@@ -0,0 +1,123 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::any::Any;
+use std::fmt;
+use std::sync::Arc;
+
+use core::codec::Codec;
+use core::search::query::{
+    BooleanQuery, DocValuesTermsQuery, MatchAllDocsQuery, Query, TermQuery, Weight,
+};
+use core::search::searcher::SearchPlanBuilder;
+
+use error::Result;
+
+/// A `Query` for drilling down into the facets of a base query.
+///
+/// Every dimension added filters the hits of the base query, the values of a
+/// dimension are OR'ed and the dimensions are AND'ed, e.g. `tag:(a OR b) AND
+/// author:c`. The filters don't change the scores of the base query.
+pub struct DrillDownQuery<C: Codec> {
+    base_query: Option<Arc<dyn Query<C>>>,
+    // (dimension, values), in the order they were added
+    dims: Vec<(String, Vec<Vec<u8>>)>,
+}
+
+impl<C: Codec> DrillDownQuery<C> {
+    /// Creates a drill-down over `base_query`, or over all documents if `None`.
+    pub fn new(base_query: Option<Box<dyn Query<C>>>) -> Self {
+        DrillDownQuery {
+            base_query: base_query.map(Arc::from),
+            dims: vec![],
+        }
+    }
+
+    /// Restricts the hits to documents having `value` in dimension `dim`.
+    pub fn add(&mut self, dim: &str, value: Vec<u8>) {
+        if let Some((_, values)) = self.dims.iter_mut().find(|(d, _)| d == dim) {
+            values.push(value);
+            return;
+        }
+        self.dims.push((dim.to_string(), vec![value]));
+    }
+
+    pub fn dims(&self) -> &[(String, Vec<Vec<u8>>)] {
+        &self.dims
+    }
+
+    fn build(&self) -> Result<Box<dyn Query<C>>> {
+        let base: Box<dyn Query<C>> = match self.base_query {
+            Some(ref query) => Box::new(Arc::clone(query)),
+            None => Box::new(MatchAllDocsQuery),
+        };
+        let filters: Vec<Box<dyn Query<C>>> = self
+            .dims
+            .iter()
+            .map(|(dim, values)| -> Box<dyn Query<C>> {
+                Box::new(DocValuesTermsQuery::new(dim.clone(), values.clone()))
+            })
+            .collect();
+        if filters.is_empty() {
+            return Ok(base);
+        }
+        BooleanQuery::build(vec![base], vec![], filters, vec![], 0)
+    }
+}
+
+impl<C: Codec> Query<C> for DrillDownQuery<C> {
+    fn create_weight(
+        &self,
+        searcher: &dyn SearchPlanBuilder<C>,
+        needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        self.build()?.create_weight(searcher, needs_scores)
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        match self.base_query {
+            Some(ref query) => query.extract_terms(),
+            None => vec![],
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn rewrite(&self, _searcher: &dyn SearchPlanBuilder<C>) -> Result<Option<Box<dyn Query<C>>>> {
+        self.build().map(Some)
+    }
+}
+
+impl<C: Codec> fmt::Display for DrillDownQuery<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let dims: Vec<String> = self
+            .dims
+            .iter()
+            .map(|(dim, values)| {
+                let values: Vec<_> = values.iter().map(|v| String::from_utf8_lossy(v)).collect();
+                format!("{}: {:?}", dim, values)
+            })
+            .collect();
+        match self.base_query {
+            Some(ref query) => write!(
+                f,
+                "DrillDownQuery(base: {}, dims: [{}])",
+                query,
+                dims.join(", ")
+            ),
+            None => write!(f, "DrillDownQuery(dims: [{}])", dims.join(", ")),
+        }
+    }
+}
@@ -0,0 +1,65 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Facet counting over the doc values of the hits of a query.
+//!
+//! A facet dimension is a keyword field indexed with `SortedDocValues` or
+//! `SortedSetDocValues`. The hits are counted per segment ordinal by
+//! [`SortedSetDocValuesFacetCollector`], and the segment counts are merged
+//! through the global ordinals of a [`SortedSetDocValuesReaderState`] by
+//! [`SortedSetDocValuesFacetCounts`]. [`DrillDownQuery`] narrows a query down
+//! to some values of one or more dimensions.
+//!
+//! Numeric fields are counted in ranges or histogram buckets by
+//! [`NumericFacetCollector`]. [`CardinalityCollector`] and
+//! [`PercentilesCollector`] compute approximate metrics of the hits.
+
+mod sorted_set_facets;
+
+pub use self::sorted_set_facets::*;
+
+mod drill_down_query;
+
+pub use self::drill_down_query::*;
+
+mod numeric_facets;
+
+pub use self::numeric_facets::*;
+
+mod cardinality;
+
+pub use self::cardinality::*;
+
+mod percentiles;
+
+pub use self::percentiles::*;
+
+/// A label of a dimension and its count.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LabelAndValue {
+    pub label: String,
+    pub value: i32,
+}
+
+/// The counts of the top labels of a dimension.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FacetResult {
+    /// The dimension, i.e. the doc values field.
+    pub dim: String,
+    /// The sum of the counts of all the labels of the dimension.
+    pub value: i32,
+    /// The number of labels with a non-zero count.
+    pub child_count: usize,
+    /// The top labels, by descending count then ascending label.
+    pub label_values: Vec<LabelAndValue>,
+}
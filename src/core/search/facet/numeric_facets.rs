@@ -0,0 +1,1115 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::f64;
+use std::mem;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Arc;
+
+use core::codec::doc_values::{NumericDocValues, SortedNumericDocValues};
+use core::codec::Codec;
+use core::doc::DocValuesType;
+use core::index::reader::LeafReaderContext;
+use core::search::collector::{Collector, ParallelLeafCollector, SearchCollector};
+use core::search::scorer::Scorer;
+use core::util::{sortable_double_bits, BitsMut, DocId, VariantValue};
+
+use error::{
+    ErrorKind::{IllegalArgument, IllegalState},
+    Result, ResultExt,
+};
+
+const MILLIS_PER_SECOND: i64 = 1000;
+const MILLIS_PER_MINUTE: i64 = 60 * MILLIS_PER_SECOND;
+const MILLIS_PER_HOUR: i64 = 60 * MILLIS_PER_MINUTE;
+const MILLIS_PER_DAY: i64 = 24 * MILLIS_PER_HOUR;
+
+/// How the values of a numeric doc values field are encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumericFieldType {
+    /// e.g. a `NumericDocValuesField` or a `SortedNumericDocValuesField`
+    Long,
+    /// the raw bits of a `DoubleDocValuesField`, or the sortable longs of
+    /// `double2sortable_long` in a `SortedNumericDocValuesField`
+    Double,
+}
+
+impl NumericFieldType {
+    /// Maps a value of a `SortedNumericDocValues` field to a raw doc value,
+    /// as double values are stored as sortable longs there.
+    fn decode_sorted_numeric(self, stored: i64) -> i64 {
+        match self {
+            NumericFieldType::Long => stored,
+            NumericFieldType::Double => sortable_double_bits(stored),
+        }
+    }
+
+    /// Maps a raw doc value to a long that sorts like the value.
+    fn sortable(self, raw: i64) -> i64 {
+        match self {
+            NumericFieldType::Long => raw,
+            NumericFieldType::Double => sortable_double_bits(raw),
+        }
+    }
+
+    pub(crate) fn to_f64(self, raw: i64) -> f64 {
+        match self {
+            NumericFieldType::Long => raw as f64,
+            NumericFieldType::Double => f64::from_bits(raw as u64),
+        }
+    }
+}
+
+/// A user-defined bucket of a range facet.
+#[derive(Debug, Clone)]
+pub struct NumericRange {
+    label: String,
+    field_type: NumericFieldType,
+    // inclusive bounds, as sortable longs of `field_type`; min > max if the range is empty
+    min: i64,
+    max: i64,
+}
+
+impl NumericRange {
+    /// Creates a range over a long field, `None` leaves a side unbounded.
+    pub fn long(
+        label: String,
+        min: Option<i64>,
+        min_inclusive: bool,
+        max: Option<i64>,
+        max_inclusive: bool,
+    ) -> NumericRange {
+        let (min, max) = Self::inclusive_bounds(min, min_inclusive, max, max_inclusive);
+        NumericRange {
+            label,
+            field_type: NumericFieldType::Long,
+            min,
+            max,
+        }
+    }
+
+    /// Creates a range over a double field, `None` leaves a side unbounded.
+    pub fn double(
+        label: String,
+        min: Option<f64>,
+        min_inclusive: bool,
+        max: Option<f64>,
+        max_inclusive: bool,
+    ) -> Result<NumericRange> {
+        if min.map_or(false, f64::is_nan) || max.map_or(false, f64::is_nan) {
+            bail!(IllegalArgument(format!(
+                "the bounds of range '{}' must not be NaN",
+                label
+            )));
+        }
+        // the sortable longs of two adjacent doubles are adjacent as well
+        let to_sortable = |v: f64| sortable_double_bits(v.to_bits() as i64);
+        let (min, max) = Self::inclusive_bounds(
+            min.map(to_sortable),
+            min_inclusive,
+            max.map(to_sortable),
+            max_inclusive,
+        );
+        Ok(NumericRange {
+            label,
+            field_type: NumericFieldType::Double,
+            min,
+            max,
+        })
+    }
+
+    fn inclusive_bounds(
+        min: Option<i64>,
+        min_inclusive: bool,
+        max: Option<i64>,
+        max_inclusive: bool,
+    ) -> (i64, i64) {
+        let min = match min {
+            Some(v) if !min_inclusive => v.checked_add(1),
+            Some(v) => Some(v),
+            None => Some(i64::min_value()),
+        };
+        let max = match max {
+            Some(v) if !max_inclusive => v.checked_sub(1),
+            Some(v) => Some(v),
+            None => Some(i64::max_value()),
+        };
+        match (min, max) {
+            (Some(min), Some(max)) => (min, max),
+            _ => (0, -1),
+        }
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    fn accept(&self, sortable: i64) -> bool {
+        sortable >= self.min && sortable <= self.max
+    }
+}
+
+/// A calendar interval of a date histogram.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateInterval {
+    Second,
+    Minute,
+    Hour,
+    Day,
+    /// weeks start on Monday
+    Week,
+    Month,
+    Quarter,
+    Year,
+}
+
+/// The buckets of a histogram facet.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HistogramInterval {
+    /// Buckets `[offset + k * interval, offset + (k + 1) * interval)` of a long field.
+    Long { interval: i64, offset: i64 },
+    /// Buckets `[offset + k * interval, offset + (k + 1) * interval)` of a double field,
+    /// infinite and NaN values are ignored.
+    Double { interval: f64, offset: f64 },
+    /// Calendar buckets of a long field holding milliseconds since the epoch. The
+    /// calendar is the UTC one shifted by `offset_millis`, i.e. a fixed time zone.
+    Date {
+        interval: DateInterval,
+        offset_millis: i64,
+    },
+}
+
+impl HistogramInterval {
+    fn field_type(&self) -> NumericFieldType {
+        match self {
+            HistogramInterval::Double { .. } => NumericFieldType::Double,
+            _ => NumericFieldType::Long,
+        }
+    }
+
+    /// Returns the id of the bucket of `raw`, ids sort like the buckets.
+    fn bucket_id(&self, raw: i64) -> Option<i64> {
+        match *self {
+            HistogramInterval::Long { interval, offset } => {
+                Some((i128::from(raw) - i128::from(offset)).div_euclid(i128::from(interval)) as i64)
+            }
+            HistogramInterval::Double { interval, offset } => {
+                let index = ((f64::from_bits(raw as u64) - offset) / interval).floor();
+                // casting a NaN or an out of range float to an integer is undefined
+                if index.is_finite() && index.abs() < 9.0e18 {
+                    Some(index as i64)
+                } else {
+                    None
+                }
+            }
+            HistogramInterval::Date {
+                interval,
+                offset_millis,
+            } => Some(date_bucket_start(raw, interval, offset_millis)),
+        }
+    }
+
+    /// Returns the key and the label of bucket `id`.
+    fn bucket_key(&self, id: i64) -> (VariantValue, String) {
+        match *self {
+            HistogramInterval::Long { interval, offset } => {
+                let key = (i128::from(id) * i128::from(interval) + i128::from(offset)) as i64;
+                (VariantValue::Long(key), key.to_string())
+            }
+            HistogramInterval::Double { interval, offset } => {
+                let key = id as f64 * interval + offset;
+                (VariantValue::Double(key), key.to_string())
+            }
+            HistogramInterval::Date { offset_millis, .. } => {
+                (VariantValue::Long(id), format_date(id, offset_millis))
+            }
+        }
+    }
+}
+
+/// Returns the first millisecond, in UTC, of the calendar bucket of `millis`.
+fn date_bucket_start(millis: i64, interval: DateInterval, offset_millis: i64) -> i64 {
+    let local = millis + offset_millis;
+    let truncate = |unit: i64| local - local.rem_euclid(unit);
+    let local_start = match interval {
+        DateInterval::Second => truncate(MILLIS_PER_SECOND),
+        DateInterval::Minute => truncate(MILLIS_PER_MINUTE),
+        DateInterval::Hour => truncate(MILLIS_PER_HOUR),
+        DateInterval::Day => truncate(MILLIS_PER_DAY),
+        DateInterval::Week => {
+            // the epoch is a Thursday, three days after a Monday
+            let days = local.div_euclid(MILLIS_PER_DAY);
+            (days - (days + 3).rem_euclid(7)) * MILLIS_PER_DAY
+        }
+        DateInterval::Month | DateInterval::Quarter | DateInterval::Year => {
+            let (year, month, _) = civil_from_days(local.div_euclid(MILLIS_PER_DAY));
+            let month = match interval {
+                DateInterval::Month => month,
+                DateInterval::Quarter => (month - 1) / 3 * 3 + 1,
+                _ => 1,
+            };
+            days_from_civil(year, month, 1) * MILLIS_PER_DAY
+        }
+    };
+    local_start - offset_millis
+}
+
+/// Formats `millis` as an ISO 8601 date time in the time zone of `offset_millis`.
+fn format_date(millis: i64, offset_millis: i64) -> String {
+    let local = millis + offset_millis;
+    let (year, month, day) = civil_from_days(local.div_euclid(MILLIS_PER_DAY));
+    let seconds = local.rem_euclid(MILLIS_PER_DAY) / MILLIS_PER_SECOND;
+    let zone = if offset_millis == 0 {
+        "Z".to_string()
+    } else {
+        let minutes = offset_millis.abs() / MILLIS_PER_MINUTE;
+        let sign = if offset_millis < 0 { '-' } else { '+' };
+        format!("{}{:02}:{:02}", sign, minutes / 60, minutes % 60)
+    };
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}{}",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60,
+        zone
+    )
+}
+
+/// Converts days since the epoch to a (year, month, day) of the proleptic
+/// Gregorian calendar.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// The inverse of `civil_from_days`.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Statistics of the values of a metric field over the docs of a bucket.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StatsMetrics {
+    /// The number of values, a doc may have none or several.
+    pub count: i64,
+    pub sum: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl StatsMetrics {
+    fn add(&mut self, value: f64) {
+        self.count += 1;
+        self.sum += value;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+    }
+
+    fn merge(&mut self, other: &StatsMetrics) {
+        self.count += other.count;
+        self.sum += other.sum;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+    }
+
+    /// Returns the average of the values, `None` if there is none.
+    pub fn avg(&self) -> Option<f64> {
+        if self.count > 0 {
+            Some(self.sum / self.count as f64)
+        } else {
+            None
+        }
+    }
+}
+
+impl Default for StatsMetrics {
+    fn default() -> Self {
+        StatsMetrics {
+            count: 0,
+            sum: 0.0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+}
+
+/// A bucket of a numeric facet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NumericBucket {
+    /// The label of a range, or the formatted key of a histogram bucket.
+    pub label: String,
+    /// The lower bound of a histogram bucket, `None` for a range.
+    pub key: Option<VariantValue>,
+    /// The number of hits with at least one value in the bucket.
+    pub count: i32,
+    /// The statistics of the metric field, if one was set.
+    pub metrics: Option<StatsMetrics>,
+}
+
+/// The buckets of a numeric facet: every range in the order they were given,
+/// or the non-empty histogram buckets by ascending key.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NumericFacetResult {
+    pub dim: String,
+    pub buckets: Vec<NumericBucket>,
+}
+
+#[derive(Clone)]
+enum Bucketing {
+    Ranges(Vec<NumericRange>),
+    Histogram(HistogramInterval),
+}
+
+impl Bucketing {
+    fn field_type(&self) -> NumericFieldType {
+        match self {
+            Bucketing::Ranges(ranges) => ranges[0].field_type,
+            Bucketing::Histogram(interval) => interval.field_type(),
+        }
+    }
+
+    fn add_bucket_ids(&self, raw: i64, ids: &mut Vec<i64>) {
+        match self {
+            Bucketing::Ranges(ranges) => {
+                let sortable = self.field_type().sortable(raw);
+                for (i, range) in ranges.iter().enumerate() {
+                    if range.accept(sortable) {
+                        ids.push(i as i64);
+                    }
+                }
+            }
+            Bucketing::Histogram(interval) => {
+                if let Some(id) = interval.bucket_id(raw) {
+                    ids.push(id);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+struct NumericFacetSpec {
+    field: String,
+    bucketing: Bucketing,
+    metric: Option<(String, NumericFieldType)>,
+}
+
+/// The smallest of `values`, `None` if there is none.
+pub(crate) fn min_value<I: IntoIterator<Item = f64>>(values: I) -> Option<f64> {
+    values
+        .into_iter()
+        .fold(None, |min, v| Some(min.map_or(v, |m: f64| m.min(v))))
+}
+
+/// The values of a `NumericDocValues` or `SortedNumericDocValues` field in a leaf.
+pub(crate) enum NumericValues {
+    Single(Box<dyn NumericDocValues>, Box<dyn BitsMut>),
+    Multi(Box<dyn SortedNumericDocValues>, NumericFieldType),
+    Empty,
+}
+
+impl NumericValues {
+    pub(crate) fn new<C: Codec>(
+        reader: &LeafReaderContext<'_, C>,
+        field: &str,
+        field_type: NumericFieldType,
+    ) -> Result<Self> {
+        match reader.reader.field_info(field).map(|fi| fi.doc_values_type) {
+            Some(DocValuesType::Numeric) => Ok(NumericValues::Single(
+                reader.reader.get_numeric_doc_values(field)?,
+                reader.reader.get_docs_with_field(field)?,
+            )),
+            Some(DocValuesType::SortedNumeric) => Ok(NumericValues::Multi(
+                reader.reader.get_sorted_numeric_doc_values(field)?,
+                field_type,
+            )),
+            Some(DocValuesType::Null) | None => Ok(NumericValues::Empty),
+            Some(dv_type) => bail!(IllegalArgument(format!(
+                "field '{}' has doc values of type {:?}, expected numeric or sorted_numeric",
+                field, dv_type
+            ))),
+        }
+    }
+
+    /// Reads the raw values of `doc` into `values`, the double values of a
+    /// `SortedNumericDocValues` field are decoded to their raw bits.
+    pub(crate) fn read(&mut self, doc: DocId, values: &mut Vec<i64>) -> Result<()> {
+        values.clear();
+        match self {
+            NumericValues::Single(dv, docs_with_field) => {
+                if docs_with_field.get(doc as usize)? {
+                    values.push(dv.get_mut(doc)?);
+                }
+            }
+            NumericValues::Multi(dv, field_type) => {
+                dv.set_document(doc)?;
+                for i in 0..dv.count() {
+                    values.push(field_type.decode_sorted_numeric(dv.value_at(i)?));
+                }
+            }
+            NumericValues::Empty => {}
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct BucketCounts {
+    count: i32,
+    metrics: StatsMetrics,
+}
+
+/// Counts the hits of one leaf into buckets.
+struct NumericFacetLeaf {
+    spec: Arc<NumericFacetSpec>,
+    values: NumericValues,
+    metric_values: Option<NumericValues>,
+    buckets: HashMap<i64, BucketCounts>,
+    value_buf: Vec<i64>,
+    id_buf: Vec<i64>,
+    metric_buf: Vec<i64>,
+}
+
+impl NumericFacetLeaf {
+    fn new<C: Codec>(
+        spec: &Arc<NumericFacetSpec>,
+        reader: &LeafReaderContext<'_, C>,
+    ) -> Result<Self> {
+        let values = NumericValues::new(reader, &spec.field, spec.bucketing.field_type())?;
+        let metric_values = match spec.metric {
+            Some((ref field, field_type)) => Some(NumericValues::new(reader, field, field_type)?),
+            None => None,
+        };
+        Ok(NumericFacetLeaf {
+            spec: Arc::clone(spec),
+            values,
+            metric_values,
+            buckets: HashMap::new(),
+            value_buf: vec![],
+            id_buf: vec![],
+            metric_buf: vec![],
+        })
+    }
+
+    fn collect(&mut self, doc: DocId) -> Result<()> {
+        self.values.read(doc, &mut self.value_buf)?;
+        self.id_buf.clear();
+        for &raw in &self.value_buf {
+            self.spec.bucketing.add_bucket_ids(raw, &mut self.id_buf);
+        }
+        if self.id_buf.is_empty() {
+            return Ok(());
+        }
+        // a doc is counted once per bucket, whatever its number of values in it
+        self.id_buf.sort_unstable();
+        self.id_buf.dedup();
+
+        match self.metric_values {
+            Some(ref mut metric_values) => metric_values.read(doc, &mut self.metric_buf)?,
+            None => self.metric_buf.clear(),
+        }
+        let metric_type = self.spec.metric.as_ref().map(|m| m.1);
+        for &id in &self.id_buf {
+            let bucket = self.buckets.entry(id).or_insert_with(BucketCounts::default);
+            bucket.count += 1;
+            if let Some(metric_type) = metric_type {
+                for &raw in &self.metric_buf {
+                    bucket.metrics.add(metric_type.to_f64(raw));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn merge_buckets(into: &mut HashMap<i64, BucketCounts>, from: &HashMap<i64, BucketCounts>) {
+    for (id, counts) in from {
+        let bucket = into.entry(*id).or_insert_with(BucketCounts::default);
+        bucket.count += counts.count;
+        bucket.metrics.merge(&counts.metrics);
+    }
+}
+
+/// The buckets collected by a leaf collector, by bucket key.
+type LeafBuckets = HashMap<i64, BucketCounts>;
+
+/// A `Collector` that counts the hits of a query in the ranges or the
+/// histogram buckets of the values of a numeric doc values field.
+///
+/// A hit is counted once in every bucket that one of its values falls in. The
+/// statistics of another (or the same) numeric field can be computed for each
+/// bucket with `set_metric_field`. Chain it with other collectors with a
+/// `ChainedCollector` to get the hits and the facets in one search.
+pub struct NumericFacetCollector {
+    spec: Arc<NumericFacetSpec>,
+    current: Option<NumericFacetLeaf>,
+    buckets: HashMap<i64, BucketCounts>,
+    channel: Option<(Sender<LeafBuckets>, Receiver<LeafBuckets>)>,
+}
+
+impl NumericFacetCollector {
+    /// Counts the hits in user-defined ranges, which may overlap.
+    pub fn ranges(field: String, ranges: Vec<NumericRange>) -> Result<Self> {
+        if ranges.is_empty() {
+            bail!(IllegalArgument("at least one range is required".into()));
+        }
+        if ranges.iter().any(|r| r.field_type != ranges[0].field_type) {
+            bail!(IllegalArgument(
+                "long and double ranges can't be mixed".into()
+            ));
+        }
+        Ok(Self::new(field, Bucketing::Ranges(ranges)))
+    }
+
+    /// Counts the hits in the buckets of a fixed interval or date histogram.
+    pub fn histogram(field: String, interval: HistogramInterval) -> Result<Self> {
+        let valid = match interval {
+            HistogramInterval::Long { interval, .. } => interval > 0,
+            HistogramInterval::Double { interval, offset } => {
+                interval > 0.0 && interval.is_finite() && offset.is_finite()
+            }
+            HistogramInterval::Date { .. } => true,
+        };
+        if !valid {
+            bail!(IllegalArgument(format!(
+                "invalid histogram interval: {:?}",
+                interval
+            )));
+        }
+        Ok(Self::new(field, Bucketing::Histogram(interval)))
+    }
+
+    fn new(field: String, bucketing: Bucketing) -> Self {
+        NumericFacetCollector {
+            spec: Arc::new(NumericFacetSpec {
+                field,
+                bucketing,
+                metric: None,
+            }),
+            current: None,
+            buckets: HashMap::new(),
+            channel: None,
+        }
+    }
+
+    /// Computes the statistics of `field` for every bucket, must be called
+    /// before searching.
+    pub fn set_metric_field(&mut self, field: String, field_type: NumericFieldType) {
+        Arc::make_mut(&mut self.spec).metric = Some((field, field_type));
+    }
+
+    pub fn field(&self) -> &str {
+        &self.spec.field
+    }
+
+    /// Returns the buckets counted so far.
+    pub fn result(&self) -> NumericFacetResult {
+        let mut buckets = self.buckets.clone();
+        if let Some(ref leaf) = self.current {
+            merge_buckets(&mut buckets, &leaf.buckets);
+        }
+        let metrics = |counts: &BucketCounts| {
+            if self.spec.metric.is_some() {
+                Some(counts.metrics)
+            } else {
+                None
+            }
+        };
+
+        let buckets = match self.spec.bucketing {
+            Bucketing::Ranges(ref ranges) => ranges
+                .iter()
+                .enumerate()
+                .map(|(i, range)| {
+                    let counts = buckets.get(&(i as i64)).cloned().unwrap_or_default();
+                    NumericBucket {
+                        label: range.label.clone(),
+                        key: None,
+                        count: counts.count,
+                        metrics: metrics(&counts),
+                    }
+                })
+                .collect(),
+            Bucketing::Histogram(ref interval) => {
+                let mut ids: Vec<i64> = buckets.keys().cloned().collect();
+                ids.sort_unstable();
+                ids.into_iter()
+                    .map(|id| {
+                        let (key, label) = interval.bucket_key(id);
+                        let counts = buckets[&id];
+                        NumericBucket {
+                            label,
+                            key: Some(key),
+                            count: counts.count,
+                            metrics: metrics(&counts),
+                        }
+                    })
+                    .collect()
+            }
+        };
+
+        NumericFacetResult {
+            dim: self.spec.field.clone(),
+            buckets,
+        }
+    }
+}
+
+impl SearchCollector for NumericFacetCollector {
+    type LC = NumericFacetLeafCollector;
+
+    fn set_next_reader<C: Codec>(&mut self, reader: &LeafReaderContext<'_, C>) -> Result<()> {
+        if let Some(leaf) = self.current.take() {
+            merge_buckets(&mut self.buckets, &leaf.buckets);
+        }
+        self.current = Some(NumericFacetLeaf::new(&self.spec, reader)?);
+        Ok(())
+    }
+
+    fn support_parallel(&self) -> bool {
+        true
+    }
+
+    fn init_parallel(&mut self) {
+        if self.channel.is_none() {
+            self.channel = Some(channel());
+        }
+    }
+
+    fn leaf_collector<C: Codec>(
+        &self,
+        reader: &LeafReaderContext<'_, C>,
+    ) -> Result<NumericFacetLeafCollector> {
+        Ok(NumericFacetLeafCollector {
+            leaf: NumericFacetLeaf::new(&self.spec, reader)?,
+            channel: self.channel.as_ref().unwrap().0.clone(),
+        })
+    }
+
+    fn finish_parallel(&mut self) -> Result<()> {
+        let channel = self.channel.take();
+        // iff all the `weight.create_scorer(leaf_reader)` return None, the channel won't
+        // inited and thus stay None
+        if let Some((sender, receiver)) = channel {
+            drop(sender);
+            while let Ok(buckets) = receiver.recv() {
+                merge_buckets(&mut self.buckets, &buckets);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Collector for NumericFacetCollector {
+    fn needs_scores(&self) -> bool {
+        false
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, _scorer: &mut S) -> Result<()> {
+        match self.current {
+            Some(ref mut leaf) => leaf.collect(doc),
+            None => bail!(IllegalState(
+                "set_next_reader must be called before collect".into()
+            )),
+        }
+    }
+}
+
+pub struct NumericFacetLeafCollector {
+    leaf: NumericFacetLeaf,
+    channel: Sender<LeafBuckets>,
+}
+
+impl ParallelLeafCollector for NumericFacetLeafCollector {
+    fn finish_leaf(&mut self) -> Result<()> {
+        let buckets = mem::replace(&mut self.leaf.buckets, HashMap::new());
+        self.channel
+            .send(buckets)
+            .chain_err(|| IllegalState("channel unexpected closed before search complete".into()))
+    }
+}
+
+impl Collector for NumericFacetLeafCollector {
+    fn needs_scores(&self) -> bool {
+        false
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, _scorer: &mut S) -> Result<()> {
+        self.leaf.collect(doc)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate tempfile;
+
+    use super::*;
+    use core::doc::{
+        DoubleDocValuesField, Fieldable, NumericDocValuesField, SortedNumericDocValuesField,
+    };
+    use core::index::writer::{IndexWriter, IndexWriterConfig};
+    use core::search::collector::{ChainedCollector, TopDocsCollector};
+    use core::search::query::MatchAllDocsQuery;
+    use core::search::{DefaultIndexSearcher, IndexSearcher};
+    use core::store::directory::FSDirectory;
+    use core::util::double2sortable_long;
+
+    struct Doc {
+        price: i64,
+        rating: Option<f64>,
+        timestamp: i64,
+        sizes: Vec<i64>,
+        weights: Vec<f64>,
+    }
+
+    #[test]
+    fn test_civil_dates() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(2020, 1, 1), 18262);
+        assert_eq!(civil_from_days(18262 + 59), (2020, 2, 29));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        assert_eq!(format_date(0, 0), "1970-01-01T00:00:00Z");
+        assert_eq!(
+            format_date(-8 * MILLIS_PER_HOUR, 8 * MILLIS_PER_HOUR),
+            "1970-01-01T00:00:00+08:00"
+        );
+        // 2020-01-01 was a Wednesday
+        let millis = days_from_civil(2020, 1, 1) * MILLIS_PER_DAY + MILLIS_PER_HOUR;
+        let week = date_bucket_start(millis, DateInterval::Week, 0);
+        assert_eq!(format_date(week, 0), "2019-12-30T00:00:00Z");
+        let quarter = date_bucket_start(millis + 100 * MILLIS_PER_DAY, DateInterval::Quarter, 0);
+        assert_eq!(format_date(quarter, 0), "2020-04-01T00:00:00Z");
+    }
+
+    #[test]
+    fn test_numeric_facets() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let directory = Arc::new(FSDirectory::with_path(temp_dir.path()).unwrap());
+        let writer = IndexWriter::new(directory, Arc::new(IndexWriterConfig::default())).unwrap();
+
+        // every seventh doc has no rating, every fourth one no size, every
+        // third one no weight
+        let start = days_from_civil(2020, 1, 1) * MILLIS_PER_DAY;
+        let mut docs = vec![];
+        for i in 0..120i64 {
+            let d = Doc {
+                price: i * 17 % 200,
+                rating: if i % 7 == 0 {
+                    None
+                } else {
+                    Some((i % 10) as f64 / 2.0)
+                },
+                timestamp: start + i * 3 * MILLIS_PER_DAY + i * MILLIS_PER_HOUR,
+                sizes: if i % 4 == 0 {
+                    vec![]
+                } else {
+                    vec![i % 5, i % 5 + 3, i % 5 + 4]
+                },
+                weights: if i % 3 == 0 {
+                    vec![]
+                } else {
+                    vec![(i % 6) as f64 * -0.75, (i % 9) as f64 / 4.0 + 1.0]
+                },
+            };
+            let mut doc: Vec<Box<dyn Fieldable>> = vec![
+                Box::new(NumericDocValuesField::new("price", d.price)),
+                Box::new(NumericDocValuesField::new("timestamp", d.timestamp)),
+            ];
+            if let Some(rating) = d.rating {
+                doc.push(Box::new(DoubleDocValuesField::new("rating", rating)));
+            }
+            for &size in &d.sizes {
+                doc.push(Box::new(SortedNumericDocValuesField::new("sizes", size)));
+            }
+            for &weight in &d.weights {
+                doc.push(Box::new(SortedNumericDocValuesField::new(
+                    "weights",
+                    double2sortable_long(weight),
+                )));
+            }
+            writer.add_document(doc).unwrap();
+            docs.push(d);
+            if i == 50 {
+                writer.commit().unwrap();
+            }
+        }
+        writer.commit().unwrap();
+
+        let reader = writer.get_reader(true, false).unwrap();
+        let searcher = DefaultIndexSearcher::new(Arc::new(reader), None);
+
+        let stats = |docs: &[&Doc]| {
+            let mut metrics = StatsMetrics::default();
+            for rating in docs.iter().filter_map(|d| d.rating) {
+                metrics.add(rating);
+            }
+            metrics
+        };
+
+        // ranges of the price, with the statistics of the rating, along with the hits
+        let ranges = vec![
+            NumericRange::long("cheap".into(), None, true, Some(30), true),
+            NumericRange::long("low".into(), Some(0), true, Some(100), false),
+            NumericRange::long("high".into(), Some(100), true, None, true),
+            NumericRange::long("none".into(), Some(500), false, None, true),
+        ];
+        let filters: Vec<Box<dyn Fn(i64) -> bool>> = vec![
+            Box::new(|p| p <= 30),
+            Box::new(|p| p < 100),
+            Box::new(|p| p >= 100),
+            Box::new(|_| false),
+        ];
+        let mut facets = NumericFacetCollector::ranges("price".into(), ranges.clone()).unwrap();
+        facets.set_metric_field("rating".into(), NumericFieldType::Double);
+        let mut top_docs = TopDocsCollector::new(10);
+        {
+            let mut collector = ChainedCollector::new(&mut top_docs, &mut facets);
+            searcher.search(&MatchAllDocsQuery, &mut collector).unwrap();
+        }
+        assert_eq!(top_docs.top_docs().total_hits(), docs.len());
+        let result = facets.result();
+        assert_eq!(result.dim, "price");
+        assert_eq!(result.buckets.len(), ranges.len());
+        for ((bucket, range), filter) in result.buckets.iter().zip(&ranges).zip(&filters) {
+            let matched: Vec<&Doc> = docs.iter().filter(|d| filter(d.price)).collect();
+            assert_eq!(bucket.label, range.label());
+            assert_eq!(bucket.key, None);
+            assert_eq!(bucket.count, matched.len() as i32);
+            assert_eq!(bucket.metrics, Some(stats(&matched)));
+        }
+        assert_eq!(result.buckets[3].metrics.unwrap().avg(), None);
+
+        // fixed interval histogram of the price
+        let mut facets = NumericFacetCollector::histogram(
+            "price".into(),
+            HistogramInterval::Long {
+                interval: 50,
+                offset: 10,
+            },
+        )
+        .unwrap();
+        searcher.search(&MatchAllDocsQuery, &mut facets).unwrap();
+        let mut expected: Vec<(i64, i32)> = vec![];
+        for key in (-40..200).step_by(50) {
+            let count = docs
+                .iter()
+                .filter(|d| d.price >= key && d.price < key + 50)
+                .count() as i32;
+            if count > 0 {
+                expected.push((key, count));
+            }
+        }
+        let actual: Vec<(i64, i32)> = facets
+            .result()
+            .buckets
+            .iter()
+            .map(|b| match b.key {
+                Some(VariantValue::Long(key)) => {
+                    assert_eq!(b.label, key.to_string());
+                    assert!(b.metrics.is_none());
+                    (key, b.count)
+                }
+                _ => panic!("unexpected key {:?}", b.key),
+            })
+            .collect();
+        assert_eq!(actual, expected);
+
+        // histogram of the rating, a double field
+        let mut facets = NumericFacetCollector::histogram(
+            "rating".into(),
+            HistogramInterval::Double {
+                interval: 1.0,
+                offset: 0.0,
+            },
+        )
+        .unwrap();
+        searcher.search(&MatchAllDocsQuery, &mut facets).unwrap();
+        let actual: Vec<(VariantValue, i32)> = facets
+            .result()
+            .buckets
+            .into_iter()
+            .map(|b| (b.key.unwrap(), b.count))
+            .collect();
+        let expected: Vec<(VariantValue, i32)> = (0..5)
+            .map(|k| {
+                let count = docs
+                    .iter()
+                    .filter(|d| d.rating.map_or(false, |r| r.floor() as i64 == k))
+                    .count() as i32;
+                (VariantValue::Double(k as f64), count)
+            })
+            .collect();
+        assert_eq!(actual, expected);
+
+        // monthly date histogram of the timestamp, in UTC+8
+        let offset_millis = 8 * MILLIS_PER_HOUR;
+        let mut facets = NumericFacetCollector::histogram(
+            "timestamp".into(),
+            HistogramInterval::Date {
+                interval: DateInterval::Month,
+                offset_millis,
+            },
+        )
+        .unwrap();
+        facets.set_metric_field("price".into(), NumericFieldType::Long);
+        searcher.search(&MatchAllDocsQuery, &mut facets).unwrap();
+        let result = facets.result();
+        let mut total = 0;
+        for bucket in &result.buckets {
+            let from = match bucket.key {
+                Some(VariantValue::Long(key)) => key,
+                _ => panic!("unexpected key {:?}", bucket.key),
+            };
+            assert!(
+                bucket.label.ends_with("-01T00:00:00+08:00"),
+                "{}",
+                bucket.label
+            );
+            let (year, month, _) = civil_from_days((from + offset_millis) / MILLIS_PER_DAY);
+            let (next_year, next_month) = if month == 12 {
+                (year + 1, 1)
+            } else {
+                (year, month + 1)
+            };
+            let to = days_from_civil(next_year, next_month, 1) * MILLIS_PER_DAY - offset_millis;
+            let matched: Vec<&Doc> = docs
+                .iter()
+                .filter(|d| d.timestamp >= from && d.timestamp < to)
+                .collect();
+            assert_eq!(bucket.count, matched.len() as i32);
+            let metrics = bucket.metrics.unwrap();
+            assert_eq!(metrics.count, matched.len() as i64);
+            let sum: f64 = matched.iter().map(|d| d.price as f64).sum();
+            assert!((metrics.sum - sum).abs() < ::std::f64::EPSILON);
+            total += bucket.count;
+        }
+        assert_eq!(total, docs.len() as i32);
+        assert_eq!(result.buckets[0].label, "2020-01-01T00:00:00+08:00");
+
+        // a doc with several values in a range is counted once
+        let mut facets = NumericFacetCollector::ranges(
+            "sizes".into(),
+            vec![
+                NumericRange::long("small".into(), None, true, Some(3), true),
+                NumericRange::long("large".into(), Some(6), true, None, true),
+            ],
+        )
+        .unwrap();
+        searcher.search(&MatchAllDocsQuery, &mut facets).unwrap();
+        let counts: Vec<i32> = facets.result().buckets.iter().map(|b| b.count).collect();
+        let small = docs
+            .iter()
+            .filter(|d| d.sizes.iter().any(|&s| s <= 3))
+            .count();
+        let large = docs
+            .iter()
+            .filter(|d| d.sizes.iter().any(|&s| s >= 6))
+            .count();
+        assert_eq!(counts, vec![small as i32, large as i32]);
+
+        // ranges of a multi-valued double field, with the statistics of its values
+        let mut facets = NumericFacetCollector::ranges(
+            "weights".into(),
+            vec![
+                NumericRange::double("negative".into(), None, true, Some(0.0), false).unwrap(),
+                NumericRange::double("high".into(), Some(2.0), true, None, true).unwrap(),
+            ],
+        )
+        .unwrap();
+        facets.set_metric_field("weights".into(), NumericFieldType::Double);
+        searcher.search(&MatchAllDocsQuery, &mut facets).unwrap();
+        let result = facets.result();
+        let filters: Vec<Box<dyn Fn(f64) -> bool>> =
+            vec![Box::new(|w| w < 0.0), Box::new(|w| w >= 2.0)];
+        for (bucket, filter) in result.buckets.iter().zip(&filters) {
+            let mut metrics = StatsMetrics::default();
+            let mut count = 0;
+            for d in docs.iter().filter(|d| d.weights.iter().any(|&w| filter(w))) {
+                count += 1;
+                for &w in &d.weights {
+                    metrics.add(w);
+                }
+            }
+            assert!(count > 0);
+            assert_eq!(bucket.count, count);
+            assert_eq!(bucket.metrics, Some(metrics));
+        }
+
+        let mut facets = NumericFacetCollector::histogram(
+            "weights".into(),
+            HistogramInterval::Double {
+                interval: 1.0,
+                offset: 0.0,
+            },
+        )
+        .unwrap();
+        searcher.search(&MatchAllDocsQuery, &mut facets).unwrap();
+        let actual: Vec<(VariantValue, i32)> = facets
+            .result()
+            .buckets
+            .into_iter()
+            .map(|b| (b.key.unwrap(), b.count))
+            .collect();
+        let expected: Vec<(VariantValue, i32)> = (-4..4)
+            .filter_map(|k| {
+                let count = docs
+                    .iter()
+                    .filter(|d| d.weights.iter().any(|w| w.floor() as i64 == k))
+                    .count() as i32;
+                if count > 0 {
+                    Some((VariantValue::Double(k as f64), count))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        assert_eq!(actual, expected);
+
+        // a double range can't be used on a long field with long ranges
+        assert!(NumericFacetCollector::ranges(
+            "price".into(),
+            vec![
+                NumericRange::long("a".into(), None, true, Some(3), true),
+                NumericRange::double("b".into(), None, true, Some(3.0), true).unwrap(),
+            ],
+        )
+        .is_err());
+        assert!(NumericFacetCollector::histogram(
+            "price".into(),
+            HistogramInterval::Long {
+                interval: 0,
+                offset: 0
+            }
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,288 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::mem;
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use core::codec::Codec;
+use core::index::reader::LeafReaderContext;
+use core::search::collector::{Collector, ParallelLeafCollector, SearchCollector};
+use core::search::facet::{NumericFieldType, NumericValues};
+use core::search::scorer::Scorer;
+use core::util::{DocId, TDigest};
+
+use error::{
+    ErrorKind::{IllegalArgument, IllegalState},
+    Result, ResultExt,
+};
+
+/// A `Collector` that estimates the percentiles of the values of a numeric
+/// doc values field over the hits of a query, with a `TDigest`.
+pub struct PercentilesCollector {
+    field: String,
+    field_type: NumericFieldType,
+    digest: TDigest,
+    current: Option<NumericValues>,
+    buf: Vec<i64>,
+    channel: Option<(Sender<TDigest>, Receiver<TDigest>)>,
+}
+
+impl PercentilesCollector {
+    /// Creates a collector with a digest of the given compression, see
+    /// `TDigest::new`.
+    pub fn new(field: String, field_type: NumericFieldType, compression: f64) -> Result<Self> {
+        Ok(PercentilesCollector {
+            field,
+            field_type,
+            digest: TDigest::new(compression)?,
+            current: None,
+            buf: vec![],
+            channel: None,
+        })
+    }
+
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    /// Returns the estimated value under which `percent`% of the values of the
+    /// hits are, `None` if the hits have no value.
+    pub fn percentile(&self, percent: f64) -> Result<Option<f64>> {
+        if !(0.0..=100.0).contains(&percent) {
+            bail!(IllegalArgument(format!(
+                "percent must be in [0, 100], got {}",
+                percent
+            )));
+        }
+        Ok(self.digest.quantile(percent / 100.0))
+    }
+
+    /// Returns the digest of the values of the hits, e.g. to merge it with the
+    /// digest of another search.
+    pub fn digest(&self) -> &TDigest {
+        &self.digest
+    }
+}
+
+impl SearchCollector for PercentilesCollector {
+    type LC = PercentilesLeafCollector;
+
+    fn set_next_reader<C: Codec>(&mut self, reader: &LeafReaderContext<'_, C>) -> Result<()> {
+        self.current = Some(NumericValues::new(reader, &self.field, self.field_type)?);
+        Ok(())
+    }
+
+    fn support_parallel(&self) -> bool {
+        true
+    }
+
+    fn init_parallel(&mut self) {
+        if self.channel.is_none() {
+            self.channel = Some(channel());
+        }
+    }
+
+    fn leaf_collector<C: Codec>(
+        &self,
+        reader: &LeafReaderContext<'_, C>,
+    ) -> Result<PercentilesLeafCollector> {
+        Ok(PercentilesLeafCollector {
+            values: NumericValues::new(reader, &self.field, self.field_type)?,
+            field_type: self.field_type,
+            digest: TDigest::new(self.digest.compression())?,
+            buf: vec![],
+            channel: self.channel.as_ref().unwrap().0.clone(),
+        })
+    }
+
+    fn finish_parallel(&mut self) -> Result<()> {
+        let channel = self.channel.take();
+        // iff all the `weight.create_scorer(leaf_reader)` return None, the channel won't
+        // inited and thus stay None
+        if let Some((sender, receiver)) = channel {
+            drop(sender);
+            while let Ok(digest) = receiver.recv() {
+                self.digest.merge(&digest);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Collector for PercentilesCollector {
+    fn needs_scores(&self) -> bool {
+        false
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, _scorer: &mut S) -> Result<()> {
+        match self.current {
+            Some(ref mut values) => {
+                values.read(doc, &mut self.buf)?;
+                for &raw in &self.buf {
+                    self.digest.add(self.field_type.to_f64(raw));
+                }
+                Ok(())
+            }
+            None => bail!(IllegalState(
+                "set_next_reader must be called before collect".into()
+            )),
+        }
+    }
+}
+
+pub struct PercentilesLeafCollector {
+    values: NumericValues,
+    field_type: NumericFieldType,
+    digest: TDigest,
+    buf: Vec<i64>,
+    channel: Sender<TDigest>,
+}
+
+impl ParallelLeafCollector for PercentilesLeafCollector {
+    fn finish_leaf(&mut self) -> Result<()> {
+        let digest = TDigest::new(self.digest.compression())?;
+        let digest = mem::replace(&mut self.digest, digest);
+        self.channel
+            .send(digest)
+            .chain_err(|| IllegalState("channel unexpected closed before search complete".into()))
+    }
+}
+
+impl Collector for PercentilesLeafCollector {
+    fn needs_scores(&self) -> bool {
+        false
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, _scorer: &mut S) -> Result<()> {
+        self.values.read(doc, &mut self.buf)?;
+        for &raw in &self.buf {
+            self.digest.add(self.field_type.to_f64(raw));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate tempfile;
+
+    use super::*;
+    use core::doc::{
+        DoubleDocValuesField, Fieldable, NumericDocValuesField, SortedNumericDocValuesField,
+    };
+    use core::index::writer::{IndexWriter, IndexWriterConfig};
+    use core::search::query::MatchAllDocsQuery;
+    use core::search::{DefaultIndexSearcher, IndexSearcher};
+    use core::store::directory::FSDirectory;
+    use core::util::double2sortable_long;
+
+    use std::sync::Arc;
+
+    #[test]
+    fn test_percentiles_collector() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let directory = Arc::new(FSDirectory::with_path(temp_dir.path()).unwrap());
+        let writer = IndexWriter::new(directory, Arc::new(IndexWriterConfig::default())).unwrap();
+
+        // a permutation of 0..2000 as latencies, their tenth as durations, and
+        // the durations with their opposite as offsets
+        for i in 0..2000i64 {
+            let latency = i * 743 % 2000;
+            let duration = latency as f64 / 10.0;
+            let doc: Vec<Box<dyn Fieldable>> = vec![
+                Box::new(NumericDocValuesField::new("latency", latency)),
+                Box::new(DoubleDocValuesField::new("duration", duration)),
+                Box::new(SortedNumericDocValuesField::new(
+                    "offsets",
+                    double2sortable_long(duration),
+                )),
+                Box::new(SortedNumericDocValuesField::new(
+                    "offsets",
+                    double2sortable_long(-duration),
+                )),
+            ];
+            writer.add_document(doc).unwrap();
+            if i == 900 {
+                writer.commit().unwrap();
+            }
+        }
+        writer.commit().unwrap();
+
+        let reader = writer.get_reader(true, false).unwrap();
+        let searcher = DefaultIndexSearcher::new(Arc::new(reader), None);
+
+        let mut latencies = PercentilesCollector::new(
+            "latency".into(),
+            NumericFieldType::Long,
+            TDigest::DEFAULT_COMPRESSION,
+        )
+        .unwrap();
+        searcher.search(&MatchAllDocsQuery, &mut latencies).unwrap();
+        let mut durations = PercentilesCollector::new(
+            "duration".into(),
+            NumericFieldType::Double,
+            TDigest::DEFAULT_COMPRESSION,
+        )
+        .unwrap();
+        searcher.search(&MatchAllDocsQuery, &mut durations).unwrap();
+
+        assert_eq!(latencies.digest().count(), 2000);
+        assert_eq!(latencies.percentile(0.0).unwrap(), Some(0.0));
+        assert_eq!(latencies.percentile(100.0).unwrap(), Some(1999.0));
+        for &percent in &[1.0, 25.0, 50.0, 75.0, 95.0, 99.0] {
+            let expected = percent * 20.0;
+            let latency = latencies.percentile(percent).unwrap().unwrap();
+            assert!(
+                (latency - expected).abs() < 20.0,
+                "{}: {}",
+                percent,
+                latency
+            );
+            let duration = durations.percentile(percent).unwrap().unwrap();
+            assert!(
+                (duration - expected / 10.0).abs() < 2.0,
+                "{}: {}",
+                percent,
+                duration
+            );
+        }
+        assert!(latencies.percentile(101.0).is_err());
+
+        // a multi-valued double field
+        let mut offsets = PercentilesCollector::new(
+            "offsets".into(),
+            NumericFieldType::Double,
+            TDigest::DEFAULT_COMPRESSION,
+        )
+        .unwrap();
+        searcher.search(&MatchAllDocsQuery, &mut offsets).unwrap();
+        assert_eq!(offsets.digest().count(), 4000);
+        assert_eq!(offsets.percentile(0.0).unwrap(), Some(-199.9));
+        assert_eq!(offsets.percentile(100.0).unwrap(), Some(199.9));
+        for &percent in &[1.0, 25.0, 50.0, 75.0, 95.0, 99.0] {
+            let expected = percent * 4.0 - 200.0;
+            let offset = offsets.percentile(percent).unwrap().unwrap();
+            assert!((offset - expected).abs() < 4.0, "{}: {}", percent, offset);
+        }
+
+        let mut missing = PercentilesCollector::new(
+            "missing".into(),
+            NumericFieldType::Long,
+            TDigest::DEFAULT_COMPRESSION,
+        )
+        .unwrap();
+        searcher.search(&MatchAllDocsQuery, &mut missing).unwrap();
+        assert_eq!(missing.percentile(50.0).unwrap(), None);
+    }
+}
@@ -0,0 +1,527 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cmp::Ordering;
+use std::mem;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Mutex;
+
+use core::codec::doc_values::lucene54::DocValues;
+use core::codec::doc_values::{OrdinalMap, SortedSetDocValues, NO_MORE_ORDS};
+use core::codec::Codec;
+use core::index::reader::{IndexReader, LeafReaderContext};
+use core::search::collector::{Collector, ParallelLeafCollector, SearchCollector};
+use core::search::facet::{FacetResult, LabelAndValue};
+use core::search::scorer::Scorer;
+use core::util::packed::COMPACT;
+use core::util::DocId;
+
+use error::{
+    ErrorKind::{IllegalArgument, IllegalState},
+    Result, ResultExt,
+};
+
+/// The per-reader state of a facet dimension: the doc values of every leaf and
+/// the map from their segment ordinals to the global ordinals of the reader.
+///
+/// Building the global ordinals is costly, so the state should be created once
+/// per reader and shared by the searches on it. The doc values of a leaf are
+/// locked while a label is looked up in them, so the state can be shared
+/// across threads.
+pub struct SortedSetDocValuesReaderState {
+    field: String,
+    leaves: Vec<Mutex<Box<dyn SortedSetDocValues>>>,
+    // `None` when the reader has at most one leaf, segment and global ordinals
+    // are the same then
+    ordinal_map: Option<OrdinalMap>,
+    value_count: usize,
+}
+
+impl SortedSetDocValuesReaderState {
+    pub fn new<R: IndexReader + ?Sized>(reader: &R, field: String) -> Result<Self> {
+        let mut leaves = vec![];
+        for leaf in reader.leaves() {
+            leaves.push(DocValues::get_sorted_set_view(leaf.reader, &field)?);
+        }
+
+        let (ordinal_map, value_count) = if leaves.len() > 1 {
+            let mut subs = Vec::with_capacity(leaves.len());
+            let mut weights = Vec::with_capacity(leaves.len());
+            for values in &leaves {
+                subs.push(Some(values.term_iterator()?));
+                weights.push(values.get_value_count());
+            }
+            let ordinal_map = OrdinalMap::build(subs, weights, COMPACT)?;
+            let value_count = ordinal_map.value_count() as usize;
+            (Some(ordinal_map), value_count)
+        } else {
+            let value_count = leaves.first().map_or(0, |v| v.get_value_count());
+            (None, value_count)
+        };
+
+        Ok(SortedSetDocValuesReaderState {
+            field,
+            leaves: leaves.into_iter().map(Mutex::new).collect(),
+            ordinal_map,
+            value_count,
+        })
+    }
+
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    /// Returns the number of distinct values of the field in the whole reader.
+    pub fn value_count(&self) -> usize {
+        self.value_count
+    }
+
+    /// Returns the value of the given global ordinal.
+    pub fn lookup_ord(&self, global_ord: i64) -> Result<Vec<u8>> {
+        match self.ordinal_map {
+            Some(ref ordinal_map) => {
+                let segment = ordinal_map.first_segment_number(global_ord) as usize;
+                let segment_ord = ordinal_map.first_segment_ord(global_ord);
+                self.leaves[segment].lock()?.lookup_ord(segment_ord)
+            }
+            None => self.leaves[0].lock()?.lookup_ord(global_ord),
+        }
+    }
+
+    /// Returns the global ordinal of `label`, or `None` if no document has it.
+    pub fn lookup_label(&self, label: &[u8]) -> Result<Option<i64>> {
+        for (i, values) in self.leaves.iter().enumerate() {
+            let ord = values.lock()?.lookup_term(label)?;
+            if ord >= 0 {
+                return Ok(Some(match self.ordinal_map {
+                    Some(ref ordinal_map) => ordinal_map.get_global_ords(i).get64(ord)?,
+                    None => ord,
+                }));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Adds the counts of the segment ordinals of leaf `leaf_ord` to `counts`,
+    /// which are indexed by global ordinals.
+    fn accumulate(
+        &self,
+        leaf_ord: usize,
+        segment_counts: &[i32],
+        counts: &mut [i32],
+    ) -> Result<()> {
+        if leaf_ord >= self.leaves.len()
+            || segment_counts.len() != self.leaves[leaf_ord].lock()?.get_value_count()
+        {
+            bail!(IllegalArgument(format!(
+                "the facet counts of field '{}' were not collected on the reader of the state",
+                self.field
+            )));
+        }
+
+        match self.ordinal_map {
+            Some(ref ordinal_map) => {
+                let global_ords = ordinal_map.get_global_ords(leaf_ord);
+                for (ord, &count) in segment_counts.iter().enumerate() {
+                    if count > 0 {
+                        counts[global_ords.get64(ord as i64)? as usize] += count;
+                    }
+                }
+            }
+            None => {
+                for (ord, &count) in segment_counts.iter().enumerate() {
+                    counts[ord] += count;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Counts the hits of one leaf by the segment ordinals of their values.
+struct SegmentOrdCounter {
+    leaf_ord: usize,
+    values: Box<dyn SortedSetDocValues>,
+    counts: Vec<i32>,
+}
+
+impl SegmentOrdCounter {
+    fn new<C: Codec>(reader: &LeafReaderContext<'_, C>, field: &str) -> Result<Self> {
+        let values = DocValues::get_sorted_set_view(reader.reader, field)?;
+        let counts = vec![0; values.get_value_count()];
+        Ok(SegmentOrdCounter {
+            leaf_ord: reader.ord,
+            values,
+            counts,
+        })
+    }
+
+    fn collect(&mut self, doc: DocId) -> Result<()> {
+        self.values.set_document(doc)?;
+        loop {
+            let ord = self.values.next_ord()?;
+            if ord == NO_MORE_ORDS {
+                break;
+            }
+            self.counts[ord as usize] += 1;
+        }
+        Ok(())
+    }
+}
+
+/// The leaf ord and the counts by segment ordinal of a collected leaf.
+type LeafCounts = (usize, Vec<i32>);
+
+/// A `Collector` that counts the hits of a query by the values of a
+/// `SortedDocValues` or `SortedSetDocValues` field.
+///
+/// The counts are kept per segment ordinal while collecting, use
+/// `SortedSetDocValuesFacetCounts` to merge them into labels once the search
+/// is done.
+pub struct SortedSetDocValuesFacetCollector {
+    field: String,
+    current: Option<SegmentOrdCounter>,
+    segment_counts: Vec<LeafCounts>,
+    channel: Option<(Sender<LeafCounts>, Receiver<LeafCounts>)>,
+}
+
+impl SortedSetDocValuesFacetCollector {
+    pub fn new(field: String) -> Self {
+        SortedSetDocValuesFacetCollector {
+            field,
+            current: None,
+            segment_counts: vec![],
+            channel: None,
+        }
+    }
+
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    fn collected_counts(&self) -> impl Iterator<Item = (usize, &[i32])> {
+        self.segment_counts
+            .iter()
+            .map(|(leaf_ord, counts)| (*leaf_ord, counts.as_slice()))
+            .chain(
+                self.current
+                    .iter()
+                    .map(|counter| (counter.leaf_ord, counter.counts.as_slice())),
+            )
+    }
+}
+
+impl SearchCollector for SortedSetDocValuesFacetCollector {
+    type LC = SortedSetDocValuesFacetLeafCollector;
+
+    fn set_next_reader<C: Codec>(&mut self, reader: &LeafReaderContext<'_, C>) -> Result<()> {
+        if let Some(counter) = self.current.take() {
+            self.segment_counts.push((counter.leaf_ord, counter.counts));
+        }
+        self.current = Some(SegmentOrdCounter::new(reader, &self.field)?);
+        Ok(())
+    }
+
+    fn support_parallel(&self) -> bool {
+        true
+    }
+
+    fn init_parallel(&mut self) {
+        if self.channel.is_none() {
+            self.channel = Some(channel());
+        }
+    }
+
+    fn leaf_collector<C: Codec>(
+        &self,
+        reader: &LeafReaderContext<'_, C>,
+    ) -> Result<SortedSetDocValuesFacetLeafCollector> {
+        Ok(SortedSetDocValuesFacetLeafCollector {
+            counter: SegmentOrdCounter::new(reader, &self.field)?,
+            channel: self.channel.as_ref().unwrap().0.clone(),
+        })
+    }
+
+    fn finish_parallel(&mut self) -> Result<()> {
+        let channel = self.channel.take();
+        // iff all the `weight.create_scorer(leaf_reader)` return None, the channel won't
+        // inited and thus stay None
+        if let Some((sender, receiver)) = channel {
+            drop(sender);
+            while let Ok(counts) = receiver.recv() {
+                self.segment_counts.push(counts);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Collector for SortedSetDocValuesFacetCollector {
+    fn needs_scores(&self) -> bool {
+        false
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, _scorer: &mut S) -> Result<()> {
+        match self.current {
+            Some(ref mut counter) => counter.collect(doc),
+            None => bail!(IllegalState(
+                "set_next_reader must be called before collect".into()
+            )),
+        }
+    }
+}
+
+pub struct SortedSetDocValuesFacetLeafCollector {
+    counter: SegmentOrdCounter,
+    channel: Sender<LeafCounts>,
+}
+
+impl ParallelLeafCollector for SortedSetDocValuesFacetLeafCollector {
+    fn finish_leaf(&mut self) -> Result<()> {
+        let counts = mem::replace(&mut self.counter.counts, vec![]);
+        self.channel
+            .send((self.counter.leaf_ord, counts))
+            .chain_err(|| IllegalState("channel unexpected closed before search complete".into()))
+    }
+}
+
+impl Collector for SortedSetDocValuesFacetLeafCollector {
+    fn needs_scores(&self) -> bool {
+        false
+    }
+
+    fn collect<S: Scorer + ?Sized>(&mut self, doc: DocId, _scorer: &mut S) -> Result<()> {
+        self.counter.collect(doc)
+    }
+}
+
+/// The facet counts of a dimension, by global ordinal, computed from the
+/// segment counts of a `SortedSetDocValuesFacetCollector`.
+pub struct SortedSetDocValuesFacetCounts<'a> {
+    state: &'a SortedSetDocValuesReaderState,
+    counts: Vec<i32>,
+}
+
+impl<'a> SortedSetDocValuesFacetCounts<'a> {
+    /// Merges the counts of `collector`, which must have collected a search on
+    /// the reader `state` was created from.
+    pub fn new(
+        state: &'a SortedSetDocValuesReaderState,
+        collector: &SortedSetDocValuesFacetCollector,
+    ) -> Result<Self> {
+        if collector.field() != state.field() {
+            bail!(IllegalArgument(format!(
+                "the facet collector counts field '{}' but the state is for field '{}'",
+                collector.field(),
+                state.field()
+            )));
+        }
+
+        let mut counts = vec![0; state.value_count()];
+        for (leaf_ord, segment_counts) in collector.collected_counts() {
+            state.accumulate(leaf_ord, segment_counts, &mut counts)?;
+        }
+        Ok(SortedSetDocValuesFacetCounts { state, counts })
+    }
+
+    /// Returns the `top_n` labels with the highest counts, ties broken by the
+    /// labels' natural order.
+    pub fn top_children(&self, top_n: usize) -> Result<FacetResult> {
+        if top_n == 0 {
+            bail!(IllegalArgument("top_n must be > 0".into()));
+        }
+
+        let mut value = 0;
+        let mut children: Vec<(usize, i32)> = vec![];
+        for (ord, &count) in self.counts.iter().enumerate() {
+            if count > 0 {
+                value += count;
+                children.push((ord, count));
+            }
+        }
+        let child_count = children.len();
+        children.sort_by(|a, b| match b.1.cmp(&a.1) {
+            Ordering::Equal => a.0.cmp(&b.0),
+            o => o,
+        });
+        children.truncate(top_n);
+
+        let mut label_values = Vec::with_capacity(children.len());
+        for (ord, count) in children {
+            let label = self.state.lookup_ord(ord as i64)?;
+            label_values.push(LabelAndValue {
+                label: String::from_utf8_lossy(&label).into_owned(),
+                value: count,
+            });
+        }
+
+        Ok(FacetResult {
+            dim: self.state.field().to_string(),
+            value,
+            child_count,
+            label_values,
+        })
+    }
+
+    /// Returns the count of `label`, 0 if no hit has it.
+    pub fn specific_value(&self, label: &[u8]) -> Result<i32> {
+        Ok(match self.state.lookup_label(label)? {
+            Some(ord) => self.counts[ord as usize],
+            None => 0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate tempfile;
+
+    use super::*;
+    use core::doc::{Field, Fieldable, SortedSetDocValuesField, SORTED_DOC_VALUES_FIELD_TYPE};
+    use core::index::writer::{IndexWriter, IndexWriterConfig};
+    use core::search::collector::TopDocsCollector;
+    use core::search::facet::DrillDownQuery;
+    use core::search::query::{MatchAllDocsQuery, Query};
+    use core::search::{DefaultIndexSearcher, IndexSearcher};
+    use core::store::directory::FSDirectory;
+    use core::util::VariantValue;
+
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_sorted_set_facets() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let directory = Arc::new(FSDirectory::with_path(temp_dir.path()).unwrap());
+        let writer = IndexWriter::new(directory, Arc::new(IndexWriterConfig::default())).unwrap();
+
+        // every fifth doc has no tags, the others have one to three tags;
+        // the second segment has tags the first one doesn't
+        let mut docs: Vec<(Vec<String>, String)> = vec![];
+        for i in 0..80 {
+            let mut tags = vec![];
+            if i % 5 != 0 {
+                let range = if i < 40 { 6 } else { 9 };
+                for j in 0..=(i % 3) {
+                    tags.push(format!("tag{}", (i * 7 + j * 3) % range));
+                }
+                tags.sort();
+                tags.dedup();
+            }
+            let author = format!("author{}", i % 4);
+            let mut doc: Vec<Box<dyn Fieldable>> = vec![Box::new(Field::new(
+                "author".into(),
+                SORTED_DOC_VALUES_FIELD_TYPE,
+                Some(VariantValue::Binary(author.as_bytes().to_vec())),
+                None,
+            ))];
+            for tag in &tags {
+                doc.push(Box::new(SortedSetDocValuesField::new(
+                    "tags",
+                    tag.as_bytes(),
+                )));
+            }
+            writer.add_document(doc).unwrap();
+            docs.push((tags, author));
+            if i == 39 {
+                writer.commit().unwrap();
+            }
+        }
+        writer.commit().unwrap();
+
+        let reader = writer.get_reader(true, false).unwrap();
+        let searcher = DefaultIndexSearcher::new(Arc::new(reader), None);
+        let tags_state =
+            SortedSetDocValuesReaderState::new(searcher.reader(), "tags".into()).unwrap();
+        let author_state =
+            SortedSetDocValuesReaderState::new(searcher.reader(), "author".into()).unwrap();
+        assert_eq!(tags_state.value_count(), 9);
+        assert_eq!(author_state.value_count(), 4);
+
+        // the states are shared by the searches on the reader
+        fn assert_send_sync<T: Send + Sync>(_: &T) {}
+        assert_send_sync(&tags_state);
+
+        let expected_top = |filter: &dyn Fn(&(Vec<String>, String)) -> bool, top_n: usize| {
+            let mut counts: HashMap<&str, i32> = HashMap::new();
+            for doc in docs.iter().filter(|d| filter(d)) {
+                for tag in &doc.0 {
+                    *counts.entry(tag.as_str()).or_insert(0) += 1;
+                }
+            }
+            let mut counts: Vec<(&str, i32)> = counts.into_iter().collect();
+            counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+            let value = counts.iter().map(|c| c.1).sum();
+            let child_count = counts.len();
+            counts.truncate(top_n);
+            FacetResult {
+                dim: "tags".into(),
+                value,
+                child_count,
+                label_values: counts
+                    .into_iter()
+                    .map(|(label, value)| LabelAndValue {
+                        label: label.to_string(),
+                        value,
+                    })
+                    .collect(),
+            }
+        };
+        let count_tags = |query: &dyn Query<_>| {
+            let mut collector = SortedSetDocValuesFacetCollector::new("tags".into());
+            searcher.search(query, &mut collector).unwrap();
+            collector
+        };
+
+        let collector = count_tags(&MatchAllDocsQuery);
+        let counts = SortedSetDocValuesFacetCounts::new(&tags_state, &collector).unwrap();
+        assert_eq!(counts.top_children(3).unwrap(), expected_top(&|_| true, 3));
+        assert_eq!(
+            counts.top_children(20).unwrap(),
+            expected_top(&|_| true, 20)
+        );
+        let tag8 = docs.iter().filter(|d| d.0.contains(&"tag8".into())).count();
+        assert_eq!(counts.specific_value(b"tag8").unwrap(), tag8 as i32);
+        assert_eq!(counts.specific_value(b"missing").unwrap(), 0);
+        // the counts of a field can only be resolved by a state of that field
+        assert!(SortedSetDocValuesFacetCounts::new(&author_state, &collector).is_err());
+
+        // drill down into an author, then into two tags of that author
+        let mut query = DrillDownQuery::new(None);
+        query.add("author", b"author1".to_vec());
+        let collector = count_tags(&query);
+        let counts = SortedSetDocValuesFacetCounts::new(&tags_state, &collector).unwrap();
+        assert_eq!(
+            counts.top_children(5).unwrap(),
+            expected_top(&|d| d.1 == "author1", 5)
+        );
+
+        query.add("tags", b"tag1".to_vec());
+        query.add("tags", b"tag7".to_vec());
+        let has_tag = |d: &(Vec<String>, String)| {
+            d.0.contains(&"tag1".into()) || d.0.contains(&"tag7".into())
+        };
+        let collector = count_tags(&query);
+        let counts = SortedSetDocValuesFacetCounts::new(&tags_state, &collector).unwrap();
+        assert_eq!(
+            counts.top_children(5).unwrap(),
+            expected_top(&|d| d.1 == "author1" && has_tag(d), 5)
+        );
+
+        let mut top_docs = TopDocsCollector::new(100);
+        let query = DrillDownQuery::new(Some(Box::new(MatchAllDocsQuery)));
+        searcher.search(&query, &mut top_docs).unwrap();
+        assert_eq!(top_docs.top_docs().total_hits(), docs.len());
+    }
+}
@@ -13,6 +13,8 @@
 
 pub mod cache;
 pub mod collector;
+pub mod expression;
+pub mod facet;
 pub mod query;
 pub mod scorer;
 pub mod similarity;
@@ -0,0 +1,496 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::any::Any;
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::sync::Arc;
+
+use core::codec::{Codec, PostingIteratorFlags, TermIterator, Terms};
+use core::doc::Term;
+use core::index::reader::LeafReaderContext;
+use core::search::query::constant_score_docs::{ConstantScoreDocs, ConstantScoreDocsWeight};
+use core::search::query::{BooleanQuery, Query, TermQuery, Weight};
+use core::search::scorer::{ConstantScoreScorer, Scorer};
+use core::search::searcher::{SearchPlanBuilder, TermContext};
+use core::search::{DocIdSet, DocIterator};
+use core::util::automaton::{Automaton, CompiledAutomaton};
+use core::util::DocIdSetBuilder;
+
+use error::{ErrorKind::IllegalArgument, Result};
+
+pub const MULTI_TERM: &str = "multi_term";
+
+/// The maximum number of terms a query may expand to with a scoring
+/// `RewriteMethod`.
+pub const MAX_CLAUSE_COUNT: usize = 1024;
+
+/// Determines how a query that matches many terms is searched.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RewriteMethod {
+    /// Collects the documents of all matching terms per segment into a doc
+    /// id set, and gives every hit the query boost as its score. This is
+    /// the default; it is fast and works for any number of terms.
+    ConstantScore,
+    /// Expands into a `BooleanQuery` with a SHOULD `TermQuery` for each
+    /// matching term, so that hits are scored by their terms. Fails if more
+    /// than `MAX_CLAUSE_COUNT` terms match.
+    ScoringBoolean,
+    /// Like `ScoringBoolean`, but only keeps the given number of terms with
+    /// the highest boost, and scores them all with the highest document
+    /// frequency among them. This keeps rare terms, like misspellings, from
+    /// outscoring the frequent ones.
+    TopTermsBlendedFreqScoring(usize),
+}
+
+impl Default for RewriteMethod {
+    fn default() -> Self {
+        RewriteMethod::ConstantScore
+    }
+}
+
+/// Decides which of the terms accepted by an automaton a query matches and
+/// how much each of them is boosted, see `FuzzyQuery`.
+pub(crate) trait TermBoost: Send + Sync {
+    /// Returns the boost of `term`, or `None` if it doesn't match.
+    fn boost(&self, term: &[u8]) -> Option<f32>;
+}
+
+/// A `Query` that matches the documents containing any term accepted by an
+/// `Automaton`.
+///
+/// This is the shared implementation of `PrefixQuery`, `WildcardQuery`,
+/// `RegexpQuery` and `FuzzyQuery`; it may also be used directly with a custom
+/// automaton.
+pub struct AutomatonQuery {
+    term: Term,
+    compiled: Arc<CompiledAutomaton>,
+    term_boost: Option<Arc<dyn TermBoost>>,
+    rewrite_method: RewriteMethod,
+    boost: f32,
+}
+
+impl AutomatonQuery {
+    /// Creates a query matching the terms of `term.field` accepted by
+    /// `automaton`; `term.bytes` is only used for display.
+    pub fn new(
+        term: Term,
+        automaton: &Automaton,
+        max_determinized_states: usize,
+    ) -> Result<AutomatonQuery> {
+        let compiled = CompiledAutomaton::new(automaton, max_determinized_states)?;
+        Ok(AutomatonQuery {
+            term,
+            compiled: Arc::new(compiled),
+            term_boost: None,
+            rewrite_method: RewriteMethod::default(),
+            boost: 1f32,
+        })
+    }
+
+    pub(crate) fn set_term_boost(&mut self, term_boost: Arc<dyn TermBoost>) {
+        self.term_boost = Some(term_boost);
+    }
+
+    pub fn field(&self) -> &str {
+        &self.term.field
+    }
+
+    pub fn term(&self) -> &Term {
+        &self.term
+    }
+
+    pub fn compiled(&self) -> &CompiledAutomaton {
+        &self.compiled
+    }
+
+    pub fn rewrite_method(&self) -> RewriteMethod {
+        self.rewrite_method
+    }
+
+    pub fn set_rewrite_method(&mut self, rewrite_method: RewriteMethod) {
+        self.rewrite_method = rewrite_method;
+    }
+
+    pub fn boost(&self) -> f32 {
+        self.boost
+    }
+
+    pub fn set_boost(&mut self, boost: f32) {
+        self.boost = boost;
+    }
+
+    fn term_boost(&self, term: &[u8]) -> Option<f32> {
+        match self.term_boost {
+            Some(ref term_boost) => term_boost.boost(term),
+            None => Some(1f32),
+        }
+    }
+
+    /// Collects the matching terms with the highest boosts; fails if
+    /// `max_terms` is exceeded and `overflow_is_error` is set.
+    fn collect_terms<C: Codec>(
+        &self,
+        searcher: &dyn SearchPlanBuilder<C>,
+        max_terms: usize,
+        overflow_is_error: bool,
+    ) -> Result<Vec<ScoreTerm>> {
+        let mut terms = BTreeSet::new();
+        for leaf in searcher.leaves() {
+            if let Some(field_terms) = leaf.reader.terms(&self.term.field)? {
+                self.compiled.visit_matching_terms(&field_terms, |iter| {
+                    let term = iter.term()?;
+                    if let Some(boost) = self.term_boost(term) {
+                        terms.insert(ScoreTerm {
+                            boost,
+                            term: term.to_vec(),
+                        });
+                        if terms.len() > max_terms {
+                            if overflow_is_error {
+                                bail!(IllegalArgument(format!(
+                                    "{} matches more than {} terms",
+                                    self, max_terms
+                                )));
+                            }
+                            let last = terms.iter().next_back().cloned();
+                            if let Some(last) = last {
+                                terms.remove(&last);
+                            }
+                        }
+                    }
+                    Ok(())
+                })?;
+            }
+        }
+        Ok(terms.into_iter().collect())
+    }
+
+    /// Replaces the document frequencies of the terms with the highest one
+    /// among them, and so for the total term frequencies.
+    fn blend_statistics<C: Codec>(
+        &self,
+        searcher: &dyn SearchPlanBuilder<C>,
+        terms: &[ScoreTerm],
+    ) -> Result<(i64, i64)> {
+        let mut contexts: Vec<_> = terms.iter().map(|_| TermContext::default()).collect();
+        for leaf in searcher.leaves() {
+            if let Some(field_terms) = leaf.reader.terms(&self.term.field)? {
+                let mut iter = field_terms.iterator()?;
+                for (score_term, context) in terms.iter().zip(contexts.iter_mut()) {
+                    if iter.seek_exact(&score_term.term)? {
+                        context.register(
+                            iter.term_state()?,
+                            leaf.doc_base,
+                            iter.doc_freq()?,
+                            iter.total_term_freq()?,
+                        );
+                    }
+                }
+            }
+        }
+
+        let mut max_doc_freq = 0i64;
+        let mut max_total_term_freq = 0i64;
+        for context in &contexts {
+            max_doc_freq = max_doc_freq.max(i64::from(context.doc_freq));
+            if context.total_term_freq == -1 || max_total_term_freq == -1 {
+                max_total_term_freq = -1;
+            } else {
+                max_total_term_freq = max_total_term_freq.max(context.total_term_freq);
+            }
+        }
+        Ok((max_doc_freq, max_total_term_freq))
+    }
+}
+
+impl<C: Codec> Query<C> for AutomatonQuery {
+    fn create_weight(
+        &self,
+        _searcher: &dyn SearchPlanBuilder<C>,
+        _needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        let docs = AutomatonDocs {
+            field: self.term.field.clone(),
+            compiled: Arc::clone(&self.compiled),
+            term_boost: self.term_boost.clone(),
+        };
+        Ok(Box::new(ConstantScoreDocsWeight::new(
+            docs, MULTI_TERM, self.boost,
+        )))
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        vec![]
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn rewrite(&self, searcher: &dyn SearchPlanBuilder<C>) -> Result<Option<Box<dyn Query<C>>>> {
+        let (terms, stats) = match self.rewrite_method {
+            RewriteMethod::ConstantScore => return Ok(None),
+            RewriteMethod::ScoringBoolean => {
+                (self.collect_terms(searcher, MAX_CLAUSE_COUNT, true)?, None)
+            }
+            RewriteMethod::TopTermsBlendedFreqScoring(size) => {
+                let size = size.min(MAX_CLAUSE_COUNT);
+                let terms = self.collect_terms(searcher, size, false)?;
+                let stats = self.blend_statistics(searcher, &terms)?;
+                (terms, Some(stats))
+            }
+        };
+        if terms.is_empty() {
+            return Ok(None);
+        }
+        let shoulds: Vec<Box<dyn Query<C>>> = terms
+            .into_iter()
+            .map(|score_term| {
+                let term = Term::new(self.term.field.clone(), score_term.term);
+                let boost = self.boost * score_term.boost;
+                let query = match stats {
+                    Some((doc_freq, total_term_freq)) => {
+                        TermQuery::with_statistics(term, boost, None, doc_freq, total_term_freq)
+                    }
+                    None => TermQuery::new(term, boost, None),
+                };
+                Box::new(query) as Box<dyn Query<C>>
+            })
+            .collect();
+        let query = BooleanQuery::build(vec![], shoulds, vec![], vec![], 0)?;
+        Ok(Some(query))
+    }
+}
+
+impl fmt::Display for AutomatonQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "AutomatonQuery(field: {}, term: {}, boost: {})",
+            &self.term.field,
+            String::from_utf8_lossy(&self.term.bytes),
+            self.boost
+        )
+    }
+}
+
+/// A matching term with its boost, ordered by descending boost and then by
+/// term.
+#[derive(Clone, Debug)]
+struct ScoreTerm {
+    boost: f32,
+    term: Vec<u8>,
+}
+
+impl Ord for ScoreTerm {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .boost
+            .partial_cmp(&self.boost)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| self.term.cmp(&other.term))
+    }
+}
+
+impl PartialOrd for ScoreTerm {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for ScoreTerm {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for ScoreTerm {}
+
+/// Constant score weight of `RewriteMethod::ConstantScore`: matches the
+/// union of the documents of all terms accepted by the automaton.
+struct AutomatonDocs {
+    field: String,
+    compiled: Arc<CompiledAutomaton>,
+    term_boost: Option<Arc<dyn TermBoost>>,
+}
+
+impl<C: Codec> ConstantScoreDocs<C> for AutomatonDocs {
+    fn scorer(
+        &self,
+        leaf_reader: &LeafReaderContext<'_, C>,
+        score: f32,
+    ) -> Result<Option<Box<dyn Scorer>>> {
+        let terms = match leaf_reader.reader.terms(&self.field)? {
+            Some(terms) => terms,
+            None => return Ok(None),
+        };
+
+        let mut builder = DocIdSetBuilder::from_terms(leaf_reader.reader.max_doc(), &terms)?;
+        let mut num_terms = 0;
+        self.compiled.visit_matching_terms(&terms, |iter| {
+            if let Some(ref term_boost) = self.term_boost {
+                if term_boost.boost(iter.term()?).is_none() {
+                    return Ok(());
+                }
+            }
+            let mut postings = iter.postings_with_flags(PostingIteratorFlags::NONE)?;
+            builder.add(&mut postings)?;
+            num_terms += 1;
+            Ok(())
+        })?;
+        if num_terms == 0 {
+            return Ok(None);
+        }
+
+        if let Some(iterator) = builder.build().iterator()? {
+            let cost = iterator.cost();
+            Ok(Some(Box::new(ConstantScoreScorer::new(
+                score, iterator, cost,
+            ))))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl fmt::Display for AutomatonDocs {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "AutomatonDocs(field: {})", &self.field)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate tempfile;
+
+    use super::*;
+    use core::doc::{Field, FieldType, Fieldable, IndexOptions};
+    use core::index::writer::{IndexWriter, IndexWriterConfig};
+    use core::search::collector::TopDocsCollector;
+    use core::search::query::{
+        BoostingQuery, ConstantScoreQuery, DisjunctionMaxQuery, PrefixQuery, RegexpQuery,
+        WildcardQuery,
+    };
+    use core::search::{DefaultIndexSearcher, IndexSearcher};
+    use core::store::directory::FSDirectory;
+    use core::util::VariantValue;
+
+    fn words() -> Vec<String> {
+        let mut words: Vec<String> = (0..2000).map(|i| format!("w{:04}", i)).collect();
+        for w in &[
+            "apple", "apply", "banana", "band", "bandana", "ñandú", "a*b", "a?b",
+        ] {
+            words.push((*w).to_string());
+        }
+        words
+    }
+
+    #[test]
+    fn test_automaton_queries() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let directory = Arc::new(FSDirectory::with_path(temp_dir.path()).unwrap());
+        let writer = IndexWriter::new(directory, Arc::new(IndexWriterConfig::default())).unwrap();
+
+        let mut field_type = FieldType::default();
+        field_type.index_options = IndexOptions::Docs;
+        let words = words();
+        // two segments, each with enough terms to need several blocks
+        for chunk in words.chunks(1100) {
+            for word in chunk {
+                let doc: Vec<Box<dyn Fieldable>> = vec![Box::new(Field::new(
+                    "word".into(),
+                    field_type.clone(),
+                    Some(VariantValue::VString(word.clone())),
+                    None,
+                ))];
+                writer.add_document(doc).unwrap();
+            }
+            writer.commit().unwrap();
+        }
+
+        let reader = writer.get_reader(true, false).unwrap();
+        let searcher = DefaultIndexSearcher::new(Arc::new(reader), None);
+        let search = |query: &dyn Query<_>| {
+            let mut collector = TopDocsCollector::new(10);
+            searcher.search(query, &mut collector)?;
+            Ok(collector.top_docs().total_hits())
+        };
+        let expected = |f: &dyn Fn(&str) -> bool| words.iter().filter(|w| f(w)).count();
+        let term = |text: &str| Term::new("word".into(), text.as_bytes().to_vec());
+
+        for &method in &[RewriteMethod::ConstantScore, RewriteMethod::ScoringBoolean] {
+            let prefix = |text: &str| -> Result<usize> {
+                let mut query = PrefixQuery::new(term(text))?;
+                query.set_rewrite_method(method);
+                search(&query)
+            };
+            assert_eq!(prefix("w01").unwrap(), 100);
+            assert_eq!(prefix("w0").unwrap(), 1000);
+            assert_eq!(prefix("band").unwrap(), 2);
+            assert_eq!(prefix("x").unwrap(), 0);
+
+            let wildcard = |text: &str| -> Result<usize> {
+                let mut query = WildcardQuery::new(term(text))?;
+                query.set_rewrite_method(method);
+                search(&query)
+            };
+            assert_eq!(
+                wildcard("*9").unwrap(),
+                expected(&|w: &str| w.ends_with('9'))
+            );
+            assert_eq!(
+                wildcard("w?5??").unwrap(),
+                expected(&|w: &str| w.len() == 5 && w.starts_with('w') && &w[2..3] == "5")
+            );
+            assert_eq!(wildcard("*an*").unwrap(), 4);
+            assert_eq!(wildcard("?and?").unwrap(), 1);
+            assert_eq!(wildcard("appl?").unwrap(), 2);
+            assert_eq!(wildcard("a\\*b").unwrap(), 1);
+            assert_eq!(wildcard("a?b").unwrap(), 2);
+
+            let regexp = |text: &str| -> Result<usize> {
+                let mut query = RegexpQuery::new(term(text))?;
+                query.set_rewrite_method(method);
+                search(&query)
+            };
+            assert_eq!(regexp("w1[2-4]0.").unwrap(), 30);
+            assert_eq!(regexp("ban.*").unwrap(), 3);
+            assert_eq!(regexp("(apple|band)a?").unwrap(), 2);
+            assert_eq!(regexp("w.*7").unwrap(), 200);
+            assert_eq!(regexp("[^w].*").unwrap(), 8);
+        }
+
+        let mut all = RegexpQuery::new(term(".*")).unwrap();
+        assert_eq!(search(&all).unwrap(), words.len());
+        all.set_rewrite_method(RewriteMethod::ScoringBoolean);
+        assert!(search(&all).is_err());
+
+        // the queries nested in another one are rewritten too
+        let all = || -> Box<dyn Query<_>> {
+            let mut query = RegexpQuery::new(term(".*")).unwrap();
+            query.set_rewrite_method(RewriteMethod::ScoringBoolean);
+            Box::new(query)
+        };
+        let band = || -> Box<dyn Query<_>> { Box::new(TermQuery::new(term("band"), 1.0, None)) };
+        let nested: Vec<Box<dyn Query<_>>> = vec![
+            Box::new(ConstantScoreQuery::new(all())),
+            BooleanQuery::build(vec![band(), all()], vec![], vec![], vec![], 0).unwrap(),
+            DisjunctionMaxQuery::build(vec![band(), all()], 0.0).unwrap(),
+            BoostingQuery::build(band(), all(), 0.5),
+        ];
+        for query in &nested {
+            assert!(search(query.as_ref()).is_err());
+        }
+    }
+}
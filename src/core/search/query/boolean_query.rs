@@ -19,9 +19,9 @@ use core::index::reader::LeafReaderContext;
 use core::search::explanation::Explanation;
 use core::search::query::{ConstantScoreQuery, MatchAllDocsQuery, Query, TermQuery, Weight};
 use core::search::scorer::{
-    ConjunctionScorer, DisjunctionSumScorer, ReqNotScorer, ReqOptScorer, Scorer,
+    ConjunctionScorer, DisjunctionSumScorer, ReqNotScorer, ReqOptScorer, Scorer, WANDScorer,
 };
-use core::search::searcher::SearchPlanBuilder;
+use core::search::searcher::{ScoreMode, SearchPlanBuilder};
 use core::util::DocId;
 use error::{ErrorKind::IllegalArgument, Result};
 
@@ -86,6 +86,26 @@ impl<C: Codec> BooleanQuery<C> {
         }))
     }
 
+    pub fn musts(&self) -> &[Box<dyn Query<C>>] {
+        &self.must_queries
+    }
+
+    pub fn shoulds(&self) -> &[Box<dyn Query<C>>] {
+        &self.should_queries
+    }
+
+    pub fn filters(&self) -> &[Box<dyn Query<C>>] {
+        &self.filter_queries
+    }
+
+    pub fn must_nots(&self) -> &[Box<dyn Query<C>>] {
+        &self.must_not_queries
+    }
+
+    pub fn min_should_match(&self) -> i32 {
+        self.min_should_match
+    }
+
     fn queries_to_str(&self, queries: &[Box<dyn Query<C>>]) -> String {
         let query_strs: Vec<String> = queries.iter().map(|q| format!("{}", q)).collect();
         query_strs.join(", ")
@@ -98,6 +118,21 @@ impl<C: Codec> Query<C> for BooleanQuery<C> {
         searcher: &dyn SearchPlanBuilder<C>,
         needs_scores: bool,
     ) -> Result<Box<dyn Weight<C>>> {
+        let score_mode = if needs_scores {
+            ScoreMode::Complete
+        } else {
+            ScoreMode::CompleteNoScores
+        };
+        self.create_weight_with_mode(searcher, score_mode)
+    }
+
+    fn create_weight_with_mode(
+        &self,
+        searcher: &dyn SearchPlanBuilder<C>,
+        score_mode: ScoreMode,
+    ) -> Result<Box<dyn Weight<C>>> {
+        // only this query may skip the non-competitive docs, not its clauses
+        let needs_scores = score_mode.needs_scores();
         let mut must_weights =
             Vec::with_capacity(self.must_queries.len() + self.filter_queries.len());
         for q in &self.must_queries {
@@ -119,7 +154,7 @@ impl<C: Codec> Query<C> for BooleanQuery<C> {
             must_weights,
             should_weights,
             must_not_weights,
-            needs_scores,
+            score_mode,
             self.min_should_match,
         )))
     }
@@ -166,7 +201,7 @@ struct BooleanWeight<C: Codec> {
     should_weights: Vec<Box<dyn Weight<C>>>,
     must_not_weights: Vec<Box<dyn Weight<C>>>,
     min_should_match: i32,
-    needs_scores: bool,
+    score_mode: ScoreMode,
 }
 
 impl<C: Codec> BooleanWeight<C> {
@@ -174,7 +209,7 @@ impl<C: Codec> BooleanWeight<C> {
         musts: Vec<Box<dyn Weight<C>>>,
         shoulds: Vec<Box<dyn Weight<C>>>,
         must_nots: Vec<Box<dyn Weight<C>>>,
-        needs_scores: bool,
+        score_mode: ScoreMode,
         min_should_match: i32,
     ) -> BooleanWeight<C> {
         BooleanWeight {
@@ -182,7 +217,7 @@ impl<C: Codec> BooleanWeight<C> {
             should_weights: shoulds,
             must_not_weights: must_nots,
             min_should_match,
-            needs_scores,
+            score_mode,
         }
     }
 
@@ -225,9 +260,18 @@ impl<C: Codec> Weight<C> for BooleanWeight<C> {
                 0 => None,
                 // min_should_match
                 // 1 => Some(scorers.remove(0)),
+                // pure disjunctions may skip the non-competitive docs
+                n if n > 1
+                    && self.must_weights.is_empty()
+                    && self.score_mode == ScoreMode::TopScores
+                    && self.min_should_match <= 1
+                    && scorers.iter().all(|s| !s.support_two_phase()) =>
+                {
+                    Some(Box::new(WANDScorer::new(scorers)))
+                }
                 _ => Some(Box::new(DisjunctionSumScorer::new(
                     scorers,
-                    self.needs_scores,
+                    self.score_mode.needs_scores(),
                     self.min_should_match,
                 ))),
             }
@@ -305,7 +349,7 @@ impl<C: Codec> Weight<C> for BooleanWeight<C> {
     }
 
     fn needs_scores(&self) -> bool {
-        self.needs_scores
+        self.score_mode.needs_scores()
     }
 
     fn explain(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Explanation> {
@@ -410,7 +454,170 @@ impl<C: Codec> fmt::Display for BooleanWeight<C> {
             f,
             "BooleanWeight(must: [{}], should: [{}], must_not: [{}], min match: {}, needs score: \
              {})",
-            must_str, should_str, must_not_str, self.min_should_match, self.needs_scores
+            must_str,
+            should_str,
+            must_not_str,
+            self.min_should_match,
+            self.score_mode.needs_scores()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate tempfile;
+
+    use super::*;
+    use core::doc::{Field, FieldType, Fieldable, IndexOptions, Term};
+    use core::index::writer::{IndexWriter, IndexWriterConfig};
+    use core::search::collector::{Collector, TopDocsCollector};
+    use core::search::query::PhraseQuery;
+    use core::search::sort_field::ScoreDocHit;
+    use core::search::{DefaultIndexSearcher, IndexSearcher};
+    use core::store::directory::FSDirectory;
+    use core::util::VariantValue;
+
+    use std::sync::Arc;
+
+    #[test]
+    fn test_two_phase_clauses() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let directory = Arc::new(FSDirectory::with_path(temp_dir.path()).unwrap());
+        let writer = IndexWriter::new(directory, Arc::new(IndexWriterConfig::default())).unwrap();
+
+        let mut field_type = FieldType::default();
+        field_type.index_options = IndexOptions::DocsAndFreqsAndPositions;
+        for text in &[
+            "quick brown fox",
+            "quick red big fox brown",
+            "fox quick brown",
+            "quick fox",
+            "lazy old sleepy dog",
+            "lazy dog",
+        ] {
+            let doc: Vec<Box<dyn Fieldable>> = vec![Box::new(Field::new(
+                "body".into(),
+                field_type.clone(),
+                Some(VariantValue::VString((*text).to_string())),
+                None,
+            ))];
+            writer.add_document(doc).unwrap();
+        }
+        writer.commit().unwrap();
+
+        let reader = writer.get_reader(true, false).unwrap();
+        let searcher = DefaultIndexSearcher::new(Arc::new(reader), None);
+        let term = |text: &str| Term::new("body".into(), text.as_bytes().to_vec());
+        let phrase_query = |first: &str, second: &str| -> Box<dyn Query<_>> {
+            Box::new(
+                PhraseQuery::new(vec![term(first), term(second)], vec![0, 1], 1, None, None)
+                    .unwrap(),
+            )
+        };
+        let search = |query: Box<dyn Query<_>>| -> Vec<DocId> {
+            let mut collector = TopDocsCollector::new(10);
+            searcher.search(query.as_ref(), &mut collector).unwrap();
+            let mut docs: Vec<DocId> = collector
+                .top_docs()
+                .score_docs()
+                .iter()
+                .map(ScoreDocHit::doc_id)
+                .collect();
+            docs.sort();
+            docs
+        };
+
+        // the conjunction confirms the approximation of the sloppy phrase
+        let query = BooleanQuery::build(
+            vec![
+                phrase_query("quick", "fox"),
+                Box::new(TermQuery::new(term("brown"), 1.0, None)),
+            ],
+            vec![],
+            vec![],
+            vec![],
+            0,
+        )
+        .unwrap();
+        assert_eq!(search(query), vec![0]);
+
+        // the disjunction iterates the matching docs of its phrases
+        let query = BooleanQuery::build(
+            vec![],
+            vec![phrase_query("quick", "fox"), phrase_query("lazy", "dog")],
+            vec![],
+            vec![],
+            0,
+        )
+        .unwrap();
+        assert_eq!(search(query), vec![0, 3, 5]);
+    }
+
+    #[test]
+    fn test_disjunction_skips_only_for_top_scores() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let directory = Arc::new(FSDirectory::with_path(temp_dir.path()).unwrap());
+        let writer = IndexWriter::new(directory, Arc::new(IndexWriterConfig::default())).unwrap();
+
+        let mut field_type = FieldType::default();
+        field_type.index_options = IndexOptions::DocsAndFreqs;
+        let mut matching = 0;
+        for i in 0..2000 {
+            let mut words = vec!["c"];
+            if i % 2 == 0 {
+                words.extend(vec!["a"; 1 + i % 5]);
+            }
+            if i % 3 == 0 {
+                words.extend(vec!["b"; 1 + i % 7]);
+            }
+            if words.len() > 1 {
+                matching += 1;
+            }
+            let doc: Vec<Box<dyn Fieldable>> = vec![Box::new(Field::new(
+                "body".into(),
+                field_type.clone(),
+                Some(VariantValue::VString(words.join(" "))),
+                None,
+            ))];
+            writer.add_document(doc).unwrap();
+        }
+        writer.commit().unwrap();
+
+        let reader = writer.get_reader(true, false).unwrap();
+        let searcher = DefaultIndexSearcher::new(Arc::new(reader), None);
+        let term_query = |text: &str| -> Box<dyn Query<_>> {
+            Box::new(TermQuery::new(
+                Term::new("body".into(), text.as_bytes().to_vec()),
+                1.0,
+                None,
+            ))
+        };
+        let query = BooleanQuery::build(
+            vec![],
+            vec![term_query("a"), term_query("b")],
+            vec![],
+            vec![],
+            0,
         )
+        .unwrap();
+
+        let mut complete = TopDocsCollector::new(5);
+        assert_eq!(complete.score_mode(), ScoreMode::Complete);
+        searcher.search(query.as_ref(), &mut complete).unwrap();
+        let complete = complete.top_docs();
+        assert_eq!(complete.total_hits(), matching);
+
+        let mut top_scores = TopDocsCollector::with_total_hits_threshold(5, 10);
+        assert_eq!(top_scores.score_mode(), ScoreMode::TopScores);
+        searcher.search(query.as_ref(), &mut top_scores).unwrap();
+        let top_scores = top_scores.top_docs();
+        assert!(top_scores.total_hits() < matching);
+
+        let scores =
+            |hits: &[ScoreDocHit]| -> Vec<f32> { hits.iter().map(|h| h.score()).collect() };
+        assert_eq!(
+            scores(top_scores.score_docs()),
+            scores(complete.score_docs())
+        );
     }
 }
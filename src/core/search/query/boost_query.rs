@@ -14,6 +14,7 @@
 use std::any::Any;
 use std::f32;
 use std::fmt;
+use std::sync::Arc;
 
 use core::codec::Codec;
 use core::index::reader::LeafReaderContext;
@@ -33,7 +34,7 @@ const BOOST_QUERY: &str = "boost";
 /// query compared to other ones while values that are greater than one will
 /// give more importance to the scores returned by this query.
 pub struct BoostQuery<C: Codec> {
-    query: Box<dyn Query<C>>,
+    query: Arc<dyn Query<C>>,
     boost: f32,
 }
 
@@ -42,9 +43,20 @@ impl<C: Codec> BoostQuery<C> {
         if (boost - 1.0f32).abs() <= f32::EPSILON {
             query
         } else {
-            Box::new(BoostQuery { query, boost })
+            Box::new(BoostQuery {
+                query: Arc::from(query),
+                boost,
+            })
         }
     }
+
+    pub fn query(&self) -> &dyn Query<C> {
+        self.query.as_ref()
+    }
+
+    pub fn boost(&self) -> f32 {
+        self.boost
+    }
 }
 
 impl<C: Codec> Query<C> for BoostQuery<C> {
@@ -66,6 +78,23 @@ impl<C: Codec> Query<C> for BoostQuery<C> {
     fn as_any(&self) -> &dyn Any {
         self
     }
+
+    fn rewrite(&self, searcher: &dyn SearchPlanBuilder<C>) -> Result<Option<Box<dyn Query<C>>>> {
+        if let Some(inner) = self.query.as_any().downcast_ref::<BoostQuery<C>>() {
+            let boost = self.boost * inner.boost;
+            if (boost - 1.0f32).abs() <= f32::EPSILON {
+                return Ok(Some(Box::new(Arc::clone(&inner.query))));
+            }
+            return Ok(Some(Box::new(BoostQuery {
+                query: Arc::clone(&inner.query),
+                boost,
+            })));
+        }
+        Ok(self
+            .query
+            .rewrite(searcher)?
+            .map(|query| BoostQuery::build(query, self.boost)))
+    }
 }
 
 impl<C: Codec> fmt::Display for BoostQuery<C> {
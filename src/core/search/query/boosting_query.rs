@@ -13,6 +13,7 @@
 
 use std::any::Any;
 use std::fmt;
+use std::sync::Arc;
 
 use core::codec::Codec;
 use core::index::reader::LeafReaderContext;
@@ -27,8 +28,8 @@ use error::Result;
 const BOOSTING_QUERY: &str = "boosting";
 
 pub struct BoostingQuery<C: Codec> {
-    positive: Box<dyn Query<C>>,
-    negative: Box<dyn Query<C>>,
+    positive: Arc<dyn Query<C>>,
+    negative: Arc<dyn Query<C>>,
     negative_boost: f32,
 }
 
@@ -39,11 +40,23 @@ impl<C: Codec> BoostingQuery<C> {
         negative_boost: f32,
     ) -> Box<dyn Query<C>> {
         Box::new(BoostingQuery {
-            positive,
-            negative,
+            positive: Arc::from(positive),
+            negative: Arc::from(negative),
             negative_boost,
         })
     }
+
+    pub fn positive(&self) -> &dyn Query<C> {
+        self.positive.as_ref()
+    }
+
+    pub fn negative(&self) -> &dyn Query<C> {
+        self.negative.as_ref()
+    }
+
+    pub fn negative_boost(&self) -> f32 {
+        self.negative_boost
+    }
 }
 
 impl<C: Codec> Query<C> for BoostingQuery<C> {
@@ -53,8 +66,8 @@ impl<C: Codec> Query<C> for BoostingQuery<C> {
         needs_scores: bool,
     ) -> Result<Box<dyn Weight<C>>> {
         Ok(Box::new(BoostingWeight::new(
-            self.positive.create_weight(searcher, needs_scores)?,
-            self.negative.create_weight(searcher, false)?,
+            searcher.create_weight(self.positive.as_ref(), needs_scores)?,
+            searcher.create_weight(self.negative.as_ref(), false)?,
             self.negative_boost,
         )))
     }
@@ -66,6 +79,19 @@ impl<C: Codec> Query<C> for BoostingQuery<C> {
     fn as_any(&self) -> &dyn Any {
         self
     }
+
+    fn rewrite(&self, searcher: &dyn SearchPlanBuilder<C>) -> Result<Option<Box<dyn Query<C>>>> {
+        let positive = self.positive.rewrite(searcher)?;
+        let negative = self.negative.rewrite(searcher)?;
+        if positive.is_none() && negative.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(Box::new(BoostingQuery {
+            positive: positive.map_or_else(|| Arc::clone(&self.positive), Arc::from),
+            negative: negative.map_or_else(|| Arc::clone(&self.negative), Arc::from),
+            negative_boost: self.negative_boost,
+        })))
+    }
 }
 
 impl<C: Codec> fmt::Display for BoostingQuery<C> {
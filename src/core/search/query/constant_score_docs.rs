@@ -0,0 +1,120 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+
+use core::codec::Codec;
+use core::index::reader::LeafReaderContext;
+use core::search::explanation::Explanation;
+use core::search::query::Weight;
+use core::search::scorer::Scorer;
+use core::search::DocIterator;
+use core::util::DocId;
+
+use error::Result;
+
+/// The docs a `ConstantScoreDocsWeight` matches in each leaf.
+pub(crate) trait ConstantScoreDocs<C: Codec>: fmt::Display {
+    /// Creates a scorer that gives `score` to the docs matching in this leaf,
+    /// returns `None` if no doc matches.
+    fn scorer(
+        &self,
+        leaf_reader: &LeafReaderContext<'_, C>,
+        score: f32,
+    ) -> Result<Option<Box<dyn Scorer>>>;
+}
+
+/// A `Weight` that gives all the docs it matches the same score, the query
+/// boost times the query norm.
+pub(crate) struct ConstantScoreDocsWeight<D> {
+    docs: D,
+    query_type: &'static str,
+    boost: f32,
+    weight: f32,
+    norm: f32,
+}
+
+impl<D> ConstantScoreDocsWeight<D> {
+    pub fn new(docs: D, query_type: &'static str, boost: f32) -> Self {
+        ConstantScoreDocsWeight {
+            docs,
+            query_type,
+            boost,
+            weight: boost,
+            norm: 1f32,
+        }
+    }
+}
+
+impl<C: Codec, D: ConstantScoreDocs<C>> Weight<C> for ConstantScoreDocsWeight<D> {
+    fn create_scorer(
+        &self,
+        leaf_reader: &LeafReaderContext<'_, C>,
+    ) -> Result<Option<Box<dyn Scorer>>> {
+        self.docs.scorer(leaf_reader, self.weight)
+    }
+
+    fn query_type(&self) -> &'static str {
+        self.query_type
+    }
+
+    fn normalize(&mut self, norm: f32, boost: f32) {
+        self.norm = norm;
+        self.weight = norm * boost * self.boost;
+    }
+
+    fn value_for_normalization(&self) -> f32 {
+        self.weight * self.weight
+    }
+
+    fn needs_scores(&self) -> bool {
+        false
+    }
+
+    fn explain(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Explanation> {
+        let exists = if let Some(mut iterator) = self.create_scorer(reader)? {
+            iterator.advance(doc)? == doc
+        } else {
+            false
+        };
+
+        if exists {
+            Ok(Explanation::new(
+                true,
+                self.weight,
+                format!("{}, product of:", self),
+                vec![
+                    Explanation::new(true, self.boost, "boost".to_string(), vec![]),
+                    Explanation::new(true, self.norm, "queryNorm".to_string(), vec![]),
+                ],
+            ))
+        } else {
+            Ok(Explanation::new(
+                false,
+                0.0f32,
+                format!("{} doesn't match id {}", self, doc),
+                vec![],
+            ))
+        }
+    }
+}
+
+impl<D: fmt::Display> fmt::Display for ConstantScoreDocsWeight<D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "ConstantScoreDocsWeight({}, weight: {}, norm: {})",
+            &self.docs, self.weight, self.norm
+        )
+    }
+}
@@ -78,7 +78,7 @@ impl<C: Codec> Query<C> for DisjunctionMaxQuery<C> {
     ) -> Result<Box<dyn Weight<C>>> {
         let mut weights = Vec::with_capacity(self.disjuncts.len());
         for q in &self.disjuncts {
-            weights.push(q.create_weight(searcher, needs_scores)?);
+            weights.push(searcher.create_weight(q.as_ref(), needs_scores)?);
         }
 
         Ok(Box::new(DisjunctionMaxWeight::new(
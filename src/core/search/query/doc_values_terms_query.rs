@@ -0,0 +1,354 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::any::Any;
+use std::fmt;
+use std::sync::Arc;
+
+use core::codec::doc_values::lucene54::DocValues;
+use core::codec::doc_values::{SortedSetDocValues, NO_MORE_ORDS};
+use core::codec::Codec;
+use core::index::reader::LeafReaderContext;
+use core::search::query::constant_score_docs::{ConstantScoreDocs, ConstantScoreDocsWeight};
+use core::search::query::{Query, TermQuery, Weight};
+use core::search::scorer::{ConstantScoreScorer, Scorer};
+use core::search::searcher::SearchPlanBuilder;
+use core::search::{DocIterator, NO_MORE_DOCS};
+use core::util::bkd::LongBitSet;
+use core::util::DocId;
+
+use error::Result;
+
+pub const DOC_VALUES_TERMS: &str = "doc_values_terms";
+
+/// A `Query` that matches documents having any of the given terms in the
+/// `SortedDocValues` or `SortedSetDocValues` of a field.
+///
+/// Like `DocValuesTermRangeQuery`, the terms are translated to ordinals once per
+/// segment and documents are then checked one by one. The check is the second
+/// phase of a two phase iteration, so in a conjunction only the docs of the
+/// other clauses are checked: it is best used as a filter on a selective
+/// query, e.g. for the drill-down of a facet.
+pub struct DocValuesTermsQuery {
+    field: String,
+    terms: Arc<[Vec<u8>]>,
+}
+
+impl DocValuesTermsQuery {
+    pub fn new(field: String, mut terms: Vec<Vec<u8>>) -> DocValuesTermsQuery {
+        terms.sort();
+        terms.dedup();
+        DocValuesTermsQuery {
+            field,
+            terms: Arc::from(terms),
+        }
+    }
+
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    pub fn terms(&self) -> &[Vec<u8>] {
+        &self.terms
+    }
+}
+
+impl<C: Codec> Query<C> for DocValuesTermsQuery {
+    fn create_weight(
+        &self,
+        _searcher: &dyn SearchPlanBuilder<C>,
+        _needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        let docs = DocValuesTermsDocs {
+            field: self.field.clone(),
+            terms: Arc::clone(&self.terms),
+        };
+        Ok(Box::new(ConstantScoreDocsWeight::new(
+            docs,
+            DOC_VALUES_TERMS,
+            1f32,
+        )))
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        vec![]
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl fmt::Display for DocValuesTermsQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let terms: Vec<_> = self
+            .terms
+            .iter()
+            .map(|t| String::from_utf8_lossy(t))
+            .collect();
+        write!(
+            f,
+            "DocValuesTermsQuery(field: {}, terms: {:?})",
+            &self.field, terms
+        )
+    }
+}
+
+struct DocValuesTermsDocs {
+    field: String,
+    terms: Arc<[Vec<u8>]>,
+}
+
+impl<C: Codec> ConstantScoreDocs<C> for DocValuesTermsDocs {
+    fn scorer(
+        &self,
+        leaf_reader: &LeafReaderContext<'_, C>,
+        score: f32,
+    ) -> Result<Option<Box<dyn Scorer>>> {
+        let mut values = DocValues::get_sorted_set_view(leaf_reader.reader, &self.field)?;
+        let value_count = values.get_value_count() as i64;
+        if value_count == 0 {
+            return Ok(None);
+        }
+
+        let mut ords = LongBitSet::new(value_count);
+        let mut num_ords = 0;
+        for term in self.terms.iter() {
+            let ord = values.lookup_term(term)?;
+            if ord >= 0 {
+                ords.set(ord);
+                num_ords += 1;
+            }
+        }
+        if num_ords == 0 {
+            return Ok(None);
+        }
+
+        let max_doc = leaf_reader.reader.max_doc();
+        let iterator = SortedSetOrdsIterator {
+            values,
+            ords,
+            doc: -1,
+            max_doc,
+        };
+        Ok(Some(Box::new(ConstantScoreScorer::new(
+            score,
+            iterator,
+            max_doc as usize,
+        ))))
+    }
+}
+
+impl fmt::Display for DocValuesTermsDocs {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "DocValuesTermsDocs(field: {}, terms: {})",
+            &self.field,
+            self.terms.len()
+        )
+    }
+}
+
+/// Iterates the documents having at least one of the `ords` in their
+/// sorted set doc values.
+///
+/// This supports two phase iteration: the approximation is all the docs of
+/// the segment, and `matches()` reads the ords of the current doc, so that a
+/// conjunction only reads the ords of the docs the other clauses match.
+struct SortedSetOrdsIterator {
+    values: Box<dyn SortedSetDocValues>,
+    ords: LongBitSet,
+    doc: DocId,
+    max_doc: DocId,
+}
+
+impl SortedSetOrdsIterator {
+    fn two_phase_next(&mut self) -> Result<DocId> {
+        while self.doc != NO_MORE_DOCS && !self.matches()? {
+            self.approximate_next()?;
+        }
+        Ok(self.doc)
+    }
+}
+
+impl DocIterator for SortedSetOrdsIterator {
+    fn doc_id(&self) -> DocId {
+        self.doc
+    }
+
+    fn next(&mut self) -> Result<DocId> {
+        self.approximate_next()?;
+        self.two_phase_next()
+    }
+
+    fn advance(&mut self, target: DocId) -> Result<DocId> {
+        self.approximate_advance(target)?;
+        self.two_phase_next()
+    }
+
+    fn cost(&self) -> usize {
+        self.max_doc as usize
+    }
+
+    fn matches(&mut self) -> Result<bool> {
+        self.values.set_document(self.doc)?;
+        loop {
+            let ord = self.values.next_ord()?;
+            if ord == NO_MORE_ORDS {
+                return Ok(false);
+            }
+            if self.ords.get(ord) {
+                return Ok(true);
+            }
+        }
+    }
+
+    fn match_cost(&self) -> f32 {
+        // a lookup in the bit set of the ords, for a few ords per doc
+        3.0
+    }
+
+    fn support_two_phase(&self) -> bool {
+        true
+    }
+
+    fn approximate_next(&mut self) -> Result<DocId> {
+        let target = self.doc + 1;
+        self.approximate_advance(target)
+    }
+
+    fn approximate_advance(&mut self, target: DocId) -> Result<DocId> {
+        self.doc = if target < self.max_doc {
+            target
+        } else {
+            NO_MORE_DOCS
+        };
+        Ok(self.doc)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate tempfile;
+
+    use super::*;
+    use core::codec::CodecEnum;
+    use core::doc::{Field, FieldType, Fieldable, IndexOptions, SortedSetDocValuesField, Term};
+    use core::index::writer::{IndexWriter, IndexWriterConfig};
+    use core::search::collector::TopDocsCollector;
+    use core::search::query::BooleanQuery;
+    use core::search::{DefaultIndexSearcher, IndexSearcher};
+    use core::store::directory::FSDirectory;
+    use core::util::VariantValue;
+
+    use std::sync::Arc;
+
+    // every fifth doc has no tags
+    fn tags(i: i32) -> Vec<&'static str> {
+        let mut tags = vec![];
+        if i % 5 != 0 {
+            tags.push(if i % 2 == 0 { "even" } else { "odd" });
+            if i % 3 == 0 {
+                tags.push("fizz");
+            }
+        }
+        tags
+    }
+
+    #[test]
+    fn test_doc_values_terms_query() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let directory = Arc::new(FSDirectory::with_path(temp_dir.path()).unwrap());
+        let writer = IndexWriter::new(directory, Arc::new(IndexWriterConfig::default())).unwrap();
+
+        let mut group_type = FieldType::default();
+        group_type.index_options = IndexOptions::Docs;
+        group_type.tokenized = false;
+        for i in 0..100 {
+            let mut doc: Vec<Box<dyn Fieldable>> = vec![Box::new(Field::new(
+                "group".into(),
+                group_type.clone(),
+                Some(VariantValue::VString((i % 4).to_string())),
+                None,
+            ))];
+            for tag in tags(i) {
+                doc.push(Box::new(SortedSetDocValuesField::new(
+                    "tag",
+                    tag.as_bytes(),
+                )));
+            }
+            writer.add_document(doc).unwrap();
+            if i == 40 {
+                writer.commit().unwrap();
+            }
+        }
+        writer.commit().unwrap();
+
+        let reader = writer.get_reader(true, false).unwrap();
+        let searcher = DefaultIndexSearcher::new(Arc::new(reader), None);
+        let count = |query: &dyn Query<CodecEnum>| {
+            let mut collector = TopDocsCollector::new(10);
+            searcher.search(query, &mut collector).unwrap();
+            collector.top_docs().total_hits()
+        };
+        let expected = |f: &dyn Fn(i32) -> bool| (0..100).filter(|&i| f(i)).count();
+        let terms = |terms: &[&str]| -> Box<dyn Query<CodecEnum>> {
+            let terms = terms.iter().map(|t| t.as_bytes().to_vec()).collect();
+            Box::new(DocValuesTermsQuery::new("tag".into(), terms))
+        };
+        let group = |g: &str| -> Box<dyn Query<CodecEnum>> {
+            Box::new(TermQuery::new(
+                Term::new("group".into(), g.as_bytes().to_vec()),
+                1.0,
+                None,
+            ))
+        };
+        let fizz = |i| tags(i).contains(&"fizz");
+
+        assert_eq!(count(terms(&["fizz"]).as_ref()), expected(&fizz));
+        assert_eq!(
+            count(terms(&["fizz", "even", "unknown"]).as_ref()),
+            expected(&|i| fizz(i) || tags(i).contains(&"even"))
+        );
+        assert_eq!(count(terms(&["unknown"]).as_ref()), 0);
+
+        // the ords are only checked for the docs of the other clauses
+        let query =
+            BooleanQuery::build(vec![group("1")], vec![], vec![terms(&["fizz"])], vec![], 0)
+                .unwrap();
+        assert_eq!(count(query.as_ref()), expected(&|i| i % 4 == 1 && fizz(i)));
+        let query = BooleanQuery::build(
+            vec![group("1"), terms(&["odd"])],
+            vec![],
+            vec![terms(&["fizz"])],
+            vec![],
+            0,
+        )
+        .unwrap();
+        assert_eq!(count(query.as_ref()), expected(&|i| i % 4 == 1 && fizz(i)));
+        let query =
+            BooleanQuery::build(vec![group("1")], vec![], vec![], vec![terms(&["fizz"])], 0)
+                .unwrap();
+        assert_eq!(count(query.as_ref()), expected(&|i| i % 4 == 1 && !fizz(i)));
+        let query = BooleanQuery::build(
+            vec![],
+            vec![group("1"), terms(&["fizz"])],
+            vec![],
+            vec![],
+            0,
+        )
+        .unwrap();
+        assert_eq!(count(query.as_ref()), expected(&|i| i % 4 == 1 || fizz(i)));
+    }
+}
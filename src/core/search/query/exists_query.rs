@@ -35,6 +35,10 @@ impl ExistsQuery {
     pub fn build(field: String) -> ExistsQuery {
         ExistsQuery { field }
     }
+
+    pub fn field(&self) -> &str {
+        &self.field
+    }
 }
 
 impl<C: Codec> Query<C> for ExistsQuery {
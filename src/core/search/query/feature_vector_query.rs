@@ -0,0 +1,232 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::any::Any;
+use std::fmt;
+use std::sync::Arc;
+
+use core::codec::Codec;
+use core::index::reader::LeafReaderContext;
+use core::search::explanation::Explanation;
+use core::search::query::{AllDocsIterator, Query, TermQuery, Weight};
+use core::search::scorer::Scorer;
+use core::search::searcher::SearchPlanBuilder;
+use core::search::DocIterator;
+use core::util::{DocId, IndexedContext, VariantValue};
+
+use error::Result;
+
+const FEATURE_VECTOR_QUERY: &str = "feature_vector";
+
+/// A query that matches all the docs, and extracts the scores of named
+/// feature queries: the `score_context()` of its scorer has the score of the
+/// `i`-th feature query at index `i` if that query matches the doc.
+///
+/// The scores of the query itself are always 0, it is meant to be the query
+/// of the `RescoreRequest` of an `LtrRescorer`.
+pub struct FeatureVectorQuery<C: Codec> {
+    features: Vec<(String, Arc<dyn Query<C>>)>,
+}
+
+impl<C: Codec> FeatureVectorQuery<C> {
+    pub fn new(features: Vec<(String, Box<dyn Query<C>>)>) -> FeatureVectorQuery<C> {
+        let features = features
+            .into_iter()
+            .map(|(name, query)| (name, Arc::from(query)))
+            .collect();
+        FeatureVectorQuery { features }
+    }
+
+    pub fn features(&self) -> &[(String, Arc<dyn Query<C>>)] {
+        &self.features
+    }
+}
+
+impl<C: Codec> Query<C> for FeatureVectorQuery<C> {
+    fn create_weight(
+        &self,
+        searcher: &dyn SearchPlanBuilder<C>,
+        needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        let mut weights = Vec::with_capacity(self.features.len());
+        for (name, query) in &self.features {
+            weights.push((name.clone(), query.create_weight(searcher, true)?));
+        }
+        Ok(Box::new(FeatureVectorWeight {
+            weights,
+            needs_scores,
+        }))
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        self.features
+            .iter()
+            .flat_map(|(_, query)| query.extract_terms())
+            .collect()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn rewrite(&self, searcher: &dyn SearchPlanBuilder<C>) -> Result<Option<Box<dyn Query<C>>>> {
+        let mut changed = false;
+        let mut features = Vec::with_capacity(self.features.len());
+        for (name, query) in &self.features {
+            match query.rewrite(searcher)? {
+                Some(q) => {
+                    changed = true;
+                    features.push((name.clone(), Arc::from(q)));
+                }
+                None => features.push((name.clone(), Arc::clone(query))),
+            }
+        }
+        if changed {
+            Ok(Some(Box::new(FeatureVectorQuery { features })))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl<C: Codec> fmt::Display for FeatureVectorQuery<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let features: Vec<String> = self
+            .features
+            .iter()
+            .map(|(name, query)| format!("{}: {}", name, query))
+            .collect();
+        write!(f, "FeatureVectorQuery({})", features.join(", "))
+    }
+}
+
+struct FeatureVectorWeight<C: Codec> {
+    weights: Vec<(String, Box<dyn Weight<C>>)>,
+    needs_scores: bool,
+}
+
+impl<C: Codec> Weight<C> for FeatureVectorWeight<C> {
+    fn create_scorer(
+        &self,
+        leaf_reader: &LeafReaderContext<'_, C>,
+    ) -> Result<Option<Box<dyn Scorer>>> {
+        let mut scorers = Vec::with_capacity(self.weights.len());
+        for (_, weight) in &self.weights {
+            scorers.push(weight.create_scorer(leaf_reader)?);
+        }
+        Ok(Some(Box::new(FeatureVectorScorer {
+            iterator: AllDocsIterator::new(leaf_reader.reader.max_doc()),
+            scorers,
+        })))
+    }
+
+    fn query_type(&self) -> &'static str {
+        FEATURE_VECTOR_QUERY
+    }
+
+    fn normalize(&mut self, norm: f32, boost: f32) {
+        for (_, weight) in &mut self.weights {
+            weight.normalize(norm, boost);
+        }
+    }
+
+    fn value_for_normalization(&self) -> f32 {
+        self.weights
+            .iter()
+            .map(|(_, weight)| weight.value_for_normalization())
+            .sum()
+    }
+
+    fn needs_scores(&self) -> bool {
+        self.needs_scores
+    }
+
+    fn explain(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Explanation> {
+        let mut details = Vec::with_capacity(self.weights.len());
+        for (name, weight) in &self.weights {
+            let explanation = weight.explain(reader, doc)?;
+            if explanation.is_match() {
+                details.push(Explanation::new(
+                    true,
+                    explanation.value(),
+                    format!("feature {}:", name),
+                    vec![explanation],
+                ));
+            }
+        }
+        Ok(Explanation::new(
+            true,
+            0.0,
+            "feature vector, of the matching features:".to_string(),
+            details,
+        ))
+    }
+}
+
+impl<C: Codec> fmt::Display for FeatureVectorWeight<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let features: Vec<String> = self
+            .weights
+            .iter()
+            .map(|(name, weight)| format!("{}: {}", name, weight))
+            .collect();
+        write!(f, "FeatureVectorWeight({})", features.join(", "))
+    }
+}
+
+struct FeatureVectorScorer {
+    iterator: AllDocsIterator,
+    // the feature scorers are only advanced when the context is read
+    scorers: Vec<Option<Box<dyn Scorer>>>,
+}
+
+impl Scorer for FeatureVectorScorer {
+    fn score(&mut self) -> Result<f32> {
+        Ok(0.0)
+    }
+
+    fn score_context(&mut self) -> Result<IndexedContext> {
+        let doc = self.iterator.doc_id();
+        let mut context = IndexedContext::default();
+        for (index, scorer) in self.scorers.iter_mut().enumerate() {
+            if let Some(scorer) = scorer {
+                let mut actual_doc = scorer.doc_id();
+                if actual_doc < doc {
+                    actual_doc = scorer.advance(doc)?;
+                }
+                if actual_doc == doc {
+                    context.set(index, VariantValue::from(scorer.score()?));
+                }
+            }
+        }
+        Ok(context)
+    }
+}
+
+impl DocIterator for FeatureVectorScorer {
+    fn doc_id(&self) -> DocId {
+        self.iterator.doc_id()
+    }
+
+    fn next(&mut self) -> Result<DocId> {
+        self.iterator.next()
+    }
+
+    fn advance(&mut self, target: DocId) -> Result<DocId> {
+        self.iterator.advance(target)
+    }
+
+    fn cost(&self) -> usize {
+        self.iterator.cost()
+    }
+}
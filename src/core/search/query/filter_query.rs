@@ -62,7 +62,7 @@ impl<C: Codec> Query<C> for FilterQuery<C> {
             filters.push(Arc::clone(f));
         }
         Ok(Box::new(FilterWeight {
-            weight: self.query.create_weight(searcher, needs_scores)?,
+            weight: searcher.create_weight(self.query.as_ref(), needs_scores)?,
             filters,
         }))
     }
@@ -74,6 +74,12 @@ impl<C: Codec> Query<C> for FilterQuery<C> {
     fn as_any(&self) -> &dyn (::std::any::Any) {
         self
     }
+
+    fn rewrite(&self, searcher: &dyn SearchPlanBuilder<C>) -> Result<Option<Box<dyn Query<C>>>> {
+        Ok(self.query.rewrite(searcher)?.map(|query| {
+            Box::new(FilterQuery::new(query, self.filters.clone())) as Box<dyn Query<C>>
+        }))
+    }
 }
 
 impl<C: Codec> fmt::Display for FilterQuery<C> {
@@ -0,0 +1,1248 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::any::Any;
+use std::f64;
+use std::fmt;
+use std::sync::Arc;
+
+use core::codec::Codec;
+use core::index::reader::LeafReaderContext;
+use core::search::explanation::Explanation;
+use core::search::facet::{min_value, NumericFieldType, NumericValues};
+use core::search::query::{Query, TermQuery, Weight};
+use core::search::scorer::{DocIteratorAsBits, Scorer};
+use core::search::searcher::SearchPlanBuilder;
+use core::search::DocIterator;
+use core::util::DocId;
+
+use error::{ErrorKind::IllegalArgument, Result};
+
+const FUNCTION_SCORE_QUERY: &str = "function_score";
+
+/// How the scores of the functions that match a doc are combined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScoreMode {
+    Multiply,
+    Sum,
+    /// the sum of the scores divided by the sum of the weights of the functions
+    Avg,
+    /// the score of the first function that matches
+    First,
+    Max,
+    Min,
+}
+
+impl fmt::Display for ScoreMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            ScoreMode::Multiply => "multiply",
+            ScoreMode::Sum => "sum",
+            ScoreMode::Avg => "avg",
+            ScoreMode::First => "first",
+            ScoreMode::Max => "max",
+            ScoreMode::Min => "min",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// How the combined score of the functions is combined with the score of the
+/// wrapped query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BoostMode {
+    Multiply,
+    /// only the score of the functions is used
+    Replace,
+    Sum,
+    Avg,
+    Max,
+    Min,
+}
+
+impl BoostMode {
+    fn combine(self, query_score: f64, function_score: f64) -> f64 {
+        match self {
+            BoostMode::Multiply => query_score * function_score,
+            BoostMode::Replace => function_score,
+            BoostMode::Sum => query_score + function_score,
+            BoostMode::Avg => (query_score + function_score) / 2.0,
+            BoostMode::Max => query_score.max(function_score),
+            BoostMode::Min => query_score.min(function_score),
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            BoostMode::Multiply => "function score, product of:",
+            BoostMode::Replace => "function score, score of the functions only:",
+            BoostMode::Sum => "function score, sum of:",
+            BoostMode::Avg => "function score, avg of:",
+            BoostMode::Max => "function score, max of:",
+            BoostMode::Min => "function score, min of:",
+        }
+    }
+}
+
+impl fmt::Display for BoostMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            BoostMode::Multiply => "multiply",
+            BoostMode::Replace => "replace",
+            BoostMode::Sum => "sum",
+            BoostMode::Avg => "avg",
+            BoostMode::Max => "max",
+            BoostMode::Min => "min",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// The function applied to a doc value, after the factor, by a
+/// `FieldValueFactor`. The `log` variants are in base 10, the `ln` ones are
+/// natural logarithms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldValueModifier {
+    None,
+    Log,
+    Log1p,
+    Log2p,
+    Ln,
+    Ln1p,
+    Ln2p,
+    Square,
+    Sqrt,
+    Reciprocal,
+}
+
+impl FieldValueModifier {
+    fn apply(self, v: f64) -> f64 {
+        match self {
+            FieldValueModifier::None => v,
+            FieldValueModifier::Log => v.log10(),
+            FieldValueModifier::Log1p => (v + 1.0).log10(),
+            FieldValueModifier::Log2p => (v + 2.0).log10(),
+            FieldValueModifier::Ln => v.ln(),
+            FieldValueModifier::Ln1p => v.ln_1p(),
+            FieldValueModifier::Ln2p => (v + 2.0).ln(),
+            FieldValueModifier::Square => v * v,
+            FieldValueModifier::Sqrt => v.sqrt(),
+            FieldValueModifier::Reciprocal => 1.0 / v,
+        }
+    }
+}
+
+impl fmt::Display for FieldValueModifier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            FieldValueModifier::None => "none",
+            FieldValueModifier::Log => "log",
+            FieldValueModifier::Log1p => "log1p",
+            FieldValueModifier::Log2p => "log2p",
+            FieldValueModifier::Ln => "ln",
+            FieldValueModifier::Ln1p => "ln1p",
+            FieldValueModifier::Ln2p => "ln2p",
+            FieldValueModifier::Square => "square",
+            FieldValueModifier::Sqrt => "sqrt",
+            FieldValueModifier::Reciprocal => "reciprocal",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Scores a doc with `modifier(factor * value)`, where `value` is the doc
+/// value of a numeric field, the smallest one if the field is multi-valued.
+#[derive(Debug, Clone)]
+pub struct FieldValueFactor {
+    field: String,
+    field_type: NumericFieldType,
+    factor: f64,
+    modifier: FieldValueModifier,
+    missing: Option<f64>,
+}
+
+impl FieldValueFactor {
+    pub fn new(field: String, field_type: NumericFieldType) -> FieldValueFactor {
+        FieldValueFactor {
+            field,
+            field_type,
+            factor: 1.0,
+            modifier: FieldValueModifier::None,
+            missing: None,
+        }
+    }
+
+    pub fn set_factor(&mut self, factor: f64) {
+        self.factor = factor;
+    }
+
+    pub fn set_modifier(&mut self, modifier: FieldValueModifier) {
+        self.modifier = modifier;
+    }
+
+    /// Sets the value of the docs without a value, scoring such a doc fails
+    /// if it is not set.
+    pub fn set_missing(&mut self, missing: f64) {
+        self.missing = Some(missing);
+    }
+
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    fn score(&self, value: Option<f64>, doc: DocId) -> Result<f64> {
+        let value = match value.or(self.missing) {
+            Some(v) => v,
+            None => bail!(IllegalArgument(format!(
+                "missing value for field [{}] of doc {}",
+                self.field, doc
+            ))),
+        };
+        let score = self.modifier.apply(self.factor * value);
+        if !score.is_finite() {
+            bail!(IllegalArgument(format!(
+                "field value function of field [{}] produced a non-finite score {} for doc {}, \
+                 consider using log1p or ln1p instead of log or ln",
+                self.field, score, doc
+            )));
+        }
+        Ok(score)
+    }
+
+    fn explain(&self, value: Option<f64>, score: f64) -> Explanation {
+        let value = match value {
+            Some(v) => v.to_string(),
+            None => format!("{}(missing)", self.missing.unwrap_or(f64::NAN)),
+        };
+        Explanation::new(
+            true,
+            score as f32,
+            format!(
+                "field value function: {}(doc['{}'].value={} * factor={})",
+                self.modifier, self.field, value, self.factor
+            ),
+            vec![],
+        )
+    }
+}
+
+/// The shape of the curve of a `DecayFunction`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecayType {
+    Gauss,
+    Exp,
+    Linear,
+}
+
+impl fmt::Display for DecayType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            DecayType::Gauss => "gauss",
+            DecayType::Exp => "exp",
+            DecayType::Linear => "linear",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Scores a doc by the distance between the doc value of a numeric field and
+/// an origin. The score is 1 up to `offset` away from the origin, and `decay`
+/// at `offset + scale` away from it. A multi-valued doc is scored by its
+/// closest value, a doc without a value gets a score of 1.
+///
+/// A date field is a long field of milliseconds since the epoch, the origin,
+/// the scale and the offset are then in milliseconds as well.
+#[derive(Debug, Clone)]
+pub struct DecayFunction {
+    field: String,
+    field_type: NumericFieldType,
+    decay_type: DecayType,
+    origin: f64,
+    scale: f64,
+    offset: f64,
+    decay: f64,
+}
+
+impl DecayFunction {
+    pub fn new(
+        field: String,
+        field_type: NumericFieldType,
+        decay_type: DecayType,
+        origin: f64,
+        scale: f64,
+    ) -> Result<DecayFunction> {
+        if !origin.is_finite() {
+            bail!(IllegalArgument(format!(
+                "origin must be a finite number, got {}",
+                origin
+            )));
+        }
+        if !scale.is_finite() || scale <= 0.0 {
+            bail!(IllegalArgument(format!(
+                "scale must be a finite number > 0, got {}",
+                scale
+            )));
+        }
+        Ok(DecayFunction {
+            field,
+            field_type,
+            decay_type,
+            origin,
+            scale,
+            offset: 0.0,
+            decay: 0.5,
+        })
+    }
+
+    pub fn set_offset(&mut self, offset: f64) -> Result<()> {
+        if !offset.is_finite() || offset < 0.0 {
+            bail!(IllegalArgument(format!(
+                "offset must be a finite number >= 0, got {}",
+                offset
+            )));
+        }
+        self.offset = offset;
+        Ok(())
+    }
+
+    pub fn set_decay(&mut self, decay: f64) -> Result<()> {
+        if decay.is_nan() || decay <= 0.0 || decay >= 1.0 {
+            bail!(IllegalArgument(format!(
+                "decay must be in (0, 1), got {}",
+                decay
+            )));
+        }
+        self.decay = decay;
+        Ok(())
+    }
+
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    fn distance(&self, value: f64) -> f64 {
+        ((value - self.origin).abs() - self.offset).max(0.0)
+    }
+
+    fn score(&self, distance: f64) -> f64 {
+        match self.decay_type {
+            DecayType::Gauss => {
+                // 2 * sigma^2, so that the score at `scale` is `decay`
+                let two_sigma_squared = -self.scale * self.scale / self.decay.ln();
+                (-distance * distance / two_sigma_squared).exp()
+            }
+            DecayType::Exp => (self.decay.ln() / self.scale * distance).exp(),
+            DecayType::Linear => {
+                let s = self.scale / (1.0 - self.decay);
+                ((s - distance) / s).max(0.0)
+            }
+        }
+    }
+
+    fn explain(&self, distance: Option<f64>, score: f64) -> Explanation {
+        let details = match distance {
+            Some(d) => vec![Explanation::new(true, d as f32, "distance".into(), vec![])],
+            None => vec![Explanation::new(true, 1.0, "missing value".into(), vec![])],
+        };
+        Explanation::new(
+            true,
+            score as f32,
+            format!(
+                "{}(doc['{}'].value, origin={}, scale={}, offset={}, decay={})",
+                self.decay_type, self.field, self.origin, self.scale, self.offset, self.decay
+            ),
+            details,
+        )
+    }
+}
+
+/// A function that scores a doc, see `FilterFunction`.
+#[derive(Debug, Clone)]
+pub enum ScoreFunction {
+    FieldValueFactor(FieldValueFactor),
+    Decay(DecayFunction),
+    /// A random score in `[0, 1)` derived from the seed and the doc id, the
+    /// score of a doc changes when its segment is merged.
+    RandomScore {
+        seed: i64,
+    },
+    /// A constant score of 1, scaled by the weight of the `FilterFunction`.
+    Weight,
+}
+
+impl ScoreFunction {
+    fn field(&self) -> Option<(&str, NumericFieldType)> {
+        match self {
+            ScoreFunction::FieldValueFactor(f) => Some((&f.field, f.field_type)),
+            ScoreFunction::Decay(f) => Some((&f.field, f.field_type)),
+            _ => None,
+        }
+    }
+}
+
+/// A `ScoreFunction` with its weight, only applied to the docs that match the
+/// filter if any.
+pub struct FilterFunction<C: Codec> {
+    filter: Option<Arc<dyn Query<C>>>,
+    function: ScoreFunction,
+    weight: f32,
+}
+
+impl<C: Codec> FilterFunction<C> {
+    pub fn new(
+        filter: Option<Box<dyn Query<C>>>,
+        function: ScoreFunction,
+        weight: f32,
+    ) -> FilterFunction<C> {
+        FilterFunction {
+            filter: filter.map(Arc::from),
+            function,
+            weight,
+        }
+    }
+
+    pub fn filter(&self) -> Option<&dyn Query<C>> {
+        self.filter.as_ref().map(|f| f.as_ref())
+    }
+
+    pub fn function(&self) -> &ScoreFunction {
+        &self.function
+    }
+
+    pub fn weight(&self) -> f32 {
+        self.weight
+    }
+}
+
+impl<C: Codec> Clone for FilterFunction<C> {
+    fn clone(&self) -> Self {
+        FilterFunction {
+            filter: self.filter.clone(),
+            function: self.function.clone(),
+            weight: self.weight,
+        }
+    }
+}
+
+/// A query that modifies the scores of the docs matching another query with
+/// score functions, e.g. to mix the text relevance with the popularity or
+/// the freshness of the docs.
+///
+/// The functions that match a doc are combined by the `ScoreMode`, and the
+/// result, capped by `max_boost`, is combined with the score of the query by
+/// the `BoostMode`. The functions are combined to 1 if none of them matches.
+pub struct FunctionScoreQuery<C: Codec> {
+    query: Arc<dyn Query<C>>,
+    functions: Vec<FilterFunction<C>>,
+    score_mode: ScoreMode,
+    boost_mode: BoostMode,
+    max_boost: f32,
+}
+
+impl<C: Codec> FunctionScoreQuery<C> {
+    pub fn new(
+        query: Box<dyn Query<C>>,
+        functions: Vec<FilterFunction<C>>,
+        score_mode: ScoreMode,
+        boost_mode: BoostMode,
+    ) -> FunctionScoreQuery<C> {
+        FunctionScoreQuery {
+            query: Arc::from(query),
+            functions,
+            score_mode,
+            boost_mode,
+            max_boost: ::std::f32::MAX,
+        }
+    }
+
+    pub fn set_max_boost(&mut self, max_boost: f32) {
+        self.max_boost = max_boost;
+    }
+
+    pub fn query(&self) -> &dyn Query<C> {
+        self.query.as_ref()
+    }
+
+    pub fn functions(&self) -> &[FilterFunction<C>] {
+        &self.functions
+    }
+
+    pub fn score_mode(&self) -> ScoreMode {
+        self.score_mode
+    }
+
+    pub fn boost_mode(&self) -> BoostMode {
+        self.boost_mode
+    }
+
+    pub fn max_boost(&self) -> f32 {
+        self.max_boost
+    }
+}
+
+impl<C: Codec> Query<C> for FunctionScoreQuery<C> {
+    fn create_weight(
+        &self,
+        searcher: &dyn SearchPlanBuilder<C>,
+        needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        let query_needs_scores = needs_scores && self.boost_mode != BoostMode::Replace;
+        let weight = self.query.create_weight(searcher, query_needs_scores)?;
+        let mut functions = Vec::with_capacity(self.functions.len());
+        for function in &self.functions {
+            let filter = match &function.filter {
+                Some(filter) => Some(filter.create_weight(searcher, false)?),
+                None => None,
+            };
+            functions.push(FunctionWeight {
+                filter,
+                function: function.function.clone(),
+                weight: function.weight,
+            });
+        }
+        Ok(Box::new(FunctionScoreWeight {
+            weight,
+            functions,
+            score_mode: self.score_mode,
+            boost_mode: self.boost_mode,
+            max_boost: self.max_boost,
+            needs_scores,
+        }))
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        self.query.extract_terms()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn rewrite(&self, searcher: &dyn SearchPlanBuilder<C>) -> Result<Option<Box<dyn Query<C>>>> {
+        let mut rewritten = false;
+        let query = match self.query.rewrite(searcher)? {
+            Some(query) => {
+                rewritten = true;
+                Arc::from(query)
+            }
+            None => Arc::clone(&self.query),
+        };
+        let mut functions = self.functions.clone();
+        for function in &mut functions {
+            if let Some(filter) = &function.filter {
+                if let Some(filter) = filter.rewrite(searcher)? {
+                    function.filter = Some(Arc::from(filter));
+                    rewritten = true;
+                }
+            }
+        }
+        if !rewritten {
+            return Ok(None);
+        }
+        Ok(Some(Box::new(FunctionScoreQuery {
+            query,
+            functions,
+            score_mode: self.score_mode,
+            boost_mode: self.boost_mode,
+            max_boost: self.max_boost,
+        })))
+    }
+}
+
+impl<C: Codec> fmt::Display for FunctionScoreQuery<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "FunctionScoreQuery(query: {}, functions: [", &self.query)?;
+        for (i, function) in self.functions.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            if let Some(filter) = &function.filter {
+                write!(f, "{{filter: {}, ", filter)?;
+            } else {
+                write!(f, "{{")?;
+            }
+            write!(
+                f,
+                "function: {:?}, weight: {}}}",
+                function.function, function.weight
+            )?;
+        }
+        write!(
+            f,
+            "], score_mode: {}, boost_mode: {}, max_boost: {})",
+            self.score_mode, self.boost_mode, self.max_boost
+        )
+    }
+}
+
+struct FunctionWeight<C: Codec> {
+    filter: Option<Box<dyn Weight<C>>>,
+    function: ScoreFunction,
+    weight: f32,
+}
+
+struct FunctionScoreWeight<C: Codec> {
+    weight: Box<dyn Weight<C>>,
+    functions: Vec<FunctionWeight<C>>,
+    score_mode: ScoreMode,
+    boost_mode: BoostMode,
+    max_boost: f32,
+    needs_scores: bool,
+}
+
+impl<C: Codec> FunctionScoreWeight<C> {
+    /// Returns the functions that may match docs of the leaf, with the filters
+    /// that have a match in the leaf.
+    fn leaf_functions(&self, reader: &LeafReaderContext<'_, C>) -> Result<Vec<LeafFunction>> {
+        let max_doc = reader.reader.max_doc();
+        let mut functions = Vec::with_capacity(self.functions.len());
+        for function in &self.functions {
+            let filter = match &function.filter {
+                Some(filter) => match filter.create_scorer(reader)? {
+                    Some(scorer) => Some(DocIteratorAsBits::new(max_doc, scorer)),
+                    None => continue,
+                },
+                None => None,
+            };
+            let values = match function.function.field() {
+                Some((field, field_type)) => {
+                    Some((NumericValues::new(reader, field, field_type)?, field_type))
+                }
+                None => None,
+            };
+            functions.push(LeafFunction {
+                filter,
+                function: function.function.clone(),
+                weight: f64::from(function.weight),
+                values,
+                value_buf: vec![],
+                doc_base: reader.doc_base,
+            });
+        }
+        Ok(functions)
+    }
+}
+
+impl<C: Codec> Weight<C> for FunctionScoreWeight<C> {
+    fn create_scorer(
+        &self,
+        leaf_reader: &LeafReaderContext<'_, C>,
+    ) -> Result<Option<Box<dyn Scorer>>> {
+        if let Some(scorer) = self.weight.create_scorer(leaf_reader)? {
+            Ok(Some(Box::new(FunctionScoreScorer {
+                scorer,
+                functions: self.leaf_functions(leaf_reader)?,
+                score_mode: self.score_mode,
+                boost_mode: self.boost_mode,
+                max_boost: f64::from(self.max_boost),
+                needs_scores: self.needs_scores,
+                scores: Vec::with_capacity(self.functions.len()),
+            })))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn query_type(&self) -> &'static str {
+        FUNCTION_SCORE_QUERY
+    }
+
+    fn normalize(&mut self, norm: f32, boost: f32) {
+        self.weight.normalize(norm, boost)
+    }
+
+    fn value_for_normalization(&self) -> f32 {
+        self.weight.value_for_normalization()
+    }
+
+    fn needs_scores(&self) -> bool {
+        self.needs_scores
+    }
+
+    fn explain(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Explanation> {
+        let query_explanation = self.weight.explain(reader, doc)?;
+        if !query_explanation.is_match() {
+            return Ok(query_explanation);
+        }
+
+        let mut details = vec![];
+        let mut scores = vec![];
+        for mut function in self.leaf_functions(reader)? {
+            if let Some(score) = function.score(doc)? {
+                details.push(function.explain(doc, score)?);
+                scores.push((score * function.weight, function.weight));
+                if self.score_mode == ScoreMode::First {
+                    break;
+                }
+            }
+        }
+        let functions_explanation = if scores.is_empty() {
+            Explanation::new(true, 1.0, "No function matched".into(), vec![])
+        } else {
+            Explanation::new(
+                true,
+                combine_functions(self.score_mode, &scores) as f32,
+                format!("function score, score mode [{}]", self.score_mode),
+                details,
+            )
+        };
+        let function_score = f64::from(functions_explanation.value());
+        let max_boost = f64::from(self.max_boost);
+        let functions_explanation = if function_score > max_boost {
+            Explanation::new(
+                true,
+                self.max_boost,
+                "min of:".into(),
+                vec![
+                    functions_explanation,
+                    Explanation::new(true, self.max_boost, "maxBoost".into(), vec![]),
+                ],
+            )
+        } else {
+            functions_explanation
+        };
+        let score = self.boost_mode.combine(
+            f64::from(query_explanation.value()),
+            function_score.min(max_boost),
+        );
+        Ok(Explanation::new(
+            true,
+            score as f32,
+            self.boost_mode.description().into(),
+            vec![query_explanation, functions_explanation],
+        ))
+    }
+}
+
+impl<C: Codec> fmt::Display for FunctionScoreWeight<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "FunctionScoreWeight(weight: {}, functions: {}, score_mode: {}, boost_mode: {})",
+            &self.weight,
+            self.functions.len(),
+            self.score_mode,
+            self.boost_mode
+        )
+    }
+}
+
+/// Combines the `(score, weight)` of the functions that match a doc, the
+/// scores are already multiplied by the weights.
+fn combine_functions(score_mode: ScoreMode, scores: &[(f64, f64)]) -> f64 {
+    debug_assert!(!scores.is_empty());
+    let values = scores.iter().map(|&(score, _)| score);
+    match score_mode {
+        ScoreMode::Multiply => values.product(),
+        ScoreMode::Sum => values.sum(),
+        ScoreMode::Avg => {
+            let weight_sum: f64 = scores.iter().map(|&(_, weight)| weight).sum();
+            if weight_sum.abs() < f64::EPSILON {
+                1.0
+            } else {
+                values.sum::<f64>() / weight_sum
+            }
+        }
+        ScoreMode::First => scores[0].0,
+        ScoreMode::Max => values.fold(f64::NEG_INFINITY, f64::max),
+        ScoreMode::Min => values.fold(f64::INFINITY, f64::min),
+    }
+}
+
+/// A score function bound to a leaf.
+struct LeafFunction {
+    filter: Option<DocIteratorAsBits>,
+    function: ScoreFunction,
+    weight: f64,
+    values: Option<(NumericValues, NumericFieldType)>,
+    value_buf: Vec<i64>,
+    doc_base: DocId,
+}
+
+impl LeafFunction {
+    /// Reads the doc values of the function's field, as doubles.
+    fn read_values(&mut self, doc: DocId) -> Result<Vec<f64>> {
+        match &mut self.values {
+            Some((values, field_type)) => {
+                values.read(doc, &mut self.value_buf)?;
+                let field_type = *field_type;
+                Ok(self
+                    .value_buf
+                    .iter()
+                    .map(|&raw| field_type.to_f64(raw))
+                    .collect())
+            }
+            None => Ok(vec![]),
+        }
+    }
+
+    /// Returns the score of the function for `doc`, before the weight is
+    /// applied, `None` if the filter doesn't match it.
+    fn score(&mut self, doc: DocId) -> Result<Option<f64>> {
+        if let Some(filter) = &mut self.filter {
+            if !filter.get(doc as usize)? {
+                return Ok(None);
+            }
+        }
+        let values = self.read_values(doc)?;
+        let score = match &self.function {
+            ScoreFunction::FieldValueFactor(f) => f.score(min_value(values), doc)?,
+            ScoreFunction::Decay(f) => min_value(values.iter().map(|&v| f.distance(v)))
+                .map_or(1.0, |distance| f.score(distance)),
+            ScoreFunction::RandomScore { seed } => random_score(*seed, self.doc_base + doc),
+            ScoreFunction::Weight => 1.0,
+        };
+        Ok(Some(score))
+    }
+
+    fn explain(&mut self, doc: DocId, score: f64) -> Result<Explanation> {
+        let weighted_score = score * self.weight;
+        let values = self.read_values(doc)?;
+        let explanation = match &self.function {
+            ScoreFunction::FieldValueFactor(f) => f.explain(min_value(values), score),
+            ScoreFunction::Decay(f) => {
+                f.explain(min_value(values.iter().map(|&v| f.distance(v))), score)
+            }
+            ScoreFunction::RandomScore { seed } => Explanation::new(
+                true,
+                score as f32,
+                format!("random score function (seed: {})", seed),
+                vec![],
+            ),
+            ScoreFunction::Weight => {
+                Explanation::new(true, score as f32, "constant score 1".into(), vec![])
+            }
+        };
+        let explanation = if (self.weight - 1.0).abs() > f64::EPSILON {
+            Explanation::new(
+                true,
+                weighted_score as f32,
+                "product of:".into(),
+                vec![
+                    explanation,
+                    Explanation::new(true, self.weight as f32, "weight".into(), vec![]),
+                ],
+            )
+        } else {
+            explanation
+        };
+        if self.filter.is_some() {
+            Ok(Explanation::new(
+                true,
+                weighted_score as f32,
+                "function score, product of:".into(),
+                vec![
+                    Explanation::new(true, 1.0, "match filter".into(), vec![]),
+                    explanation,
+                ],
+            ))
+        } else {
+            Ok(explanation)
+        }
+    }
+}
+
+/// Hashes the seed and the doc id into `[0, 1)` with the SplitMix64 finalizer.
+fn random_score(seed: i64, doc: DocId) -> f64 {
+    let mut z = (seed as u64) ^ (doc as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^= z >> 31;
+    // the top 53 bits make a double in [0, 1)
+    (z >> 11) as f64 / (1u64 << 53) as f64
+}
+
+struct FunctionScoreScorer {
+    scorer: Box<dyn Scorer>,
+    functions: Vec<LeafFunction>,
+    score_mode: ScoreMode,
+    boost_mode: BoostMode,
+    max_boost: f64,
+    needs_scores: bool,
+    scores: Vec<(f64, f64)>,
+}
+
+impl Scorer for FunctionScoreScorer {
+    fn score(&mut self) -> Result<f32> {
+        if !self.needs_scores {
+            return Ok(0.0);
+        }
+        let doc = self.scorer.doc_id();
+        let query_score = if self.boost_mode == BoostMode::Replace {
+            0.0
+        } else {
+            f64::from(self.scorer.score()?)
+        };
+        self.scores.clear();
+        for function in &mut self.functions {
+            if let Some(score) = function.score(doc)? {
+                self.scores.push((score * function.weight, function.weight));
+                if self.score_mode == ScoreMode::First {
+                    break;
+                }
+            }
+        }
+        let function_score = if self.scores.is_empty() {
+            1.0
+        } else {
+            combine_functions(self.score_mode, &self.scores)
+        };
+        Ok(self
+            .boost_mode
+            .combine(query_score, function_score.min(self.max_boost)) as f32)
+    }
+}
+
+impl DocIterator for FunctionScoreScorer {
+    fn doc_id(&self) -> DocId {
+        self.scorer.doc_id()
+    }
+
+    fn next(&mut self) -> Result<DocId> {
+        self.scorer.next()
+    }
+
+    fn advance(&mut self, target: DocId) -> Result<DocId> {
+        self.scorer.advance(target)
+    }
+
+    fn cost(&self) -> usize {
+        self.scorer.cost()
+    }
+
+    fn matches(&mut self) -> Result<bool> {
+        self.scorer.matches()
+    }
+
+    fn match_cost(&self) -> f32 {
+        self.scorer.match_cost()
+    }
+
+    fn support_two_phase(&self) -> bool {
+        self.scorer.support_two_phase()
+    }
+
+    fn approximate_next(&mut self) -> Result<DocId> {
+        self.scorer.approximate_next()
+    }
+
+    fn approximate_advance(&mut self, target: DocId) -> Result<DocId> {
+        self.scorer.approximate_advance(target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate tempfile;
+
+    use super::*;
+    use core::codec::CodecEnum;
+    use core::doc::{
+        DoubleDocValuesField, Field, FieldType, Fieldable, IndexOptions, NumericDocValuesField,
+        SortedNumericDocValuesField, Term,
+    };
+    use core::index::writer::{IndexWriter, IndexWriterConfig};
+    use core::search::collector::TopDocsCollector;
+    use core::search::query::{ConstantScoreQuery, MatchAllDocsQuery};
+    use core::search::{DefaultIndexSearcher, IndexSearcher};
+    use core::store::directory::FSDirectory;
+    use core::util::{double2sortable_long, VariantValue};
+
+    use std::collections::HashMap;
+
+    const MILLIS_PER_DAY: f64 = 24.0 * 3600.0 * 1000.0;
+    const START: i64 = 1_577_836_800_000;
+
+    fn scores<S: IndexSearcher<CodecEnum>>(
+        searcher: &S,
+        query: &dyn Query<CodecEnum>,
+    ) -> Result<HashMap<DocId, f32>> {
+        let mut collector = TopDocsCollector::new(100);
+        searcher.search(query, &mut collector)?;
+        let top_docs = collector.top_docs();
+        Ok(top_docs
+            .score_docs()
+            .iter()
+            .map(|hit| (hit.doc_id(), hit.score()))
+            .collect())
+    }
+
+    fn function_score(
+        functions: Vec<FilterFunction<CodecEnum>>,
+        score_mode: ScoreMode,
+        boost_mode: BoostMode,
+    ) -> FunctionScoreQuery<CodecEnum> {
+        FunctionScoreQuery::new(match_all(), functions, score_mode, boost_mode)
+    }
+
+    // a bare `MatchAllDocsQuery` scores 0 as the weights aren't normalized
+    fn match_all() -> Box<dyn Query<CodecEnum>> {
+        Box::new(ConstantScoreQuery::with_boost(
+            Box::new(MatchAllDocsQuery),
+            1.0,
+        ))
+    }
+
+    fn assert_close(actual: f32, expected: f64) {
+        assert!(
+            (f64::from(actual) - expected).abs() < 1e-5,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_function_score_query() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let directory = Arc::new(FSDirectory::with_path(temp_dir.path()).unwrap());
+        let writer = IndexWriter::new(directory, Arc::new(IndexWriterConfig::default())).unwrap();
+
+        let mut tag_type = FieldType::default();
+        tag_type.index_options = IndexOptions::Docs;
+        tag_type.tokenized = false;
+        // every third doc is hot, the last doc has no popularity
+        for i in 0..10i64 {
+            let mut doc: Vec<Box<dyn Fieldable>> = vec![
+                Box::new(DoubleDocValuesField::new("rating", i as f64 / 2.0)),
+                Box::new(NumericDocValuesField::new(
+                    "published",
+                    START + i * MILLIS_PER_DAY as i64,
+                )),
+            ];
+            for &offset in &[10.0 - i as f64 / 4.0, i as f64 - 4.5] {
+                doc.push(Box::new(SortedNumericDocValuesField::new(
+                    "offsets",
+                    double2sortable_long(offset),
+                )));
+            }
+            if i < 9 {
+                doc.push(Box::new(NumericDocValuesField::new("popularity", i * 10)));
+            }
+            if i % 3 == 0 {
+                doc.push(Box::new(Field::new(
+                    "tag".into(),
+                    tag_type.clone(),
+                    Some(VariantValue::VString("hot".into())),
+                    None,
+                )));
+            }
+            writer.add_document(doc).unwrap();
+            if i == 4 {
+                writer.commit().unwrap();
+            }
+        }
+        writer.commit().unwrap();
+
+        let reader = writer.get_reader(true, false).unwrap();
+        let searcher = DefaultIndexSearcher::new(Arc::new(reader), None);
+        let hot = || -> Option<Box<dyn Query<CodecEnum>>> {
+            Some(Box::new(TermQuery::new(
+                Term::new("tag".into(), b"hot".to_vec()),
+                1.0,
+                None,
+            )))
+        };
+
+        // field value factor
+        let mut factor = FieldValueFactor::new("popularity".into(), NumericFieldType::Long);
+        factor.set_factor(0.1);
+        factor.set_modifier(FieldValueModifier::Ln1p);
+        let query = function_score(
+            vec![FilterFunction::new(
+                None,
+                ScoreFunction::FieldValueFactor(factor.clone()),
+                2.0,
+            )],
+            ScoreMode::Multiply,
+            BoostMode::Multiply,
+        );
+        // the missing value is required
+        assert!(scores(&searcher, &query).is_err());
+        factor.set_missing(1.0);
+        let query = function_score(
+            vec![FilterFunction::new(
+                None,
+                ScoreFunction::FieldValueFactor(factor),
+                2.0,
+            )],
+            ScoreMode::Multiply,
+            BoostMode::Multiply,
+        );
+        let hits = scores(&searcher, &query).unwrap();
+        assert_eq!(hits.len(), 10);
+        for i in 0..9 {
+            assert_close(hits[&i], 2.0 * (f64::from(i)).ln_1p());
+        }
+        assert_close(hits[&9], 2.0 * 0.1f64.ln_1p());
+        for doc in 0..10 {
+            let explanation = searcher.explain(&query, doc).unwrap();
+            assert_close(explanation.value(), f64::from(hits[&doc]));
+        }
+
+        // a double field
+        let mut factor = FieldValueFactor::new("rating".into(), NumericFieldType::Double);
+        factor.set_factor(2.0);
+        factor.set_modifier(FieldValueModifier::Square);
+        let query = function_score(
+            vec![FilterFunction::new(
+                None,
+                ScoreFunction::FieldValueFactor(factor),
+                1.0,
+            )],
+            ScoreMode::Multiply,
+            BoostMode::Sum,
+        );
+        let hits = scores(&searcher, &query).unwrap();
+        for i in 0..10 {
+            assert_close(hits[&i], 1.0 + f64::from(i * i));
+        }
+
+        // a multi-valued double field, the smallest value is i - 4.5
+        let mut factor = FieldValueFactor::new("offsets".into(), NumericFieldType::Double);
+        factor.set_modifier(FieldValueModifier::Square);
+        let query = function_score(
+            vec![FilterFunction::new(
+                None,
+                ScoreFunction::FieldValueFactor(factor),
+                1.0,
+            )],
+            ScoreMode::Multiply,
+            BoostMode::Replace,
+        );
+        let hits = scores(&searcher, &query).unwrap();
+        for i in 0..10 {
+            assert_close(hits[&i], (f64::from(i) - 4.5).powi(2));
+            let explanation = searcher.explain(&query, i).unwrap();
+            assert_close(explanation.value(), (f64::from(i) - 4.5).powi(2));
+        }
+        // the closest value to the origin is i - 4.5 as well
+        let decay = DecayFunction::new(
+            "offsets".into(),
+            NumericFieldType::Double,
+            DecayType::Linear,
+            0.0,
+            20.0,
+        )
+        .unwrap();
+        let query = function_score(
+            vec![FilterFunction::new(None, ScoreFunction::Decay(decay), 1.0)],
+            ScoreMode::Multiply,
+            BoostMode::Replace,
+        );
+        let hits = scores(&searcher, &query).unwrap();
+        for i in 0..10 {
+            assert_close(hits[&i], 1.0 - (f64::from(i) - 4.5).abs() / 40.0);
+        }
+
+        // decay functions over a date field, the score is 0.5 at offset + scale
+        let origin = (START as f64) + 3.0 * MILLIS_PER_DAY;
+        for &(decay_type, score_7) in &[
+            (DecayType::Gauss, 0.5f64.powf(2.25)),
+            (DecayType::Exp, 0.5f64.powf(1.5)),
+            (DecayType::Linear, 0.25),
+        ] {
+            let mut decay = DecayFunction::new(
+                "published".into(),
+                NumericFieldType::Long,
+                decay_type,
+                origin,
+                2.0 * MILLIS_PER_DAY,
+            )
+            .unwrap();
+            decay.set_offset(MILLIS_PER_DAY).unwrap();
+            let query = function_score(
+                vec![FilterFunction::new(None, ScoreFunction::Decay(decay), 1.0)],
+                ScoreMode::Multiply,
+                BoostMode::Replace,
+            );
+            let hits = scores(&searcher, &query).unwrap();
+            for doc in 2..5 {
+                assert_close(hits[&doc], 1.0);
+            }
+            assert_close(hits[&0], 0.5);
+            assert_close(hits[&6], 0.5);
+            assert_close(hits[&7], score_7);
+            let explanation = searcher.explain(&query, 7).unwrap();
+            assert_close(explanation.value(), score_7);
+        }
+
+        // weights, filters and score modes
+        let weights = || {
+            vec![
+                FilterFunction::new(hot(), ScoreFunction::Weight, 2.0),
+                FilterFunction::new(None, ScoreFunction::Weight, 3.0),
+            ]
+        };
+        for &(score_mode, hot_score, other_score) in &[
+            (ScoreMode::Multiply, 6.0, 3.0),
+            (ScoreMode::Sum, 5.0, 3.0),
+            (ScoreMode::Avg, 1.0, 1.0),
+            (ScoreMode::First, 2.0, 3.0),
+            (ScoreMode::Max, 3.0, 3.0),
+            (ScoreMode::Min, 2.0, 3.0),
+        ] {
+            let query = function_score(weights(), score_mode, BoostMode::Sum);
+            let hits = scores(&searcher, &query).unwrap();
+            for doc in 0..10 {
+                let expected = if doc % 3 == 0 { hot_score } else { other_score };
+                assert_close(hits[&doc], 1.0 + expected);
+                let explanation = searcher.explain(&query, doc).unwrap();
+                assert_close(explanation.value(), 1.0 + expected);
+            }
+        }
+        let mut query = function_score(weights(), ScoreMode::Sum, BoostMode::Avg);
+        query.set_max_boost(4.0);
+        let hits = scores(&searcher, &query).unwrap();
+        assert_close(hits[&0], 2.5);
+        assert_close(hits[&1], 2.0);
+        assert_close(searcher.explain(&query, 0).unwrap().value(), 2.5);
+
+        // no function matches the docs that are not hot
+        let query = function_score(
+            vec![FilterFunction::new(hot(), ScoreFunction::Weight, 4.0)],
+            ScoreMode::Multiply,
+            BoostMode::Replace,
+        );
+        let hits = scores(&searcher, &query).unwrap();
+        assert_close(hits[&3], 4.0);
+        assert_close(hits[&4], 1.0);
+
+        // random scores are reproducible
+        let query = function_score(
+            vec![FilterFunction::new(
+                None,
+                ScoreFunction::RandomScore { seed: 42 },
+                1.0,
+            )],
+            ScoreMode::Multiply,
+            BoostMode::Replace,
+        );
+        let hits = scores(&searcher, &query).unwrap();
+        assert_eq!(hits, scores(&searcher, &query).unwrap());
+        assert!(hits.values().all(|&s| s >= 0.0 && s < 1.0));
+        let mut distinct: Vec<_> = hits.values().map(|s| s.to_bits()).collect();
+        distinct.sort();
+        distinct.dedup();
+        assert_eq!(distinct.len(), 10);
+
+        assert!(DecayFunction::new(
+            "published".into(),
+            NumericFieldType::Long,
+            DecayType::Gauss,
+            0.0,
+            0.0
+        )
+        .is_err());
+    }
+}
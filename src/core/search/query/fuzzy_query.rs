@@ -0,0 +1,286 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::any::Any;
+use std::fmt;
+use std::sync::Arc;
+
+use core::codec::Codec;
+use core::doc::Term;
+use core::search::query::{AutomatonQuery, Query, RewriteMethod, TermBoost, TermQuery, Weight};
+use core::search::searcher::SearchPlanBuilder;
+use core::util::automaton::{
+    CompiledAutomaton, LevenshteinAutomata, DEFAULT_MAX_DETERMINIZED_STATES,
+    MAXIMUM_SUPPORTED_DISTANCE,
+};
+
+use error::{ErrorKind::IllegalArgument, Result};
+
+pub const DEFAULT_MAX_EDITS: usize = MAXIMUM_SUPPORTED_DISTANCE;
+pub const DEFAULT_PREFIX_LENGTH: usize = 0;
+pub const DEFAULT_MAX_EXPANSIONS: usize = 50;
+pub const DEFAULT_TRANSPOSITIONS: bool = true;
+
+/// Implements the fuzzy search query, matching terms within an edit distance
+/// of the query term.
+///
+/// Each matching term is boosted by its similarity to the query term,
+/// `1 - edits / min(term length, query length)`. By default the query is
+/// rewritten with `RewriteMethod::TopTermsBlendedFreqScoring`, keeping the
+/// `max_expansions` most similar terms; a non-zero `prefix_length` makes the
+/// query a lot faster, since the leading chars have to match exactly.
+pub struct FuzzyQuery {
+    query: AutomatonQuery,
+    max_edits: usize,
+    prefix_length: usize,
+    max_expansions: usize,
+    transpositions: bool,
+}
+
+impl FuzzyQuery {
+    pub fn new(term: Term) -> Result<FuzzyQuery> {
+        FuzzyQuery::with_params(
+            term,
+            DEFAULT_MAX_EDITS,
+            DEFAULT_PREFIX_LENGTH,
+            DEFAULT_MAX_EXPANSIONS,
+            DEFAULT_TRANSPOSITIONS,
+        )
+    }
+
+    /// Creates a `FuzzyQuery` matching terms within `max_edits` of `term`
+    /// whose first `prefix_length` chars are equal to those of `term`.
+    ///
+    /// `max_edits` must be at most `MAXIMUM_SUPPORTED_DISTANCE`, and
+    /// `max_expansions` is the number of terms kept by the default rewrite.
+    /// If `transpositions` is set, swapping two adjacent chars counts as a
+    /// single edit.
+    pub fn with_params(
+        term: Term,
+        max_edits: usize,
+        prefix_length: usize,
+        max_expansions: usize,
+        transpositions: bool,
+    ) -> Result<FuzzyQuery> {
+        if max_edits > MAXIMUM_SUPPORTED_DISTANCE {
+            bail!(IllegalArgument(format!(
+                "max_edits must be between 0 and {}",
+                MAXIMUM_SUPPORTED_DISTANCE
+            )));
+        }
+        if max_expansions == 0 {
+            bail!(IllegalArgument(
+                "max_expansions must be positive".to_string()
+            ));
+        }
+
+        let text = term.text()?;
+        let prefix: String = text.chars().take(prefix_length).collect();
+        let suffix = &text[prefix.len()..];
+        let levenshtein = LevenshteinAutomata::new(suffix, transpositions);
+
+        let mut matchers = Vec::with_capacity(max_edits);
+        for edits in 0..max_edits {
+            let automaton = levenshtein.to_automaton(edits, &prefix);
+            matchers.push(CompiledAutomaton::new(
+                &automaton,
+                DEFAULT_MAX_DETERMINIZED_STATES,
+            )?);
+        }
+        let term_boost = FuzzyTermBoost {
+            matchers,
+            max_edits,
+            term_length: text.chars().count(),
+        };
+
+        let automaton = levenshtein.to_automaton(max_edits, &prefix);
+        let mut query = AutomatonQuery::new(term, &automaton, DEFAULT_MAX_DETERMINIZED_STATES)?;
+        query.set_term_boost(Arc::new(term_boost));
+        query.set_rewrite_method(RewriteMethod::TopTermsBlendedFreqScoring(max_expansions));
+        Ok(FuzzyQuery {
+            query,
+            max_edits,
+            prefix_length,
+            max_expansions,
+            transpositions,
+        })
+    }
+
+    pub fn term(&self) -> &Term {
+        self.query.term()
+    }
+
+    pub fn max_edits(&self) -> usize {
+        self.max_edits
+    }
+
+    pub fn prefix_length(&self) -> usize {
+        self.prefix_length
+    }
+
+    pub fn max_expansions(&self) -> usize {
+        self.max_expansions
+    }
+
+    pub fn transpositions(&self) -> bool {
+        self.transpositions
+    }
+
+    pub fn set_rewrite_method(&mut self, rewrite_method: RewriteMethod) {
+        self.query.set_rewrite_method(rewrite_method);
+    }
+
+    pub fn set_boost(&mut self, boost: f32) {
+        self.query.set_boost(boost);
+    }
+}
+
+impl<C: Codec> Query<C> for FuzzyQuery {
+    fn create_weight(
+        &self,
+        searcher: &dyn SearchPlanBuilder<C>,
+        needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        self.query.create_weight(searcher, needs_scores)
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        vec![]
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn rewrite(&self, searcher: &dyn SearchPlanBuilder<C>) -> Result<Option<Box<dyn Query<C>>>> {
+        Query::<C>::rewrite(&self.query, searcher)
+    }
+}
+
+impl fmt::Display for FuzzyQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let term = self.query.term();
+        write!(
+            f,
+            "FuzzyQuery(field: {}, term: {}, max_edits: {}, prefix_length: {}, boost: {})",
+            &term.field,
+            String::from_utf8_lossy(&term.bytes),
+            self.max_edits,
+            self.prefix_length,
+            self.query.boost()
+        )
+    }
+}
+
+/// Boosts the terms matched by a `FuzzyQuery` by their similarity to the
+/// query term.
+struct FuzzyTermBoost {
+    // matchers[i] accepts the terms within i edits
+    matchers: Vec<CompiledAutomaton>,
+    max_edits: usize,
+    term_length: usize,
+}
+
+impl TermBoost for FuzzyTermBoost {
+    fn boost(&self, term: &[u8]) -> Option<f32> {
+        let edits = self
+            .matchers
+            .iter()
+            .position(|m| m.run(term))
+            .unwrap_or(self.max_edits);
+        if edits == 0 {
+            return Some(1f32);
+        }
+
+        let length = term.iter().filter(|&&b| b & 0xC0 != 0x80).count();
+        let similarity = 1f32 - edits as f32 / length.min(self.term_length) as f32;
+        if similarity > 0f32 {
+            Some(similarity)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate tempfile;
+
+    use super::*;
+    use core::doc::{Field, FieldType, Fieldable, IndexOptions};
+    use core::index::writer::{IndexWriter, IndexWriterConfig};
+    use core::search::collector::TopDocsCollector;
+    use core::search::{DefaultIndexSearcher, IndexSearcher};
+    use core::store::directory::FSDirectory;
+    use core::util::VariantValue;
+
+    #[test]
+    fn test_fuzzy_query() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let directory = Arc::new(FSDirectory::with_path(temp_dir.path()).unwrap());
+        let writer = IndexWriter::new(directory, Arc::new(IndexWriterConfig::default())).unwrap();
+
+        let mut field_type = FieldType::default();
+        field_type.index_options = IndexOptions::DocsAndFreqs;
+        let words = [
+            "foobar", "foobar", "foobar", "foobar", "fobar", "foobaz", "ofobar", "fxxbar", "barfoo",
+        ];
+        for word in &words {
+            let doc: Vec<Box<dyn Fieldable>> = vec![Box::new(Field::new(
+                "word".into(),
+                field_type.clone(),
+                Some(VariantValue::VString((*word).to_string())),
+                None,
+            ))];
+            writer.add_document(doc).unwrap();
+        }
+        writer.commit().unwrap();
+
+        let reader = writer.get_reader(true, false).unwrap();
+        let searcher = DefaultIndexSearcher::new(Arc::new(reader), None);
+        let search = |query: &FuzzyQuery| {
+            let mut collector = TopDocsCollector::new(10);
+            searcher.search(query, &mut collector).unwrap();
+            collector.top_docs()
+        };
+        let term = Term::new("word".into(), b"foobar".to_vec());
+        let fuzzy = |edits, prefix, expansions, transpositions| {
+            FuzzyQuery::with_params(term.clone(), edits, prefix, expansions, transpositions)
+                .unwrap()
+        };
+
+        assert_eq!(search(&fuzzy(0, 0, 50, true)).total_hits(), 4);
+        assert_eq!(search(&fuzzy(1, 0, 50, false)).total_hits(), 6);
+        assert_eq!(search(&fuzzy(1, 0, 50, true)).total_hits(), 7);
+        assert_eq!(search(&fuzzy(2, 0, 50, true)).total_hits(), 8);
+        assert_eq!(search(&fuzzy(2, 2, 50, true)).total_hits(), 6);
+        assert_eq!(search(&fuzzy(2, 0, 1, true)).total_hits(), 4);
+
+        // the blended document frequency keeps the rare misspellings from
+        // scoring higher than the exact term
+        let top_docs = search(&FuzzyQuery::new(term.clone()).unwrap());
+        let hits = top_docs.score_docs();
+        assert_eq!(hits.len(), 8);
+        for hit in &hits[..4] {
+            assert!(words[hit.doc_id() as usize] == "foobar");
+            assert!(hit.score() > hits[4].score());
+        }
+
+        let mut constant = fuzzy(2, 0, 1, true);
+        constant.set_rewrite_method(RewriteMethod::ConstantScore);
+        assert_eq!(search(&constant).total_hits(), 8);
+
+        assert!(FuzzyQuery::with_params(term.clone(), 3, 0, 50, true).is_err());
+        assert!(FuzzyQuery::with_params(term, 1, 0, 0, true).is_err());
+    }
+}
@@ -180,6 +180,10 @@ impl<C: Codec> ConstantScoreQuery<C> {
     pub fn get_raw_query(&self) -> &dyn Query<C> {
         self.query.as_ref()
     }
+
+    pub fn boost(&self) -> f32 {
+        self.boost
+    }
 }
 
 impl<C: Codec> fmt::Display for ConstantScoreQuery<C> {
@@ -213,6 +217,12 @@ impl<C: Codec> Query<C> for ConstantScoreQuery<C> {
     fn as_any(&self) -> &dyn (::std::any::Any) {
         self
     }
+
+    fn rewrite(&self, searcher: &dyn SearchPlanBuilder<C>) -> Result<Option<Box<dyn Query<C>>>> {
+        Ok(self.query.rewrite(searcher)?.map(|query| {
+            Box::new(ConstantScoreQuery::with_boost(query, self.boost)) as Box<dyn Query<C>>
+        }))
+    }
 }
 
 struct ConstantScoreWeight<C: Codec> {
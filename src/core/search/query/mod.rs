@@ -58,11 +58,61 @@ mod exists_query;
 
 pub use self::exists_query::*;
 
+mod constant_score_docs;
+
+mod automaton_query;
+
+pub use self::automaton_query::*;
+
+mod prefix_query;
+
+pub use self::prefix_query::*;
+
+mod wildcard_query;
+
+pub use self::wildcard_query::*;
+
+mod regexp_query;
+
+pub use self::regexp_query::*;
+
+mod fuzzy_query;
+
+pub use self::fuzzy_query::*;
+
+mod term_range_query;
+
+pub use self::term_range_query::*;
+
+mod term_in_set_query;
+
+pub use self::term_in_set_query::*;
+
+mod doc_values_terms_query;
+
+pub use self::doc_values_terms_query::*;
+
+mod function_score_query;
+
+pub use self::function_score_query::*;
+
+mod script_score_query;
+
+pub use self::script_score_query::*;
+
+mod feature_vector_query;
+
+pub use self::feature_vector_query::*;
+
+mod query_dsl;
+
+pub use self::query_dsl::*;
+
 use core::codec::Codec;
 use core::index::reader::LeafReaderContext;
 use core::search::explanation::Explanation;
 use core::search::scorer::{BatchScorer, Scorer};
-use core::search::searcher::SearchPlanBuilder;
+use core::search::searcher::{self, SearchPlanBuilder};
 use core::util::DocId;
 
 use error::Result;
@@ -70,6 +120,7 @@ use std::any::Any;
 use std::collections::hash_map::DefaultHasher;
 use std::fmt::Display;
 use std::hash::{Hash, Hasher};
+use std::sync::Arc;
 
 /// The abstract base class for queries.
 ///
@@ -98,6 +149,14 @@ use std::hash::{Hash, Hasher};
 /// * [`ConstantScoreQuery`](match_all/struct.ConstantScoreQuery.html)
 /// * [`DisjunctionMaxQuery`](disjunction/struct.DisjunctionMaxQuery.html)
 /// * [`MatchAllDocsQuery`](match_all/struct.MatchAllDocsQuery.html)
+/// * [`TermRangeQuery`] and [`DocValuesTermRangeQuery`]
+/// * [`TermInSetQuery`] and [`DocValuesTermsQuery`]
+/// * [`AutomatonQuery`], [`PrefixQuery`], [`WildcardQuery`], [`RegexpQuery`] and [`FuzzyQuery`]
+/// * [`FunctionScoreQuery`] and [`ScriptScoreQuery`]
+/// * [`FeatureVectorQuery`], which extracts the features of an `LtrRescorer`
+///
+/// [`QueryStringQueryBuilder`] and [`QueryDsl`] build query trees from a query string
+/// and from JSON respectively.
 ///
 /// See also the family of [`Span Queries`](spans/index.html)
 pub trait Query<C: Codec>: Display {
@@ -108,10 +167,65 @@ pub trait Query<C: Codec>: Display {
         needs_scores: bool,
     ) -> Result<Box<dyn Weight<C>>>;
 
+    /// Creates the `Weight` of the top-level query of a search for the
+    /// `ScoreMode` of its collector.
+    ///
+    /// Only the queries whose scorers can skip the non-competitive docs need to
+    /// look at more than whether scores are needed.
+    fn create_weight_with_mode(
+        &self,
+        searcher: &dyn SearchPlanBuilder<C>,
+        score_mode: searcher::ScoreMode,
+    ) -> Result<Box<dyn Weight<C>>> {
+        self.create_weight(searcher, score_mode.needs_scores())
+    }
+
     /// For highlight use.
     fn extract_terms(&self) -> Vec<TermQuery>;
 
     fn as_any(&self) -> &dyn Any;
+
+    /// Expert: rewrites this query into a simpler or more primitive form, e.g. a
+    /// multi-term query into a `BooleanQuery` of the matching terms.
+    ///
+    /// Returns `None` if the query doesn't need to be rewritten. The searcher
+    /// rewrites every query until it reaches a fixed point before creating its
+    /// `Weight`, so an implementation only needs to do one step at a time.
+    fn rewrite(&self, _searcher: &dyn SearchPlanBuilder<C>) -> Result<Option<Box<dyn Query<C>>>> {
+        Ok(None)
+    }
+}
+
+/// A query shared by several query trees, e.g. a clause kept as is when its
+/// parent `BoostingQuery` is rewritten.
+impl<C: Codec> Query<C> for Arc<dyn Query<C>> {
+    fn create_weight(
+        &self,
+        searcher: &dyn SearchPlanBuilder<C>,
+        needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        self.as_ref().create_weight(searcher, needs_scores)
+    }
+
+    fn create_weight_with_mode(
+        &self,
+        searcher: &dyn SearchPlanBuilder<C>,
+        score_mode: searcher::ScoreMode,
+    ) -> Result<Box<dyn Weight<C>>> {
+        self.as_ref().create_weight_with_mode(searcher, score_mode)
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        self.as_ref().extract_terms()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self.as_ref().as_any()
+    }
+
+    fn rewrite(&self, searcher: &dyn SearchPlanBuilder<C>) -> Result<Option<Box<dyn Query<C>>>> {
+        self.as_ref().rewrite(searcher)
+    }
 }
 
 /// Expert: Calculate query weights and build query scorers.
@@ -129,6 +129,22 @@ impl PhraseQuery {
         Self::new(terms, positions, slop, ctx, ctxs)
     }
 
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    pub fn terms(&self) -> &[Term] {
+        &self.terms
+    }
+
+    pub fn positions(&self) -> &[i32] {
+        &self.positions
+    }
+
+    pub fn slop(&self) -> i32 {
+        self.slop
+    }
+
     fn increment_positions(length: usize) -> Vec<i32> {
         (0..length as i32).collect()
     }
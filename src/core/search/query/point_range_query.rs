@@ -405,6 +405,26 @@ impl PointRangeQuery {
             value_type,
         })
     }
+
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    pub fn num_dims(&self) -> usize {
+        self.num_dims
+    }
+
+    pub fn lower_point(&self) -> &[u8] {
+        &self.lower_point
+    }
+
+    pub fn upper_point(&self) -> &[u8] {
+        &self.upper_point
+    }
+
+    pub fn value_type(&self) -> PointValueType {
+        self.value_type
+    }
 }
 
 pub const POINT_RANGE: &str = "point_range";
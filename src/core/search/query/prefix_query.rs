@@ -0,0 +1,88 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::any::Any;
+use std::fmt;
+
+use core::codec::Codec;
+use core::doc::Term;
+use core::search::query::{AutomatonQuery, Query, RewriteMethod, TermQuery, Weight};
+use core::search::searcher::SearchPlanBuilder;
+use core::util::automaton::{concatenate, Automaton};
+
+use error::Result;
+
+/// A `Query` that matches documents containing terms with a specified prefix.
+pub struct PrefixQuery {
+    query: AutomatonQuery,
+}
+
+impl PrefixQuery {
+    pub fn new(prefix: Term) -> Result<PrefixQuery> {
+        let automaton = Self::build_automaton(&prefix.bytes);
+        let query = AutomatonQuery::new(prefix, &automaton, usize::max_value())?;
+        Ok(PrefixQuery { query })
+    }
+
+    /// Builds an automaton accepting all terms with the given prefix.
+    pub fn build_automaton(prefix: &[u8]) -> Automaton {
+        concatenate(&[Automaton::binary(prefix), Automaton::any_binary()])
+    }
+
+    pub fn prefix(&self) -> &Term {
+        self.query.term()
+    }
+
+    pub fn set_rewrite_method(&mut self, rewrite_method: RewriteMethod) {
+        self.query.set_rewrite_method(rewrite_method);
+    }
+
+    pub fn set_boost(&mut self, boost: f32) {
+        self.query.set_boost(boost);
+    }
+}
+
+impl<C: Codec> Query<C> for PrefixQuery {
+    fn create_weight(
+        &self,
+        searcher: &dyn SearchPlanBuilder<C>,
+        needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        self.query.create_weight(searcher, needs_scores)
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        vec![]
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn rewrite(&self, searcher: &dyn SearchPlanBuilder<C>) -> Result<Option<Box<dyn Query<C>>>> {
+        Query::<C>::rewrite(&self.query, searcher)
+    }
+}
+
+impl fmt::Display for PrefixQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let term = self.query.term();
+        write!(
+            f,
+            "PrefixQuery(field: {}, prefix: {}, boost: {})",
+            &term.field,
+            String::from_utf8_lossy(&term.bytes),
+            self.query.boost()
+        )
+    }
+}
@@ -0,0 +1,925 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use serde::de::{self, Deserialize, Deserializer};
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use serde_json;
+use std::collections::BTreeMap;
+use std::{f32, f64, i64};
+
+use core::codec::Codec;
+use core::doc::Term;
+use core::search::query::spans::{
+    SpanBoostQuery, SpanBoostQueryEnum, SpanNearQuery, SpanOrQuery, SpanQueryEnum, SpanTermQuery,
+};
+use core::search::query::{
+    BooleanQuery, BoostQuery, BoostingQuery, ConstantScoreQuery, DisjunctionMaxQuery, DoublePoint,
+    ExistsQuery, FloatPoint, IntPoint, LongPoint, MatchAllDocsQuery, PhraseQuery, PointRangeQuery,
+    PointValueType, Query, TermQuery, TermRangeQuery,
+};
+use error::{Error, ErrorKind::IllegalArgument, Result};
+
+fn default_boost() -> f32 {
+    1.0
+}
+
+#[allow(clippy::trivially_copy_pass_by_ref)]
+fn is_default_boost(boost: &f32) -> bool {
+    (boost - 1.0).abs() <= f32::EPSILON
+}
+
+#[allow(clippy::trivially_copy_pass_by_ref)]
+fn is_zero(value: &i32) -> bool {
+    *value == 0
+}
+
+#[allow(clippy::trivially_copy_pass_by_ref)]
+fn is_zero_f32(value: &f32) -> bool {
+    *value == 0.0
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// An Elasticsearch like JSON query DSL.
+///
+/// A `QueryDsl` deserializes from JSON such as
+/// `{"bool": {"must": [{"term": {"title": "rust"}}]}}`, is turned into a query tree
+/// with `build` and can be recovered from a supported query tree with `from_query`.
+///
+/// A `range` queries the points of the `IntPoint`, `LongPoint`, `FloatPoint` or
+/// `DoublePoint` field named by its `"type"`, or builds a `TermRangeQuery` for the
+/// `term` type. The type can only be omitted when all the bounds are strings.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryDsl {
+    Term(FieldQuery<TermDsl>),
+    Bool(BoolDsl),
+    MatchPhrase(FieldQuery<MatchPhraseDsl>),
+    Range(FieldQuery<RangeDsl>),
+    Exists(ExistsDsl),
+    DisMax(DisMaxDsl),
+    Boosting(BoostingDsl),
+    SpanNear(SpanNearDsl),
+    MatchAll(MatchAllDsl),
+}
+
+/// A clause keyed by the field it applies to, e.g. `{"title": {"value": "rust"}}`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldQuery<T> {
+    pub field: String,
+    pub params: T,
+}
+
+impl<T> FieldQuery<T> {
+    pub fn new<S: Into<String>>(field: S, params: T) -> Self {
+        FieldQuery {
+            field: field.into(),
+            params,
+        }
+    }
+}
+
+impl<T: Serialize> Serialize for FieldQuery<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry(&self.field, &self.params)?;
+        map.end()
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for FieldQuery<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> ::std::result::Result<Self, D::Error> {
+        let map = BTreeMap::<String, T>::deserialize(deserializer)?;
+        if map.len() != 1 {
+            return Err(de::Error::custom(format!(
+                "expected exactly one field, found {}",
+                map.len()
+            )));
+        }
+        let (field, params) = map.into_iter().next().unwrap();
+        Ok(FieldQuery { field, params })
+    }
+}
+
+/// `{"term": {"title": "rust"}}` or `{"term": {"title": {"value": "rust", "boost": 2.0}}}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "TermDslRepr")]
+pub struct TermDsl {
+    pub value: String,
+    #[serde(skip_serializing_if = "is_default_boost")]
+    pub boost: f32,
+}
+
+impl TermDsl {
+    pub fn new<S: Into<String>>(value: S) -> Self {
+        TermDsl {
+            value: value.into(),
+            boost: 1.0,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TermDslRepr {
+    Value(String),
+    Params {
+        value: String,
+        #[serde(default = "default_boost")]
+        boost: f32,
+    },
+}
+
+impl From<TermDslRepr> for TermDsl {
+    fn from(repr: TermDslRepr) -> Self {
+        match repr {
+            TermDslRepr::Value(value) => TermDsl::new(value),
+            TermDslRepr::Params { value, boost } => TermDsl { value, boost },
+        }
+    }
+}
+
+/// `{"match_phrase": {"title": {"query": "quick fox", "slop": 1}}}`, the query is
+/// split on whitespace.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "MatchPhraseDslRepr")]
+pub struct MatchPhraseDsl {
+    pub query: String,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub slop: i32,
+    #[serde(skip_serializing_if = "is_default_boost")]
+    pub boost: f32,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum MatchPhraseDslRepr {
+    Query(String),
+    Params {
+        query: String,
+        #[serde(default)]
+        slop: i32,
+        #[serde(default = "default_boost")]
+        boost: f32,
+    },
+}
+
+impl From<MatchPhraseDslRepr> for MatchPhraseDsl {
+    fn from(repr: MatchPhraseDslRepr) -> Self {
+        match repr {
+            MatchPhraseDslRepr::Query(query) => MatchPhraseDsl {
+                query,
+                slop: 0,
+                boost: 1.0,
+            },
+            MatchPhraseDslRepr::Params { query, slop, boost } => {
+                MatchPhraseDsl { query, slop, boost }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum RangeValue {
+    Long(i64),
+    Double(f64),
+    String(String),
+}
+
+impl RangeValue {
+    fn as_f64(&self) -> Option<f64> {
+        match *self {
+            RangeValue::Long(v) => Some(v as f64),
+            RangeValue::Double(v) => Some(v),
+            RangeValue::String(_) => None,
+        }
+    }
+}
+
+/// The type of the values of a `range`, `{"type": "long"}`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RangeType {
+    Term,
+    Integer,
+    Long,
+    Float,
+    Double,
+}
+
+impl From<PointValueType> for RangeType {
+    fn from(value_type: PointValueType) -> Self {
+        match value_type {
+            PointValueType::Integer => RangeType::Integer,
+            PointValueType::Long => RangeType::Long,
+            PointValueType::Float => RangeType::Float,
+            PointValueType::Double => RangeType::Double,
+        }
+    }
+}
+
+/// `{"range": {"age": {"gte": 10, "lt": 20, "type": "long"}}}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RangeDsl {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gt: Option<RangeValue>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gte: Option<RangeValue>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lt: Option<RangeValue>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lte: Option<RangeValue>,
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub value_type: Option<RangeType>,
+    #[serde(default = "default_boost", skip_serializing_if = "is_default_boost")]
+    pub boost: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BoolDsl {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub must: Vec<QueryDsl>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub should: Vec<QueryDsl>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub filter: Vec<QueryDsl>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub must_not: Vec<QueryDsl>,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub minimum_should_match: i32,
+    #[serde(default = "default_boost", skip_serializing_if = "is_default_boost")]
+    pub boost: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExistsDsl {
+    pub field: String,
+    #[serde(default = "default_boost", skip_serializing_if = "is_default_boost")]
+    pub boost: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DisMaxDsl {
+    pub queries: Vec<QueryDsl>,
+    #[serde(default, skip_serializing_if = "is_zero_f32")]
+    pub tie_breaker: f32,
+    #[serde(default = "default_boost", skip_serializing_if = "is_default_boost")]
+    pub boost: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BoostingDsl {
+    pub positive: Box<QueryDsl>,
+    pub negative: Box<QueryDsl>,
+    pub negative_boost: f32,
+    #[serde(default = "default_boost", skip_serializing_if = "is_default_boost")]
+    pub boost: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpanNearDsl {
+    pub clauses: Vec<SpanQueryDsl>,
+    #[serde(default)]
+    pub slop: i32,
+    #[serde(default = "default_true")]
+    pub in_order: bool,
+    #[serde(default = "default_boost", skip_serializing_if = "is_default_boost")]
+    pub boost: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpanOrDsl {
+    pub clauses: Vec<SpanQueryDsl>,
+    #[serde(default = "default_boost", skip_serializing_if = "is_default_boost")]
+    pub boost: f32,
+}
+
+/// The clauses allowed inside a `span_near`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpanQueryDsl {
+    SpanTerm(FieldQuery<TermDsl>),
+    SpanOr(SpanOrDsl),
+    SpanNear(SpanNearDsl),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MatchAllDsl {
+    #[serde(default = "default_boost", skip_serializing_if = "is_default_boost")]
+    pub boost: f32,
+}
+
+impl QueryDsl {
+    pub fn from_json(json: &str) -> Result<QueryDsl> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    fn boost_mut(&mut self) -> &mut f32 {
+        match self {
+            QueryDsl::Term(q) => &mut q.params.boost,
+            QueryDsl::Bool(q) => &mut q.boost,
+            QueryDsl::MatchPhrase(q) => &mut q.params.boost,
+            QueryDsl::Range(q) => &mut q.params.boost,
+            QueryDsl::Exists(q) => &mut q.boost,
+            QueryDsl::DisMax(q) => &mut q.boost,
+            QueryDsl::Boosting(q) => &mut q.boost,
+            QueryDsl::SpanNear(q) => &mut q.boost,
+            QueryDsl::MatchAll(q) => &mut q.boost,
+        }
+    }
+
+    pub fn build<C: Codec>(&self) -> Result<Box<dyn Query<C>>> {
+        let (query, boost): (Box<dyn Query<C>>, f32) = match self {
+            QueryDsl::Term(FieldQuery { field, params }) => {
+                let term = Term::new(field.clone(), params.value.as_bytes().to_vec());
+                return Ok(Box::new(TermQuery::new(term, params.boost, None)));
+            }
+            QueryDsl::Bool(q) => (
+                BooleanQuery::build(
+                    build_all::<C>(&q.must)?,
+                    build_all::<C>(&q.should)?,
+                    build_all::<C>(&q.filter)?,
+                    build_all::<C>(&q.must_not)?,
+                    q.minimum_should_match,
+                )?,
+                q.boost,
+            ),
+            QueryDsl::MatchPhrase(FieldQuery { field, params }) => {
+                let mut terms: Vec<Term> = params
+                    .query
+                    .split_whitespace()
+                    .map(|t| Term::new(field.clone(), t.as_bytes().to_vec()))
+                    .collect();
+                let query: Box<dyn Query<C>> = match terms.len() {
+                    0 => bail!(IllegalArgument("match_phrase query is empty".into())),
+                    1 => Box::new(TermQuery::new(terms.remove(0), 1.0, None)),
+                    _ => Box::new(PhraseQuery::build(terms, params.slop, None, None)?),
+                };
+                (query, params.boost)
+            }
+            QueryDsl::Range(FieldQuery { field, params }) => {
+                (range_query(field, params)?, params.boost)
+            }
+            QueryDsl::Exists(q) => (Box::new(ExistsQuery::build(q.field.clone())), q.boost),
+            QueryDsl::DisMax(q) => (
+                DisjunctionMaxQuery::build(build_all::<C>(&q.queries)?, q.tie_breaker)?,
+                q.boost,
+            ),
+            QueryDsl::Boosting(q) => (
+                BoostingQuery::build(q.positive.build()?, q.negative.build()?, q.negative_boost),
+                q.boost,
+            ),
+            QueryDsl::SpanNear(q) => (Box::new(span_near_query(q)?), q.boost),
+            QueryDsl::MatchAll(q) => (Box::new(MatchAllDocsQuery), q.boost),
+        };
+        Ok(BoostQuery::build(query, boost))
+    }
+
+    /// Converts a query tree back into the DSL, fails if the tree contains a
+    /// query that has no DSL counterpart.
+    pub fn from_query<C: Codec>(query: &dyn Query<C>) -> Result<QueryDsl> {
+        let from_all = |queries: &[Box<dyn Query<C>>]| -> Result<Vec<QueryDsl>> {
+            queries
+                .iter()
+                .map(|q| QueryDsl::from_query(q.as_ref()))
+                .collect()
+        };
+        let any = query.as_any();
+        let dsl = if let Some(q) = any.downcast_ref::<TermQuery>() {
+            QueryDsl::Term(FieldQuery::new(
+                q.term.field.clone(),
+                TermDsl {
+                    value: q.term.text()?,
+                    boost: q.boost,
+                },
+            ))
+        } else if let Some(q) = any.downcast_ref::<BoostQuery<C>>() {
+            let mut dsl = QueryDsl::from_query(q.query())?;
+            *dsl.boost_mut() *= q.boost();
+            dsl
+        } else if let Some(q) = any.downcast_ref::<BooleanQuery<C>>() {
+            // `BooleanQuery::build` requires one should clause when there is no must
+            let minimum_should_match = if q.musts().is_empty() && q.min_should_match() == 1 {
+                0
+            } else {
+                q.min_should_match()
+            };
+            QueryDsl::Bool(BoolDsl {
+                must: from_all(q.musts())?,
+                should: from_all(q.shoulds())?,
+                filter: from_all(q.filters())?,
+                must_not: from_all(q.must_nots())?,
+                minimum_should_match,
+                boost: 1.0,
+            })
+        } else if let Some(q) = any.downcast_ref::<ConstantScoreQuery<C>>() {
+            // a boolean query with a single filter is built as a zero score
+            // `ConstantScoreQuery`
+            if q.boost() != 0.0 {
+                bail!(IllegalArgument(format!(
+                    "query can't be converted to dsl: {}",
+                    q
+                )));
+            }
+            QueryDsl::Bool(BoolDsl {
+                must: vec![],
+                should: vec![],
+                filter: vec![QueryDsl::from_query(q.get_raw_query())?],
+                must_not: vec![],
+                minimum_should_match: 0,
+                boost: 1.0,
+            })
+        } else if let Some(q) = any.downcast_ref::<PhraseQuery>() {
+            let consecutive = q.positions().windows(2).all(|w| w[1] == w[0] + 1);
+            if !consecutive {
+                bail!(IllegalArgument(
+                    "phrase query with position gaps can't be converted to dsl".into()
+                ));
+            }
+            let terms: Vec<String> = q.terms().iter().map(Term::text).collect::<Result<_>>()?;
+            QueryDsl::MatchPhrase(FieldQuery::new(
+                q.field(),
+                MatchPhraseDsl {
+                    query: terms.join(" "),
+                    slop: q.slop(),
+                    boost: 1.0,
+                },
+            ))
+        } else if let Some(q) = any.downcast_ref::<TermRangeQuery>() {
+            let bound = |b: Option<&[u8]>| -> Result<Option<RangeValue>> {
+                match b {
+                    Some(bytes) => Ok(Some(RangeValue::String(String::from_utf8(bytes.to_vec())?))),
+                    None => Ok(None),
+                }
+            };
+            let (lower, upper) = (bound(q.lower())?, bound(q.upper())?);
+            let (gte, gt) = if q.include_lower() {
+                (lower, None)
+            } else {
+                (None, lower)
+            };
+            let (lte, lt) = if q.include_upper() {
+                (upper, None)
+            } else {
+                (None, upper)
+            };
+            QueryDsl::Range(FieldQuery::new(
+                q.field(),
+                RangeDsl {
+                    gt,
+                    gte,
+                    lt,
+                    lte,
+                    value_type: Some(RangeType::Term),
+                    boost: q.boost(),
+                },
+            ))
+        } else if let Some(q) = any.downcast_ref::<PointRangeQuery>() {
+            if q.num_dims() != 1 {
+                bail!(IllegalArgument(
+                    "multi dimensional point range can't be converted to dsl".into()
+                ));
+            }
+            QueryDsl::Range(FieldQuery::new(
+                q.field(),
+                RangeDsl {
+                    gt: None,
+                    gte: point_value(q.value_type(), q.lower_point(), true),
+                    lt: None,
+                    lte: point_value(q.value_type(), q.upper_point(), false),
+                    value_type: Some(q.value_type().into()),
+                    boost: 1.0,
+                },
+            ))
+        } else if let Some(q) = any.downcast_ref::<ExistsQuery>() {
+            QueryDsl::Exists(ExistsDsl {
+                field: q.field().to_string(),
+                boost: 1.0,
+            })
+        } else if let Some(q) = any.downcast_ref::<DisjunctionMaxQuery<C>>() {
+            QueryDsl::DisMax(DisMaxDsl {
+                queries: from_all(&q.disjuncts)?,
+                tie_breaker: q.tie_breaker_multiplier,
+                boost: 1.0,
+            })
+        } else if let Some(q) = any.downcast_ref::<BoostingQuery<C>>() {
+            QueryDsl::Boosting(BoostingDsl {
+                positive: Box::new(QueryDsl::from_query(q.positive())?),
+                negative: Box::new(QueryDsl::from_query(q.negative())?),
+                negative_boost: q.negative_boost(),
+                boost: 1.0,
+            })
+        } else if let Some(q) = any.downcast_ref::<SpanNearQuery>() {
+            QueryDsl::SpanNear(span_near_dsl(q, 1.0)?)
+        } else if any.downcast_ref::<MatchAllDocsQuery>().is_some() {
+            QueryDsl::MatchAll(MatchAllDsl { boost: 1.0 })
+        } else {
+            bail!(IllegalArgument(format!(
+                "query can't be converted to dsl: {}",
+                query
+            )));
+        };
+        Ok(dsl)
+    }
+}
+
+fn build_all<C: Codec>(queries: &[QueryDsl]) -> Result<Vec<Box<dyn Query<C>>>> {
+    queries.iter().map(QueryDsl::build).collect()
+}
+
+fn range_query<C: Codec>(field: &str, range: &RangeDsl) -> Result<Box<dyn Query<C>>> {
+    if range.gt.is_some() && range.gte.is_some() || range.lt.is_some() && range.lte.is_some() {
+        bail!(IllegalArgument(format!(
+            "range on '{}' has both an inclusive and an exclusive bound",
+            field
+        )));
+    }
+    let lower = match (&range.gt, &range.gte) {
+        (Some(v), _) => Some((v, false)),
+        (_, Some(v)) => Some((v, true)),
+        _ => None,
+    };
+    let upper = match (&range.lt, &range.lte) {
+        (Some(v), _) => Some((v, false)),
+        (_, Some(v)) => Some((v, true)),
+        _ => None,
+    };
+    let bounds = || lower.iter().chain(upper.iter()).map(|(v, _)| *v);
+    let is_string = |v: &RangeValue| match v {
+        RangeValue::String(_) => true,
+        _ => false,
+    };
+
+    // numeric bounds don't tell the type of the points of the field
+    let value_type = match range.value_type {
+        Some(value_type) => value_type,
+        None if lower.is_some() || upper.is_some() => {
+            if !bounds().all(is_string) {
+                bail!(IllegalArgument(format!(
+                    "range on '{}' has numeric bounds but no type",
+                    field
+                )));
+            }
+            RangeType::Term
+        }
+        None => bail!(IllegalArgument(format!(
+            "range on '{}' has neither bounds nor a type",
+            field
+        ))),
+    };
+    let mismatch = || -> Error {
+        IllegalArgument(format!(
+            "range on '{}' has bounds that aren't {:?} values",
+            field, value_type
+        ))
+        .into()
+    };
+    match value_type {
+        RangeType::Term => {
+            if !bounds().all(is_string) {
+                return Err(mismatch());
+            }
+            let bytes = |bound: Option<(&RangeValue, bool)>| match bound {
+                Some((RangeValue::String(s), _)) => Some(s.as_bytes().to_vec()),
+                _ => None,
+            };
+            Ok(Box::new(TermRangeQuery::new(
+                field.to_string(),
+                bytes(lower),
+                bytes(upper),
+                lower.map_or(true, |(_, inclusive)| inclusive),
+                upper.map_or(true, |(_, inclusive)| inclusive),
+            )))
+        }
+        RangeType::Integer | RangeType::Long => {
+            let (min, max) = if value_type == RangeType::Integer {
+                (i64::from(i32::min_value()), i64::from(i32::max_value()))
+            } else {
+                (i64::MIN, i64::MAX)
+            };
+            // exclusive bounds are moved by `step` to the next included value,
+            // there is none past the edge of the type, e.g. for `gt` the max
+            let integer = |bound: Option<(&RangeValue, bool)>, default: i64, step: i64| {
+                let (value, inclusive) = match bound {
+                    Some((RangeValue::Long(v), inclusive)) => (*v, inclusive),
+                    Some(_) => return Err(mismatch()),
+                    None => return Ok(Some(default)),
+                };
+                if value < min || value > max {
+                    bail!(IllegalArgument(format!(
+                        "range bound on '{}' overflows",
+                        field
+                    )));
+                }
+                if inclusive {
+                    Ok(Some(value))
+                } else {
+                    Ok(value.checked_add(step).filter(|v| *v >= min && *v <= max))
+                }
+            };
+            let (lower, upper) = match (integer(lower, min, 1)?, integer(upper, max, -1)?) {
+                (Some(lower), Some(upper)) => (lower, upper),
+                // a point range with its lower bound above its upper one is empty
+                _ => (max, min),
+            };
+            if value_type == RangeType::Integer {
+                IntPoint::new_range_query(field.to_string(), lower as i32, upper as i32)
+            } else {
+                LongPoint::new_range_query(field.to_string(), lower, upper)
+            }
+        }
+        RangeType::Float | RangeType::Double => {
+            if bounds().any(is_string) {
+                return Err(mismatch());
+            }
+            let value =
+                |bound: Option<(&RangeValue, bool)>| bound.map(|(v, i)| (v.as_f64().unwrap(), i));
+            if value_type == RangeType::Float {
+                let lower = match value(lower) {
+                    Some((v, true)) => v as f32,
+                    Some((v, false)) => FloatPoint::next_up(v as f32),
+                    None => f32::NEG_INFINITY,
+                };
+                let upper = match value(upper) {
+                    Some((v, true)) => v as f32,
+                    Some((v, false)) => FloatPoint::next_down(v as f32),
+                    None => f32::INFINITY,
+                };
+                FloatPoint::new_range_query(field.to_string(), lower, upper)
+            } else {
+                let lower = match value(lower) {
+                    Some((v, true)) => v,
+                    Some((v, false)) => DoublePoint::next_up(v),
+                    None => f64::NEG_INFINITY,
+                };
+                let upper = match value(upper) {
+                    Some((v, true)) => v,
+                    Some((v, false)) => DoublePoint::next_down(v),
+                    None => f64::INFINITY,
+                };
+                DoublePoint::new_range_query(field.to_string(), lower, upper)
+            }
+        }
+    }
+}
+
+/// Decodes a single dimension point bound, the unbounded end of its side, the
+/// min value of a lower bound or the max value of an upper one, is omitted.
+fn point_value(value_type: PointValueType, bytes: &[u8], lower: bool) -> Option<RangeValue> {
+    match value_type {
+        PointValueType::Long => Some(LongPoint::decode_dimension(bytes))
+            .filter(|v| if lower { *v > i64::MIN } else { *v < i64::MAX })
+            .map(RangeValue::Long),
+        PointValueType::Integer => Some(IntPoint::decode_dimension(bytes))
+            .filter(|v| if lower { *v > i32::MIN } else { *v < i32::MAX })
+            .map(|v| RangeValue::Long(i64::from(v))),
+        PointValueType::Double => Some(DoublePoint::decode_dimension(bytes))
+            .filter(|v| {
+                if lower {
+                    *v > f64::NEG_INFINITY
+                } else {
+                    *v < f64::INFINITY
+                }
+            })
+            .map(RangeValue::Double),
+        PointValueType::Float => Some(FloatPoint::decode_dimension(bytes))
+            .filter(|v| {
+                if lower {
+                    *v > f32::NEG_INFINITY
+                } else {
+                    *v < f32::INFINITY
+                }
+            })
+            .map(|v| RangeValue::Double(f64::from(v))),
+    }
+}
+
+fn span_query(dsl: &SpanQueryDsl) -> Result<SpanQueryEnum> {
+    let (query, boost) = match dsl {
+        SpanQueryDsl::SpanTerm(FieldQuery { field, params }) => {
+            let term = Term::new(field.clone(), params.value.as_bytes().to_vec());
+            (
+                SpanBoostQueryEnum::Term(SpanTermQuery::new(term, None)),
+                params.boost,
+            )
+        }
+        SpanQueryDsl::SpanOr(q) => {
+            let clauses = q.clauses.iter().map(span_query).collect::<Result<_>>()?;
+            (SpanBoostQueryEnum::Or(SpanOrQuery::new(clauses)?), q.boost)
+        }
+        SpanQueryDsl::SpanNear(q) => (SpanBoostQueryEnum::Near(span_near_query(q)?), q.boost),
+    };
+    if !is_default_boost(&boost) {
+        return Ok(SpanQueryEnum::Boost(SpanBoostQuery::new(query, boost)));
+    }
+    Ok(match query {
+        SpanBoostQueryEnum::Term(q) => SpanQueryEnum::Term(q),
+        SpanBoostQueryEnum::Or(q) => SpanQueryEnum::Or(q),
+        SpanBoostQueryEnum::Near(q) => SpanQueryEnum::Near(q),
+        SpanBoostQueryEnum::Gap(q) => SpanQueryEnum::Gap(q),
+    })
+}
+
+fn span_near_query(dsl: &SpanNearDsl) -> Result<SpanNearQuery> {
+    let clauses = dsl.clauses.iter().map(span_query).collect::<Result<_>>()?;
+    SpanNearQuery::new(clauses, dsl.slop, dsl.in_order)
+}
+
+fn span_term_dsl(query: &SpanTermQuery, boost: f32) -> Result<SpanQueryDsl> {
+    Ok(SpanQueryDsl::SpanTerm(FieldQuery::new(
+        query.term.field.clone(),
+        TermDsl {
+            value: query.term.text()?,
+            boost,
+        },
+    )))
+}
+
+fn span_or_dsl(query: &SpanOrQuery, boost: f32) -> Result<SpanQueryDsl> {
+    Ok(SpanQueryDsl::SpanOr(SpanOrDsl {
+        clauses: query
+            .clauses()
+            .iter()
+            .map(span_dsl)
+            .collect::<Result<_>>()?,
+        boost,
+    }))
+}
+
+fn span_near_dsl(query: &SpanNearQuery, boost: f32) -> Result<SpanNearDsl> {
+    Ok(SpanNearDsl {
+        clauses: query
+            .clauses()
+            .iter()
+            .map(span_dsl)
+            .collect::<Result<_>>()?,
+        slop: query.slop(),
+        in_order: query.in_order(),
+        boost,
+    })
+}
+
+fn span_dsl(query: &SpanQueryEnum) -> Result<SpanQueryDsl> {
+    match query {
+        SpanQueryEnum::Term(q) => span_term_dsl(q, 1.0),
+        SpanQueryEnum::Or(q) => span_or_dsl(q, 1.0),
+        SpanQueryEnum::Near(q) => Ok(SpanQueryDsl::SpanNear(span_near_dsl(q, 1.0)?)),
+        SpanQueryEnum::Boost(q) => match &q.query {
+            SpanBoostQueryEnum::Term(t) => span_term_dsl(t, q.boost()),
+            SpanBoostQueryEnum::Or(o) => span_or_dsl(o, q.boost()),
+            SpanBoostQueryEnum::Near(n) => Ok(SpanQueryDsl::SpanNear(span_near_dsl(n, q.boost())?)),
+            SpanBoostQueryEnum::Gap(_) => {
+                bail!(IllegalArgument("span gap can't be converted to dsl".into()))
+            }
+        },
+        SpanQueryEnum::Gap(_) => {
+            bail!(IllegalArgument("span gap can't be converted to dsl".into()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::codec::tests::TestCodec;
+
+    #[test]
+    fn test_query_dsl_round_trip() {
+        let json = r#"{"bool": {
+            "must": [
+                {"term": {"title": "rust"}},
+                {"match_phrase": {"body": {"query": "quick fox", "slop": 1}}},
+                {"span_near": {"clauses": [
+                    {"span_term": {"body": "quick"}},
+                    {"span_or": {"clauses": [
+                        {"span_term": {"body": "brown"}},
+                        {"span_term": {"body": {"value": "red", "boost": 2.0}}}
+                    ]}}
+                ], "slop": 2, "in_order": false}}
+            ],
+            "should": [
+                {"range": {"age": {"gte": 10, "lte": 20, "type": "integer"}}},
+                {"range": {"score": {"gte": 0.5, "type": "double"}}},
+                {"range": {"name": {"gte": "a", "lt": "m", "type": "term", "boost": 2.0}}},
+                {"exists": {"field": "tags"}},
+                {"boosting": {
+                    "positive": {"term": {"title": "apple"}},
+                    "negative": {"term": {"title": "pie"}},
+                    "negative_boost": 0.5
+                }}
+            ],
+            "filter": [{"dis_max": {"queries": [
+                {"term": {"a": "x"}},
+                {"term": {"b": {"value": "y", "boost": 3.0}}}
+            ], "tie_breaker": 0.5}}],
+            "must_not": [{"bool": {"should": [{"term": {"a": "z"}}, {"match_all": {}}]}}],
+            "boost": 2.0
+        }}"#;
+        let dsl = QueryDsl::from_json(json).unwrap();
+        match &dsl {
+            QueryDsl::Bool(b) => {
+                assert_eq!(b.must.len(), 3);
+                assert_eq!(b.should.len(), 5);
+                assert_eq!(
+                    b.must[0],
+                    QueryDsl::Term(FieldQuery::new("title", TermDsl::new("rust")))
+                );
+            }
+            _ => panic!("expected a bool query"),
+        }
+
+        let query: Box<dyn Query<TestCodec>> = dsl.build().unwrap();
+        let query_str = query.to_string();
+        assert!(query_str.starts_with("BoostQuery(query: BooleanQuery("));
+        assert!(query_str.contains("TermRangeQuery(name:[a TO m}, boost: 1)"));
+        assert!(query_str.contains("ExistsQuery(field=tags)"));
+
+        assert_eq!(QueryDsl::from_query(query.as_ref()).unwrap(), dsl);
+        let json = dsl.to_json().unwrap();
+        assert_eq!(QueryDsl::from_json(&json).unwrap(), dsl);
+        assert!(json.starts_with(r#"{"bool":{"must":[{"term":{"title":{"value":"rust"}}}"#));
+    }
+
+    #[test]
+    fn test_query_dsl_errors() {
+        let build = |json: &str| -> Result<Box<dyn Query<TestCodec>>> {
+            QueryDsl::from_json(json)?.build()
+        };
+        assert!(build(r#"{"term": {"a": "x", "b": "y"}}"#).is_err());
+        assert!(build(r#"{"unknown": {"a": "x"}}"#).is_err());
+        assert!(build(r#"{"range": {"a": {"gt": 1, "gte": 2}}}"#).is_err());
+        assert!(build(r#"{"range": {"a": {"gt": 1, "lt": "z"}}}"#).is_err());
+        // the type of numeric bounds is ambiguous
+        assert!(build(r#"{"range": {"a": {"gt": 1}}}"#).is_err());
+        assert!(build(r#"{"range": {"a": {"gt": 1.5}}}"#).is_err());
+        assert!(build(r#"{"range": {"a": {}}}"#).is_err());
+        assert!(build(r#"{"range": {"a": {"gt": "x"}}}"#).is_ok());
+        assert!(build(r#"{"range": {"a": {"type": "long"}}}"#).is_ok());
+        // the bounds must be values of the type
+        assert!(build(r#"{"range": {"a": {"gt": 1.5, "type": "long"}}}"#).is_err());
+        assert!(build(r#"{"range": {"a": {"gt": 1, "type": "term"}}}"#).is_err());
+        assert!(build(r#"{"range": {"a": {"gt": "x", "type": "double"}}}"#).is_err());
+        assert!(build(r#"{"range": {"a": {"lt": 3000000000, "type": "integer"}}}"#).is_err());
+        assert!(build(r#"{"range": {"a": {"gt": 1, "type": "short"}}}"#).is_err());
+        assert!(build(r#"{"match_phrase": {"a": " "}}"#).is_err());
+
+        let round_trip = |json: &str| {
+            let query = build(json).unwrap();
+            QueryDsl::from_query(query.as_ref())
+                .unwrap()
+                .to_json()
+                .unwrap()
+        };
+        assert_eq!(
+            round_trip(r#"{"range": {"a": {"gt": 1, "lt": 5, "type": "long"}}}"#),
+            r#"{"range":{"a":{"gte":2,"lte":4,"type":"long"}}}"#
+        );
+        assert_eq!(
+            round_trip(r#"{"range": {"a": {"gte": 1, "type": "float"}}}"#),
+            r#"{"range":{"a":{"gte":1.0,"type":"float"}}}"#
+        );
+        // no value is past an exclusive bound at the edge of the type
+        assert_eq!(
+            round_trip(r#"{"range": {"a": {"gt": 9223372036854775807, "type": "long"}}}"#),
+            r#"{"range":{"a":{"gte":9223372036854775807,"lte":-9223372036854775808,"type":"long"}}}"#
+        );
+        assert_eq!(
+            round_trip(r#"{"range": {"a": {"gte": 1, "lt": -2147483648, "type": "integer"}}}"#),
+            r#"{"range":{"a":{"gte":2147483647,"lte":-2147483648,"type":"integer"}}}"#
+        );
+        assert_eq!(
+            round_trip(r#"{"range": {"a": {"lte": -9223372036854775808, "type": "long"}}}"#),
+            r#"{"range":{"a":{"lte":-9223372036854775808,"type":"long"}}}"#
+        );
+
+        let query = PhraseQuery::new(
+            vec![
+                Term::new("a".into(), b"x".to_vec()),
+                Term::new("a".into(), b"y".to_vec()),
+            ],
+            vec![0, 2],
+            0,
+            None,
+            None,
+        )
+        .unwrap();
+        assert!(QueryDsl::from_query::<TestCodec>(&query).is_err());
+    }
+}
@@ -12,26 +12,44 @@
 // limitations under the License.
 
 use error::{ErrorKind::IllegalArgument, Result};
-use std::option::Option::{None, Some};
-use std::result::Result::Ok;
-use std::str::Chars;
-use std::vec::Vec;
+use std::cmp::{max, min};
+use std::sync::Arc;
 
+use core::analysis::Analyzer;
 use core::codec::Codec;
 use core::doc::Term;
-use core::search::query::{BooleanQuery, BoostQuery, PhraseQuery, Query, TermQuery};
+use core::search::query::spans::{SpanNearQuery, SpanOrQuery, SpanQueryEnum, SpanTermQuery};
+use core::search::query::{
+    BooleanQuery, BoostQuery, FuzzyQuery, MatchAllDocsQuery, PhraseQuery, PrefixQuery, Query,
+    RegexpQuery, TermQuery, TermRangeQuery, WildcardQuery, DEFAULT_MAX_EDITS,
+    DEFAULT_MAX_EXPANSIONS, DEFAULT_PREFIX_LENGTH, DEFAULT_TRANSPOSITIONS,
+};
+use core::util::automaton::MAXIMUM_SUPPORTED_DISTANCE;
+
+/// The boolean operator applied between clauses that are not joined by an
+/// explicit `AND` or `OR`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Or,
+    And,
+}
 
 /// A query that parses a query string and runs it.
 ///
+/// The query string follows the Lucene classic query parser syntax: `AND`/`&&`,
+/// `OR`/`||`/`|`, `NOT`/`!`, `+` and `-` modifiers, `field:` prefixes, grouping
+/// with parentheses, `"phrases"~slop`, `term~edits` fuzzy terms, `^boost`,
+/// `[lower TO upper}` ranges, `*` and `?` wildcards, `/regexp/` and `\`
+/// escaping. `*:*` matches all documents.
+///
 /// See: [`Query String Syntax`](https://www.elastic.co/guide/en/elasticsearch/reference/6.3/query-dsl-query-string-query.html#query-string-syntax) for more detail.
-/// NOTE: currently this builder only support some of the syntax than elastic search do.
 pub struct QueryStringQueryBuilder {
     query_string: String,
     fields: Vec<(String, f32)>,
-    #[allow(dead_code)]
     min_should_match: i32,
-    #[allow(dead_code)]
     boost: f32,
+    default_operator: Operator,
+    analyzer: Option<Arc<dyn Analyzer>>,
 }
 
 impl QueryStringQueryBuilder {
@@ -46,212 +64,738 @@ impl QueryStringQueryBuilder {
             fields,
             min_should_match,
             boost,
+            default_operator: Operator::Or,
+            analyzer: None,
         }
     }
 
+    pub fn with_default_operator(mut self, operator: Operator) -> Self {
+        self.default_operator = operator;
+        self
+    }
+
+    /// Analyzes terms and phrases with `analyzer`, tokens stacked at the same
+    /// position (e.g. synonyms) are matched as alternatives. Without an
+    /// analyzer a term is used as is and a phrase is split on whitespace.
+    pub fn with_analyzer(mut self, analyzer: Arc<dyn Analyzer>) -> Self {
+        self.analyzer = Some(analyzer);
+        self
+    }
+
     pub fn build<C: Codec>(&self) -> Result<Box<dyn Query<C>>> {
-        match self.parse_query(&mut self.query_string.chars(), None) {
-            Ok(Some(q)) => Ok(q),
-            Ok(None) => bail!(IllegalArgument("empty query string!".into())),
-            Err(e) => Err(e),
+        let lexemes = tokenize(&self.query_string)?;
+        let mut parser = QueryParser {
+            builder: self,
+            lexemes,
+            pos: 0,
+        };
+        match parser.parse()? {
+            Some(q) => Ok(BoostQuery::build(q, self.boost)),
+            None => bail!(IllegalArgument("empty query string!".into())),
         }
     }
+}
 
-    fn parse_query<C: Codec>(
-        &self,
-        chars: &mut Chars,
-        end_char: Option<char>,
-    ) -> Result<Option<Box<dyn Query<C>>>> {
-        let mut musts = Vec::new();
-        let mut shoulds = Vec::new();
-        let mut is_option = true;
-        while let Some(ch) = chars.next() {
-            match ch {
-                '+' => is_option = false,
-                '|' => is_option = true,
-                '(' => {
-                    if let Ok(Some(query)) = self.parse_query(chars, Some(')')) {
-                        if is_option {
-                            shoulds.push(query);
-                        } else {
-                            musts.push(query);
-                        }
-                    }
+fn syntax_error<T>(input: &str, column: usize, msg: &str) -> Result<T> {
+    bail!(IllegalArgument(format!(
+        "Cannot parse '{}': {} at column {}",
+        input, msg, column
+    )))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    /// a bare term, backslash escapes are kept so that wildcards can still
+    /// tell escaped `*` and `?` apart
+    Term {
+        text: String,
+        wildcard: bool,
+    },
+    Phrase(String),
+    Regexp(String),
+    Range {
+        lower: Option<String>,
+        upper: Option<String>,
+        include_lower: bool,
+        include_upper: bool,
+    },
+    Colon,
+    Boost(f32),
+    Fuzzy(Option<f32>),
+    Plus,
+    Minus,
+    Not,
+    And,
+    Or,
+    LParen,
+    RParen,
+}
+
+#[derive(Debug, Clone)]
+struct Lexeme {
+    kind: TokenKind,
+    /// 1-based char column of the first char of this token
+    column: usize,
+}
+
+fn is_term_char(c: char) -> bool {
+    !c.is_whitespace() && !['(', ')', ':', '^', '~', '"', '[', ']', '{', '}', '|'].contains(&c)
+}
+
+fn read_number(chars: &[char], pos: &mut usize) -> String {
+    let start = *pos;
+    while *pos < chars.len() && (chars[*pos].is_ascii_digit() || chars[*pos] == '.') {
+        *pos += 1;
+    }
+    chars[start..*pos].iter().collect()
+}
+
+/// Reads chars up to the unescaped `end`, `pos` points after the opening char.
+fn read_quoted(
+    input: &str,
+    chars: &[char],
+    pos: &mut usize,
+    end: char,
+    column: usize,
+) -> Result<String> {
+    let mut text = String::new();
+    loop {
+        match chars.get(*pos) {
+            None => {
+                return syntax_error(input, column, &format!("missing closing '{}'", end));
+            }
+            Some(&'\\') if *pos + 1 < chars.len() => {
+                let c = chars[*pos + 1];
+                // a regexp keeps its own escapes, only `\/` is ours
+                if end == '/' && c != '/' {
+                    text.push('\\');
                 }
-                '"' => {
-                    let mut term_chars = Vec::new();
-                    while let Some(ch) = chars.next() {
-                        if ch == '"' {
-                            break;
-                        }
-                        term_chars.push(ch);
-                    }
+                text.push(c);
+                *pos += 2;
+            }
+            Some(&c) if c == end => {
+                *pos += 1;
+                return Ok(text);
+            }
+            Some(&c) => {
+                text.push(c);
+                *pos += 1;
+            }
+        }
+    }
+}
 
-                    if let Some(ch) = chars.next() {
-                        if ch == '^' || ch == '~' {
-                            term_chars.push(ch);
-                            while let Some(ch) = chars.next() {
-                                if ch == ' ' {
-                                    break;
-                                }
-                                term_chars.push(ch);
-                            }
-                        }
+fn read_range(input: &str, chars: &[char], pos: &mut usize, column: usize) -> Result<TokenKind> {
+    let include_lower = chars[*pos] == '[';
+    *pos += 1;
+    // (text, quoted)
+    let mut parts: Vec<(String, bool)> = vec![];
+    let include_upper = loop {
+        match chars.get(*pos) {
+            None => return syntax_error(input, column, "missing end of range"),
+            Some(c) if c.is_whitespace() => *pos += 1,
+            Some(&']') => break true,
+            Some(&'}') => break false,
+            Some(&'"') => {
+                let start = *pos + 1;
+                *pos += 1;
+                parts.push((read_quoted(input, chars, pos, '"', start)?, true));
+            }
+            Some(_) => {
+                let mut text = String::new();
+                while let Some(&c) = chars.get(*pos) {
+                    if c.is_whitespace() || c == ']' || c == '}' {
+                        break;
                     }
-
-                    if !term_chars.is_empty() {
-                        let term: String = term_chars.iter().cloned().collect();
-                        let query = self.build_field_query(term);
-                        match query {
-                            Ok(q) => {
-                                if is_option {
-                                    shoulds.push(q);
-                                } else {
-                                    musts.push(q);
-                                }
-                            }
-                            Err(e) => {
-                                return Err(e);
-                            }
-                        }
+                    if c == '\\' && *pos + 1 < chars.len() {
+                        *pos += 1;
+                        text.push(chars[*pos]);
+                    } else {
+                        text.push(c);
                     }
-                    is_option = true;
+                    *pos += 1;
+                }
+                parts.push((text, false));
+            }
+        }
+    };
+    *pos += 1;
+    if parts.len() != 3 || parts[1] != ("TO".to_string(), false) {
+        return syntax_error(input, column, "expected '<lower> TO <upper>' in range");
+    }
+    let bound = |(text, quoted): &(String, bool)| {
+        if !quoted && text == "*" {
+            None
+        } else {
+            Some(text.clone())
+        }
+    };
+    Ok(TokenKind::Range {
+        lower: bound(&parts[0]),
+        upper: bound(&parts[2]),
+        include_lower,
+        include_upper,
+    })
+}
+
+fn tokenize(input: &str) -> Result<Vec<Lexeme>> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut lexemes = vec![];
+    let mut pos = 0;
+    while pos < chars.len() {
+        let c = chars[pos];
+        let column = pos + 1;
+        if c.is_whitespace() {
+            pos += 1;
+            continue;
+        }
+        let kind = match c {
+            '(' | ')' | ':' | '+' | '-' | '!' => {
+                pos += 1;
+                match c {
+                    '(' => TokenKind::LParen,
+                    ')' => TokenKind::RParen,
+                    ':' => TokenKind::Colon,
+                    '+' => TokenKind::Plus,
+                    '-' => TokenKind::Minus,
+                    _ => TokenKind::Not,
+                }
+            }
+            '|' => {
+                pos += if chars.get(pos + 1) == Some(&'|') {
+                    2
+                } else {
+                    1
+                };
+                TokenKind::Or
+            }
+            '&' if chars.get(pos + 1) == Some(&'&') => {
+                pos += 2;
+                TokenKind::And
+            }
+            '^' => {
+                pos += 1;
+                match read_number(&chars, &mut pos).parse::<f32>() {
+                    Ok(boost) => TokenKind::Boost(boost),
+                    Err(_) => return syntax_error(input, column, "expected a number after '^'"),
                 }
-                ' ' => is_option = true,
-                ')' => {
-                    if end_char.is_none() || end_char.unwrap() != ')' {
-                        bail!(IllegalArgument("parenthesis not match!".into()));
+            }
+            '~' => {
+                pos += 1;
+                let number = read_number(&chars, &mut pos);
+                if number.is_empty() {
+                    TokenKind::Fuzzy(None)
+                } else {
+                    match number.parse::<f32>() {
+                        Ok(n) => TokenKind::Fuzzy(Some(n)),
+                        Err(_) => return syntax_error(input, column, "invalid number after '~'"),
                     }
-                    break;
                 }
-                _ => {
-                    let mut term_chars = Vec::new();
-                    term_chars.push(ch);
-                    let mut should_return = false;
-                    while let Some(c) = chars.next() {
-                        if c == ' ' {
-                            break;
-                        }
-                        if c == ')' {
-                            if end_char.is_none() || end_char.unwrap() != ')' {
-                                bail!(IllegalArgument("parenthesis not match!".into()));
-                            }
-                            should_return = true;
-                            break;
-                        }
-                        term_chars.push(c);
+            }
+            '"' => {
+                pos += 1;
+                TokenKind::Phrase(read_quoted(input, &chars, &mut pos, '"', column)?)
+            }
+            '/' => {
+                pos += 1;
+                TokenKind::Regexp(read_quoted(input, &chars, &mut pos, '/', column)?)
+            }
+            '[' | '{' => read_range(input, &chars, &mut pos, column)?,
+            ']' | '}' => return syntax_error(input, column, &format!("unexpected '{}'", c)),
+            _ => {
+                let mut text = String::new();
+                let mut wildcard = false;
+                while pos < chars.len() && is_term_char(chars[pos]) {
+                    let c = chars[pos];
+                    if c == '&' && chars.get(pos + 1) == Some(&'&') {
+                        break;
                     }
-                    if !term_chars.is_empty() {
-                        let term: String = term_chars.iter().cloned().collect();
-                        let query_res = self.build_field_query(term);
-                        match query_res {
-                            Ok(q) => {
-                                if is_option {
-                                    shoulds.push(q);
-                                } else {
-                                    musts.push(q);
-                                }
-                            }
-                            Err(e) => {
-                                return Err(e);
-                            }
+                    if c == '\\' {
+                        if pos + 1 == chars.len() {
+                            return syntax_error(
+                                input,
+                                pos + 1,
+                                "term can not end with escape character",
+                            );
                         }
+                        text.push(c);
+                        pos += 1;
+                    } else if c == '*' || c == '?' {
+                        wildcard = true;
                     }
-                    is_option = true;
-                    if should_return {
-                        break;
-                    }
+                    text.push(chars[pos]);
+                    pos += 1;
+                }
+                match text.as_str() {
+                    "AND" => TokenKind::And,
+                    "OR" => TokenKind::Or,
+                    "NOT" => TokenKind::Not,
+                    _ => TokenKind::Term { text, wildcard },
                 }
             }
-        }
-        let query: Box<dyn Query<C>> = if musts.len() + shoulds.len() == 1 {
-            if !musts.is_empty() {
-                musts.remove(0)
-            } else {
-                shoulds.remove(0)
+        };
+        lexemes.push(Lexeme { kind, column });
+    }
+    Ok(lexemes)
+}
+
+/// Removes the backslash escapes of a term.
+fn unescape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            if let Some(escaped) = chars.next() {
+                result.push(escaped);
             }
         } else {
-            BooleanQuery::build(musts, shoulds, vec![], vec![], self.min_should_match)?
-        };
-        Ok(Some(query))
+            result.push(c);
+        }
     }
+    result
+}
 
-    fn term_query<C: Codec>(&self, term: String, field: String, boost: f32) -> Box<dyn Query<C>> {
-        Box::new(TermQuery::new(Term::new(field, term.into()), boost, None))
+/// Returns true if the only unescaped wildcard of `text` is a trailing `*`.
+fn is_prefix(text: &str) -> bool {
+    let mut escaped = false;
+    let mut wildcards = vec![];
+    for (i, c) in text.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == '*' || c == '?' {
+            wildcards.push((i, c));
+        }
+    }
+    text.len() > 1 && wildcards == [(text.len() - 1, '*')]
+}
+
+/// Converts the number after `~` into edit distance, values below 1 are the
+/// legacy minimum similarity.
+fn fuzzy_edits(similarity: Option<f32>, term_len: usize) -> usize {
+    match similarity {
+        None => DEFAULT_MAX_EDITS,
+        Some(s) if s >= 1.0 => min(s as usize, MAXIMUM_SUPPORTED_DISTANCE),
+        Some(s) => min(
+            ((1.0 - s) * term_len as f32) as usize,
+            MAXIMUM_SUPPORTED_DISTANCE,
+        ),
     }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Conjunction {
+    None,
+    And,
+    Or,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Modifier {
+    None,
+    Required,
+    Prohibited,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Occur {
+    Must,
+    Should,
+    MustNot,
+}
+
+/// A recursive-descent parser over the lexemes of a query string:
+///
+/// ```text
+/// query   := modifier? clause (conjunction? modifier? clause)*
+/// clause  := (term ':')? (term | phrase | range | regexp) ('~' number?)? ('^' number)?
+///          | (term ':')? '(' query ')' ('^' number)?
+/// ```
+struct QueryParser<'a> {
+    builder: &'a QueryStringQueryBuilder,
+    lexemes: Vec<Lexeme>,
+    pos: usize,
+}
 
-    fn build_field_query<C: Codec>(&self, term_boost: String) -> Result<Box<dyn Query<C>>> {
-        let mut queries = if term_boost.find('~').is_some() {
-            self.field_phrase_query(&term_boost)?
+impl<'a> QueryParser<'a> {
+    fn peek(&self) -> Option<&TokenKind> {
+        self.lexemes.get(self.pos).map(|l| &l.kind)
+    }
+
+    fn next(&mut self) -> Option<Lexeme> {
+        let lexeme = self.lexemes.get(self.pos).cloned();
+        if lexeme.is_some() {
+            self.pos += 1;
+        }
+        lexeme
+    }
+
+    fn end_column(&self) -> usize {
+        self.builder.query_string.chars().count() + 1
+    }
+
+    fn error<T>(&self, column: usize, msg: &str) -> Result<T> {
+        syntax_error(&self.builder.query_string, column, msg)
+    }
+
+    fn parse<C: Codec>(&mut self) -> Result<Option<Box<dyn Query<C>>>> {
+        let query = self.parse_query(None, self.builder.min_should_match)?;
+        // the top level query only stops early on a `)`
+        if let Some(lexeme) = self.lexemes.get(self.pos) {
+            return self.error(lexeme.column, "unexpected ')'");
+        }
+        Ok(query)
+    }
+
+    fn parse_query<C: Codec>(
+        &mut self,
+        field: Option<&str>,
+        min_should_match: i32,
+    ) -> Result<Option<Box<dyn Query<C>>>> {
+        let mut clauses: Vec<(Occur, Box<dyn Query<C>>)> = vec![];
+        let mut first = true;
+        loop {
+            let conjunction = match self.peek() {
+                None | Some(TokenKind::RParen) => break,
+                Some(TokenKind::And) => Conjunction::And,
+                Some(TokenKind::Or) => Conjunction::Or,
+                _ => Conjunction::None,
+            };
+            if conjunction != Conjunction::None {
+                let column = self.lexemes[self.pos].column;
+                if first {
+                    return self.error(column, "unexpected conjunction");
+                }
+                self.pos += 1;
+            }
+            let modifier = match self.peek() {
+                Some(TokenKind::Plus) => Modifier::Required,
+                Some(TokenKind::Minus) | Some(TokenKind::Not) => Modifier::Prohibited,
+                _ => Modifier::None,
+            };
+            if modifier != Modifier::None {
+                self.pos += 1;
+            }
+            let query = self.parse_clause(field)?;
+            self.add_clause(&mut clauses, conjunction, modifier, query);
+            first = false;
+        }
+
+        if clauses.is_empty() {
+            return Ok(None);
+        }
+        let mut musts = vec![];
+        let mut shoulds = vec![];
+        let mut must_nots = vec![];
+        for (occur, query) in clauses {
+            match occur {
+                Occur::Must => musts.push(query),
+                Occur::Should => shoulds.push(query),
+                Occur::MustNot => must_nots.push(query),
+            }
+        }
+        let query = BooleanQuery::build(musts, shoulds, vec![], must_nots, min_should_match)?;
+        Ok(Some(query))
+    }
+
+    /// Same as Lucene's `QueryParserBase#addClause`: an explicit conjunction
+    /// also changes the occur of the previous clause.
+    fn add_clause<C: Codec>(
+        &self,
+        clauses: &mut Vec<(Occur, Box<dyn Query<C>>)>,
+        conjunction: Conjunction,
+        modifier: Modifier,
+        query: Option<Box<dyn Query<C>>>,
+    ) {
+        let operator = self.builder.default_operator;
+        if let Some(last) = clauses.last_mut() {
+            if last.0 != Occur::MustNot {
+                if conjunction == Conjunction::And {
+                    last.0 = Occur::Must;
+                } else if operator == Operator::And && conjunction == Conjunction::Or {
+                    last.0 = Occur::Should;
+                }
+            }
+        }
+        let query = match query {
+            Some(q) => q,
+            None => return,
+        };
+        let occur = if modifier == Modifier::Prohibited {
+            Occur::MustNot
+        } else if operator == Operator::Or {
+            if modifier == Modifier::Required || conjunction == Conjunction::And {
+                Occur::Must
+            } else {
+                Occur::Should
+            }
+        } else if conjunction == Conjunction::Or {
+            Occur::Should
         } else {
-            self.field_term_query(term_boost)?
+            Occur::Must
         };
+        clauses.push((occur, query));
+    }
 
-        let res = if queries.len() == 1 {
-            queries.remove(0)
-        } else {
-            BooleanQuery::build(Vec::new(), queries, vec![], vec![], self.min_should_match)?
+    fn parse_clause<C: Codec>(&mut self, field: Option<&str>) -> Result<Option<Box<dyn Query<C>>>> {
+        let lexeme = match self.next() {
+            Some(l) => l,
+            None => return self.error(self.end_column(), "expected a clause"),
         };
-        Ok(res)
+        match lexeme.kind {
+            TokenKind::Term { ref text, .. } if self.peek() == Some(&TokenKind::Colon) => {
+                self.pos += 1;
+                let field = unescape(text);
+                match self.next() {
+                    Some(Lexeme {
+                        kind: TokenKind::LParen,
+                        ..
+                    }) => self.parse_group(Some(&field)),
+                    Some(Lexeme { kind, column }) => match kind {
+                        TokenKind::Term { .. }
+                        | TokenKind::Phrase(_)
+                        | TokenKind::Regexp(_)
+                        | TokenKind::Range { .. } => self.parse_term(Some(&field), &kind, column),
+                        _ => self.error(column, "expected a term after ':'"),
+                    },
+                    None => self.error(self.end_column(), "expected a term after ':'"),
+                }
+            }
+            TokenKind::LParen => self.parse_group(field),
+            TokenKind::Term { .. }
+            | TokenKind::Phrase(_)
+            | TokenKind::Regexp(_)
+            | TokenKind::Range { .. } => self.parse_term(field, &lexeme.kind, lexeme.column),
+            _ => self.error(lexeme.column, "expected a clause"),
+        }
     }
 
-    fn field_term_query<C: Codec>(&self, query: String) -> Result<Vec<Box<dyn Query<C>>>> {
-        let (term, boost) = if let Some(i) = query.find('^') {
-            let (t, b) = query.split_at(i as usize);
-            let boost_str: String = b.chars().skip(1).collect();
-            let boost = boost_str.parse::<f32>()?;
-            (t.to_string(), boost)
-        } else {
-            (query, 1f32)
+    fn parse_group<C: Codec>(&mut self, field: Option<&str>) -> Result<Option<Box<dyn Query<C>>>> {
+        let query = self.parse_query(field, 0)?;
+        if self.next().is_none() {
+            return self.error(self.end_column(), "missing ')'");
+        }
+        if let Some(&TokenKind::Boost(boost)) = self.peek() {
+            self.pos += 1;
+            return Ok(query.map(|q| BoostQuery::build(q, boost)));
+        }
+        Ok(query)
+    }
+
+    fn parse_term<C: Codec>(
+        &mut self,
+        field: Option<&str>,
+        kind: &TokenKind,
+        column: usize,
+    ) -> Result<Option<Box<dyn Query<C>>>> {
+        let mut fuzzy = false;
+        let mut similarity = None;
+        let mut boost = None;
+        loop {
+            match self.peek() {
+                Some(&TokenKind::Fuzzy(f)) if !fuzzy => {
+                    fuzzy = true;
+                    similarity = f;
+                }
+                Some(&TokenKind::Boost(b)) if boost.is_none() => boost = Some(b),
+                _ => break,
+            }
+            self.pos += 1;
+        }
+        let boost = boost.unwrap_or(1.0);
+
+        let fields = match field {
+            Some(f) => vec![(f.to_string(), 1.0)],
+            None => self.builder.fields.clone(),
         };
-        let term = if term.starts_with('"') {
-            term.chars().skip(1).take(term.len() - 2).collect()
-        } else {
-            term
+        if fields.is_empty() {
+            return self.error(column, "no field to search");
+        }
+        let mut queries = Vec::with_capacity(fields.len());
+        for (name, field_boost) in &fields {
+            if let Some(q) = self.term_query(name, kind, fuzzy, similarity, boost * field_boost)? {
+                queries.push(q);
+            }
+        }
+        match queries.len() {
+            0 => Ok(None),
+            1 => Ok(queries.pop()),
+            _ => Ok(Some(BooleanQuery::build(
+                vec![],
+                queries,
+                vec![],
+                vec![],
+                0,
+            )?)),
+        }
+    }
+
+    fn term_query<C: Codec>(
+        &self,
+        field: &str,
+        kind: &TokenKind,
+        fuzzy: bool,
+        similarity: Option<f32>,
+        boost: f32,
+    ) -> Result<Option<Box<dyn Query<C>>>> {
+        let term = |text: &str| Term::new(field.to_string(), text.as_bytes().to_vec());
+        let query: Box<dyn Query<C>> = match kind {
+            TokenKind::Term { text, wildcard } => {
+                if fuzzy {
+                    let text = unescape(text);
+                    let max_edits = fuzzy_edits(similarity, text.chars().count());
+                    let mut q = FuzzyQuery::with_params(
+                        term(&text),
+                        max_edits,
+                        DEFAULT_PREFIX_LENGTH,
+                        DEFAULT_MAX_EXPANSIONS,
+                        DEFAULT_TRANSPOSITIONS,
+                    )?;
+                    q.set_boost(boost);
+                    Box::new(q)
+                } else if !wildcard {
+                    return self.analyzed_query(field, &unescape(text), boost, false, 0);
+                } else if field == "*" && text == "*" {
+                    BoostQuery::build(Box::new(MatchAllDocsQuery), boost)
+                } else if is_prefix(text) {
+                    let mut q = PrefixQuery::new(term(&unescape(&text[..text.len() - 1])))?;
+                    q.set_boost(boost);
+                    Box::new(q)
+                } else {
+                    let mut q = WildcardQuery::new(term(text))?;
+                    q.set_boost(boost);
+                    Box::new(q)
+                }
+            }
+            TokenKind::Phrase(text) => {
+                let slop = similarity.map_or(0, |slop| slop as i32);
+                return self.analyzed_query(field, text, boost, true, slop);
+            }
+            TokenKind::Regexp(text) => {
+                let mut q = RegexpQuery::new(term(text))?;
+                q.set_boost(boost);
+                Box::new(q)
+            }
+            TokenKind::Range {
+                lower,
+                upper,
+                include_lower,
+                include_upper,
+            } => {
+                let mut q = TermRangeQuery::new_string_range(
+                    field.to_string(),
+                    lower.as_deref(),
+                    upper.as_deref(),
+                    *include_lower,
+                    *include_upper,
+                );
+                q.set_boost(boost);
+                Box::new(q)
+            }
+            _ => unreachable!(),
         };
-        let mut queries = Vec::new();
-        for fb in &self.fields {
-            queries.push(self.term_query(term.clone(), fb.0.clone(), fb.1 * boost));
+        Ok(Some(query))
+    }
+
+    /// Returns the terms of `text` grouped by position.
+    fn analyze(&self, field: &str, text: &str, quoted: bool) -> Result<Vec<(i32, Vec<Vec<u8>>)>> {
+        let mut positions: Vec<(i32, Vec<Vec<u8>>)> = vec![];
+        if let Some(ref analyzer) = self.builder.analyzer {
+            let mut stream = analyzer.token_stream_for_str(field, text)?;
+            stream.reset()?;
+            let mut position = -1;
+            while stream.next_token()? {
+                let token = stream.token();
+                if token.position > 0 || positions.is_empty() {
+                    position += max(token.position, 1) as i32;
+                    positions.push((position, vec![token.term.clone()]));
+                } else {
+                    positions.last_mut().unwrap().1.push(token.term.clone());
+                }
+            }
+            stream.end()?;
+        } else if quoted {
+            for (i, word) in text.split_whitespace().enumerate() {
+                positions.push((i as i32, vec![word.as_bytes().to_vec()]));
+            }
+        } else if !text.is_empty() {
+            positions.push((0, vec![text.as_bytes().to_vec()]));
         }
-        Ok(queries)
+        Ok(positions)
     }
 
-    fn field_phrase_query<C: Codec>(&self, query: &str) -> Result<Vec<Box<dyn Query<C>>>> {
-        if let Some(idx) = query.find('~') {
-            let (t, s) = query.split_at(idx);
-            let slop_str: String = s.chars().skip(1).collect();
-            let slop = slop_str.parse::<i32>()?;
-            let term_strs: Vec<&str> = t.split_whitespace().collect();
-            if term_strs.len() < 2 {
-                bail!(IllegalArgument(
-                    "phrase query terms size must not small than 2".into()
-                ));
-            }
-            let mut queries = Vec::with_capacity(self.fields.len());
-            for fb in &self.fields {
-                let terms: Vec<Term> = term_strs
-                    .iter()
-                    .map(|term| Term::new(fb.0.clone(), term.as_bytes().to_vec()))
-                    .collect();
-                queries.push(BoostQuery::build(
-                    Box::new(PhraseQuery::build(terms, slop, None, None)?),
-                    fb.1,
-                ))
+    fn analyzed_query<C: Codec>(
+        &self,
+        field: &str,
+        text: &str,
+        boost: f32,
+        quoted: bool,
+        slop: i32,
+    ) -> Result<Option<Box<dyn Query<C>>>> {
+        let positions = self.analyze(field, text, quoted)?;
+        let term = |bytes: &[u8]| Term::new(field.to_string(), bytes.to_vec());
+        // stacked terms at a single position match as alternatives
+        let synonyms = |terms: &[Vec<u8>]| -> Result<Box<dyn Query<C>>> {
+            let mut queries: Vec<Box<dyn Query<C>>> = terms
+                .iter()
+                .map(|t| -> Box<dyn Query<C>> { Box::new(TermQuery::new(term(t), boost, None)) })
+                .collect();
+            if queries.len() == 1 {
+                Ok(queries.remove(0))
+            } else {
+                BooleanQuery::build(vec![], queries, vec![], vec![], 0)
             }
+        };
 
-            Ok(queries)
-        } else {
-            bail!(IllegalArgument(format!(
-                "invalid query string '{}' for phrase query",
-                &query
-            )));
+        if positions.is_empty() {
+            return Ok(None);
+        }
+        if positions.len() == 1 {
+            return synonyms(&positions[0].1).map(Some);
         }
+        if !quoted {
+            let mut clauses = Vec::with_capacity(positions.len());
+            for (_, terms) in &positions {
+                clauses.push(synonyms(terms)?);
+            }
+            let query = match self.builder.default_operator {
+                Operator::Or => BooleanQuery::build(vec![], clauses, vec![], vec![], 0)?,
+                Operator::And => BooleanQuery::build(clauses, vec![], vec![], vec![], 0)?,
+            };
+            return Ok(Some(query));
+        }
+
+        let query: Box<dyn Query<C>> = if positions.iter().all(|(_, terms)| terms.len() == 1) {
+            let terms = positions.iter().map(|(_, t)| term(&t[0])).collect();
+            let positions = positions.iter().map(|(p, _)| *p).collect();
+            Box::new(PhraseQuery::new(terms, positions, slop, None, None)?)
+        } else {
+            let mut clauses = Vec::with_capacity(positions.len());
+            for (_, terms) in &positions {
+                let mut spans: Vec<SpanQueryEnum> = terms
+                    .iter()
+                    .map(|t| SpanQueryEnum::Term(SpanTermQuery::new(term(t), None)))
+                    .collect();
+                if spans.len() == 1 {
+                    clauses.push(spans.remove(0));
+                } else {
+                    clauses.push(SpanQueryEnum::Or(SpanOrQuery::new(spans)?));
+                }
+            }
+            Box::new(SpanNearQuery::new(clauses, slop, true)?)
+        };
+        Ok(Some(BoostQuery::build(query, boost)))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use core::analysis::{
+        CustomAnalyzer, SynonymFilter, SynonymMapBuilder, TokenStream, WhitespaceAnalyzer,
+        WhitespaceTokenizer,
+    };
     use core::codec::tests::TestCodec;
 
     #[test]
@@ -278,9 +822,9 @@ mod tests {
         assert_eq!(
             term_str,
             String::from(
-                "BooleanQuery(must: [], should: [TermQuery(field: title, term: test, boost: 0.2), \
-                 TermQuery(field: title, term: 测试, boost: 2)], filters: [], must_not: [], \
-                 match: 1)",
+                "BoostQuery(query: BooleanQuery(must: [], should: [TermQuery(field: title, term: \
+                 test, boost: 0.2), TermQuery(field: title, term: 测试, boost: 2)], filters: [], \
+                 must_not: [], match: 1), boost: 2)",
             )
         );
 
@@ -294,9 +838,9 @@ mod tests {
         assert_eq!(
             term_str,
             String::from(
-                "BooleanQuery(must: [], should: [TermQuery(field: title, term: test, boost: 0.2), \
-                 TermQuery(field: title, term: 测试, boost: 2)], filters: [], must_not: [], \
-                 match: 1)",
+                "BoostQuery(query: BooleanQuery(must: [], should: [TermQuery(field: title, term: \
+                 test, boost: 0.2), TermQuery(field: title, term: 测试, boost: 2)], filters: [], \
+                 must_not: [], match: 1), boost: 2)",
             )
         );
 
@@ -410,4 +954,146 @@ mod tests {
             )
         );
     }
+
+    fn parse(query_string: &str, operator: Operator) -> Result<String> {
+        let q: Box<dyn Query<TestCodec>> =
+            QueryStringQueryBuilder::new(query_string.into(), vec![("f".into(), 1.0)], 0, 1.0)
+                .with_default_operator(operator)
+                .build()?;
+        Ok(q.to_string())
+    }
+
+    #[test]
+    fn test_query_string_syntax() {
+        let term = |t: &str| format!("TermQuery(field: f, term: {}, boost: 1)", t);
+        let boolean = |musts: &[&str], shoulds: &[&str], must_nots: &[&str], msm: i32| {
+            format!(
+                "BooleanQuery(must: [{}], should: [{}], filters: [], must_not: [{}], match: {})",
+                musts.join(", "),
+                shoulds.join(", "),
+                must_nots.join(", "),
+                msm
+            )
+        };
+        let (a, b, c) = (term("a"), term("b"), term("c"));
+
+        assert_eq!(
+            parse("a AND b", Operator::Or).unwrap(),
+            boolean(&[&a, &b], &[], &[], 0)
+        );
+        assert_eq!(
+            parse("a && b || c", Operator::Or).unwrap(),
+            boolean(&[&a, &b], &[&c], &[], 0)
+        );
+        assert_eq!(
+            parse("a AND NOT b", Operator::Or).unwrap(),
+            boolean(&[&a], &[], &[&b], 0)
+        );
+        assert_eq!(
+            parse("a -b !c", Operator::Or).unwrap(),
+            boolean(&[], &[&a], &[&b, &c], 1)
+        );
+        assert_eq!(
+            parse("a b", Operator::And).unwrap(),
+            boolean(&[&a, &b], &[], &[], 0)
+        );
+        assert_eq!(
+            parse("a OR b", Operator::And).unwrap(),
+            boolean(&[], &[&a, &b], &[], 1)
+        );
+
+        assert_eq!(
+            parse("title:(foo bar)^3", Operator::Or).unwrap(),
+            "BoostQuery(query: BooleanQuery(must: [], should: [TermQuery(field: title, term: foo, \
+             boost: 1), TermQuery(field: title, term: bar, boost: 1)], filters: [], must_not: [], \
+             match: 1), boost: 3)"
+        );
+        assert_eq!(
+            parse("title:foo a", Operator::Or).unwrap(),
+            boolean(
+                &[],
+                &["TermQuery(field: title, term: foo, boost: 1)", &a],
+                &[],
+                1
+            )
+        );
+        assert_eq!(
+            parse("[a TO *}", Operator::Or).unwrap(),
+            "TermRangeQuery(f:[a TO *}, boost: 1)"
+        );
+        assert_eq!(
+            parse("{\"x y\" TO b]^2", Operator::Or).unwrap(),
+            "TermRangeQuery(f:{x y TO b], boost: 2)"
+        );
+        assert_eq!(
+            parse("te?t*", Operator::Or).unwrap(),
+            "WildcardQuery(field: f, term: te?t*, boost: 1)"
+        );
+        assert_eq!(
+            parse("te\\*st*", Operator::Or).unwrap(),
+            "PrefixQuery(field: f, prefix: te*st, boost: 1)"
+        );
+        assert_eq!(
+            parse("/ab+c/", Operator::Or).unwrap(),
+            "RegexpQuery(field: f, regexp: ab+c, boost: 1)"
+        );
+        assert_eq!(
+            parse("foo~1^2", Operator::Or).unwrap(),
+            "FuzzyQuery(field: f, term: foo, max_edits: 1, prefix_length: 0, boost: 2)"
+        );
+        assert_eq!(
+            parse("\"a b\"~2", Operator::Or).unwrap(),
+            "PhraseQuery(field: f, terms: [Term { field: \"f\", bytes: [97] }, Term { field: \
+             \"f\", bytes: [98] }], positions: [0, 1], slop: 2)"
+        );
+        assert_eq!(parse("*:*", Operator::Or).unwrap(), "MatchAllDocsQuery()");
+        assert_eq!(parse("a\\:b", Operator::Or).unwrap(), term("a:b"));
+    }
+
+    #[test]
+    fn test_query_string_errors() {
+        let error = |query_string: &str| parse(query_string, Operator::Or).unwrap_err().to_string();
+        assert!(error("(a b").ends_with("missing ')' at column 5"));
+        assert!(error("a b)").ends_with("unexpected ')' at column 4"));
+        assert!(error("a \"b c").ends_with("missing closing '\"' at column 3"));
+        assert!(error("a^x").ends_with("expected a number after '^' at column 2"));
+        assert!(error("a [b c]").ends_with("in range at column 3"));
+        assert!(error("AND a").ends_with("unexpected conjunction at column 1"));
+        assert!(error("a AND").ends_with("expected a clause at column 6"));
+        assert!(error("title:").ends_with("expected a term after ':' at column 7"));
+    }
+
+    #[test]
+    fn test_query_string_analyzer() {
+        let mut builder = SynonymMapBuilder::new();
+        builder
+            .parse_solr(&b"tv, television"[..], &WhitespaceAnalyzer, true)
+            .unwrap();
+        let synonyms = Arc::new(builder.build().unwrap());
+        let analyzer: Arc<dyn Analyzer> = Arc::new(
+            CustomAnalyzer::new(|reader| -> Box<dyn TokenStream> {
+                Box::new(WhitespaceTokenizer::new(reader))
+            })
+            .add_filter(move |input| -> Box<dyn TokenStream> {
+                Box::new(SynonymFilter::new(input, Arc::clone(&synonyms)))
+            }),
+        );
+        let build = |query_string: &str| {
+            let q: Box<dyn Query<TestCodec>> =
+                QueryStringQueryBuilder::new(query_string.into(), vec![("f".into(), 1.0)], 0, 1.0)
+                    .with_analyzer(Arc::clone(&analyzer))
+                    .build()
+                    .unwrap();
+            q.to_string()
+        };
+
+        assert_eq!(
+            build("tv"),
+            "BooleanQuery(must: [], should: [TermQuery(field: f, term: tv, boost: 1), \
+             TermQuery(field: f, term: television, boost: 1)], filters: [], must_not: [], match: \
+             1)"
+        );
+        assert!(build("\"big tv\"").starts_with("SpanNearQuery("));
+        assert!(build("\"big screen\"").starts_with("PhraseQuery("));
+    }
 }
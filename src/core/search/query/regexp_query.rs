@@ -0,0 +1,93 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::any::Any;
+use std::fmt;
+
+use core::codec::Codec;
+use core::doc::Term;
+use core::search::query::{AutomatonQuery, Query, RewriteMethod, TermQuery, Weight};
+use core::search::searcher::SearchPlanBuilder;
+use core::util::automaton::{RegExp, DEFAULT_MAX_DETERMINIZED_STATES};
+
+use error::Result;
+
+/// A fast regular expression query based on the `core::util::automaton` package.
+///
+/// The supported syntax is documented in `RegExp`. Note this might be slow for
+/// expressions that can match many terms, such as `.*`.
+pub struct RegexpQuery {
+    query: AutomatonQuery,
+}
+
+impl RegexpQuery {
+    pub fn new(term: Term) -> Result<RegexpQuery> {
+        Self::with_max_determinized_states(term, DEFAULT_MAX_DETERMINIZED_STATES)
+    }
+
+    pub fn with_max_determinized_states(
+        term: Term,
+        max_determinized_states: usize,
+    ) -> Result<RegexpQuery> {
+        let automaton = RegExp::new(&term.text()?)?.to_automaton(max_determinized_states)?;
+        let query = AutomatonQuery::new(term, &automaton, max_determinized_states)?;
+        Ok(RegexpQuery { query })
+    }
+
+    pub fn regexp(&self) -> &Term {
+        self.query.term()
+    }
+
+    pub fn set_rewrite_method(&mut self, rewrite_method: RewriteMethod) {
+        self.query.set_rewrite_method(rewrite_method);
+    }
+
+    pub fn set_boost(&mut self, boost: f32) {
+        self.query.set_boost(boost);
+    }
+}
+
+impl<C: Codec> Query<C> for RegexpQuery {
+    fn create_weight(
+        &self,
+        searcher: &dyn SearchPlanBuilder<C>,
+        needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        self.query.create_weight(searcher, needs_scores)
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        vec![]
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn rewrite(&self, searcher: &dyn SearchPlanBuilder<C>) -> Result<Option<Box<dyn Query<C>>>> {
+        Query::<C>::rewrite(&self.query, searcher)
+    }
+}
+
+impl fmt::Display for RegexpQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let term = self.query.term();
+        write!(
+            f,
+            "RegexpQuery(field: {}, regexp: {}, boost: {})",
+            &term.field,
+            String::from_utf8_lossy(&term.bytes),
+            self.query.boost()
+        )
+    }
+}
@@ -0,0 +1,449 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::any::Any;
+use std::fmt;
+use std::sync::Arc;
+
+use core::codec::Codec;
+use core::index::reader::LeafReaderContext;
+use core::search::explanation::Explanation;
+use core::search::expression::{Binding, Expression, LeafExpression};
+use core::search::query::{Query, TermQuery, Weight};
+use core::search::scorer::Scorer;
+use core::search::searcher::SearchPlanBuilder;
+use core::search::DocIterator;
+use core::util::{DocId, IndexedContext};
+
+use error::{ErrorKind::IllegalArgument, Result};
+
+const SCRIPT_SCORE_QUERY: &str = "script_score";
+
+/// A query that scores the docs matched by a query with an `Expression`.
+///
+/// The `_score` variable of the expression reads the score of the wrapped
+/// query, and its feature variables the `score_context()` of the scorer of
+/// the wrapped query, which must then implement it. The expression must not
+/// produce negative or NaN scores.
+pub struct ScriptScoreQuery<C: Codec> {
+    query: Arc<dyn Query<C>>,
+    expression: Arc<Expression>,
+}
+
+impl<C: Codec> ScriptScoreQuery<C> {
+    pub fn new(query: Box<dyn Query<C>>, expression: Expression) -> ScriptScoreQuery<C> {
+        ScriptScoreQuery {
+            query: Arc::from(query),
+            expression: Arc::new(expression),
+        }
+    }
+
+    pub fn query(&self) -> &dyn Query<C> {
+        self.query.as_ref()
+    }
+
+    pub fn expression(&self) -> &Expression {
+        &self.expression
+    }
+}
+
+impl<C: Codec> Query<C> for ScriptScoreQuery<C> {
+    fn create_weight(
+        &self,
+        searcher: &dyn SearchPlanBuilder<C>,
+        needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        let query_needs_scores = needs_scores && self.expression.needs_scores();
+        let weight = self.query.create_weight(searcher, query_needs_scores)?;
+        Ok(Box::new(ScriptScoreWeight {
+            weight,
+            expression: Arc::clone(&self.expression),
+            needs_scores,
+        }))
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        self.query.extract_terms()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn rewrite(&self, searcher: &dyn SearchPlanBuilder<C>) -> Result<Option<Box<dyn Query<C>>>> {
+        match self.query.rewrite(searcher)? {
+            Some(query) => Ok(Some(Box::new(ScriptScoreQuery {
+                query: Arc::from(query),
+                expression: Arc::clone(&self.expression),
+            }))),
+            None => Ok(None),
+        }
+    }
+}
+
+impl<C: Codec> fmt::Display for ScriptScoreQuery<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "ScriptScoreQuery(query: {}, script: {})",
+            &self.query, &self.expression
+        )
+    }
+}
+
+struct ScriptScoreWeight<C: Codec> {
+    weight: Box<dyn Weight<C>>,
+    expression: Arc<Expression>,
+    needs_scores: bool,
+}
+
+impl<C: Codec> Weight<C> for ScriptScoreWeight<C> {
+    fn create_scorer(
+        &self,
+        leaf_reader: &LeafReaderContext<'_, C>,
+    ) -> Result<Option<Box<dyn Scorer>>> {
+        if let Some(scorer) = self.weight.create_scorer(leaf_reader)? {
+            let expression = if self.needs_scores {
+                Some(LeafExpression::new(
+                    Arc::clone(&self.expression),
+                    leaf_reader,
+                )?)
+            } else {
+                None
+            };
+            Ok(Some(Box::new(ScriptScoreScorer { scorer, expression })))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn query_type(&self) -> &'static str {
+        SCRIPT_SCORE_QUERY
+    }
+
+    fn normalize(&mut self, norm: f32, boost: f32) {
+        self.weight.normalize(norm, boost)
+    }
+
+    fn value_for_normalization(&self) -> f32 {
+        self.weight.value_for_normalization()
+    }
+
+    fn needs_scores(&self) -> bool {
+        self.needs_scores
+    }
+
+    fn explain(&self, reader: &LeafReaderContext<'_, C>, doc: DocId) -> Result<Explanation> {
+        let query_explanation = self.weight.explain(reader, doc)?;
+        if !query_explanation.is_match() {
+            return Ok(query_explanation);
+        }
+
+        let mut features = IndexedContext::default();
+        if self.expression.needs_features() {
+            if let Some(mut scorer) = self.weight.create_scorer(reader)? {
+                if scorer.advance(doc)? == doc {
+                    features = scorer.score_context()?;
+                }
+            }
+        }
+        let mut expression = LeafExpression::new(Arc::clone(&self.expression), reader)?;
+        let score = expression.evaluate(doc, query_explanation.value(), &features)?;
+        // the variables are distinct, so `_score` is explained at most once
+        let mut query_explanation = Some(query_explanation);
+        let mut details = vec![];
+        for ((name, binding), value) in self.expression.variables().iter().zip(expression.values())
+        {
+            if *binding == Binding::Score {
+                details.extend(query_explanation.take());
+            } else {
+                details.push(Explanation::new(
+                    true,
+                    *value as f32,
+                    format!("{} = {}", name, binding),
+                    vec![],
+                ));
+            }
+        }
+        Ok(Explanation::new(
+            true,
+            score as f32,
+            format!(
+                "script score function, computed with script: [{}]",
+                self.expression
+            ),
+            details,
+        ))
+    }
+}
+
+impl<C: Codec> fmt::Display for ScriptScoreWeight<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "ScriptScoreWeight(weight: {}, script: {})",
+            &self.weight, &self.expression
+        )
+    }
+}
+
+struct ScriptScoreScorer {
+    scorer: Box<dyn Scorer>,
+    // not set if the scores are not needed
+    expression: Option<LeafExpression>,
+}
+
+impl Scorer for ScriptScoreScorer {
+    fn score(&mut self) -> Result<f32> {
+        let expression = match &mut self.expression {
+            Some(expression) => expression,
+            None => return Ok(0.0),
+        };
+        let doc = self.scorer.doc_id();
+        let score = if expression.expression().needs_scores() {
+            self.scorer.score()?
+        } else {
+            0.0
+        };
+        let score = if expression.expression().needs_features() {
+            let features = self.scorer.score_context()?;
+            expression.evaluate(doc, score, &features)?
+        } else {
+            expression.evaluate(doc, score, &IndexedContext::default())?
+        };
+        if score.is_nan() || score < 0.0 {
+            bail!(IllegalArgument(format!(
+                "script [{}] produced an invalid score {} for doc {}, the scores must be \
+                 non-negative numbers",
+                expression.expression(),
+                score,
+                doc
+            )));
+        }
+        Ok(score as f32)
+    }
+
+    fn score_context(&mut self) -> Result<IndexedContext> {
+        self.scorer.score_context()
+    }
+}
+
+impl DocIterator for ScriptScoreScorer {
+    fn doc_id(&self) -> DocId {
+        self.scorer.doc_id()
+    }
+
+    fn next(&mut self) -> Result<DocId> {
+        self.scorer.next()
+    }
+
+    fn advance(&mut self, target: DocId) -> Result<DocId> {
+        self.scorer.advance(target)
+    }
+
+    fn cost(&self) -> usize {
+        self.scorer.cost()
+    }
+
+    fn matches(&mut self) -> Result<bool> {
+        self.scorer.matches()
+    }
+
+    fn match_cost(&self) -> f32 {
+        self.scorer.match_cost()
+    }
+
+    fn support_two_phase(&self) -> bool {
+        self.scorer.support_two_phase()
+    }
+
+    fn approximate_next(&mut self) -> Result<DocId> {
+        self.scorer.approximate_next()
+    }
+
+    fn approximate_advance(&mut self, target: DocId) -> Result<DocId> {
+        self.scorer.approximate_advance(target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate tempfile;
+
+    use super::*;
+    use core::codec::CodecEnum;
+    use core::doc::{
+        DoubleDocValuesField, Fieldable, NumericDocValuesField, SortedNumericDocValuesField,
+    };
+    use core::index::writer::{IndexWriter, IndexWriterConfig};
+    use core::search::collector::{TopDocsCollector, TopFieldCollector};
+    use core::search::expression::Bindings;
+    use core::search::facet::NumericFieldType;
+    use core::search::query::{ConstantScoreQuery, MatchAllDocsQuery};
+    use core::search::sort_field::{
+        ComparatorValue, ExpressionComparator, ExpressionSortField, FieldComparator, ScoreDocHit,
+        Sort, SortField,
+    };
+    use core::search::{DefaultIndexSearcher, IndexSearcher};
+    use core::store::directory::FSDirectory;
+    use core::util::{double2sortable_long, VariantValue};
+
+    use std::collections::HashMap;
+
+    // a bare `MatchAllDocsQuery` scores 0 as the weights aren't normalized
+    fn match_all() -> Box<dyn Query<CodecEnum>> {
+        Box::new(ConstantScoreQuery::with_boost(
+            Box::new(MatchAllDocsQuery),
+            1.0,
+        ))
+    }
+
+    fn assert_close(actual: f32, expected: f64) {
+        assert!(
+            (f64::from(actual) - expected).abs() < 1e-5,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    fn sorted<S: IndexSearcher<CodecEnum>>(
+        searcher: &S,
+        field: ExpressionSortField,
+    ) -> Vec<(DocId, VariantValue)> {
+        let sort = Sort::new(vec![SortField::Custom(field)]);
+        let mut collector = TopFieldCollector::new(sort, 10, false).unwrap();
+        searcher.search(&MatchAllDocsQuery, &mut collector).unwrap();
+        collector
+            .top_docs()
+            .score_docs()
+            .iter()
+            .map(|hit| match hit {
+                ScoreDocHit::Field(field_doc) => (field_doc.doc, field_doc.fields[0].clone()),
+                ScoreDocHit::Score(_) => panic!("expected a field doc"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_script_score_query() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let directory = Arc::new(FSDirectory::with_path(temp_dir.path()).unwrap());
+        let writer = IndexWriter::new(directory, Arc::new(IndexWriterConfig::default())).unwrap();
+
+        // the last doc has no popularity
+        for i in 0..6i64 {
+            let mut doc: Vec<Box<dyn Fieldable>> = vec![Box::new(DoubleDocValuesField::new(
+                "rating",
+                i as f64 / 2.0,
+            ))];
+            if i < 5 {
+                doc.push(Box::new(NumericDocValuesField::new("popularity", i * 10)));
+            }
+            for &offset in &[i as f64 - 2.5, 5.0 - i as f64] {
+                doc.push(Box::new(SortedNumericDocValuesField::new(
+                    "offsets",
+                    double2sortable_long(offset),
+                )));
+            }
+            writer.add_document(doc).unwrap();
+            if i == 2 {
+                writer.commit().unwrap();
+            }
+        }
+        writer.commit().unwrap();
+
+        let reader = writer.get_reader(true, false).unwrap();
+        let searcher = DefaultIndexSearcher::new(Arc::new(reader), None);
+        let mut bindings = Bindings::default();
+        bindings.add_field("popularity", NumericFieldType::Long);
+        bindings.add_field("rating", NumericFieldType::Double);
+        bindings.add_field("offsets", NumericFieldType::Double);
+        bindings.add_feature("ctr".into(), 0);
+        let script = |source: &str| -> ScriptScoreQuery<CodecEnum> {
+            ScriptScoreQuery::new(match_all(), Expression::compile(source, &bindings).unwrap())
+        };
+
+        let query = script("_score * ln(2 + popularity) + (rating > 1 ? 1 : 0)");
+        let mut collector = TopDocsCollector::new(10);
+        searcher.search(&query, &mut collector).unwrap();
+        let hits: HashMap<DocId, f32> = collector
+            .top_docs()
+            .score_docs()
+            .iter()
+            .map(|hit| (hit.doc_id(), hit.score()))
+            .collect();
+        assert_eq!(hits.len(), 6);
+        for i in 0..6 {
+            let popularity = if i < 5 { f64::from(i * 10) } else { 0.0 };
+            let bonus = if i > 2 { 1.0 } else { 0.0 };
+            let expected = (2.0 + popularity).ln() + bonus;
+            assert_close(hits[&i], expected);
+            let explanation = searcher.explain(&query, i).unwrap();
+            assert_close(explanation.value(), expected);
+            assert_eq!(explanation.details().len(), 3);
+        }
+
+        // a multi-valued double field reads its smallest value
+        let query = script("offsets + 3");
+        let mut collector = TopDocsCollector::new(10);
+        searcher.search(&query, &mut collector).unwrap();
+        for hit in collector.top_docs().score_docs() {
+            let i = f64::from(hit.doc_id());
+            assert_close(hit.score(), (i - 2.5).min(5.0 - i) + 3.0);
+        }
+
+        // the scores can't be negative
+        let query = script("rating - 1");
+        let mut collector = TopDocsCollector::new(10);
+        assert!(searcher.search(&query, &mut collector).is_err());
+
+        // popularity % 30 + rating is 0, 10.5, 21, 1.5, 12 and 2.5
+        let field = |source: &str, reverse: bool| {
+            let expression = Expression::compile(source, &bindings).unwrap();
+            ExpressionSortField::new(expression, reverse).unwrap()
+        };
+        let hits = sorted(&searcher, field("popularity % 30 + rating", false));
+        assert_eq!(
+            hits,
+            vec![
+                (0, VariantValue::Double(0.0)),
+                (3, VariantValue::Double(1.5)),
+                (5, VariantValue::Double(2.5)),
+                (1, VariantValue::Double(10.5)),
+                (4, VariantValue::Double(12.0)),
+                (2, VariantValue::Double(21.0)),
+            ]
+        );
+        let docs: Vec<DocId> = sorted(&searcher, field("popularity % 30 + rating", true))
+            .into_iter()
+            .map(|(doc, _)| doc)
+            .collect();
+        assert_eq!(docs, vec![2, 4, 1, 5, 3, 0]);
+
+        // the scores are collected for the expressions that read them
+        let docs: Vec<DocId> = sorted(&searcher, field("2 * _score - rating", false))
+            .into_iter()
+            .map(|(doc, _)| doc)
+            .collect();
+        assert_eq!(docs, vec![5, 4, 3, 2, 1, 0]);
+
+        let expression = Expression::compile("ctr * rating", &bindings).unwrap();
+        assert!(ExpressionSortField::new(expression, false).is_err());
+
+        // a comparator must be bound to a leaf
+        let expression = Expression::compile("rating", &bindings).unwrap();
+        let mut comparator = ExpressionComparator::new(1, Arc::new(expression));
+        assert!(comparator.copy(0, ComparatorValue::Doc(0)).is_err());
+    }
+}
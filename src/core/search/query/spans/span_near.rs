@@ -123,6 +123,18 @@ impl SpanNearQuery {
         })
     }
 
+    pub fn clauses(&self) -> &[SpanQueryEnum] {
+        &self.clauses
+    }
+
+    pub fn slop(&self) -> i32 {
+        self.slop
+    }
+
+    pub fn in_order(&self) -> bool {
+        self.in_order
+    }
+
     fn merge_idf_ctx(
         ctx1: Option<KeyedContext>,
         ctx2: Option<KeyedContext>,
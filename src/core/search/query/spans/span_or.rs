@@ -62,6 +62,10 @@ impl SpanOrQuery {
         Ok(SpanOrQuery { field, clauses })
     }
 
+    pub fn clauses(&self) -> &[SpanQueryEnum] {
+        &self.clauses
+    }
+
     fn span_or_weight<C: Codec>(
         &self,
         searcher: &dyn SearchPlanBuilder<C>,
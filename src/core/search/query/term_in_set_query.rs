@@ -0,0 +1,233 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::any::Any;
+use std::fmt;
+use std::sync::Arc;
+
+use core::codec::{Codec, PostingIteratorFlags, TermIterator, Terms};
+use core::index::reader::LeafReaderContext;
+use core::search::query::constant_score_docs::{ConstantScoreDocs, ConstantScoreDocsWeight};
+use core::search::query::{Query, TermQuery, Weight, MULTI_TERM};
+use core::search::scorer::{ConstantScoreScorer, Scorer};
+use core::search::searcher::SearchPlanBuilder;
+use core::search::{DocIdSet, DocIterator};
+use core::util::DocIdSetBuilder;
+
+use error::Result;
+
+/// A `Query` that matches documents containing any of the given terms of a
+/// field, with a constant score equal to the query boost.
+///
+/// This is much faster than a `BooleanQuery` of `TermQuery`s when filtering by
+/// many terms, e.g. thousands of ids: the terms are sorted once, each leaf's
+/// terms dictionary is walked once in that order, and the matching documents
+/// are collected into a single doc id set.
+pub struct TermInSetQuery {
+    field: String,
+    terms: Arc<[Vec<u8>]>,
+    boost: f32,
+}
+
+impl TermInSetQuery {
+    pub fn new(field: String, mut terms: Vec<Vec<u8>>) -> TermInSetQuery {
+        terms.sort();
+        terms.dedup();
+        TermInSetQuery {
+            field,
+            terms: Arc::from(terms),
+            boost: 1f32,
+        }
+    }
+
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    /// The terms of the query, sorted and without duplicates.
+    pub fn terms(&self) -> &[Vec<u8>] {
+        &self.terms
+    }
+
+    pub fn set_boost(&mut self, boost: f32) {
+        self.boost = boost;
+    }
+}
+
+impl<C: Codec> Query<C> for TermInSetQuery {
+    fn create_weight(
+        &self,
+        _searcher: &dyn SearchPlanBuilder<C>,
+        _needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        let docs = TermInSetDocs {
+            field: self.field.clone(),
+            terms: Arc::clone(&self.terms),
+        };
+        Ok(Box::new(ConstantScoreDocsWeight::new(
+            docs, MULTI_TERM, self.boost,
+        )))
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        vec![]
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// The number of terms that are displayed before the others are elided.
+const MAX_DISPLAYED_TERMS: usize = 5;
+
+fn fmt_terms(f: &mut fmt::Formatter, terms: &[Vec<u8>]) -> fmt::Result {
+    write!(f, "[")?;
+    for (i, term) in terms.iter().take(MAX_DISPLAYED_TERMS).enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}", String::from_utf8_lossy(term))?;
+    }
+    if terms.len() > MAX_DISPLAYED_TERMS {
+        write!(f, ", ... ({} terms)", terms.len())?;
+    }
+    write!(f, "]")
+}
+
+impl fmt::Display for TermInSetQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "TermInSetQuery(field: {}, terms: ", &self.field)?;
+        fmt_terms(f, &self.terms)?;
+        write!(f, ", boost: {})", self.boost)
+    }
+}
+
+struct TermInSetDocs {
+    field: String,
+    terms: Arc<[Vec<u8>]>,
+}
+
+impl<C: Codec> ConstantScoreDocs<C> for TermInSetDocs {
+    fn scorer(
+        &self,
+        leaf_reader: &LeafReaderContext<'_, C>,
+        score: f32,
+    ) -> Result<Option<Box<dyn Scorer>>> {
+        let terms = match leaf_reader.reader.terms(&self.field)? {
+            Some(terms) => terms,
+            None => return Ok(None),
+        };
+
+        let mut builder = DocIdSetBuilder::from_terms(leaf_reader.reader.max_doc(), &terms)?;
+        let mut iter = terms.iterator()?;
+        let mut num_terms = 0;
+        for term in self.terms.iter() {
+            if iter.seek_exact(term)? {
+                let mut postings = iter.postings_with_flags(PostingIteratorFlags::NONE)?;
+                builder.add(&mut postings)?;
+                num_terms += 1;
+            }
+        }
+        if num_terms == 0 {
+            return Ok(None);
+        }
+
+        if let Some(iterator) = builder.build().iterator()? {
+            let cost = iterator.cost();
+            Ok(Some(Box::new(ConstantScoreScorer::new(
+                score, iterator, cost,
+            ))))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl fmt::Display for TermInSetDocs {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "TermInSetDocs(field: {}, terms: ", &self.field)?;
+        fmt_terms(f, &self.terms)?;
+        write!(f, ")")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate tempfile;
+
+    use super::*;
+    use core::doc::{Field, FieldType, Fieldable, IndexOptions};
+    use core::index::writer::{IndexWriter, IndexWriterConfig};
+    use core::search::collector::TopDocsCollector;
+    use core::search::{DefaultIndexSearcher, IndexSearcher};
+    use core::store::directory::FSDirectory;
+    use core::util::VariantValue;
+
+    #[test]
+    fn test_term_in_set_query() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let directory = Arc::new(FSDirectory::with_path(temp_dir.path()).unwrap());
+        let writer = IndexWriter::new(directory, Arc::new(IndexWriterConfig::default())).unwrap();
+
+        let mut field_type = FieldType::default();
+        field_type.index_options = IndexOptions::Docs;
+        field_type.tokenized = false;
+        for i in 0..3000 {
+            let doc: Vec<Box<dyn Fieldable>> = vec![Box::new(Field::new(
+                "id".into(),
+                field_type.clone(),
+                Some(VariantValue::VString(format!("id{}", i))),
+                None,
+            ))];
+            writer.add_document(doc).unwrap();
+            if i == 1500 {
+                writer.commit().unwrap();
+            }
+        }
+        writer.commit().unwrap();
+
+        let reader = writer.get_reader(true, false).unwrap();
+        let searcher = DefaultIndexSearcher::new(Arc::new(reader), None);
+
+        // every 7th id, in reverse order, with duplicates and missing ids
+        let mut ids: Vec<Vec<u8>> = (0..5000)
+            .rev()
+            .step_by(7)
+            .map(|i| format!("id{}", i).into_bytes())
+            .collect();
+        ids.extend_from_slice(&ids[..100].to_vec());
+        let mut query = TermInSetQuery::new("id".into(), ids);
+        assert_eq!(query.terms().len(), 715);
+        query.set_boost(2.0);
+        assert_eq!(
+            query.to_string(),
+            "TermInSetQuery(field: id, terms: [id1, id1002, id1009, id1016, id1023, ... (715 \
+             terms)], boost: 2)"
+        );
+
+        let mut collector = TopDocsCollector::new(1000);
+        searcher.search(&query, &mut collector).unwrap();
+        let top_docs = collector.top_docs();
+        assert_eq!(top_docs.total_hits(), 429);
+        for hit in top_docs.score_docs() {
+            assert_eq!(hit.doc_id() % 7, 4999 % 7);
+            assert!((hit.score() - 2.0).abs() < std::f32::EPSILON);
+        }
+
+        let query = TermInSetQuery::new("id".into(), vec![b"missing".to_vec()]);
+        let mut collector = TopDocsCollector::new(10);
+        searcher.search(&query, &mut collector).unwrap();
+        assert_eq!(collector.top_docs().total_hits(), 0);
+    }
+}
@@ -40,12 +40,35 @@ pub struct TermQuery {
     pub term: Term,
     pub boost: f32,
     pub ctx: Option<KeyedContext>,
+    /// Overrides the `(doc_freq, total_term_freq)` statistics of the term
+    /// used for scoring, e.g. when a rewrite blends the statistics of
+    /// several terms.
+    pub stats: Option<(i64, i64)>,
 }
 
 impl TermQuery {
     pub fn new<T: Into<Option<KeyedContext>>>(term: Term, boost: f32, ctx: T) -> TermQuery {
         let ctx = ctx.into();
-        TermQuery { term, boost, ctx }
+        TermQuery {
+            term,
+            boost,
+            ctx,
+            stats: None,
+        }
+    }
+
+    /// Creates a `TermQuery` scored with the given term statistics instead
+    /// of the ones from the index.
+    pub fn with_statistics<T: Into<Option<KeyedContext>>>(
+        term: Term,
+        boost: f32,
+        ctx: T,
+        doc_freq: i64,
+        total_term_freq: i64,
+    ) -> TermQuery {
+        let mut query = TermQuery::new(term, boost, ctx);
+        query.stats = Some((doc_freq, total_term_freq));
+        query
     }
 
     #[inline]
@@ -62,10 +85,16 @@ impl<C: Codec> Query<C> for TermQuery {
     ) -> Result<Box<dyn Weight<C>>> {
         let max_doc = searcher.max_doc() as i64;
 
-        let term_stats = if needs_scores {
-            vec![searcher.term_statistics(&self.term)?]
-        } else {
+        let term_stats = if !needs_scores {
             vec![TermStatistics::new(self.term.bytes.clone(), max_doc, -1)]
+        } else if let Some((doc_freq, total_term_freq)) = self.stats {
+            vec![TermStatistics::new(
+                self.term.bytes.clone(),
+                doc_freq,
+                total_term_freq,
+            )]
+        } else {
+            vec![searcher.term_statistics(&self.term)?]
         };
 
         let collection_stats = if needs_scores {
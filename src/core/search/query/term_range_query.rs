@@ -0,0 +1,575 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::any::Any;
+use std::cmp::Ordering;
+use std::fmt;
+
+use core::codec::doc_values::SortedDocValues;
+use core::codec::{Codec, PostingIteratorFlags, SeekStatus, TermIterator, Terms};
+use core::doc::DocValuesType;
+use core::index::reader::LeafReaderContext;
+use core::search::query::constant_score_docs::{ConstantScoreDocs, ConstantScoreDocsWeight};
+use core::search::query::{Query, TermQuery, Weight, MULTI_TERM};
+use core::search::scorer::{ConstantScoreScorer, Scorer};
+use core::search::searcher::SearchPlanBuilder;
+use core::search::{DocIdSet, DocIterator, NO_MORE_DOCS};
+use core::util::{DocId, DocIdSetBuilder};
+
+use error::{ErrorKind::IllegalArgument, Result};
+
+pub const DOC_VALUES_TERM_RANGE: &str = "doc_values_term_range";
+
+/// A range of terms, used by both `TermRangeQuery` and `DocValuesTermRangeQuery`.
+#[derive(Clone, Debug)]
+struct TermRange {
+    field: String,
+    lower: Option<Vec<u8>>,
+    upper: Option<Vec<u8>>,
+    include_lower: bool,
+    include_upper: bool,
+}
+
+impl TermRange {
+    fn below_upper(&self, term: &[u8]) -> bool {
+        match self.upper {
+            Some(ref upper) => match term.cmp(upper) {
+                Ordering::Less => true,
+                Ordering::Equal => self.include_upper,
+                Ordering::Greater => false,
+            },
+            None => true,
+        }
+    }
+}
+
+impl fmt::Display for TermRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let bound = |term: &Option<Vec<u8>>| match term {
+            Some(term) => String::from_utf8_lossy(term).into_owned(),
+            None => "*".to_string(),
+        };
+        write!(
+            f,
+            "{}:{}{} TO {}{}",
+            &self.field,
+            if self.include_lower { '[' } else { '{' },
+            bound(&self.lower),
+            bound(&self.upper),
+            if self.include_upper { ']' } else { '}' }
+        )
+    }
+}
+
+/// A `Query` that matches documents within a range of terms, e.g. `id:[a TO m}`.
+///
+/// The terms are compared by their bytes; either bound may be `None` for an
+/// open-ended range. Matching documents get a constant score equal to the
+/// query boost.
+pub struct TermRangeQuery {
+    range: TermRange,
+    boost: f32,
+}
+
+impl TermRangeQuery {
+    pub fn new(
+        field: String,
+        lower: Option<Vec<u8>>,
+        upper: Option<Vec<u8>>,
+        include_lower: bool,
+        include_upper: bool,
+    ) -> TermRangeQuery {
+        TermRangeQuery {
+            range: TermRange {
+                field,
+                lower,
+                upper,
+                include_lower,
+                include_upper,
+            },
+            boost: 1f32,
+        }
+    }
+
+    pub fn new_string_range(
+        field: String,
+        lower: Option<&str>,
+        upper: Option<&str>,
+        include_lower: bool,
+        include_upper: bool,
+    ) -> TermRangeQuery {
+        TermRangeQuery::new(
+            field,
+            lower.map(|s| s.as_bytes().to_vec()),
+            upper.map(|s| s.as_bytes().to_vec()),
+            include_lower,
+            include_upper,
+        )
+    }
+
+    pub fn field(&self) -> &str {
+        &self.range.field
+    }
+
+    pub fn lower(&self) -> Option<&[u8]> {
+        self.range.lower.as_deref()
+    }
+
+    pub fn upper(&self) -> Option<&[u8]> {
+        self.range.upper.as_deref()
+    }
+
+    pub fn include_lower(&self) -> bool {
+        self.range.include_lower
+    }
+
+    pub fn include_upper(&self) -> bool {
+        self.range.include_upper
+    }
+
+    pub fn boost(&self) -> f32 {
+        self.boost
+    }
+
+    pub fn set_boost(&mut self, boost: f32) {
+        self.boost = boost;
+    }
+}
+
+impl<C: Codec> Query<C> for TermRangeQuery {
+    fn create_weight(
+        &self,
+        _searcher: &dyn SearchPlanBuilder<C>,
+        _needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        let docs = TermRangeDocs {
+            range: self.range.clone(),
+        };
+        Ok(Box::new(ConstantScoreDocsWeight::new(
+            docs, MULTI_TERM, self.boost,
+        )))
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        vec![]
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl fmt::Display for TermRangeQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "TermRangeQuery({}, boost: {})", &self.range, self.boost)
+    }
+}
+
+/// A `Query` that matches documents within a range of terms, using the
+/// `SortedDocValues` of the field instead of its terms dictionary.
+///
+/// The bounds are translated to ordinals once per segment, and documents are
+/// then checked one by one. This is slow on its own, but fast when used as a
+/// filter in a conjunction with a selective query, since only the documents
+/// the other clauses match are checked.
+pub struct DocValuesTermRangeQuery {
+    range: TermRange,
+    boost: f32,
+}
+
+impl DocValuesTermRangeQuery {
+    pub fn new(
+        field: String,
+        lower: Option<Vec<u8>>,
+        upper: Option<Vec<u8>>,
+        include_lower: bool,
+        include_upper: bool,
+    ) -> DocValuesTermRangeQuery {
+        DocValuesTermRangeQuery {
+            range: TermRange {
+                field,
+                lower,
+                upper,
+                include_lower,
+                include_upper,
+            },
+            boost: 1f32,
+        }
+    }
+
+    pub fn set_boost(&mut self, boost: f32) {
+        self.boost = boost;
+    }
+}
+
+impl<C: Codec> Query<C> for DocValuesTermRangeQuery {
+    fn create_weight(
+        &self,
+        _searcher: &dyn SearchPlanBuilder<C>,
+        _needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        let docs = DocValuesTermRangeDocs {
+            range: self.range.clone(),
+        };
+        Ok(Box::new(ConstantScoreDocsWeight::new(
+            docs,
+            DOC_VALUES_TERM_RANGE,
+            self.boost,
+        )))
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        vec![]
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl fmt::Display for DocValuesTermRangeQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "DocValuesTermRangeQuery({}, boost: {})",
+            &self.range, self.boost
+        )
+    }
+}
+
+/// The docs of the terms within a range, read from the terms dictionary.
+struct TermRangeDocs {
+    range: TermRange,
+}
+
+impl<C: Codec> ConstantScoreDocs<C> for TermRangeDocs {
+    fn scorer(
+        &self,
+        leaf_reader: &LeafReaderContext<'_, C>,
+        score: f32,
+    ) -> Result<Option<Box<dyn Scorer>>> {
+        let terms = match leaf_reader.reader.terms(&self.range.field)? {
+            Some(terms) => terms,
+            None => return Ok(None),
+        };
+
+        let mut iter = terms.iterator()?;
+        let mut has_term = match self.range.lower {
+            Some(ref lower) => match iter.seek_ceil(lower)? {
+                SeekStatus::End => false,
+                SeekStatus::Found if !self.range.include_lower => iter.next()?.is_some(),
+                _ => true,
+            },
+            None => iter.next()?.is_some(),
+        };
+
+        let mut builder = DocIdSetBuilder::from_terms(leaf_reader.reader.max_doc(), &terms)?;
+        let mut num_terms = 0;
+        while has_term && self.range.below_upper(iter.term()?) {
+            let mut postings = iter.postings_with_flags(PostingIteratorFlags::NONE)?;
+            builder.add(&mut postings)?;
+            num_terms += 1;
+            has_term = iter.next()?.is_some();
+        }
+        if num_terms == 0 {
+            return Ok(None);
+        }
+
+        if let Some(iterator) = builder.build().iterator()? {
+            let cost = iterator.cost();
+            Ok(Some(Box::new(ConstantScoreScorer::new(
+                score, iterator, cost,
+            ))))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl fmt::Display for TermRangeDocs {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "TermRangeDocs({})", &self.range)
+    }
+}
+
+/// The docs whose sorted doc values ordinal is within the ordinals of a range.
+struct DocValuesTermRangeDocs {
+    range: TermRange,
+}
+
+impl<C: Codec> ConstantScoreDocs<C> for DocValuesTermRangeDocs {
+    fn scorer(
+        &self,
+        leaf_reader: &LeafReaderContext<'_, C>,
+        score: f32,
+    ) -> Result<Option<Box<dyn Scorer>>> {
+        match leaf_reader
+            .reader
+            .field_info(&self.range.field)
+            .map(|fi| fi.doc_values_type)
+        {
+            Some(DocValuesType::Sorted) => {}
+            Some(DocValuesType::Null) | None => return Ok(None),
+            Some(dv_type) => bail!(IllegalArgument(format!(
+                "field '{}' has doc values of type {:?}, expected sorted",
+                &self.range.field, dv_type
+            ))),
+        }
+
+        let mut values = leaf_reader
+            .reader
+            .get_sorted_doc_values(&self.range.field)?;
+        let min_ord = match self.range.lower {
+            Some(ref lower) => {
+                let ord = values.lookup_term(lower)?;
+                if ord < 0 {
+                    -ord - 1
+                } else if self.range.include_lower {
+                    ord
+                } else {
+                    ord + 1
+                }
+            }
+            None => 0,
+        };
+        let max_ord = match self.range.upper {
+            Some(ref upper) => {
+                let ord = values.lookup_term(upper)?;
+                if ord < 0 {
+                    -ord - 2
+                } else if self.range.include_upper {
+                    ord
+                } else {
+                    ord - 1
+                }
+            }
+            None => values.value_count() as i32 - 1,
+        };
+        if min_ord > max_ord {
+            return Ok(None);
+        }
+
+        let max_doc = leaf_reader.reader.max_doc();
+        let iterator = SortedOrdRangeIterator {
+            values,
+            min_ord,
+            max_ord,
+            doc: -1,
+            max_doc,
+        };
+        Ok(Some(Box::new(ConstantScoreScorer::new(
+            score,
+            iterator,
+            max_doc as usize,
+        ))))
+    }
+}
+
+impl fmt::Display for DocValuesTermRangeDocs {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "DocValuesTermRangeDocs({})", &self.range)
+    }
+}
+
+/// Iterates the documents whose sorted doc values ordinal is within
+/// `[min_ord, max_ord]`.
+///
+/// The approximation of its two phase iteration is all the docs of the
+/// segment, `matches()` reads the ordinal of the current doc.
+struct SortedOrdRangeIterator {
+    values: Box<dyn SortedDocValues>,
+    min_ord: i32,
+    max_ord: i32,
+    doc: DocId,
+    max_doc: DocId,
+}
+
+impl SortedOrdRangeIterator {
+    fn two_phase_next(&mut self) -> Result<DocId> {
+        while self.doc != NO_MORE_DOCS && !self.matches()? {
+            self.approximate_next()?;
+        }
+        Ok(self.doc)
+    }
+}
+
+impl DocIterator for SortedOrdRangeIterator {
+    fn doc_id(&self) -> DocId {
+        self.doc
+    }
+
+    fn next(&mut self) -> Result<DocId> {
+        self.approximate_next()?;
+        self.two_phase_next()
+    }
+
+    fn advance(&mut self, target: DocId) -> Result<DocId> {
+        self.approximate_advance(target)?;
+        self.two_phase_next()
+    }
+
+    fn cost(&self) -> usize {
+        self.max_doc as usize
+    }
+
+    fn matches(&mut self) -> Result<bool> {
+        let ord = self.values.get_ord(self.doc)?;
+        Ok(ord >= self.min_ord && ord <= self.max_ord)
+    }
+
+    fn match_cost(&self) -> f32 {
+        // the lookup of the ordinal and two comparisons
+        3.0
+    }
+
+    fn support_two_phase(&self) -> bool {
+        true
+    }
+
+    fn approximate_next(&mut self) -> Result<DocId> {
+        let target = self.doc + 1;
+        self.approximate_advance(target)
+    }
+
+    fn approximate_advance(&mut self, target: DocId) -> Result<DocId> {
+        self.doc = if target < self.max_doc {
+            target
+        } else {
+            NO_MORE_DOCS
+        };
+        Ok(self.doc)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate tempfile;
+
+    use super::*;
+    use core::codec::CodecEnum;
+    use core::doc::{
+        Field, FieldType, Fieldable, IndexOptions, NumericDocValuesField,
+        SORTED_DOC_VALUES_FIELD_TYPE,
+    };
+    use core::index::writer::{IndexWriter, IndexWriterConfig};
+    use core::search::collector::TopDocsCollector;
+    use core::search::query::BooleanQuery;
+    use core::search::{DefaultIndexSearcher, IndexSearcher};
+    use core::store::directory::FSDirectory;
+    use core::util::VariantValue;
+
+    use std::sync::Arc;
+
+    #[test]
+    fn test_term_range_query() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let directory = Arc::new(FSDirectory::with_path(temp_dir.path()).unwrap());
+        let writer = IndexWriter::new(directory, Arc::new(IndexWriterConfig::default())).unwrap();
+
+        let mut field_type = FieldType::default();
+        field_type.index_options = IndexOptions::Docs;
+        field_type.tokenized = false;
+        let ids: Vec<String> = (b'a'..=b'z')
+            .flat_map(|c| (0..20).map(move |i| format!("{}{:02}", c as char, i)))
+            .collect();
+        for (i, id) in ids.iter().enumerate() {
+            let doc: Vec<Box<dyn Fieldable>> = vec![
+                Box::new(Field::new(
+                    "id".into(),
+                    field_type.clone(),
+                    Some(VariantValue::VString(id.clone())),
+                    None,
+                )),
+                Box::new(Field::new(
+                    "id_dv".into(),
+                    SORTED_DOC_VALUES_FIELD_TYPE,
+                    Some(VariantValue::Binary(id.as_bytes().to_vec())),
+                    None,
+                )),
+                Box::new(NumericDocValuesField::new("num", i as i64)),
+            ];
+            writer.add_document(doc).unwrap();
+            if i == 300 {
+                writer.commit().unwrap();
+            }
+        }
+        writer.commit().unwrap();
+
+        let reader = writer.get_reader(true, false).unwrap();
+        let searcher = DefaultIndexSearcher::new(Arc::new(reader), None);
+        let count = |query: &dyn Query<_>| {
+            let mut collector = TopDocsCollector::new(10);
+            searcher.search(query, &mut collector).unwrap();
+            collector.top_docs().total_hits()
+        };
+        let check = |lower: Option<&str>, upper: Option<&str>, il: bool, iu: bool| {
+            let in_range = |id: &str| {
+                lower.map_or(true, |l| if il { id >= l } else { id > l })
+                    && upper.map_or(true, |u| if iu { id <= u } else { id < u })
+            };
+            let expected = ids.iter().filter(|id| in_range(id)).count();
+            let query = TermRangeQuery::new_string_range("id".into(), lower, upper, il, iu);
+            assert_eq!(count(&query), expected, "{}", &query);
+
+            let query = DocValuesTermRangeQuery::new(
+                "id_dv".into(),
+                lower.map(|s| s.as_bytes().to_vec()),
+                upper.map(|s| s.as_bytes().to_vec()),
+                il,
+                iu,
+            );
+            assert_eq!(count(&query), expected, "{}", &query);
+        };
+
+        check(Some("a"), Some("m"), true, false);
+        check(Some("c05"), Some("c10"), true, true);
+        check(Some("c05"), Some("c10"), false, false);
+        check(Some("c05"), Some("c05"), true, true);
+        check(Some("c05"), Some("c05"), false, true);
+        check(Some("x"), None, true, true);
+        check(None, Some("b"), true, true);
+        check(None, None, true, true);
+        check(Some("m"), Some("a"), true, true);
+        check(Some("zz"), None, true, true);
+
+        // as a filter, the ordinals are only checked for the docs of the query
+        let filtered = |lower: &str, upper: &str| {
+            let query: Box<dyn Query<CodecEnum>> = Box::new(TermRangeQuery::new_string_range(
+                "id".into(),
+                Some("c00"),
+                Some("c10"),
+                true,
+                true,
+            ));
+            let filter: Box<dyn Query<CodecEnum>> = Box::new(DocValuesTermRangeQuery::new(
+                "id_dv".into(),
+                Some(lower.as_bytes().to_vec()),
+                Some(upper.as_bytes().to_vec()),
+                true,
+                false,
+            ));
+            let query = BooleanQuery::build(vec![query], vec![], vec![filter], vec![], 0).unwrap();
+            count(query.as_ref())
+        };
+        assert_eq!(filtered("b", "d"), 11);
+        assert_eq!(filtered("c05", "c07"), 2);
+        assert_eq!(filtered("x", "z"), 0);
+
+        // the ordinals are read from sorted doc values only
+        let query = DocValuesTermRangeQuery::new("id".into(), None, None, true, true);
+        assert_eq!(count(&query), 0);
+        let query = DocValuesTermRangeQuery::new("num".into(), None, None, true, true);
+        let mut collector = TopDocsCollector::new(10);
+        assert!(searcher.search(&query, &mut collector).is_err());
+    }
+}
@@ -0,0 +1,122 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::any::Any;
+use std::fmt;
+
+use core::codec::Codec;
+use core::doc::Term;
+use core::search::query::{AutomatonQuery, Query, RewriteMethod, TermQuery, Weight};
+use core::search::searcher::SearchPlanBuilder;
+use core::util::automaton::{concatenate, Automaton, DEFAULT_MAX_DETERMINIZED_STATES};
+
+use error::Result;
+
+/// String equality with support for wildcards
+pub const WILDCARD_STRING: char = '*';
+/// Char equality with support for wildcards
+pub const WILDCARD_CHAR: char = '?';
+/// Escape character
+pub const WILDCARD_ESCAPE: char = '\\';
+
+/// Implements the wildcard search query.
+///
+/// Supported wildcards are `*`, which matches any character sequence (including
+/// the empty one), and `?`, which matches any single character. `\` is the escape
+/// character. Note this query can be slow, as it needs to iterate over many terms;
+/// in order to prevent extremely slow queries, a term should not start with `*`.
+pub struct WildcardQuery {
+    query: AutomatonQuery,
+}
+
+impl WildcardQuery {
+    pub fn new(term: Term) -> Result<WildcardQuery> {
+        Self::with_max_determinized_states(term, DEFAULT_MAX_DETERMINIZED_STATES)
+    }
+
+    pub fn with_max_determinized_states(
+        term: Term,
+        max_determinized_states: usize,
+    ) -> Result<WildcardQuery> {
+        let automaton = Self::build_automaton(&term.text()?);
+        let query = AutomatonQuery::new(term, &automaton, max_determinized_states)?;
+        Ok(WildcardQuery { query })
+    }
+
+    /// Converts a wildcard pattern to an automaton.
+    pub fn build_automaton(pattern: &str) -> Automaton {
+        let mut automata = vec![];
+        let mut chars = pattern.chars();
+        while let Some(c) = chars.next() {
+            let automaton = match c {
+                WILDCARD_STRING => Automaton::any_string(),
+                WILDCARD_CHAR => Automaton::any_char(),
+                WILDCARD_ESCAPE => {
+                    // a trailing escape is matched literally
+                    let escaped = chars.next().unwrap_or(WILDCARD_ESCAPE);
+                    Automaton::string(&escaped.to_string())
+                }
+                _ => Automaton::string(&c.to_string()),
+            };
+            automata.push(automaton);
+        }
+        concatenate(&automata)
+    }
+
+    pub fn term(&self) -> &Term {
+        self.query.term()
+    }
+
+    pub fn set_rewrite_method(&mut self, rewrite_method: RewriteMethod) {
+        self.query.set_rewrite_method(rewrite_method);
+    }
+
+    pub fn set_boost(&mut self, boost: f32) {
+        self.query.set_boost(boost);
+    }
+}
+
+impl<C: Codec> Query<C> for WildcardQuery {
+    fn create_weight(
+        &self,
+        searcher: &dyn SearchPlanBuilder<C>,
+        needs_scores: bool,
+    ) -> Result<Box<dyn Weight<C>>> {
+        self.query.create_weight(searcher, needs_scores)
+    }
+
+    fn extract_terms(&self) -> Vec<TermQuery> {
+        vec![]
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn rewrite(&self, searcher: &dyn SearchPlanBuilder<C>) -> Result<Option<Box<dyn Query<C>>>> {
+        Query::<C>::rewrite(&self.query, searcher)
+    }
+}
+
+impl fmt::Display for WildcardQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let term = self.query.term();
+        write!(
+            f,
+            "WildcardQuery(field: {}, term: {}, boost: {})",
+            &term.field,
+            String::from_utf8_lossy(&term.bytes),
+            self.query.boost()
+        )
+    }
+}
@@ -71,6 +71,14 @@ impl DocIterator for BoostingScorer {
         self.positive.matches()
     }
 
+    fn match_cost(&self) -> f32 {
+        self.positive.match_cost()
+    }
+
+    fn support_two_phase(&self) -> bool {
+        self.positive.support_two_phase()
+    }
+
     fn approximate_next(&mut self) -> Result<DocId> {
         self.positive.approximate_next()
     }
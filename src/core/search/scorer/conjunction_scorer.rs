@@ -21,6 +21,9 @@ pub struct ConjunctionScorer<T: Scorer> {
     lead1: T,
     lead2: T,
     others: Vec<T>,
+    // whether some of the scorers iterate approximations, whose docs must be
+    // confirmed by `matches()`
+    two_phase: bool,
 }
 
 impl<T: Scorer> ConjunctionScorer<T> {
@@ -29,18 +32,31 @@ impl<T: Scorer> ConjunctionScorer<T> {
 
         children.sort_by(|a, b| a.cost().cmp(&b.cost()));
 
-        let others = children.drain(2..).collect();
+        let others: Vec<T> = children.drain(2..).collect();
 
         let lead2 = children.remove(1);
         let lead1 = children.remove(0);
+        let two_phase = lead1.support_two_phase()
+            || lead2.support_two_phase()
+            || others.iter().any(|s| s.support_two_phase());
 
         ConjunctionScorer {
             lead1,
             lead2,
             others,
+            two_phase,
         }
     }
 
+    fn two_phase_next(&mut self, mut doc: DocId) -> Result<DocId> {
+        if self.two_phase {
+            while doc != NO_MORE_DOCS && !self.matches()? {
+                doc = self.approximate_next()?;
+            }
+        }
+        Ok(doc)
+    }
+
     fn skip_to_approx(&mut self, target: DocId) -> Result<DocId> {
         let mut doc = target;
 
@@ -101,21 +117,54 @@ impl<T: Scorer> DocIterator for ConjunctionScorer<T> {
     }
 
     fn next(&mut self) -> Result<DocId> {
-        self.approximate_next()
+        let doc = self.approximate_next()?;
+        self.two_phase_next(doc)
     }
 
     fn advance(&mut self, target: DocId) -> Result<DocId> {
-        self.approximate_advance(target)
+        let doc = self.approximate_advance(target)?;
+        self.two_phase_next(doc)
     }
 
     fn cost(&self) -> usize {
         self.lead1.cost()
     }
 
+    /// Confirms the approximation of the scorers that support two phase
+    /// iteration, the other ones only iterate matching docs.
     fn matches(&mut self) -> Result<bool> {
+        if self.lead1.support_two_phase() && !self.lead1.matches()? {
+            return Ok(false);
+        }
+        if self.lead2.support_two_phase() && !self.lead2.matches()? {
+            return Ok(false);
+        }
+        for other in &mut self.others {
+            if other.support_two_phase() && !other.matches()? {
+                return Ok(false);
+            }
+        }
         Ok(true)
     }
 
+    fn match_cost(&self) -> f32 {
+        let mut match_cost = 0f32;
+        for scorer in [&self.lead1, &self.lead2]
+            .iter()
+            .cloned()
+            .chain(&self.others)
+        {
+            if scorer.support_two_phase() {
+                match_cost += scorer.match_cost();
+            }
+        }
+        match_cost
+    }
+
+    fn support_two_phase(&self) -> bool {
+        self.two_phase
+    }
+
     fn approximate_next(&mut self) -> Result<DocId> {
         let doc = self.lead1.approximate_next()?;
         self.skip_to_approx(doc)
@@ -292,6 +292,8 @@ impl<T: Scorer> SubScorers<T> {
         }
     }
 
+    // the sub scorers are iterated on their matching docs, as the docs of a
+    // disjunction aren't confirmed by `matches()`
     fn approximate_next(&mut self, min_should_match: Option<i32>) -> Result<DocId> {
         match self {
             SubScorers::SQ(sq) => {
@@ -307,7 +309,7 @@ impl<T: Scorer> SubScorers<T> {
                     let mut min_doc = NO_MORE_DOCS;
                     for s in sq.scorers.iter_mut() {
                         if s.doc_id() == curr_doc {
-                            s.approximate_next()?;
+                            s.next()?;
                         }
 
                         min_doc = min_doc.min(s.doc_id());
@@ -336,7 +338,7 @@ impl<T: Scorer> SubScorers<T> {
                 let doc = dbq.peek().doc();
 
                 loop {
-                    dbq.peek_mut().approximate_next()?;
+                    dbq.peek_mut().next_doc()?;
                     if dbq.peek().doc() != doc {
                         break;
                     }
@@ -353,7 +355,7 @@ impl<T: Scorer> SubScorers<T> {
                 let mut min_doc = NO_MORE_DOCS;
                 for s in sq.scorers.iter_mut() {
                     if s.doc_id() < target {
-                        s.approximate_advance(target)?;
+                        s.advance(target)?;
                     }
 
                     min_doc = min_doc.min(s.doc_id());
@@ -364,7 +366,7 @@ impl<T: Scorer> SubScorers<T> {
             }
             SubScorers::DPQ(dbq) => {
                 loop {
-                    dbq.peek_mut().approximate_advance(target)?;
+                    dbq.peek_mut().advance(target)?;
                     if dbq.peek().doc() >= target {
                         break;
                     }
@@ -0,0 +1,340 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use serde_json;
+
+use error::{ErrorKind::IllegalArgument, Result};
+
+/// A learning to rank model, that scores a doc from its feature values.
+///
+/// Models are loaded from JSON, and are either a linear model:
+///
+/// ```json
+/// {"linear": {"bias": 0.5, "weights": {"title_bm25": 1.2, "popularity": 0.01}}}
+/// ```
+///
+/// or an ensemble of regression trees, e.g. trained by gradient boosting,
+/// whose score is the base score plus the weighted sum of the values of the
+/// leaves the doc falls into:
+///
+/// ```json
+/// {"ensemble": {"base_score": 0.5, "trees": [
+///     {"weight": 0.1, "root": {"feature": "title_bm25", "threshold": 2.5,
+///         "left": {"value": -1.0}, "right": {"value": 1.0}}}
+/// ]}}
+/// ```
+///
+/// A split sends the docs whose feature value is lower than its threshold to
+/// the left, and the other ones to the right.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LtrModel {
+    Linear(LinearModel),
+    Ensemble(TreeEnsemble),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LinearModel {
+    #[serde(default)]
+    pub bias: f64,
+    pub weights: BTreeMap<String, f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TreeEnsemble {
+    #[serde(default)]
+    pub base_score: f64,
+    pub trees: Vec<RegressionTree>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RegressionTree {
+    #[serde(default = "default_tree_weight")]
+    pub weight: f64,
+    pub root: TreeNode,
+}
+
+fn default_tree_weight() -> f64 {
+    1.0
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum TreeNode {
+    Split {
+        feature: String,
+        threshold: f64,
+        left: Box<TreeNode>,
+        right: Box<TreeNode>,
+    },
+    Leaf {
+        value: f64,
+    },
+}
+
+impl TreeNode {
+    fn collect_features<'a>(&'a self, features: &mut BTreeSet<&'a str>) {
+        if let TreeNode::Split {
+            feature,
+            left,
+            right,
+            ..
+        } = self
+        {
+            features.insert(feature);
+            left.collect_features(features);
+            right.collect_features(features);
+        }
+    }
+
+    // flattens the tree in pre-order, so the left child of a split is the
+    // node that follows it
+    fn flatten(&self, features: &[String], nodes: &mut Vec<FlatNode>) -> Result<()> {
+        match self {
+            TreeNode::Split {
+                feature,
+                threshold,
+                left,
+                right,
+            } => {
+                let split = nodes.len();
+                nodes.push(FlatNode::Leaf(0.0));
+                left.flatten(features, nodes)?;
+                let right_child = nodes.len();
+                right.flatten(features, nodes)?;
+                nodes[split] = FlatNode::Split {
+                    feature: feature_index(features, feature)?,
+                    threshold: *threshold,
+                    right: right_child,
+                };
+            }
+            TreeNode::Leaf { value } => nodes.push(FlatNode::Leaf(*value)),
+        }
+        Ok(())
+    }
+}
+
+impl LtrModel {
+    pub fn from_json(json: &str) -> Result<LtrModel> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Loads a model from a JSON file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<LtrModel> {
+        let file = File::open(path)?;
+        Ok(serde_json::from_reader(BufReader::new(file))?)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            LtrModel::Linear(_) => "linear",
+            LtrModel::Ensemble(_) => "ensemble",
+        }
+    }
+
+    /// The names of the features the model reads, sorted.
+    pub fn features(&self) -> Vec<&str> {
+        let mut features = BTreeSet::new();
+        match self {
+            LtrModel::Linear(linear) => {
+                features.extend(linear.weights.keys().map(String::as_str));
+            }
+            LtrModel::Ensemble(ensemble) => {
+                for tree in &ensemble.trees {
+                    tree.root.collect_features(&mut features);
+                }
+            }
+        }
+        features.into_iter().collect()
+    }
+
+    /// Resolves the features of the model to their index in `features`, the
+    /// names of the values the compiled model is evaluated with.
+    pub(crate) fn compile(&self, features: &[String]) -> Result<CompiledModel> {
+        match self {
+            LtrModel::Linear(linear) => {
+                let mut weights = Vec::with_capacity(linear.weights.len());
+                for (feature, weight) in &linear.weights {
+                    weights.push((feature_index(features, feature)?, *weight));
+                }
+                Ok(CompiledModel::Linear {
+                    bias: linear.bias,
+                    weights,
+                })
+            }
+            LtrModel::Ensemble(ensemble) => {
+                let mut trees = Vec::with_capacity(ensemble.trees.len());
+                for tree in &ensemble.trees {
+                    let mut nodes = vec![];
+                    tree.root.flatten(features, &mut nodes)?;
+                    trees.push((tree.weight, nodes));
+                }
+                Ok(CompiledModel::Ensemble {
+                    base_score: ensemble.base_score,
+                    trees,
+                })
+            }
+        }
+    }
+}
+
+fn feature_index(features: &[String], feature: &str) -> Result<usize> {
+    match features.iter().position(|f| f == feature) {
+        Some(index) => Ok(index),
+        None => bail!(IllegalArgument(format!(
+            "the model reads the undefined feature '{}'",
+            feature
+        ))),
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum FlatNode {
+    Split {
+        feature: usize,
+        threshold: f64,
+        right: usize,
+    },
+    Leaf(f64),
+}
+
+/// An `LtrModel` whose features are resolved to the indices of the feature
+/// values it is evaluated with.
+#[derive(Debug)]
+pub(crate) enum CompiledModel {
+    Linear {
+        bias: f64,
+        weights: Vec<(usize, f64)>,
+    },
+    Ensemble {
+        base_score: f64,
+        trees: Vec<(f64, Vec<FlatNode>)>,
+    },
+}
+
+impl CompiledModel {
+    pub fn evaluate(&self, values: &[f64]) -> f64 {
+        match self {
+            CompiledModel::Linear { bias, weights } => {
+                weights.iter().fold(*bias, |score, &(feature, weight)| {
+                    score + weight * values[feature]
+                })
+            }
+            CompiledModel::Ensemble { base_score, trees } => {
+                trees.iter().fold(*base_score, |score, (weight, nodes)| {
+                    score + weight * Self::leaf_value(nodes, values)
+                })
+            }
+        }
+    }
+
+    fn leaf_value(nodes: &[FlatNode], values: &[f64]) -> f64 {
+        let mut node = 0;
+        loop {
+            match nodes[node] {
+                FlatNode::Split {
+                    feature,
+                    threshold,
+                    right,
+                } => {
+                    node = if values[feature] < threshold {
+                        node + 1
+                    } else {
+                        right
+                    };
+                }
+                FlatNode::Leaf(value) => return value,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(features: &[&str]) -> Vec<String> {
+        features.iter().map(|f| (*f).to_string()).collect()
+    }
+
+    fn assert_score(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < ::std::f64::EPSILON,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_linear_model() {
+        let model = LtrModel::from_json(
+            r#"{"linear": {"bias": 0.5, "weights": {"bm25": 2.0, "popularity": 0.01}}}"#,
+        )
+        .unwrap();
+        assert_eq!(model.name(), "linear");
+        assert_eq!(model.features(), vec!["bm25", "popularity"]);
+
+        let compiled = model
+            .compile(&names(&["popularity", "unused", "bm25"]))
+            .unwrap();
+        assert_score(compiled.evaluate(&[100.0, 7.0, 1.5]), 4.5);
+        assert!(model.compile(&names(&["bm25"])).is_err());
+
+        // the bias is optional
+        let model = LtrModel::from_json(r#"{"linear": {"weights": {}}}"#).unwrap();
+        assert_score(model.compile(&[]).unwrap().evaluate(&[]), 0.0);
+
+        for json in &[
+            "",
+            "{}",
+            r#"{"linear": {}}"#,
+            r#"{"logistic": {"weights": {}}}"#,
+        ] {
+            assert!(LtrModel::from_json(json).is_err(), "{}", json);
+        }
+    }
+
+    #[test]
+    fn test_tree_ensemble() {
+        let model = LtrModel::from_json(
+            r#"{"ensemble": {"base_score": 1.0, "trees": [
+                {"weight": 0.5, "root": {"feature": "bm25", "threshold": 2.0,
+                    "left": {"value": -1.0},
+                    "right": {"feature": "popularity", "threshold": 10.0,
+                        "left": {"value": 1.0}, "right": {"value": 3.0}}}},
+                {"root": {"feature": "popularity", "threshold": 5.0,
+                    "left": {"value": 0.0}, "right": {"value": 0.25}}},
+                {"root": {"value": 0.125}}
+            ]}}"#,
+        )
+        .unwrap();
+        assert_eq!(model.name(), "ensemble");
+        assert_eq!(model.features(), vec!["bm25", "popularity"]);
+
+        let compiled = model.compile(&names(&["bm25", "popularity"])).unwrap();
+        assert_score(compiled.evaluate(&[1.0, 0.0]), 1.0 - 0.5 + 0.125);
+        // the docs with a value equal to the threshold go to the right
+        assert_score(compiled.evaluate(&[2.0, 5.0]), 1.0 + 0.5 + 0.25 + 0.125);
+        assert_score(compiled.evaluate(&[3.0, 20.0]), 1.0 + 1.5 + 0.25 + 0.125);
+        assert!(model.compile(&names(&["popularity"])).is_err());
+
+        let json = serde_json::to_string(&model).unwrap();
+        assert_eq!(LtrModel::from_json(&json).unwrap(), model);
+    }
+}
@@ -0,0 +1,621 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use serde_json;
+
+use core::codec::Codec;
+use core::index::reader::IndexReader;
+use core::search::explanation::Explanation;
+use core::search::facet::{min_value, NumericFieldType, NumericValues};
+use core::search::scorer::{
+    score_field_index, CompiledModel, LtrModel, QueryRescorer, RescoreRequest, Rescorer, Scorer,
+};
+use core::search::searcher::IndexSearcher;
+use core::search::sort_field::{ScoreDocHit, TopDocs};
+use core::search::DocIterator;
+use core::util::{DocId, IndexedContext, VariantValue};
+
+use error::{ErrorKind::IllegalArgument, Result};
+
+/// Where the value of a feature of an `LtrRescorer` comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LtrFeature {
+    /// The value at this index of the `score_context()` of the rescore query,
+    /// e.g. the score of a feature query of a `FeatureVectorQuery`, 0 if it
+    /// is not set.
+    Query(usize),
+    /// The value of a `NumericDocValues` or `SortedNumericDocValues` field,
+    /// the smallest one if the doc has several values, 0 if it has none.
+    DocValues(String, NumericFieldType),
+    /// The score of the doc before it is rescored.
+    PreviousScore,
+}
+
+impl fmt::Display for LtrFeature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LtrFeature::Query(index) => write!(f, "query[{}]", index),
+            LtrFeature::DocValues(field, _) => write!(f, "doc['{}'].value", field),
+            LtrFeature::PreviousScore => write!(f, "previous_score"),
+        }
+    }
+}
+
+/// Receives the feature values of the docs rescored by an `LtrRescorer`, e.g.
+/// to build the training data of the next model.
+pub trait FeatureLogger: Send + Sync {
+    /// Logs the named feature values of `doc`, and its score by the model.
+    fn log(&self, doc: DocId, features: &[(&str, f64)], score: f64) -> Result<()>;
+}
+
+#[derive(Serialize)]
+struct FeatureLogLine<'a> {
+    doc: DocId,
+    score: f64,
+    features: BTreeMap<&'a str, f64>,
+}
+
+/// A `FeatureLogger` that writes a JSON object per doc and line, e.g.
+/// `{"doc":3,"score":1.5,"features":{"popularity":20.0,"title":1.2}}`.
+pub struct JsonFeatureLogger<W: Write + Send> {
+    writer: Mutex<W>,
+}
+
+impl<W: Write + Send> JsonFeatureLogger<W> {
+    pub fn new(writer: W) -> JsonFeatureLogger<W> {
+        JsonFeatureLogger {
+            writer: Mutex::new(writer),
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer.into_inner().unwrap()
+    }
+}
+
+impl<W: Write + Send> FeatureLogger for JsonFeatureLogger<W> {
+    fn log(&self, doc: DocId, features: &[(&str, f64)], score: f64) -> Result<()> {
+        let line = FeatureLogLine {
+            doc,
+            score,
+            features: features.iter().cloned().collect(),
+        };
+        let mut writer = self.writer.lock().unwrap();
+        serde_json::to_writer(&mut *writer, &line)?;
+        writer.write_all(b"\n")?;
+        Ok(())
+    }
+}
+
+/// A learning to rank `Rescorer`, that scores the top hits with an
+/// `LtrModel` evaluated over named features, and combines the model scores
+/// with the previous ones like a `QueryRescorer`.
+///
+/// The `LtrFeature::Query` features read the `score_context()` of the query
+/// of the `RescoreRequest`, which is usually a `FeatureVectorQuery`.
+///
+/// `rescore_features()` extracts the feature values of the top hits without
+/// rescoring them, and a `FeatureLogger` logs the feature values of the docs
+/// as they are rescored.
+pub struct LtrRescorer {
+    model: Arc<LtrModel>,
+    compiled: CompiledModel,
+    features: Vec<(String, LtrFeature)>,
+    feature_names: Vec<String>,
+    logger: Option<Arc<dyn FeatureLogger>>,
+}
+
+impl LtrRescorer {
+    /// Creates a rescorer that evaluates `model` over `features`, which must
+    /// define all the features the model reads.
+    pub fn new(model: Arc<LtrModel>, features: Vec<(String, LtrFeature)>) -> Result<LtrRescorer> {
+        let feature_names: Vec<String> = features.iter().map(|(name, _)| name.clone()).collect();
+        for (i, name) in feature_names.iter().enumerate() {
+            if feature_names[..i].contains(name) {
+                bail!(IllegalArgument(format!(
+                    "the feature '{}' is defined twice",
+                    name
+                )));
+            }
+        }
+        let compiled = model.compile(&feature_names)?;
+        Ok(LtrRescorer {
+            model,
+            compiled,
+            features,
+            feature_names,
+            logger: None,
+        })
+    }
+
+    pub fn set_feature_logger(&mut self, logger: Arc<dyn FeatureLogger>) {
+        self.logger = Some(logger);
+    }
+
+    pub fn model(&self) -> &LtrModel {
+        &self.model
+    }
+
+    pub fn features(&self) -> &[(String, LtrFeature)] {
+        &self.features
+    }
+
+    /// Extracts the feature values of the docs, which must be sorted by id,
+    /// given with their previous score.
+    fn extract_features<C: Codec, IS: IndexSearcher<C>>(
+        &self,
+        searcher: &IS,
+        req: &RescoreRequest<C>,
+        docs: &[(DocId, f32)],
+    ) -> Result<Vec<Vec<f64>>> {
+        let needs_query = self.features.iter().any(|(_, feature)| match feature {
+            LtrFeature::Query(_) => true,
+            _ => false,
+        });
+        let weight = if needs_query {
+            Some(searcher.create_normalized_weight(req.query(), true)?)
+        } else {
+            None
+        };
+
+        let readers = searcher.reader().leaves();
+        let mut reader_idx: i32 = -1;
+        let mut end_doc = 0;
+        let mut doc_base = 0;
+        let mut scorer: Option<Box<dyn Scorer>> = None;
+        let mut doc_values: Vec<Option<NumericValues>> = vec![];
+        let mut value_buf = vec![];
+        let mut features = Vec::with_capacity(docs.len());
+        for &(doc_id, previous_score) in docs {
+            if doc_id >= end_doc {
+                while doc_id >= end_doc && reader_idx < readers.len() as i32 - 1 {
+                    reader_idx += 1;
+                    end_doc = readers[reader_idx as usize].doc_base()
+                        + readers[reader_idx as usize].reader.max_doc();
+                }
+                let reader = &readers[reader_idx as usize];
+                doc_base = reader.doc_base();
+                scorer = match &weight {
+                    Some(weight) => weight.create_scorer(reader)?,
+                    None => None,
+                };
+                doc_values.clear();
+                for (_, feature) in &self.features {
+                    doc_values.push(match feature {
+                        LtrFeature::DocValues(field, field_type) => {
+                            Some(NumericValues::new(reader, field, *field_type)?)
+                        }
+                        _ => None,
+                    });
+                }
+            }
+
+            let target_doc = doc_id - doc_base;
+            let mut context = None;
+            if let Some(ref mut scorer) = scorer {
+                let mut actual_doc = scorer.doc_id();
+                if actual_doc < target_doc {
+                    actual_doc = scorer.advance(target_doc)?;
+                }
+                if actual_doc == target_doc {
+                    context = Some(scorer.score_context()?);
+                }
+            }
+            let context = context.unwrap_or_else(IndexedContext::default);
+
+            let mut values = Vec::with_capacity(self.features.len());
+            for (i, (_, feature)) in self.features.iter().enumerate() {
+                values.push(match feature {
+                    LtrFeature::Query(index) => context
+                        .get(*index)
+                        .and_then(|v| v.get_numeric())
+                        .map_or(0.0, |v| v.double_value()),
+                    LtrFeature::DocValues(_, field_type) => {
+                        doc_values[i]
+                            .as_mut()
+                            .unwrap()
+                            .read(target_doc, &mut value_buf)?;
+                        min_value(value_buf.iter().map(|&raw| field_type.to_f64(raw)))
+                            .unwrap_or(0.0)
+                    }
+                    LtrFeature::PreviousScore => f64::from(previous_score),
+                });
+            }
+            features.push(values);
+        }
+        Ok(features)
+    }
+
+    fn log(&self, doc: DocId, values: &[f64], score: f64) -> Result<()> {
+        if let Some(logger) = &self.logger {
+            let features: Vec<(&str, f64)> = self
+                .feature_names
+                .iter()
+                .map(String::as_str)
+                .zip(values.iter().cloned())
+                .collect();
+            logger.log(doc, &features, score)?;
+        }
+        Ok(())
+    }
+}
+
+impl Rescorer for LtrRescorer {
+    fn rescore<C: Codec, IS: IndexSearcher<C>>(
+        &self,
+        searcher: &IS,
+        rescore_req: &RescoreRequest<C>,
+        top_docs: &mut TopDocs,
+    ) -> Result<()> {
+        if top_docs.total_hits() == 0 || top_docs.score_docs().is_empty() {
+            return Ok(());
+        }
+
+        let mut hits = top_docs.score_docs().to_vec();
+        hits.truncate(rescore_req.window_size);
+        hits.sort_by(ScoreDocHit::order_by_doc);
+        let docs: Vec<(DocId, f32)> = hits.iter().map(|h| (h.doc_id(), h.score())).collect();
+        let features = self.extract_features(searcher, rescore_req, &docs)?;
+
+        let score_field_index = score_field_index(top_docs);
+        for (hit, values) in hits.iter_mut().zip(features.iter()) {
+            let score = self.compiled.evaluate(values);
+            self.log(hit.doc_id(), values, score)?;
+            let combined =
+                QueryRescorer.combine_score(rescore_req, hit.score(), true, score as f32);
+            hit.set_score(combined);
+            if let Some(index) = score_field_index {
+                match hit {
+                    ScoreDocHit::Field(f) => {
+                        f.fields[index] = VariantValue::from(f.score);
+                    }
+                    ScoreDocHit::Score(_) => {
+                        unreachable!();
+                    }
+                }
+            }
+        }
+
+        hits.sort();
+        QueryRescorer.combine_docs(top_docs, hits, rescore_req);
+        Ok(())
+    }
+
+    /// Extracts the feature values of the top hits, along with their
+    /// `previous_score`. Like `QueryRescorer`, this truncates the hits to the
+    /// window and sorts them by doc id, the feature values are in that order.
+    fn rescore_features<C: Codec, IS: IndexSearcher<C>>(
+        &self,
+        searcher: &IS,
+        rescore_req: &RescoreRequest<C>,
+        top_docs: &mut TopDocs,
+    ) -> Result<Vec<HashMap<String, VariantValue>>> {
+        if top_docs.total_hits() == 0 || top_docs.score_docs().is_empty() {
+            return Ok(Vec::new());
+        }
+        let docs: Vec<(DocId, f32)> = {
+            let hits = top_docs.score_docs_mut();
+            hits.truncate(rescore_req.window_size);
+            hits.sort_by(ScoreDocHit::order_by_doc);
+            hits.iter().map(|h| (h.doc_id(), h.score())).collect()
+        };
+
+        let features = self.extract_features(searcher, rescore_req, &docs)?;
+        let mut result_features = Vec::with_capacity(features.len());
+        for (&(_, score), values) in docs.iter().zip(features.iter()) {
+            let mut feature_map: HashMap<String, VariantValue> = self
+                .feature_names
+                .iter()
+                .cloned()
+                .zip(values.iter().map(|&v| VariantValue::from(v)))
+                .collect();
+            feature_map.insert("previous_score".to_string(), VariantValue::from(score));
+            result_features.push(feature_map);
+        }
+        Ok(result_features)
+    }
+
+    fn explain<C: Codec, IS: IndexSearcher<C>>(
+        &self,
+        searcher: &IS,
+        req: &RescoreRequest<C>,
+        first: Explanation,
+        doc: DocId,
+    ) -> Result<Explanation> {
+        if !first.is_match() {
+            return Ok(Explanation::new(
+                false,
+                0.0f32,
+                "First pass did not match".to_string(),
+                vec![first],
+            ));
+        }
+
+        let first_value = first.value();
+        let values = self
+            .extract_features(searcher, req, &[(doc, first_value)])?
+            .pop()
+            .unwrap();
+        let score = self.compiled.evaluate(&values) as f32;
+        let details = self
+            .features
+            .iter()
+            .zip(values.iter())
+            .map(|((name, feature), &value)| {
+                Explanation::new(
+                    true,
+                    value as f32,
+                    format!("{} = {}", name, feature),
+                    vec![],
+                )
+            })
+            .collect();
+        let model = Explanation::new(
+            true,
+            score,
+            format!("{} model, of the features:", self.model.name()),
+            details,
+        );
+        Ok(Explanation::new(
+            true,
+            QueryRescorer.combine_score(req, first_value, true, score),
+            format!("{} of the first pass and model scores:", req.rescore_mode()),
+            vec![first, model],
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate tempfile;
+
+    use super::*;
+    use core::codec::CodecEnum;
+    use core::doc::{
+        DoubleDocValuesField, Field, FieldType, Fieldable, IndexOptions, NumericDocValuesField,
+        SortedNumericDocValuesField, Term,
+    };
+    use core::index::writer::{IndexWriter, IndexWriterConfig};
+    use core::search::collector::TopDocsCollector;
+    use core::search::expression::{Bindings, Expression};
+    use core::search::query::{
+        ConstantScoreQuery, FeatureVectorQuery, MatchAllDocsQuery, Query, ScriptScoreQuery,
+        TermQuery,
+    };
+    use core::search::scorer::RescoreMode;
+    use core::search::DefaultIndexSearcher;
+    use core::store::directory::FSDirectory;
+    use core::util::double2sortable_long;
+
+    // a bare `MatchAllDocsQuery` scores 0 as the weights aren't normalized
+    fn match_all() -> Box<dyn Query<CodecEnum>> {
+        Box::new(ConstantScoreQuery::with_boost(
+            Box::new(MatchAllDocsQuery),
+            1.0,
+        ))
+    }
+
+    fn assert_close(actual: f32, expected: f64) {
+        assert!(
+            (f64::from(actual) - expected).abs() < 1e-5,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_ltr_rescorer() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let directory = Arc::new(FSDirectory::with_path(temp_dir.path()).unwrap());
+        let writer = IndexWriter::new(directory, Arc::new(IndexWriterConfig::default())).unwrap();
+
+        let mut tag_type = FieldType::default();
+        tag_type.index_options = IndexOptions::Docs;
+        tag_type.tokenized = false;
+        // every third doc is hot, the last doc has no popularity
+        for i in 0..10i64 {
+            let mut doc: Vec<Box<dyn Fieldable>> = vec![Box::new(DoubleDocValuesField::new(
+                "rating",
+                i as f64 / 2.0,
+            ))];
+            if i < 9 {
+                doc.push(Box::new(NumericDocValuesField::new("popularity", i * 10)));
+            }
+            for &offset in &[i as f64 - 2.5, 7.0 - i as f64] {
+                doc.push(Box::new(SortedNumericDocValuesField::new(
+                    "offsets",
+                    double2sortable_long(offset),
+                )));
+            }
+            if i % 3 == 0 {
+                doc.push(Box::new(Field::new(
+                    "tag".into(),
+                    tag_type.clone(),
+                    Some(VariantValue::VString("hot".into())),
+                    None,
+                )));
+            }
+            writer.add_document(doc).unwrap();
+            if i == 4 {
+                writer.commit().unwrap();
+            }
+        }
+        writer.commit().unwrap();
+
+        let reader = writer.get_reader(true, false).unwrap();
+        let searcher = DefaultIndexSearcher::new(Arc::new(reader), None);
+
+        let mut bindings = Bindings::default();
+        bindings.add_field("rating", NumericFieldType::Double);
+        let request = || -> RescoreRequest<CodecEnum> {
+            let rating = ScriptScoreQuery::new(
+                match_all(),
+                Expression::compile("rating * 2", &bindings).unwrap(),
+            );
+            let hot = ConstantScoreQuery::with_boost(
+                Box::new(TermQuery::new(
+                    Term::new("tag".into(), b"hot".to_vec()),
+                    1.0,
+                    None,
+                )),
+                1.0,
+            );
+            let query = FeatureVectorQuery::new(vec![
+                ("rating".to_string(), Box::new(rating)),
+                ("hot".to_string(), Box::new(hot)),
+            ]);
+            RescoreRequest::new(Box::new(query), 1.0, 1.0, RescoreMode::Total, 6, false)
+        };
+        let features = vec![
+            ("rating".to_string(), LtrFeature::Query(0)),
+            ("hot".to_string(), LtrFeature::Query(1)),
+            (
+                "popularity".to_string(),
+                LtrFeature::DocValues("popularity".into(), NumericFieldType::Long),
+            ),
+            ("first_score".to_string(), LtrFeature::PreviousScore),
+            (
+                "offset".to_string(),
+                LtrFeature::DocValues("offsets".into(), NumericFieldType::Double),
+            ),
+        ];
+        let top_docs = || {
+            let mut collector = TopDocsCollector::new(10);
+            searcher
+                .search(match_all().as_ref(), &mut collector)
+                .unwrap();
+            // all the docs score 1, the collector doesn't order the ties by id
+            let mut top_docs = collector.top_docs();
+            top_docs.score_docs_mut().sort_by_key(|hit| hit.doc_id());
+            top_docs
+        };
+
+        // the model score of the first 6 docs is 0.5 + 2 * hot + 0.6 * i,
+        // the other docs are out of the window
+        let model = LtrModel::from_json(
+            r#"{"linear": {"bias": 0.5,
+                "weights": {"rating": 0.5, "hot": 2.0, "popularity": 0.01}}}"#,
+        )
+        .unwrap();
+        let mut rescorer = LtrRescorer::new(Arc::new(model), features.clone()).unwrap();
+        let logger = Arc::new(JsonFeatureLogger::new(vec![]));
+        rescorer.set_feature_logger(Arc::clone(&logger) as Arc<dyn FeatureLogger>);
+
+        let mut docs = top_docs();
+        rescorer.rescore(&searcher, &request(), &mut docs).unwrap();
+        let hits: Vec<(DocId, f32)> = docs
+            .score_docs()
+            .iter()
+            .map(|hit| (hit.doc_id(), hit.score()))
+            .collect();
+        let expected = vec![
+            (3, 5.3),
+            (5, 4.5),
+            (4, 3.9),
+            (0, 3.5),
+            (2, 2.7),
+            (1, 2.1),
+            (6, 1.0),
+            (7, 1.0),
+            (8, 1.0),
+            (9, 1.0),
+        ];
+        assert_eq!(hits.len(), expected.len());
+        for ((doc, score), (expected_doc, expected_score)) in hits.into_iter().zip(expected) {
+            assert_eq!(doc, expected_doc);
+            assert_close(score, expected_score);
+        }
+
+        let explanation = rescorer
+            .explain(
+                &searcher,
+                &request(),
+                searcher.explain(match_all().as_ref(), 3).unwrap(),
+                3,
+            )
+            .unwrap();
+        assert_close(explanation.value(), 5.3);
+        assert_eq!(explanation.details()[1].details().len(), 5);
+
+        // the rescored docs are logged in order of id
+        drop(rescorer);
+        let log = Arc::try_unwrap(logger).ok().unwrap().into_inner();
+        let lines: Vec<serde_json::Value> = String::from_utf8(log)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 6);
+        assert_eq!(lines[3]["doc"], 3);
+        assert_eq!(lines[3]["features"]["hot"].as_f64(), Some(1.0));
+        assert_eq!(lines[3]["features"]["popularity"].as_f64(), Some(30.0));
+        assert_eq!(lines[3]["features"]["offset"].as_f64(), Some(0.5));
+        assert_eq!(lines[4]["features"]["hot"].as_f64(), Some(0.0));
+
+        // the trees split on the previous score and the doc values
+        let model = LtrModel::from_json(
+            r#"{"ensemble": {"trees": [
+                {"root": {"feature": "popularity", "threshold": 25,
+                    "left": {"value": 1.0}, "right": {"value": 2.0}}},
+                {"weight": 0.5, "root": {"feature": "first_score", "threshold": 1,
+                    "left": {"value": 0.0}, "right": {"value": 1.0}}}
+            ]}}"#,
+        )
+        .unwrap();
+        let rescorer = LtrRescorer::new(Arc::new(model), features.clone()).unwrap();
+        let mut docs = top_docs();
+        rescorer.rescore(&searcher, &request(), &mut docs).unwrap();
+        for (i, hit) in docs.score_docs().iter().take(6).enumerate() {
+            assert_eq!(hit.doc_id(), (i as DocId + 3) % 6);
+            assert_close(hit.score(), if i < 3 { 3.5 } else { 2.5 });
+        }
+
+        // the features are extracted without rescoring
+        let mut docs = top_docs();
+        let extracted = rescorer
+            .rescore_features(&searcher, &request(), &mut docs)
+            .unwrap();
+        assert_eq!(extracted.len(), 6);
+        for (i, features) in extracted.iter().enumerate() {
+            assert_eq!(docs.score_docs()[i].doc_id(), i as DocId);
+            assert_eq!(features.len(), 6);
+            assert_eq!(features["rating"], VariantValue::Double(i as f64));
+            let hot = if i % 3 == 0 { 1.0 } else { 0.0 };
+            assert_eq!(features["hot"], VariantValue::Double(hot));
+            assert_eq!(
+                features["popularity"],
+                VariantValue::Double(i as f64 * 10.0)
+            );
+            assert_eq!(features["first_score"], VariantValue::Double(1.0));
+            // the smallest value of the multi-valued double field
+            let offset = (i as f64 - 2.5).min(7.0 - i as f64);
+            assert_eq!(features["offset"], VariantValue::Double(offset));
+            assert_eq!(features["previous_score"], VariantValue::Float(1.0));
+        }
+
+        // the model can only read the defined features, which must be unique
+        let model = LtrModel::from_json(r#"{"linear": {"weights": {"ctr": 1.0}}}"#).unwrap();
+        assert!(LtrRescorer::new(Arc::new(model), features.clone()).is_err());
+        let model = LtrModel::from_json(r#"{"linear": {"weights": {"rating": 1.0}}}"#).unwrap();
+        assert!(LtrRescorer::new(Arc::new(model.clone()), features.clone()).is_ok());
+        let mut features = features;
+        features.push(("rating".to_string(), LtrFeature::PreviousScore));
+        assert!(LtrRescorer::new(Arc::new(model), features).is_err());
+    }
+}
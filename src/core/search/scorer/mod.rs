@@ -39,6 +39,14 @@ mod rescorer;
 
 pub use self::rescorer::*;
 
+mod ltr_model;
+
+pub use self::ltr_model::*;
+
+mod ltr_rescorer;
+
+pub use self::ltr_rescorer::*;
+
 mod term_scorer;
 
 pub use self::term_scorer::*;
@@ -51,7 +59,12 @@ mod phrase_scorer;
 
 pub use self::phrase_scorer::*;
 
+mod wand_scorer;
+
+pub use self::wand_scorer::*;
+
 use std::collections::HashMap;
+use std::f32;
 use std::i32;
 
 use core::util::{DocId, IndexedContext, VariantValue};
@@ -96,6 +109,29 @@ pub trait Scorer: DocIterator {
     fn score_feature(&mut self) -> Result<Vec<FeatureResult>> {
         Ok(vec![])
     }
+
+    /// Advances the skip data of this scorer to `target` without moving its
+    /// iterator, and returns the last doc of the block that contains `target`,
+    /// or `NO_MORE_DOCS` if this scorer has no block information.
+    ///
+    /// Targets must be non-decreasing across calls.
+    fn advance_shallow(&mut self, _target: DocId) -> Result<DocId> {
+        Ok(NO_MORE_DOCS)
+    }
+
+    /// Returns an upper bound of the scores of the docs between the last
+    /// `advance_shallow()` target and `up_to`, both inclusive.
+    fn max_score(&mut self, _up_to: DocId) -> Result<f32> {
+        Ok(f32::INFINITY)
+    }
+
+    /// Tells this scorer that the docs that score less than `min_score` are
+    /// not competitive anymore, so that it may skip them.
+    ///
+    /// `min_score` is non-decreasing across calls.
+    fn set_min_competitive_score(&mut self, _min_score: f32) -> Result<()> {
+        Ok(())
+    }
 }
 
 impl Scorer for Box<dyn Scorer> {
@@ -110,6 +146,18 @@ impl Scorer for Box<dyn Scorer> {
     fn score_feature(&mut self) -> Result<Vec<FeatureResult>> {
         (**self).score_feature()
     }
+
+    fn advance_shallow(&mut self, target: DocId) -> Result<DocId> {
+        (**self).advance_shallow(target)
+    }
+
+    fn max_score(&mut self, up_to: DocId) -> Result<f32> {
+        (**self).max_score(up_to)
+    }
+
+    fn set_min_competitive_score(&mut self, min_score: f32) -> Result<()> {
+        (**self).set_min_competitive_score(min_score)
+    }
 }
 
 impl DocIterator for Box<dyn Scorer> {
@@ -295,6 +343,18 @@ impl<T: DocIterator> DocIterator for ConstantScoreScorer<T> {
         self.cost
     }
 
+    fn matches(&mut self) -> Result<bool> {
+        self.iterator.matches()
+    }
+
+    fn match_cost(&self) -> f32 {
+        self.iterator.match_cost()
+    }
+
+    fn support_two_phase(&self) -> bool {
+        self.iterator.support_two_phase()
+    }
+
     fn approximate_next(&mut self) -> Result<DocId> {
         self.iterator.approximate_next()
     }
@@ -36,6 +36,10 @@ impl Scorer for ReqNotScorer {
     fn score(&mut self) -> Result<f32> {
         self.req_scorer.score()
     }
+
+    fn set_min_competitive_score(&mut self, min_score: f32) -> Result<()> {
+        self.req_scorer.set_min_competitive_score(min_score)
+    }
 }
 
 impl DocIterator for ReqNotScorer {
@@ -84,6 +88,14 @@ impl DocIterator for ReqNotScorer {
         self.req_scorer.matches()
     }
 
+    fn match_cost(&self) -> f32 {
+        self.req_scorer.match_cost()
+    }
+
+    fn support_two_phase(&self) -> bool {
+        self.req_scorer.support_two_phase()
+    }
+
     fn approximate_next(&mut self) -> Result<DocId> {
         while let Ok(doc) = self.req_scorer.approximate_next() {
             if unlikely(doc == NO_MORE_DOCS) {
@@ -94,7 +106,7 @@ impl DocIterator for ReqNotScorer {
             } else if doc < self.not_scorer.doc_id() {
                 return Ok(doc);
             }
-            let not_doc = self.not_scorer.approximate_advance(doc)?;
+            let not_doc = self.not_scorer.advance(doc)?;
             if doc < not_doc {
                 return Ok(doc);
             }
@@ -111,7 +123,7 @@ impl DocIterator for ReqNotScorer {
                 } else if doc < self.not_scorer.doc_id() {
                     return Ok(doc);
                 }
-                self.not_scorer.approximate_advance(doc)?;
+                self.not_scorer.advance(doc)?;
             }
         }
         Ok(NO_MORE_DOCS)
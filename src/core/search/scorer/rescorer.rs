@@ -91,6 +91,27 @@ impl<C: Codec> RescoreRequest<C> {
             rescore_movedout,
         }
     }
+
+    pub fn query(&self) -> &dyn Query<C> {
+        self.query.as_ref()
+    }
+
+    pub fn rescore_mode(&self) -> &RescoreMode {
+        &self.rescore_mode
+    }
+}
+
+/// The index of the score sort field of the `TopDocs`, if they are sorted by
+/// fields and one of them is the score.
+pub(crate) fn score_field_index(top_docs: &TopDocs) -> Option<usize> {
+    let fields = match top_docs {
+        TopDocs::Field(f) => &f.fields,
+        TopDocs::Collapse(c) => &c.fields,
+        _ => return None,
+    };
+    fields
+        .iter()
+        .position(|field| field.field_type() == SortFieldType::Score)
 }
 
 #[derive(Debug, Clone)]
@@ -310,26 +331,7 @@ impl QueryRescorer {
         hits.sort_by(ScoreDocHit::order_by_doc);
 
         let readers = searcher.reader().leaves();
-        let mut score_field_index = -1;
-        match *top_docs {
-            TopDocs::Field(ref f) => {
-                for (index, field) in f.fields.iter().enumerate() {
-                    if field.field_type() == SortFieldType::Score {
-                        score_field_index = index as i32;
-                        break;
-                    }
-                }
-            }
-            TopDocs::Collapse(ref c) => {
-                for (index, field) in c.fields.iter().enumerate() {
-                    if field.field_type() == SortFieldType::Score {
-                        score_field_index = index as i32;
-                        break;
-                    }
-                }
-            }
-            _ => {}
-        }
+        let score_field_index = score_field_index(top_docs).map_or(-1, |index| index as i32);
 
         let weight = req.query.create_weight(searcher, true)?;
 
@@ -353,7 +355,7 @@ impl QueryRescorer {
         Ok(hits)
     }
 
-    fn combine_score<C: Codec>(
+    pub(crate) fn combine_score<C: Codec>(
         &self,
         ctx: &RescoreRequest<C>,
         last_score: f32,
@@ -372,7 +374,7 @@ impl QueryRescorer {
         }
     }
 
-    fn combine_docs<C: Codec>(
+    pub(crate) fn combine_docs<C: Codec>(
         &self,
         docs: &mut TopDocs,
         resorted: Vec<ScoreDocHit>,
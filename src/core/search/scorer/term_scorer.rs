@@ -18,6 +18,8 @@ use core::search::DocIterator;
 use core::util::DocId;
 use error::Result;
 
+use std::f32;
+
 pub struct TermScorer<T: PostingIterator> {
     sim_scorer: Box<dyn SimScorer>,
     postings_iterator: T,
@@ -46,6 +48,17 @@ impl<T: PostingIterator> Scorer for TermScorer<T> {
         let freq = self.freq();
         Ok(self.sim_scorer.score(doc_id, freq as f32)?)
     }
+
+    fn advance_shallow(&mut self, target: DocId) -> Result<DocId> {
+        self.postings_iterator.advance_shallow(target)
+    }
+
+    fn max_score(&mut self, up_to: DocId) -> Result<f32> {
+        Ok(match self.postings_iterator.impact(up_to) {
+            Some(impact) => self.sim_scorer.max_score(&impact),
+            None => f32::INFINITY,
+        })
+    }
 }
 
 impl<T: PostingIterator> DocIterator for TermScorer<T> {
@@ -0,0 +1,383 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::search::scorer::Scorer;
+use core::search::{DocIterator, NO_MORE_DOCS};
+use core::util::DocId;
+
+use error::Result;
+use std::cmp::Ordering;
+use std::f32;
+
+/// A pure disjunction `Scorer` that skips the docs which can't be competitive
+/// using the block-max WAND algorithm.
+///
+/// The doc id space is split into windows that end where the first block of
+/// the sub scorers ends. Within a window, the sub scorers are sorted by their
+/// max score, and the longest prefix whose sum of max scores is below the
+/// minimum competitive score is non-essential: a doc that only matches these
+/// scorers can't be competitive, so candidates are only taken from the other,
+/// essential scorers.
+///
+/// Until `set_min_competitive_score()` is called, this behaves like a
+/// `DisjunctionSumScorer` with `min_should_match` of 1.
+pub struct WANDScorer<T: Scorer> {
+    scorers: Vec<T>,
+    max_scores: Vec<f32>,
+    // indices of the sub scorers, sorted by max score
+    order: Vec<usize>,
+    num_non_essential: usize,
+    non_essential_max_score: f32,
+    min_competitive_score: f32,
+    doc: DocId,
+    up_to: DocId,
+    cost: usize,
+}
+
+impl<T: Scorer> WANDScorer<T> {
+    pub fn new(children: Vec<T>) -> WANDScorer<T> {
+        debug_assert!(!children.is_empty());
+
+        let cost = children.iter().map(|s| s.cost()).sum();
+        let num = children.len();
+        WANDScorer {
+            scorers: children,
+            max_scores: vec![f32::INFINITY; num],
+            order: (0..num).collect(),
+            num_non_essential: 0,
+            non_essential_max_score: 0.0,
+            min_competitive_score: 0.0,
+            doc: -1,
+            up_to: -1,
+            cost,
+        }
+    }
+
+    /// Whether a doc whose score is at most `max_score` may be competitive,
+    /// with some slack for the rounding errors of the float sums.
+    fn competitive(&self, max_score: f32) -> bool {
+        let slack = 1.0 + 2.0 * self.scorers.len() as f64 * f64::from(f32::EPSILON);
+        f64::from(max_score) * slack >= f64::from(self.min_competitive_score)
+    }
+
+    /// Moves the window to the one that contains `target`, and updates the
+    /// max scores of the sub scorers within it.
+    fn move_window(&mut self, target: DocId) -> Result<()> {
+        let mut up_to = NO_MORE_DOCS;
+        for scorer in &mut self.scorers {
+            if scorer.doc_id() != NO_MORE_DOCS {
+                up_to = up_to.min(scorer.advance_shallow(target)?);
+            }
+        }
+        self.up_to = up_to.max(target);
+
+        for (scorer, max_score) in self.scorers.iter_mut().zip(self.max_scores.iter_mut()) {
+            *max_score = if scorer.doc_id() == NO_MORE_DOCS {
+                0.0
+            } else {
+                scorer.max_score(self.up_to)?
+            };
+            // a NaN bound says nothing about the scores, so the scorer must
+            // stay essential
+            if max_score.is_nan() {
+                *max_score = f32::INFINITY;
+            }
+        }
+        let max_scores = &self.max_scores;
+        self.order.sort_by(|&a, &b| {
+            max_scores[a]
+                .partial_cmp(&max_scores[b])
+                .unwrap_or(Ordering::Equal)
+        });
+        self.partition();
+        Ok(())
+    }
+
+    fn partition(&mut self) {
+        let mut sum = 0f32;
+        let mut num = 0;
+        for &i in &self.order {
+            let next = sum + self.max_scores[i];
+            if self.competitive(next) {
+                break;
+            }
+            sum = next;
+            num += 1;
+        }
+        self.num_non_essential = num;
+        self.non_essential_max_score = sum;
+    }
+
+    fn do_next(&mut self, target: DocId) -> Result<DocId> {
+        let mut target = target;
+        loop {
+            if target == NO_MORE_DOCS {
+                self.doc = NO_MORE_DOCS;
+                return Ok(NO_MORE_DOCS);
+            }
+            if target > self.up_to {
+                self.move_window(target)?;
+            }
+            if self.num_non_essential == self.scorers.len() {
+                // no doc of this window can be competitive
+                target = if self.up_to == NO_MORE_DOCS {
+                    NO_MORE_DOCS
+                } else {
+                    self.up_to + 1
+                };
+                continue;
+            }
+
+            let mut candidate = NO_MORE_DOCS;
+            for &i in &self.order[self.num_non_essential..] {
+                let scorer = &mut self.scorers[i];
+                let mut doc = scorer.doc_id();
+                if doc < target {
+                    doc = scorer.advance(target)?;
+                }
+                candidate = candidate.min(doc);
+            }
+            if candidate > self.up_to {
+                // the non-essential scorers may become essential in the
+                // next window
+                target = if self.up_to == NO_MORE_DOCS {
+                    NO_MORE_DOCS
+                } else {
+                    self.up_to + 1
+                };
+                continue;
+            }
+
+            let mut max_score = self.non_essential_max_score;
+            for &i in &self.order[self.num_non_essential..] {
+                if self.scorers[i].doc_id() == candidate {
+                    max_score += self.max_scores[i];
+                }
+            }
+            if !self.competitive(max_score) {
+                target = candidate + 1;
+                continue;
+            }
+
+            for &i in &self.order[..self.num_non_essential] {
+                let scorer = &mut self.scorers[i];
+                if scorer.doc_id() < candidate {
+                    scorer.advance(candidate)?;
+                }
+            }
+            self.doc = candidate;
+            return Ok(candidate);
+        }
+    }
+}
+
+impl<T: Scorer> Scorer for WANDScorer<T> {
+    fn score(&mut self) -> Result<f32> {
+        let mut score = 0f32;
+        for scorer in &mut self.scorers {
+            if scorer.doc_id() == self.doc {
+                score += scorer.score()?;
+            }
+        }
+        Ok(score)
+    }
+
+    fn advance_shallow(&mut self, target: DocId) -> Result<DocId> {
+        let mut up_to = NO_MORE_DOCS;
+        for scorer in &mut self.scorers {
+            if scorer.doc_id() != NO_MORE_DOCS {
+                up_to = up_to.min(scorer.advance_shallow(target)?);
+            }
+        }
+        Ok(up_to)
+    }
+
+    fn max_score(&mut self, up_to: DocId) -> Result<f32> {
+        let mut max_score = 0f32;
+        for scorer in &mut self.scorers {
+            if scorer.doc_id() != NO_MORE_DOCS {
+                max_score += scorer.max_score(up_to)?;
+            }
+        }
+        Ok(max_score)
+    }
+
+    fn set_min_competitive_score(&mut self, min_score: f32) -> Result<()> {
+        debug_assert!(min_score >= self.min_competitive_score);
+        self.min_competitive_score = min_score;
+        if self.up_to >= 0 {
+            self.partition();
+        }
+        Ok(())
+    }
+}
+
+impl<T: Scorer> DocIterator for WANDScorer<T> {
+    fn doc_id(&self) -> DocId {
+        self.doc
+    }
+
+    fn next(&mut self) -> Result<DocId> {
+        let target = self.doc + 1;
+        self.do_next(target)
+    }
+
+    fn advance(&mut self, target: DocId) -> Result<DocId> {
+        self.do_next(target)
+    }
+
+    fn cost(&self) -> usize {
+        self.cost
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::search::scorer::DisjunctionSumScorer;
+
+    const BLOCK: usize = 2;
+
+    /// Scores the docs with the given scores, with blocks of `BLOCK` docs.
+    struct MockBlockScorer {
+        docs: Vec<(DocId, f32)>,
+        offset: usize,
+        shallow_block: usize,
+        advanced: usize,
+    }
+
+    impl MockBlockScorer {
+        fn new(docs: Vec<(DocId, f32)>) -> MockBlockScorer {
+            MockBlockScorer {
+                docs,
+                offset: 0,
+                shallow_block: 0,
+                advanced: 0,
+            }
+        }
+
+        fn block_end(&self, block: usize) -> DocId {
+            let last = (block + 1) * BLOCK - 1;
+            if last + 1 >= self.docs.len() {
+                NO_MORE_DOCS
+            } else {
+                self.docs[last].0
+            }
+        }
+    }
+
+    impl Scorer for MockBlockScorer {
+        fn score(&mut self) -> Result<f32> {
+            Ok(self.docs[self.offset - 1].1)
+        }
+
+        fn advance_shallow(&mut self, target: DocId) -> Result<DocId> {
+            while self.block_end(self.shallow_block) < target {
+                self.shallow_block += 1;
+            }
+            Ok(self.block_end(self.shallow_block))
+        }
+
+        fn max_score(&mut self, up_to: DocId) -> Result<f32> {
+            Ok(self.docs[self.shallow_block * BLOCK..]
+                .iter()
+                .take_while(|(doc, _)| *doc <= up_to)
+                .map(|(_, score)| *score)
+                .fold(0.0, f32::max))
+        }
+    }
+
+    impl DocIterator for MockBlockScorer {
+        fn doc_id(&self) -> DocId {
+            if self.offset == 0 {
+                -1
+            } else if self.offset > self.docs.len() {
+                NO_MORE_DOCS
+            } else {
+                self.docs[self.offset - 1].0
+            }
+        }
+
+        fn next(&mut self) -> Result<DocId> {
+            self.offset += 1;
+            self.advanced += 1;
+            Ok(self.doc_id())
+        }
+
+        fn advance(&mut self, target: DocId) -> Result<DocId> {
+            self.advanced += 1;
+            while self.offset <= self.docs.len() && (self.offset == 0 || self.doc_id() < target) {
+                self.offset += 1;
+            }
+            Ok(self.doc_id())
+        }
+
+        fn cost(&self) -> usize {
+            self.docs.len()
+        }
+    }
+
+    fn create_scorers() -> Vec<MockBlockScorer> {
+        let a = (0..100)
+            .map(|i| (i * 2, if i == 37 { 5.0 } else { 1.0 }))
+            .collect();
+        let b = (0..60)
+            .map(|i| (i * 3, if i == 20 { 4.0 } else { 0.5 }))
+            .collect();
+        let c = vec![(7, 0.25), (60, 3.0), (111, 0.25), (150, 0.25)];
+        vec![
+            MockBlockScorer::new(a),
+            MockBlockScorer::new(b),
+            MockBlockScorer::new(c),
+        ]
+    }
+
+    fn collect_all<T: Scorer>(scorer: &mut T) -> Vec<(DocId, f32)> {
+        let mut hits = vec![];
+        while scorer.next().unwrap() != NO_MORE_DOCS {
+            hits.push((scorer.doc_id(), scorer.score().unwrap()));
+        }
+        hits
+    }
+
+    #[test]
+    fn test_wand_scorer() {
+        let mut expected = collect_all(&mut DisjunctionSumScorer::new(create_scorers(), true, 1));
+        let mut scorer = WANDScorer::new(create_scorers());
+        assert_eq!(collect_all(&mut scorer), expected);
+
+        // only the docs that score at least 4 are competitive
+        let mut scorer = WANDScorer::new(create_scorers());
+        scorer.set_min_competitive_score(4.0).unwrap();
+        let hits = collect_all(&mut scorer);
+        expected.retain(|(_, score)| *score >= 4.0);
+        assert_eq!(expected.len(), 2);
+        for hit in &expected {
+            assert!(hits.contains(hit));
+        }
+        let advanced: usize = scorer.scorers.iter().map(|s| s.advanced).sum();
+        assert!(advanced < 100);
+    }
+
+    #[test]
+    fn test_wand_scorer_nan_max_score() {
+        // a NaN max score can't be used to skip the docs of its scorer
+        let mut scorers = create_scorers();
+        scorers[2].docs[1].1 = f32::NAN;
+        let mut scorer = WANDScorer::new(scorers);
+        scorer.set_min_competitive_score(4.0).unwrap();
+        let hits = collect_all(&mut scorer);
+        assert!(hits.iter().any(|(doc, _)| *doc == 60));
+        assert!(hits.contains(&(74, 5.0)));
+    }
+}
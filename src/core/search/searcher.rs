@@ -52,6 +52,16 @@ pub struct TermContext<S: TermState> {
     pub states: Vec<(DocId, S)>,
 }
 
+impl<S: TermState> Default for TermContext<S> {
+    fn default() -> Self {
+        TermContext {
+            doc_freq: 0,
+            total_term_freq: 0,
+            states: Vec::new(),
+        }
+    }
+}
+
 impl<S: TermState> TermContext<S> {
     pub fn new<TI, Tm, FP, C, IR>(reader: &IR) -> TermContext<S>
     where
@@ -96,6 +106,13 @@ impl<S: TermState> TermContext<S> {
         Ok(())
     }
 
+    /// Records the term state and statistics of the term in the leaf
+    /// starting at `doc_base`.
+    pub fn register(&mut self, state: S, doc_base: DocId, doc_freq: i32, total_term_freq: i64) {
+        self.accumulate_statistics(doc_freq, total_term_freq);
+        self.states.push((doc_base, state));
+    }
+
     fn accumulate_statistics(&mut self, doc_freq: i32, total_term_freq: i64) {
         self.doc_freq += doc_freq;
         if self.total_term_freq >= 0 && total_term_freq >= 0 {
@@ -201,6 +218,24 @@ impl SimScorer for NonScoringSimScorer {
     }
 }
 
+/// Indicates how the scorers of a search need to score the matching docs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScoreMode {
+    /// Every matching doc is visited and scored.
+    Complete,
+    /// Every matching doc is visited, but the scores are not needed.
+    CompleteNoScores,
+    /// Only the top scoring docs are needed, the scorers may skip the docs that
+    /// score less than the min competitive score set by the collector.
+    TopScores,
+}
+
+impl ScoreMode {
+    pub fn needs_scores(self) -> bool {
+        self != ScoreMode::CompleteNoScores
+    }
+}
+
 /// trait that used for build `Weight` and `Similarity` for `Query`.
 pub trait SearchPlanBuilder<C: Codec> {
     /// num docs of the reader in searcher, same as IndexSearcher::reader()::num_docs()
@@ -211,6 +246,9 @@ pub trait SearchPlanBuilder<C: Codec> {
 
     /// Creates a `Weight` for the given query, potentially adding caching
     /// if possible and configured.
+    ///
+    /// The query is rewritten to a fixed point first, so the queries nested in
+    /// another one are rewritten when their parent creates their weights.
     fn create_weight(&self, query: &dyn Query<C>, needs_scores: bool)
         -> Result<Box<dyn Weight<C>>>;
 
@@ -229,6 +267,10 @@ pub trait SearchPlanBuilder<C: Codec> {
     fn term_statistics(&self, term: &Term) -> Result<TermStatistics>;
 
     fn collections_statistics(&self, field: &str) -> Option<&CollectionStatistics>;
+
+    /// Returns the leaf readers of the searched index, used by queries that
+    /// need to look at the terms dictionary before creating their weight.
+    fn leaves(&self) -> Vec<LeafReaderContext<'_, C>>;
 }
 
 pub trait IndexSearcher<C: Codec>: SearchPlanBuilder<C> {
@@ -390,6 +432,32 @@ where
         self.cache_policy = cache_policy;
     }
 
+    /// Expert: rewrites `query` until it reaches a fixed point, returns `None`
+    /// if it doesn't need to be rewritten at all.
+    pub fn rewrite(&self, query: &dyn Query<C>) -> Result<Option<Box<dyn Query<C>>>> {
+        let mut rewritten = match query.rewrite(self)? {
+            Some(q) => q,
+            None => return Ok(None),
+        };
+        while let Some(q) = rewritten.rewrite(self)? {
+            rewritten = q;
+        }
+        Ok(Some(rewritten))
+    }
+
+    /// Creates the weight of the top-level query of a search, like
+    /// `create_normalized_weight` but for the `ScoreMode` of the collector.
+    fn create_search_weight(
+        &self,
+        query: &dyn Query<C>,
+        score_mode: ScoreMode,
+    ) -> Result<Box<dyn Weight<C>>> {
+        match self.rewrite(query)? {
+            Some(rewritten) => rewritten.create_weight_with_mode(self, score_mode),
+            None => query.create_weight_with_mode(self, score_mode),
+        }
+    }
+
     fn do_search<S: Scorer + ?Sized, T: Collector, B: Bits + ?Sized>(
         scorer: &mut S,
         collector: &mut T,
@@ -488,7 +556,7 @@ where
     where
         S: SearchCollector,
     {
-        let weight = self.create_weight(query, collector.needs_scores())?;
+        let weight = self.create_search_weight(query, collector.score_mode())?;
 
         for reader in self.reader.leaves() {
             if let Some(mut scorer) = weight.create_scorer(&reader)? {
@@ -531,7 +599,7 @@ where
         if collector.support_parallel() && self.leaf_ord_slices.len() > 1 {
             debug_assert!(self.thread_pool.is_some());
             let thread_pool = self.thread_pool.as_ref().unwrap();
-            let weight = self.create_weight(query, collector.needs_scores())?;
+            let weight = self.create_search_weight(query, collector.score_mode())?;
             let leaf_readers = self.reader.leaves();
 
             collector.init_parallel();
@@ -692,7 +760,10 @@ where
         query: &dyn Query<C>,
         needs_scores: bool,
     ) -> Result<Box<dyn Weight<C>>> {
-        let mut weight = query.create_weight(self, needs_scores)?;
+        let mut weight = match self.rewrite(query)? {
+            Some(rewritten) => rewritten.create_weight(self, needs_scores)?,
+            None => query.create_weight(self, needs_scores)?,
+        };
         // currently not to use query_cache.
         if false && !needs_scores {
             weight = self
@@ -769,6 +840,10 @@ where
     fn collections_statistics(&self, field: &str) -> Option<&CollectionStatistics> {
         self.collection_statistics.get(field)
     }
+
+    fn leaves(&self) -> Vec<LeafReaderContext<'_, C>> {
+        self.reader.leaves()
+    }
 }
 
 struct TotalHitCountCollector {
@@ -875,7 +950,7 @@ mod tests {
     use core::codec::tests::TestCodec;
     use core::index::tests::*;
     use core::search::collector::*;
-    use core::search::query::TermQuery;
+    use core::search::query::{BoostQuery, TermQuery};
     use core::search::tests::*;
     use core::util::DocId;
 
@@ -950,4 +1025,34 @@ mod tests {
         assert!((score_docs[1].score() - 5f32) < ::std::f32::EPSILON);
         assert!((score_docs[2].score() - 5f32) < ::std::f32::EPSILON);
     }
+
+    #[test]
+    fn test_rewrite_to_fixed_point() {
+        let index_reader: Arc<dyn IndexReader<Codec = TestCodec>> =
+            Arc::new(MockIndexReader::new(vec![MockLeafReader::new(0)]));
+        let searcher = DefaultIndexSearcher::new(index_reader, None);
+        let term_query = |text: &str| -> Box<dyn Query<TestCodec>> {
+            Box::new(TermQuery::new(
+                Term::new("field".into(), text.as_bytes().to_vec()),
+                1.0,
+                None,
+            ))
+        };
+
+        // nested boosts collapse into a single one
+        let query = BoostQuery::build(BoostQuery::build(term_query("a"), 2.0), 3.0);
+        let rewritten = searcher.rewrite(query.as_ref()).unwrap().unwrap();
+        let boost_query = rewritten
+            .as_any()
+            .downcast_ref::<BoostQuery<TestCodec>>()
+            .unwrap();
+        assert!((boost_query.boost() - 6.0).abs() < ::std::f32::EPSILON);
+        assert!(boost_query.query().as_any().is::<TermQuery>());
+        assert!(searcher.rewrite(rewritten.as_ref()).unwrap().is_none());
+
+        // and vanish if they cancel out
+        let query = BoostQuery::build(BoostQuery::build(term_query("a"), 2.0), 0.5);
+        let rewritten = searcher.rewrite(query.as_ref()).unwrap().unwrap();
+        assert!(rewritten.as_any().is::<TermQuery>());
+    }
 }
@@ -22,7 +22,7 @@ use std::sync::Arc;
 
 use core::codec::doc_values::NumericDocValues;
 use core::codec::field_infos::FieldInvertState;
-use core::codec::Codec;
+use core::codec::{Codec, Impact};
 use core::index::reader::SearchLeafReader;
 use core::search::explanation::Explanation;
 use core::search::similarity::{SimScorer, SimWeight, Similarity};
@@ -92,7 +92,7 @@ impl BM25Similarity {
     }
 
     #[inline]
-    fn decode_norm_value(b: usize) -> f32 {
+    pub(crate) fn decode_norm_value(b: usize) -> f32 {
         NORM_TABLE[b]
     }
 
@@ -220,6 +220,17 @@ impl SimScorer for BM25SimScorer {
     fn compute_slop_factor(&self, distance: i32) -> f32 {
         BM25Similarity::sloppy_freq(distance)
     }
+
+    fn max_score(&self, impact: &Impact) -> f32 {
+        // the cache decreases with the encoded norm, shorter fields score higher
+        let norm = if self.norms.is_some() {
+            self.cache[(impact.norm & 0xFF) as usize]
+        } else {
+            self.k1
+        };
+        let freq = impact.freq as f32;
+        self.weight * (self.k1 + 1.0) * freq / (freq + norm)
+    }
 }
 
 struct BM25SimWeight {
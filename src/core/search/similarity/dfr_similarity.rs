@@ -0,0 +1,443 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Divergence from randomness (DFR) framework. Introduced in Gianni Amati and
+//! Cornelis Joost Van Rijsbergen. 2002. Probabilistic models of information
+//! retrieval based on measuring the divergence from randomness. ACM Trans.
+//! Inf. Syst. 20, 4 (October 2002), 357-389.
+
+use std::f64::consts::{E, LOG2_E, PI};
+use std::fmt;
+
+use core::search::explanation::Explanation;
+use core::search::similarity::{value_explanation, BasicStats, SimilarityBase};
+
+/// The basic model of information content of a DFR similarity.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BasicModel {
+    /// Limiting form of the Bose-Einstein model.
+    BE,
+    /// Divergence approximation of the binomial model.
+    D,
+    /// Geometric as limiting form of the Bose-Einstein model.
+    G,
+    /// Tf-idf with the frequency of the term in the collection.
+    IF,
+    /// Tf-idf with the document frequency of the term.
+    In,
+    /// Tf-idf with the expected document frequency of the term.
+    Ine,
+    /// Poisson approximation of the binomial model.
+    P,
+}
+
+impl BasicModel {
+    /// Returns the informative content of the normalized term frequency `tfn`.
+    pub fn score(self, stats: &BasicStats, tfn: f32) -> f32 {
+        let tfn = f64::from(tfn);
+        let n = stats.number_of_documents as f64;
+        let score = match self {
+            BasicModel::BE => {
+                let f = stats.total_term_freq as f64 + 1.0 + tfn;
+                // approximation only holds true when F << N, so we use N += F
+                let n = n + f;
+                -((n - 1.0) * E).log2() + Self::be_f(n + f - 1.0, n + f - tfn - 2.0)
+                    - Self::be_f(f, f - tfn)
+            }
+            BasicModel::D => {
+                // we have to ensure phi is always < 1 for tiny TTF values,
+                // otherwise nphi can go negative, resulting in NaN
+                let f = stats.total_term_freq as f64 + 1.0 + tfn;
+                let phi = tfn / f;
+                let nphi = 1.0 - phi;
+                let p = 1.0 / (n + 1.0);
+                let d = phi * (phi / p).log2() + nphi * (nphi / (1.0 - p)).log2();
+                d * f + 0.5 * (1.0 + 2.0 * PI * tfn * nphi).log2()
+            }
+            BasicModel::G => {
+                // just like in BE, approximation only holds true when F << N
+                let f = stats.total_term_freq as f64 + 1.0;
+                let lambda = f / (n + f);
+                (lambda + 1.0).log2() + tfn * ((1.0 + lambda) / lambda).log2()
+            }
+            BasicModel::IF => {
+                let f = stats.total_term_freq as f64;
+                tfn * (1.0 + (n + 1.0) / (f + 0.5)).log2()
+            }
+            BasicModel::In => {
+                let df = stats.doc_freq as f64;
+                tfn * ((n + 1.0) / (df + 0.5)).log2()
+            }
+            BasicModel::Ine => {
+                let f = stats.total_term_freq as f64;
+                let ne = n * (1.0 - ((n - 1.0) / n).powf(f));
+                tfn * ((n + 1.0) / (ne + 0.5)).log2()
+            }
+            BasicModel::P => {
+                let lambda = (stats.total_term_freq as f64 + 1.0) / (n + 1.0);
+                tfn * (tfn / lambda).log2()
+                    + (lambda + 1.0 / (12.0 * tfn) - tfn) * LOG2_E
+                    + 0.5 * (2.0 * PI * tfn).log2()
+            }
+        };
+        score as f32
+    }
+
+    pub fn explain(self, stats: &BasicStats, tfn: f32) -> Explanation {
+        let mut subs = vec![
+            value_explanation(tfn, "tfn"),
+            value_explanation(stats.number_of_documents as f32, "numberOfDocuments"),
+        ];
+        match self {
+            BasicModel::In => {
+                subs.push(value_explanation(stats.doc_freq as f32, "docFreq"));
+            }
+            _ => {
+                subs.push(value_explanation(
+                    stats.total_term_freq as f32,
+                    "totalTermFreq",
+                ));
+            }
+        }
+        Explanation::new(
+            true,
+            self.score(stats, tfn),
+            format!("basic model {}, computed from:", self),
+            subs,
+        )
+    }
+
+    /// The f helper function of the BE model.
+    fn be_f(n: f64, m: f64) -> f64 {
+        (m + 0.5) * (n / m).log2() + (n - m) * n.log2()
+    }
+}
+
+impl fmt::Display for BasicModel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            BasicModel::BE => "Be",
+            BasicModel::D => "D",
+            BasicModel::G => "G",
+            BasicModel::IF => "I(F)",
+            BasicModel::In => "I(n)",
+            BasicModel::Ine => "I(ne)",
+            BasicModel::P => "P",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// The first normalization of a DFR similarity, the information gain of
+/// another occurrence of the term.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AfterEffect {
+    /// Ratio of two Bernoulli processes.
+    B,
+    /// Laplace's law of succession.
+    L,
+    /// No first normalization.
+    NoAfterEffect,
+}
+
+impl AfterEffect {
+    pub fn score(self, stats: &BasicStats, tfn: f32) -> f32 {
+        match self {
+            AfterEffect::B => {
+                let f = stats.total_term_freq as f32 + 1.0;
+                let n = stats.doc_freq as f32 + 1.0;
+                (f + 1.0) / (n * (tfn + 1.0))
+            }
+            AfterEffect::L => 1.0 / (tfn + 1.0),
+            AfterEffect::NoAfterEffect => 1.0,
+        }
+    }
+
+    pub fn explain(self, stats: &BasicStats, tfn: f32) -> Explanation {
+        let subs = match self {
+            AfterEffect::B => vec![
+                value_explanation(tfn, "tfn"),
+                value_explanation(stats.total_term_freq as f32, "totalTermFreq"),
+                value_explanation(stats.doc_freq as f32, "docFreq"),
+            ],
+            AfterEffect::L => vec![value_explanation(tfn, "tfn")],
+            AfterEffect::NoAfterEffect => {
+                return value_explanation(1.0, "no aftereffect");
+            }
+        };
+        Explanation::new(
+            true,
+            self.score(stats, tfn),
+            format!("after effect {}, computed from:", self),
+            subs,
+        )
+    }
+}
+
+impl fmt::Display for AfterEffect {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AfterEffect::B => write!(f, "B"),
+            AfterEffect::L => write!(f, "L"),
+            AfterEffect::NoAfterEffect => Ok(()),
+        }
+    }
+}
+
+/// The second normalization of a DFR or IB similarity, the normalization of
+/// the term frequency by the length of the field.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Normalization {
+    /// Uniform distribution of the term frequency, `tf * c * avgFieldLength / len`.
+    H1 { c: f32 },
+    /// The term frequency density is inversely related to the length,
+    /// `tf * log2(1 + c * avgFieldLength / len)`.
+    H2 { c: f32 },
+    /// Dirichlet priors, `(tf + mu * p) / (len + mu) * mu`, where `p` is the
+    /// probability of the term in the collection.
+    H3 { mu: f32 },
+    /// Pareto-Zipf normalization, `tf * (avgFieldLength / len) ^ z`.
+    Z { z: f32 },
+    /// No second normalization.
+    NoNormalization,
+}
+
+impl Normalization {
+    pub fn h1() -> Normalization {
+        Normalization::H1 { c: 1.0 }
+    }
+
+    pub fn h2() -> Normalization {
+        Normalization::H2 { c: 1.0 }
+    }
+
+    pub fn h3() -> Normalization {
+        Normalization::H3 { mu: 800.0 }
+    }
+
+    pub fn z() -> Normalization {
+        Normalization::Z { z: 0.3 }
+    }
+
+    /// Returns the normalized term frequency.
+    pub fn tfn(self, stats: &BasicStats, tf: f32, len: f32) -> f32 {
+        match self {
+            Normalization::H1 { c } => tf * c * (stats.avg_field_length / len),
+            Normalization::H2 { c } => {
+                tf * (f64::from(1.0 + c * stats.avg_field_length / len)).log2() as f32
+            }
+            Normalization::H3 { mu } => {
+                let p = (stats.total_term_freq as f32 + 1.0)
+                    / (stats.number_of_field_tokens as f32 + 1.0);
+                (tf + mu * p) / (len + mu) * mu
+            }
+            Normalization::Z { z } => {
+                tf * (f64::from(stats.avg_field_length / len)).powf(f64::from(z)) as f32
+            }
+            Normalization::NoNormalization => tf,
+        }
+    }
+
+    pub fn explain(self, stats: &BasicStats, tf: f32, len: f32) -> Explanation {
+        let mut subs = vec![value_explanation(tf, "tf")];
+        match self {
+            Normalization::H1 { c } | Normalization::H2 { c } => {
+                subs.push(value_explanation(c, "c"));
+                subs.push(value_explanation(stats.avg_field_length, "avgFieldLength"));
+                subs.push(value_explanation(len, "len"));
+            }
+            Normalization::H3 { mu } => {
+                subs.push(value_explanation(mu, "mu"));
+                subs.push(value_explanation(
+                    stats.total_term_freq as f32,
+                    "totalTermFreq",
+                ));
+                subs.push(value_explanation(
+                    stats.number_of_field_tokens as f32,
+                    "numberOfFieldTokens",
+                ));
+                subs.push(value_explanation(len, "len"));
+            }
+            Normalization::Z { z } => {
+                subs.push(value_explanation(z, "z"));
+                subs.push(value_explanation(stats.avg_field_length, "avgFieldLength"));
+                subs.push(value_explanation(len, "len"));
+            }
+            Normalization::NoNormalization => {
+                return value_explanation(tf, "no normalization");
+            }
+        }
+        Explanation::new(
+            true,
+            self.tfn(stats, tf, len),
+            format!("normalization {}, computed from:", self),
+            subs,
+        )
+    }
+}
+
+impl fmt::Display for Normalization {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Normalization::H1 { c } => write!(f, "1({})", c),
+            Normalization::H2 { c } => write!(f, "2({})", c),
+            Normalization::H3 { mu } => write!(f, "3({})", mu),
+            Normalization::Z { z } => write!(f, "Z({})", z),
+            Normalization::NoNormalization => Ok(()),
+        }
+    }
+}
+
+/// Implements the divergence from randomness (DFR) framework.
+///
+/// The DFR scoring formula is composed of three separate components: the
+/// basic model, the after effect and an additional normalization component,
+/// represented by `BasicModel`, `AfterEffect` and `Normalization`:
+///
+/// `score = boost * basicModel.score(tfn) * afterEffect.score(tfn)`, where
+/// `tfn = normalization.tfn(freq, len)`.
+///
+/// Note that qtf, the multiplicity of the term occurrence in the query, is
+/// not handled by this implementation.
+#[derive(Debug, Clone)]
+pub struct DFRSimilarity {
+    basic_model: BasicModel,
+    after_effect: AfterEffect,
+    normalization: Normalization,
+}
+
+impl DFRSimilarity {
+    pub fn new(
+        basic_model: BasicModel,
+        after_effect: AfterEffect,
+        normalization: Normalization,
+    ) -> DFRSimilarity {
+        DFRSimilarity {
+            basic_model,
+            after_effect,
+            normalization,
+        }
+    }
+
+    pub fn basic_model(&self) -> BasicModel {
+        self.basic_model
+    }
+
+    pub fn after_effect(&self) -> AfterEffect {
+        self.after_effect
+    }
+
+    pub fn normalization(&self) -> Normalization {
+        self.normalization
+    }
+}
+
+impl SimilarityBase for DFRSimilarity {
+    fn score(&self, stats: &BasicStats, freq: f32, doc_len: f32) -> f32 {
+        let tfn = self.normalization.tfn(stats, freq, doc_len);
+        stats.boost * self.basic_model.score(stats, tfn) * self.after_effect.score(stats, tfn)
+    }
+
+    fn explain(&self, subs: &mut Vec<Explanation>, stats: &BasicStats, freq: f32, doc_len: f32) {
+        let norm_explanation = self.normalization.explain(stats, freq, doc_len);
+        let tfn = norm_explanation.value();
+        subs.push(norm_explanation);
+        subs.push(self.basic_model.explain(stats, tfn));
+        subs.push(self.after_effect.explain(stats, tfn));
+    }
+}
+
+impl fmt::Display for DFRSimilarity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "DFR {}{}{}",
+            self.basic_model, self.after_effect, self.normalization
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::index::tests::MockLeafReader;
+    use core::search::similarity::Similarity;
+    use core::search::statistics::{CollectionStatistics, TermStatistics};
+
+    #[test]
+    fn test_dfr_similarity() {
+        let collection_stats =
+            CollectionStatistics::new(String::from("world"), 0, 32, 32, 6400, -1);
+        let term_stats = vec![TermStatistics::new(Vec::new(), 4, 10)];
+        let leaf_reader = MockLeafReader::new(1);
+
+        let basic_models = [
+            BasicModel::BE,
+            BasicModel::D,
+            BasicModel::G,
+            BasicModel::IF,
+            BasicModel::In,
+            BasicModel::Ine,
+            BasicModel::P,
+        ];
+        let after_effects = [AfterEffect::B, AfterEffect::L, AfterEffect::NoAfterEffect];
+        let normalizations = [
+            Normalization::h1(),
+            Normalization::h2(),
+            Normalization::h3(),
+            Normalization::z(),
+        ];
+        for &basic_model in &basic_models {
+            for &after_effect in &after_effects {
+                for &normalization in &normalizations {
+                    let sim = DFRSimilarity::new(basic_model, after_effect, normalization);
+                    let sim_weight =
+                        sim.compute_weight(&collection_stats, &term_stats, None, 1.0f32);
+                    let mut sim_scorer = sim_weight.sim_scorer(&leaf_reader).unwrap();
+
+                    // doc 1 is shorter than doc 2
+                    let short_1 = sim_scorer.score(1, 1.0).unwrap();
+                    let short_3 = sim_scorer.score(1, 3.0).unwrap();
+                    let long_1 = sim_scorer.score(2, 1.0).unwrap();
+                    let long_3 = sim_scorer.score(2, 3.0).unwrap();
+                    assert!(long_1 > 0.0 && long_1.is_finite(), "{}", sim);
+                    assert!(short_3 > short_1, "{}", sim);
+                    assert!(long_3 > long_1, "{}", sim);
+                    assert!(short_1 > long_1, "{}", sim);
+
+                    let freq = Explanation::new(true, 3.0, "freq".to_string(), vec![]);
+                    let explanation = sim_weight.explain(&leaf_reader, 1, freq).unwrap();
+                    assert!((explanation.value() - short_3).abs() < ::std::f32::EPSILON);
+                    // freq, tfn, basic model and after effect
+                    assert_eq!(explanation.details().len(), 4);
+                }
+            }
+        }
+
+        let sim = DFRSimilarity::new(BasicModel::IF, AfterEffect::B, Normalization::h2());
+        assert_eq!(sim.to_string(), "DFR I(F)B2(1)");
+        let boosted = sim.compute_weight(&collection_stats, &term_stats, None, 2.0f32);
+        let sim_weight = sim.compute_weight(&collection_stats, &term_stats, None, 1.0f32);
+        let score = boosted
+            .sim_scorer(&leaf_reader)
+            .unwrap()
+            .score(1, 2.0)
+            .unwrap();
+        let expected = sim_weight
+            .sim_scorer(&leaf_reader)
+            .unwrap()
+            .score(1, 2.0)
+            .unwrap();
+        assert!((score - 2.0 * expected).abs() < 1e-5);
+    }
+}
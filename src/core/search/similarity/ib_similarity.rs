@@ -0,0 +1,212 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Information based models. Introduced in Stéphane Clinchant and Eric
+//! Gaussier. 2010. Information-based models for ad hoc IR. In Proceeding of
+//! the 33rd international ACM SIGIR conference on Research and development in
+//! information retrieval (SIGIR '10). ACM, New York, NY, USA, 234-241.
+
+use std::fmt;
+
+use core::search::explanation::Explanation;
+use core::search::similarity::{value_explanation, BasicStats, Normalization, SimilarityBase};
+
+/// The probabilistic distribution of an IB similarity.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Distribution {
+    /// Log-logistic distribution.
+    LL,
+    /// The smoothed power-law (SPL) distribution.
+    SPL,
+}
+
+impl Distribution {
+    pub fn score(self, tfn: f32, lambda: f32) -> f32 {
+        let tfn = f64::from(tfn);
+        let lambda = f64::from(lambda);
+        let score = match self {
+            Distribution::LL => -(lambda / (tfn + lambda)).ln(),
+            Distribution::SPL => {
+                // the formula is undefined for lambda == 1
+                let lambda = if (lambda - 1.0).abs() < ::std::f64::EPSILON {
+                    0.99
+                } else {
+                    lambda
+                };
+                -((lambda.powf(tfn / (tfn + 1.0)) - lambda) / (1.0 - lambda)).ln()
+            }
+        };
+        score as f32
+    }
+}
+
+impl fmt::Display for Distribution {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Distribution::LL => write!(f, "LL"),
+            Distribution::SPL => write!(f, "SPL"),
+        }
+    }
+}
+
+/// The `lambda` parameter of the distribution of an IB similarity.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Lambda {
+    /// Computed as `(docFreq + 1) / (numberOfDocuments + 1)`.
+    DF,
+    /// Computed as `(totalTermFreq + 1) / (numberOfDocuments + 1)`.
+    TTF,
+}
+
+impl Lambda {
+    pub fn lambda(self, stats: &BasicStats) -> f32 {
+        let freq = match self {
+            Lambda::DF => stats.doc_freq,
+            Lambda::TTF => stats.total_term_freq,
+        };
+        (freq as f32 + 1.0) / (stats.number_of_documents as f32 + 1.0)
+    }
+
+    pub fn explain(self, stats: &BasicStats) -> Explanation {
+        let freq = match self {
+            Lambda::DF => value_explanation(stats.doc_freq as f32, "docFreq"),
+            Lambda::TTF => value_explanation(stats.total_term_freq as f32, "totalTermFreq"),
+        };
+        Explanation::new(
+            true,
+            self.lambda(stats),
+            format!("lambda {}, computed from:", self),
+            vec![
+                freq,
+                value_explanation(stats.number_of_documents as f32, "numberOfDocuments"),
+            ],
+        )
+    }
+}
+
+impl fmt::Display for Lambda {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Lambda::DF => write!(f, "D"),
+            Lambda::TTF => write!(f, "L"),
+        }
+    }
+}
+
+/// Provides a framework for the family of information-based models.
+///
+/// The retrieval function is of the form `RSV(q, d) = Σ -x^q_w log Prob(X_w >=
+/// t^d_w | λ_w)`, where `x^q_w` is the query boost, `X_w` follows the
+/// `Distribution` with the parameter `λ_w` given by `Lambda`, and `t^d_w` is
+/// the term frequency normalized by `Normalization`.
+#[derive(Debug, Clone)]
+pub struct IBSimilarity {
+    distribution: Distribution,
+    lambda: Lambda,
+    normalization: Normalization,
+}
+
+impl IBSimilarity {
+    pub fn new(
+        distribution: Distribution,
+        lambda: Lambda,
+        normalization: Normalization,
+    ) -> IBSimilarity {
+        IBSimilarity {
+            distribution,
+            lambda,
+            normalization,
+        }
+    }
+
+    pub fn distribution(&self) -> Distribution {
+        self.distribution
+    }
+
+    pub fn lambda(&self) -> Lambda {
+        self.lambda
+    }
+
+    pub fn normalization(&self) -> Normalization {
+        self.normalization
+    }
+}
+
+impl SimilarityBase for IBSimilarity {
+    fn score(&self, stats: &BasicStats, freq: f32, doc_len: f32) -> f32 {
+        let tfn = self.normalization.tfn(stats, freq, doc_len);
+        stats.boost * self.distribution.score(tfn, self.lambda.lambda(stats))
+    }
+
+    fn explain(&self, subs: &mut Vec<Explanation>, stats: &BasicStats, freq: f32, doc_len: f32) {
+        let norm_explanation = self.normalization.explain(stats, freq, doc_len);
+        let lambda_explanation = self.lambda.explain(stats);
+        let score = self
+            .distribution
+            .score(norm_explanation.value(), lambda_explanation.value());
+        subs.push(norm_explanation);
+        subs.push(lambda_explanation);
+        subs.push(value_explanation(
+            score,
+            &format!("distribution {}", self.distribution),
+        ));
+    }
+}
+
+impl fmt::Display for IBSimilarity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "IB {}-{}{}",
+            self.distribution, self.lambda, self.normalization
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::index::tests::MockLeafReader;
+    use core::search::similarity::Similarity;
+    use core::search::statistics::{CollectionStatistics, TermStatistics};
+
+    #[test]
+    fn test_ib_similarity() {
+        let collection_stats =
+            CollectionStatistics::new(String::from("world"), 0, 32, 32, 6400, -1);
+        let term_stats = vec![TermStatistics::new(Vec::new(), 4, 10)];
+        let leaf_reader = MockLeafReader::new(1);
+
+        for &distribution in &[Distribution::LL, Distribution::SPL] {
+            for &lambda in &[Lambda::DF, Lambda::TTF] {
+                let sim = IBSimilarity::new(distribution, lambda, Normalization::h2());
+                let sim_weight = sim.compute_weight(&collection_stats, &term_stats, None, 1.0f32);
+                let mut sim_scorer = sim_weight.sim_scorer(&leaf_reader).unwrap();
+
+                let short_1 = sim_scorer.score(1, 1.0).unwrap();
+                let short_3 = sim_scorer.score(1, 3.0).unwrap();
+                let long_1 = sim_scorer.score(2, 1.0).unwrap();
+                assert!(long_1 > 0.0 && long_1.is_finite(), "{}", sim);
+                assert!(short_3 > short_1, "{}", sim);
+                assert!(short_1 > long_1, "{}", sim);
+
+                let freq = Explanation::new(true, 1.0, "freq".to_string(), vec![]);
+                let explanation = sim_weight.explain(&leaf_reader, 2, freq).unwrap();
+                assert!((explanation.value() - long_1).abs() < ::std::f32::EPSILON);
+            }
+        }
+
+        let sim = IBSimilarity::new(Distribution::SPL, Lambda::DF, Normalization::h1());
+        assert_eq!(sim.to_string(), "IB SPL-D1(1)");
+    }
+}
@@ -0,0 +1,200 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Language models with Bayesian smoothing, from Chengxiang Zhai and John
+//! Lafferty. 2004. A study of smoothing methods for language models applied
+//! to information retrieval. ACM Trans. Inf. Syst. 22, 2 (April 2004),
+//! 179-214.
+
+use std::fmt;
+
+use core::search::explanation::Explanation;
+use core::search::similarity::{value_explanation, BasicStats, SimilarityBase};
+use error::{ErrorKind::IllegalArgument, Result};
+
+pub const DEFAULT_LM_DIRICHLET_MU: f32 = 2000.0;
+
+/// Returns the probability of the term in the collection, with the default
+/// collection model `(totalTermFreq + 1) / (numberOfFieldTokens + 1)`.
+fn collection_probability(stats: &BasicStats) -> f32 {
+    (stats.total_term_freq as f32 + 1.0) / (stats.number_of_field_tokens as f32 + 1.0)
+}
+
+fn collection_probability_explanation(stats: &BasicStats) -> Explanation {
+    Explanation::new(
+        true,
+        collection_probability(stats),
+        "collection probability, computed as (totalTermFreq + 1) / (numberOfFieldTokens + 1) from:"
+            .to_string(),
+        vec![
+            value_explanation(stats.total_term_freq as f32, "totalTermFreq"),
+            value_explanation(stats.number_of_field_tokens as f32, "numberOfFieldTokens"),
+        ],
+    )
+}
+
+/// Bayesian smoothing using Dirichlet priors.
+///
+/// The formula as defined in the paper assigns a negative score to documents
+/// that contain the term, but with fewer occurrences than predicted by the
+/// collection language model. This implementation assigns them a score of 0
+/// instead.
+#[derive(Debug, Clone)]
+pub struct LMDirichletSimilarity {
+    mu: f32,
+}
+
+impl Default for LMDirichletSimilarity {
+    fn default() -> Self {
+        LMDirichletSimilarity {
+            mu: DEFAULT_LM_DIRICHLET_MU,
+        }
+    }
+}
+
+impl LMDirichletSimilarity {
+    pub fn new(mu: f32) -> Result<LMDirichletSimilarity> {
+        if !mu.is_finite() || mu <= 0.0 {
+            bail!(IllegalArgument(format!(
+                "mu must be a finite number > 0, got {}",
+                mu
+            )));
+        }
+        Ok(LMDirichletSimilarity { mu })
+    }
+
+    pub fn mu(&self) -> f32 {
+        self.mu
+    }
+}
+
+impl SimilarityBase for LMDirichletSimilarity {
+    fn score(&self, stats: &BasicStats, freq: f32, doc_len: f32) -> f32 {
+        let p = f64::from(collection_probability(stats));
+        let mu = f64::from(self.mu);
+        let score = f64::from(stats.boost)
+            * ((1.0 + f64::from(freq) / (mu * p)).ln() + (mu / (f64::from(doc_len) + mu)).ln());
+        if score > 0.0 {
+            score as f32
+        } else {
+            0.0
+        }
+    }
+
+    fn explain(&self, subs: &mut Vec<Explanation>, stats: &BasicStats, freq: f32, doc_len: f32) {
+        let p = collection_probability(stats);
+        subs.push(value_explanation(self.mu, "mu"));
+        subs.push(value_explanation(
+            (1.0 + f64::from(freq) / f64::from(self.mu * p)).ln() as f32,
+            "term weight",
+        ));
+        subs.push(value_explanation(
+            (f64::from(self.mu) / f64::from(doc_len + self.mu)).ln() as f32,
+            "document norm",
+        ));
+        subs.push(collection_probability_explanation(stats));
+    }
+}
+
+impl fmt::Display for LMDirichletSimilarity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "LM Dirichlet({})", self.mu)
+    }
+}
+
+/// Language model based on the Jelinek-Mercer smoothing method, which linearly
+/// interpolates the maximum likelihood model of the document with the
+/// collection model, weighted by `lambda`.
+///
+/// The optimal value of `lambda` depends on both the collection and the query,
+/// it is around 0.1 for title queries and 0.7 for long queries.
+#[derive(Debug, Clone)]
+pub struct LMJelinekMercerSimilarity {
+    lambda: f32,
+}
+
+impl LMJelinekMercerSimilarity {
+    pub fn new(lambda: f32) -> Result<LMJelinekMercerSimilarity> {
+        if lambda.is_nan() || lambda <= 0.0 || lambda > 1.0 {
+            bail!(IllegalArgument(format!(
+                "lambda must be in (0, 1], got {}",
+                lambda
+            )));
+        }
+        Ok(LMJelinekMercerSimilarity { lambda })
+    }
+
+    pub fn lambda(&self) -> f32 {
+        self.lambda
+    }
+}
+
+impl SimilarityBase for LMJelinekMercerSimilarity {
+    fn score(&self, stats: &BasicStats, freq: f32, doc_len: f32) -> f32 {
+        let p = collection_probability(stats);
+        let score = 1.0 + ((1.0 - self.lambda) * freq / doc_len) / (self.lambda * p);
+        stats.boost * f64::from(score).ln() as f32
+    }
+
+    fn explain(&self, subs: &mut Vec<Explanation>, stats: &BasicStats, _freq: f32, _doc_len: f32) {
+        subs.push(value_explanation(self.lambda, "lambda"));
+        subs.push(collection_probability_explanation(stats));
+    }
+}
+
+impl fmt::Display for LMJelinekMercerSimilarity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "LM Jelinek-Mercer({})", self.lambda)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::index::tests::MockLeafReader;
+    use core::search::similarity::Similarity;
+    use core::search::statistics::{CollectionStatistics, TermStatistics};
+
+    #[test]
+    fn test_lm_similarity() {
+        let collection_stats =
+            CollectionStatistics::new(String::from("world"), 0, 32, 32, 6400, -1);
+        let term_stats = vec![TermStatistics::new(Vec::new(), 4, 10)];
+        let leaf_reader = MockLeafReader::new(1);
+
+        let sim = LMDirichletSimilarity::default();
+        let sim_weight = sim.compute_weight(&collection_stats, &term_stats, None, 1.0f32);
+        let mut sim_scorer = sim_weight.sim_scorer(&leaf_reader).unwrap();
+        let short_1 = sim_scorer.score(1, 1.0).unwrap();
+        assert!(short_1 > 0.0);
+        assert!(sim_scorer.score(1, 3.0).unwrap() > short_1);
+        // fewer occurrences than predicted by the collection model
+        assert!(sim_scorer.score(2, 1.0).unwrap().abs() < ::std::f32::EPSILON);
+        let freq = Explanation::new(true, 1.0, "freq".to_string(), vec![]);
+        let explanation = sim_weight.explain(&leaf_reader, 1, freq).unwrap();
+        assert!((explanation.value() - short_1).abs() < ::std::f32::EPSILON);
+        assert_eq!(sim.to_string(), "LM Dirichlet(2000)");
+
+        let sim = LMJelinekMercerSimilarity::new(0.7).unwrap();
+        let sim_weight = sim.compute_weight(&collection_stats, &term_stats, None, 1.0f32);
+        let mut sim_scorer = sim_weight.sim_scorer(&leaf_reader).unwrap();
+        let short_1 = sim_scorer.score(1, 1.0).unwrap();
+        let long_1 = sim_scorer.score(2, 1.0).unwrap();
+        assert!(short_1 > long_1 && long_1 > 0.0);
+        assert!(sim_scorer.score(1, 3.0).unwrap() > short_1);
+
+        assert!(LMDirichletSimilarity::new(0.0).is_err());
+        assert!(LMJelinekMercerSimilarity::new(0.0).is_err());
+        assert!(LMJelinekMercerSimilarity::new(1.5).is_err());
+    }
+}
@@ -15,14 +15,40 @@ mod bm25_similarity;
 
 pub use self::bm25_similarity::*;
 
+mod tfidf_similarity;
+
+pub use self::tfidf_similarity::*;
+
+mod similarity_base;
+
+pub use self::similarity_base::*;
+
+mod dfr_similarity;
+
+pub use self::dfr_similarity::*;
+
+mod ib_similarity;
+
+pub use self::ib_similarity::*;
+
+mod lm_similarity;
+
+pub use self::lm_similarity::*;
+
+mod multi_similarity;
+
+pub use self::multi_similarity::*;
+
 use core::util::{DocId, KeyedContext};
 
-use core::codec::Codec;
+use core::codec::{Codec, Impact};
 use core::index::reader::SearchLeafReader;
 use core::search::explanation::Explanation;
 use core::search::statistics::{CollectionStatistics, TermStatistics};
 use error::Result;
+use std::collections::HashMap;
 use std::fmt::Display;
+use std::sync::Arc;
 
 /// Similarity defines the components of Lucene scoring.
 ///
@@ -126,6 +152,50 @@ impl<C: Codec> SimilarityProducer<C> for Box<dyn SimilarityProducer<C>> {
     }
 }
 
+impl<C: Codec> Similarity<C> for Arc<dyn Similarity<C>> {
+    fn compute_weight(
+        &self,
+        collection_stats: &CollectionStatistics,
+        term_stats: &[TermStatistics],
+        context: Option<&KeyedContext>,
+        boost: f32,
+    ) -> Box<dyn SimWeight<C>> {
+        (**self).compute_weight(collection_stats, term_stats, context, boost)
+    }
+
+    fn query_norm(&self, value_for_normalization: f32, context: Option<&KeyedContext>) -> f32 {
+        (**self).query_norm(value_for_normalization, context)
+    }
+}
+
+/// A `SimilarityProducer` that provides a different `Similarity` per field,
+/// and a default one for the other fields.
+pub struct PerFieldSimilarityProducer<C: Codec> {
+    default: Arc<dyn Similarity<C>>,
+    fields: HashMap<String, Arc<dyn Similarity<C>>>,
+}
+
+impl<C: Codec> PerFieldSimilarityProducer<C> {
+    pub fn new(default: Arc<dyn Similarity<C>>) -> PerFieldSimilarityProducer<C> {
+        PerFieldSimilarityProducer {
+            default,
+            fields: HashMap::new(),
+        }
+    }
+
+    /// Uses `similarity` for `field`.
+    pub fn set(&mut self, field: String, similarity: Arc<dyn Similarity<C>>) {
+        self.fields.insert(field, similarity);
+    }
+}
+
+impl<C: Codec> SimilarityProducer<C> for PerFieldSimilarityProducer<C> {
+    fn create(&self, field: &str) -> Box<dyn Similarity<C>> {
+        let similarity = self.fields.get(field).unwrap_or(&self.default);
+        Box::new(Arc::clone(similarity))
+    }
+}
+
 /// API for scoring "sloppy" queries such as `TermQuery`, `SpanQuery`, `PhraseQuery`.
 ///
 /// Frequencies are floating-point values: an approximate within-document
@@ -140,6 +210,12 @@ pub trait SimScorer: Send {
     /// Computes the amount of a sloppy phrase match, based on an edit distance.
     fn compute_slop_factor(&self, distance: i32) -> f32;
 
+    /// Returns an upper bound of the score of the docs whose freq and norm are
+    /// not more competitive than `impact`.
+    fn max_score(&self, _impact: &Impact) -> f32 {
+        ::std::f32::INFINITY
+    }
+
     // Calculate a scoring factor based on the data in the payload.
     // fn compute_payload_factor(&self, doc: DocId, start: i32, end: i32, payload: &Payload);
 }
@@ -0,0 +1,137 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use error::Result;
+use std::fmt;
+
+use core::codec::{Codec, Impact};
+use core::index::reader::SearchLeafReader;
+use core::search::explanation::Explanation;
+use core::search::similarity::{SimScorer, SimWeight, Similarity};
+use core::search::statistics::{CollectionStatistics, TermStatistics};
+use core::util::{DocId, KeyedContext};
+
+/// Implements the `Similarity` with the sum of the scores of several
+/// similarities, e.g. to combine the scores of different ranking functions.
+///
+/// The slop factor of sloppy queries is the one of the first similarity.
+pub struct MultiSimilarity<C: Codec> {
+    sims: Vec<Box<dyn Similarity<C>>>,
+}
+
+impl<C: Codec> MultiSimilarity<C> {
+    pub fn new(sims: Vec<Box<dyn Similarity<C>>>) -> MultiSimilarity<C> {
+        assert!(
+            !sims.is_empty(),
+            "MultiSimilarity needs at least one similarity"
+        );
+        MultiSimilarity { sims }
+    }
+}
+
+impl<C: Codec> Similarity<C> for MultiSimilarity<C> {
+    fn compute_weight(
+        &self,
+        collection_stats: &CollectionStatistics,
+        term_stats: &[TermStatistics],
+        context: Option<&KeyedContext>,
+        boost: f32,
+    ) -> Box<dyn SimWeight<C>> {
+        let weights = self
+            .sims
+            .iter()
+            .map(|sim| sim.compute_weight(collection_stats, term_stats, context, boost))
+            .collect();
+        Box::new(MultiSimWeight { weights })
+    }
+}
+
+impl<C: Codec> fmt::Display for MultiSimilarity<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "MultiSimilarity(")?;
+        for (i, sim) in self.sims.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", sim)?;
+        }
+        write!(f, ")")
+    }
+}
+
+struct MultiSimWeight<C: Codec> {
+    weights: Vec<Box<dyn SimWeight<C>>>,
+}
+
+impl<C: Codec> SimWeight<C> for MultiSimWeight<C> {
+    fn get_value_for_normalization(&self) -> f32 {
+        self.weights
+            .iter()
+            .map(|w| w.get_value_for_normalization())
+            .sum()
+    }
+
+    fn normalize(&mut self, query_norm: f32, boost: f32) {
+        for weight in &mut self.weights {
+            weight.normalize(query_norm, boost);
+        }
+    }
+
+    fn sim_scorer(&self, reader: &SearchLeafReader<C>) -> Result<Box<dyn SimScorer>> {
+        let mut scorers = Vec::with_capacity(self.weights.len());
+        for weight in &self.weights {
+            scorers.push(weight.sim_scorer(reader)?);
+        }
+        Ok(Box::new(MultiSimScorer { scorers }))
+    }
+
+    fn explain(
+        &self,
+        reader: &SearchLeafReader<C>,
+        doc: DocId,
+        freq: Explanation,
+    ) -> Result<Explanation> {
+        let mut subs = Vec::with_capacity(self.weights.len());
+        for weight in &self.weights {
+            subs.push(weight.explain(reader, doc, freq.clone())?);
+        }
+        Ok(Explanation::new(
+            true,
+            subs.iter().map(|e| e.value()).sum(),
+            format!("score(doc={},freq={}), sum of:", doc, freq.value()),
+            subs,
+        ))
+    }
+}
+
+struct MultiSimScorer {
+    scorers: Vec<Box<dyn SimScorer>>,
+}
+
+impl SimScorer for MultiSimScorer {
+    fn score(&mut self, doc: DocId, freq: f32) -> Result<f32> {
+        let mut sum = 0f32;
+        for scorer in &mut self.scorers {
+            sum += scorer.score(doc, freq)?;
+        }
+        Ok(sum)
+    }
+
+    fn compute_slop_factor(&self, distance: i32) -> f32 {
+        self.scorers[0].compute_slop_factor(distance)
+    }
+
+    fn max_score(&self, impact: &Impact) -> f32 {
+        self.scorers.iter().map(|s| s.max_score(impact)).sum()
+    }
+}
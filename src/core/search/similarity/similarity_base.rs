@@ -0,0 +1,242 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use error::Result;
+use std::fmt;
+
+use core::codec::doc_values::NumericDocValues;
+use core::codec::Codec;
+use core::index::reader::SearchLeafReader;
+use core::search::explanation::Explanation;
+use core::search::similarity::{BM25Similarity, SimScorer, SimWeight, Similarity};
+use core::search::statistics::{CollectionStatistics, TermStatistics};
+use core::util::{DocId, KeyedContext};
+
+/// The statistics of a term a `SimilarityBase` scores with.
+#[derive(Debug, Clone)]
+pub struct BasicStats {
+    /// The number of documents, `docCount` or `maxDoc` if unknown.
+    pub number_of_documents: i64,
+    /// The total number of tokens in the field.
+    pub number_of_field_tokens: i64,
+    /// The average length of the field.
+    pub avg_field_length: f32,
+    /// The document frequency of the term.
+    pub doc_freq: i64,
+    /// The total number of occurrences of the term.
+    pub total_term_freq: i64,
+    /// The query boost.
+    pub boost: f32,
+}
+
+impl BasicStats {
+    pub fn new(
+        collection_stats: &CollectionStatistics,
+        term_stats: &TermStatistics,
+        boost: f32,
+    ) -> BasicStats {
+        let number_of_documents = if collection_stats.doc_count == -1 {
+            collection_stats.max_doc
+        } else {
+            collection_stats.doc_count
+        };
+        let doc_freq = term_stats.doc_freq;
+        // the codec does not supply totalTermFreq: substitute docFreq
+        let total_term_freq = if term_stats.total_term_freq == -1 {
+            doc_freq
+        } else {
+            term_stats.total_term_freq
+        };
+        let sum_total_term_freq = collection_stats.sum_total_term_freq;
+        let (number_of_field_tokens, avg_field_length) = if sum_total_term_freq <= 0 {
+            // the field does not exist, or the stat is not supported
+            (doc_freq, 1f32)
+        } else {
+            (
+                sum_total_term_freq,
+                (sum_total_term_freq as f64 / number_of_documents as f64) as f32,
+            )
+        };
+        BasicStats {
+            number_of_documents,
+            number_of_field_tokens,
+            avg_field_length,
+            doc_freq,
+            total_term_freq,
+            boost,
+        }
+    }
+}
+
+/// A simple framework for the implementation of probabilistic retrieval
+/// models, e.g. `DFRSimilarity`, `IBSimilarity` or the language models.
+///
+/// An implementation only scores a single term from its `BasicStats`, the
+/// frequency of the term and the length of the field in the document, and
+/// gets a `Similarity` for free. The scores of the terms of a multi-term query
+/// are summed.
+///
+/// The length of the field is decoded from the same norms as
+/// `BM25Similarity`, so the models can be switched without reindexing.
+pub trait SimilarityBase: fmt::Display + Clone + Send + 'static {
+    /// Scores a document.
+    ///
+    /// @param stats the statistics of the term
+    /// @param freq the (sloppy) frequency of the term in the document
+    /// @param doc_len the length of the field in the document
+    fn score(&self, stats: &BasicStats, freq: f32, doc_len: f32) -> f32;
+
+    /// Adds the explanations of the factors of the score to `subs`, by
+    /// default there are none.
+    fn explain(
+        &self,
+        _subs: &mut Vec<Explanation>,
+        _stats: &BasicStats,
+        _freq: f32,
+        _doc_len: f32,
+    ) {
+    }
+}
+
+impl<C: Codec, T: SimilarityBase> Similarity<C> for T {
+    fn compute_weight(
+        &self,
+        collection_stats: &CollectionStatistics,
+        term_stats: &[TermStatistics],
+        _context: Option<&KeyedContext>,
+        boost: f32,
+    ) -> Box<dyn SimWeight<C>> {
+        let stats = term_stats
+            .iter()
+            .map(|term_stat| BasicStats::new(collection_stats, term_stat, boost))
+            .collect();
+        Box::new(BasicSimWeight {
+            similarity: self.clone(),
+            field: collection_stats.field.clone(),
+            stats,
+        })
+    }
+}
+
+/// Returns an explanation of a plain value, without details.
+pub(crate) fn value_explanation(value: f32, description: &str) -> Explanation {
+    Explanation::new(true, value, description.to_string(), vec![])
+}
+
+/// Returns the length of the field in `doc`, 1 if the field omits norms.
+fn doc_len(norms: &Option<Box<dyn NumericDocValues>>, doc: DocId) -> Result<f32> {
+    match norms {
+        Some(norms) => Ok(BM25Similarity::decode_norm_value(
+            (norms.get(doc)? & 0xFF) as usize,
+        )),
+        None => Ok(1f32),
+    }
+}
+
+struct BasicSimWeight<T: SimilarityBase> {
+    similarity: T,
+    field: String,
+    stats: Vec<BasicStats>,
+}
+
+impl<T: SimilarityBase> BasicSimWeight<T> {
+    fn explain_stats(
+        &self,
+        stats: &BasicStats,
+        doc: DocId,
+        freq: Explanation,
+        doc_len: f32,
+    ) -> Explanation {
+        let freq_value = freq.value();
+        let mut subs = vec![freq];
+        if (stats.boost - 1.0).abs() > ::std::f32::EPSILON {
+            subs.push(value_explanation(stats.boost, "boost"));
+        }
+        self.similarity
+            .explain(&mut subs, stats, freq_value, doc_len);
+        Explanation::new(
+            true,
+            self.similarity.score(stats, freq_value, doc_len),
+            format!(
+                "score({}, doc={}, freq={}), computed from:",
+                self.similarity, doc, freq_value
+            ),
+            subs,
+        )
+    }
+}
+
+impl<C: Codec, T: SimilarityBase> SimWeight<C> for BasicSimWeight<T> {
+    fn get_value_for_normalization(&self) -> f32 {
+        self.stats.iter().map(|s| s.boost * s.boost).sum()
+    }
+
+    fn normalize(&mut self, _query_norm: f32, boost: f32) {
+        for stats in &mut self.stats {
+            stats.boost = boost;
+        }
+    }
+
+    fn sim_scorer(&self, reader: &SearchLeafReader<C>) -> Result<Box<dyn SimScorer>> {
+        Ok(Box::new(BasicSimScorer {
+            similarity: self.similarity.clone(),
+            stats: self.stats.clone(),
+            norms: reader.norm_values(&self.field)?,
+        }))
+    }
+
+    fn explain(
+        &self,
+        reader: &SearchLeafReader<C>,
+        doc: DocId,
+        freq: Explanation,
+    ) -> Result<Explanation> {
+        let norms = reader.norm_values(&self.field)?;
+        let doc_len = doc_len(&norms, doc)?;
+        if self.stats.len() == 1 {
+            return Ok(self.explain_stats(&self.stats[0], doc, freq, doc_len));
+        }
+        let subs: Vec<Explanation> = self
+            .stats
+            .iter()
+            .map(|stats| self.explain_stats(stats, doc, freq.clone(), doc_len))
+            .collect();
+        Ok(Explanation::new(
+            true,
+            subs.iter().map(|e| e.value()).sum(),
+            format!("score(doc={},freq={}), sum of:", doc, freq.value()),
+            subs,
+        ))
+    }
+}
+
+struct BasicSimScorer<T: SimilarityBase> {
+    similarity: T,
+    stats: Vec<BasicStats>,
+    norms: Option<Box<dyn NumericDocValues>>,
+}
+
+impl<T: SimilarityBase> SimScorer for BasicSimScorer<T> {
+    fn score(&mut self, doc: DocId, freq: f32) -> Result<f32> {
+        let doc_len = doc_len(&self.norms, doc)?;
+        Ok(self
+            .stats
+            .iter()
+            .map(|stats| self.similarity.score(stats, freq, doc_len))
+            .sum())
+    }
+
+    fn compute_slop_factor(&self, distance: i32) -> f32 {
+        1.0 / (distance as f32 + 1.0)
+    }
+}
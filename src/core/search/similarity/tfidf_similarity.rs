@@ -0,0 +1,355 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use error::Result;
+use std::fmt;
+
+use core::codec::doc_values::NumericDocValues;
+use core::codec::{Codec, Impact};
+use core::index::reader::SearchLeafReader;
+use core::search::explanation::Explanation;
+use core::search::similarity::{value_explanation, SimScorer, SimWeight, Similarity};
+use core::search::statistics::{CollectionStatistics, TermStatistics};
+use core::util::SmallFloat;
+use core::util::{DocId, KeyedContext};
+
+lazy_static! {
+    static ref NORM_TABLE: [f32; 256] = {
+        let mut norm_table: [f32; 256] = [0f32; 256];
+        for (i, norm) in norm_table.iter_mut().enumerate() {
+            *norm = SmallFloat::byte315_to_float(i as u8);
+        }
+        norm_table
+    };
+}
+
+/// The classic Lucene scoring, a vector space model of TF-IDF.
+///
+/// `score(q, d) = Σ tf(t in d) * idf(t)^2 * boost(t) * norm(t, d)`, where
+/// `tf = sqrt(freq)`, `idf = 1 + ln((docCount + 1) / (docFreq + 1))` and
+/// `norm = 1 / sqrt(fieldLength)`, the index-time boost included.
+///
+/// The norms are the ones written at index time for `BM25Similarity`, both
+/// encode `boost / sqrt(fieldLength)` in a single byte.
+#[derive(Debug, Clone, Default)]
+pub struct ClassicSimilarity;
+
+impl ClassicSimilarity {
+    /// Implemented as `sqrt(freq)`.
+    pub fn tf(freq: f32) -> f32 {
+        freq.sqrt()
+    }
+
+    /// Implemented as `1 / (distance + 1)`.
+    pub fn sloppy_freq(distance: i32) -> f32 {
+        1.0 / (distance as f32 + 1.0)
+    }
+
+    /// Implemented as `ln((docCount + 1) / (docFreq + 1)) + 1`.
+    pub fn idf(doc_freq: i64, doc_count: i64) -> f32 {
+        ((doc_count as f64 + 1.0) / (doc_freq as f64 + 1.0)).ln() as f32 + 1.0
+    }
+
+    #[inline]
+    fn decode_norm_value(b: usize) -> f32 {
+        NORM_TABLE[b]
+    }
+
+    fn idf_explain(
+        collection_stats: &CollectionStatistics,
+        term_stats: &[TermStatistics],
+    ) -> Explanation {
+        let doc_count = if collection_stats.doc_count == -1 {
+            collection_stats.max_doc
+        } else {
+            collection_stats.doc_count
+        };
+        let details: Vec<Explanation> = term_stats
+            .iter()
+            .map(|stat| {
+                Explanation::new(
+                    true,
+                    ClassicSimilarity::idf(stat.doc_freq, doc_count),
+                    "idf, computed as log((docCount+1)/(docFreq+1)) + 1 from:".to_string(),
+                    vec![
+                        value_explanation(stat.doc_freq as f32, "docFreq"),
+                        value_explanation(doc_count as f32, "docCount"),
+                    ],
+                )
+            })
+            .collect();
+        if details.len() == 1 {
+            return details.into_iter().next().unwrap();
+        }
+        Explanation::new(
+            true,
+            details.iter().map(|e| e.value()).sum(),
+            "idf(), sum of:".to_string(),
+            details,
+        )
+    }
+}
+
+impl<C: Codec> Similarity<C> for ClassicSimilarity {
+    fn compute_weight(
+        &self,
+        collection_stats: &CollectionStatistics,
+        term_stats: &[TermStatistics],
+        _context: Option<&KeyedContext>,
+        boost: f32,
+    ) -> Box<dyn SimWeight<C>> {
+        let idf = ClassicSimilarity::idf_explain(collection_stats, term_stats);
+        let mut weight = TFIDFSimWeight {
+            field: collection_stats.field.clone(),
+            idf,
+            boost: 1.0,
+            query_norm: 1.0,
+            query_weight: 0.0,
+            value: 0.0,
+        };
+        weight.do_normalize(1.0, boost);
+        Box::new(weight)
+    }
+
+    /// Implemented as `1 / sqrt(sumOfSquaredWeights)`.
+    fn query_norm(&self, value_for_normalization: f32, _context: Option<&KeyedContext>) -> f32 {
+        1.0 / value_for_normalization.sqrt()
+    }
+}
+
+impl fmt::Display for ClassicSimilarity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ClassicSimilarity")
+    }
+}
+
+struct TFIDFSimWeight {
+    field: String,
+    idf: Explanation,
+    boost: f32,
+    query_norm: f32,
+    // idf * boost * query_norm
+    query_weight: f32,
+    // query_weight * idf
+    value: f32,
+}
+
+impl TFIDFSimWeight {
+    fn do_normalize(&mut self, query_norm: f32, boost: f32) {
+        self.boost = boost;
+        self.query_norm = query_norm;
+        self.query_weight = query_norm * boost * self.idf.value();
+        self.value = self.query_weight * self.idf.value();
+    }
+
+    fn explain_query(&self) -> Explanation {
+        let mut subs = vec![];
+        if (self.boost - 1.0).abs() > ::std::f32::EPSILON {
+            subs.push(value_explanation(self.boost, "boost"));
+        }
+        subs.push(self.idf.clone());
+        if (self.query_norm - 1.0).abs() > ::std::f32::EPSILON {
+            subs.push(value_explanation(self.query_norm, "queryNorm"));
+        }
+        Explanation::new(
+            true,
+            self.query_weight,
+            "queryWeight, product of:".to_string(),
+            subs,
+        )
+    }
+
+    fn explain_field(
+        &self,
+        doc: DocId,
+        freq: Explanation,
+        norms: Option<Box<dyn NumericDocValues>>,
+    ) -> Result<Explanation> {
+        let freq_value = freq.value();
+        let tf = Explanation::new(
+            true,
+            ClassicSimilarity::tf(freq_value),
+            format!("tf(freq={}), with freq of:", freq_value),
+            vec![freq],
+        );
+        let field_norm = match norms {
+            Some(norms) => ClassicSimilarity::decode_norm_value((norms.get(doc)? & 0xFF) as usize),
+            None => 1.0,
+        };
+        let field_norm = value_explanation(field_norm, &format!("fieldNorm(doc={})", doc));
+        Ok(Explanation::new(
+            true,
+            tf.value() * self.idf.value() * field_norm.value(),
+            format!("fieldWeight in {}, product of:", doc),
+            vec![tf, self.idf.clone(), field_norm],
+        ))
+    }
+}
+
+impl<C: Codec> SimWeight<C> for TFIDFSimWeight {
+    fn get_value_for_normalization(&self) -> f32 {
+        self.query_weight * self.query_weight
+    }
+
+    fn normalize(&mut self, query_norm: f32, boost: f32) {
+        self.do_normalize(query_norm, boost)
+    }
+
+    fn sim_scorer(&self, reader: &SearchLeafReader<C>) -> Result<Box<dyn SimScorer>> {
+        Ok(Box::new(TFIDFSimScorer {
+            weight_value: self.value,
+            norms: reader.norm_values(&self.field)?,
+        }))
+    }
+
+    fn explain(
+        &self,
+        reader: &SearchLeafReader<C>,
+        doc: DocId,
+        freq: Explanation,
+    ) -> Result<Explanation> {
+        let norms = reader.norm_values(&self.field)?;
+        let freq_value = freq.value();
+        let query = self.explain_query();
+        let field = self.explain_field(doc, freq, norms)?;
+        if (query.value() - 1.0).abs() < ::std::f32::EPSILON {
+            return Ok(field);
+        }
+        Ok(Explanation::new(
+            true,
+            query.value() * field.value(),
+            format!("score(doc={},freq={}), product of:", doc, freq_value),
+            vec![query, field],
+        ))
+    }
+}
+
+struct TFIDFSimScorer {
+    weight_value: f32,
+    norms: Option<Box<dyn NumericDocValues>>,
+}
+
+impl SimScorer for TFIDFSimScorer {
+    fn score(&mut self, doc: DocId, freq: f32) -> Result<f32> {
+        let raw = ClassicSimilarity::tf(freq) * self.weight_value;
+        match self.norms {
+            Some(ref norms) => {
+                let norm = ClassicSimilarity::decode_norm_value((norms.get(doc)? & 0xFF) as usize);
+                Ok(raw * norm)
+            }
+            None => Ok(raw),
+        }
+    }
+
+    fn compute_slop_factor(&self, distance: i32) -> f32 {
+        ClassicSimilarity::sloppy_freq(distance)
+    }
+
+    fn max_score(&self, impact: &Impact) -> f32 {
+        let raw = ClassicSimilarity::tf(impact.freq as f32) * self.weight_value;
+        if self.norms.is_some() {
+            raw * ClassicSimilarity::decode_norm_value((impact.norm & 0xFF) as usize)
+        } else {
+            raw
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::codec::tests::TestCodec;
+    use core::index::tests::MockLeafReader;
+    use core::search::similarity::{BM25Similarity, SimilarityProducer};
+    use core::search::similarity::{MultiSimilarity, PerFieldSimilarityProducer};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_classic_similarity() {
+        assert!((ClassicSimilarity::idf(1, 11) - (6f32.ln() + 1.0)).abs() < ::std::f32::EPSILON);
+        assert!((ClassicSimilarity::tf(4.0) - 2.0).abs() < ::std::f32::EPSILON);
+
+        let collection_stats = CollectionStatistics::new(String::from("world"), 0, 32, 32, 120, -1);
+        let term_stats = vec![TermStatistics::new(Vec::new(), 1, -1)];
+        let sim = ClassicSimilarity;
+        let sim_weight = sim.compute_weight(&collection_stats, &term_stats, None, 2.0f32);
+        let idf = ClassicSimilarity::idf(1, 32);
+        assert!((sim_weight.get_value_for_normalization() - 4.0 * idf * idf).abs() < 1e-4);
+
+        let leaf_reader = MockLeafReader::new(1);
+        let mut sim_scorer = sim_weight.sim_scorer(&leaf_reader).unwrap();
+        let score = sim_scorer.score(1, 4.0).unwrap();
+        let norm = ClassicSimilarity::decode_norm_value(
+            BM25Similarity::encode_norm_value(1f32, 120) as usize,
+        );
+        assert!((score - 2.0 * 2.0 * idf * idf * norm).abs() < 1e-4);
+        assert!(sim_scorer.score(2, 4.0).unwrap() < score);
+
+        let freq = Explanation::new(true, 4.0, "freq".to_string(), vec![]);
+        let explanation = sim_weight.explain(&leaf_reader, 1, freq).unwrap();
+        assert!((explanation.value() - score).abs() < 1e-4);
+        assert_eq!(explanation.details().len(), 2);
+    }
+
+    #[test]
+    fn test_multi_and_per_field_similarity() {
+        let collection_stats = CollectionStatistics::new(String::from("world"), 0, 32, 32, 120, -1);
+        let term_stats = vec![TermStatistics::new(Vec::new(), 1, -1)];
+        let leaf_reader = MockLeafReader::new(1);
+
+        let classic: Arc<dyn Similarity<TestCodec>> = Arc::new(ClassicSimilarity);
+        let bm25: Arc<dyn Similarity<TestCodec>> = Arc::new(BM25Similarity::default());
+        let multi = MultiSimilarity::new(vec![
+            Box::new(ClassicSimilarity),
+            Box::new(BM25Similarity::default()),
+        ]);
+        assert_eq!(
+            multi.to_string(),
+            "MultiSimilarity(ClassicSimilarity, BM25Similarity(k1: 1.2, b: 0.75))"
+        );
+
+        let mut producer = PerFieldSimilarityProducer::new(Arc::clone(&bm25));
+        producer.set("title".to_string(), Arc::new(multi));
+        producer.set("body".to_string(), Arc::clone(&classic));
+
+        let score = |field: &str| {
+            producer
+                .create(field)
+                .compute_weight(&collection_stats, &term_stats, None, 1.0f32)
+                .sim_scorer(&leaf_reader)
+                .unwrap()
+                .score(1, 3.0)
+                .unwrap()
+        };
+        let title = score("title");
+        let body = score("body");
+        let other = score("other");
+        assert!((title - (body + other)).abs() < 1e-4);
+        let classic_score = classic
+            .compute_weight(&collection_stats, &term_stats, None, 1.0f32)
+            .sim_scorer(&leaf_reader)
+            .unwrap()
+            .score(1, 3.0)
+            .unwrap();
+        assert!((body - classic_score).abs() < ::std::f32::EPSILON);
+
+        let freq = Explanation::new(true, 3.0, "freq".to_string(), vec![]);
+        let explanation = producer
+            .create("title")
+            .compute_weight(&collection_stats, &term_stats, None, 1.0f32)
+            .explain(&leaf_reader, 1, freq)
+            .unwrap();
+        assert!((explanation.value() - title).abs() < 1e-4);
+        assert_eq!(explanation.details().len(), 2);
+    }
+}
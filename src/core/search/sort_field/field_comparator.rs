@@ -11,20 +11,31 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use core::codec::doc_values::NumericDocValues;
+use core::codec::doc_values::{
+    EmptySortedSetDocValues, NumericDocValues, SortedSetDocValues, NO_MORE_ORDS,
+};
+use core::doc::DocValuesType;
 use core::index::reader::{LeafReaderContext, SearchLeafReader};
-use core::search::sort_field::{SortFieldType, SortedWrapperDocValuesSource};
-use core::util::{BitsMut, DocId, VariantValue};
-use error::Result;
+use core::search::expression::{Expression, LeafExpression};
+use core::search::sort_field::{
+    SortFieldMissingValue, SortFieldType, SortedSetSelectorType, SortedWrapperDocValuesSource,
+};
+use core::util::{BitsMut, DocId, IndexedContext, VariantValue};
+use error::{
+    ErrorKind::{IllegalArgument, IllegalState},
+    Result,
+};
 
 use core::codec::Codec;
 use std::cmp::Ordering;
 use std::fmt;
+use std::sync::Arc;
 
 #[derive(Copy, Clone, Debug)]
 pub enum ComparatorValue {
     Doc(DocId),
-    Score(f32), // this is only used in RelevanceComparator
+    Score(f32),              // this is only used in RelevanceComparator
+    DocAndScore(DocId, f32), // this is only used in ExpressionComparator
 }
 
 impl ComparatorValue {
@@ -62,7 +73,7 @@ impl ComparatorValue {
 
     pub fn as_variant(self) -> VariantValue {
         match self {
-            ComparatorValue::Doc(d) => VariantValue::Int(d),
+            ComparatorValue::Doc(d) | ComparatorValue::DocAndScore(d, _) => VariantValue::Int(d),
             ComparatorValue::Score(s) => VariantValue::Float(s),
         }
     }
@@ -75,6 +86,9 @@ impl PartialEq for ComparatorValue {
         match (self, other) {
             (ComparatorValue::Doc(d1), ComparatorValue::Doc(d2)) => *d1 == *d2,
             (ComparatorValue::Score(s1), ComparatorValue::Score(s2)) => s1.eq(s2),
+            (ComparatorValue::DocAndScore(d1, s1), ComparatorValue::DocAndScore(d2, s2)) => {
+                *d1 == *d2 && s1.eq(s2)
+            }
             (_, _) => false,
         }
     }
@@ -87,6 +101,9 @@ impl Ord for ComparatorValue {
             (ComparatorValue::Score(s1), ComparatorValue::Score(s2)) => {
                 (*s1).partial_cmp(s2).unwrap()
             }
+            (ComparatorValue::DocAndScore(d1, s1), ComparatorValue::DocAndScore(d2, s2)) => {
+                d1.cmp(d2).then_with(|| (*s1).partial_cmp(s2).unwrap())
+            }
             (_, _) => panic!("Non-comparable"),
         }
     }
@@ -132,6 +149,14 @@ pub trait FieldComparator: fmt::Display {
 
     fn copy(&mut self, slot: usize, value: ComparatorValue) -> Result<()>;
 
+    fn set_top_value(&mut self, value: VariantValue) -> Result<()>;
+
+    fn compare_top(&mut self, value: ComparatorValue) -> Result<Ordering>;
+
+    /// Compares two values returned by `value()` in the sort order of this
+    /// comparator, e.g. to merge hits of several searches.
+    fn compare_values(&self, value1: &VariantValue, value2: &VariantValue) -> Ordering;
+
     fn get_information_from_reader<C: Codec>(
         &mut self,
         reader: &LeafReaderContext<'_, C>,
@@ -145,6 +170,20 @@ pub enum FieldComparatorEnum {
     Doc(DocComparator),
     NumericDV(NumericDocValuesComparator<DefaultDocValuesSource>),
     SortedNumericDV(NumericDocValuesComparator<SortedWrapperDocValuesSource>),
+    TermOrdVal(TermOrdValComparator),
+    Expression(ExpressionComparator),
+}
+
+impl FieldComparatorEnum {
+    /// Returns the value that the comparator reads for the hit `doc` with
+    /// `score`.
+    pub fn comparator_value(&self, doc: DocId, score: f32) -> ComparatorValue {
+        match self {
+            FieldComparatorEnum::Score(_) => ComparatorValue::Score(score),
+            FieldComparatorEnum::Expression(_) => ComparatorValue::DocAndScore(doc, score),
+            _ => ComparatorValue::Doc(doc),
+        }
+    }
 }
 
 impl FieldComparator for FieldComparatorEnum {
@@ -154,6 +193,8 @@ impl FieldComparator for FieldComparatorEnum {
             FieldComparatorEnum::Doc(c) => c.compare(slot1, slot2),
             FieldComparatorEnum::NumericDV(c) => c.compare(slot1, slot2),
             FieldComparatorEnum::SortedNumericDV(c) => c.compare(slot1, slot2),
+            FieldComparatorEnum::TermOrdVal(c) => c.compare(slot1, slot2),
+            FieldComparatorEnum::Expression(c) => c.compare(slot1, slot2),
         }
     }
 
@@ -163,6 +204,8 @@ impl FieldComparator for FieldComparatorEnum {
             FieldComparatorEnum::Doc(c) => c.value(slot),
             FieldComparatorEnum::NumericDV(c) => c.value(slot),
             FieldComparatorEnum::SortedNumericDV(c) => c.value(slot),
+            FieldComparatorEnum::TermOrdVal(c) => c.value(slot),
+            FieldComparatorEnum::Expression(c) => c.value(slot),
         }
     }
 
@@ -172,6 +215,8 @@ impl FieldComparator for FieldComparatorEnum {
             FieldComparatorEnum::Doc(c) => c.set_bottom(slot),
             FieldComparatorEnum::NumericDV(c) => c.set_bottom(slot),
             FieldComparatorEnum::SortedNumericDV(c) => c.set_bottom(slot),
+            FieldComparatorEnum::TermOrdVal(c) => c.set_bottom(slot),
+            FieldComparatorEnum::Expression(c) => c.set_bottom(slot),
         }
     }
 
@@ -181,6 +226,8 @@ impl FieldComparator for FieldComparatorEnum {
             FieldComparatorEnum::Doc(c) => c.compare_bottom(value),
             FieldComparatorEnum::NumericDV(c) => c.compare_bottom(value),
             FieldComparatorEnum::SortedNumericDV(c) => c.compare_bottom(value),
+            FieldComparatorEnum::TermOrdVal(c) => c.compare_bottom(value),
+            FieldComparatorEnum::Expression(c) => c.compare_bottom(value),
         }
     }
 
@@ -190,6 +237,41 @@ impl FieldComparator for FieldComparatorEnum {
             FieldComparatorEnum::Doc(c) => c.copy(slot, value),
             FieldComparatorEnum::NumericDV(c) => c.copy(slot, value),
             FieldComparatorEnum::SortedNumericDV(c) => c.copy(slot, value),
+            FieldComparatorEnum::TermOrdVal(c) => c.copy(slot, value),
+            FieldComparatorEnum::Expression(c) => c.copy(slot, value),
+        }
+    }
+
+    fn set_top_value(&mut self, value: VariantValue) -> Result<()> {
+        match self {
+            FieldComparatorEnum::Score(c) => c.set_top_value(value),
+            FieldComparatorEnum::Doc(c) => c.set_top_value(value),
+            FieldComparatorEnum::NumericDV(c) => c.set_top_value(value),
+            FieldComparatorEnum::SortedNumericDV(c) => c.set_top_value(value),
+            FieldComparatorEnum::TermOrdVal(c) => c.set_top_value(value),
+            FieldComparatorEnum::Expression(c) => c.set_top_value(value),
+        }
+    }
+
+    fn compare_top(&mut self, value: ComparatorValue) -> Result<Ordering> {
+        match self {
+            FieldComparatorEnum::Score(c) => c.compare_top(value),
+            FieldComparatorEnum::Doc(c) => c.compare_top(value),
+            FieldComparatorEnum::NumericDV(c) => c.compare_top(value),
+            FieldComparatorEnum::SortedNumericDV(c) => c.compare_top(value),
+            FieldComparatorEnum::TermOrdVal(c) => c.compare_top(value),
+            FieldComparatorEnum::Expression(c) => c.compare_top(value),
+        }
+    }
+
+    fn compare_values(&self, value1: &VariantValue, value2: &VariantValue) -> Ordering {
+        match self {
+            FieldComparatorEnum::Score(c) => c.compare_values(value1, value2),
+            FieldComparatorEnum::Doc(c) => c.compare_values(value1, value2),
+            FieldComparatorEnum::NumericDV(c) => c.compare_values(value1, value2),
+            FieldComparatorEnum::SortedNumericDV(c) => c.compare_values(value1, value2),
+            FieldComparatorEnum::TermOrdVal(c) => c.compare_values(value1, value2),
+            FieldComparatorEnum::Expression(c) => c.compare_values(value1, value2),
         }
     }
 
@@ -202,6 +284,8 @@ impl FieldComparator for FieldComparatorEnum {
             FieldComparatorEnum::Doc(c) => c.get_information_from_reader(reader),
             FieldComparatorEnum::NumericDV(c) => c.get_information_from_reader(reader),
             FieldComparatorEnum::SortedNumericDV(c) => c.get_information_from_reader(reader),
+            FieldComparatorEnum::TermOrdVal(c) => c.get_information_from_reader(reader),
+            FieldComparatorEnum::Expression(c) => c.get_information_from_reader(reader),
         }
     }
 
@@ -211,6 +295,8 @@ impl FieldComparator for FieldComparatorEnum {
             FieldComparatorEnum::Doc(c) => c.get_type(),
             FieldComparatorEnum::NumericDV(c) => c.get_type(),
             FieldComparatorEnum::SortedNumericDV(c) => c.get_type(),
+            FieldComparatorEnum::TermOrdVal(c) => c.get_type(),
+            FieldComparatorEnum::Expression(c) => c.get_type(),
         }
     }
 }
@@ -222,6 +308,8 @@ impl fmt::Display for FieldComparatorEnum {
             FieldComparatorEnum::Doc(c) => write!(f, "FieldComparatorEnum({})", c),
             FieldComparatorEnum::NumericDV(c) => write!(f, "FieldComparatorEnum({})", c),
             FieldComparatorEnum::SortedNumericDV(c) => write!(f, "FieldComparatorEnum({})", c),
+            FieldComparatorEnum::TermOrdVal(c) => write!(f, "FieldComparatorEnum({})", c),
+            FieldComparatorEnum::Expression(c) => write!(f, "FieldComparatorEnum({})", c),
         }
     }
 }
@@ -234,6 +322,7 @@ impl fmt::Display for FieldComparatorEnum {
 pub struct RelevanceComparator {
     scores: Vec<f32>,
     bottom: f32,
+    top_value: f32,
 }
 
 impl RelevanceComparator {
@@ -243,6 +332,7 @@ impl RelevanceComparator {
         RelevanceComparator {
             scores,
             bottom: 0f32,
+            top_value: 0f32,
         }
     }
 }
@@ -276,6 +366,31 @@ impl FieldComparator for RelevanceComparator {
         Ok(())
     }
 
+    fn set_top_value(&mut self, value: VariantValue) -> Result<()> {
+        match value.get_float() {
+            Some(score) => {
+                self.top_value = score;
+                Ok(())
+            }
+            None => bail!(IllegalArgument(format!(
+                "score top value must be a float, got {}",
+                value
+            ))),
+        }
+    }
+
+    fn compare_top(&mut self, value: ComparatorValue) -> Result<Ordering> {
+        debug_assert!(value.is_score());
+        Ok(value
+            .score()
+            .partial_cmp(&self.top_value)
+            .unwrap_or(Ordering::Equal))
+    }
+
+    fn compare_values(&self, value1: &VariantValue, value2: &VariantValue) -> Ordering {
+        value2.cmp(value1)
+    }
+
     fn get_information_from_reader<C: Codec>(
         &mut self,
         _reader: &LeafReaderContext<'_, C>,
@@ -298,6 +413,7 @@ impl fmt::Display for RelevanceComparator {
 pub struct DocComparator {
     doc_ids: Vec<i32>,
     bottom: i32,
+    top_value: i32,
     doc_base: i32,
 }
 
@@ -311,6 +427,7 @@ impl DocComparator {
         DocComparator {
             doc_ids,
             bottom: 0,
+            top_value: 0,
             doc_base: 0,
         }
     }
@@ -331,7 +448,7 @@ impl FieldComparator for DocComparator {
 
     fn compare_bottom(&mut self, value: ComparatorValue) -> Result<Ordering> {
         debug_assert!(value.is_doc());
-        Ok(self.bottom.cmp(&value.doc()))
+        Ok(self.bottom.cmp(&(value.doc() + self.doc_base)))
     }
 
     fn copy(&mut self, slot: usize, value: ComparatorValue) -> Result<()> {
@@ -340,6 +457,28 @@ impl FieldComparator for DocComparator {
         Ok(())
     }
 
+    fn set_top_value(&mut self, value: VariantValue) -> Result<()> {
+        match value.get_int() {
+            Some(doc) => {
+                self.top_value = doc;
+                Ok(())
+            }
+            None => bail!(IllegalArgument(format!(
+                "doc top value must be an int, got {}",
+                value
+            ))),
+        }
+    }
+
+    fn compare_top(&mut self, value: ComparatorValue) -> Result<Ordering> {
+        debug_assert!(value.is_doc());
+        Ok(self.top_value.cmp(&(value.doc() + self.doc_base)))
+    }
+
+    fn compare_values(&self, value1: &VariantValue, value2: &VariantValue) -> Ordering {
+        value1.cmp(value2)
+    }
+
     fn get_information_from_reader<C: Codec>(
         &mut self,
         reader: &LeafReaderContext<'_, C>,
@@ -412,6 +551,17 @@ impl<T: DocValuesSource> NumericDocValuesComparator<T> {
         };
         Ok(value)
     }
+
+    /// Returns the value of `doc_id`, or the missing value if it has none.
+    fn get_value_or_missing(&mut self, doc_id: DocId) -> Result<VariantValue> {
+        let value = self.get_doc_value(doc_id)?;
+        if let Some(ref mut bits) = self.docs_with_fields {
+            if value.is_zero() && !bits.get(doc_id as usize)? {
+                return Ok(self.missing_value.as_ref().unwrap().clone());
+            }
+        }
+        Ok(value)
+    }
 }
 
 impl<T: DocValuesSource> FieldComparator for NumericDocValuesComparator<T> {
@@ -429,29 +579,31 @@ impl<T: DocValuesSource> FieldComparator for NumericDocValuesComparator<T> {
 
     fn compare_bottom(&mut self, value: ComparatorValue) -> Result<Ordering> {
         debug_assert!(value.is_doc());
-        let doc_id = value.doc();
-        let value = self.get_doc_value(doc_id)?;
-        if let Some(ref mut bits) = self.docs_with_fields {
-            if value.is_zero() && bits.get(doc_id as usize)? {
-                return Ok(self.bottom.cmp(self.missing_value.as_ref().unwrap()));
-            }
-        }
+        let value = self.get_value_or_missing(value.doc())?;
         Ok(self.bottom.cmp(&value))
     }
 
     fn copy(&mut self, slot: usize, value: ComparatorValue) -> Result<()> {
         debug_assert!(value.is_doc());
-        let doc_id = value.doc();
-        let mut value = self.get_doc_value(doc_id)?;
-        if let Some(ref mut bits) = self.docs_with_fields {
-            if value.is_zero() && bits.get(doc_id as usize)? {
-                value = self.missing_value.as_ref().unwrap().clone();
-            }
-        }
-        self.values[slot] = value;
+        self.values[slot] = self.get_value_or_missing(value.doc())?;
         Ok(())
     }
 
+    fn set_top_value(&mut self, value: VariantValue) -> Result<()> {
+        self.top_value = value;
+        Ok(())
+    }
+
+    fn compare_top(&mut self, value: ComparatorValue) -> Result<Ordering> {
+        debug_assert!(value.is_doc());
+        let value = self.get_value_or_missing(value.doc())?;
+        Ok(self.top_value.cmp(&value))
+    }
+
+    fn compare_values(&self, value1: &VariantValue, value2: &VariantValue) -> Ordering {
+        value1.cmp(value2)
+    }
+
     fn get_information_from_reader<C: Codec>(
         &mut self,
         reader: &LeafReaderContext<'_, C>,
@@ -485,6 +637,429 @@ impl<T: DocValuesSource> fmt::Display for NumericDocValuesComparator<T> {
     }
 }
 
+/// Sorts by the terms of a `SortedSetDocValues` field, using the value picked
+/// by a `SortedSetSelectorType` for documents with several values.
+///
+/// Ordinals are only comparable within a segment, so every slot also keeps
+/// its term and the generation of the leaf it was copied from. Slots copied
+/// from different leaves are compared by their terms, and the bottom and top
+/// values are looked up in the terms of each new leaf.
+///
+/// The value of a document is the binary of its term, a document without a
+/// value is reported by `value()` as `VariantValue::Null`, and sorts first
+/// unless `missing_last` is set.
+pub struct TermOrdValComparator {
+    field: String,
+    selector: SortedSetSelectorType,
+    missing_last: bool,
+    /// Ordinal used for documents without a value.
+    missing_ord: i64,
+    ords: Vec<i64>,
+    values: Vec<Option<Vec<u8>>>,
+    reader_gens: Vec<i32>,
+    current_reader_gen: i32,
+    doc_values: Option<Box<dyn SortedSetDocValues>>,
+    /// Buffer for the ordinals of the current document.
+    doc_ords: Vec<i64>,
+    bottom_slot: Option<usize>,
+    /// Whether `bottom_ord` must be resolved for the current leaf.
+    bottom_dirty: bool,
+    bottom_ord: i64,
+    bottom_same_reader: bool,
+    top_value: Option<Vec<u8>>,
+    top_ord: i64,
+    top_same_reader: bool,
+}
+
+impl TermOrdValComparator {
+    pub fn new(
+        num_hits: usize,
+        field: String,
+        selector: SortedSetSelectorType,
+        missing_last: bool,
+    ) -> Self {
+        let missing_ord = if missing_last { i64::max_value() } else { -1 };
+        TermOrdValComparator {
+            field,
+            selector,
+            missing_last,
+            missing_ord,
+            ords: vec![0; num_hits],
+            values: vec![None; num_hits],
+            reader_gens: vec![-1; num_hits],
+            current_reader_gen: -1,
+            doc_values: None,
+            doc_ords: vec![],
+            bottom_slot: None,
+            bottom_dirty: false,
+            bottom_ord: 0,
+            bottom_same_reader: false,
+            top_value: None,
+            top_ord: missing_ord,
+            top_same_reader: true,
+        }
+    }
+
+    fn doc_values(&mut self) -> &mut dyn SortedSetDocValues {
+        debug_assert!(self.doc_values.is_some());
+        self.doc_values.as_mut().unwrap().as_mut()
+    }
+
+    /// Returns the ordinal selected for `doc_id`, or `missing_ord`.
+    fn ord_for_doc(&mut self, doc_id: DocId) -> Result<i64> {
+        let mut doc_ords = ::std::mem::replace(&mut self.doc_ords, vec![]);
+        doc_ords.clear();
+        {
+            let doc_values = self.doc_values();
+            doc_values.set_document(doc_id)?;
+            loop {
+                let ord = doc_values.next_ord()?;
+                if ord == NO_MORE_ORDS {
+                    break;
+                }
+                doc_ords.push(ord);
+            }
+        }
+        let ord = if doc_ords.is_empty() {
+            self.missing_ord
+        } else {
+            let last = doc_ords.len() - 1;
+            match self.selector {
+                SortedSetSelectorType::Min => doc_ords[0],
+                SortedSetSelectorType::Max => doc_ords[last],
+                SortedSetSelectorType::MiddleMin => doc_ords[last / 2],
+                SortedSetSelectorType::MiddleMax => doc_ords[(last + 1) / 2],
+            }
+        };
+        self.doc_ords = doc_ords;
+        Ok(ord)
+    }
+
+    /// Looks `term` up in the current leaf, returns its ordinal and whether it
+    /// exists there; if it doesn't, the ordinal of the greatest smaller term.
+    fn lookup_term(&mut self, term: Option<&[u8]>) -> Result<(i64, bool)> {
+        match term {
+            Some(term) => {
+                let index = self.doc_values().lookup_term(term)?;
+                if index < 0 {
+                    Ok((-index - 2, false))
+                } else {
+                    Ok((index, true))
+                }
+            }
+            None => Ok((self.missing_ord, true)),
+        }
+    }
+
+    fn resolve_bottom(&mut self) -> Result<()> {
+        let slot = self.bottom_slot.unwrap();
+        if self.reader_gens[slot] == self.current_reader_gen {
+            self.bottom_ord = self.ords[slot];
+            self.bottom_same_reader = true;
+        } else {
+            let value = self.values[slot].take();
+            let (ord, same_reader) = self.lookup_term(value.as_deref())?;
+            self.values[slot] = value;
+            self.bottom_ord = ord;
+            self.bottom_same_reader = same_reader;
+            if same_reader {
+                // the slot can now be compared by ordinal
+                self.reader_gens[slot] = self.current_reader_gen;
+                self.ords[slot] = ord;
+            }
+        }
+        self.bottom_dirty = false;
+        Ok(())
+    }
+
+    fn compare_terms(&self, term1: Option<&[u8]>, term2: Option<&[u8]>) -> Ordering {
+        let missing_cmp = if self.missing_last {
+            Ordering::Greater
+        } else {
+            Ordering::Less
+        };
+        match (term1, term2) {
+            (Some(t1), Some(t2)) => t1.cmp(t2),
+            (None, Some(_)) => missing_cmp,
+            (Some(_), None) => missing_cmp.reverse(),
+            (None, None) => Ordering::Equal,
+        }
+    }
+
+    fn term_of(value: &VariantValue) -> Option<&[u8]> {
+        match value {
+            VariantValue::Binary(b) => Some(b.as_slice()),
+            _ => None,
+        }
+    }
+}
+
+impl FieldComparator for TermOrdValComparator {
+    fn compare(&self, slot1: usize, slot2: usize) -> Ordering {
+        if self.reader_gens[slot1] == self.reader_gens[slot2] {
+            return self.ords[slot1].cmp(&self.ords[slot2]);
+        }
+        self.compare_terms(self.values[slot1].as_deref(), self.values[slot2].as_deref())
+    }
+
+    fn value(&self, slot: usize) -> VariantValue {
+        match &self.values[slot] {
+            Some(term) => VariantValue::Binary(term.clone()),
+            None => VariantValue::Null,
+        }
+    }
+
+    fn set_bottom(&mut self, slot: usize) {
+        self.bottom_slot = Some(slot);
+        self.bottom_dirty = true;
+    }
+
+    fn compare_bottom(&mut self, value: ComparatorValue) -> Result<Ordering> {
+        debug_assert!(value.is_doc());
+        if self.bottom_dirty {
+            self.resolve_bottom()?;
+        }
+        let doc_ord = self.ord_for_doc(value.doc())?;
+        if self.bottom_same_reader {
+            Ok(self.bottom_ord.cmp(&doc_ord))
+        } else if self.bottom_ord >= doc_ord {
+            Ok(Ordering::Greater)
+        } else {
+            Ok(Ordering::Less)
+        }
+    }
+
+    fn copy(&mut self, slot: usize, value: ComparatorValue) -> Result<()> {
+        debug_assert!(value.is_doc());
+        let ord = self.ord_for_doc(value.doc())?;
+        self.values[slot] = if ord == self.missing_ord {
+            None
+        } else {
+            Some(self.doc_values().lookup_ord(ord)?)
+        };
+        self.ords[slot] = ord;
+        self.reader_gens[slot] = self.current_reader_gen;
+        if self.bottom_slot == Some(slot) {
+            self.bottom_dirty = true;
+        }
+        Ok(())
+    }
+
+    fn set_top_value(&mut self, value: VariantValue) -> Result<()> {
+        match value {
+            VariantValue::Binary(term) => {
+                self.top_value = Some(term);
+                Ok(())
+            }
+            VariantValue::Null => {
+                self.top_value = None;
+                Ok(())
+            }
+            _ => match SortFieldMissingValue::from_value(&value) {
+                Some(missing)
+                    if (missing == SortFieldMissingValue::StringLast) == self.missing_last =>
+                {
+                    self.top_value = None;
+                    Ok(())
+                }
+                Some(_) => bail!(IllegalArgument(format!(
+                    "sorted set top value {} doesn't match the missing value of the sort field",
+                    value
+                ))),
+                None => bail!(IllegalArgument(format!(
+                    "sorted set top value must be a binary or null, got {}",
+                    value
+                ))),
+            },
+        }
+    }
+
+    fn compare_top(&mut self, value: ComparatorValue) -> Result<Ordering> {
+        debug_assert!(value.is_doc());
+        let doc_ord = self.ord_for_doc(value.doc())?;
+        if self.top_same_reader {
+            Ok(self.top_ord.cmp(&doc_ord))
+        } else if doc_ord <= self.top_ord {
+            Ok(Ordering::Greater)
+        } else {
+            Ok(Ordering::Less)
+        }
+    }
+
+    fn compare_values(&self, value1: &VariantValue, value2: &VariantValue) -> Ordering {
+        self.compare_terms(Self::term_of(value1), Self::term_of(value2))
+    }
+
+    fn get_information_from_reader<C: Codec>(
+        &mut self,
+        reader: &LeafReaderContext<'_, C>,
+    ) -> Result<()> {
+        let doc_values: Box<dyn SortedSetDocValues> = match reader.reader.field_info(&self.field) {
+            Some(field_info) if field_info.doc_values_type == DocValuesType::SortedSet => {
+                reader.reader.get_sorted_set_doc_values(&self.field)?
+            }
+            _ => Box::new(EmptySortedSetDocValues),
+        };
+        self.doc_values = Some(doc_values);
+        self.current_reader_gen += 1;
+        self.bottom_dirty = self.bottom_slot.is_some();
+
+        let top_value = self.top_value.take();
+        let (top_ord, top_same_reader) = self.lookup_term(top_value.as_deref())?;
+        self.top_value = top_value;
+        self.top_ord = top_ord;
+        self.top_same_reader = top_same_reader;
+        Ok(())
+    }
+
+    fn get_type(&self) -> SortFieldType {
+        SortFieldType::String
+    }
+}
+
+impl fmt::Display for TermOrdValComparator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "TermOrdValComparator(field: {}, selector: {:?}, missing_last: {})",
+            self.field, self.selector, self.missing_last
+        )
+    }
+}
+
+/// Sorts by the values of an `Expression`, which can't read the features of
+/// the score context.
+pub struct ExpressionComparator {
+    expression: Arc<Expression>,
+    leaf: Option<LeafExpression>,
+    // the last evaluated doc of the leaf and its value, as a competitive hit
+    // is evaluated by `compare_bottom` and then by `copy`
+    last: Option<(DocId, f64)>,
+    values: Vec<f64>,
+    bottom: f64,
+    top_value: f64,
+}
+
+impl ExpressionComparator {
+    pub fn new(num_hits: usize, expression: Arc<Expression>) -> ExpressionComparator {
+        debug_assert!(!expression.needs_features());
+        ExpressionComparator {
+            expression,
+            leaf: None,
+            last: None,
+            values: vec![0.0; num_hits],
+            bottom: 0.0,
+            top_value: 0.0,
+        }
+    }
+
+    fn evaluate(&mut self, value: ComparatorValue) -> Result<f64> {
+        let (doc, score) = match value {
+            ComparatorValue::DocAndScore(doc, score) => (doc, score),
+            ComparatorValue::Doc(doc) if !self.expression.needs_scores() => (doc, 0.0),
+            _ => bail!(IllegalState(format!(
+                "expression '{}' can't be evaluated for {:?}",
+                self.expression, value
+            ))),
+        };
+        if let Some((last_doc, v)) = self.last {
+            if last_doc == doc {
+                return Ok(v);
+            }
+        }
+        let leaf = match &mut self.leaf {
+            Some(leaf) => leaf,
+            None => bail!(IllegalState(format!(
+                "expression '{}' evaluated before get_information_from_reader",
+                self.expression
+            ))),
+        };
+        let v = leaf.evaluate(doc, score, &IndexedContext::default())?;
+        if v.is_nan() {
+            bail!(IllegalArgument(format!(
+                "expression '{}' produced NaN for doc {}",
+                self.expression, doc
+            )));
+        }
+        self.last = Some((doc, v));
+        Ok(v)
+    }
+}
+
+impl FieldComparator for ExpressionComparator {
+    fn compare(&self, slot1: usize, slot2: usize) -> Ordering {
+        self.values[slot1]
+            .partial_cmp(&self.values[slot2])
+            .unwrap_or(Ordering::Equal)
+    }
+
+    fn value(&self, slot: usize) -> VariantValue {
+        VariantValue::Double(self.values[slot])
+    }
+
+    fn set_bottom(&mut self, slot: usize) {
+        self.bottom = self.values[slot];
+    }
+
+    fn compare_bottom(&mut self, value: ComparatorValue) -> Result<Ordering> {
+        let value = self.evaluate(value)?;
+        Ok(self.bottom.partial_cmp(&value).unwrap_or(Ordering::Equal))
+    }
+
+    fn copy(&mut self, slot: usize, value: ComparatorValue) -> Result<()> {
+        self.values[slot] = self.evaluate(value)?;
+        Ok(())
+    }
+
+    fn set_top_value(&mut self, value: VariantValue) -> Result<()> {
+        match value.get_double() {
+            Some(v) => {
+                self.top_value = v;
+                Ok(())
+            }
+            None => bail!(IllegalArgument(format!(
+                "expression top value must be a double, got {}",
+                value
+            ))),
+        }
+    }
+
+    fn compare_top(&mut self, value: ComparatorValue) -> Result<Ordering> {
+        let value = self.evaluate(value)?;
+        Ok(self
+            .top_value
+            .partial_cmp(&value)
+            .unwrap_or(Ordering::Equal))
+    }
+
+    fn compare_values(&self, value1: &VariantValue, value2: &VariantValue) -> Ordering {
+        value1.cmp(value2)
+    }
+
+    fn get_information_from_reader<C: Codec>(
+        &mut self,
+        reader: &LeafReaderContext<'_, C>,
+    ) -> Result<()> {
+        self.leaf = Some(LeafExpression::new(Arc::clone(&self.expression), reader)?);
+        self.last = None;
+        Ok(())
+    }
+
+    fn get_type(&self) -> SortFieldType {
+        SortFieldType::Custom
+    }
+}
+
+impl fmt::Display for ExpressionComparator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "ExpressionComparator(expression: {}, bottom: {}, top: {})",
+            self.expression, self.bottom, self.top_value
+        )
+    }
+}
+
 pub trait DocValuesSource {
     fn numeric_doc_values<C: Codec>(
         &self,
@@ -577,4 +1152,20 @@ mod tests {
             Ordering::Greater
         );
     }
+    #[test]
+    fn test_term_ord_val_comparator_top_value() {
+        let mut comparator =
+            TermOrdValComparator::new(3, "tags".into(), SortedSetSelectorType::Min, true);
+        assert!(comparator
+            .set_top_value(VariantValue::from(&b"t01"[..]))
+            .is_ok());
+        assert!(comparator.set_top_value(VariantValue::Null).is_ok());
+        assert!(comparator
+            .set_top_value(SortFieldMissingValue::StringLast.value())
+            .is_ok());
+        assert!(comparator
+            .set_top_value(SortFieldMissingValue::StringFirst.value())
+            .is_err());
+        assert!(comparator.set_top_value(VariantValue::Long(1)).is_err());
+    }
 }
@@ -14,7 +14,7 @@
 use std::cmp::Ordering;
 use std::hash::{Hash, Hasher};
 
-use core::search::sort_field::{ComparatorValue, SortFieldType};
+use core::search::sort_field::{ComparatorValue, ScoreDocHit, SortField, SortFieldType};
 use core::util::DocId;
 use core::util::VariantValue;
 
@@ -79,6 +79,9 @@ pub struct CollectedSearchGroup {
     pub max_wilson_info: WilsonInfo,
 
     pub top_doc: DocId,
+    /// The values of the top doc for the group sort, as returned by the
+    /// comparators, e.g. to merge the groups of several searches.
+    pub sort_values: Vec<VariantValue>,
 }
 
 impl CollectedSearchGroup {
@@ -95,8 +98,14 @@ impl CollectedSearchGroup {
             max_wilson_info,
             comparator_slot,
             top_doc,
+            sort_values: vec![],
         }
     }
+
+    pub fn with_sort_values(mut self, sort_values: Vec<VariantValue>) -> Self {
+        self.sort_values = sort_values;
+        self
+    }
 }
 
 impl Ord for CollectedSearchGroup {
@@ -139,6 +148,46 @@ impl PartialEq for CollectedSearchGroup {
     }
 }
 
+/// The top documents of one group, returned by `SecondPassGroupingCollector`.
+pub struct GroupDocs {
+    /// The value all the documents of the group share.
+    pub group_value: VariantValue,
+
+    /// The values of the group's top doc for the group sort.
+    pub group_sort_values: Vec<VariantValue>,
+
+    /// The total number of hits in the group.
+    pub total_hits: usize,
+
+    /// The top hits of the group, sorted by the within group sort.
+    pub score_docs: Vec<ScoreDocHit>,
+
+    /// The maximum score of the group, `NaN` if scores weren't tracked.
+    pub max_score: f32,
+}
+
+/// Represents the groups returned by `SecondPassGroupingCollector`.
+pub struct TopGroups {
+    /// The sort the groups are sorted by.
+    pub group_sort: Vec<SortField>,
+
+    /// The sort the documents of every group are sorted by.
+    pub within_group_sort: Vec<SortField>,
+
+    /// The total number of hits for the query.
+    pub total_hit_count: usize,
+
+    /// The number of hits that are in one of the top groups.
+    pub total_grouped_hit_count: usize,
+
+    /// The number of distinct groups of all the hits, only known if it was
+    /// counted by an `AllGroupsCollector`.
+    pub total_group_count: Option<usize>,
+
+    /// The top groups, sorted by the group sort.
+    pub groups: Vec<GroupDocs>,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -14,9 +14,10 @@
 use core::codec::doc_values::{NumericDocValues, SortedNumericDocValues};
 use core::codec::Codec;
 use core::index::reader::SearchLeafReader;
+use core::search::expression::Expression;
 use core::search::sort_field::{
-    DefaultDocValuesSource, DocComparator, DocValuesSource, FieldComparatorEnum,
-    NumericDocValuesComparator, RelevanceComparator,
+    DefaultDocValuesSource, DocComparator, DocValuesSource, ExpressionComparator,
+    FieldComparatorEnum, NumericDocValuesComparator, RelevanceComparator, TermOrdValComparator,
 };
 use core::util::{sortable_double_bits, sortable_float_bits};
 use core::util::{BitsMut, DocId, VariantValue};
@@ -24,6 +25,8 @@ use core::util::{BitsMut, DocId, VariantValue};
 use error::ErrorKind::IllegalArgument;
 use error::Result;
 
+use std::sync::Arc;
+
 #[derive(PartialEq, Debug, Clone, Copy, Eq)]
 pub enum SortFieldType {
     String,
@@ -58,11 +61,31 @@ pub enum SortFieldMissingValue {
     StringFirst,
 }
 
+impl SortFieldMissingValue {
+    /// The missing value of a string `SortField`, `"_first"` or `"_last"`.
+    pub fn value(self) -> VariantValue {
+        let value = match self {
+            SortFieldMissingValue::StringFirst => "_first",
+            SortFieldMissingValue::StringLast => "_last",
+        };
+        VariantValue::VString(value.to_string())
+    }
+
+    pub fn from_value(value: &VariantValue) -> Option<SortFieldMissingValue> {
+        match value {
+            VariantValue::VString(s) if s == "_first" => Some(SortFieldMissingValue::StringFirst),
+            VariantValue::VString(s) if s == "_last" => Some(SortFieldMissingValue::StringLast),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Clone, Eq, PartialEq, Debug)]
 pub enum SortField {
     Simple(SimpleSortField),
     SortedNumeric(SortedNumericSortField),
-    // SortedSet(SortedSetSortField),
+    SortedSet(SortedSetSortField),
+    Custom(ExpressionSortField),
 }
 
 impl SortField {
@@ -74,6 +97,8 @@ impl SortField {
         match self {
             SortField::Simple(s) => &s.field,
             SortField::SortedNumeric(s) => &s.raw_field.field,
+            SortField::SortedSet(s) => &s.raw_field.field,
+            SortField::Custom(s) => s.expression.source(),
         }
     }
 
@@ -81,6 +106,8 @@ impl SortField {
         match self {
             SortField::Simple(s) => s.field_type,
             SortField::SortedNumeric(s) => s.raw_field.field_type,
+            SortField::SortedSet(s) => s.raw_field.field_type,
+            SortField::Custom(_) => SortFieldType::Custom,
         }
     }
 
@@ -88,6 +115,8 @@ impl SortField {
         match self {
             SortField::Simple(s) => s.is_reverse,
             SortField::SortedNumeric(s) => s.raw_field.is_reverse,
+            SortField::SortedSet(s) => s.raw_field.is_reverse,
+            SortField::Custom(s) => s.is_reverse,
         }
     }
 
@@ -95,6 +124,8 @@ impl SortField {
         match self {
             SortField::Simple(s) => s.missing_value.as_ref(),
             SortField::SortedNumeric(s) => s.raw_field.missing_value.as_ref(),
+            SortField::SortedSet(s) => s.raw_field.missing_value.as_ref(),
+            SortField::Custom(_) => None,
         }
     }
 
@@ -102,9 +133,13 @@ impl SortField {
         match self {
             SortField::Simple(s) => s.needs_scores(),
             SortField::SortedNumeric(s) => s.raw_field.needs_scores(),
+            SortField::SortedSet(s) => s.raw_field.needs_scores(),
+            SortField::Custom(s) => s.expression.needs_scores(),
         }
     }
 
+    /// Sets the value used for the documents without a value, for a
+    /// `SortedSet` field it is a `SortFieldMissingValue::value()`.
     pub fn set_missing_value(&mut self, value: Option<VariantValue>) {
         match self {
             SortField::Simple(s) => {
@@ -113,6 +148,12 @@ impl SortField {
             SortField::SortedNumeric(s) => {
                 s.raw_field.missing_value = value;
             }
+            SortField::SortedSet(s) => {
+                s.raw_field.missing_value = value;
+            }
+            SortField::Custom(_) => {
+                // an expression has a value for every doc
+            }
         }
     }
 
@@ -124,6 +165,8 @@ impl SortField {
         match self {
             SortField::Simple(s) => s.get_comparator(num_hits, missing_value),
             SortField::SortedNumeric(s) => s.get_comparator(num_hits, missing_value),
+            SortField::SortedSet(s) => s.get_comparator(num_hits, missing_value),
+            SortField::Custom(s) => s.get_comparator(num_hits),
         }
     }
 }
@@ -422,25 +465,113 @@ impl NumericDocValues for SortedNumAsNumDocValues {
     }
 }
 
-/// SortField for {@link SortedSetDocValues}.
+/// SortField for `SortedSetDocValues`.
 ///
 /// A SortedSetDocValues contains multiple values for a field, so sorting with
 /// this technique "selects" a value as the representative sort value for the document.
 ///
 /// By default, the minimum value in the set is selected as the sort value, but
-/// this can be customized. Selectors other than the default do have some limitations
-/// to ensure that all selections happen in constant-time for performance.
+/// this can be customized.
 ///
-/// Like sorting by string, this also supports sorting missing values as first or last,
-/// via {@link #setMissingValue(Object)}.
-/// @see SortedSetSelector
-/// TODO, may implement later
-#[allow(dead_code)]
-struct SortedSetSortField {
+/// Documents without a value sort first by default, use
+/// `SortedSetSortField::set_missing_value` to sort them last instead. The
+/// missing value is kept as the `SortFieldMissingValue::value()` of the field,
+/// like the missing values of the other sort fields.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SortedSetSortField {
     selector: SortedSetSelectorType,
     raw_field: SimpleSortField,
 }
 
+impl SortedSetSortField {
+    pub fn with_field(field: String) -> Self {
+        Self::with_default_selector(field, false)
+    }
+
+    pub fn with_default_selector(field: String, reverse: bool) -> Self {
+        Self::new(field, reverse, SortedSetSelectorType::Min)
+    }
+
+    pub fn new(field: String, reverse: bool, selector: SortedSetSelectorType) -> Self {
+        let raw_field = SimpleSortField::new(field, SortFieldType::Custom, reverse);
+        SortedSetSortField {
+            selector,
+            raw_field,
+        }
+    }
+
+    pub fn selector(&self) -> SortedSetSelectorType {
+        self.selector
+    }
+
+    pub fn missing_value(&self) -> SortFieldMissingValue {
+        self.raw_field
+            .missing_value
+            .as_ref()
+            .and_then(SortFieldMissingValue::from_value)
+            .unwrap_or(SortFieldMissingValue::StringFirst)
+    }
+
+    pub fn set_missing_value(&mut self, missing_value: SortFieldMissingValue) {
+        self.raw_field.missing_value = Some(missing_value.value());
+    }
+
+    pub fn get_comparator(
+        &self,
+        num_hits: usize,
+        missing_value: Option<&VariantValue>,
+    ) -> FieldComparatorEnum {
+        let missing_value = missing_value.and_then(SortFieldMissingValue::from_value);
+        FieldComparatorEnum::TermOrdVal(TermOrdValComparator::new(
+            num_hits,
+            self.raw_field.field.clone(),
+            self.selector,
+            missing_value == Some(SortFieldMissingValue::StringLast),
+        ))
+    }
+
+    #[inline]
+    pub fn raw_field(&self) -> &SimpleSortField {
+        &self.raw_field
+    }
+}
+
+/// SortField that sorts by the values of an `Expression`, e.g. to sort by a
+/// combination of the score and of doc values fields.
+///
+/// The expression can't read the features of the score context.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ExpressionSortField {
+    expression: Arc<Expression>,
+    is_reverse: bool,
+}
+
+impl ExpressionSortField {
+    pub fn new(expression: Expression, reverse: bool) -> Result<Self> {
+        if expression.needs_features() {
+            bail!(IllegalArgument(format!(
+                "can't sort by expression '{}', which reads the features of the score context",
+                expression
+            )));
+        }
+        Ok(ExpressionSortField {
+            expression: Arc::new(expression),
+            is_reverse: reverse,
+        })
+    }
+
+    pub fn expression(&self) -> &Expression {
+        &self.expression
+    }
+
+    pub fn get_comparator(&self, num_hits: usize) -> FieldComparatorEnum {
+        FieldComparatorEnum::Expression(ExpressionComparator::new(
+            num_hits,
+            Arc::clone(&self.expression),
+        ))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,396 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashSet;
+
+/// A transition from one state to `dest` on any byte in `[min, max]`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Transition {
+    pub min: u8,
+    pub max: u8,
+    pub dest: usize,
+}
+
+impl Transition {
+    pub fn new(min: u8, max: u8, dest: usize) -> Transition {
+        debug_assert!(min <= max);
+        Transition { min, max, dest }
+    }
+
+    #[inline]
+    pub fn matches(&self, label: u8) -> bool {
+        self.min <= label && label <= self.max
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct State {
+    accept: bool,
+    transitions: Vec<Transition>,
+    epsilons: Vec<usize>,
+}
+
+/// Represents an automaton over UTF-8 encoded bytes; state `0` is always the
+/// initial state.
+///
+/// An automaton may be non-deterministic: a state can have epsilon transitions
+/// or several transitions matching the same byte. Use `determinize` or
+/// `minimize` to obtain a DFA before stepping through it with `step` or
+/// building a `ByteRunAutomaton`.
+#[derive(Clone, Debug)]
+pub struct Automaton {
+    states: Vec<State>,
+    deterministic: bool,
+}
+
+impl Default for Automaton {
+    fn default() -> Self {
+        Automaton::new()
+    }
+}
+
+impl Automaton {
+    /// Creates an automaton without any state; call `create_state` to add
+    /// the initial state.
+    pub fn new() -> Automaton {
+        Automaton {
+            states: Vec::new(),
+            deterministic: true,
+        }
+    }
+
+    /// Returns an automaton that accepts nothing.
+    pub fn empty() -> Automaton {
+        let mut a = Automaton::new();
+        a.create_state();
+        a
+    }
+
+    /// Returns an automaton that accepts only the empty string.
+    pub fn empty_string() -> Automaton {
+        let mut a = Automaton::new();
+        let s = a.create_state();
+        a.set_accept(s, true);
+        a
+    }
+
+    /// Returns an automaton that accepts every byte sequence.
+    pub fn any_binary() -> Automaton {
+        let mut a = Automaton::new();
+        let s = a.create_state();
+        a.set_accept(s, true);
+        a.add_transition(s, s, 0, 255);
+        a
+    }
+
+    /// Returns an automaton that accepts exactly the given bytes.
+    pub fn binary(bytes: &[u8]) -> Automaton {
+        let mut a = Automaton::new();
+        let mut last = a.create_state();
+        for &b in bytes {
+            let state = a.create_state();
+            a.add_transition(last, state, b, b);
+            last = state;
+        }
+        a.set_accept(last, true);
+        a
+    }
+
+    /// Returns an automaton that accepts exactly the given string.
+    pub fn string(s: &str) -> Automaton {
+        Automaton::binary(s.as_bytes())
+    }
+
+    /// Returns an automaton that accepts a single char in `[min, max]`.
+    pub fn char_range(min: char, max: char) -> Automaton {
+        let mut a = Automaton::new();
+        let start = a.create_state();
+        if min <= max {
+            let end = a.create_state();
+            a.set_accept(end, true);
+            a.add_char_range(start, end, min, max);
+        }
+        a
+    }
+
+    /// Returns an automaton that accepts any single char.
+    pub fn any_char() -> Automaton {
+        Automaton::char_range('\0', ::std::char::MAX)
+    }
+
+    /// Returns an automaton that accepts every valid UTF-8 string.
+    pub fn any_string() -> Automaton {
+        let mut a = Automaton::new();
+        let s = a.create_state();
+        a.set_accept(s, true);
+        a.add_char_range(s, s, '\0', ::std::char::MAX);
+        a
+    }
+
+    pub fn create_state(&mut self) -> usize {
+        self.states.push(State::default());
+        self.states.len() - 1
+    }
+
+    #[inline]
+    pub fn num_states(&self) -> usize {
+        self.states.len()
+    }
+
+    pub fn set_accept(&mut self, state: usize, accept: bool) {
+        self.states[state].accept = accept;
+    }
+
+    #[inline]
+    pub fn is_accept(&self, state: usize) -> bool {
+        self.states[state].accept
+    }
+
+    /// Whether this automaton is known to be deterministic: no epsilon
+    /// transitions and no two transitions of a state overlap.
+    #[inline]
+    pub fn is_deterministic(&self) -> bool {
+        self.deterministic
+    }
+
+    pub fn add_transition(&mut self, source: usize, dest: usize, min: u8, max: u8) {
+        debug_assert!(dest < self.states.len());
+        let transition = Transition::new(min, max, dest);
+        let state = &mut self.states[source];
+        if self.deterministic
+            && state
+                .transitions
+                .iter()
+                .any(|t| t.min <= transition.max && transition.min <= t.max)
+        {
+            self.deterministic = false;
+        }
+        state.transitions.push(transition);
+    }
+
+    /// Adds an epsilon transition: everything accepted from `dest` is also
+    /// accepted from `source`.
+    pub fn add_epsilon(&mut self, source: usize, dest: usize) {
+        debug_assert!(dest < self.states.len());
+        if source != dest {
+            self.states[source].epsilons.push(dest);
+            self.deterministic = false;
+        }
+    }
+
+    /// Adds the UTF-8 byte paths for every char in `[min, max]` from `source`
+    /// to `dest`, creating intermediate states as needed.
+    pub fn add_char_range(&mut self, source: usize, dest: usize, min: char, max: char) {
+        let mut stack = vec![(min as u32, max as u32)];
+        'ranges: while let Some((start, end)) = stack.pop() {
+            if start > end {
+                continue;
+            }
+            // surrogates are not valid chars
+            if start <= 0xDFFF && end >= 0xD800 {
+                stack.push((start, 0xD7FF));
+                stack.push((0xE000, end));
+                continue;
+            }
+            // split at the boundaries of the encoded length
+            for &b in &[0x7F, 0x7FF, 0xFFFF] {
+                if start <= b && end > b {
+                    stack.push((start, b));
+                    stack.push((b + 1, end));
+                    continue 'ranges;
+                }
+            }
+            let len = utf8_len(start);
+            // split until every trailing byte either covers its full range
+            // or belongs to a single leading sequence
+            for i in 1..len {
+                let mask = (1u32 << (6 * i)) - 1;
+                if (start & !mask) != (end & !mask) {
+                    if (start & mask) != 0 {
+                        stack.push((start, start | mask));
+                        stack.push(((start | mask) + 1, end));
+                        continue 'ranges;
+                    }
+                    if (end & mask) != mask {
+                        stack.push((start, (end & !mask) - 1));
+                        stack.push((end & !mask, end));
+                        continue 'ranges;
+                    }
+                }
+            }
+            let (mut lo, mut hi) = ([0u8; 4], [0u8; 4]);
+            encode_utf8(start, &mut lo);
+            encode_utf8(end, &mut hi);
+            let mut state = source;
+            for i in 0..len {
+                let next = if i == len - 1 {
+                    dest
+                } else {
+                    self.create_state()
+                };
+                self.add_transition(state, next, lo[i], hi[i]);
+                state = next;
+            }
+        }
+    }
+
+    #[inline]
+    pub fn transitions(&self, state: usize) -> &[Transition] {
+        &self.states[state].transitions
+    }
+
+    #[inline]
+    pub fn epsilons(&self, state: usize) -> &[usize] {
+        &self.states[state].epsilons
+    }
+
+    /// Sorts the transitions of every state and merges adjacent ranges that
+    /// lead to the same destination.
+    pub fn reduce(&mut self) {
+        for state in &mut self.states {
+            if state.transitions.len() < 2 {
+                continue;
+            }
+            state.transitions.sort_by_key(|t| (t.min, t.max, t.dest));
+            let mut reduced: Vec<Transition> = Vec::with_capacity(state.transitions.len());
+            for t in &state.transitions {
+                if let Some(last) = reduced.last_mut() {
+                    if last.dest == t.dest && u16::from(last.max) + 1 >= u16::from(t.min) {
+                        if t.max > last.max {
+                            last.max = t.max;
+                        }
+                        continue;
+                    }
+                }
+                reduced.push(*t);
+            }
+            state.transitions = reduced;
+        }
+    }
+
+    /// Returns the destination of `state` on `label`; only valid for a
+    /// deterministic automaton.
+    pub fn step(&self, state: usize, label: u8) -> Option<usize> {
+        debug_assert!(self.deterministic);
+        self.states[state]
+            .transitions
+            .iter()
+            .find(|t| t.matches(label))
+            .map(|t| t.dest)
+    }
+
+    /// Returns the epsilon closure of the given states, sorted.
+    pub(crate) fn closure(&self, states: &[usize]) -> Vec<usize> {
+        let mut seen: HashSet<usize> = states.iter().cloned().collect();
+        let mut stack = states.to_vec();
+        while let Some(s) = stack.pop() {
+            for &e in &self.states[s].epsilons {
+                if seen.insert(e) {
+                    stack.push(e);
+                }
+            }
+        }
+        let mut closure: Vec<usize> = seen.into_iter().collect();
+        closure.sort_unstable();
+        closure
+    }
+
+    /// Returns true if the given bytes are accepted by this automaton.
+    pub fn run(&self, input: &[u8]) -> bool {
+        if self.states.is_empty() {
+            return false;
+        }
+        if self.deterministic {
+            let mut state = 0;
+            for &b in input {
+                match self.step(state, b) {
+                    Some(s) => state = s,
+                    None => return false,
+                }
+            }
+            return self.is_accept(state);
+        }
+        let mut current = self.closure(&[0]);
+        for &b in input {
+            let next: Vec<usize> = current
+                .iter()
+                .flat_map(|&s| self.states[s].transitions.iter())
+                .filter(|t| t.matches(b))
+                .map(|t| t.dest)
+                .collect();
+            if next.is_empty() {
+                return false;
+            }
+            current = self.closure(&next);
+        }
+        current.iter().any(|&s| self.is_accept(s))
+    }
+}
+
+fn utf8_len(code: u32) -> usize {
+    if code < 0x80 {
+        1
+    } else if code < 0x800 {
+        2
+    } else if code < 0x10000 {
+        3
+    } else {
+        4
+    }
+}
+
+fn encode_utf8(code: u32, buf: &mut [u8; 4]) {
+    let c = ::std::char::from_u32(code).unwrap();
+    c.encode_utf8(buf);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_char_range_utf8() {
+        let a = Automaton::char_range('a', '\u{10FFFF}');
+        for c in &[
+            'a',
+            'z',
+            '\u{7F}',
+            '\u{80}',
+            'é',
+            '中',
+            '\u{FFFF}',
+            '\u{10000}',
+            '😀',
+        ] {
+            let mut buf = [0u8; 4];
+            assert!(a.run(c.encode_utf8(&mut buf).as_bytes()), "{}", c);
+        }
+        assert!(!a.run(b"A"));
+        assert!(!a.run(b"ab"));
+        // an unpaired surrogate encoded as UTF-8 is rejected
+        assert!(!a.run(&[0xED, 0xA0, 0x80]));
+
+        let a = Automaton::char_range('\u{7FF}', '\u{801}');
+        assert!(a.run("\u{800}".as_bytes()));
+        assert!(!a.run("\u{802}".as_bytes()));
+    }
+
+    #[test]
+    fn test_any_string() {
+        let a = Automaton::any_string();
+        assert!(a.run(b""));
+        assert!(a.run("abc中文".as_bytes()));
+        assert!(!a.run(&[0xFF]));
+        assert!(!a.run(&[0xE4, 0xB8]));
+    }
+}
@@ -0,0 +1,254 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use core::codec::{TermIterator, Terms};
+use core::util::automaton::{
+    common_suffix, get_singleton, is_finite, minimize, Automaton, ByteRunAutomaton,
+};
+
+use error::Result;
+
+/// The kind of language accepted by a `CompiledAutomaton`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AutomatonType {
+    /// Accepts no terms.
+    None,
+    /// Accepts all terms.
+    All,
+    /// Accepts exactly one term, see `CompiledAutomaton::term`.
+    Single,
+    /// Any other language; terms are matched by intersecting the terms
+    /// dictionary with the run automaton.
+    Normal,
+}
+
+/// Immutable class holding the minimized form of an automaton, ready to be
+/// intersected with a terms dictionary by `Terms::intersect`.
+///
+/// Trivial languages are detected up front so that callers can handle them
+/// without running the automaton at all.
+#[derive(Clone, Debug)]
+pub struct CompiledAutomaton {
+    pub automaton_type: AutomatonType,
+    /// The single accepted term, for `AutomatonType::Single`.
+    pub term: Option<Vec<u8>>,
+    /// The minimal DFA, for `AutomatonType::Normal`.
+    pub automaton: Option<Arc<Automaton>>,
+    /// Matcher over the same states as `automaton`.
+    pub run_automaton: Option<Arc<ByteRunAutomaton>>,
+    /// Shared suffix of all accepted terms for infinite languages; used to
+    /// cheaply reject terms before running the automaton.
+    pub common_suffix: Option<Vec<u8>>,
+    /// Whether the language is finite.
+    pub finite: bool,
+}
+
+impl CompiledAutomaton {
+    pub fn new(automaton: &Automaton, max_determinized_states: usize) -> Result<Self> {
+        let automaton = minimize(automaton, max_determinized_states)?;
+        if !automaton.is_accept(0) && automaton.transitions(0).is_empty() {
+            return Ok(CompiledAutomaton::with_type(
+                AutomatonType::None,
+                None,
+                true,
+            ));
+        }
+        if is_total(&automaton, max_determinized_states)? {
+            return Ok(CompiledAutomaton::with_type(
+                AutomatonType::All,
+                None,
+                false,
+            ));
+        }
+        if let Some(term) = get_singleton(&automaton) {
+            return Ok(CompiledAutomaton::with_type(
+                AutomatonType::Single,
+                Some(term),
+                true,
+            ));
+        }
+
+        let finite = is_finite(&automaton);
+        let common_suffix = if finite {
+            // intersecting a finite language is already fast enough
+            None
+        } else {
+            let suffix = common_suffix(&automaton, max_determinized_states)?;
+            if suffix.is_empty() {
+                None
+            } else {
+                Some(suffix)
+            }
+        };
+        let run_automaton = ByteRunAutomaton::new(&automaton);
+        Ok(CompiledAutomaton {
+            automaton_type: AutomatonType::Normal,
+            term: None,
+            automaton: Some(Arc::new(automaton)),
+            run_automaton: Some(Arc::new(run_automaton)),
+            common_suffix,
+            finite,
+        })
+    }
+
+    fn with_type(automaton_type: AutomatonType, term: Option<Vec<u8>>, finite: bool) -> Self {
+        CompiledAutomaton {
+            automaton_type,
+            term,
+            automaton: None,
+            run_automaton: None,
+            common_suffix: None,
+            finite,
+        }
+    }
+
+    /// Calls `visitor` with the iterator positioned on each term of `terms`
+    /// accepted by this automaton, in term order.
+    pub fn visit_matching_terms<T, F>(&self, terms: &T, mut visitor: F) -> Result<()>
+    where
+        T: Terms,
+        F: FnMut(&mut T::Iterator) -> Result<()>,
+    {
+        match self.automaton_type {
+            AutomatonType::None => {}
+            AutomatonType::Single => {
+                let mut iter = terms.iterator()?;
+                if iter.seek_exact(self.term.as_ref().unwrap())? {
+                    visitor(&mut iter)?;
+                }
+            }
+            AutomatonType::All => {
+                let mut iter = terms.iterator()?;
+                while iter.next()?.is_some() {
+                    visitor(&mut iter)?;
+                }
+            }
+            AutomatonType::Normal => {
+                let mut iter = terms.intersect(self)?;
+                while iter.next()?.is_some() {
+                    visitor(&mut iter)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns true if the given term is accepted.
+    pub fn run(&self, term: &[u8]) -> bool {
+        match self.automaton_type {
+            AutomatonType::None => false,
+            AutomatonType::All => true,
+            AutomatonType::Single => self.term.as_ref().map_or(false, |t| t.as_slice() == term),
+            AutomatonType::Normal => self.run_automaton.as_ref().unwrap().run(term),
+        }
+    }
+}
+
+/// Whether the minimal DFA `a` accepts every byte sequence or every valid
+/// UTF-8 string.
+fn is_total(a: &Automaton, max_determinized_states: usize) -> Result<bool> {
+    if a.num_states() == 1 && a.is_accept(0) {
+        let transitions = a.transitions(0);
+        if transitions.len() == 1 && transitions[0].min == 0 && transitions[0].max == 255 {
+            return Ok(true);
+        }
+    }
+    let any = minimize(&Automaton::any_string(), max_determinized_states)?;
+    Ok(same_structure(a, &any))
+}
+
+/// Checks whether two minimal DFAs are isomorphic, which for minimal DFAs
+/// means they accept the same language.
+fn same_structure(a: &Automaton, b: &Automaton) -> bool {
+    if a.num_states() != b.num_states() {
+        return false;
+    }
+    let mut mapping = vec![usize::max_value(); a.num_states()];
+    let mut queue = VecDeque::new();
+    mapping[0] = 0;
+    queue.push_back(0);
+    while let Some(s) = queue.pop_front() {
+        let (ta, tb) = (a.transitions(s), b.transitions(mapping[s]));
+        if a.is_accept(s) != b.is_accept(mapping[s]) || ta.len() != tb.len() {
+            return false;
+        }
+        for (x, y) in ta.iter().zip(tb) {
+            if x.min != y.min || x.max != y.max {
+                return false;
+            }
+            if mapping[x.dest] == usize::max_value() {
+                mapping[x.dest] = y.dest;
+                queue.push_back(x.dest);
+            } else if mapping[x.dest] != y.dest {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::util::automaton::{concatenate, union, DEFAULT_MAX_DETERMINIZED_STATES};
+
+    fn compile(a: &Automaton) -> CompiledAutomaton {
+        CompiledAutomaton::new(a, DEFAULT_MAX_DETERMINIZED_STATES).unwrap()
+    }
+
+    #[test]
+    fn test_automaton_types() {
+        assert_eq!(
+            compile(&Automaton::empty()).automaton_type,
+            AutomatonType::None
+        );
+        assert_eq!(
+            compile(&Automaton::any_string()).automaton_type,
+            AutomatonType::All
+        );
+        assert_eq!(
+            compile(&Automaton::any_binary()).automaton_type,
+            AutomatonType::All
+        );
+
+        let single = compile(&Automaton::string("foo"));
+        assert_eq!(single.automaton_type, AutomatonType::Single);
+        assert_eq!(single.term, Some(b"foo".to_vec()));
+        assert!(single.run(b"foo"));
+        assert!(!single.run(b"fo"));
+
+        let finite = compile(&union(&[
+            Automaton::string("foo"),
+            Automaton::string("bar"),
+        ]));
+        assert_eq!(finite.automaton_type, AutomatonType::Normal);
+        assert!(finite.finite);
+        assert!(finite.common_suffix.is_none());
+        assert!(finite.run(b"bar"));
+        assert!(!finite.run(b"baz"));
+
+        let suffix = compile(&concatenate(&[
+            Automaton::any_string(),
+            Automaton::string("ing"),
+        ]));
+        assert_eq!(suffix.automaton_type, AutomatonType::Normal);
+        assert!(!suffix.finite);
+        assert_eq!(suffix.common_suffix, Some(b"ing".to_vec()));
+        assert!(suffix.run(b"sing"));
+        assert!(suffix.run(b"ing"));
+        assert!(!suffix.run(b"singer"));
+    }
+}
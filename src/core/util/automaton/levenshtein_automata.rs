@@ -0,0 +1,149 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::char;
+
+use core::util::automaton::{concatenate, Automaton};
+
+/// The maximum edit distance `LevenshteinAutomata` supports.
+pub const MAXIMUM_SUPPORTED_DISTANCE: usize = 2;
+
+/// Builds automata accepting all strings within an edit distance of a word.
+///
+/// Edits are counted in unicode code points: an insertion, a deletion or a
+/// substitution of a single char costs one edit, and so does the
+/// transposition of two adjacent chars when `transpositions` is enabled
+/// (the Damerau-Levenshtein distance).
+///
+/// Unlike Lucene this doesn't use the precomputed parametric tables; it
+/// builds the classic Levenshtein NFA and leaves determinization to
+/// `CompiledAutomaton`, which is cheap for the supported distances.
+pub struct LevenshteinAutomata {
+    word: Vec<char>,
+    transpositions: bool,
+}
+
+impl LevenshteinAutomata {
+    pub fn new(word: &str, transpositions: bool) -> LevenshteinAutomata {
+        LevenshteinAutomata {
+            word: word.chars().collect(),
+            transpositions,
+        }
+    }
+
+    /// Returns an automaton accepting every string that starts with `prefix`
+    /// followed by a string within `n` edits of the word.
+    pub fn to_automaton(&self, n: usize, prefix: &str) -> Automaton {
+        debug_assert!(n <= MAXIMUM_SUPPORTED_DISTANCE);
+        let lev = self.build(n);
+        if prefix.is_empty() {
+            lev
+        } else {
+            concatenate(&[Automaton::string(prefix), lev])
+        }
+    }
+
+    fn build(&self, n: usize) -> Automaton {
+        let len = self.word.len();
+        let mut a = Automaton::new();
+        // state (i, e): `i` chars of the word consumed using `e` edits
+        let state = |i: usize, e: usize| i * (n + 1) + e;
+        for _ in 0..(len + 1) * (n + 1) {
+            a.create_state();
+        }
+
+        for i in 0..=len {
+            for e in 0..=n {
+                let s = state(i, e);
+                if i == len {
+                    a.set_accept(s, true);
+                } else {
+                    let c = self.word[i];
+                    a.add_char_range(s, state(i + 1, e), c, c);
+                }
+                if e == n {
+                    continue;
+                }
+                // insertion
+                a.add_char_range(s, state(i, e + 1), '\0', char::MAX);
+                if i < len {
+                    // substitution
+                    a.add_char_range(s, state(i + 1, e + 1), '\0', char::MAX);
+                    // deletion
+                    a.add_epsilon(s, state(i + 1, e + 1));
+                }
+                if self.transpositions && i + 1 < len && self.word[i] != self.word[i + 1] {
+                    let swapped = a.create_state();
+                    let (c1, c2) = (self.word[i], self.word[i + 1]);
+                    a.add_char_range(s, swapped, c2, c2);
+                    a.add_char_range(swapped, state(i + 2, e + 1), c1, c1);
+                }
+            }
+        }
+        a
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::util::automaton::CompiledAutomaton;
+
+    fn matches(word: &str, n: usize, transpositions: bool, input: &str) -> bool {
+        let a = LevenshteinAutomata::new(word, transpositions).to_automaton(n, "");
+        CompiledAutomaton::new(&a, 10000)
+            .unwrap()
+            .run(input.as_bytes())
+    }
+
+    #[test]
+    fn test_levenshtein_distance() {
+        assert!(matches("foobar", 0, false, "foobar"));
+        assert!(!matches("foobar", 0, false, "fobar"));
+
+        assert!(matches("foobar", 1, false, "fobar"));
+        assert!(matches("foobar", 1, false, "foobars"));
+        assert!(matches("foobar", 1, false, "foxbar"));
+        assert!(!matches("foobar", 1, false, "ofobar"));
+        assert!(matches("foobar", 1, true, "ofobar"));
+        assert!(!matches("foobar", 1, true, "fxxbar"));
+        assert!(matches("foobar", 2, false, "fxxbar"));
+        assert!(matches("foobar", 2, true, "ofobra"));
+        assert!(!matches("foobar", 2, false, "ofobra"));
+
+        // edits count chars, not bytes
+        assert!(matches("café", 1, false, "cafe"));
+        assert!(matches("cafe", 1, false, "café"));
+        assert!(matches("", 1, false, "ü"));
+        assert!(!matches("", 1, false, "üü"));
+    }
+
+    #[test]
+    fn test_prefix() {
+        let a = LevenshteinAutomata::new("bar", true).to_automaton(1, "foo");
+        let compiled = CompiledAutomaton::new(&a, 10000).unwrap();
+        assert!(compiled.run(b"foobr"));
+        assert!(compiled.run(b"foobra"));
+        assert!(!compiled.run(b"fxobar"));
+    }
+
+    #[test]
+    fn test_long_word() {
+        let word = "abcdefghijklmnopqrstuvwxyzäöüß";
+        let a = LevenshteinAutomata::new(word, true).to_automaton(2, "");
+        let compiled = CompiledAutomaton::new(&a, 10000).unwrap();
+        assert!(compiled.run("abcdefghijklmnopqrstuvwxyzäöü".as_bytes()));
+        assert!(compiled.run("bacdefghijklmnopqrstuvwxyzäöüßx".as_bytes()));
+        assert!(!compiled.run("bacdefghijklmnopqrstuvwxyzäößxy".as_bytes()));
+    }
+}
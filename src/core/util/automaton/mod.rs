@@ -0,0 +1,44 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Finite-state automata over UTF-8 bytes.
+//!
+//! An [`Automaton`] is built from simple pieces (strings, character ranges)
+//! and combined with the functions in this module (`concatenate`, `union`,
+//! `repeat`, ...). The result is usually non-deterministic; `determinize`
+//! and `minimize` turn it into the minimal DFA that [`CompiledAutomaton`]
+//! uses to intersect with a terms dictionary.
+
+mod automaton;
+
+pub use self::automaton::*;
+
+mod operations;
+
+pub use self::operations::*;
+
+mod run_automaton;
+
+pub use self::run_automaton::*;
+
+mod compiled_automaton;
+
+pub use self::compiled_automaton::*;
+
+mod regexp;
+
+pub use self::regexp::*;
+
+mod levenshtein_automata;
+
+pub use self::levenshtein_automata::*;
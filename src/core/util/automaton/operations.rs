@@ -0,0 +1,636 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::{HashMap, VecDeque};
+
+use core::util::automaton::Automaton;
+
+use error::{ErrorKind::IllegalArgument, Result};
+
+/// Default maximum number of states that determinizing an automaton may
+/// create before giving up.
+pub const DEFAULT_MAX_DETERMINIZED_STATES: usize = 10000;
+
+/// Copies all states of `source` into `target`, returning the offset of
+/// `source`'s initial state.
+fn copy_into(target: &mut Automaton, source: &Automaton) -> usize {
+    let offset = target.num_states();
+    for s in 0..source.num_states() {
+        let state = target.create_state();
+        target.set_accept(state, source.is_accept(s));
+    }
+    for s in 0..source.num_states() {
+        for t in source.transitions(s) {
+            target.add_transition(s + offset, t.dest + offset, t.min, t.max);
+        }
+        for &e in source.epsilons(s) {
+            target.add_epsilon(s + offset, e + offset);
+        }
+    }
+    offset
+}
+
+/// Returns an automaton that accepts the concatenation of the languages of
+/// the given automata.
+pub fn concatenate(automata: &[Automaton]) -> Automaton {
+    if automata.is_empty() {
+        return Automaton::empty_string();
+    }
+    let mut result = Automaton::new();
+    let mut prev: Option<(usize, usize)> = None;
+    for a in automata {
+        let offset = copy_into(&mut result, a);
+        if let Some((prev_offset, prev_len)) = prev {
+            for s in prev_offset..prev_offset + prev_len {
+                if result.is_accept(s) {
+                    result.set_accept(s, false);
+                    result.add_epsilon(s, offset);
+                }
+            }
+        }
+        prev = Some((offset, a.num_states()));
+    }
+    result
+}
+
+/// Returns an automaton that accepts the union of the languages of the
+/// given automata.
+pub fn union(automata: &[Automaton]) -> Automaton {
+    let mut result = Automaton::empty();
+    for a in automata {
+        let offset = copy_into(&mut result, a);
+        result.add_epsilon(0, offset);
+    }
+    result
+}
+
+/// Returns an automaton that accepts the language of `a` or the empty string.
+pub fn optional(a: &Automaton) -> Automaton {
+    union(&[Automaton::empty_string(), a.clone()])
+}
+
+/// Returns an automaton that accepts the Kleene star (zero or more
+/// concatenated repetitions) of the language of `a`.
+pub fn repeat(a: &Automaton) -> Automaton {
+    let mut result = Automaton::empty_string();
+    let offset = copy_into(&mut result, a);
+    result.add_epsilon(0, offset);
+    for s in offset..result.num_states() {
+        if result.is_accept(s) {
+            result.add_epsilon(s, 0);
+        }
+    }
+    result
+}
+
+/// Returns an automaton that accepts `min` or more concatenated repetitions
+/// of the language of `a`.
+pub fn repeat_min(a: &Automaton, min: usize) -> Automaton {
+    let mut parts = vec![a.clone(); min];
+    parts.push(repeat(a));
+    concatenate(&parts)
+}
+
+/// Returns an automaton that accepts between `min` and `max` (inclusive)
+/// concatenated repetitions of the language of `a`.
+pub fn repeat_range(a: &Automaton, min: usize, max: usize) -> Automaton {
+    if min > max {
+        return Automaton::empty();
+    }
+    let mut parts = vec![a.clone(); min];
+    if max > min {
+        let opt = optional(a);
+        parts.extend((min..max).map(|_| opt.clone()));
+    }
+    concatenate(&parts)
+}
+
+/// Determinizes the given automaton using the subset construction.
+///
+/// Fails with `IllegalArgument` if the result would need more than
+/// `max_determinized_states` states.
+pub fn determinize(a: &Automaton, max_determinized_states: usize) -> Result<Automaton> {
+    if a.is_deterministic() || a.num_states() == 0 {
+        let mut result = a.clone();
+        result.reduce();
+        return Ok(result);
+    }
+
+    let mut result = Automaton::new();
+    let mut sets: HashMap<Vec<usize>, usize> = HashMap::new();
+    let mut work_list = VecDeque::new();
+
+    let start = a.closure(&[0]);
+    let s = result.create_state();
+    result.set_accept(s, start.iter().any(|&s| a.is_accept(s)));
+    sets.insert(start.clone(), s);
+    work_list.push_back(start);
+
+    let mut points: Vec<u16> = Vec::new();
+    let mut dests: Vec<usize> = Vec::new();
+    while let Some(set) = work_list.pop_front() {
+        let source = sets[&set];
+        points.clear();
+        for &s in &set {
+            for t in a.transitions(s) {
+                points.push(u16::from(t.min));
+                points.push(u16::from(t.max) + 1);
+            }
+        }
+        points.sort_unstable();
+        points.dedup();
+
+        for w in points.windows(2) {
+            let (min, max) = (w[0] as u8, (w[1] - 1) as u8);
+            dests.clear();
+            for &s in &set {
+                for t in a.transitions(s) {
+                    if t.matches(min) {
+                        dests.push(t.dest);
+                    }
+                }
+            }
+            if dests.is_empty() {
+                continue;
+            }
+            let closure = a.closure(&dests);
+            let dest = if let Some(&dest) = sets.get(&closure) {
+                dest
+            } else {
+                if result.num_states() >= max_determinized_states {
+                    bail!(IllegalArgument(format!(
+                        "determinizing automaton would result in more than {} states",
+                        max_determinized_states
+                    )));
+                }
+                let dest = result.create_state();
+                result.set_accept(dest, closure.iter().any(|&s| a.is_accept(s)));
+                sets.insert(closure.clone(), dest);
+                work_list.push_back(closure);
+                dest
+            };
+            result.add_transition(source, dest, min, max);
+        }
+    }
+    result.reduce();
+    debug_assert!(result.is_deterministic());
+    Ok(result)
+}
+
+/// Removes states that are unreachable from the initial state or can't reach
+/// an accept state. The initial state is always kept.
+pub fn remove_dead_states(a: &Automaton) -> Automaton {
+    let n = a.num_states();
+    if n == 0 {
+        return Automaton::empty();
+    }
+    let mut reachable = vec![false; n];
+    let mut reverse_edges: Vec<Vec<usize>> = vec![Vec::new(); n];
+    let mut stack = vec![0];
+    reachable[0] = true;
+    while let Some(s) = stack.pop() {
+        let dests = a
+            .transitions(s)
+            .iter()
+            .map(|t| t.dest)
+            .chain(a.epsilons(s).iter().cloned());
+        for d in dests {
+            reverse_edges[d].push(s);
+            if !reachable[d] {
+                reachable[d] = true;
+                stack.push(d);
+            }
+        }
+    }
+
+    let mut live = vec![false; n];
+    let mut stack: Vec<usize> = (0..n).filter(|&s| reachable[s] && a.is_accept(s)).collect();
+    for &s in &stack {
+        live[s] = true;
+    }
+    while let Some(s) = stack.pop() {
+        for &p in &reverse_edges[s] {
+            if !live[p] {
+                live[p] = true;
+                stack.push(p);
+            }
+        }
+    }
+
+    let mut result = Automaton::new();
+    let mut map = vec![usize::max_value(); n];
+    map[0] = result.create_state();
+    for s in 1..n {
+        if live[s] {
+            map[s] = result.create_state();
+        }
+    }
+    for s in 0..n {
+        if map[s] == usize::max_value() {
+            continue;
+        }
+        result.set_accept(map[s], a.is_accept(s));
+        if !live[s] {
+            continue;
+        }
+        for t in a.transitions(s) {
+            if live[t.dest] {
+                result.add_transition(map[s], map[t.dest], t.min, t.max);
+            }
+        }
+        for &e in a.epsilons(s) {
+            if live[e] {
+                result.add_epsilon(map[s], map[e]);
+            }
+        }
+    }
+    result
+}
+
+/// Returns the minimal deterministic automaton accepting the same language
+/// as `a`, without dead states.
+pub fn minimize(a: &Automaton, max_determinized_states: usize) -> Result<Automaton> {
+    let mut dfa = remove_dead_states(&determinize(a, max_determinized_states)?);
+    dfa.reduce();
+    let n = dfa.num_states();
+
+    // Moore's partition refinement: two states stay in the same block as
+    // long as they agree on acceptance and on the blocks they move to for
+    // every byte.
+    let mut block: Vec<usize> = (0..n).map(|s| dfa.is_accept(s) as usize).collect();
+    let mut num_blocks = if block.iter().all(|&b| b == block[0]) {
+        1
+    } else {
+        2
+    };
+    loop {
+        let mut signatures = HashMap::new();
+        let mut next = vec![0; n];
+        for s in 0..n {
+            let mut signature: Vec<(u8, u8, usize)> = Vec::new();
+            for t in dfa.transitions(s) {
+                let b = block[t.dest];
+                if let Some(last) = signature.last_mut() {
+                    if last.2 == b && u16::from(last.1) + 1 == u16::from(t.min) {
+                        last.1 = t.max;
+                        continue;
+                    }
+                }
+                signature.push((t.min, t.max, b));
+            }
+            let len = signatures.len();
+            next[s] = *signatures.entry((block[s], signature)).or_insert(len);
+        }
+        block = next;
+        if signatures.len() == num_blocks {
+            break;
+        }
+        num_blocks = signatures.len();
+    }
+
+    // number the blocks in breadth first order so that the initial state
+    // stays at 0
+    let mut representative = vec![usize::max_value(); num_blocks];
+    for s in (0..n).rev() {
+        representative[block[s]] = s;
+    }
+    let mut order = vec![usize::max_value(); num_blocks];
+    let mut result = Automaton::new();
+    let mut queue = VecDeque::new();
+    order[block[0]] = result.create_state();
+    queue.push_back(block[0]);
+    while let Some(b) = queue.pop_front() {
+        let rep = representative[b];
+        let source = order[b];
+        result.set_accept(source, dfa.is_accept(rep));
+        for t in dfa.transitions(rep) {
+            let dest_block = block[t.dest];
+            if order[dest_block] == usize::max_value() {
+                order[dest_block] = result.create_state();
+                queue.push_back(dest_block);
+            }
+            result.add_transition(source, order[dest_block], t.min, t.max);
+        }
+    }
+    result.reduce();
+    Ok(result)
+}
+
+/// Returns an automaton accepting the reverse of every string accepted by `a`.
+pub fn reverse(a: &Automaton) -> Automaton {
+    let mut result = Automaton::empty();
+    if a.num_states() == 0 {
+        return result;
+    }
+    for _ in 0..a.num_states() {
+        result.create_state();
+    }
+    for s in 0..a.num_states() {
+        for t in a.transitions(s) {
+            result.add_transition(t.dest + 1, s + 1, t.min, t.max);
+        }
+        for &e in a.epsilons(s) {
+            result.add_epsilon(e + 1, s + 1);
+        }
+        if a.is_accept(s) {
+            result.add_epsilon(0, s + 1);
+        }
+    }
+    result.set_accept(1, true);
+    result
+}
+
+/// Returns an automaton accepting the strings accepted by both `a1` and `a2`.
+pub fn intersection(
+    a1: &Automaton,
+    a2: &Automaton,
+    max_determinized_states: usize,
+) -> Result<Automaton> {
+    let a1 = determinize(a1, max_determinized_states)?;
+    let a2 = determinize(a2, max_determinized_states)?;
+    let mut result = Automaton::new();
+    if a1.num_states() == 0 || a2.num_states() == 0 {
+        return Ok(Automaton::empty());
+    }
+    let mut pairs: HashMap<(usize, usize), usize> = HashMap::new();
+    let mut work_list = VecDeque::new();
+    let s = result.create_state();
+    result.set_accept(s, a1.is_accept(0) && a2.is_accept(0));
+    pairs.insert((0, 0), s);
+    work_list.push_back((0, 0));
+    while let Some((p, q)) = work_list.pop_front() {
+        let source = pairs[&(p, q)];
+        for t1 in a1.transitions(p) {
+            for t2 in a2.transitions(q) {
+                let min = t1.min.max(t2.min);
+                let max = t1.max.min(t2.max);
+                if min > max {
+                    continue;
+                }
+                let key = (t1.dest, t2.dest);
+                let dest = if let Some(&dest) = pairs.get(&key) {
+                    dest
+                } else {
+                    let dest = result.create_state();
+                    result.set_accept(dest, a1.is_accept(t1.dest) && a2.is_accept(t2.dest));
+                    pairs.insert(key, dest);
+                    work_list.push_back(key);
+                    dest
+                };
+                result.add_transition(source, dest, min, max);
+            }
+        }
+    }
+    result.reduce();
+    Ok(result)
+}
+
+/// Returns an automaton accepting every valid UTF-8 string that `a` does
+/// not accept.
+pub fn complement(a: &Automaton, max_determinized_states: usize) -> Result<Automaton> {
+    let mut total = determinize(a, max_determinized_states)?;
+    if total.num_states() == 0 {
+        total.create_state();
+    }
+    let n = total.num_states();
+    let sink = total.create_state();
+    total.add_transition(sink, sink, 0, 255);
+    for s in 0..n {
+        let mut gaps = Vec::new();
+        let mut next = 0u16;
+        for t in total.transitions(s) {
+            if u16::from(t.min) > next {
+                gaps.push((next as u8, t.min - 1));
+            }
+            next = u16::from(t.max) + 1;
+        }
+        if next <= 255 {
+            gaps.push((next as u8, 255));
+        }
+        for (min, max) in gaps {
+            total.add_transition(s, sink, min, max);
+        }
+    }
+    for s in 0..total.num_states() {
+        let accept = total.is_accept(s);
+        total.set_accept(s, !accept);
+    }
+    intersection(&total, &Automaton::any_string(), max_determinized_states)
+}
+
+/// Returns true if the given automaton accepts no strings.
+pub fn is_empty(a: &Automaton) -> bool {
+    let a = remove_dead_states(a);
+    !a.is_accept(0) && a.transitions(0).is_empty() && a.epsilons(0).is_empty()
+}
+
+/// Returns true if the language of `a` is finite. `a` must not have dead
+/// states or epsilon cycles, e.g. the result of `minimize`.
+pub fn is_finite(a: &Automaton) -> bool {
+    let n = a.num_states();
+    let mut in_degree = vec![0usize; n];
+    for s in 0..n {
+        for t in a.transitions(s) {
+            in_degree[t.dest] += 1;
+        }
+        for &e in a.epsilons(s) {
+            in_degree[e] += 1;
+        }
+    }
+    let mut stack: Vec<usize> = (0..n).filter(|&s| in_degree[s] == 0).collect();
+    let mut visited = 0;
+    while let Some(s) = stack.pop() {
+        visited += 1;
+        let dests = a
+            .transitions(s)
+            .iter()
+            .map(|t| t.dest)
+            .chain(a.epsilons(s).iter().cloned());
+        for d in dests {
+            in_degree[d] -= 1;
+            if in_degree[d] == 0 {
+                stack.push(d);
+            }
+        }
+    }
+    visited == n
+}
+
+/// If `a` accepts exactly one string, returns it. `a` must be deterministic
+/// and without dead states.
+pub fn get_singleton(a: &Automaton) -> Option<Vec<u8>> {
+    debug_assert!(a.is_deterministic());
+    let mut bytes = Vec::new();
+    let mut state = 0;
+    loop {
+        let transitions = a.transitions(state);
+        if a.is_accept(state) {
+            return if transitions.is_empty() {
+                Some(bytes)
+            } else {
+                None
+            };
+        }
+        if transitions.len() != 1
+            || transitions[0].min != transitions[0].max
+            || bytes.len() > a.num_states()
+        {
+            return None;
+        }
+        bytes.push(transitions[0].min);
+        state = transitions[0].dest;
+    }
+}
+
+/// Returns the longest byte string that prefixes every string accepted by
+/// `a`. `a` must be deterministic and without dead states.
+pub fn common_prefix(a: &Automaton) -> Vec<u8> {
+    debug_assert!(a.is_deterministic());
+    let mut bytes = Vec::new();
+    if a.num_states() == 0 {
+        return bytes;
+    }
+    let mut visited = vec![false; a.num_states()];
+    let mut state = 0;
+    while !visited[state] && !a.is_accept(state) {
+        visited[state] = true;
+        let transitions = a.transitions(state);
+        if transitions.len() != 1 || transitions[0].min != transitions[0].max {
+            break;
+        }
+        bytes.push(transitions[0].min);
+        state = transitions[0].dest;
+    }
+    bytes
+}
+
+/// Returns the longest byte string that suffixes every string accepted by
+/// `a`.
+pub fn common_suffix(a: &Automaton, max_determinized_states: usize) -> Result<Vec<u8>> {
+    let reversed = remove_dead_states(&determinize(&reverse(a), max_determinized_states)?);
+    let mut suffix = common_prefix(&reversed);
+    suffix.reverse();
+    Ok(suffix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn accepts(a: &Automaton, s: &str) -> bool {
+        a.run(s.as_bytes())
+    }
+
+    #[test]
+    fn test_concatenate_union_repeat() {
+        let ab = concatenate(&[Automaton::string("a"), Automaton::string("b")]);
+        let a = union(&[ab, Automaton::string("c")]);
+        let a = repeat(&a);
+        assert!(!a.is_deterministic());
+        for s in &["", "ab", "c", "abcab", "ccc"] {
+            assert!(accepts(&a, s), "{}", s);
+        }
+        for s in &["a", "b", "abb", "cb"] {
+            assert!(!accepts(&a, s), "{}", s);
+        }
+
+        let dfa = determinize(&a, DEFAULT_MAX_DETERMINIZED_STATES).unwrap();
+        assert!(dfa.is_deterministic());
+        for s in &["", "ab", "c", "abcab", "ccc", "a", "b", "abb", "cb"] {
+            assert_eq!(accepts(&dfa, s), accepts(&a, s), "{}", s);
+        }
+
+        let min = minimize(&a, DEFAULT_MAX_DETERMINIZED_STATES).unwrap();
+        assert_eq!(min.num_states(), 2);
+        assert!(accepts(&min, "abcab"));
+        assert!(!accepts(&min, "abb"));
+    }
+
+    #[test]
+    fn test_repeat_range() {
+        let a = repeat_range(&Automaton::string("ab"), 1, 3);
+        assert!(!accepts(&a, ""));
+        assert!(accepts(&a, "ab"));
+        assert!(accepts(&a, "ababab"));
+        assert!(!accepts(&a, "abababab"));
+        let a = repeat_min(&Automaton::string("x"), 2);
+        assert!(!accepts(&a, "x"));
+        assert!(accepts(&a, "xxxxx"));
+        assert!(!is_finite(
+            &minimize(&a, DEFAULT_MAX_DETERMINIZED_STATES).unwrap()
+        ));
+    }
+
+    #[test]
+    fn test_minimize_equivalent_branches() {
+        let a = union(&[
+            Automaton::string("foobar"),
+            Automaton::string("fooxar"),
+            Automaton::string("barbar"),
+        ]);
+        let min = minimize(&a, DEFAULT_MAX_DETERMINIZED_STATES).unwrap();
+        // f-o-o-[bx]-a-r and b-a-r-b-a-r share their "ar" tails
+        assert_eq!(min.num_states(), 10);
+        assert!(is_finite(&min));
+        assert!(accepts(&min, "fooxar"));
+        assert!(!accepts(&min, "foo"));
+        assert_eq!(common_suffix(&a, 100).unwrap(), b"ar".to_vec());
+        assert_eq!(common_prefix(&min), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn test_singleton_and_empty() {
+        let a = minimize(
+            &concatenate(&[Automaton::string("foo"), Automaton::string("bar")]),
+            DEFAULT_MAX_DETERMINIZED_STATES,
+        )
+        .unwrap();
+        assert_eq!(get_singleton(&a), Some(b"foobar".to_vec()));
+        assert_eq!(common_prefix(&a), b"foobar".to_vec());
+        assert!(!is_empty(&a));
+        assert!(is_empty(&Automaton::empty()));
+        assert!(is_empty(&concatenate(&[
+            Automaton::string("a"),
+            Automaton::empty()
+        ])));
+    }
+
+    #[test]
+    fn test_intersection_complement() {
+        let a = concatenate(&[Automaton::string("a"), Automaton::any_string()]);
+        let b = concatenate(&[Automaton::any_string(), Automaton::string("z")]);
+        let both = intersection(&a, &b, DEFAULT_MAX_DETERMINIZED_STATES).unwrap();
+        assert!(accepts(&both, "az"));
+        assert!(accepts(&both, "abcz"));
+        assert!(!accepts(&both, "abc"));
+
+        let not_a = complement(&a, DEFAULT_MAX_DETERMINIZED_STATES).unwrap();
+        assert!(accepts(&not_a, ""));
+        assert!(accepts(&not_a, "ba"));
+        assert!(!accepts(&not_a, "ab"));
+        assert!(!not_a.run(&[0xFF]));
+    }
+
+    #[test]
+    fn test_determinize_limit() {
+        // (a|b)*a(a|b){12} needs 2^13 states as a DFA
+        let ab = Automaton::char_range('a', 'b');
+        let a = concatenate(&[
+            repeat(&ab),
+            Automaton::string("a"),
+            repeat_range(&ab, 12, 12),
+        ]);
+        assert!(determinize(&a, 1000).is_err());
+        assert!(determinize(&a, 10000).is_ok());
+    }
+}
@@ -0,0 +1,453 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+
+use core::util::automaton::{
+    complement, concatenate, intersection, optional, repeat, repeat_min, repeat_range, union,
+    Automaton,
+};
+
+use error::{ErrorKind::IllegalArgument, Result};
+
+#[derive(Clone, Debug, PartialEq)]
+enum Node {
+    Union(Box<Node>, Box<Node>),
+    Concatenation(Box<Node>, Box<Node>),
+    Intersection(Box<Node>, Box<Node>),
+    Optional(Box<Node>),
+    Repeat(Box<Node>),
+    RepeatMin(Box<Node>, usize),
+    RepeatMinMax(Box<Node>, usize, usize),
+    Complement(Box<Node>),
+    CharClass(Vec<(char, char)>, bool),
+    AnyChar,
+    Empty,
+    AnyString,
+    String(String),
+}
+
+/// Regular expression compiled to an `Automaton`.
+///
+/// The syntax follows Lucene's `RegExp`:
+///
+/// | expression | matches |
+/// |------------|---------|
+/// | `a|b`      | either `a` or `b` |
+/// | `a&b`      | both `a` and `b` |
+/// | `ab`       | `a` followed by `b` |
+/// | `a?` `a*` `a+` | zero or one, zero or more, one or more `a` |
+/// | `a{n}` `a{n,}` `a{n,m}` | `n`, at least `n`, or `n` to `m` repetitions |
+/// | `~a`       | any string not matched by `a` |
+/// | `[abc]` `[a-z]` `[^a-z]` | a char in, or not in, the class |
+/// | `.`        | any char |
+/// | `#`        | nothing |
+/// | `@`        | any string |
+/// | `"..."`    | the literal string |
+/// | `()` `(a)` | the empty string, grouping |
+/// | `\c`       | the literal char `c` |
+///
+/// A regular expression always matches the whole term.
+#[derive(Clone, Debug)]
+pub struct RegExp {
+    original: String,
+    node: Node,
+}
+
+impl RegExp {
+    pub fn new(s: &str) -> Result<RegExp> {
+        let mut parser = Parser {
+            chars: s.chars().collect(),
+            pos: 0,
+        };
+        let node = if parser.chars.is_empty() {
+            Node::String(String::new())
+        } else {
+            let node = parser.parse_union()?;
+            if parser.more() {
+                bail!(IllegalArgument(format!(
+                    "end-of-string expected at position {} in regexp '{}'",
+                    parser.pos, s
+                )));
+            }
+            node
+        };
+        Ok(RegExp {
+            original: s.to_string(),
+            node,
+        })
+    }
+
+    /// Builds the (non-deterministic) automaton for this expression.
+    /// `max_determinized_states` bounds the work of `~` and `&`, which need
+    /// to determinize their operands.
+    pub fn to_automaton(&self, max_determinized_states: usize) -> Result<Automaton> {
+        to_automaton(&self.node, max_determinized_states)
+    }
+}
+
+impl fmt::Display for RegExp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.original)
+    }
+}
+
+fn to_automaton(node: &Node, max_determinized_states: usize) -> Result<Automaton> {
+    let a = match node {
+        Node::Union(n1, n2) => union(&[
+            to_automaton(n1, max_determinized_states)?,
+            to_automaton(n2, max_determinized_states)?,
+        ]),
+        Node::Concatenation(n1, n2) => concatenate(&[
+            to_automaton(n1, max_determinized_states)?,
+            to_automaton(n2, max_determinized_states)?,
+        ]),
+        Node::Intersection(n1, n2) => intersection(
+            &to_automaton(n1, max_determinized_states)?,
+            &to_automaton(n2, max_determinized_states)?,
+            max_determinized_states,
+        )?,
+        Node::Optional(n) => optional(&to_automaton(n, max_determinized_states)?),
+        Node::Repeat(n) => repeat(&to_automaton(n, max_determinized_states)?),
+        Node::RepeatMin(n, min) => repeat_min(&to_automaton(n, max_determinized_states)?, *min),
+        Node::RepeatMinMax(n, min, max) => {
+            repeat_range(&to_automaton(n, max_determinized_states)?, *min, *max)
+        }
+        Node::Complement(n) => complement(
+            &to_automaton(n, max_determinized_states)?,
+            max_determinized_states,
+        )?,
+        Node::CharClass(ranges, negated) => {
+            let mut a = Automaton::new();
+            let start = a.create_state();
+            let end = a.create_state();
+            a.set_accept(end, true);
+            let ranges = if *negated {
+                negate_ranges(ranges)
+            } else {
+                ranges.clone()
+            };
+            for (min, max) in ranges {
+                a.add_char_range(start, end, min, max);
+            }
+            a
+        }
+        Node::AnyChar => Automaton::any_char(),
+        Node::Empty => Automaton::empty(),
+        Node::AnyString => Automaton::any_string(),
+        Node::String(s) => Automaton::string(s),
+    };
+    Ok(a)
+}
+
+/// Returns the ranges of all chars not covered by `ranges`.
+fn negate_ranges(ranges: &[(char, char)]) -> Vec<(char, char)> {
+    let mut sorted: Vec<(u32, u32)> = ranges.iter().map(|&(a, b)| (a as u32, b as u32)).collect();
+    sorted.sort_unstable();
+    let mut gaps = Vec::new();
+    let mut next = 0u32;
+    for (min, max) in sorted {
+        if min > next {
+            gaps.push((next, min - 1));
+        }
+        next = next.max(max + 1);
+    }
+    if next <= ::std::char::MAX as u32 {
+        gaps.push((next, ::std::char::MAX as u32));
+    }
+    gaps.into_iter()
+        .filter_map(|(min, max)| {
+            // gaps may start or end inside the surrogate block
+            let min = if min >= 0xD800 && min <= 0xDFFF {
+                0xE000
+            } else {
+                min
+            };
+            let max = if max >= 0xD800 && max <= 0xDFFF {
+                0xD7FF
+            } else {
+                max
+            };
+            if min > max {
+                None
+            } else {
+                Some((
+                    ::std::char::from_u32(min).unwrap(),
+                    ::std::char::from_u32(max).unwrap(),
+                ))
+            }
+        })
+        .collect()
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn more(&self) -> bool {
+        self.pos < self.chars.len()
+    }
+
+    fn peek(&self, candidates: &str) -> bool {
+        self.more() && candidates.contains(self.chars[self.pos])
+    }
+
+    fn matches(&mut self, c: char) -> bool {
+        if self.more() && self.chars[self.pos] == c {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn next(&mut self) -> Result<char> {
+        if !self.more() {
+            bail!(IllegalArgument(format!(
+                "unexpected end-of-string in regexp '{}'",
+                self.original()
+            )));
+        }
+        self.pos += 1;
+        Ok(self.chars[self.pos - 1])
+    }
+
+    fn original(&self) -> String {
+        self.chars.iter().collect()
+    }
+
+    fn error<T>(&self, expected: &str) -> Result<T> {
+        bail!(IllegalArgument(format!(
+            "expected {} at position {} in regexp '{}'",
+            expected,
+            self.pos,
+            self.original()
+        )))
+    }
+
+    fn parse_union(&mut self) -> Result<Node> {
+        let e = self.parse_intersection()?;
+        if self.matches('|') {
+            Ok(Node::Union(Box::new(e), Box::new(self.parse_union()?)))
+        } else {
+            Ok(e)
+        }
+    }
+
+    fn parse_intersection(&mut self) -> Result<Node> {
+        let e = self.parse_concatenation()?;
+        if self.matches('&') {
+            Ok(Node::Intersection(
+                Box::new(e),
+                Box::new(self.parse_intersection()?),
+            ))
+        } else {
+            Ok(e)
+        }
+    }
+
+    fn parse_concatenation(&mut self) -> Result<Node> {
+        let e = self.parse_repeat()?;
+        if self.more() && !self.peek(")|&") {
+            Ok(Node::Concatenation(
+                Box::new(e),
+                Box::new(self.parse_concatenation()?),
+            ))
+        } else {
+            Ok(e)
+        }
+    }
+
+    fn parse_repeat(&mut self) -> Result<Node> {
+        let mut e = self.parse_complement()?;
+        while self.peek("?*+{") {
+            e = match self.next()? {
+                '?' => Node::Optional(Box::new(e)),
+                '*' => Node::Repeat(Box::new(e)),
+                '+' => Node::RepeatMin(Box::new(e), 1),
+                _ => {
+                    let min = match self.parse_integer() {
+                        Some(n) => n,
+                        None => return self.error("integer"),
+                    };
+                    let max = if self.matches(',') {
+                        self.parse_integer()
+                    } else {
+                        Some(min)
+                    };
+                    if !self.matches('}') {
+                        return self.error("'}'");
+                    }
+                    match max {
+                        Some(max) if max < min => return self.error("max >= min"),
+                        Some(max) => Node::RepeatMinMax(Box::new(e), min, max),
+                        None => Node::RepeatMin(Box::new(e), min),
+                    }
+                }
+            };
+        }
+        Ok(e)
+    }
+
+    fn parse_integer(&mut self) -> Option<usize> {
+        let start = self.pos;
+        while self.more() && self.chars[self.pos].is_ascii_digit() {
+            self.pos += 1;
+        }
+        if start == self.pos {
+            return None;
+        }
+        self.chars[start..self.pos]
+            .iter()
+            .collect::<String>()
+            .parse()
+            .ok()
+    }
+
+    fn parse_complement(&mut self) -> Result<Node> {
+        if self.matches('~') {
+            Ok(Node::Complement(Box::new(self.parse_complement()?)))
+        } else {
+            self.parse_char_class_exp()
+        }
+    }
+
+    fn parse_char_class_exp(&mut self) -> Result<Node> {
+        if !self.matches('[') {
+            return self.parse_simple();
+        }
+        let negated = self.matches('^');
+        let mut ranges = Vec::new();
+        loop {
+            let min = self.parse_char()?;
+            let max = if self.peek("-")
+                && self.pos + 1 < self.chars.len()
+                && self.chars[self.pos + 1] != ']'
+            {
+                self.pos += 1;
+                self.parse_char()?
+            } else {
+                min
+            };
+            if max < min {
+                return self.error("a valid char range");
+            }
+            ranges.push((min, max));
+            if self.matches(']') {
+                break;
+            }
+            if !self.more() {
+                return self.error("']'");
+            }
+        }
+        Ok(Node::CharClass(ranges, negated))
+    }
+
+    fn parse_simple(&mut self) -> Result<Node> {
+        if self.matches('.') {
+            Ok(Node::AnyChar)
+        } else if self.matches('#') {
+            Ok(Node::Empty)
+        } else if self.matches('@') {
+            Ok(Node::AnyString)
+        } else if self.matches('"') {
+            let start = self.pos;
+            while self.more() && self.chars[self.pos] != '"' {
+                self.pos += 1;
+            }
+            if !self.matches('"') {
+                return self.error("'\"'");
+            }
+            Ok(Node::String(
+                self.chars[start..self.pos - 1].iter().collect(),
+            ))
+        } else if self.matches('(') {
+            if self.matches(')') {
+                return Ok(Node::String(String::new()));
+            }
+            let e = self.parse_union()?;
+            if !self.matches(')') {
+                return self.error("')'");
+            }
+            Ok(e)
+        } else {
+            let c = self.parse_char()?;
+            Ok(Node::String(c.to_string()))
+        }
+    }
+
+    fn parse_char(&mut self) -> Result<char> {
+        self.matches('\\');
+        self.next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::util::automaton::{CompiledAutomaton, DEFAULT_MAX_DETERMINIZED_STATES};
+
+    fn compile(s: &str) -> CompiledAutomaton {
+        let a = RegExp::new(s)
+            .unwrap()
+            .to_automaton(DEFAULT_MAX_DETERMINIZED_STATES)
+            .unwrap();
+        CompiledAutomaton::new(&a, DEFAULT_MAX_DETERMINIZED_STATES).unwrap()
+    }
+
+    fn check(regexp: &str, matches: &[&str], non_matches: &[&str]) {
+        let a = compile(regexp);
+        for s in matches {
+            assert!(a.run(s.as_bytes()), "{} should match {}", regexp, s);
+        }
+        for s in non_matches {
+            assert!(!a.run(s.as_bytes()), "{} should not match {}", regexp, s);
+        }
+    }
+
+    #[test]
+    fn test_regexp_syntax() {
+        check("ab|cd", &["ab", "cd"], &["abcd", "a", ""]);
+        check("a(b|c)*d", &["ad", "abd", "acbcd"], &["abc", "bd"]);
+        check("colou?r", &["color", "colour"], &["colouur"]);
+        check("a+b", &["ab", "aaab"], &["b"]);
+        check("a{2,3}", &["aa", "aaa"], &["a", "aaaa"]);
+        check("a{2}", &["aa"], &["aaa"]);
+        check("a{2,}", &["aa", "aaaaa"], &["a"]);
+        check("[a-c]x", &["ax", "cx"], &["dx", "x"]);
+        check("[^a-c]x", &["dx", "中x"], &["ax", "x"]);
+        check("[a\\-]", &["a", "-"], &["b"]);
+        check(".中.", &["a中b", "中中中"], &["中", "ab"]);
+        check("\"a+b\"", &["a+b"], &["ab"]);
+        check("a\\*", &["a*"], &["a", "aa"]);
+        check("@ing", &["ing", "running"], &["inga"]);
+        check("~(foo)", &["", "fo", "fooo", "bar"], &["foo"]);
+        check("a.*&.*b", &["ab", "axyb"], &["a", "ba"]);
+        check("()|a", &["", "a"], &["b"]);
+        check("#|a", &["a"], &[""]);
+    }
+
+    #[test]
+    fn test_regexp_errors() {
+        assert!(RegExp::new("(ab").is_err());
+        assert!(RegExp::new("ab)").is_err());
+        assert!(RegExp::new("[ab").is_err());
+        assert!(RegExp::new("a{3,1}").is_err());
+        assert!(RegExp::new("a{x}").is_err());
+        assert!(RegExp::new("[z-a]").is_err());
+        assert!(RegExp::new("ab\\").is_err());
+        assert!(RegExp::new("\"ab").is_err());
+    }
+}
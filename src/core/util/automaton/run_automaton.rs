@@ -0,0 +1,116 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::util::automaton::Automaton;
+
+const NO_STATE: u32 = u32::max_value();
+
+/// A table-driven view of a deterministic `Automaton` for fast stepping over
+/// bytes.
+///
+/// Bytes that behave the same in every state share a column of the
+/// transition table, so the table stays small even for large automata.
+#[derive(Clone, Debug)]
+pub struct ByteRunAutomaton {
+    accept: Vec<bool>,
+    classmap: Vec<u16>,
+    num_classes: usize,
+    transitions: Vec<u32>,
+}
+
+impl ByteRunAutomaton {
+    /// Creates the table for the given automaton, which must be
+    /// deterministic; the states keep their numbering.
+    pub fn new(a: &Automaton) -> ByteRunAutomaton {
+        assert!(a.is_deterministic());
+        let size = a.num_states();
+
+        let mut is_point = [false; 257];
+        is_point[0] = true;
+        for s in 0..size {
+            for t in a.transitions(s) {
+                is_point[t.min as usize] = true;
+                is_point[t.max as usize + 1] = true;
+            }
+        }
+        let points: Vec<u8> = (0..256usize)
+            .filter(|&p| is_point[p])
+            .map(|p| p as u8)
+            .collect();
+        let mut classmap = vec![0u16; 256];
+        let mut class = 0;
+        for (b, entry) in classmap.iter_mut().enumerate() {
+            if class + 1 < points.len() && points[class + 1] as usize == b {
+                class += 1;
+            }
+            *entry = class as u16;
+        }
+
+        let num_classes = points.len();
+        let mut transitions = vec![NO_STATE; size * num_classes];
+        for s in 0..size {
+            for (c, &p) in points.iter().enumerate() {
+                if let Some(dest) = a.step(s, p) {
+                    transitions[s * num_classes + c] = dest as u32;
+                }
+            }
+        }
+        let accept = (0..size).map(|s| a.is_accept(s)).collect();
+
+        ByteRunAutomaton {
+            accept,
+            classmap,
+            num_classes,
+            transitions,
+        }
+    }
+
+    /// Returns the number of states.
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.accept.len()
+    }
+
+    #[inline]
+    pub fn is_accept(&self, state: usize) -> bool {
+        self.accept[state]
+    }
+
+    /// Returns the state reached from `state` on `label`, or `None` if
+    /// the automaton rejects.
+    #[inline]
+    pub fn step(&self, state: usize, label: u8) -> Option<usize> {
+        let dest =
+            self.transitions[state * self.num_classes + self.classmap[label as usize] as usize];
+        if dest == NO_STATE {
+            None
+        } else {
+            Some(dest as usize)
+        }
+    }
+
+    /// Returns true if the given bytes are accepted.
+    pub fn run(&self, input: &[u8]) -> bool {
+        if self.accept.is_empty() {
+            return false;
+        }
+        let mut state = 0;
+        for &b in input {
+            match self.step(state, b) {
+                Some(s) => state = s,
+                None => return false,
+            }
+        }
+        self.is_accept(state)
+    }
+}
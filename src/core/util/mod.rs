@@ -13,6 +13,7 @@
 
 pub type DocId = i32;
 
+pub mod automaton;
 pub mod bkd;
 pub mod external;
 pub mod fst;
@@ -114,6 +115,14 @@ mod disi;
 
 pub use self::disi::*;
 
+mod hyper_log_log;
+
+pub use self::hyper_log_log::*;
+
+mod t_digest;
+
+pub use self::t_digest::*;
+
 use std::ops::Deref;
 
 use core::codec::doc_values::NumericDocValues;
@@ -0,0 +1,231 @@
+// Copyright 2019 Zhizhesihai (Beijing) Technology Limited.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cmp::Ordering;
+use std::f64;
+use std::mem;
+
+use error::{ErrorKind::IllegalArgument, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Centroid {
+    mean: f64,
+    weight: f64,
+}
+
+/// A sketch of a distribution for estimating its quantiles, following the
+/// merging t-digest of Ted Dunning.
+///
+/// The values are summarized by at most about `compression` centroids, which
+/// are smaller near the extremes, so that the extreme quantiles are more
+/// accurate than the median. Two digests can be merged, e.g. the digests of
+/// the segments of an index.
+#[derive(Debug, Clone)]
+pub struct TDigest {
+    compression: f64,
+    // sorted by mean
+    centroids: Vec<Centroid>,
+    // the values added since the last compression
+    buffer: Vec<f64>,
+    count: u64,
+    min: f64,
+    max: f64,
+}
+
+impl TDigest {
+    pub const DEFAULT_COMPRESSION: f64 = 100.0;
+
+    pub fn new(compression: f64) -> Result<TDigest> {
+        if !compression.is_finite() || compression < 1.0 {
+            bail!(IllegalArgument(format!(
+                "compression must be a finite number >= 1, got {}",
+                compression
+            )));
+        }
+        Ok(TDigest {
+            compression,
+            centroids: vec![],
+            buffer: vec![],
+            count: 0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        })
+    }
+
+    pub fn compression(&self) -> f64 {
+        self.compression
+    }
+
+    /// Returns the number of values added.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Adds a value, NaN values are ignored.
+    pub fn add(&mut self, value: f64) {
+        if value.is_nan() {
+            return;
+        }
+        self.count += 1;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.buffer.push(value);
+        if self.buffer.len() as f64 >= self.compression * 5.0 {
+            self.compress(vec![]);
+        }
+    }
+
+    /// Adds the values of `other` to this digest.
+    pub fn merge(&mut self, other: &TDigest) {
+        if other.count == 0 {
+            return;
+        }
+        self.count += other.count;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        self.buffer.extend_from_slice(&other.buffer);
+        self.compress(other.centroids.clone());
+    }
+
+    /// Returns the estimated value at quantile `q`, in `[0, 1]`, `None` if
+    /// the digest is empty.
+    pub fn quantile(&self, q: f64) -> Option<f64> {
+        if self.count == 0 {
+            return None;
+        }
+        if !self.buffer.is_empty() {
+            let mut digest = self.clone();
+            digest.compress(vec![]);
+            return digest.quantile(q);
+        }
+
+        let q = if q.is_nan() { 0.0 } else { q.max(0.0).min(1.0) };
+        let centroids = &self.centroids;
+        let n = centroids.len();
+        if n == 1 {
+            return Some(centroids[0].mean);
+        }
+
+        // every centroid is considered centered on its mean, and the quantiles
+        // between two centers are interpolated
+        let total = self.count as f64;
+        let index = q * total;
+        let first = centroids[0];
+        if index < first.weight / 2.0 {
+            return Some(self.min + (first.mean - self.min) * index / (first.weight / 2.0));
+        }
+        let last = centroids[n - 1];
+        if index > total - last.weight / 2.0 {
+            let z = (total - index) / (last.weight / 2.0);
+            return Some(self.max - (self.max - last.mean) * z);
+        }
+        let mut weight_so_far = first.weight / 2.0;
+        for i in 0..n - 1 {
+            let dw = (centroids[i].weight + centroids[i + 1].weight) / 2.0;
+            if weight_so_far + dw >= index {
+                let z1 = index - weight_so_far;
+                let z2 = weight_so_far + dw - index;
+                return Some((centroids[i].mean * z2 + centroids[i + 1].mean * z1) / dw);
+            }
+            weight_so_far += dw;
+        }
+        Some(last.mean)
+    }
+
+    /// Merges `centroids` and the buffered values into the centroids of this
+    /// digest.
+    fn compress(&mut self, mut centroids: Vec<Centroid>) {
+        centroids.extend(self.buffer.drain(..).map(|value| Centroid {
+            mean: value,
+            weight: 1.0,
+        }));
+        if centroids.is_empty() {
+            return;
+        }
+        centroids.extend_from_slice(&self.centroids);
+        centroids.sort_by(|a, b| a.mean.partial_cmp(&b.mean).unwrap_or(Ordering::Equal));
+
+        let total: f64 = centroids.iter().map(|c| c.weight).sum();
+        let mut merged = Vec::with_capacity(self.centroids.len() + 1);
+        let mut current = centroids[0];
+        let mut weight_so_far = 0.0;
+        for &next in &centroids[1..] {
+            let q0 = weight_so_far / total;
+            let q2 = (weight_so_far + current.weight + next.weight) / total;
+            if self.scale(q2) - self.scale(q0) <= 1.0 {
+                let weight = current.weight + next.weight;
+                current.mean += (next.mean - current.mean) * next.weight / weight;
+                current.weight = weight;
+            } else {
+                weight_so_far += current.weight;
+                merged.push(mem::replace(&mut current, next));
+            }
+        }
+        merged.push(current);
+        self.centroids = merged;
+    }
+
+    /// The k1 scale function, centroids span at most one unit of it.
+    fn scale(&self, q: f64) -> f64 {
+        self.compression / (2.0 * f64::consts::PI) * (2.0 * q.min(1.0) - 1.0).asin()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_t_digest() {
+        let mut digest = TDigest::new(TDigest::DEFAULT_COMPRESSION).unwrap();
+        assert_eq!(digest.quantile(0.5), None);
+        for &v in &[4.0, 1.0, 3.0, 2.0] {
+            digest.add(v);
+        }
+        // few values are kept as is
+        assert_eq!(digest.quantile(0.0), Some(1.0));
+        assert_eq!(digest.quantile(0.5), Some(2.5));
+        assert_eq!(digest.quantile(1.0), Some(4.0));
+
+        // two digests of interleaved values, merged
+        let mut a = TDigest::new(TDigest::DEFAULT_COMPRESSION).unwrap();
+        let mut b = TDigest::new(TDigest::DEFAULT_COMPRESSION).unwrap();
+        for i in 0..100_000 {
+            let value = f64::from((i * 7919) % 100_000);
+            if i % 3 == 0 {
+                a.add(value);
+            } else {
+                b.add(value);
+            }
+        }
+        b.add(f64::NAN);
+        a.merge(&b);
+        assert_eq!(a.count(), 100_000);
+        assert!(a.centroids.len() <= 2 * TDigest::DEFAULT_COMPRESSION as usize);
+        for &q in &[0.001, 0.01, 0.1, 0.5, 0.9, 0.99, 0.999] {
+            let estimate = a.quantile(q).unwrap();
+            let actual = q * 100_000.0;
+            assert!(
+                (estimate - actual).abs() < 100_000.0 * 0.01,
+                "q: {}, estimate: {}",
+                q,
+                estimate
+            );
+        }
+        assert_eq!(a.quantile(0.0), Some(0.0));
+        assert_eq!(a.quantile(1.0), Some(99_999.0));
+
+        assert!(TDigest::new(0.5).is_err());
+        assert!(TDigest::new(f64::NAN).is_err());
+    }
+}
@@ -22,6 +22,9 @@ use core::util::numeric::Numeric;
 
 #[derive(Debug, Clone, Deserialize)]
 pub enum VariantValue {
+    /// The absence of a value, e.g. the sort value of a document without a
+    /// value for the sort field.
+    Null,
     Bool(bool),
     Char(char),
     Short(i16),
@@ -141,6 +144,13 @@ impl VariantValue {
             _ => None,
         }
     }
+
+    pub fn is_null(&self) -> bool {
+        match self {
+            VariantValue::Null => true,
+            _ => false,
+        }
+    }
 }
 
 impl Eq for VariantValue {}
@@ -148,6 +158,7 @@ impl Eq for VariantValue {}
 impl fmt::Display for VariantValue {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
+            VariantValue::Null => write!(f, "null"),
             VariantValue::Bool(b) => write!(f, "{}", b),
             VariantValue::Char(c) => write!(f, "{}", c),
             VariantValue::Short(s) => write!(f, "{}s", s),
@@ -169,6 +180,7 @@ impl serde::Serialize for VariantValue {
         S: serde::Serializer,
     {
         match *self {
+            VariantValue::Null => serializer.serialize_unit(),
             VariantValue::Bool(b) => serializer.serialize_bool(b),
             VariantValue::Char(c) => serializer.serialize_char(c),
             VariantValue::Short(s) => serializer.serialize_i16(s),
@@ -217,6 +229,7 @@ impl Hash for VariantValue {
 impl PartialEq for VariantValue {
     fn eq(&self, other: &VariantValue) -> bool {
         match *self {
+            VariantValue::Null => other.is_null(),
             VariantValue::Bool(ref b) => {
                 if let VariantValue::Bool(ref o) = *other {
                     b.eq(o)
@@ -288,6 +301,7 @@ impl PartialEq for VariantValue {
 impl Ord for VariantValue {
     fn cmp(&self, other: &Self) -> Ordering {
         match (self, other) {
+            (&VariantValue::Null, &VariantValue::Null) => Ordering::Equal,
             (&VariantValue::Bool(b1), &VariantValue::Bool(b2)) => b1.cmp(&b2),
             (&VariantValue::Char(c1), &VariantValue::Char(c2)) => c1.cmp(&c2),
             (&VariantValue::Short(v1), &VariantValue::Short(v2)) => v1.cmp(&v2),
@@ -383,6 +397,15 @@ impl From<Numeric> for VariantValue {
 mod tests {
     use super::*;
 
+    #[test]
+    fn variant_null_test() {
+        let n = VariantValue::Null;
+        assert_eq!(format!("{}", n), "null");
+        assert!(n.is_null());
+        assert_eq!(n, VariantValue::Null);
+        assert_ne!(n, VariantValue::Int(0));
+    }
+
     #[test]
     fn variant_bool_test() {
         let b = VariantValue::Bool(true);